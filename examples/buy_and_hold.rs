@@ -51,10 +51,9 @@ fn main() {
     let mut algorithm = BuyAndHold::new(asset);
 
     // Create engine
-    let config = EngineConfig {
-        starting_cash: 100_000.0,
-        max_history_len: 1000,
-    };
+    let config = EngineConfig::default()
+        .with_starting_cash(100_000.0)
+        .with_max_history_len(1000);
 
     let calendar = Arc::new(NYSECalendar::new());
     let broker = SimulatedBroker::default_broker();
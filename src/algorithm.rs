@@ -4,18 +4,179 @@ use crate::asset::{Asset, AssetType};
 use crate::assets::AssetFinder;
 use crate::data::BarData;
 use crate::error::{Result, ZiplineError};
-use crate::finance::{Account, CommissionModel, Portfolio, SlippageModel};
-use crate::order::{Order, OrderSide};
+use crate::execution::{FinanceCommission, FinanceSlippage, SimulatedBroker};
+use crate::finance::{
+    Account, CapacityLimits, CapacityTracker, CommissionModel, ControlManager, LotSizes,
+    DayTrades, MarketStatsService, MaxOrdersPerBar, NoPrices, Portfolio, PriceLookup, Quantization,
+    optimize, realized_volatility, Constraint, Objective, Restrictions, Settlement, SlippageModel, TradingHalt, Transaction,
+    VolatilitySource, VolatilityTargeting,
+};
+use crate::finance::controls::TradingControl;
+use crate::order::{BracketBook, BracketOrder, BracketOrderIds, ExecutionOverride, Order, OrderSide, OrderType, Trail};
+use crate::performance::PerformanceTracker;
+use crate::rng::SimulationRng;
 use crate::pipeline::engine::Pipeline;
-use crate::types::{Quantity, Timestamp};
-use chrono::{DateTime, Utc};
-use hashbrown::HashMap;
+use crate::schedule::{EventRule, ScheduledCallback, Scheduler};
+use crate::types::{AssetId, Cash, Price, Quantity, SessionId, Timestamp, QUANTITY_TOLERANCE};
+use chrono::{DateTime, NaiveDate, Utc};
+use hashbrown::{HashMap, HashSet};
 use std::sync::Arc;
 
+pub mod allocation;
+pub mod compose;
+pub mod multi_strategy;
+
+pub use allocation::{
+    AllocationRecord, Allocator, EqualWeight, KellyCapped, Reallocation, RiskParity, SleeveReturns,
+};
+pub use compose::{Conditional, Ensemble, PipelineGated};
+pub use multi_strategy::MultiStrategy;
+
+/// How orders outside the pipeline screen universe are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniverseEnforcement {
+    /// Log a warning and place the order anyway
+    Warn,
+    /// Reject the order with an error
+    Reject,
+}
+
+/// Restricts new exposure to the assets in a pipeline screen for the current day
+///
+/// Orders that only reduce an existing position are always allowed, so assets
+/// dropping out of the screen can still be exited.
+#[derive(Debug, Clone)]
+pub struct UniverseMask {
+    /// Name of the pipeline whose screen defines the universe
+    pub pipeline: String,
+    /// Action taken for orders outside the universe
+    pub enforcement: UniverseEnforcement,
+    /// Assets passing the screen
+    members: HashSet<AssetId>,
+    /// Session the members were computed for
+    session: Option<NaiveDate>,
+}
+
+impl UniverseMask {
+    /// Create a mask for a pipeline with no screen output yet
+    pub fn new(pipeline: impl Into<String>, enforcement: UniverseEnforcement) -> Self {
+        Self {
+            pipeline: pipeline.into(),
+            enforcement,
+            members: HashSet::new(),
+            session: None,
+        }
+    }
+
+    /// Replace the screen members for a session
+    pub fn update(&mut self, session: NaiveDate, members: impl IntoIterator<Item = AssetId>) {
+        self.members = members.into_iter().collect();
+        self.session = Some(session);
+    }
+
+    /// Check if an asset is in the screen for a session
+    pub fn contains(&self, asset_id: AssetId, session: NaiveDate) -> bool {
+        self.session == Some(session) && self.members.contains(&asset_id)
+    }
+
+    /// Session of the latest screen output
+    pub fn session(&self) -> Option<NaiveDate> {
+        self.session
+    }
+
+    /// Number of assets in the latest screen output
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Check if the latest screen output is empty
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+/// Estimated outcome of an order, computed by [`Context::preview_order`]
+#[derive(Debug, Clone)]
+pub struct OrderPreview {
+    /// Asset being traded
+    pub asset_id: AssetId,
+    /// Order side
+    pub side: OrderSide,
+    /// Unsigned order quantity
+    pub quantity: Quantity,
+    /// Price the estimate was made at
+    pub market_price: Price,
+    /// Estimated fill price including slippage
+    pub fill_price: Price,
+    /// Estimated slippage cost (positive when the fill is worse than the market price)
+    pub slippage: Cash,
+    /// Estimated commission
+    pub commission: Cash,
+    /// Position in the asset after the fill
+    pub resulting_position: Quantity,
+    /// Cash after the fill
+    pub resulting_cash: Cash,
+    /// Market value of long positions after the fill
+    pub long_exposure: Cash,
+    /// Absolute market value of short positions after the fill
+    pub short_exposure: Cash,
+    /// Portfolio value after the fill
+    pub portfolio_value: Cash,
+    /// Gross leverage after the fill
+    pub leverage: f64,
+    /// Reasons the order would be rejected; empty if it would be accepted
+    pub rejections: Vec<String>,
+}
+
+impl OrderPreview {
+    /// Whether submitting the order would be rejected
+    pub fn would_reject(&self) -> bool {
+        !self.rejections.is_empty()
+    }
+
+    /// Slippage plus commission
+    pub fn total_cost(&self) -> Cash {
+        self.slippage + self.commission
+    }
+
+    /// Net exposure (long minus short) after the fill
+    pub fn net_exposure(&self) -> Cash {
+        self.long_exposure - self.short_exposure
+    }
+}
+
+/// A single quoted price in front of another price lookup
+struct Quote<'a> {
+    asset_id: AssetId,
+    price: Price,
+    rest: &'a dyn PriceLookup,
+}
+
+impl PriceLookup for Quote<'_> {
+    fn price(&self, asset: &Asset) -> Option<Price> {
+        if asset.id == self.asset_id {
+            Some(self.price)
+        } else {
+            self.rest.price(asset)
+        }
+    }
+}
+
+/// Account-wide book trading controls check orders against while a
+/// [`MultiStrategy`] sleeve runs on its own sub-portfolio
+pub(crate) struct ControlBook {
+    /// The account's portfolio
+    pub(crate) portfolio: Portfolio,
+    /// Open orders of the other sleeves
+    pub(crate) orders: Vec<Order>,
+}
+
 /// Trading algorithm context
 pub struct Context {
     /// Current simulation timestamp
     pub timestamp: Timestamp,
+    /// Trading session of the current bar, set by the engine
+    pub(crate) session: Option<SessionId>,
     /// Portfolio state
     pub portfolio: Portfolio,
     /// Account state (account-level metrics)
@@ -26,6 +187,52 @@ pub struct Context {
     pub variables: HashMap<String, Box<dyn std::any::Any + Send>>,
     /// Pending orders
     pub pending_orders: Vec<Order>,
+    /// Trading controls checked before orders are queued
+    pub trading_controls: Option<Arc<ControlManager>>,
+    /// Assets that may not be traded, checked before the trading controls
+    pub(crate) restrictions: Option<Arc<dyn Restrictions>>,
+    /// Guard against order loops, checked before the trading controls
+    pub(crate) order_loop_guard: Option<MaxOrdersPerBar>,
+    /// Minimum trade size, as a fraction of portfolio value, placed when rebalancing
+    pub rebalance_threshold: f64,
+    /// Pipeline screen restricting which assets may be ordered
+    pub universe_mask: Option<UniverseMask>,
+    /// Trade notes waiting for the next order in an asset (asset_id -> notes)
+    pub(crate) trade_notes: HashMap<u64, Vec<String>>,
+    /// Broker whose slippage and commission models are used for order previews
+    pub broker: Option<Arc<SimulatedBroker>>,
+    /// Rolling prices, volatility and volume shared with controls
+    pub market_stats: Option<Arc<MarketStatsService>>,
+    /// Resolves asset ids to assets for orders that name only an id
+    pub asset_finder: Option<Arc<AssetFinder>>,
+    /// Functions run by the engine on a date and time schedule
+    pub scheduler: Scheduler,
+    /// Scales orders down to the strategy's capacity limits
+    pub(crate) capacity: Option<CapacityTracker>,
+    /// Lot sizes and minimum order sizes orders are rounded to
+    pub lot_sizes: Option<Arc<LotSizes>>,
+    /// Orders materially changed by quantization since the engine last looked
+    pub(crate) quantized: Vec<Quantization>,
+    /// Orders amended since the engine last looked (replaced id, replacement id)
+    pub(crate) amended: Vec<(OrderId, OrderId)>,
+    /// Whether an amended order keeps its place in the queue
+    pub amend_keeps_priority: bool,
+    /// Orders the engine has completely filled this session
+    pub(crate) filled_orders: HashSet<OrderId>,
+    /// Bracket exits waiting on their entries, and OCO links between active exits
+    pub(crate) brackets: BracketBook,
+    /// Book the controls see instead of the context's own, if any
+    pub(crate) control_book: Option<ControlBook>,
+    /// Halt an account control put on trading, if any
+    pub(crate) halted: Option<TradingHalt>,
+    /// Sale proceeds not yet spendable, if settlement is modelled
+    pub(crate) settlement: Option<Settlement>,
+    /// Day trades made over the run
+    pub(crate) day_trades: DayTrades,
+    /// Results of the finished run, set before `analyze`
+    pub(crate) results: Option<PerformanceTracker>,
+    /// Source of order ids, seeded by the engine for reproducible runs
+    pub(crate) rng: SimulationRng,
 }
 
 impl Context {
@@ -33,14 +240,252 @@ impl Context {
     pub fn new(starting_cash: f64) -> Self {
         Self {
             timestamp: Timestamp::default(),
+            session: None,
             portfolio: Portfolio::new(starting_cash),
             account: Account::new(starting_cash),
             recorded_vars: HashMap::new(),
             variables: HashMap::new(),
             pending_orders: Vec::new(),
+            trading_controls: None,
+            restrictions: None,
+            order_loop_guard: Some(MaxOrdersPerBar::default()),
+            rebalance_threshold: 0.001,
+            universe_mask: None,
+            trade_notes: HashMap::new(),
+            broker: None,
+            market_stats: None,
+            asset_finder: None,
+            scheduler: Scheduler::new(),
+            capacity: None,
+            lot_sizes: None,
+            quantized: Vec::new(),
+            amended: Vec::new(),
+            amend_keeps_priority: false,
+            filled_orders: HashSet::new(),
+            brackets: BracketBook::new(),
+            control_book: None,
+            halted: None,
+            settlement: None,
+            day_trades: DayTrades::new(),
+            results: None,
+            rng: SimulationRng::from_entropy(),
         }
     }
 
+    /// Halt an account control has put on trading, if any
+    ///
+    /// Once halted, every order is rejected with
+    /// [`ZiplineError::TradingHalted`] for the rest of the run.
+    pub fn trading_halt(&self) -> Option<&TradingHalt> {
+        self.halted.as_ref()
+    }
+
+    /// Cash that may be spent on buys
+    ///
+    /// All cash without a settlement model; with one, cash less the proceeds
+    /// of sales still to settle.
+    pub fn settled_cash(&self) -> Cash {
+        self.portfolio.cash - self.unsettled_cash()
+    }
+
+    /// Proceeds of sales still to settle
+    pub fn unsettled_cash(&self) -> Cash {
+        self.settlement.as_ref().map_or(0.0, |s| s.unsettled())
+    }
+
+    /// Day trades made so far this run
+    pub fn day_trades(&self) -> &DayTrades {
+        &self.day_trades
+    }
+
+    /// Trading session of the current bar
+    ///
+    /// Outside an engine run this falls back to the UTC date of `timestamp`.
+    pub fn session(&self) -> SessionId {
+        self.session.unwrap_or_else(|| SessionId::utc_label_of(self.timestamp))
+    }
+
+    /// Start a session: settle proceeds that are due and reset the day's trades
+    /// and fills
+    pub(crate) fn start_session(&mut self, session: SessionId) {
+        self.session = Some(session);
+        self.filled_orders.clear();
+        if let Some(settlement) = self.settlement.as_mut() {
+            settlement.start_session();
+        }
+        self.day_trades.start_session(session.date());
+        self.account.settled_cash = self.settled_cash();
+    }
+
+    /// Account for a fill already applied to the portfolio
+    pub(crate) fn record_trade(&mut self, transaction: &Transaction) {
+        let after = self.portfolio.get_position(transaction.asset_id).map_or(0.0, |p| p.quantity);
+        self.day_trades.record(transaction.asset_id, after - transaction.amount, transaction.amount);
+        if let Some(settlement) = self.settlement.as_mut() {
+            settlement.record(transaction);
+        }
+        self.account.settled_cash = self.settled_cash();
+    }
+
+    /// Results of the run, available once it has finished (in `analyze`)
+    pub fn results(&self) -> Option<&PerformanceTracker> {
+        self.results.as_ref()
+    }
+
+    /// Run `callback` when `event_rule` and `time_rule` are both satisfied
+    ///
+    /// Usually called from `initialize`. With minute data the time rule is
+    /// resolved against the calendar's session open and close; with daily data
+    /// the function runs on the session's bar.
+    pub fn schedule_function(
+        &mut self,
+        callback: ScheduledCallback,
+        event_rule: Box<dyn EventRule>,
+        time_rule: Box<dyn crate::schedule::TimeRule>,
+        name: &str,
+    ) {
+        self.scheduler
+            .schedule_function(callback, event_rule, time_rule, name.to_string());
+    }
+
+    /// Restrict orders to the assets in a pipeline's screen
+    ///
+    /// The screen output must be supplied each day via [`Context::update_universe`],
+    /// typically from `before_trading_start` after computing the pipeline.
+    pub fn set_universe_mask(&mut self, pipeline: &str, enforcement: UniverseEnforcement) {
+        self.universe_mask = Some(UniverseMask::new(pipeline, enforcement));
+    }
+
+    /// Supply today's screen output for the universe pipeline
+    ///
+    /// Output for any pipeline other than the masking one is ignored.
+    pub fn update_universe(&mut self, pipeline: &str, members: impl IntoIterator<Item = AssetId>) {
+        let session = self.session().date();
+        if let Some(mask) = self.universe_mask.as_mut() {
+            if mask.pipeline == pipeline {
+                mask.update(session, members);
+            }
+        }
+    }
+
+    /// Check an order of `delta` shares in `asset` against the universe mask
+    fn enforce_universe(&self, asset: &Asset, delta: Quantity) -> Result<()> {
+        let mask = match &self.universe_mask {
+            Some(mask) => mask,
+            None => return Ok(()),
+        };
+
+        let session = self.session().date();
+        if mask.contains(asset.id, session) {
+            return Ok(());
+        }
+
+        // Reducing an existing position is always allowed
+        let current = self
+            .portfolio
+            .get_position(asset.id)
+            .map(|p| p.quantity)
+            .unwrap_or(0.0);
+        let target = current + delta;
+        if current != 0.0 && target.abs() <= current.abs() && target * current >= 0.0 {
+            return Ok(());
+        }
+
+        let reason = if mask.session() == Some(session) {
+            format!(
+                "{} is not in the '{}' pipeline screen for {}",
+                asset.symbol, mask.pipeline, session
+            )
+        } else {
+            format!(
+                "No '{}' pipeline screen output for {}; cannot order {}",
+                mask.pipeline, session, asset.symbol
+            )
+        };
+
+        match mask.enforcement {
+            UniverseEnforcement::Warn => {
+                tracing::warn!(pipeline = %mask.pipeline, asset = %asset.symbol, "{}", reason);
+                Ok(())
+            }
+            UniverseEnforcement::Reject => Err(ZiplineError::TradingControlViolation(reason)),
+        }
+    }
+
+    /// Set the trading controls checked before orders are queued
+    pub fn set_trading_controls(&mut self, controls: Arc<ControlManager>) {
+        self.trading_controls = Some(controls);
+    }
+
+    /// Set how many orders may be placed for one asset in a single bar
+    ///
+    /// Defaults to [`DEFAULT_MAX_ORDERS_PER_BAR`](crate::finance::DEFAULT_MAX_ORDERS_PER_BAR);
+    /// `None` removes the limit.
+    pub fn set_max_orders_per_bar(&mut self, max_count: Option<usize>) {
+        self.order_loop_guard = max_count.map(MaxOrdersPerBar::new);
+    }
+
+    /// Set the broker used to estimate fills in [`Context::preview_order`]
+    pub fn set_broker(&mut self, broker: Arc<SimulatedBroker>) {
+        self.broker = Some(broker);
+    }
+
+    /// Set the market statistics service read by trading controls
+    pub fn set_market_stats(&mut self, stats: Arc<MarketStatsService>) {
+        self.market_stats = Some(stats);
+    }
+
+    /// Set the asset finder that resolves ids passed to
+    /// [`order_optimal_portfolio`](Self::order_optimal_portfolio)
+    pub fn set_asset_finder(&mut self, asset_finder: Arc<AssetFinder>) {
+        self.asset_finder = Some(asset_finder);
+    }
+
+    /// Scale orders down to fractions of ADV and float
+    ///
+    /// Average daily volume comes from the market statistics service.
+    pub fn set_capacity_limits(&mut self, limits: CapacityLimits) {
+        self.capacity = Some(CapacityTracker::new(limits));
+    }
+
+    /// Reject orders in assets `restrictions` restrict at the time of the order
+    pub fn set_restrictions(&mut self, restrictions: Arc<dyn Restrictions>) {
+        self.restrictions = Some(restrictions);
+    }
+
+    /// Whether the configured restrictions forbid trading `asset` now
+    pub fn is_restricted(&self, asset: &Asset) -> bool {
+        self.restrictions
+            .as_ref()
+            .is_some_and(|r| r.is_restricted(asset, self.timestamp).is_err())
+    }
+
+    /// Hold back sale proceeds for `lag` sessions and only let buys spend settled cash
+    pub fn set_settlement(&mut self, lag: usize) {
+        self.settlement = Some(Settlement::new(lag));
+    }
+
+    /// Round orders to whole lots and ticks and reject those below the minimum size
+    pub fn set_lot_sizes(&mut self, lot_sizes: Arc<LotSizes>) {
+        self.lot_sizes = Some(lot_sizes);
+    }
+
+    /// Let amended orders keep their place in the queue
+    ///
+    /// By default an amended order is filled after every order placed before
+    /// the amendment, as with a cancel-replace on an exchange.
+    pub fn set_amend_keeps_priority(&mut self, keep: bool) {
+        self.amend_keeps_priority = keep;
+    }
+
+    /// Set the minimum trade size placed when rebalancing
+    ///
+    /// # Arguments
+    /// * `threshold` - Fraction of portfolio value (0.001 = 0.1%); smaller trades are skipped
+    pub fn set_rebalance_threshold(&mut self, threshold: f64) {
+        self.rebalance_threshold = threshold.max(0.0);
+    }
+
     /// Record a custom variable for later analysis
     ///
     /// Recorded variables are stored as time series and can be retrieved
@@ -135,47 +580,545 @@ impl Context {
 
         let delta = target_quantity - current_position;
 
-        if delta.abs() < f64::EPSILON {
+        if delta.abs() < QUANTITY_TOLERANCE {
             // Already at target
             return Err(crate::error::ZiplineError::InvalidOrder(
                 "Already at target position".to_string(),
             ));
         }
 
+        self.enforce_universe(&asset, delta)?;
+
         let (side, quantity) = if delta > 0.0 {
             (OrderSide::Buy, delta)
         } else {
             (OrderSide::Sell, -delta)
         };
 
-        let order = Order::market(asset, side, quantity, self.timestamp);
-        let order_id = order.id;
-        self.pending_orders.push(order);
-
-        Ok(order_id)
+        self.submit(Order::market(asset, side, quantity, self.timestamp))
     }
 
     /// Order a specific quantity of an asset
     pub fn order(&mut self, asset: Asset, quantity: Quantity) -> Result<OrderId> {
-        if quantity.abs() < f64::EPSILON {
+        if quantity.abs() < QUANTITY_TOLERANCE {
+            return Err(crate::error::ZiplineError::InvalidOrder(
+                "Quantity must be non-zero".to_string(),
+            ));
+        }
+
+        self.enforce_universe(&asset, quantity)?;
+
+        let (side, qty) = if quantity > 0.0 {
+            (OrderSide::Buy, quantity)
+        } else {
+            (OrderSide::Sell, -quantity)
+        };
+
+        self.submit(Order::market(asset, side, qty, self.timestamp))
+    }
+
+    /// Buy `quantity` of the asset that paid a dividend, at market
+    ///
+    /// Reinvestment is not the algorithm's choice, so the universe screen is
+    /// not applied; lot sizes and trading controls are.
+    pub(crate) fn reinvest_dividend(&mut self, asset: Asset, quantity: Quantity) -> Result<OrderId> {
+        let mut order = Order::market(asset, OrderSide::Buy, quantity, self.timestamp);
+        order.note = Some("dividend reinvestment".to_string());
+        self.submit(order)
+    }
+
+    /// Order a specific quantity of an asset at a limit price
+    ///
+    /// Buys fill at `limit_price` or lower, sells at `limit_price` or higher.
+    pub fn order_limit(&mut self, asset: Asset, quantity: Quantity, limit_price: Price) -> Result<OrderId> {
+        if quantity.abs() < QUANTITY_TOLERANCE {
             return Err(crate::error::ZiplineError::InvalidOrder(
                 "Quantity must be non-zero".to_string(),
             ));
         }
 
+        self.enforce_universe(&asset, quantity)?;
+
         let (side, qty) = if quantity > 0.0 {
             (OrderSide::Buy, quantity)
         } else {
             (OrderSide::Sell, -quantity)
         };
 
-        let order = Order::market(asset, side, qty, self.timestamp);
+        self.submit(Order::limit(asset, side, qty, limit_price, self.timestamp))
+    }
+
+    /// Order a specific quantity of an asset when its price retraces by `trail`
+    ///
+    /// Sell stops trail below the highest price since the order was placed,
+    /// buy stops above the lowest; once crossed the order fills at market.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Exit a long position after a 5% pullback from its high
+    /// context.order_trailing_stop(aapl.clone(), -100.0, Trail::Percent(0.05))?;
+    /// ```
+    pub fn order_trailing_stop(&mut self, asset: Asset, quantity: Quantity, trail: Trail) -> Result<OrderId> {
+        if quantity.abs() < QUANTITY_TOLERANCE {
+            return Err(crate::error::ZiplineError::InvalidOrder(
+                "Quantity must be non-zero".to_string(),
+            ));
+        }
+
+        self.enforce_universe(&asset, quantity)?;
+
+        let (side, qty) = if quantity > 0.0 {
+            (OrderSide::Buy, quantity)
+        } else {
+            (OrderSide::Sell, -quantity)
+        };
+
+        self.submit(Order::trailing_stop(asset, side, qty, trail, self.timestamp))
+    }
+
+    /// Enter a position with attached stop-loss and take-profit exits
+    ///
+    /// The entry is a market order, or a limit order at `entry_limit`. Once it
+    /// fills (or is cancelled after a partial fill) the exits are opened for
+    /// the filled quantity as a one-cancels-other pair. Only the entry is
+    /// checked against the trading controls, since the exits only reduce it.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Buy 100 shares, stop out at 95, take profit at 110
+    /// context.order_bracket(aapl.clone(), 100.0, None, 95.0, 110.0)?;
+    /// ```
+    pub fn order_bracket(
+        &mut self,
+        asset: Asset,
+        quantity: Quantity,
+        entry_limit: Option<Price>,
+        stop_loss: Price,
+        take_profit: Price,
+    ) -> Result<BracketOrderIds> {
+        if quantity.abs() < QUANTITY_TOLERANCE {
+            return Err(crate::error::ZiplineError::InvalidOrder(
+                "Quantity must be non-zero".to_string(),
+            ));
+        }
+
+        self.enforce_universe(&asset, quantity)?;
+
+        let side = if quantity > 0.0 { OrderSide::Buy } else { OrderSide::Sell };
+        let BracketOrder {
+            parent,
+            mut stop_loss,
+            mut take_profit,
+        } = BracketOrder::new(asset, side, quantity.abs(), entry_limit, stop_loss, take_profit, self.timestamp)?;
+
+        let parent = self.submit(parent)?;
+        stop_loss.id = self.rng.uuid();
+        take_profit.id = self.rng.uuid();
+        let ids = BracketOrderIds {
+            parent,
+            stop_loss: stop_loss.id,
+            take_profit: take_profit.id,
+        };
+        self.brackets.insert(parent, [stop_loss, take_profit]);
+        Ok(ids)
+    }
+
+    /// Order a specific quantity of an asset under its own execution terms
+    ///
+    /// The order is filled under `execution` instead of the broker's slippage
+    /// and commission models.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // A hedge assumed to cross at mid with no fees
+    /// context.order_with_execution(spy.clone(), -200.0, ExecutionOverride::Midpoint)?;
+    /// ```
+    pub fn order_with_execution(
+        &mut self,
+        asset: Asset,
+        quantity: Quantity,
+        execution: ExecutionOverride,
+    ) -> Result<OrderId> {
+        if quantity.abs() < QUANTITY_TOLERANCE {
+            return Err(crate::error::ZiplineError::InvalidOrder(
+                "Quantity must be non-zero".to_string(),
+            ));
+        }
+
+        self.enforce_universe(&asset, quantity)?;
+
+        let (side, qty) = if quantity > 0.0 {
+            (OrderSide::Buy, quantity)
+        } else {
+            (OrderSide::Sell, -quantity)
+        };
+
+        self.submit(Order::market(asset, side, qty, self.timestamp).with_execution(execution))
+    }
+
+    /// Annotate the trade in an asset with the reasoning behind it
+    ///
+    /// The note is attached to the most recent order for `asset` placed on the
+    /// current bar, or, if there is none, to the next order placed for it.
+    /// Notes are carried onto the resulting transactions, so they appear in
+    /// the transactions export.
+    ///
+    /// # Example
+    /// ```ignore
+    /// context.order(asset.clone(), 100.0)?;
+    /// context.log_trade_note(&asset, "20d momentum crossed above 5%");
+    /// ```
+    pub fn log_trade_note(&mut self, asset: &Asset, text: impl Into<String>) {
+        let text = text.into();
+        let timestamp = self.timestamp;
+        let recent = self
+            .pending_orders
+            .iter_mut()
+            .rev()
+            .find(|o| o.asset.id == asset.id && o.created_at == timestamp);
+
+        match recent {
+            Some(order) => order.add_note(&text),
+            None => self.trade_notes.entry(asset.id).or_default().push(text),
+        }
+    }
+
+    /// Prices controls value orders at when none are supplied: the last
+    /// traded prices from the market statistics, if any
+    pub(crate) fn price_lookup(&self) -> &dyn PriceLookup {
+        match &self.market_stats {
+            Some(stats) => stats.as_ref(),
+            None => &NoPrices,
+        }
+    }
+
+    /// Check an order against the trading controls and queue it
+    fn submit(&mut self, order: Order) -> Result<OrderId> {
+        let stats = self.market_stats.clone();
+        match &stats {
+            Some(stats) => self.submit_with_prices(order, stats.as_ref()),
+            None => self.submit_with_prices(order, &NoPrices),
+        }
+    }
+
+    /// Check an order against the trading controls, valued at `prices`, and queue it
+    fn submit_with_prices(&mut self, order: Order, prices: &dyn PriceLookup) -> Result<OrderId> {
+        let order_id = self.queue_checked(order, prices)?;
+        self.record_accepted(order_id, prices);
+        Ok(order_id)
+    }
+
+    /// Let the trading controls see an order that has been queued
+    fn record_accepted(&mut self, order_id: OrderId, prices: &dyn PriceLookup) {
+        let Some(controls) = self.trading_controls.clone() else {
+            return;
+        };
+        let Some(order) = self.pending_orders.iter().rev().find(|o| o.id == order_id).cloned() else {
+            return;
+        };
+        self.in_control_book(|context| controls.record_accepted(&order, context, prices));
+    }
+
+    /// Run `check` with the control book, if any, in place of the portfolio
+    /// and alongside the open orders
+    fn in_control_book<T>(&mut self, check: impl FnOnce(&Self) -> T) -> T {
+        let Some(mut book) = self.control_book.take() else {
+            return check(self);
+        };
+        std::mem::swap(&mut self.portfolio, &mut book.portfolio);
+        let own = self.pending_orders.len();
+        self.pending_orders.append(&mut book.orders);
+        let result = check(self);
+        book.orders = self.pending_orders.split_off(own);
+        std::mem::swap(&mut self.portfolio, &mut book.portfolio);
+        self.control_book = Some(book);
+        result
+    }
+
+    /// Check an order and queue it, without recording it with the controls
+    fn queue_checked(&mut self, mut order: Order, prices: &dyn PriceLookup) -> Result<OrderId> {
+        self.check_not_halted()?;
+        order.id = self.rng.uuid();
+        // Requested signed quantity, kept to record clipping once the order is queued
+        let mut capacity_request = None;
+        if let Some(capacity) = self.capacity.as_mut() {
+            let asset_id = order.asset.id;
+            let current = self
+                .portfolio
+                .get_position(asset_id)
+                .map(|p| p.quantity)
+                .unwrap_or(0.0);
+            let delta = match order.side {
+                OrderSide::Buy => order.quantity,
+                OrderSide::Sell => -order.quantity,
+            };
+            let adv = self
+                .market_stats
+                .as_ref()
+                .and_then(|stats| stats.average_daily_volume(asset_id));
+            match capacity.allowed_quantity(asset_id, current, delta, adv) {
+                Ok(allowed) => order.quantity = allowed.abs(),
+                Err(e) => {
+                    capacity.commit(asset_id, delta, 0.0, prices.price(&order.asset));
+                    return Err(e);
+                }
+            }
+            capacity_request = Some(delta);
+        }
+        let mut quantized = None;
+        if let Some(lot_sizes) = &self.lot_sizes {
+            let price = prices.price(&order.asset);
+            if let Some(change) = lot_sizes.quantize(&mut order, price)? {
+                tracing::warn!(
+                    asset = %order.asset.symbol,
+                    requested = change.requested_quantity,
+                    quantity = change.quantity,
+                    requested_limit = ?change.requested_limit_price,
+                    limit = ?change.limit_price,
+                    "order quantized to lot and tick size"
+                );
+                quantized = Some(change);
+            }
+        }
+
+        if let Some(restrictions) = &self.restrictions {
+            restrictions.is_restricted(&order.asset, self.timestamp)?;
+        }
+        if let Some(guard) = self.order_loop_guard {
+            guard.validate_order_at(&order, self, prices)?;
+        }
+        if let Some(controls) = self.trading_controls.clone() {
+            self.in_control_book(|context| controls.validate_order_at(&order, context, prices))?;
+        }
+        if self.settlement.is_some() && order.side == OrderSide::Buy {
+            self.check_settled_cash(&order, prices)?;
+        }
+
+        // Notes wait for an accepted order; a rejected one leaves them in place
+        if let Some(notes) = self.trade_notes.remove(&order.asset.id) {
+            for note in &notes {
+                order.add_note(note);
+            }
+        }
+
+        if let (Some(capacity), Some(requested)) = (self.capacity.as_mut(), capacity_request) {
+            let ordered = match order.side {
+                OrderSide::Buy => order.quantity,
+                OrderSide::Sell => -order.quantity,
+            };
+            capacity.commit(order.asset.id, requested, ordered, prices.price(&order.asset));
+        }
+
         let order_id = order.id;
         self.pending_orders.push(order);
-
+        self.quantized.extend(quantized);
         Ok(order_id)
     }
 
+    /// Reject orders once an account control has halted trading
+    fn check_not_halted(&self) -> Result<()> {
+        match &self.halted {
+            Some(halt) => Err(ZiplineError::TradingHalted {
+                reason: format!("{}: {}", halt.control, halt.reason),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the new terms of an amended order
+    ///
+    /// Unlike [`queue_checked`](Self::queue_checked) nothing is adjusted or
+    /// recorded: terms that capacity limits or lot sizes would change are
+    /// rejected, the order-loop guard does not count the amendment and the
+    /// controls do not record it as a new order.
+    fn check_amendment(&mut self, order: &Order, prices: &dyn PriceLookup) -> Result<()> {
+        self.check_not_halted()?;
+        if let Some(capacity) = &self.capacity {
+            let asset_id = order.asset.id;
+            let current = self.portfolio.get_position(asset_id).map_or(0.0, |p| p.quantity);
+            let delta = match order.side {
+                OrderSide::Buy => order.remaining(),
+                OrderSide::Sell => -order.remaining(),
+            };
+            let adv = self
+                .market_stats
+                .as_ref()
+                .and_then(|stats| stats.average_daily_volume(asset_id));
+            let allowed = capacity.allowed_quantity(asset_id, current, delta, adv)?;
+            if (allowed - delta).abs() >= QUANTITY_TOLERANCE {
+                return Err(ZiplineError::TradingControlViolation(format!(
+                    "Amended order for {} of {} exceeds capacity limits, which allow {} more",
+                    order.quantity,
+                    order.asset.symbol,
+                    allowed.abs()
+                )));
+            }
+        }
+        if let Some(lot_sizes) = &self.lot_sizes {
+            let mut quantized = order.clone();
+            lot_sizes.quantize(&mut quantized, prices.price(&order.asset))?;
+            if quantized.quantity != order.quantity || quantized.limit_price != order.limit_price {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "Amended order for {} of {} at {:?} is not in whole lots and ticks",
+                    order.quantity, order.asset.symbol, order.limit_price
+                )));
+            }
+        }
+
+        if let Some(restrictions) = &self.restrictions {
+            restrictions.is_restricted(&order.asset, self.timestamp)?;
+        }
+        if let Some(controls) = self.trading_controls.clone() {
+            self.in_control_book(|context| controls.validate_order_at(order, context, prices))?;
+        }
+        if self.settlement.is_some() && order.side == OrderSide::Buy {
+            self.check_settled_cash(order, prices)?;
+        }
+        Ok(())
+    }
+
+    /// Queue a market order closing `quantity` shares, bypassing every check
+    ///
+    /// Used by the engine to liquidate positions it has to close.
+    pub(crate) fn queue_closing_order(&mut self, asset: Asset, quantity: Quantity, note: &str) {
+        let side = if quantity > 0.0 { OrderSide::Sell } else { OrderSide::Buy };
+        let mut order = Order::market(asset, side, quantity.abs(), self.timestamp);
+        order.id = self.rng.uuid();
+        order.note = Some(note.to_string());
+        self.pending_orders.push(order);
+    }
+
+    /// Reject a buy costing more than the settled cash left after pending buys
+    fn check_settled_cash(&self, order: &Order, prices: &dyn PriceLookup) -> Result<()> {
+        let cost = |o: &Order| {
+            let price = o
+                .limit_price
+                .or_else(|| prices.price(&o.asset))
+                .or_else(|| self.portfolio.get_position(o.asset.id).map(|p| p.last_price))
+                .unwrap_or(0.0);
+            o.remaining() * price
+        };
+        let committed: Cash = self
+            .pending_orders
+            .iter()
+            .filter(|o| o.side == OrderSide::Buy)
+            .map(cost)
+            .sum();
+        let available = self.settled_cash() - committed;
+        let required = cost(order);
+        if required > available + 1e-6 {
+            return Err(ZiplineError::InsufficientFunds { required, available });
+        }
+        Ok(())
+    }
+
+    /// Estimate the outcome of ordering `quantity` shares without submitting
+    ///
+    /// Reports the expected fill price, slippage and commission under the
+    /// broker's models, the resulting position, cash and exposure, and every
+    /// trading control (and the universe mask, when rejecting) that would
+    /// reject the order. Without a broker the fill is assumed to be at `price`
+    /// with no commission.
+    ///
+    /// # Arguments
+    /// * `asset` - Asset to trade
+    /// * `quantity` - Signed quantity (negative to sell)
+    /// * `price` - Current price of the asset
+    ///
+    /// # Example
+    /// ```ignore
+    /// let preview = context.preview_order(&asset, 500.0, price)?;
+    /// if preview.would_reject() || preview.leverage > 1.5 {
+    ///     context.order(asset, 250.0)?;
+    /// }
+    /// ```
+    pub fn preview_order(&self, asset: &Asset, quantity: Quantity, price: Price) -> Result<OrderPreview> {
+        if quantity.abs() < QUANTITY_TOLERANCE {
+            return Err(ZiplineError::InvalidOrder("Quantity must be non-zero".to_string()));
+        }
+        if !price.is_finite() || price <= 0.0 {
+            return Err(ZiplineError::InvalidOrder(format!(
+                "Cannot preview {} at price {}",
+                asset.symbol, price
+            )));
+        }
+
+        let side = if quantity > 0.0 { OrderSide::Buy } else { OrderSide::Sell };
+        let order = Order::market(asset.clone(), side, quantity.abs(), self.timestamp);
+
+        let (fill_price, commission) = match &self.broker {
+            Some(broker) => broker.estimate_fill(&order, price),
+            None => (price, 0.0),
+        };
+        let slippage = (fill_price - price) * quantity;
+
+        let mut rejections = Vec::new();
+        if let Some(mask) = &self.universe_mask {
+            if mask.enforcement == UniverseEnforcement::Reject {
+                if let Err(e) = self.enforce_universe(asset, quantity) {
+                    rejections.push(e.to_string());
+                }
+            }
+        }
+        if let Some(controls) = &self.trading_controls {
+            let quote = Quote {
+                asset_id: asset.id,
+                price,
+                rest: self.price_lookup(),
+            };
+            rejections.extend(controls.order_violations(&order, self, &quote));
+        }
+
+        // Mark other positions at their last price and this one at `price`
+        let current = self
+            .portfolio
+            .get_position(asset.id)
+            .map(|p| p.quantity)
+            .unwrap_or(0.0);
+        let resulting_position = current + quantity;
+        let resulting_cash = self.portfolio.cash - fill_price * quantity - commission;
+
+        let (mut long_exposure, mut short_exposure) = (0.0, 0.0);
+        let values = self
+            .portfolio
+            .positions
+            .values()
+            .filter(|p| p.asset.id != asset.id)
+            .map(|p| p.market_value())
+            .chain(std::iter::once(resulting_position * price));
+        for value in values {
+            if value > 0.0 {
+                long_exposure += value;
+            } else {
+                short_exposure -= value;
+            }
+        }
+
+        let portfolio_value = resulting_cash + long_exposure - short_exposure;
+        let leverage = if portfolio_value == 0.0 {
+            0.0
+        } else {
+            (long_exposure + short_exposure) / portfolio_value
+        };
+
+        Ok(OrderPreview {
+            asset_id: asset.id,
+            side,
+            quantity: quantity.abs(),
+            market_price: price,
+            fill_price,
+            slippage,
+            commission,
+            resulting_position,
+            resulting_cash,
+            long_exposure,
+            short_exposure,
+            portfolio_value,
+            leverage,
+            rejections,
+        })
+    }
+
     /// Get number of pending orders
     pub fn pending_orders_count(&self) -> usize {
         self.pending_orders.len()
@@ -218,18 +1161,234 @@ impl Context {
 
     /// Order to target a specific dollar value position
     ///
-    /// # Arguments
-    /// * `asset` - Asset to trade
-    /// * `target_value` - Target dollar value of position
-    /// * `price` - Current price for conversion to shares
-    pub fn order_target_value(&mut self, asset: Asset, target_value: f64, price: f64) -> Result<OrderId> {
-        let target_quantity = target_value / price;
-        self.order_target(asset, target_quantity)
+    /// # Arguments
+    /// * `asset` - Asset to trade
+    /// * `target_value` - Target dollar value of position
+    /// * `price` - Current price for conversion to shares
+    pub fn order_target_value(&mut self, asset: Asset, target_value: f64, price: f64) -> Result<OrderId> {
+        let target_quantity = target_value / price;
+        self.order_target(asset, target_quantity)
+    }
+
+    /// Rebalance the portfolio to target weights
+    ///
+    /// Computes the market orders that move each asset from its current
+    /// position, plus what its open orders will still buy or sell, to
+    /// `weight * portfolio value` at its last traded price. Held assets without
+    /// a weight are closed out. Trades smaller than the rebalance threshold are
+    /// skipped and sells are placed before buys. Every order is checked against
+    /// the universe mask and the trading controls; if any order is rejected, no
+    /// orders are placed.
+    ///
+    /// Ids are resolved from held positions and open orders, then from the
+    /// [asset finder](Self::set_asset_finder). An asset found in neither, such
+    /// as a new name without a finder set, cannot be bought and the call fails
+    /// with [`ZiplineError::AssetNotFound`].
+    ///
+    /// # Arguments
+    /// * `target_weights` - Target weight by asset ID (0.1 = 10% of portfolio value)
+    ///
+    /// # Returns
+    /// IDs of the orders placed
+    ///
+    /// # Example
+    /// ```ignore
+    /// context.set_asset_finder(asset_finder.clone());
+    /// let mut weights = HashMap::new();
+    /// weights.insert(aapl.id, 0.6);
+    /// weights.insert(msft.id, 0.4);
+    /// let order_ids = context.order_optimal_portfolio(weights)?;
+    /// ```
+    pub fn order_optimal_portfolio(&mut self, target_weights: HashMap<AssetId, f64>) -> Result<Vec<OrderId>> {
+        self.rebalance(target_weights, &[])
+    }
+
+    /// Rebalance the portfolio to the weights that best meet an objective
+    ///
+    /// Solves `objective` under `constraints` (see [`optimize`]), then trades
+    /// to the weights as [`order_optimal_portfolio`](Self::order_optimal_portfolio)
+    /// does.
+    ///
+    /// # Arguments
+    /// * `objective` - Target weights by asset ID, or an [`Objective`] to optimize
+    /// * `constraints` - Bounds on the weights
+    /// * `assets` - Assets that may be bought but are not currently held, in
+    ///   addition to those the asset finder resolves
+    ///
+    /// # Example
+    /// ```ignore
+    /// let covariance = Covariance::from_history(&universe, data, 120)?;
+    /// context.order_optimal_portfolio_with(Objective::MinimumVariance(covariance), &[Constraint::LongOnly], &universe)?;
+    /// ```
+    pub fn order_optimal_portfolio_with(
+        &mut self,
+        objective: impl Into<Objective>,
+        constraints: &[Constraint],
+        assets: &[Asset],
+    ) -> Result<Vec<OrderId>> {
+        let target_weights = optimize(&objective.into(), constraints)?;
+        self.rebalance(target_weights, assets)
+    }
+
+    /// Place the orders that move the portfolio to `target_weights`
+    fn rebalance(&mut self, target_weights: HashMap<AssetId, f64>, assets: &[Asset]) -> Result<Vec<OrderId>> {
+        // Quantity still to trade on open orders, by asset
+        let mut pending: HashMap<AssetId, (Asset, Quantity)> = HashMap::new();
+        for order in &self.pending_orders {
+            let remaining = match order.side {
+                OrderSide::Buy => order.remaining(),
+                OrderSide::Sell => -order.remaining(),
+            };
+            pending
+                .entry(order.asset.id)
+                .or_insert_with(|| (order.asset.clone(), 0.0))
+                .1 += remaining;
+        }
+
+        let mut asset_ids: Vec<AssetId> = target_weights
+            .keys()
+            .chain(self.portfolio.positions.keys())
+            .chain(pending.keys())
+            .copied()
+            .collect();
+        asset_ids.sort_unstable();
+        asset_ids.dedup();
+
+        // Resolve asset, current quantity and price for every asset involved
+        let prices = self.price_lookup();
+        let mut holdings: Vec<(Asset, Quantity, Quantity, Price)> = Vec::with_capacity(asset_ids.len());
+        for asset_id in asset_ids {
+            let position = self.portfolio.get_position(asset_id);
+            let asset = match (position, pending.get(&asset_id)) {
+                (Some(position), _) => position.asset.clone(),
+                (None, Some((asset, _))) => asset.clone(),
+                (None, None) => assets
+                    .iter()
+                    .find(|a| a.id == asset_id)
+                    .cloned()
+                    .or_else(|| self.asset_finder.as_ref()?.retrieve_asset(asset_id).ok())
+                    .ok_or(ZiplineError::AssetNotFound(asset_id))?,
+            };
+
+            let price = prices
+                .price(&asset)
+                .or_else(|| position.map(|p| p.last_price))
+                .ok_or_else(|| ZiplineError::DataError(format!("No price for {}", asset.symbol)))?;
+            if price <= 0.0 || !price.is_finite() {
+                return Err(ZiplineError::InvalidData(format!(
+                    "Cannot rebalance {} at price {}",
+                    asset.symbol, price
+                )));
+            }
+
+            let quantity = position.map(|p| p.quantity).unwrap_or(0.0);
+            let on_order = pending.get(&asset_id).map_or(0.0, |(_, quantity)| *quantity);
+            holdings.push((asset, quantity, on_order, price));
+        }
+
+        let portfolio_value = self.portfolio.cash
+            + holdings
+                .iter()
+                .map(|(_, quantity, _, price)| quantity * price)
+                .sum::<f64>();
+        if portfolio_value <= 0.0 {
+            return Err(ZiplineError::InsufficientFunds {
+                required: 0.0,
+                available: portfolio_value,
+            });
+        }
+
+        let mut orders = Vec::new();
+        for (asset, quantity, on_order, price) in holdings {
+            let weight = target_weights.get(&asset.id).copied().unwrap_or(0.0);
+            let delta = weight * portfolio_value / price - quantity - on_order;
+
+            if (delta * price).abs() < self.rebalance_threshold * portfolio_value
+                || delta.abs() < QUANTITY_TOLERANCE
+            {
+                continue;
+            }
+
+            self.enforce_universe(&asset, delta)?;
+
+            let (side, qty) = if delta > 0.0 {
+                (OrderSide::Buy, delta)
+            } else {
+                (OrderSide::Sell, -delta)
+            };
+            orders.push(Order::market(asset, side, qty, self.timestamp));
+        }
+
+        // Sells first so their proceeds fund the buys
+        orders.sort_by_key(|o| o.side == OrderSide::Buy);
+
+        let stats = self.market_stats.clone();
+        let prices: &dyn PriceLookup = match &stats {
+            Some(stats) => stats.as_ref(),
+            None => &NoPrices,
+        };
+        let existing = self.pending_orders.len();
+        let trade_notes = self.trade_notes.clone();
+        let capacity = self.capacity.clone();
+        let quantized = self.quantized.len();
+        let mut order_ids = Vec::with_capacity(orders.len());
+        for order in orders {
+            match self.queue_checked(order, prices) {
+                Ok(order_id) => order_ids.push(order_id),
+                Err(e) => {
+                    self.pending_orders.truncate(existing);
+                    self.trade_notes = trade_notes;
+                    self.capacity = capacity;
+                    self.quantized.truncate(quantized);
+                    return Err(e);
+                }
+            }
+        }
+        // Only a batch that is queued in full counts as accepted
+        for order_id in &order_ids {
+            self.record_accepted(*order_id, prices);
+        }
+
+        Ok(order_ids)
+    }
+
+    /// Weights that size `source`'s assets to `sizer`'s target volatility
+    ///
+    /// Pass the weights to [`order_optimal_portfolio_with`](Self::order_optimal_portfolio_with)
+    /// to trade to them. Assets without enough history, or without a value in
+    /// the pipeline column, are left out.
+    ///
+    /// # Errors
+    /// * `InvalidConfiguration` - If the sizer's target or leverage is not positive
+    pub fn size_for_target_vol(
+        &self,
+        sizer: &VolatilityTargeting,
+        source: VolatilitySource,
+    ) -> Result<HashMap<AssetId, f64>> {
+        let volatilities: HashMap<AssetId, f64> = match source {
+            VolatilitySource::History { assets, data, bars } => assets
+                .iter()
+                .filter_map(|asset| {
+                    let prices = data.history_prices(asset, bars).ok()?;
+                    realized_volatility(&prices).map(|vol| (asset.id, vol))
+                })
+                .collect(),
+            VolatilitySource::Pipeline { output, column } => output
+                .iter()
+                .filter_map(|(id, row)| row.get(column).map(|vol| (*id, *vol)))
+                .collect(),
+        };
+        sizer.weights(&volatilities)
     }
 
     /// Get an order by ID
+    ///
+    /// Bracket exits are found while they wait for their entry too.
     pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
-        self.pending_orders.iter().find(|o| o.id == order_id)
+        self.pending_orders
+            .iter()
+            .find(|o| o.id == order_id)
+            .or_else(|| self.brackets.pending_exit(order_id))
     }
 
     /// Get all open orders, optionally filtered by asset
@@ -245,10 +1404,15 @@ impl Context {
     }
 
     /// Cancel a pending order
+    ///
+    /// Cancelling a bracket entry opens its exits for whatever it has filled,
+    /// or drops them if nothing filled; cancelling a bracket exit also cancels
+    /// its sibling.
     pub fn cancel_order(&mut self, order_id: OrderId) -> Result<()> {
         if let Some(pos) = self.pending_orders.iter().position(|o| o.id == order_id) {
             let mut order = self.pending_orders.remove(pos);
             order.cancel(self.timestamp);
+            self.close_bracket_order(&order);
             Ok(())
         } else {
             Err(crate::error::ZiplineError::InvalidOrder(
@@ -256,6 +1420,182 @@ impl Context {
             ))
         }
     }
+
+    /// Open the exits of a closed bracket entry, or cancel the sibling of a
+    /// closed bracket exit
+    pub(crate) fn close_bracket_order(&mut self, order: &Order) {
+        if let Some(exits) = self.brackets.close_entry(order.id, order.filled, self.timestamp) {
+            self.pending_orders
+                .extend(exits.into_iter().filter(|exit| exit.is_open()));
+        }
+        if let Some(sibling_id) = self.brackets.close_exit(order.id) {
+            if let Some(pos) = self.pending_orders.iter().position(|o| o.id == sibling_id) {
+                self.pending_orders.remove(pos).cancel(self.timestamp);
+            }
+        }
+    }
+
+    /// Change the quantity or limit price of an open order
+    ///
+    /// Cancel-replace: the order is replaced by a copy with the new terms and
+    /// a new id, which is returned. The new terms are checked against
+    /// restrictions, trading controls and settled cash; if they are rejected
+    /// the original order is left as it was. Unlike a new order, an amendment
+    /// is never resized: one that capacity limits or lot sizes would change
+    /// is rejected, and it does not count toward the orders-per-bar limit.
+    /// The replacement goes to the back of the queue unless
+    /// [`set_amend_keeps_priority`](Self::set_amend_keeps_priority) is on.
+    ///
+    /// Amending an order that filled earlier in the session is an
+    /// [`OrderAlreadyFilled`](ZiplineError::OrderAlreadyFilled) error; one
+    /// filled in an earlier session is no longer known and reports
+    /// [`OrderIdNotFound`](ZiplineError::OrderIdNotFound).
+    ///
+    /// # Arguments
+    /// * `order_id` - Open order to amend
+    /// * `new_qty` - New total quantity of the order, on the same side
+    /// * `new_limit` - New limit price, for limit and stop-limit orders
+    pub fn update_order(
+        &mut self,
+        order_id: OrderId,
+        new_qty: Option<Quantity>,
+        new_limit: Option<Price>,
+    ) -> Result<OrderId> {
+        if self.filled_orders.contains(&order_id) {
+            return Err(ZiplineError::OrderAlreadyFilled { order_id });
+        }
+        let pos = self
+            .pending_orders
+            .iter()
+            .position(|o| o.id == order_id)
+            .ok_or(ZiplineError::OrderIdNotFound { order_id })?;
+
+        let mut replacement = self.pending_orders[pos].clone();
+        if let Some(quantity) = new_qty {
+            if quantity <= replacement.filled + QUANTITY_TOLERANCE {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "Amended quantity {} must exceed the {} already filled",
+                    quantity, replacement.filled
+                )));
+            }
+            replacement.quantity = quantity;
+        }
+        if let Some(limit) = new_limit {
+            if replacement.limit_price.is_none() {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "{:?} order has no limit price to amend",
+                    replacement.order_type
+                )));
+            }
+            replacement.limit_price = Some(limit);
+        }
+        replacement.updated_at = self.timestamp;
+
+        // The original is out of the book while the new terms are checked
+        let original = self.pending_orders.remove(pos);
+        let stats = self.market_stats.clone();
+        let prices: &dyn PriceLookup = match &stats {
+            Some(stats) => stats.as_ref(),
+            None => &NoPrices,
+        };
+        if let Err(e) = self.check_amendment(&replacement, prices) {
+            self.pending_orders.insert(pos, original);
+            return Err(e);
+        }
+        replacement.id = self.rng.uuid();
+        let new_id = replacement.id;
+        if self.amend_keeps_priority {
+            self.pending_orders.insert(pos, replacement);
+        } else {
+            self.pending_orders.push(replacement);
+        }
+
+        self.brackets.replace(order_id, new_id);
+
+        // An order amended twice before the engine sees it is one amendment
+        match self.amended.iter_mut().find(|(_, replaced_by)| *replaced_by == order_id) {
+            Some(entry) => entry.1 = new_id,
+            None => self.amended.push((order_id, new_id)),
+        }
+        Ok(new_id)
+    }
+
+    /// Fingerprint of the trading state: cash, positions and pending orders
+    ///
+    /// Two runs that should be identical produce the same fingerprint on every
+    /// bar, so comparing recorded fingerprints pinpoints the first bar where
+    /// they diverge. Positions and orders are hashed in a canonical order, and
+    /// order ids (random per run) are left out. The hash is stable across
+    /// processes and platforms.
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        hasher.write_f64(self.portfolio.cash);
+
+        let mut positions: Vec<_> = self.portfolio.positions.values().collect();
+        positions.sort_by_key(|p| p.asset.id);
+        hasher.write_u64(positions.len() as u64);
+        for position in positions {
+            hasher.write_u64(position.asset.id);
+            hasher.write_f64(position.quantity);
+            hasher.write_f64(position.cost_basis);
+        }
+
+        hasher.write_u64(self.pending_orders.len() as u64);
+        for order in &self.pending_orders {
+            hasher.write_u64(order.asset.id);
+            hasher.write_u64(order.side as u64);
+            match order.order_type {
+                OrderType::Market => hasher.write_u64(0),
+                OrderType::Limit => hasher.write_u64(1),
+                OrderType::Stop => hasher.write_u64(2),
+                OrderType::StopLimit => hasher.write_u64(3),
+                OrderType::TrailingStop { trail: Trail::Amount(amount) } => {
+                    hasher.write_u64(4);
+                    hasher.write_f64(amount);
+                }
+                OrderType::TrailingStop { trail: Trail::Percent(fraction) } => {
+                    hasher.write_u64(5);
+                    hasher.write_f64(fraction);
+                }
+            }
+            hasher.write_f64(order.quantity);
+            hasher.write_f64(order.filled);
+            hasher.write_f64(order.limit_price.unwrap_or(f64::NAN));
+            hasher.write_f64(order.stop_price.unwrap_or(f64::NAN));
+        }
+
+        hasher.finish()
+    }
+}
+
+/// FNV-1a hasher, used where hashes must be reproducible between runs
+struct StateHasher(u64);
+
+impl StateHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_f64(&mut self, value: f64) {
+        // Normalise -0.0 and NaN payloads so equal states hash equally
+        let value = if value == 0.0 { 0.0 } else { value };
+        let bits = if value.is_nan() { f64::NAN.to_bits() } else { value.to_bits() };
+        self.write_u64(bits);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
 }
 
 use uuid::Uuid;
@@ -595,6 +1935,40 @@ impl TradingAlgorithm {
             .or_else(|| self.default_config.as_ref().map(|c| &c.commission_model))
     }
 
+    /// Broker that fills each order with its asset class's models
+    ///
+    /// Classes without their own models use the defaults, and no costs
+    /// without those. Pass the engine's market statistics so volume-based
+    /// slippage sees each asset's average daily volume.
+    pub fn broker(&self, market_stats: Option<Arc<MarketStatsService>>) -> SimulatedBroker {
+        let slippage = |model: &Arc<dyn SlippageModel>| -> Box<dyn crate::execution::SlippageModel> {
+            let adapter = FinanceSlippage::new(model.clone());
+            Box::new(match &market_stats {
+                Some(stats) => adapter.with_market_stats(stats.clone()),
+                None => adapter,
+            })
+        };
+        let commission = |model: &Arc<dyn CommissionModel>| -> Box<dyn crate::execution::CommissionModel> {
+            Box::new(FinanceCommission(model.clone()))
+        };
+
+        let mut broker = match &self.default_config {
+            Some(config) => SimulatedBroker::new(
+                slippage(&config.slippage_model),
+                commission(&config.commission_model),
+            ),
+            None => SimulatedBroker::default_broker(),
+        };
+        for (asset_type, config) in &self.asset_configs {
+            broker = broker.with_asset_class(
+                *asset_type,
+                slippage(&config.slippage_model),
+                commission(&config.commission_model),
+            );
+        }
+        broker
+    }
+
     /// Set the order cancel policy
     ///
     /// Must be called during initialize().
@@ -685,6 +2059,17 @@ impl TradingAlgorithm {
         &self.pipelines
     }
 
+    /// Longest factor window across the attached pipelines
+    ///
+    /// Suitable as the result of [`Algorithm::warm_up_bars`].
+    pub fn warm_up_bars(&self) -> usize {
+        self.pipelines
+            .values()
+            .map(Pipeline::max_window_length)
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Update pipeline output (internal use)
     pub fn update_pipeline_output(
         &mut self,
@@ -717,6 +2102,15 @@ pub trait Algorithm: Send {
         let _ = context;
         Ok(())
     }
+
+    /// Bars of history needed on the first bar (optional)
+    ///
+    /// Called after `initialize`. Return the longest `window_length` of the
+    /// attached pipelines and `BarData::history_frame` requests; the engine loads that
+    /// many bars before the start date so windows are full on day one.
+    fn warm_up_bars(&self) -> usize {
+        0
+    }
 }
 
 /// Example: Buy and hold strategy
@@ -787,6 +2181,609 @@ mod tests {
         assert_eq!(context.pending_orders[0].id, order_id);
     }
 
+    fn create_rebalance_context() -> (Context, Asset, Asset) {
+        use crate::finance::{MarketStatsService, Position};
+        use crate::types::Bar;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        // 100 shares of AAPL at $100 plus $90,000 cash = $100,000
+        let mut context = Context::new(100000.0);
+        context.portfolio.cash = 90000.0;
+        context
+            .portfolio
+            .positions
+            .insert(aapl.id, Position::new(aapl.clone(), 100.0, 10000.0, 100.0));
+
+        let stats = MarketStatsService::default();
+        let now = Utc::now();
+        for (asset, price) in [(&aapl, 100.0), (&msft, 200.0)] {
+            let bar = Bar::new(now, price, price, price, price, 1_000_000.0);
+            stats.update(asset.id, SessionId::utc_label_of(now), &bar);
+        }
+        context.set_market_stats(Arc::new(stats));
+
+        let asset_finder = AssetFinder::new();
+        for asset in [&aapl, &msft] {
+            asset_finder.insert_asset(asset.clone()).unwrap();
+        }
+        context.set_asset_finder(Arc::new(asset_finder));
+
+        (context, aapl, msft)
+    }
+
+    #[test]
+    fn test_order_optimal_portfolio() {
+        let (mut context, aapl, msft) = create_rebalance_context();
+
+        let mut weights = HashMap::new();
+        weights.insert(aapl.id, 0.5);
+        weights.insert(msft.id, 0.5);
+
+        // MSFT is neither held nor on order, so it needs the asset finder
+        let asset_finder = context.asset_finder.take();
+        assert!(matches!(
+            context.order_optimal_portfolio(weights.clone()),
+            Err(ZiplineError::AssetNotFound(2))
+        ));
+        assert_eq!(context.pending_orders_count(), 0);
+
+        context.asset_finder = asset_finder;
+        let order_ids = context.order_optimal_portfolio(weights).unwrap();
+
+        assert_eq!(order_ids.len(), 2);
+        let aapl_order = context.get_open_orders(Some(&aapl))[0];
+        assert_eq!(aapl_order.side, OrderSide::Buy);
+        assert!((aapl_order.quantity - 400.0).abs() < 1e-9);
+        let msft_order = context.get_open_orders(Some(&msft))[0];
+        assert!((msft_order.quantity - 250.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_order_optimal_portfolio_closes_and_nets() {
+        let (mut context, aapl, msft) = create_rebalance_context();
+        context.set_rebalance_threshold(0.01);
+
+        // AAPL is dropped from the targets; a 0.5% MSFT trade is below threshold
+        let mut weights = HashMap::new();
+        weights.insert(msft.id, 0.005);
+
+        let order_ids = context.order_optimal_portfolio(weights).unwrap();
+
+        assert_eq!(order_ids.len(), 1);
+        let order = context.get_order(order_ids[0]).unwrap();
+        assert_eq!(order.asset.id, aapl.id);
+        assert_eq!(order.side, OrderSide::Sell);
+        assert!((order.quantity - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_order_optimal_portfolio_nets_open_orders() {
+        let (mut context, aapl, msft) = create_rebalance_context();
+        let mut weights = HashMap::new();
+        weights.insert(aapl.id, 0.5);
+        weights.insert(msft.id, 0.5);
+
+        // Rebalancing again before the first orders fill places nothing new
+        context.order_optimal_portfolio(weights.clone()).unwrap();
+        assert!(context.order_optimal_portfolio(weights.clone()).unwrap().is_empty());
+        assert_eq!(context.pending_orders_count(), 2);
+
+        // A stray open order is traded against, not on top of
+        context.cancel_order(context.get_open_orders(Some(&msft))[0].id).unwrap();
+        context.order(msft.clone(), 100.0).unwrap();
+        let order_ids = context.order_optimal_portfolio(weights).unwrap();
+        assert_eq!(order_ids.len(), 1);
+        let order = context.get_order(order_ids[0]).unwrap();
+        assert_eq!((order.asset.id, order.side), (msft.id, OrderSide::Buy));
+        assert!((order.quantity - 150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_order_optimal_portfolio_respects_controls() {
+        use crate::finance::{ControlManager, ControlMaxOrderSize};
+
+        let (mut context, aapl, msft) = create_rebalance_context();
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(300.0)));
+        context.set_trading_controls(Arc::new(controls));
+
+        let mut weights = HashMap::new();
+        weights.insert(aapl.id, 0.5);
+        weights.insert(msft.id, 0.5);
+
+        // The 400-share AAPL buy breaches the control, so nothing is placed
+        assert!(context
+            .order_optimal_portfolio_with(weights, &[], &[msft.clone()])
+            .is_err());
+        assert_eq!(context.pending_orders_count(), 0);
+
+        // Unknown assets cannot be bought
+        let mut weights = HashMap::new();
+        weights.insert(99, 0.1);
+        assert!(context.order_optimal_portfolio_with(weights, &[], &[msft]).is_err());
+    }
+
+    #[test]
+    fn test_preview_order() {
+        use crate::execution::{FixedSlippage, PerShareCommission};
+
+        let (mut context, aapl, msft) = create_rebalance_context();
+        context.set_broker(Arc::new(SimulatedBroker::new(
+            Box::new(FixedSlippage::new(0.10)),
+            Box::new(PerShareCommission::new(0.01)),
+        )));
+
+        let preview = context.preview_order(&aapl, 100.0, 100.0).unwrap();
+        assert_eq!(preview.side, OrderSide::Buy);
+        assert!((preview.fill_price - 100.10).abs() < 1e-9);
+        assert!((preview.slippage - 10.0).abs() < 1e-9);
+        assert!((preview.commission - 1.0).abs() < 1e-9);
+        assert!((preview.resulting_position - 200.0).abs() < 1e-9);
+        assert!((preview.resulting_cash - 79989.0).abs() < 1e-9);
+        assert!((preview.long_exposure - 20000.0).abs() < 1e-9);
+        assert!((preview.total_cost() - 11.0).abs() < 1e-9);
+        assert!(!preview.would_reject());
+
+        // Shorting MSFT adds short exposure on top of the AAPL long
+        let preview = context.preview_order(&msft, -50.0, 200.0).unwrap();
+        assert_eq!(preview.side, OrderSide::Sell);
+        assert!((preview.short_exposure - 10000.0).abs() < 1e-9);
+        assert!((preview.net_exposure() - 0.0).abs() < 1e-9);
+        assert!(preview.leverage > 0.19 && preview.leverage < 0.21);
+
+        // Nothing was submitted
+        assert_eq!(context.pending_orders_count(), 0);
+        assert_eq!(context.portfolio.cash, 90000.0);
+        assert!(context.preview_order(&aapl, 0.0, 100.0).is_err());
+    }
+
+    #[test]
+    fn test_preview_order_lists_rejections() {
+        use crate::finance::{ControlManager, ControlMaxOrderSize, LongOnly};
+
+        let (mut context, aapl, msft) = create_rebalance_context();
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(300.0)));
+        controls.add_order_control(Box::new(LongOnly));
+        context.set_trading_controls(Arc::new(controls));
+
+        assert_eq!(context.preview_order(&aapl, 400.0, 100.0).unwrap().rejections.len(), 1);
+        assert_eq!(context.preview_order(&msft, -400.0, 200.0).unwrap().rejections.len(), 2);
+        assert!(!context.preview_order(&aapl, -100.0, 100.0).unwrap().would_reject());
+    }
+
+    #[test]
+    fn test_order_checks_trading_controls() {
+        use crate::finance::{ControlManager, DuplicateOrder};
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(DuplicateOrder::default()));
+        context.set_trading_controls(Arc::new(controls));
+
+        assert!(context.order(aapl.clone(), 10.0).is_ok());
+        assert!(context.order(aapl.clone(), 10.0).is_err());
+        assert!(context.order_target(aapl, 10.0).is_err());
+        assert_eq!(context.pending_orders_count(), 1);
+    }
+
+    #[test]
+    fn test_fat_finger_learns_only_from_queued_orders() {
+        use crate::finance::{ControlManager, ControlMaxOrderSize, FatFinger};
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut fat_finger = FatFinger::new().with_max_typical_multiple(10.0).with_history(20, 1);
+        fat_finger.update_price(1, 50.0);
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(fat_finger));
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(500.0)));
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_trading_controls(Arc::new(controls));
+
+        // Passes the fat-finger check but is rejected by the size limit after it
+        assert!(context.order(aapl.clone(), 1_000.0).is_err());
+        assert!(context.preview_order(&aapl, 1_000.0, 50.0).is_ok());
+        assert!(context.order(aapl.clone(), 10.0).is_ok());
+
+        // Typical order is 10 x 50 = 500, so 200 shares (10,000) is a fat finger
+        assert!(context.order(aapl, 200.0).is_err());
+    }
+
+    #[test]
+    fn test_order_loop_guard() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_max_orders_per_bar(Some(3));
+        for _ in 0..3 {
+            context.order(aapl.clone(), 1.0).unwrap();
+        }
+        assert!(matches!(
+            context.order(aapl.clone(), 1.0),
+            Err(ZiplineError::MaxOrdersPerBarExceeded { asset: 1, count: 4, max_count: 3, .. })
+        ));
+        // Other assets and later bars are unaffected
+        assert!(context.order(msft, 1.0).is_ok());
+        context.timestamp += chrono::Duration::minutes(1);
+        assert!(context.order(aapl.clone(), 1.0).is_ok());
+
+        context.set_max_orders_per_bar(None);
+        for _ in 0..200 {
+            context.order(aapl.clone(), 1.0).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_bracket_entry_cancel_after_partial_fill() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let mut context = Context::new(100000.0);
+
+        let ids = context.order_bracket(aapl.clone(), 10.0, Some(100.0), 95.0, 110.0).unwrap();
+        assert_eq!(context.pending_orders.len(), 1);
+        assert!(context.get_order(ids.stop_loss).is_some());
+
+        // Amending the entry keeps the exits attached to the replacement
+        let entry = context.update_order(ids.parent, Some(20.0), None).unwrap();
+        context.pending_orders[0].fill(4.0, context.timestamp);
+        context.cancel_order(entry).unwrap();
+
+        // The exits cover the 4 shares that filled, one cancelling the other
+        let exits: Vec<_> = context.pending_orders.iter().map(|o| (o.id, o.quantity)).collect();
+        assert_eq!(exits, [(ids.stop_loss, 4.0), (ids.take_profit, 4.0)]);
+        context.cancel_order(ids.take_profit).unwrap();
+        assert!(context.pending_orders.is_empty());
+
+        // An entry cancelled unfilled takes its exits with it
+        let ids = context.order_bracket(aapl, -10.0, Some(100.0), 105.0, 90.0).unwrap();
+        context.cancel_order(ids.parent).unwrap();
+        assert!(context.pending_orders.is_empty());
+        assert!(context.get_order(ids.stop_loss).is_none());
+    }
+
+    #[test]
+    fn test_update_order_cancel_replaces() {
+        use crate::finance::{ControlManager, ControlMaxOrderSize};
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let mut context = Context::new(100000.0);
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(300.0)));
+        context.set_trading_controls(Arc::new(controls));
+
+        let limit = context.order_limit(aapl.clone(), 100.0, 50.0).unwrap();
+        let market = context.order(aapl.clone(), 10.0).unwrap();
+        let ids = |context: &Context| context.pending_orders.iter().map(|o| o.id).collect::<Vec<_>>();
+
+        // The replacement goes to the back of the queue under a new id
+        let amended = context.update_order(limit, Some(200.0), Some(49.5)).unwrap();
+        assert_eq!(ids(&context), [market, amended]);
+        let order = context.get_order(amended).unwrap();
+        assert_eq!((order.quantity, order.limit_price), (200.0, Some(49.5)));
+
+        // ...or takes the original's place
+        let later = context.order(aapl, 5.0).unwrap();
+        context.set_amend_keeps_priority(true);
+        let again = context.update_order(amended, Some(150.0), None).unwrap();
+        assert_eq!(ids(&context), [market, again, later]);
+        assert_eq!(context.amended, [(limit, again)]);
+
+        // Rejected amendments leave the order as it was
+        assert!(context.update_order(again, Some(500.0), None).is_err());
+        assert!(matches!(
+            context.update_order(market, None, Some(49.0)),
+            Err(ZiplineError::InvalidOrder(_))
+        ));
+        assert_eq!(ids(&context), [market, again, later]);
+        assert_eq!(context.get_order(again).unwrap().quantity, 150.0);
+
+        assert!(matches!(context.update_order(limit, Some(1.0), None), Err(ZiplineError::OrderIdNotFound { .. })));
+        context.pending_orders.retain(|o| o.id != market);
+        context.filled_orders.insert(market);
+        assert!(matches!(context.update_order(market, Some(1.0), None), Err(ZiplineError::OrderAlreadyFilled { .. })));
+
+        // Fills are only remembered for the session they happened in
+        context.start_session(context.session().next_day());
+        assert!(context.filled_orders.is_empty());
+        assert!(matches!(context.update_order(market, Some(1.0), None), Err(ZiplineError::OrderIdNotFound { .. })));
+    }
+
+    #[test]
+    fn test_update_order_is_not_resized() {
+        use crate::finance::{LotSize, LotSizes};
+        use crate::types::Bar;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        // Two completed sessions of 1,000 shares at $50: orders capped at 100
+        let stats = Arc::new(MarketStatsService::new(5));
+        let day0 = Utc::now() - chrono::Duration::days(5);
+        for i in 0..3 {
+            let ts = day0 + chrono::Duration::days(i);
+            stats.update(1, SessionId::utc_label_of(ts), &Bar::new(ts, 50.0, 50.0, 50.0, 50.0, 1_000.0));
+        }
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_market_stats(stats);
+        context.set_capacity_limits(CapacityLimits::new().with_max_adv_fraction(0.1));
+        context.set_lot_sizes(Arc::new(LotSizes::new().with_asset(aapl.id, LotSize::new(10.0))));
+        context.set_max_orders_per_bar(Some(1));
+
+        let order_id = context.order_limit(aapl, 50.0, 49.0).unwrap();
+        let report = context.capacity.as_ref().unwrap().report().clone();
+
+        // Amendments past the one order allowed per bar are not new orders
+        let order_id = context.update_order(order_id, Some(80.0), None).unwrap();
+        let order_id = context.update_order(order_id, Some(100.0), None).unwrap();
+        assert_eq!(context.get_order(order_id).unwrap().quantity, 100.0);
+
+        // Terms capacity or lot rounding would change are rejected, not resized
+        assert!(matches!(
+            context.update_order(order_id, Some(150.0), None),
+            Err(ZiplineError::TradingControlViolation(_))
+        ));
+        assert!(matches!(
+            context.update_order(order_id, Some(95.0), None),
+            Err(ZiplineError::InvalidOrder(_))
+        ));
+        assert_eq!(context.pending_orders.len(), 1);
+        assert_eq!(context.get_order(order_id).unwrap().quantity, 100.0);
+        assert!(context.quantized.is_empty());
+        assert_eq!(context.capacity.as_ref().unwrap().report(), &report);
+    }
+
+    #[test]
+    fn test_order_scaled_to_capacity() {
+        use crate::types::Bar;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        // Two completed sessions of 1,000 shares at $50
+        let stats = Arc::new(MarketStatsService::new(5));
+        let day0 = Utc::now() - chrono::Duration::days(5);
+        for i in 0..3 {
+            let ts = day0 + chrono::Duration::days(i);
+            let bar = Bar::new(ts, 50.0, 50.0, 50.0, 50.0, 1_000.0);
+            stats.update(1, SessionId::utc_label_of(ts), &bar);
+        }
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_market_stats(stats);
+        context.set_capacity_limits(CapacityLimits::new().with_max_adv_fraction(0.1));
+
+        context.order(aapl.clone(), 250.0).unwrap();
+        assert_eq!(context.pending_orders[0].quantity, 100.0);
+        context.order(aapl, -80.0).unwrap();
+        assert_eq!(context.pending_orders[1].quantity, 80.0);
+
+        let report = context.capacity.as_ref().unwrap().report();
+        assert_eq!(report.orders_scaled, 1);
+        assert_eq!(report.clipped_notional, 150.0 * 50.0);
+    }
+
+    #[test]
+    fn test_rejected_order_is_not_reported_quantized() {
+        use crate::finance::{ControlManager, ControlMaxOrderSize, LotSize, LotSizes};
+
+        let (mut context, aapl, _) = create_rebalance_context();
+        context.set_lot_sizes(Arc::new(LotSizes::new().with_asset(aapl.id, LotSize::new(100.0))));
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(300.0)));
+        context.set_trading_controls(Arc::new(controls));
+
+        // Rounded to 400 shares, then rejected by the control
+        assert!(context.order(aapl.clone(), 420.0).is_err());
+        assert!(context.quantized.is_empty());
+
+        context.order(aapl, 220.0).unwrap();
+        assert_eq!(context.quantized.len(), 1);
+        assert_eq!(context.quantized[0].quantity, 200.0);
+    }
+
+    #[test]
+    fn test_rejected_order_leaves_capacity_report() {
+        use crate::finance::{CapacityReport, ControlManager, ControlMaxOrderSize};
+        use crate::types::Bar;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let stats = Arc::new(MarketStatsService::new(5));
+        let day0 = Utc::now() - chrono::Duration::days(5);
+        for i in 0..3 {
+            let ts = day0 + chrono::Duration::days(i);
+            stats.update(1, SessionId::utc_label_of(ts), &Bar::new(ts, 50.0, 50.0, 50.0, 50.0, 1_000.0));
+        }
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_market_stats(stats);
+        context.set_capacity_limits(CapacityLimits::new().with_max_adv_fraction(0.1));
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(50.0)));
+        context.set_trading_controls(Arc::new(controls));
+
+        // Scaled to 100 shares by capacity, then rejected by the control
+        assert!(context.order(aapl.clone(), 250.0).is_err());
+        assert!(context.pending_orders.is_empty());
+        assert_eq!(context.capacity.as_ref().unwrap().report(), &CapacityReport::default());
+
+        // A later order is capped against the real position, not a drifted shadow
+        context.order(aapl, 40.0).unwrap();
+        assert_eq!(context.capacity.as_ref().unwrap().report(), &CapacityReport::default());
+    }
+
+    #[test]
+    fn test_state_fingerprint() {
+        use crate::finance::Position;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let build = |reverse: bool| {
+            let mut context = Context::new(100000.0);
+            let mut positions = vec![
+                Position::new(aapl.clone(), 100.0, 10000.0, 100.0),
+                Position::new(msft.clone(), -50.0, -5000.0, 100.0),
+            ];
+            if reverse {
+                positions.reverse();
+            }
+            for position in positions {
+                context.portfolio.positions.insert(position.asset.id, position);
+            }
+            context.order(aapl.clone(), 10.0).unwrap();
+            context
+        };
+
+        // Order ids and insertion order don't matter
+        let left = build(false);
+        let right = build(true);
+        assert_eq!(left.state_fingerprint(), right.state_fingerprint());
+
+        let mut changed = build(false);
+        changed.portfolio.cash -= 0.01;
+        assert_ne!(left.state_fingerprint(), changed.state_fingerprint());
+
+        let mut changed = build(false);
+        changed.order(msft.clone(), 1.0).unwrap();
+        assert_ne!(left.state_fingerprint(), changed.state_fingerprint());
+
+        let mut changed = build(false);
+        changed.portfolio.positions.get_mut(&msft.id).unwrap().quantity = -49.0;
+        assert_ne!(left.state_fingerprint(), changed.state_fingerprint());
+    }
+
+    #[test]
+    fn test_universe_mask_rejects_outside_screen() {
+        use crate::finance::Position;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_universe_mask("universe", UniverseEnforcement::Reject);
+
+        // No screen output for today yet
+        assert!(context.order(aapl.clone(), 10.0).is_err());
+
+        context.update_universe("other", vec![2]);
+        context.update_universe("universe", vec![aapl.id]);
+        assert!(context.order(aapl.clone(), 10.0).is_ok());
+        assert!(context.order(msft.clone(), 10.0).is_err());
+        assert!(context.order_target(msft.clone(), 10.0).is_err());
+
+        // Exiting a position outside the screen is allowed, flipping it is not
+        context
+            .portfolio
+            .positions
+            .insert(msft.id, Position::new(msft.clone(), 50.0, 5000.0, 100.0));
+        assert!(context.order_target(msft.clone(), 0.0).is_ok());
+        assert!(context.order(msft, -80.0).is_err());
+        assert_eq!(context.pending_orders_count(), 2);
+    }
+
+    #[test]
+    fn test_universe_mask_warn_mode() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.set_universe_mask("universe", UniverseEnforcement::Warn);
+        context.update_universe("universe", vec![1]);
+
+        assert!(context.order(msft, 10.0).is_ok());
+        assert_eq!(context.universe_mask.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_trade_note_attaches_to_order() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+
+        // Logged after the order on the same bar
+        context.order(aapl.clone(), 10.0).unwrap();
+        context.log_trade_note(&aapl, "momentum breakout");
+        context.log_trade_note(&aapl, "sized at 10 shares");
+        assert_eq!(
+            context.pending_orders[0].note.as_deref(),
+            Some("momentum breakout; sized at 10 shares")
+        );
+
+        // Logged ahead of the order
+        context.log_trade_note(&msft, "rebalance into tech");
+        assert!(context.trade_notes.contains_key(&2));
+        context.order(msft, 5.0).unwrap();
+        assert_eq!(context.pending_orders[1].note.as_deref(), Some("rebalance into tech"));
+        assert!(context.trade_notes.is_empty());
+    }
+
+    #[test]
+    fn test_trade_note_kept_when_order_rejected() {
+        use crate::finance::{ControlManager, ControlMaxOrderSize};
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::shares(100.0)));
+        context.set_trading_controls(Arc::new(controls));
+
+        context.log_trade_note(&aapl, "breakout");
+        assert!(context.order(aapl.clone(), 500.0).is_err());
+        assert!(context.trade_notes.contains_key(&1));
+
+        context.order(aapl, 50.0).unwrap();
+        assert_eq!(context.pending_orders[0].note.as_deref(), Some("breakout"));
+        assert!(context.trade_notes.is_empty());
+    }
+
+    #[test]
+    fn test_trade_note_waits_for_next_bar_order() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        context.order(aapl.clone(), 10.0).unwrap();
+
+        // An order from an earlier bar does not pick up the note
+        context.timestamp += chrono::Duration::minutes(1);
+        context.log_trade_note(&aapl, "take profit");
+        assert!(context.pending_orders[0].note.is_none());
+
+        context.order(aapl, -10.0).unwrap();
+        assert_eq!(context.pending_orders[1].note.as_deref(), Some("take profit"));
+    }
+
     #[test]
     fn test_trading_algorithm_creation() {
         let asset_finder = Arc::new(AssetFinder::new());
@@ -799,6 +2796,65 @@ mod tests {
         assert_eq!(algo.get_scheduled_functions().len(), 0);
     }
 
+    #[test]
+    fn test_fills_use_asset_class_models() {
+        use crate::calendar::NYSECalendar;
+        use crate::data::InMemoryDataSource;
+        use crate::engine::{EngineConfig, SimulationEngine};
+        use crate::finance::{FixedBasisPointsSlippage, NoSlippage, PerContract, PerShare};
+        use crate::types::Bar;
+        use chrono::TimeZone;
+
+        struct BuyEach(Vec<Asset>);
+        impl Algorithm for BuyEach {
+            fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+                if context.portfolio.positions.is_empty() && context.pending_orders.is_empty() {
+                    for asset in &self.0 {
+                        context.order(asset.clone(), 10.0)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let stock = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let future = Asset::new(2, "ESH4".to_string(), "CME".to_string(), AssetType::Future, listed);
+
+        let mut algo = TradingAlgorithm::new(Arc::new(AssetFinder::new()));
+        algo.set_equities_models(Arc::new(NoSlippage), Arc::new(PerShare::new(0.01)))
+            .unwrap();
+        algo.set_futures_models(
+            Arc::new(FixedBasisPointsSlippage::new(100.0)),
+            Arc::new(PerContract::new(2.0, 0.0, 0.0)),
+        )
+        .unwrap();
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        for asset in [&stock, &future] {
+            source.add_asset(asset.clone());
+            source.add_bar(asset.id, Bar::new(start, 100.0, 100.0, 100.0, 100.0, 1e6));
+        }
+        source.set_date_range(start, start);
+
+        let mut engine = SimulationEngine::new(
+            EngineConfig::default(),
+            algo.broker(None),
+            Arc::new(NYSECalendar::new()),
+        );
+        let results = engine.run(&mut BuyEach(vec![stock, future]), &source, start, start).unwrap();
+
+        let fill = |asset_id: u64| {
+            let t = results.transactions.iter().find(|t| t.asset_id == asset_id).unwrap();
+            (t.price, t.commission)
+        };
+        assert_eq!(fill(1), (100.0, 0.1));
+        let (price, commission) = fill(2);
+        assert!((price - 101.0).abs() < 1e-9);
+        assert_eq!(commission, 20.0);
+    }
+
     #[test]
     fn test_symbol_lookup() {
         let asset_finder = Arc::new(AssetFinder::new());
@@ -1030,7 +3086,7 @@ mod tests {
         use crate::performance::PerformanceTracker;
 
         let mut tracker = PerformanceTracker::new();
-        let mut context_vars = HashMap::new();
+        let mut context_vars = std::collections::HashMap::new();
 
         let now = Utc::now();
         context_vars.insert(
@@ -1050,7 +3106,7 @@ mod tests {
         use crate::performance::PerformanceTracker;
 
         let mut tracker = PerformanceTracker::new();
-        let mut context_vars = HashMap::new();
+        let mut context_vars = std::collections::HashMap::new();
 
         let now = Utc::now();
         context_vars.insert("var1".to_string(), vec![(now, 1.0)]);
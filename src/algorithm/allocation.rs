@@ -0,0 +1,260 @@
+//! Re-weighting capital between the strategies of a [`MultiStrategy`]
+//!
+//! An [`Allocator`] is consulted on the first bar of every week or month. It
+//! sees each sleeve's returns since the start of the run and returns the
+//! fraction of the account each sleeve should hold; cash is then moved
+//! between the sleeves (and the unallocated remainder) so every sleeve's
+//! value matches its new share of the account. Strategies size against their
+//! sleeve, so they pick up the change the next time they trade.
+//!
+//! ```ignore
+//! let book = MultiStrategy::new(strategies, vec![0.5, 0.5])?
+//!     .with_allocator(RiskParity::new(60), Reallocation::Monthly);
+//! ```
+//!
+//! [`MultiStrategy`]: super::MultiStrategy
+
+use crate::types::Timestamp;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// How often sleeves are re-weighted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Reallocation {
+    /// On the first bar of each ISO week
+    Weekly,
+    /// On the first bar of each month
+    Monthly,
+}
+
+impl Reallocation {
+    /// Period `timestamp` falls in
+    pub(crate) fn period(self, timestamp: Timestamp) -> (i32, u32) {
+        let date = timestamp.date_naive();
+        match self {
+            Reallocation::Weekly => (date.iso_week().year(), date.iso_week().week()),
+            Reallocation::Monthly => (date.year(), date.month()),
+        }
+    }
+}
+
+/// What an allocator knows about one sleeve
+#[derive(Debug, Clone, Copy)]
+pub struct SleeveReturns<'a> {
+    pub name: &'a str,
+    /// Fraction of the account the sleeve holds now
+    pub allocation: f64,
+    /// Return of every bar since the start of the run, oldest first
+    pub returns: &'a [f64],
+}
+
+/// Chooses each sleeve's fraction of the account
+pub trait Allocator: Send {
+    /// New allocations, one per sleeve in order
+    ///
+    /// Allocations are clamped to be non-negative and scaled down if they
+    /// add up to more than 1.
+    fn allocate(&mut self, sleeves: &[SleeveReturns]) -> Vec<f64>;
+
+    fn name(&self) -> &str;
+}
+
+/// Sleeves' allocations as of one re-weighting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllocationRecord {
+    pub session: NaiveDate,
+    /// Sleeve name and allocation
+    pub allocations: Vec<(String, f64)>,
+}
+
+/// Mean and variance of the last `lookback` returns, if there are at least two
+fn trailing_moments(returns: &[f64], lookback: usize) -> Option<(f64, f64)> {
+    let window = &returns[returns.len().saturating_sub(lookback)..];
+    if window.len() < 2 {
+        return None;
+    }
+    let n = window.len() as f64;
+    let mean = window.iter().sum::<f64>() / n;
+    let variance = window.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some((mean, variance))
+}
+
+/// The same share of the account for every sleeve
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EqualWeight;
+
+impl Allocator for EqualWeight {
+    fn allocate(&mut self, sleeves: &[SleeveReturns]) -> Vec<f64> {
+        vec![1.0 / sleeves.len().max(1) as f64; sleeves.len()]
+    }
+
+    fn name(&self) -> &str {
+        "EqualWeight"
+    }
+}
+
+/// Shares inversely proportional to each sleeve's trailing volatility
+///
+/// Until every sleeve has `lookback`-bar history with some variation, the
+/// current allocations are kept.
+#[derive(Debug, Clone, Copy)]
+pub struct RiskParity {
+    pub lookback: usize,
+}
+
+impl RiskParity {
+    pub fn new(lookback: usize) -> Self {
+        Self { lookback }
+    }
+}
+
+impl Allocator for RiskParity {
+    fn allocate(&mut self, sleeves: &[SleeveReturns]) -> Vec<f64> {
+        let current = || sleeves.iter().map(|s| s.allocation).collect();
+        let inverse: Option<Vec<f64>> = sleeves
+            .iter()
+            .map(|s| {
+                trailing_moments(s.returns, self.lookback)
+                    .filter(|(_, variance)| *variance > 0.0)
+                    .map(|(_, variance)| 1.0 / variance.sqrt())
+            })
+            .collect();
+        match inverse {
+            Some(inverse) if !inverse.is_empty() => {
+                let total: f64 = inverse.iter().sum();
+                inverse.iter().map(|w| w / total).collect()
+            }
+            _ => current(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "RiskParity"
+    }
+}
+
+/// Kelly fractions of trailing returns, each capped at `cap`
+///
+/// A sleeve's Kelly fraction is its mean return over the variance of its
+/// returns; sleeves losing money get nothing. Sleeves without `lookback`-bar
+/// history keep their current allocation.
+#[derive(Debug, Clone, Copy)]
+pub struct KellyCapped {
+    pub lookback: usize,
+    /// Largest share of the account any sleeve may hold
+    pub cap: f64,
+}
+
+impl KellyCapped {
+    pub fn new(lookback: usize, cap: f64) -> Self {
+        Self { lookback, cap }
+    }
+}
+
+impl Allocator for KellyCapped {
+    fn allocate(&mut self, sleeves: &[SleeveReturns]) -> Vec<f64> {
+        sleeves
+            .iter()
+            .map(|s| match trailing_moments(s.returns, self.lookback) {
+                Some((mean, variance)) if variance > 0.0 => (mean / variance).clamp(0.0, self.cap),
+                Some(_) => s.allocation.min(self.cap),
+                None => s.allocation,
+            })
+            .collect()
+    }
+
+    fn name(&self) -> &str {
+        "KellyCapped"
+    }
+}
+
+/// Make allocations non-negative and at most 1 in total
+pub(crate) fn normalize(mut allocations: Vec<f64>) -> Vec<f64> {
+    for allocation in &mut allocations {
+        if !allocation.is_finite() || *allocation < 0.0 {
+            *allocation = 0.0;
+        }
+    }
+    let total: f64 = allocations.iter().sum();
+    if total > 1.0 {
+        allocations.iter_mut().for_each(|a| *a /= total);
+    }
+    allocations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, Context, MultiStrategy};
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::SimulationEngine;
+    use crate::error::Result;
+    use crate::types::Bar;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Keeps its whole sleeve in one asset
+    struct AllIn(Asset);
+
+    impl Algorithm for AllIn {
+        fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+            let price = data.current_price(&self.0)?;
+            // Already holding the whole sleeve is not an error here
+            let _ = context.order_target_percent(self.0.clone(), 1.0, price);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_allocators_and_weekly_reallocation() {
+        let steady = [0.01, 0.01, 0.01, 0.01];
+        let volatile = [0.04, -0.02, 0.04, -0.02];
+        let sleeves = [
+            SleeveReturns { name: "steady", allocation: 0.5, returns: &steady[..3] },
+            SleeveReturns { name: "volatile", allocation: 0.5, returns: &volatile },
+        ];
+        assert_eq!(EqualWeight.allocate(&sleeves), vec![0.5, 0.5]);
+        // The steady sleeve never varies, so risk parity keeps the weights
+        assert_eq!(RiskParity::new(4).allocate(&sleeves), vec![0.5, 0.5]);
+        // The volatile sleeve's Kelly fraction, 0.01 / 0.0012, is capped
+        assert_eq!(KellyCapped::new(4, 0.6).allocate(&sleeves), vec![0.5, 0.6]);
+        assert_eq!(normalize(vec![1.5, 0.5, -1.0]), vec![0.75, 0.25, 0.0]);
+
+        // Two weeks of bars: the second Monday re-weights 80/20 to 50/50
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        let assets: Vec<Asset> = (1..=2)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+            .collect();
+        for asset in &assets {
+            source.add_asset(asset.clone());
+            for offset in [0, 1, 2, 3, 6, 7] {
+                source.add_bar(asset.id, Bar::new(start + Duration::days(offset), 10.0, 10.0, 10.0, 10.0, 1e6));
+            }
+        }
+        let end = start + Duration::days(7);
+        source.set_date_range(start, end);
+
+        let strategies: Vec<(&str, Box<dyn Algorithm>)> = vec![
+            ("a", Box::new(AllIn(assets[0].clone()))),
+            ("b", Box::new(AllIn(assets[1].clone()))),
+        ];
+        let mut book = MultiStrategy::new(strategies, vec![0.8, 0.2])
+            .unwrap()
+            .with_allocator(EqualWeight, Reallocation::Weekly);
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        engine.run(&mut book, &source, start, end).unwrap();
+
+        let history = book.allocation_history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].session, NaiveDate::from_ymd_opt(2024, 1, 8).unwrap());
+        assert_eq!(history[0].allocations, vec![("a".to_string(), 0.5), ("b".to_string(), 0.5)]);
+        // Each strategy traded to its new half of the account
+        assert_eq!(book.sleeve("a").unwrap().get_position(1).unwrap().quantity, 5_000.0);
+        assert_eq!(book.sleeve("b").unwrap().get_position(2).unwrap().quantity, 5_000.0);
+        assert!(book.sleeves().all(|(_, allocation, sleeve)| sleeve.returns.abs() < 1e-9 && allocation == 0.5));
+    }
+}
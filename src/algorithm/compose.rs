@@ -0,0 +1,497 @@
+//! Building strategies out of other strategies
+//!
+//! The combinators here are themselves [`Algorithm`]s, so they nest and run on
+//! the engine like any other strategy:
+//!
+//! - [`Ensemble`] runs every member each bar, scaling each member's orders by
+//!   its weight.
+//! - [`Conditional`] hands each bar to one of two algorithms, chosen by a
+//!   regime function.
+//! - [`PipelineGated`] runs a pipeline each session and drops the wrapped
+//!   algorithm's orders that would add exposure outside the pipeline's screen.
+//!
+//! Members share the context, so they see the whole portfolio and their
+//! orders merge into one order flow. Each member's recorded variables are
+//! kept under its own name, as `"<member>/<variable>"`, while the member
+//! itself keeps reading them by their plain names.
+//!
+//! ```ignore
+//! let strategy = Ensemble::new(
+//!     vec![("momentum", Box::new(Momentum::new()) as Box<dyn Algorithm>),
+//!          ("reversal", Box::new(Reversal::new()))],
+//!     vec![0.7, 0.3],
+//! )?;
+//! engine.run(&mut Conditional::new(|ctx, _| ctx.portfolio.returns > -0.1, strategy, Flat))?;
+//! ```
+
+use super::{Algorithm, Context};
+use crate::data::BarData;
+use crate::error::{Result, ZiplineError};
+use crate::order::OrderSide;
+use crate::pipeline::engine::{DataProvider, Pipeline};
+use crate::types::{AssetId, Timestamp};
+use hashbrown::{HashMap, HashSet};
+use std::sync::Arc;
+
+type RecordedVars = HashMap<String, Vec<(Timestamp, f64)>>;
+
+/// An algorithm with its own view of the recorded variables
+pub(super) struct Member {
+    pub(super) name: String,
+    pub(super) algorithm: Box<dyn Algorithm>,
+    recorded: RecordedVars,
+}
+
+impl Member {
+    pub(super) fn new(name: impl Into<String>, algorithm: Box<dyn Algorithm>) -> Self {
+        Self {
+            name: name.into(),
+            algorithm,
+            recorded: RecordedVars::new(),
+        }
+    }
+
+    /// Run `step` against the context, returning the index of the first order
+    /// it placed
+    ///
+    /// While `step` runs the context's recorded variables are the member's
+    /// own; what it records is then copied out under the member's name.
+    pub(super) fn run<T>(
+        &mut self,
+        context: &mut Context,
+        step: impl FnOnce(&mut dyn Algorithm, &mut Context) -> Result<T>,
+    ) -> Result<(T, usize)> {
+        let first_order = context.pending_orders.len();
+        let before: HashMap<String, usize> = self
+            .recorded
+            .iter()
+            .map(|(name, values)| (name.clone(), values.len()))
+            .collect();
+
+        let shared = std::mem::replace(&mut context.recorded_vars, std::mem::take(&mut self.recorded));
+        let result = step(self.algorithm.as_mut(), context);
+        self.recorded = std::mem::replace(&mut context.recorded_vars, shared);
+
+        for (name, values) in &self.recorded {
+            let seen = before.get(name).copied().unwrap_or(0);
+            if values.len() > seen {
+                context
+                    .recorded_vars
+                    .entry(format!("{}/{}", self.name, name))
+                    .or_default()
+                    .extend_from_slice(&values[seen..]);
+            }
+        }
+
+        Ok((result?, first_order))
+    }
+
+    pub(super) fn initialize(&mut self, context: &mut Context) {
+        let _ = self.run(context, |algorithm, context| {
+            algorithm.initialize(context);
+            Ok(())
+        });
+    }
+
+    pub(super) fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<usize> {
+        self.run(context, |algorithm, context| algorithm.before_trading_start(context, data))
+            .map(|(_, first)| first)
+    }
+
+    pub(super) fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<usize> {
+        self.run(context, |algorithm, context| algorithm.handle_data(context, data))
+            .map(|(_, first)| first)
+    }
+
+    pub(super) fn analyze(&mut self, context: &Context) -> Result<()> {
+        self.algorithm.analyze(context)
+    }
+}
+
+/// Runs several algorithms together, scaling each one's orders by its weight
+///
+/// A weight of 0.5 halves every order the member places. Members sizing
+/// orders from the whole portfolio (`order_percent`, `order_value`) thus
+/// trade their share of it; target orders are computed against the combined
+/// positions and scale the same way.
+pub struct Ensemble {
+    members: Vec<(Member, f64)>,
+}
+
+impl Ensemble {
+    /// Combine named algorithms with one non-negative weight each
+    pub fn new<S: Into<String>>(members: Vec<(S, Box<dyn Algorithm>)>, weights: Vec<f64>) -> Result<Self> {
+        if members.len() != weights.len() {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Ensemble has {} members but {} weights",
+                members.len(),
+                weights.len()
+            )));
+        }
+        if let Some(weight) = weights.iter().find(|w| !w.is_finite() || **w < 0.0) {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Ensemble weights must be finite and non-negative, got {}",
+                weight
+            )));
+        }
+
+        Ok(Self {
+            members: members
+                .into_iter()
+                .zip(weights)
+                .map(|((name, algorithm), weight)| (Member::new(name, algorithm), weight))
+                .collect(),
+        })
+    }
+
+    /// Scale the orders placed from `first` on, dropping any scaled to nothing
+    fn scale_orders(context: &mut Context, first: usize, weight: f64) {
+        if weight == 1.0 {
+            return;
+        }
+        for order in &mut context.pending_orders[first..] {
+            order.quantity *= weight;
+            order.amount *= weight;
+        }
+        let mut index = 0;
+        context.pending_orders.retain(|order| {
+            index += 1;
+            index <= first || order.quantity > 0.0
+        });
+    }
+}
+
+impl Algorithm for Ensemble {
+    fn initialize(&mut self, context: &mut Context) {
+        for (member, _) in &mut self.members {
+            member.initialize(context);
+        }
+    }
+
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        for (member, weight) in &mut self.members {
+            let first = member.handle_data(context, data)?;
+            Self::scale_orders(context, first, *weight);
+        }
+        Ok(())
+    }
+
+    fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        for (member, weight) in &mut self.members {
+            let first = member.before_trading_start(context, data)?;
+            Self::scale_orders(context, first, *weight);
+        }
+        Ok(())
+    }
+
+    fn analyze(&mut self, context: &Context) -> Result<()> {
+        for (member, _) in &mut self.members {
+            member.analyze(context)?;
+        }
+        Ok(())
+    }
+
+    fn warm_up_bars(&self) -> usize {
+        self.members
+            .iter()
+            .map(|(member, _)| member.algorithm.warm_up_bars())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Function choosing between the two algorithms of a [`Conditional`]
+pub type RegimeFn = Box<dyn FnMut(&Context, &BarData) -> bool + Send>;
+
+/// Hands each bar to one of two algorithms, chosen by a regime function
+///
+/// Both algorithms are initialized and see `before_trading_start` every
+/// session, so the inactive one keeps its daily state current; only the
+/// chosen one handles the bar. Their variables are recorded as `"when_true/…"`
+/// and `"when_false/…"`, and the regime itself as `"regime"` (1 or 0).
+pub struct Conditional {
+    regime: RegimeFn,
+    when_true: Member,
+    when_false: Member,
+}
+
+impl Conditional {
+    pub fn new<F, A, B>(regime: F, when_true: A, when_false: B) -> Self
+    where
+        F: FnMut(&Context, &BarData) -> bool + Send + 'static,
+        A: Algorithm + 'static,
+        B: Algorithm + 'static,
+    {
+        Self {
+            regime: Box::new(regime),
+            when_true: Member::new("when_true", Box::new(when_true)),
+            when_false: Member::new("when_false", Box::new(when_false)),
+        }
+    }
+}
+
+impl Algorithm for Conditional {
+    fn initialize(&mut self, context: &mut Context) {
+        self.when_true.initialize(context);
+        self.when_false.initialize(context);
+    }
+
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        let active = (self.regime)(context, data);
+        context.record("regime", if active { 1.0 } else { 0.0 });
+        let member = if active { &mut self.when_true } else { &mut self.when_false };
+        member.handle_data(context, data).map(|_| ())
+    }
+
+    fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.when_true.before_trading_start(context, data)?;
+        self.when_false.before_trading_start(context, data)?;
+        Ok(())
+    }
+
+    fn analyze(&mut self, context: &Context) -> Result<()> {
+        self.when_true.analyze(context)?;
+        self.when_false.analyze(context)
+    }
+
+    fn warm_up_bars(&self) -> usize {
+        self.when_true
+            .algorithm
+            .warm_up_bars()
+            .max(self.when_false.algorithm.warm_up_bars())
+    }
+}
+
+/// Restricts an algorithm to the assets passing a pipeline filter
+///
+/// The pipeline runs at the start of each session. Orders the wrapped
+/// algorithm places in assets outside the filter are dropped, unless they only
+/// reduce an existing position, so assets leaving the screen can be exited.
+pub struct PipelineGated<A: Algorithm> {
+    algorithm: A,
+    pipeline: Pipeline,
+    provider: Arc<dyn DataProvider>,
+    filter: String,
+    members: HashSet<AssetId>,
+}
+
+impl<A: Algorithm> PipelineGated<A> {
+    /// Gate `algorithm` on the assets passing `filter` in `pipeline`
+    pub fn new(algorithm: A, pipeline: Pipeline, provider: Arc<dyn DataProvider>, filter: &str) -> Self {
+        Self {
+            algorithm,
+            pipeline,
+            provider,
+            filter: filter.to_string(),
+            members: HashSet::new(),
+        }
+    }
+
+    /// Assets passing the filter this session
+    pub fn members(&self) -> &HashSet<AssetId> {
+        &self.members
+    }
+
+    /// Drop the orders placed from `first` on that add exposure outside the screen
+    fn gate_orders(&self, context: &mut Context, first: usize) {
+        let positions = &context.portfolio.positions;
+        let mut index = 0;
+        context.pending_orders.retain(|order| {
+            index += 1;
+            if index <= first || self.members.contains(&order.asset.id) {
+                return true;
+            }
+            let held = positions.get(&order.asset.id).map_or(0.0, |p| p.quantity);
+            let reduces = match order.side {
+                OrderSide::Sell => held > 0.0 && order.quantity <= held,
+                OrderSide::Buy => held < 0.0 && order.quantity <= -held,
+            };
+            if !reduces {
+                tracing::debug!(
+                    asset = %order.asset.symbol,
+                    filter = %self.filter,
+                    "Dropping order not in pipeline filter"
+                );
+            }
+            reduces
+        });
+    }
+}
+
+impl<A: Algorithm> Algorithm for PipelineGated<A> {
+    fn initialize(&mut self, context: &mut Context) {
+        self.algorithm.initialize(context);
+    }
+
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        let first = context.pending_orders.len();
+        self.algorithm.handle_data(context, data)?;
+        self.gate_orders(context, first);
+        Ok(())
+    }
+
+    fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        let output = self.pipeline.run(context.timestamp, self.provider.clone())?;
+        self.members = output.get_filtered_assets(&self.filter).into_iter().collect();
+
+        let first = context.pending_orders.len();
+        self.algorithm.before_trading_start(context, data)?;
+        self.gate_orders(context, first);
+        Ok(())
+    }
+
+    fn analyze(&mut self, context: &Context) -> Result<()> {
+        self.algorithm.analyze(context)
+    }
+
+    fn warm_up_bars(&self) -> usize {
+        self.algorithm
+            .warm_up_bars()
+            .max(self.pipeline.max_window_length())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::finance::Position;
+    use crate::pipeline::engine::{Filter, OHLCVBar, PipelineContext};
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    /// Buys a fixed number of shares of each asset every bar
+    struct Buyer {
+        assets: Vec<Asset>,
+        shares: f64,
+    }
+
+    impl Algorithm for Buyer {
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            for asset in &self.assets {
+                context.order(asset.clone(), self.shares)?;
+            }
+            let bars = context.recorded_vars.get("bars").map_or(0, Vec::len);
+            context.record("bars", bars as f64 + 1.0);
+            Ok(())
+        }
+    }
+
+    struct Flat;
+
+    impl Algorithm for Flat {
+        fn handle_data(&mut self, _context: &mut Context, _data: &BarData) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Passes asset 1 only
+    #[derive(Clone)]
+    struct FirstAsset;
+
+    impl Filter for FirstAsset {
+        fn evaluate(&self, _timestamp: Timestamp, context: &PipelineContext) -> Result<HashMap<u64, bool>> {
+            Ok(context.assets().iter().map(|a| (a.id, a.id == 1)).collect())
+        }
+
+        fn name(&self) -> &str {
+            "FirstAsset"
+        }
+
+        fn clone_box(&self) -> Box<dyn Filter> {
+            Box::new(self.clone())
+        }
+    }
+
+    struct FlatPrices;
+
+    impl DataProvider for FlatPrices {
+        fn get_prices(&self, _asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(vec![10.0; lookback])
+        }
+
+        fn get_volumes(&self, _asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(vec![1000.0; lookback])
+        }
+
+        fn get_ohlcv(&self, _asset_id: u64, _lookback: usize) -> Result<Vec<OHLCVBar>> {
+            Ok(Vec::new())
+        }
+
+        fn get_latest_price(&self, _asset_id: u64) -> Result<f64> {
+            Ok(10.0)
+        }
+    }
+
+    fn asset(id: u64) -> Asset {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed)
+    }
+
+    fn new_context() -> Context {
+        let mut context = Context::new(100_000.0);
+        context.timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        context
+    }
+
+    #[test]
+    fn test_ensemble_scales_orders_and_namespaces_records() {
+        let buyer = |shares| Box::new(Buyer { assets: vec![asset(1)], shares }) as Box<dyn Algorithm>;
+        assert!(Ensemble::new(vec![("a", buyer(100.0))], vec![0.5, 0.5]).is_err());
+
+        let mut ensemble = Ensemble::new(vec![("a", buyer(100.0)), ("b", buyer(40.0)), ("c", buyer(10.0))], vec![0.75, 0.5, 0.0]).unwrap();
+        let mut context = new_context();
+        let data = BarData::new(10);
+        for _ in 0..2 {
+            ensemble.handle_data(&mut context, &data).unwrap();
+        }
+
+        let quantities: Vec<f64> = context.pending_orders.iter().map(|o| o.quantity).collect();
+        assert_eq!(quantities, vec![75.0, 20.0, 75.0, 20.0]);
+        // Each member counted its own bars
+        assert_eq!(context.recorded_vars["a/bars"].last().unwrap().1, 2.0);
+        assert_eq!(context.recorded_vars["c/bars"].len(), 2);
+        assert!(!context.recorded_vars.contains_key("bars"));
+    }
+
+    #[test]
+    fn test_conditional_and_pipeline_gate() {
+        let mut switch = true;
+        let mut conditional = Conditional::new(
+            move |_, _| {
+                switch = !switch;
+                !switch
+            },
+            Buyer { assets: vec![asset(1)], shares: 10.0 },
+            Flat,
+        );
+        let mut context = new_context();
+        let data = BarData::new(10);
+        for _ in 0..3 {
+            conditional.handle_data(&mut context, &data).unwrap();
+        }
+        assert_eq!(context.pending_orders.len(), 2);
+        let regime: Vec<f64> = context.recorded_vars["regime"].iter().map(|(_, v)| *v).collect();
+        assert_eq!(regime, vec![1.0, 0.0, 1.0]);
+        assert_eq!(context.recorded_vars["when_true/bars"].len(), 2);
+
+        // Asset 2 is outside the screen: buying it is dropped, selling the held 5 shares is not
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .set_universe(vec![asset(1), asset(2)])
+            .add_filter("screen".to_string(), Box::new(FirstAsset));
+        let gated_buyer = Buyer { assets: vec![asset(1), asset(2)], shares: 10.0 };
+        let mut gated = PipelineGated::new(gated_buyer, pipeline, Arc::new(FlatPrices), "screen");
+        let mut context = new_context();
+        context.portfolio.positions.insert(2, Position::new(asset(2), 5.0, 50.0, 10.0));
+        gated.before_trading_start(&mut context, &data).unwrap();
+        assert_eq!(gated.members().len(), 1);
+        gated.handle_data(&mut context, &data).unwrap();
+        assert_eq!(context.pending_orders.len(), 1);
+        assert_eq!(context.pending_orders[0].asset.id, 1);
+
+        context.pending_orders.clear();
+        context.order(asset(2), -5.0).unwrap();
+        gated.gate_orders(&mut context, 0);
+        assert_eq!(context.pending_orders.len(), 1);
+    }
+}
@@ -0,0 +1,433 @@
+//! Several strategies trading one account, each in its own sleeve
+//!
+//! [`MultiStrategy`] runs named strategies side by side, giving each a sleeve:
+//! a virtual sub-portfolio funded with its allocation of the starting capital.
+//! While a strategy runs, the context's portfolio and open orders are its
+//! sleeve's, so `order_percent` and `order_target` size against its own
+//! capital and it only sees (and cancels) its own orders. Fills are booked to
+//! the sleeve whose order they fill; the context's portfolio is the master
+//! book, holding every sleeve's positions together.
+//!
+//! Trading controls apply to the master book: each order a strategy places is
+//! checked against the controls with the master portfolio and every sleeve's
+//! open orders in view, and an order failing a control is rejected with the
+//! error returned to the strategy's `order*` call. Cash the engine pays
+//! or charges outside of fills (interest, dividends) and orders the engine
+//! places itself are only reflected in the master portfolio.
+//!
+//! ```ignore
+//! let mut book = MultiStrategy::new(
+//!     vec![("momentum", Box::new(Momentum::new()) as Box<dyn Algorithm>),
+//!          ("carry", Box::new(Carry::new()))],
+//!     vec![0.6, 0.4],
+//! )?;
+//! engine.run(&mut book, &source, start, end)?;
+//! println!("{}", book.sleeve("carry").unwrap().returns);
+//! ```
+//!
+//! Allocations are fixed unless an [`Allocator`] is installed with
+//! [`MultiStrategy::with_allocator`]; see [`allocation`](super::allocation).
+
+use super::allocation::{normalize, AllocationRecord, Allocator, Reallocation, SleeveReturns};
+use super::compose::Member;
+use super::{Algorithm, Context, ControlBook};
+use crate::asset::Asset;
+use crate::data::BarData;
+use crate::error::{Result, ZiplineError};
+use crate::finance::Portfolio;
+use crate::order::Order;
+use crate::types::{AssetId, OrderId, Timestamp};
+use hashbrown::HashMap;
+
+/// A strategy and the sub-portfolio it trades
+struct Sleeve {
+    member: Member,
+    /// Fraction of the account funding the sleeve
+    allocation: f64,
+    portfolio: Portfolio,
+    /// Return of every bar marked, net of capital moved in or out
+    returns: Vec<f64>,
+    /// Value as of the last mark
+    last_value: Option<f64>,
+}
+
+/// Runs several strategies in one account, each with its own sub-portfolio
+pub struct MultiStrategy {
+    sleeves: Vec<Sleeve>,
+    /// Sleeve that placed each order
+    owners: HashMap<OrderId, usize>,
+    /// Assets ordered, to open sleeve positions in
+    assets: HashMap<AssetId, Asset>,
+    /// Master ledger transactions already booked to sleeves
+    booked: usize,
+    allocator: Option<(Box<dyn Allocator>, Reallocation)>,
+    /// Period of the last bar, for spotting the first bar of the next
+    period: Option<(i32, u32)>,
+    history: Vec<AllocationRecord>,
+}
+
+impl MultiStrategy {
+    /// Combine named strategies with the fraction of capital each is allocated
+    ///
+    /// Allocations must be non-negative and sum to at most 1; whatever is
+    /// left over stays in the master book as cash.
+    pub fn new<S: Into<String>>(strategies: Vec<(S, Box<dyn Algorithm>)>, allocations: Vec<f64>) -> Result<Self> {
+        if strategies.len() != allocations.len() {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "MultiStrategy has {} strategies but {} allocations",
+                strategies.len(),
+                allocations.len()
+            )));
+        }
+        if let Some(allocation) = allocations.iter().find(|a| !a.is_finite() || **a < 0.0) {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "MultiStrategy allocations must be finite and non-negative, got {}",
+                allocation
+            )));
+        }
+        let total: f64 = allocations.iter().sum();
+        if total > 1.0 + 1e-9 {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "MultiStrategy allocations sum to {}, more than the whole account",
+                total
+            )));
+        }
+
+        Ok(Self {
+            sleeves: strategies
+                .into_iter()
+                .zip(allocations)
+                .map(|((name, algorithm), allocation)| Sleeve {
+                    member: Member::new(name, algorithm),
+                    allocation,
+                    portfolio: Portfolio::new(0.0),
+                    returns: Vec::new(),
+                    last_value: None,
+                })
+                .collect(),
+            owners: HashMap::new(),
+            assets: HashMap::new(),
+            booked: 0,
+            allocator: None,
+            period: None,
+            history: Vec::new(),
+        })
+    }
+
+    /// Re-weight the sleeves with `allocator` on the first bar of every
+    /// week or month
+    pub fn with_allocator(mut self, allocator: impl Allocator + 'static, frequency: Reallocation) -> Self {
+        self.allocator = Some((Box::new(allocator), frequency));
+        self
+    }
+
+    /// Allocations chosen at each re-weighting, oldest first
+    pub fn allocation_history(&self) -> &[AllocationRecord] {
+        &self.history
+    }
+
+    /// Sub-portfolio of the strategy named `name`
+    pub fn sleeve(&self, name: &str) -> Option<&Portfolio> {
+        self.sleeves
+            .iter()
+            .find(|sleeve| sleeve.member.name == name)
+            .map(|sleeve| &sleeve.portfolio)
+    }
+
+    /// Every strategy's name, allocation and sub-portfolio
+    pub fn sleeves(&self) -> impl Iterator<Item = (&str, f64, &Portfolio)> {
+        self.sleeves
+            .iter()
+            .map(|sleeve| (sleeve.member.name.as_str(), sleeve.allocation, &sleeve.portfolio))
+    }
+
+    /// Book fills of sleeve orders made since the last call to their sleeves
+    fn book_fills(&mut self, context: &Context) {
+        let transactions = context.portfolio.ledger().get_all_transactions();
+        for transaction in &transactions[self.booked.min(transactions.len())..] {
+            let owner = self.owners.get(&transaction.order_id);
+            let asset = self.assets.get(&transaction.asset_id);
+            if let (Some(&owner), Some(asset)) = (owner, asset) {
+                // Lots are the master ledger's, not the sleeve's
+                let mut transaction = transaction.clone();
+                transaction.lots.clear();
+                self.sleeves[owner].portfolio.record_fill(asset, transaction);
+            }
+        }
+        self.booked = transactions.len();
+    }
+
+    /// Value every sleeve, marking its positions at `data`'s prices if given
+    fn mark(&mut self, timestamp: Timestamp, data: Option<&BarData>) {
+        for sleeve in &mut self.sleeves {
+            if let Some(data) = data {
+                for position in sleeve.portfolio.positions.values_mut() {
+                    if let Ok(price) = data.current_price(&position.asset) {
+                        position.update_price(price);
+                    }
+                }
+            }
+            sleeve.portfolio.update_value(timestamp);
+            let value = sleeve.portfolio.portfolio_value;
+            if data.is_some() {
+                if let Some(last) = sleeve.last_value.filter(|last| *last > 0.0) {
+                    sleeve.returns.push(value / last - 1.0);
+                }
+                sleeve.last_value = Some(value);
+            }
+        }
+    }
+
+    /// Re-weight the sleeves if `timestamp` starts a new period
+    ///
+    /// Sleeves are resized against the whole account: the master's cash plus
+    /// every sleeve's positions, as just marked.
+    fn reallocate(&mut self, context: &Context) {
+        let Some((allocator, frequency)) = &mut self.allocator else {
+            return;
+        };
+        let period = frequency.period(context.timestamp);
+        let first = self.period.replace(period);
+        if first.is_none_or(|last| last == period) {
+            return;
+        }
+
+        let sleeves: Vec<SleeveReturns> = self
+            .sleeves
+            .iter()
+            .map(|sleeve| SleeveReturns {
+                name: &sleeve.member.name,
+                allocation: sleeve.allocation,
+                returns: &sleeve.returns,
+            })
+            .collect();
+        let mut allocations = normalize(allocator.allocate(&sleeves));
+        allocations.resize(self.sleeves.len(), 0.0);
+
+        let account = context.portfolio.cash
+            + self
+                .sleeves
+                .iter()
+                .map(|sleeve| sleeve.portfolio.positions_value)
+                .sum::<f64>();
+        for (sleeve, allocation) in self.sleeves.iter_mut().zip(&allocations) {
+            // Moved capital counts as funding, not profit, so the sleeve's
+            // cash stays in step with its ledger
+            let transfer = account * allocation - sleeve.portfolio.portfolio_value;
+            sleeve.portfolio.starting_cash += transfer;
+            sleeve.portfolio.cash += transfer;
+            sleeve.portfolio.portfolio_value += transfer;
+            sleeve.portfolio.pnl = sleeve.portfolio.portfolio_value - sleeve.portfolio.starting_cash;
+            sleeve.last_value = Some(sleeve.portfolio.portfolio_value);
+            sleeve.allocation = *allocation;
+        }
+        self.history.push(AllocationRecord {
+            session: context.timestamp.date_naive(),
+            allocations: self
+                .sleeves
+                .iter()
+                .map(|sleeve| (sleeve.member.name.clone(), sleeve.allocation))
+                .collect(),
+        });
+    }
+
+    /// Run one hook of sleeve `index` against its own portfolio and orders
+    fn run_sleeve(
+        &mut self,
+        index: usize,
+        context: &mut Context,
+        step: impl FnOnce(&mut Member, &mut Context) -> Result<()>,
+    ) -> Result<()> {
+        let sleeve = &mut self.sleeves[index];
+        let owners = &mut self.owners;
+        let (mine, others): (Vec<Order>, Vec<Order>) = std::mem::take(&mut context.pending_orders)
+            .into_iter()
+            .partition(|order| owners.get(&order.id) == Some(&index));
+
+        // The controls keep seeing the master book while the sleeve trades
+        context.pending_orders = mine;
+        let own = std::mem::replace(&mut sleeve.portfolio, Portfolio::new(0.0));
+        let master = std::mem::replace(&mut context.portfolio, own);
+        context.control_book = Some(ControlBook {
+            portfolio: master,
+            orders: others,
+        });
+        let result = step(&mut sleeve.member, context);
+        let book = context.control_book.take().expect("control book is set while a sleeve runs");
+        sleeve.portfolio = std::mem::replace(&mut context.portfolio, book.portfolio);
+        let mine = std::mem::replace(&mut context.pending_orders, book.orders);
+
+        for order in mine {
+            if !owners.contains_key(&order.id) {
+                owners.insert(order.id, index);
+                self.assets.insert(order.asset.id, order.asset.clone());
+            }
+            context.pending_orders.push(order);
+        }
+        result
+    }
+}
+
+impl Algorithm for MultiStrategy {
+    fn initialize(&mut self, context: &mut Context) {
+        let capital = context.portfolio.starting_cash;
+        self.owners.clear();
+        self.assets.clear();
+        self.booked = 0;
+        self.period = None;
+        self.history.clear();
+        for index in 0..self.sleeves.len() {
+            let sleeve = &mut self.sleeves[index];
+            sleeve.portfolio = Portfolio::new(capital * sleeve.allocation);
+            sleeve.returns.clear();
+            sleeve.last_value = None;
+            let _ = self.run_sleeve(index, context, |member, context| {
+                member.initialize(context);
+                Ok(())
+            });
+        }
+    }
+
+    fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.book_fills(context);
+        for index in 0..self.sleeves.len() {
+            self.run_sleeve(index, context, |member, context| {
+                member.before_trading_start(context, data).map(|_| ())
+            })?;
+        }
+        Ok(())
+    }
+
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.book_fills(context);
+        self.mark(context.timestamp, Some(data));
+        self.reallocate(context);
+        for index in 0..self.sleeves.len() {
+            self.run_sleeve(index, context, |member, context| {
+                member.handle_data(context, data).map(|_| ())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Book the last fills, then let each strategy analyze the master book
+    fn analyze(&mut self, context: &Context) -> Result<()> {
+        self.book_fills(context);
+        self.mark(context.timestamp, None);
+        for sleeve in &mut self.sleeves {
+            sleeve.member.analyze(context)?;
+        }
+        Ok(())
+    }
+
+    fn warm_up_bars(&self) -> usize {
+        self.sleeves
+            .iter()
+            .map(|sleeve| sleeve.member.algorithm.warm_up_bars())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::NYSECalendar;
+    use crate::data::InMemoryDataSource;
+    use crate::engine::SimulationEngine;
+    use crate::finance::{ControlManager, ControlMaxOrderSize};
+    use crate::types::Bar;
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use std::sync::{Arc, Mutex};
+
+    /// Puts a fraction of its capital into one asset on the first bar
+    struct Allocate {
+        asset: Asset,
+        percent: f64,
+        done: bool,
+        rejections: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Algorithm for Allocate {
+        fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+            if !self.done {
+                let price = data.current_price(&self.asset)?;
+                if let Err(e) = context.order_percent(self.asset.clone(), self.percent, price) {
+                    self.rejections.lock().unwrap().push(e.to_string());
+                }
+                self.done = true;
+            }
+            Ok(())
+        }
+    }
+
+    type Run = (MultiStrategy, crate::performance::PerformanceTracker, Vec<String>);
+
+    fn run(controls: Option<ControlManager>) -> Run {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let assets: Vec<Asset> = (1..=2)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+            .collect();
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        for (asset, (first, last)) in assets.iter().zip([(100.0, 110.0), (50.0, 50.0)]) {
+            source.add_asset(asset.clone());
+            for (offset, price) in [(0, first), (1, first), (2, last)] {
+                source.add_bar(asset.id, Bar::new(start + Duration::days(offset), price, price, price, price, 1e6));
+            }
+        }
+        let end = start + Duration::days(2);
+        source.set_date_range(start, end);
+
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let strategy = |asset: &Asset, percent| {
+            let rejections = rejections.clone();
+            Box::new(Allocate { asset: asset.clone(), percent, done: false, rejections }) as Box<dyn Algorithm>
+        };
+        let mut book = MultiStrategy::new(
+            vec![("a", strategy(&assets[0], 1.0)), ("b", strategy(&assets[1], 0.5))],
+            vec![0.6, 0.4],
+        )
+        .unwrap();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        if let Some(controls) = controls {
+            engine = engine.with_trading_controls(Arc::new(controls));
+        }
+        let results = engine.run(&mut book, &source, start, end).unwrap();
+        let rejections = rejections.lock().unwrap().clone();
+        (book, results, rejections)
+    }
+
+    #[test]
+    fn test_sleeves_trade_their_own_capital() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "A1".to_string(), "NYSE".to_string(), listed);
+        let idle = || Box::new(crate::algorithm::BuyAndHold::new(asset.clone())) as Box<dyn Algorithm>;
+        assert!(MultiStrategy::new(vec![("a", idle()), ("b", idle())], vec![0.6, 0.5]).is_err());
+
+        // Sizing against its own sleeve, a buys $60,000 of A1 at $100 and b
+        // $20,000 of A2 at $50
+        let (book, results, rejections) = run(None);
+        assert!(rejections.is_empty());
+        let a = book.sleeve("a").unwrap();
+        let b = book.sleeve("b").unwrap();
+        assert_eq!(a.get_position(1).unwrap().quantity, 600.0);
+        assert_eq!(b.get_position(2).unwrap().quantity, 400.0);
+        assert!(a.get_position(2).is_none() && b.get_position(1).is_none());
+        assert!((b.cash - 20_000.0).abs() < 1e-6);
+        // A1 rose 10%, all of it in a's sleeve
+        assert!((a.returns - 0.1).abs() < 1e-9);
+        assert_eq!(b.returns, 0.0);
+        assert_eq!(results.transactions.len(), 2);
+
+        // The combined book's controls reject a's order, not b's, and a sees why
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(ControlMaxOrderSize::notional(50_000.0)));
+        let (book, results, rejections) = run(Some(controls));
+        assert_eq!(rejections.len(), 1);
+        assert!(rejections[0].contains("50000"), "{}", rejections[0]);
+        assert_eq!(results.transactions.len(), 1);
+        assert!(book.sleeve("a").unwrap().positions.is_empty());
+        assert_eq!(book.sleeve("b").unwrap().get_position(2).unwrap().quantity, 400.0);
+    }
+}
@@ -0,0 +1,11 @@
+//! Research tools for studying signals outside of a backtest
+
+pub mod dataset;
+pub mod event_study;
+pub mod factor_tearsheet;
+pub mod forward_returns;
+
+pub use dataset::{Dataset, DatasetExporter, DatasetRow};
+pub use event_study::{EventStudy, EventStudyResult, ExpectedReturn};
+pub use factor_tearsheet::{FactorAnalysis, FactorTearSheet, FactorValues, IcSummary};
+pub use forward_returns::{ForwardReturns, DEFAULT_PERIODS};
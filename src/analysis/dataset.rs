@@ -0,0 +1,340 @@
+//! Training datasets of pipeline factors labeled with forward returns
+//!
+//! [`DatasetExporter`] runs a pipeline over a range of sessions and pairs
+//! each asset's factor values on a date with its forward returns from that
+//! date, giving one tidy row per date and asset:
+//!
+//! | date | sid | factor ... | label_1d | label_5d | ... |
+//!
+//! Rows can be limited to the assets passing one of the pipeline's filters,
+//! and split by date into training and validation sets written to separate
+//! Parquet files. Missing values are written as nulls.
+//!
+//! ```ignore
+//! let labels = ForwardReturns::from_bars(&reader, &calendar, &assets, start, end, vec![1, 5, 21])?;
+//! let files = DatasetExporter::new()
+//!     .with_universe("liquid")
+//!     .with_split("train", ..cutoff)
+//!     .with_split("validation", cutoff..)
+//!     .export(&pipeline, &sessions, provider, &labels, Path::new("datasets/momentum"))?;
+//! ```
+
+use super::forward_returns::ForwardReturns;
+use crate::error::{Result, ZiplineError};
+use crate::pipeline::engine::{DataProvider, Pipeline, PipelineOutput};
+use crate::types::AssetId;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use polars::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One date and asset's features and labels
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetRow {
+    pub date: NaiveDate,
+    pub sid: AssetId,
+    /// Factor values, in the order of [`Dataset::features`]; NaN if missing
+    pub features: Vec<f64>,
+    /// Forward returns, in the order of [`Dataset::periods`]; NaN if unknown
+    pub labels: Vec<f64>,
+}
+
+/// Rows of features and labels, ordered by date then asset
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dataset {
+    features: Vec<String>,
+    periods: Vec<usize>,
+    rows: Vec<DatasetRow>,
+}
+
+impl Dataset {
+    /// Factor names, one per feature column
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Forward return periods in sessions, one per label column
+    pub fn periods(&self) -> &[usize] {
+        &self.periods
+    }
+
+    pub fn rows(&self) -> &[DatasetRow] {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Rows dated within `dates`
+    pub fn between(&self, dates: &impl RangeBounds<NaiveDate>) -> Self {
+        Self {
+            features: self.features.clone(),
+            periods: self.periods.clone(),
+            rows: self.rows.iter().filter(|row| dates.contains(&row.date)).cloned().collect(),
+        }
+    }
+
+    /// The rows as a data frame with `date`, `sid`, one column per factor and
+    /// `label_<n>d` for each period
+    pub fn to_frame(&self) -> Result<DataFrame> {
+        let nullable = |value: f64| value.is_finite().then_some(value);
+        let midnight = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc().timestamp_millis();
+
+        let mut columns = vec![
+            Series::new("date", self.rows.iter().map(|row| midnight(row.date)).collect::<Vec<_>>())
+                .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+                .map_err(|e| ZiplineError::DataError(format!("Failed to build dataset dates: {}", e)))?,
+            Series::new("sid", self.rows.iter().map(|row| row.sid).collect::<Vec<_>>()),
+        ];
+        for (i, feature) in self.features.iter().enumerate() {
+            let values: Vec<Option<f64>> = self.rows.iter().map(|row| nullable(row.features[i])).collect();
+            columns.push(Series::new(feature, values));
+        }
+        for (i, period) in self.periods.iter().enumerate() {
+            let values: Vec<Option<f64>> = self.rows.iter().map(|row| nullable(row.labels[i])).collect();
+            columns.push(Series::new(&format!("label_{}d", period), values));
+        }
+
+        DataFrame::new(columns)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to build dataset frame: {}", e)))
+    }
+
+    /// Write the rows to a Parquet file
+    pub fn write_parquet(&self, path: &Path) -> Result<()> {
+        let mut frame = self.to_frame()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        ParquetWriter::new(std::fs::File::create(path)?)
+            .finish(&mut frame)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to write Parquet: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Dates from a start bound to an end bound
+type DateRange = (Bound<NaiveDate>, Bound<NaiveDate>);
+
+/// Builds labeled datasets from pipeline outputs
+#[derive(Debug, Clone)]
+pub struct DatasetExporter {
+    features: Option<Vec<String>>,
+    universe: Option<String>,
+    splits: Vec<(String, DateRange)>,
+    keep_unlabeled: bool,
+}
+
+impl DatasetExporter {
+    /// Every factor as a feature, every asset, and no splits
+    pub fn new() -> Self {
+        Self {
+            features: None,
+            universe: None,
+            splits: Vec::new(),
+            keep_unlabeled: false,
+        }
+    }
+
+    /// Export only these factors, in this order
+    pub fn with_features(mut self, features: Vec<String>) -> Self {
+        self.features = Some(features);
+        self
+    }
+
+    /// Keep only assets passing the pipeline filter `filter` on each date
+    pub fn with_universe(mut self, filter: &str) -> Self {
+        self.universe = Some(filter.to_string());
+        self
+    }
+
+    /// Write rows dated within `dates` to `<name>.parquet`
+    ///
+    /// Without splits every row goes to `dataset.parquet`. Rows outside all
+    /// splits are not written.
+    pub fn with_split(mut self, name: &str, dates: impl RangeBounds<NaiveDate>) -> Self {
+        let bounds = (dates.start_bound().cloned(), dates.end_bound().cloned());
+        self.splits.push((name.to_string(), bounds));
+        self
+    }
+
+    /// Keep rows with no known forward return, such as the most recent dates
+    pub fn with_unlabeled_rows(mut self) -> Self {
+        self.keep_unlabeled = true;
+        self
+    }
+
+    /// Pair each asset's factor values in `outputs` with its forward returns
+    pub fn build(&self, outputs: &[PipelineOutput], labels: &ForwardReturns) -> Result<Dataset> {
+        let features = match &self.features {
+            Some(features) => features.clone(),
+            None => outputs
+                .iter()
+                .flat_map(|output| output.factors.keys().cloned())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        };
+        let unknown = vec![f64::NAN; labels.periods().len()];
+
+        let mut rows = Vec::new();
+        for output in outputs {
+            let date = output.timestamp.date_naive();
+            let universe = match &self.universe {
+                Some(filter) => Some(output.filters.get(filter).ok_or_else(|| {
+                    ZiplineError::InvalidConfiguration(format!(
+                        "Pipeline output on {} has no filter named {}",
+                        date, filter
+                    ))
+                })?),
+                None => None,
+            };
+
+            let sids: BTreeSet<AssetId> = features
+                .iter()
+                .filter_map(|feature| output.factors.get(feature))
+                .flat_map(|values| values.keys().copied())
+                .filter(|sid| universe.is_none_or(|passed| passed.get(sid).copied().unwrap_or(false)))
+                .collect();
+            let returns = labels.on(date);
+
+            for sid in sids {
+                let label = returns.and_then(|returns| returns.get(&sid)).unwrap_or(&unknown);
+                if !self.keep_unlabeled && label.iter().all(|r| r.is_nan()) {
+                    continue;
+                }
+                let values = features
+                    .iter()
+                    .map(|feature| {
+                        output
+                            .factors
+                            .get(feature)
+                            .and_then(|values| values.get(&sid))
+                            .copied()
+                            .unwrap_or(f64::NAN)
+                    })
+                    .collect();
+                rows.push(DatasetRow {
+                    date,
+                    sid,
+                    features: values,
+                    labels: label.clone(),
+                });
+            }
+        }
+        rows.sort_by_key(|row| (row.date, row.sid));
+
+        Ok(Dataset {
+            features,
+            periods: labels.periods().to_vec(),
+            rows,
+        })
+    }
+
+    /// Write `dataset`, or each split of it, under `dir`
+    ///
+    /// Returns the files written.
+    pub fn write(&self, dataset: &Dataset, dir: &Path) -> Result<Vec<PathBuf>> {
+        if self.splits.is_empty() {
+            let path = dir.join("dataset.parquet");
+            dataset.write_parquet(&path)?;
+            return Ok(vec![path]);
+        }
+        let mut files = Vec::new();
+        for (name, dates) in &self.splits {
+            let path = dir.join(format!("{}.parquet", name));
+            dataset.between(dates).write_parquet(&path)?;
+            files.push(path);
+        }
+        Ok(files)
+    }
+
+    /// Run `pipeline` on every session, label the outputs and write them
+    /// under `dir`
+    ///
+    /// `provider` supplies the point-in-time data for each session.
+    pub fn export<F>(
+        &self,
+        pipeline: &Pipeline,
+        sessions: &[DateTime<Utc>],
+        mut provider: F,
+        labels: &ForwardReturns,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>>
+    where
+        F: FnMut(DateTime<Utc>) -> Result<Arc<dyn DataProvider>>,
+    {
+        let mut outputs = BTreeMap::new();
+        for &session in sessions {
+            outputs.insert(session, pipeline.run(session, provider(session)?)?);
+        }
+        let outputs: Vec<PipelineOutput> = outputs.into_values().collect();
+        self.write(&self.build(&outputs, labels)?, dir)
+    }
+}
+
+impl Default for DatasetExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use hashbrown::HashMap;
+
+    #[test]
+    fn test_build_and_split_dataset() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let liquid = HashMap::from([(1, true), (2, true), (3, false)]);
+        let outputs: Vec<PipelineOutput> = [2, 3, 4]
+            .into_iter()
+            .map(|d| PipelineOutput {
+                timestamp: Utc.with_ymd_and_hms(2024, 1, d, 21, 0, 0).unwrap(),
+                factors: HashMap::from([
+                    ("momentum".to_string(), HashMap::from([(1, d as f64), (2, -(d as f64)), (3, 0.0)])),
+                    ("value".to_string(), HashMap::from([(1, 0.5)])),
+                ]),
+                filters: HashMap::from([("liquid".to_string(), liquid.clone())]),
+                classifiers: HashMap::new(),
+            })
+            .collect();
+        let mut labels = ForwardReturns::new(vec![1, 5]);
+        for d in [2, 3] {
+            labels.insert(day(d), 1, vec![0.01, 0.03]).unwrap();
+            labels.insert(day(d), 2, vec![-0.02, f64::NAN]).unwrap();
+        }
+
+        let exporter = DatasetExporter::new()
+            .with_universe("liquid")
+            .with_split("train", ..day(3))
+            .with_split("validation", day(3)..);
+        let dataset = exporter.build(&outputs, &labels).unwrap();
+        assert_eq!(dataset.features(), ["momentum", "value"]);
+        // Asset 3 is outside the universe and the 4th has no labels yet
+        assert_eq!(dataset.len(), 4);
+        assert_eq!(dataset.rows()[1].sid, 2);
+        assert_eq!(dataset.rows()[1].features[0], -2.0);
+        assert!(dataset.rows()[1].features[1].is_nan());
+        assert_eq!(DatasetExporter::new().with_unlabeled_rows().build(&outputs, &labels).unwrap().len(), 9);
+        assert!(DatasetExporter::new().with_universe("missing").build(&outputs, &labels).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let files = exporter.write(&dataset, dir.path()).unwrap();
+        let expected = vec![dir.path().join("train.parquet"), dir.path().join("validation.parquet")];
+        assert_eq!(files, expected);
+        let validation = ParquetReader::new(std::fs::File::open(&files[1]).unwrap()).finish().unwrap();
+        let columns: Vec<&str> = validation.get_column_names();
+        assert_eq!(columns, ["date", "sid", "momentum", "value", "label_1d", "label_5d"]);
+        assert_eq!(validation.height(), 2);
+        assert_eq!(validation.column("label_5d").unwrap().null_count(), 1);
+    }
+}
@@ -0,0 +1,518 @@
+//! Factor analysis in the manner of alphalens
+//!
+//! Given a factor's value for each asset on each date and the assets'
+//! forward returns from those dates, [`FactorAnalysis`] measures how well the
+//! factor predicts returns:
+//!
+//! - the information coefficient (IC), the rank correlation between factor
+//!   and forward return on every date, with its mean, spread and t-statistic
+//! - the mean forward return of each factor quantile, and the spread between
+//!   the top and bottom quantiles
+//! - quantile turnover and the factor's rank autocorrelation, showing how
+//!   much trading following the factor takes
+//! - the decay curve: the IC of the factor against returns starting 0, 1, 2,
+//!   ... dates later, showing how quickly its information goes stale
+//!
+//! ```ignore
+//! let factor = FactorValues::from_pipeline(&outputs, "momentum");
+//! let returns = ForwardReturns::from_bars(&reader, &calendar, &assets, start, end, vec![1, 5, 21])?;
+//! let sheet = FactorAnalysis::new().with_quantiles(5).tearsheet(&factor, &returns)?;
+//! println!("{}", sheet);
+//! ```
+
+use super::forward_returns::ForwardReturns;
+use crate::error::{Result, ZiplineError};
+use crate::pipeline::engine::PipelineOutput;
+use crate::types::AssetId;
+use chrono::NaiveDate;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Rows of a CSV file with `date` and `sid` columns, and the other columns' headers
+pub(super) type CsvTable = (Vec<String>, Vec<(NaiveDate, AssetId, Vec<f64>)>);
+
+/// Read a CSV file of `date`, `sid` and numeric columns; empty cells are NaN
+pub(super) fn read_csv(path: &Path) -> Result<CsvTable> {
+    let csv_error = |e: csv::Error| {
+        ZiplineError::DataError(format!("Failed to read {}: {}", path.display(), e))
+    };
+    let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| ZiplineError::InvalidData(format!("{} has no {} column", path.display(), name)))
+    };
+    let (date_col, sid_col) = (column("date")?, column("sid")?);
+    let value_cols: Vec<usize> = (0..headers.len()).filter(|c| *c != date_col && *c != sid_col).collect();
+
+    let mut rows = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let field = |col: usize| record.get(col).unwrap_or("").trim();
+        let bad = |name: &str, value: &str| {
+            ZiplineError::InvalidData(format!(
+                "Bad {} '{}' on line {} of {}",
+                name,
+                value,
+                line + 2,
+                path.display()
+            ))
+        };
+        let date = NaiveDate::parse_from_str(field(date_col), "%Y-%m-%d")
+            .map_err(|_| bad("date", field(date_col)))?;
+        let sid: AssetId = field(sid_col).parse().map_err(|_| bad("sid", field(sid_col)))?;
+        let values = value_cols
+            .iter()
+            .map(|col| match field(*col) {
+                "" => Ok(f64::NAN),
+                value => value.parse().map_err(|_| bad(&headers[*col], value)),
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        rows.push((date, sid, values));
+    }
+    Ok((value_cols.into_iter().map(|c| headers[c].clone()).collect(), rows))
+}
+
+/// A factor's value for each asset, by date
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FactorValues {
+    by_date: BTreeMap<NaiveDate, HashMap<AssetId, f64>>,
+}
+
+impl FactorValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an asset's factor value on a date; NaN values are ignored
+    pub fn insert(&mut self, date: NaiveDate, asset_id: AssetId, value: f64) {
+        if value.is_finite() {
+            self.by_date.entry(date).or_default().insert(asset_id, value);
+        }
+    }
+
+    /// Values of the factor `name` in a run of pipeline outputs
+    pub fn from_pipeline(outputs: &[PipelineOutput], name: &str) -> Self {
+        let mut values = Self::new();
+        for output in outputs {
+            let date = output.timestamp.date_naive();
+            for (asset_id, value) in output.factors.get(name).into_iter().flatten() {
+                values.insert(date, *asset_id, *value);
+            }
+        }
+        values
+    }
+
+    /// Read a CSV file with `date`, `sid` and `value` columns
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let (headers, rows) = read_csv(path)?;
+        let value_col = headers
+            .iter()
+            .position(|h| h == "value")
+            .ok_or_else(|| ZiplineError::InvalidData(format!("{} has no value column", path.display())))?;
+        let mut values = Self::new();
+        for (date, sid, row) in rows {
+            values.insert(date, sid, row[value_col]);
+        }
+        Ok(values)
+    }
+
+    /// Dates with values, oldest first
+    pub fn dates(&self) -> impl Iterator<Item = &NaiveDate> {
+        self.by_date.keys()
+    }
+
+    /// Every asset's value on `date`
+    pub fn on(&self, date: NaiveDate) -> Option<&HashMap<AssetId, f64>> {
+        self.by_date.get(&date)
+    }
+}
+
+/// Information coefficient statistics for one forward-return period
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IcSummary {
+    /// Period in sessions
+    pub period: usize,
+    pub mean: f64,
+    pub std: f64,
+    /// Mean over standard deviation
+    pub information_ratio: f64,
+    /// t-statistic of the mean
+    pub t_stat: f64,
+    /// Share of dates with a positive IC
+    pub hit_rate: f64,
+}
+
+/// Results of analysing a factor against forward returns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FactorTearSheet {
+    /// Forward-return periods in sessions
+    pub periods: Vec<usize>,
+    pub quantiles: usize,
+    /// Information coefficient on each date, one per period
+    pub ic: BTreeMap<NaiveDate, Vec<f64>>,
+    pub ic_summary: Vec<IcSummary>,
+    /// Mean forward return of each quantile (lowest factor values first), one
+    /// per period
+    pub quantile_returns: Vec<Vec<f64>>,
+    /// Top quantile's mean return less the bottom quantile's, per period
+    pub spread: Vec<f64>,
+    /// Mean share of each quantile's assets that were not in it on the
+    /// previous date
+    pub turnover: Vec<f64>,
+    /// Mean rank correlation of the factor between consecutive dates
+    pub rank_autocorrelation: f64,
+    /// Mean IC against the first period's returns from 0, 1, 2, ... dates later
+    pub decay: Vec<f64>,
+}
+
+/// Settings for a factor tear sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FactorAnalysis {
+    quantiles: usize,
+    decay_lags: usize,
+}
+
+impl Default for FactorAnalysis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FactorAnalysis {
+    /// Quintiles and a decay curve over ten dates
+    pub fn new() -> Self {
+        Self {
+            quantiles: 5,
+            decay_lags: 10,
+        }
+    }
+
+    /// Bucket assets into `quantiles` groups by factor value on each date
+    pub fn with_quantiles(mut self, quantiles: usize) -> Self {
+        self.quantiles = quantiles;
+        self
+    }
+
+    /// Extend the decay curve to returns starting `lags` dates later
+    pub fn with_decay_lags(mut self, lags: usize) -> Self {
+        self.decay_lags = lags;
+        self
+    }
+
+    /// Analyse `factor` against `returns`
+    ///
+    /// Dates with fewer assets than quantiles are left out of the quantile
+    /// statistics.
+    ///
+    /// # Errors
+    /// * `InvalidConfiguration` - If fewer than two quantiles are asked for
+    /// * `MissingData` - If no date has both factor values and returns
+    pub fn tearsheet(&self, factor: &FactorValues, returns: &ForwardReturns) -> Result<FactorTearSheet> {
+        if self.quantiles < 2 {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "A factor tear sheet needs at least 2 quantiles, got {}",
+                self.quantiles
+            )));
+        }
+        let periods = returns.periods().to_vec();
+        let return_dates: Vec<NaiveDate> = returns.dates().copied().collect();
+
+        let mut ic = BTreeMap::new();
+        let mut quantile_sums = vec![vec![(0.0, 0usize); periods.len()]; self.quantiles];
+        let mut turnover = vec![(0.0, 0usize); self.quantiles];
+        let mut autocorrelation = Vec::new();
+        let mut decay = vec![Vec::new(); self.decay_lags + 1];
+        let mut previous: Option<(HashMap<AssetId, f64>, HashMap<AssetId, usize>)> = None;
+
+        for (position, date) in return_dates.iter().enumerate() {
+            let Some(values) = factor.on(*date) else {
+                continue;
+            };
+            let forward = returns.on(*date).expect("date listed by the returns");
+
+            // Information coefficient of every period
+            let ics: Vec<f64> = (0..periods.len())
+                .map(|p| {
+                    let pairs: Vec<(f64, f64)> = values
+                        .iter()
+                        .filter_map(|(id, v)| forward.get(id).map(|r| (*v, r[p])))
+                        .filter(|(_, r)| r.is_finite())
+                        .collect();
+                    spearman(&pairs)
+                })
+                .collect();
+            if ics.iter().any(|v| v.is_finite()) {
+                ic.insert(*date, ics);
+            }
+
+            // The factor against first-period returns from later dates
+            if !periods.is_empty() {
+                for (lag, curve) in decay.iter_mut().enumerate() {
+                    let Some(later) = return_dates.get(position + lag).and_then(|d| returns.on(*d)) else {
+                        break;
+                    };
+                    let pairs: Vec<(f64, f64)> = values
+                        .iter()
+                        .filter_map(|(id, v)| later.get(id).map(|r| (*v, r[0])))
+                        .filter(|(_, r)| r.is_finite())
+                        .collect();
+                    let value = spearman(&pairs);
+                    if value.is_finite() {
+                        curve.push(value);
+                    }
+                }
+            }
+
+            // Quantile membership, returns and turnover
+            let buckets = quantiles(values, self.quantiles);
+            if buckets.is_empty() {
+                continue;
+            }
+            for (id, bucket) in &buckets {
+                for (p, sum) in quantile_sums[*bucket].iter_mut().enumerate() {
+                    if let Some(r) = forward.get(id).map(|r| r[p]).filter(|r| r.is_finite()) {
+                        sum.0 += r;
+                        sum.1 += 1;
+                    }
+                }
+            }
+            if let Some((previous_values, previous_buckets)) = &previous {
+                for (bucket, total) in turnover.iter_mut().enumerate() {
+                    let members: Vec<&AssetId> =
+                        buckets.iter().filter(|(_, b)| **b == bucket).map(|(id, _)| id).collect();
+                    if !members.is_empty() {
+                        let new = members
+                            .iter()
+                            .filter(|id| previous_buckets.get(**id) != Some(&bucket))
+                            .count();
+                        total.0 += new as f64 / members.len() as f64;
+                        total.1 += 1;
+                    }
+                }
+                let pairs: Vec<(f64, f64)> = values
+                    .iter()
+                    .filter_map(|(id, v)| previous_values.get(id).map(|p| (*p, *v)))
+                    .collect();
+                let value = spearman(&pairs);
+                if value.is_finite() {
+                    autocorrelation.push(value);
+                }
+            }
+            previous = Some((values.clone(), buckets));
+        }
+
+        if ic.is_empty() {
+            return Err(ZiplineError::MissingData(
+                "No date has both factor values and forward returns".to_string(),
+            ));
+        }
+
+        let ic_summary = periods
+            .iter()
+            .enumerate()
+            .map(|(p, period)| {
+                let series: Vec<f64> = ic.values().map(|v| v[p]).filter(|v| v.is_finite()).collect();
+                summarize(*period, &series)
+            })
+            .collect();
+        let quantile_returns: Vec<Vec<f64>> = quantile_sums
+            .iter()
+            .map(|sums| sums.iter().map(average).collect())
+            .collect();
+        let spread = (0..periods.len())
+            .map(|p| quantile_returns[self.quantiles - 1][p] - quantile_returns[0][p])
+            .collect();
+
+        Ok(FactorTearSheet {
+            periods,
+            quantiles: self.quantiles,
+            ic,
+            ic_summary,
+            quantile_returns,
+            spread,
+            turnover: turnover.iter().map(average).collect(),
+            rank_autocorrelation: mean(&autocorrelation),
+            decay: decay.iter().map(|curve| mean(curve)).collect(),
+        })
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        f64::NAN
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+/// Mean of an accumulated sum and count
+fn average((sum, n): &(f64, usize)) -> f64 {
+    if *n > 0 {
+        sum / *n as f64
+    } else {
+        f64::NAN
+    }
+}
+
+fn summarize(period: usize, series: &[f64]) -> IcSummary {
+    let n = series.len() as f64;
+    let mean = mean(series);
+    let std = if series.len() > 1 {
+        (series.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+    } else {
+        f64::NAN
+    };
+    let information_ratio = mean / std;
+    IcSummary {
+        period,
+        mean,
+        std,
+        information_ratio,
+        t_stat: information_ratio * n.sqrt(),
+        hit_rate: series.iter().filter(|v| **v > 0.0).count() as f64 / n.max(1.0),
+    }
+}
+
+/// Ranks starting at 1, ties sharing their average rank
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        order[start..end].iter().for_each(|i| ranks[*i] = rank);
+        start = end;
+    }
+    ranks
+}
+
+/// Spearman rank correlation, NaN with fewer than two pairs or no variation
+fn spearman(pairs: &[(f64, f64)]) -> f64 {
+    if pairs.len() < 2 {
+        return f64::NAN;
+    }
+    let x = ranks(&pairs.iter().map(|p| p.0).collect::<Vec<_>>());
+    let y = ranks(&pairs.iter().map(|p| p.1).collect::<Vec<_>>());
+    let (mx, my) = (mean(&x), mean(&y));
+    let covariance: f64 = x.iter().zip(&y).map(|(a, b)| (a - mx) * (b - my)).sum();
+    let vx: f64 = x.iter().map(|a| (a - mx).powi(2)).sum();
+    let vy: f64 = y.iter().map(|b| (b - my).powi(2)).sum();
+    if vx == 0.0 || vy == 0.0 {
+        f64::NAN
+    } else {
+        covariance / (vx * vy).sqrt()
+    }
+}
+
+/// Quantile of every asset by value, 0 holding the lowest values
+///
+/// Empty when there are fewer assets than quantiles.
+fn quantiles(values: &HashMap<AssetId, f64>, quantiles: usize) -> HashMap<AssetId, usize> {
+    if values.len() < quantiles {
+        return HashMap::new();
+    }
+    let mut sorted: Vec<(&AssetId, &f64)> = values.iter().collect();
+    sorted.sort_by(|a, b| a.1.total_cmp(b.1).then(a.0.cmp(b.0)));
+    let n = sorted.len();
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (id, _))| (*id, rank * quantiles / n))
+        .collect()
+}
+
+impl fmt::Display for FactorTearSheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header: String = self.periods.iter().map(|p| format!("{:>10}", format!("{}D", p))).collect();
+        writeln!(f, "Information coefficient ({} dates)", self.ic.len())?;
+        writeln!(f, "{:<18}{}", "", header)?;
+        type Field = fn(&IcSummary) -> f64;
+        let rows: [(&str, Field); 5] = [
+            ("Mean", |s| s.mean),
+            ("Std", |s| s.std),
+            ("IR", |s| s.information_ratio),
+            ("t-stat", |s| s.t_stat),
+            ("Hit rate", |s| s.hit_rate),
+        ];
+        for (name, field) in rows {
+            let cells: String = self.ic_summary.iter().map(|s| format!("{:>10.4}", field(s))).collect();
+            writeln!(f, "{:<18}{}", name, cells)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Mean forward return by quantile")?;
+        writeln!(f, "{:<18}{}", "", header)?;
+        for (q, returns) in self.quantile_returns.iter().enumerate() {
+            let cells: String = returns.iter().map(|r| format!("{:>9.3}%", r * 100.0)).collect();
+            writeln!(f, "{:<18}{}", format!("Q{}", q + 1), cells)?;
+        }
+        let cells: String = self.spread.iter().map(|r| format!("{:>9.3}%", r * 100.0)).collect();
+        writeln!(f, "{:<18}{}", "Top - bottom", cells)?;
+
+        writeln!(f)?;
+        let turnover: Vec<String> = self
+            .turnover
+            .iter()
+            .enumerate()
+            .map(|(q, t)| format!("Q{} {:.1}%", q + 1, t * 100.0))
+            .collect();
+        writeln!(f, "Turnover: {}", turnover.join(", "))?;
+        writeln!(f, "Rank autocorrelation: {:.4}", self.rank_autocorrelation)?;
+        let decay: Vec<String> = self.decay.iter().map(|ic| format!("{:.4}", ic)).collect();
+        writeln!(f, "IC decay by lag: {}", decay.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tearsheet_of_a_predictive_factor() {
+        // Ten assets over twenty dates; the factor is each asset's id and the
+        // return is proportional to it, except the order of two assets swaps
+        // every other date
+        let day = |i: i64| NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(i);
+        let mut factor = FactorValues::new();
+        let mut returns = ForwardReturns::new(vec![1, 5]);
+        for i in 0..20 {
+            for id in 1..=10u64 {
+                let value = if i % 2 == 1 && id <= 2 { 3.0 - id as f64 } else { id as f64 };
+                factor.insert(day(i), id, value);
+                returns.insert(day(i), id, vec![id as f64 * 0.001, id as f64 * 0.005]).unwrap();
+            }
+        }
+
+        let sheet = FactorAnalysis::new().with_decay_lags(2).tearsheet(&factor, &returns).unwrap();
+        assert_eq!(sheet.ic.len(), 20);
+        assert_eq!(sheet.ic[&day(0)], vec![1.0, 1.0]);
+        assert!(sheet.ic_summary[0].mean > 0.98 && sheet.ic_summary[0].hit_rate == 1.0);
+        // Quintiles hold two assets each: Q1 earns ids 1 and 2, Q5 ids 9 and 10
+        assert!((sheet.quantile_returns[0][0] - 0.0015).abs() < 1e-12);
+        assert!((sheet.spread[1] - 0.04).abs() < 1e-12);
+        // Swapping within the bottom quintile changes no membership
+        assert!(sheet.turnover.iter().all(|t| *t == 0.0));
+        assert!(sheet.rank_autocorrelation < 1.0 && sheet.rank_autocorrelation > 0.9);
+        assert_eq!(sheet.decay.len(), 3);
+        assert!(sheet.to_string().contains("Top - bottom"));
+
+        assert!(FactorAnalysis::new().with_quantiles(1).tearsheet(&factor, &returns).is_err());
+        assert!(FactorAnalysis::new().tearsheet(&FactorValues::new(), &returns).is_err());
+    }
+}
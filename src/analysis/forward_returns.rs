@@ -0,0 +1,228 @@
+//! Forward returns for labeling factors and training data
+//!
+//! The return for period `n` on a session runs from that session's close to
+//! the close `n` trading sessions later, counted on the trading calendar so
+//! weekends and holidays never shorten a period. Bars are keyed by their UTC
+//! date, as the bar readers label sessions.
+//!
+//! An asset delisted before the end of a period realizes its return at its
+//! last close while alive, as a held position would be closed out. A period
+//! running past the end of the data, or ending on a session the asset has no
+//! bar for, is NaN.
+//!
+//! ```ignore
+//! let periods = DEFAULT_PERIODS.to_vec();
+//! let returns = ForwardReturns::from_bars(&reader, &calendar, &assets, start, end, periods)?;
+//! let sheet = FactorAnalysis::new().tearsheet(&factor, &returns)?;
+//! ```
+
+use super::factor_tearsheet::read_csv;
+use crate::asset::Asset;
+use crate::calendar::TradingCalendar;
+use crate::data::bar_reader::BarReader;
+use crate::error::{Result, ZiplineError};
+use crate::types::AssetId;
+use chrono::{Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use hashbrown::HashMap;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Periods of one week-day, one week and one month of sessions
+pub const DEFAULT_PERIODS: [usize; 3] = [1, 5, 21];
+
+/// Returns over the next few sessions for each asset, by date
+///
+/// The return for period `n` on a date runs from that date's close to the
+/// close `n` sessions later.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForwardReturns {
+    periods: Vec<usize>,
+    by_date: BTreeMap<NaiveDate, HashMap<AssetId, Vec<f64>>>,
+}
+
+impl ForwardReturns {
+    /// Returns over each of `periods` sessions
+    pub fn new(periods: Vec<usize>) -> Self {
+        Self {
+            periods,
+            by_date: BTreeMap::new(),
+        }
+    }
+
+    /// Compute each asset's forward returns on every session from `start` to
+    /// `end` from the closes in `reader`
+    ///
+    /// Sessions after `end` are read as far as the longest period reaches.
+    /// Assets the reader has no data for are skipped, as are sessions an asset
+    /// is not alive for or has no bar on.
+    pub fn from_bars(
+        reader: &dyn BarReader,
+        calendar: &dyn TradingCalendar,
+        assets: &[Asset],
+        start: NaiveDate,
+        end: NaiveDate,
+        periods: Vec<usize>,
+    ) -> Result<Self> {
+        if periods.is_empty() || periods.contains(&0) {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Forward return periods must be at least one session, got {:?}",
+                periods
+            )));
+        }
+        let mut returns = Self::new(periods);
+        let dates = calendar.trading_days_between(start, end);
+        let Some(&last) = dates.last() else {
+            return Ok(returns);
+        };
+
+        let horizon = returns.periods.iter().copied().max().unwrap_or(0);
+        let mut sessions = dates.clone();
+        let mut next = last;
+        for _ in 0..horizon {
+            next = calendar.next_trading_day(next)?;
+            sessions.push(next);
+        }
+        let midnight = |date: NaiveDate| Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN));
+        let (from, to) = (midnight(dates[0]), midnight(next + Duration::days(1)) - Duration::nanoseconds(1));
+
+        for asset in assets {
+            let bars = match reader.get_bars(asset, from, to) {
+                Ok(bars) => bars,
+                Err(ZiplineError::AssetNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            let closes: BTreeMap<NaiveDate, f64> = bars
+                .iter()
+                .filter(|bar| bar.close.is_finite() && bar.close > 0.0)
+                .map(|bar| (bar.dt.date_naive(), bar.close))
+                .collect();
+
+            for (i, date) in dates.iter().enumerate() {
+                if !asset.is_alive_for_session(*date) {
+                    continue;
+                }
+                let Some(&entry) = closes.get(date) else {
+                    continue;
+                };
+                let row = returns
+                    .periods
+                    .iter()
+                    .map(|n| {
+                        let target = sessions[i + n];
+                        let exit = if asset.is_alive_for_session(target) {
+                            closes.get(&target).copied()
+                        } else {
+                            // Delisted within the period: out at the last close
+                            closes.range(*date..target).next_back().map(|(_, close)| *close)
+                        };
+                        exit.map_or(f64::NAN, |exit| exit / entry - 1.0)
+                    })
+                    .collect();
+                returns.by_date.entry(*date).or_default().insert(asset.id, row);
+            }
+        }
+        Ok(returns)
+    }
+
+    /// Set an asset's forward returns on a date, one per period (NaN if unknown)
+    pub fn insert(&mut self, date: NaiveDate, asset_id: AssetId, returns: Vec<f64>) -> Result<()> {
+        if returns.len() != self.periods.len() {
+            return Err(ZiplineError::InvalidData(format!(
+                "Expected {} forward returns for asset {} on {}, got {}",
+                self.periods.len(),
+                asset_id,
+                date,
+                returns.len()
+            )));
+        }
+        self.by_date.entry(date).or_default().insert(asset_id, returns);
+        Ok(())
+    }
+
+    /// Read a CSV file with `date` and `sid` columns and one column per
+    /// period, headed by its length in sessions such as `1d`, `5d`, `21d`
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let (headers, rows) = read_csv(path)?;
+        let periods = headers
+            .iter()
+            .map(|h| {
+                h.trim_end_matches('d').parse::<usize>().map_err(|_| {
+                    let message = format!("{}: '{}' is not a period such as 5d", path.display(), h);
+                    ZiplineError::InvalidData(message)
+                })
+            })
+            .collect::<Result<Vec<usize>>>()?;
+        let mut returns = Self::new(periods);
+        for (date, sid, row) in rows {
+            returns.insert(date, sid, row)?;
+        }
+        Ok(returns)
+    }
+
+    /// Period lengths in sessions
+    pub fn periods(&self) -> &[usize] {
+        &self.periods
+    }
+
+    /// Dates with returns, oldest first
+    pub fn dates(&self) -> impl Iterator<Item = &NaiveDate> {
+        self.by_date.keys()
+    }
+
+    /// Every asset's forward returns on `date`, one per period
+    pub fn on(&self, date: NaiveDate) -> Option<&HashMap<AssetId, Vec<f64>>> {
+        self.by_date.get(&date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+    use crate::calendar::NYSECalendar;
+    use crate::data::bar_reader::{Bar, DailyBarReader};
+
+    #[test]
+    fn test_from_bars_calendar_and_delisting() {
+        let calendar = NYSECalendar::new();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let listed = day(2);
+        let stock = Asset::new(1, "AAA".to_string(), "NYSE".to_string(), AssetType::Equity, listed);
+        let delisted = Asset::new(2, "BBB".to_string(), "NYSE".to_string(), AssetType::Equity, listed)
+            .with_end_date(day(16));
+        let missing = Asset::new(3, "CCC".to_string(), "NYSE".to_string(), AssetType::Equity, listed);
+
+        let mut reader = DailyBarReader::new();
+        let sessions = calendar.trading_days_between(day(2), day(31));
+        for (i, session) in sessions.iter().enumerate() {
+            let dt = Utc.from_utc_datetime(&session.and_hms_opt(21, 0, 0).unwrap());
+            let close = 100.0 + i as f64;
+            reader.load_from_memory(1, vec![Bar::new(close, close, close, close, 1000.0, dt)]).unwrap();
+            if *session <= day(16) {
+                let close = 50.0 + i as f64;
+                reader.load_from_memory(2, vec![Bar::new(close, close, close, close, 1000.0, dt)]).unwrap();
+            }
+        }
+
+        let assets = [stock, delisted, missing];
+        let returns = ForwardReturns::from_bars(&reader, &calendar, &assets, day(12), day(16), vec![1, 5]);
+        let returns = returns.unwrap();
+        assert_eq!(returns.dates().copied().collect::<Vec<_>>(), vec![day(12), day(16)]);
+
+        // Friday the 12th (session 8) is one session from Tuesday the 16th,
+        // past the weekend and Martin Luther King Day
+        let friday = returns.on(day(12)).unwrap();
+        assert!((friday[&1][0] - (109.0 / 108.0 - 1.0)).abs() < 1e-12);
+        assert!((friday[&1][1] - (113.0 / 108.0 - 1.0)).abs() < 1e-12);
+        // Delisted after the 16th: the 5-session return stops there
+        assert!((friday[&2][1] - (59.0 / 58.0 - 1.0)).abs() < 1e-12);
+        assert!(!friday.contains_key(&3));
+        assert_eq!(returns.on(day(16)).unwrap()[&2], vec![0.0, 0.0]);
+
+        // Data ending before a period does not count as a delisting
+        let late = ForwardReturns::from_bars(&reader, &calendar, &assets[..1], day(31), day(31), vec![1]);
+        let late = late.unwrap();
+        assert!(late.on(day(31)).unwrap()[&1][0].is_nan());
+        assert!(ForwardReturns::from_bars(&reader, &calendar, &assets, day(12), day(16), vec![0]).is_err());
+    }
+}
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Type of asset
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum AssetType {
     /// Common stock
     Equity,
@@ -94,7 +94,9 @@ impl Asset {
     ///
     /// An asset is alive if:
     /// - The session date is on or after the start_date
-    /// - The session date is before the end_date (if set)
+    /// - The session date is on or before the end_date (if set)
+    /// - The session date is before the auto_close_date (if set), the date
+    ///   the asset is delisted and positions in it are closed
     pub fn is_alive_for_session(&self, dt: NaiveDate) -> bool {
         // Check if session is after start date
         if dt < self.start_date {
@@ -108,6 +110,13 @@ impl Asset {
             }
         }
 
+        // Check if the asset has been delisted
+        if let Some(auto_close_date) = self.auto_close_date {
+            if dt >= auto_close_date {
+                return false;
+            }
+        }
+
         true
     }
 
@@ -228,6 +237,11 @@ mod tests {
 
         // Should not be alive after end date
         assert!(!delisted_asset.is_alive_for_session(NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()));
+
+        // Should not be alive from the auto close date
+        let closed_asset = delisted_asset.with_auto_close_date(NaiveDate::from_ymd_opt(2020, 6, 1).unwrap());
+        assert!(closed_asset.is_alive_for_session(NaiveDate::from_ymd_opt(2020, 5, 29).unwrap()));
+        assert!(!closed_asset.is_alive_for_session(NaiveDate::from_ymd_opt(2020, 6, 1).unwrap()));
     }
 
     #[test]
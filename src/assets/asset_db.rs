@@ -34,6 +34,28 @@ impl AssetMetadata {
     }
 }
 
+/// A symbol held by an asset over a range of dates
+///
+/// Symbols are not permanent: companies rename (FB became META on
+/// 2022-06-09) and tickers are reused by unrelated companies after a
+/// delisting. Each row says which sid held `symbol` from `start_date` through
+/// `end_date`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolMapping {
+    pub sid: u64,
+    pub symbol: String,
+    pub start_date: NaiveDate,
+    /// Last date the symbol was held (inclusive); None while still held
+    pub end_date: Option<NaiveDate>,
+}
+
+impl SymbolMapping {
+    /// Whether the mapping is in effect on `date`
+    pub fn is_active(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && self.end_date.is_none_or(|end| date <= end)
+    }
+}
+
 /// Asset database with SQLite backend
 pub struct AssetDB {
     conn: Connection,
@@ -88,10 +110,36 @@ impl AssetDB {
             [],
         ).map_err(|e| ZiplineError::DataError(format!("Failed to create exchange index: {}", e)))?;
 
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS symbol_mappings (
+                sid INTEGER NOT NULL,
+                symbol TEXT NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT
+            )",
+            [],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to create symbol_mappings table: {}", e)))?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_mapping_symbol ON symbol_mappings(symbol)",
+            [],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to create mapping symbol index: {}", e)))?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_mapping_sid ON symbol_mappings(sid)",
+            [],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to create mapping sid index: {}", e)))?;
+
         Ok(())
     }
 
     /// Insert a new asset
+    ///
+    /// The asset's symbol is mapped to it from its start date (or first
+    /// traded date) through its end date. Use [`record_symbol_change`] for
+    /// later renames.
+    ///
+    /// [`record_symbol_change`]: AssetDB::record_symbol_change
     pub fn insert_asset(&mut self, asset: &AssetMetadata) -> Result<u64> {
         let asset_type_int = asset.asset_type as i32;
 
@@ -112,9 +160,173 @@ impl AssetDB {
             ],
         ).map_err(|e| ZiplineError::DataError(format!("Failed to insert asset: {}", e)))?;
 
+        let start_date = asset
+            .start_date
+            .or(asset.first_traded)
+            .unwrap_or_else(|| NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
+        self.insert_symbol_mapping(&SymbolMapping {
+            sid: asset.id,
+            symbol: asset.symbol.clone(),
+            start_date,
+            end_date: asset.end_date,
+        })?;
+
         Ok(asset.id)
     }
 
+    /// Insert a symbol mapping as-is
+    ///
+    /// Symbols are stored upper-cased so lookups are case-insensitive.
+    pub fn insert_symbol_mapping(&mut self, mapping: &SymbolMapping) -> Result<()> {
+        if let Some(end) = mapping.end_date {
+            if end < mapping.start_date {
+                return Err(ZiplineError::InvalidData(format!(
+                    "Symbol mapping {} -> {} ends ({}) before it starts ({})",
+                    mapping.symbol, mapping.sid, end, mapping.start_date
+                )));
+            }
+        }
+
+        self.conn.execute(
+            "INSERT INTO symbol_mappings (sid, symbol, start_date, end_date) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                mapping.sid as i64,
+                mapping.symbol.to_uppercase(),
+                mapping.start_date.to_string(),
+                mapping.end_date.map(|d| d.to_string()),
+            ],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to insert symbol mapping: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Rename an asset's ticker, effective from `effective_date`
+    ///
+    /// The asset's open mapping is closed the day before `effective_date`, a
+    /// new mapping is opened for `new_symbol`, and the asset row takes the new
+    /// symbol. Lookups of the old symbol as of earlier dates still resolve to
+    /// this asset.
+    pub fn record_symbol_change(
+        &mut self,
+        sid: u64,
+        new_symbol: &str,
+        effective_date: NaiveDate,
+    ) -> Result<()> {
+        let current = self
+            .symbol_history(sid)?
+            .into_iter()
+            .find(|m| m.end_date.is_none())
+            .ok_or(ZiplineError::AssetNotFound(sid))?;
+        if effective_date <= current.start_date {
+            return Err(ZiplineError::InvalidData(format!(
+                "Symbol change for sid {} on {} precedes current symbol {} (held since {})",
+                sid, effective_date, current.symbol, current.start_date
+            )));
+        }
+
+        let new_symbol = new_symbol.to_uppercase();
+        let tx = self.conn.transaction()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to start transaction: {}", e)))?;
+        tx.execute(
+            "UPDATE symbol_mappings SET end_date = ?2 WHERE sid = ?1 AND end_date IS NULL",
+            params![sid as i64, effective_date.pred_opt().unwrap().to_string()],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to close symbol mapping: {}", e)))?;
+        tx.execute(
+            "INSERT INTO symbol_mappings (sid, symbol, start_date, end_date) VALUES (?1, ?2, ?3, NULL)",
+            params![sid as i64, &new_symbol, effective_date.to_string()],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to insert symbol mapping: {}", e)))?;
+        tx.execute(
+            "UPDATE assets SET symbol = ?2 WHERE id = ?1",
+            params![sid as i64, &new_symbol],
+        ).map_err(|e| ZiplineError::DataError(format!("Failed to update asset symbol: {}", e)))?;
+        tx.commit()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to commit symbol change: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Resolve a symbol to the asset holding it on `as_of`
+    ///
+    /// Without a date, the asset currently holding the symbol is returned.
+    /// Fails with `SymbolNotFound` if nobody held the symbol then, and with
+    /// `InvalidData` if overlapping mappings make the answer ambiguous.
+    pub fn lookup_symbol(&self, symbol: &str, as_of: Option<NaiveDate>) -> Result<AssetMetadata> {
+        let holders: Vec<u64> = self
+            .mappings_where("symbol = ?1", params![symbol.to_uppercase()])?
+            .into_iter()
+            .filter(|m| match as_of {
+                Some(date) => m.is_active(date),
+                None => m.end_date.is_none(),
+            })
+            .map(|m| m.sid)
+            .collect();
+
+        let sid = match holders.as_slice() {
+            [] => {
+                return Err(ZiplineError::SymbolNotFound {
+                    symbol: symbol.to_string(),
+                })
+            }
+            [first, rest @ ..] if rest.iter().any(|sid| sid != first) => {
+                return Err(ZiplineError::InvalidData(format!(
+                    "Symbol {} is ambiguous{}: held by sids {:?}",
+                    symbol,
+                    as_of.map(|d| format!(" on {}", d)).unwrap_or_default(),
+                    holders
+                )))
+            }
+            [first, ..] => *first,
+        };
+
+        self.get_asset(sid)?.ok_or(ZiplineError::AssetNotFound(sid))
+    }
+
+    /// Symbols held by an asset, oldest first
+    pub fn symbol_history(&self, sid: u64) -> Result<Vec<SymbolMapping>> {
+        self.mappings_where("sid = ?1", params![sid as i64])
+    }
+
+    /// Every symbol mapping in the database
+    pub fn get_all_symbol_mappings(&self) -> Result<Vec<SymbolMapping>> {
+        self.mappings_where("1 = 1", [])
+    }
+
+    fn mappings_where<P: rusqlite::Params>(&self, filter: &str, params: P) -> Result<Vec<SymbolMapping>> {
+        let query = format!(
+            "SELECT sid, symbol, start_date, end_date FROM symbol_mappings WHERE {} ORDER BY start_date, sid",
+            filter
+        );
+        let mut stmt = self.conn.prepare(&query)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to prepare query: {}", e)))?;
+
+        let parse = |s: String| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok();
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, i64>(0)? as u64,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .map_err(|e| ZiplineError::DataError(format!("Failed to query symbol mappings: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| ZiplineError::DataError(format!("Failed to collect symbol mappings: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(sid, symbol, start, end)| {
+                let start_date = parse(start.clone()).ok_or_else(|| {
+                    ZiplineError::InvalidData(format!("Bad start date {} for {}", start, symbol))
+                })?;
+                Ok(SymbolMapping {
+                    sid,
+                    symbol,
+                    start_date,
+                    end_date: end.and_then(parse),
+                })
+            })
+            .collect()
+    }
+
     /// Get asset by ID
     pub fn get_asset(&self, asset_id: u64) -> Result<Option<AssetMetadata>> {
         let result = self.conn.query_row(
@@ -340,4 +552,76 @@ mod tests {
         let not_found2 = db.find_by_symbol("TEST", Some(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())).unwrap();
         assert_eq!(not_found2.len(), 0);
     }
+
+    fn equity(id: u64, symbol: &str, start: NaiveDate, end: Option<NaiveDate>) -> AssetMetadata {
+        AssetMetadata {
+            id,
+            symbol: symbol.to_string(),
+            exchange: "NASDAQ".to_string(),
+            asset_type: AssetType::Equity,
+            name: None,
+            start_date: Some(start),
+            end_date: end,
+            first_traded: None,
+            auto_close_date: None,
+            tick_size: None,
+        }
+    }
+
+    #[test]
+    fn test_symbol_change_resolves_by_date() {
+        let mut db = AssetDB::new_in_memory().unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+
+        db.insert_asset(&equity(1, "FB", d(2012, 5, 18), None)).unwrap();
+        db.record_symbol_change(1, "META", d(2022, 6, 9)).unwrap();
+
+        // The old ticker resolves before the rename, the new one after
+        assert_eq!(db.lookup_symbol("FB", Some(d(2020, 1, 2))).unwrap().id, 1);
+        assert_eq!(db.lookup_symbol("fb", Some(d(2022, 6, 8))).unwrap().id, 1);
+        assert!(db.lookup_symbol("FB", Some(d(2022, 6, 9))).is_err());
+        assert!(db.lookup_symbol("META", Some(d(2020, 1, 2))).is_err());
+        assert_eq!(db.lookup_symbol("META", None).unwrap().symbol, "META");
+        assert!(matches!(
+            db.lookup_symbol("FB", None),
+            Err(ZiplineError::SymbolNotFound { .. })
+        ));
+
+        let history = db.symbol_history(1).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].end_date, Some(d(2022, 6, 8)));
+        assert_eq!(history[1].symbol, "META");
+
+        // A rename cannot predate the current symbol
+        assert!(db.record_symbol_change(1, "MVRS", d(2021, 1, 4)).is_err());
+        assert!(db.record_symbol_change(99, "X", d(2021, 1, 4)).is_err());
+    }
+
+    #[test]
+    fn test_ticker_reuse_disambiguated_by_date() {
+        let mut db = AssetDB::new_in_memory().unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+
+        // Two unrelated companies trading as "ABC" at different times
+        db.insert_asset(&equity(1, "ABC", d(2001, 1, 2), Some(d(2008, 9, 30)))).unwrap();
+        db.insert_asset(&equity(2, "ABC", d(2015, 3, 2), None)).unwrap();
+
+        assert_eq!(db.lookup_symbol("ABC", Some(d(2005, 6, 1))).unwrap().id, 1);
+        assert_eq!(db.lookup_symbol("ABC", Some(d(2016, 6, 1))).unwrap().id, 2);
+        assert_eq!(db.lookup_symbol("ABC", None).unwrap().id, 2);
+        assert!(db.lookup_symbol("ABC", Some(d(2010, 1, 4))).is_err());
+
+        // Overlapping mappings are reported rather than guessed
+        db.insert_symbol_mapping(&SymbolMapping {
+            sid: 3,
+            symbol: "ABC".to_string(),
+            start_date: d(2016, 1, 4),
+            end_date: Some(d(2016, 12, 30)),
+        })
+        .unwrap();
+        assert!(matches!(
+            db.lookup_symbol("ABC", Some(d(2016, 6, 1))),
+            Err(ZiplineError::InvalidData(_))
+        ));
+    }
 }
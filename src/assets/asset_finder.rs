@@ -5,10 +5,12 @@
 //! - Symbol history (ticker changes)
 //! - Batch lookups
 //! - SID-based retrieval
+//! - Asset lifetimes (which assets were tradable on which sessions)
 
 use crate::asset::{Asset, AssetType};
+use crate::assets::asset_db::AssetDB;
 use crate::error::{Result, ZiplineError};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
@@ -21,6 +23,46 @@ pub struct SymbolEntry {
     pub end_date: Option<DateTime<Utc>>,
 }
 
+/// Tradability of a set of assets over a set of sessions
+///
+/// `alive[i][j]` is true if `sids[j]` was listed and not yet delisted on
+/// `dates[i]`, as computed by [`AssetFinder::lifetimes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lifetimes {
+    /// Sessions (rows)
+    pub dates: Vec<NaiveDate>,
+    /// Asset SIDs (columns)
+    pub sids: Vec<u64>,
+    /// Tradability matrix, one row per date
+    pub alive: Vec<Vec<bool>>,
+}
+
+impl Lifetimes {
+    /// Whether `sid` was tradable on `date`; false if either is not in the matrix
+    pub fn is_alive(&self, date: NaiveDate, sid: u64) -> bool {
+        let row = self.dates.iter().position(|d| *d == date);
+        let col = self.sids.iter().position(|s| *s == sid);
+        match (row, col) {
+            (Some(row), Some(col)) => self.alive[row][col],
+            _ => false,
+        }
+    }
+
+    /// SIDs tradable on `date`
+    pub fn alive_on(&self, date: NaiveDate) -> Vec<u64> {
+        match self.dates.iter().position(|d| *d == date) {
+            Some(row) => self
+                .sids
+                .iter()
+                .zip(&self.alive[row])
+                .filter(|(_, alive)| **alive)
+                .map(|(sid, _)| *sid)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
 /// AssetFinder - Central asset lookup and management
 ///
 /// Provides fast lookups for:
@@ -53,9 +95,74 @@ impl AssetFinder {
         }
     }
 
+    /// Build a finder from an asset database, including its symbol history
+    ///
+    /// Every symbol mapping in the database becomes a symbol entry, so renamed
+    /// assets resolve under their old ticker before the change and reused
+    /// tickers resolve to whichever company held them on the lookup date.
+    pub fn from_asset_db(db: &AssetDB) -> Result<Self> {
+        let finder = Self::new();
+        let metadata = db.get_all_assets()?;
+        let max_sid = metadata.iter().map(|m| m.id).max();
+        {
+            let mut assets = finder.assets.write().unwrap();
+            for meta in &metadata {
+                assets.insert(meta.id, meta.to_asset());
+            }
+        }
+        for mapping in db.get_all_symbol_mappings()? {
+            finder.add_symbol_mapping(
+                mapping.sid,
+                &mapping.symbol,
+                mapping.start_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+                mapping
+                    .end_date
+                    .map(|d| d.and_hms_opt(23, 59, 59).unwrap().and_utc()),
+            );
+        }
+        if let Some(max_sid) = max_sid {
+            *finder.next_sid.write().unwrap() = max_sid + 1;
+        }
+        Ok(finder)
+    }
+
+    /// Map an additional symbol to an existing SID over a date range
+    ///
+    /// Used for ticker changes, where one asset is known by different symbols
+    /// over its life.
+    pub fn add_symbol_mapping(
+        &self,
+        sid: u64,
+        symbol: &str,
+        start_date: DateTime<Utc>,
+        end_date: Option<DateTime<Utc>>,
+    ) {
+        let symbol = symbol.to_uppercase();
+        let mut symbol_index = self.symbol_index.write().unwrap();
+        let entries = symbol_index.entry(symbol.clone()).or_default();
+        entries.push(SymbolEntry {
+            sid,
+            symbol: symbol.clone(),
+            start_date,
+            end_date,
+        });
+        entries.sort_by_key(|e| e.start_date);
+
+        if end_date.is_none_or(|end| end > Utc::now()) {
+            self.current_symbols.write().unwrap().insert(symbol, sid);
+        }
+    }
+
     /// Insert an asset into the finder
+    ///
+    /// The symbol is mapped to the asset from its start date through its end
+    /// date.
     pub fn insert_asset(&self, asset: Asset) -> Result<()> {
-        self.insert_asset_with_dates(asset, Utc::now(), None)
+        let start_date = asset.start_date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let end_date = asset
+            .end_date
+            .map(|d| d.and_hms_opt(23, 59, 59).unwrap().and_utc());
+        self.insert_asset_with_dates(asset, start_date, end_date)
     }
 
     /// Insert an asset with specific start and end dates for symbol tracking
@@ -103,6 +210,10 @@ impl AssetFinder {
 
     /// Look up a symbol at a specific point in time
     ///
+    /// With an `as_of_date`, only assets alive on that session are considered,
+    /// so a symbol cannot resolve to an asset before it listed or after it
+    /// delisted. Without one, the current holder of the symbol is returned.
+    ///
     /// # Arguments
     /// * `symbol` - Symbol to look up (case-insensitive)
     /// * `as_of_date` - Point in time for lookup (None = current)
@@ -123,10 +234,17 @@ impl AssetFinder {
             })?;
 
         // Find entry valid at as_of_date
+        let assets = self.assets.read().unwrap();
+        let alive = |sid: u64| match as_of_date {
+            Some(dt) => assets
+                .get(&sid)
+                .is_some_and(|a| a.is_alive_for_session(dt.date_naive())),
+            None => true,
+        };
         let mut matching_entry: Option<&SymbolEntry> = None;
 
         for entry in entries {
-            if entry.start_date <= lookup_date {
+            if entry.start_date <= lookup_date && alive(entry.sid) {
                 if let Some(end) = entry.end_date {
                     if end >= lookup_date {
                         matching_entry = Some(entry);
@@ -144,7 +262,10 @@ impl AssetFinder {
         })?;
 
         // Retrieve asset
-        self.retrieve_asset(entry.sid)
+        assets
+            .get(&entry.sid)
+            .cloned()
+            .ok_or(ZiplineError::AssetNotFound(entry.sid))
     }
 
     /// Retrieve asset by SID
@@ -213,6 +334,49 @@ impl AssetFinder {
             .contains_key(&symbol_upper)
     }
 
+    /// Tradability matrix of `sids` over `dates`
+    ///
+    /// Pipelines and domains use this to exclude assets that had not yet
+    /// listed or had already delisted on each session, avoiding survivorship
+    /// bias. Fails if any SID is unknown.
+    pub fn lifetimes(&self, dates: &[NaiveDate], sids: &[u64]) -> Result<Lifetimes> {
+        let assets = self.assets.read().unwrap();
+        let lifetimes = sids
+            .iter()
+            .map(|sid| assets.get(sid).ok_or(ZiplineError::AssetNotFound(*sid)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let alive = dates
+            .iter()
+            .map(|date| {
+                lifetimes
+                    .iter()
+                    .map(|asset| asset.is_alive_for_session(*date))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Lifetimes {
+            dates: dates.to_vec(),
+            sids: sids.to_vec(),
+            alive,
+        })
+    }
+
+    /// All assets tradable on a session
+    pub fn alive_assets(&self, date: NaiveDate) -> Vec<Asset> {
+        let mut alive: Vec<Asset> = self
+            .assets
+            .read()
+            .unwrap()
+            .values()
+            .filter(|a| a.is_alive_for_session(date))
+            .cloned()
+            .collect();
+        alive.sort_by_key(|a| a.id);
+        alive
+    }
+
     /// Get total number of assets
     pub fn asset_count(&self) -> usize {
         self.assets.read().unwrap().len()
@@ -244,14 +408,18 @@ impl Default for AssetFinder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
-    fn create_test_asset(sid: u64, symbol: &str, _start: DateTime<Utc>) -> Asset {
+    fn create_test_asset(sid: u64, symbol: &str, start: DateTime<Utc>) -> Asset {
         Asset {
             id: sid,
             symbol: symbol.to_string(),
             asset_type: AssetType::Equity,
             exchange: "NYSE".to_string(),
             name: None,
+            start_date: start.date_naive(),
+            end_date: None,
+            auto_close_date: None,
         }
     }
 
@@ -357,6 +525,55 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_lookup_symbol_respects_lifetime() {
+        let finder = AssetFinder::new();
+        let listed = Utc.with_ymd_and_hms(2010, 3, 1, 0, 0, 0).unwrap();
+        let asset = create_test_asset(1, "XYZ", listed)
+            .with_end_date(NaiveDate::from_ymd_opt(2015, 6, 30).unwrap());
+        finder.insert_asset(asset).unwrap();
+
+        let during = Utc.with_ymd_and_hms(2012, 1, 3, 15, 0, 0).unwrap();
+        assert_eq!(finder.lookup_symbol("XYZ", Some(during)).unwrap().id, 1);
+
+        // Not yet listed, then delisted
+        let before = Utc.with_ymd_and_hms(2009, 12, 31, 15, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2016, 1, 4, 15, 0, 0).unwrap();
+        assert!(finder.lookup_symbol("XYZ", Some(before)).is_err());
+        assert!(finder.lookup_symbol("XYZ", Some(after)).is_err());
+    }
+
+    #[test]
+    fn test_lifetimes() {
+        let finder = AssetFinder::new();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+
+        let early = create_test_asset(1, "OLD", Utc.with_ymd_and_hms(2010, 1, 4, 0, 0, 0).unwrap())
+            .with_end_date(d(2020, 1, 10));
+        let ipo = create_test_asset(2, "IPO", Utc.with_ymd_and_hms(2020, 1, 8, 0, 0, 0).unwrap());
+        let closed = create_test_asset(3, "GONE", Utc.with_ymd_and_hms(2010, 1, 4, 0, 0, 0).unwrap())
+            .with_auto_close_date(d(2020, 1, 9));
+        finder.insert_assets(vec![early, ipo, closed]).unwrap();
+
+        let dates = [d(2020, 1, 7), d(2020, 1, 8), d(2020, 1, 9), d(2020, 1, 13)];
+        let lifetimes = finder.lifetimes(&dates, &[1, 2, 3]).unwrap();
+
+        assert_eq!(
+            lifetimes.alive,
+            vec![
+                vec![true, false, true],
+                vec![true, true, true],
+                vec![true, true, false],
+                vec![false, true, false],
+            ]
+        );
+        assert!(lifetimes.is_alive(d(2020, 1, 8), 2));
+        assert_eq!(lifetimes.alive_on(d(2020, 1, 13)), vec![2]);
+        assert_eq!(finder.alive_assets(d(2020, 1, 7)).len(), 2);
+
+        assert!(finder.lifetimes(&dates, &[1, 99]).is_err());
+    }
+
     #[test]
     fn test_get_assets_by_type() {
         let finder = AssetFinder::new();
@@ -429,4 +646,37 @@ mod tests {
         finder.insert_assets(assets).unwrap();
         assert_eq!(finder.asset_count(), 3);
     }
+
+    #[test]
+    fn test_from_asset_db_follows_symbol_changes() {
+        use crate::assets::asset_db::AssetMetadata;
+
+        let mut db = AssetDB::new_in_memory().unwrap();
+        db.insert_asset(&AssetMetadata {
+            id: 7,
+            symbol: "FB".to_string(),
+            exchange: "NASDAQ".to_string(),
+            asset_type: AssetType::Equity,
+            name: None,
+            start_date: NaiveDate::from_ymd_opt(2012, 5, 18),
+            end_date: None,
+            first_traded: None,
+            auto_close_date: None,
+            tick_size: None,
+        })
+        .unwrap();
+        db.record_symbol_change(7, "META", NaiveDate::from_ymd_opt(2022, 6, 9).unwrap())
+            .unwrap();
+
+        let finder = AssetFinder::from_asset_db(&db).unwrap();
+        let before = Utc.with_ymd_and_hms(2020, 1, 2, 15, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2023, 1, 3, 15, 0, 0).unwrap();
+
+        assert_eq!(finder.lookup_symbol("FB", Some(before)).unwrap().id, 7);
+        assert_eq!(finder.lookup_symbol("META", Some(after)).unwrap().id, 7);
+        assert!(finder.lookup_symbol("FB", Some(after)).is_err());
+        assert!(finder.lookup_symbol("META", Some(before)).is_err());
+        assert_eq!(finder.get_symbol_history("FB").len(), 1);
+        assert_eq!(finder.next_sid(), 8);
+    }
 }
@@ -3,5 +3,5 @@
 pub mod asset_db;
 pub mod asset_finder; // NEW: Symbol lookup and asset retrieval
 
-pub use asset_db::{AssetDB, AssetMetadata};
-pub use asset_finder::{AssetFinder, SymbolEntry};
+pub use asset_db::{AssetDB, AssetMetadata, SymbolMapping};
+pub use asset_finder::{AssetFinder, Lifetimes, SymbolEntry};
@@ -8,25 +8,81 @@
 //! # Run a backtest
 //! rusty-zipline run my_algo.rs --start 2020-01-01 --end 2023-12-31 --capital 100000
 //!
+//! # Select slippage and commission models
+//! rusty-zipline run my_algo.rs --slippage volume_share:volume_limit=0.025,price_impact=0.1 \
+//!     --commission per_share:cost_per_share=0.001
+//!
+//! # Run the backtest described in a config file
+//! rusty-zipline run --config backtest.toml
+//!
 //! # List bundles
 //! rusty-zipline bundle list
 //!
 //! # Ingest data
 //! rusty-zipline ingest quandl --show-progress
 //!
+//! # Purge cached data source downloads
+//! rusty-zipline cache purge --source yahoo --force
+//!
+//! # Analyse a factor against forward returns
+//! rusty-zipline factor-tearsheet factor.csv forward_returns.csv --quantiles 5
+//!
 //! # Show system info
 //! rusty-zipline info --detailed
 //! ```
+//!
+//! ## Backtest Files
+//!
+//! A config file can describe a whole run, so it can be versioned alongside
+//! the strategy. Options given on the command line take precedence; paths are
+//! relative to the file.
+//!
+//! ```toml
+//! algorithm = "target/release/libmomentum.so"
+//! bundle = "quandl"
+//! calendar = "NYSE"
+//! start = "2020-01-01"
+//! end = "2023-12-31"
+//! capital_base = 1000000.0
+//! benchmark = "SPY"
+//! seed = 42
+//! cash_rate = 0.04
+//! margin_rate = 0.07
+//! slippage = { model = "volume_share", volume_limit = 0.025, price_impact = 0.1 }
+//! commission = { model = "per_share", cost_per_share = 0.001 }
+//!
+//! [[controls]]
+//! model = "max_position_size"
+//! max_shares = 10000
+//! ```
 
+use chrono::NaiveDate;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use rusty_zipline::data::bundle::{BundleRegistry, BundleStats, CSVBundleReader};
+use rusty_zipline::analysis::{FactorAnalysis, FactorValues, ForwardReturns};
+use rusty_zipline::calendar::{get_calendar, TradingCalendar};
+use rusty_zipline::data::bundle::{BundleData, BundleRegistry, BundleStats, CSVBundleReader};
+use rusty_zipline::data::sources::DiskCache;
+use rusty_zipline::engine::{EngineConfig, Holdout, OrderLog, OrderLogFormat, SimulationEngine};
+use rusty_zipline::execution::{FinanceCommission, FinanceSlippage, SimulatedBroker};
 use rusty_zipline::error::{Result as ZiplineResult, ZiplineError};
+use rusty_zipline::finance::commission::CommissionModel;
+use rusty_zipline::finance::controls::{ControlManager, TradingControl};
+use rusty_zipline::finance::slippage::SlippageModel;
+use rusty_zipline::finance::{FixedRates, MarketStatsService, ModelRegistry, ModelSpec};
+use rusty_zipline::performance::{compact, BacktestResult, RunComparison, TearSheet};
+use rusty_zipline::algorithm::Algorithm;
+use rusty_zipline::plugin::PluginAlgorithm;
+#[cfg(feature = "wasm")]
+use rusty_zipline::plugin::wasm::WasmAlgorithm;
+use rusty_zipline::rng::SimulationRng;
+use rusty_zipline::serialization;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
 use std::time::Instant;
 
 /// rusty-zipline: High-performance algorithmic trading backtester
@@ -52,9 +108,10 @@ struct Cli {
 enum Commands {
     /// Run a backtest from algorithm file
     Run {
-        /// Path to algorithm Python/Rust file
+        /// Path to algorithm file, or a strategy compiled as a cdylib;
+        /// defaults to the config file's `algorithm`
         #[arg(value_name = "ALGO_FILE")]
-        algo_file: PathBuf,
+        algo_file: Option<PathBuf>,
 
         /// Start date (YYYY-MM-DD)
         #[arg(short = 's', long)]
@@ -64,21 +121,37 @@ enum Commands {
         #[arg(short = 'e', long)]
         end: Option<String>,
 
-        /// Initial capital (default: $10,000,000)
-        #[arg(short = 'c', long, default_value = "10000000.0")]
-        capital_base: f64,
+        /// Initial capital (default: the config's capital_base, else $10,000,000)
+        #[arg(long)]
+        capital_base: Option<f64>,
 
-        /// Data bundle to use
-        #[arg(short = 'b', long, default_value = "quandl")]
-        bundle: String,
+        /// Data bundle to use (default: quandl)
+        #[arg(short = 'b', long)]
+        bundle: Option<String>,
 
         /// Output file for results (CSV/JSON)
         #[arg(short = 'o', long)]
         output: Option<PathBuf>,
 
         /// Benchmark symbol (default: SPY)
-        #[arg(long, default_value = "SPY")]
-        benchmark: String,
+        #[arg(long)]
+        benchmark: Option<String>,
+
+        /// Slippage model, e.g. volume_share:volume_limit=0.025,price_impact=0.1
+        #[arg(long, value_name = "MODEL")]
+        slippage: Option<ModelSpec>,
+
+        /// Commission model, e.g. per_share:cost_per_share=0.001
+        #[arg(long, value_name = "MODEL")]
+        commission: Option<ModelSpec>,
+
+        /// Random seed; runs with the same seed produce identical results
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Directory to write each session's orders, fills and cancellations to
+        #[arg(long, value_name = "DIR")]
+        order_log: Option<PathBuf>,
     },
 
     /// Manage data bundles
@@ -133,6 +206,94 @@ enum Commands {
         force: bool,
     },
 
+    /// Manage the external data source cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Manage the holdout period reserved for out-of-sample evaluation
+    Holdout {
+        #[command(subcommand)]
+        action: HoldoutAction,
+    },
+
+    /// Upgrade saved results, bundles, checkpoints and journals to the current format
+    Migrate {
+        /// Files to upgrade
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Kind of files without a format header (portfolio, bundle, checkpoint, journal, ...)
+        #[arg(short = 'k', long)]
+        kind: Option<String>,
+
+        /// Only report what would be upgraded
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare the results of several backtest runs side by side
+    Compare {
+        /// Saved performance results (full, compact or perf packet), one per run
+        #[arg(value_name = "RESULTS", required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+
+        /// Also write the comparison as an HTML page
+        #[arg(long, value_name = "FILE")]
+        html: Option<PathBuf>,
+    },
+
+    /// Render an HTML tear sheet of a backtest run
+    Report {
+        /// Saved performance results (full, compact or perf packet)
+        #[arg(value_name = "RESULTS")]
+        file: PathBuf,
+
+        /// Output HTML file
+        #[arg(short = 'o', long, value_name = "FILE", default_value = "report.html")]
+        output: PathBuf,
+
+        /// Report title (defaults to the results file stem)
+        #[arg(long)]
+        title: Option<String>,
+    },
+
+    /// Measure how well a factor predicts forward returns (IC, quantile returns, turnover, decay)
+    FactorTearsheet {
+        /// Factor values: CSV with date, sid and value columns
+        #[arg(value_name = "FACTOR")]
+        factor: PathBuf,
+
+        /// Forward returns: CSV with date, sid and one column per period (1d, 5d, ...)
+        #[arg(value_name = "RETURNS")]
+        returns: PathBuf,
+
+        /// Number of factor quantiles
+        #[arg(short = 'q', long, default_value = "5")]
+        quantiles: usize,
+
+        /// Also write the tear sheet as JSON
+        #[arg(long, value_name = "FILE")]
+        json: Option<PathBuf>,
+    },
+
+    /// Chart the equity curve, rolling Sharpe ratio and drawdown of a run
+    #[cfg(feature = "plot")]
+    Plot {
+        /// Saved performance results (full, compact or perf packet)
+        #[arg(value_name = "RESULTS")]
+        file: PathBuf,
+
+        /// Directory to write the charts into
+        #[arg(short = 'o', long, value_name = "DIR", default_value = ".")]
+        output_dir: PathBuf,
+
+        /// Image format (svg, png)
+        #[arg(short = 'f', long, default_value = "svg")]
+        format: String,
+    },
+
     /// Show system information
     Info {
         /// Show detailed information
@@ -195,6 +356,55 @@ enum BundleAction {
     },
 }
 
+#[derive(Subcommand)]
+enum HoldoutAction {
+    /// Show the holdout period and whether it is unlocked
+    Status,
+
+    /// Declare a holdout period that runs may not touch
+    Lock {
+        /// First date of the holdout (YYYY-MM-DD)
+        #[arg(long)]
+        start: String,
+
+        /// Last date of the holdout (YYYY-MM-DD)
+        #[arg(long)]
+        end: String,
+    },
+
+    /// Unlock the holdout for out-of-sample evaluation, recording why
+    Unlock {
+        /// Why the holdout is being unlocked
+        #[arg(short = 'r', long)]
+        reason: String,
+
+        /// Who is unlocking it (defaults to $USER)
+        #[arg(long)]
+        by: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show cache location and size
+    Info,
+
+    /// Remove cached downloads
+    Purge {
+        /// Only purge entries from this data source
+        #[arg(short = 's', long)]
+        source: Option<String>,
+
+        /// Only purge entries older than this many hours
+        #[arg(long, value_name = "HOURS")]
+        older_than: Option<i64>,
+
+        /// Force purge without confirmation
+        #[arg(short = 'f', long)]
+        force: bool,
+    },
+}
+
 /// Configuration file structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Config {
@@ -204,8 +414,50 @@ struct Config {
     cache_dir: PathBuf,
     #[serde(default)]
     bundles: Vec<BundleConfig>,
-    #[serde(default = "default_capital")]
+    #[serde(default = "default_capital", alias = "capital_base")]
     default_capital: f64,
+    /// Algorithm file to run, relative to the config file
+    #[serde(default)]
+    algorithm: Option<PathBuf>,
+    /// Data bundle to run on
+    #[serde(default)]
+    bundle: Option<String>,
+    /// Trading calendar, by exchange name
+    #[serde(default)]
+    calendar: Option<String>,
+    /// First day of the run (YYYY-MM-DD)
+    #[serde(default)]
+    start: Option<String>,
+    /// Last day of the run (YYYY-MM-DD)
+    #[serde(default)]
+    end: Option<String>,
+    /// Benchmark symbol
+    #[serde(default)]
+    benchmark: Option<String>,
+    /// Random seed for runs
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Results file, relative to the config file
+    #[serde(default)]
+    output: Option<PathBuf>,
+    /// Annualized interest paid on idle cash
+    #[serde(default)]
+    cash_rate: Option<f64>,
+    /// Annualized interest charged on negative cash
+    #[serde(default)]
+    margin_rate: Option<f64>,
+    /// Default slippage model for runs
+    #[serde(default)]
+    slippage: Option<ModelSpec>,
+    /// Default commission model for runs
+    #[serde(default)]
+    commission: Option<ModelSpec>,
+    /// Trading controls applied to every run
+    #[serde(default)]
+    controls: Vec<ModelSpec>,
+    /// Holdout manifest; defaults to `holdout.json` in the data directory
+    #[serde(default)]
+    holdout: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -240,17 +492,37 @@ impl Default for Config {
             cache_dir: default_cache_dir(),
             bundles: Vec::new(),
             default_capital: default_capital(),
+            algorithm: None,
+            bundle: None,
+            calendar: None,
+            start: None,
+            end: None,
+            benchmark: None,
+            seed: None,
+            output: None,
+            cash_rate: None,
+            margin_rate: None,
+            slippage: None,
+            commission: None,
+            controls: Vec::new(),
+            holdout: None,
         }
     }
 }
 
 impl Config {
+    fn holdout_manifest(&self) -> PathBuf {
+        self.holdout
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("holdout.json"))
+    }
+
     fn load(path: Option<&Path>) -> Self {
         if let Some(config_path) = path {
             if config_path.exists() {
                 match fs::read_to_string(config_path) {
-                    Ok(contents) => match toml::from_str(&contents) {
-                        Ok(config) => return config,
+                    Ok(contents) => match toml::from_str::<Config>(&contents) {
+                        Ok(config) => return config.relative_to(config_path.parent().unwrap_or(Path::new("."))),
                         Err(e) => {
                             eprintln!(
                                 "{} Failed to parse config: {}",
@@ -285,6 +557,13 @@ impl Config {
         Config::default()
     }
 
+    /// Resolve the run's relative file paths against the config file's directory
+    fn relative_to(mut self, dir: &Path) -> Self {
+        self.algorithm = self.algorithm.map(|path| dir.join(path));
+        self.output = self.output.map(|path| dir.join(path));
+        self
+    }
+
     fn ensure_dirs(&self) -> std::io::Result<()> {
         fs::create_dir_all(&self.data_dir)?;
         fs::create_dir_all(&self.cache_dir)?;
@@ -328,17 +607,33 @@ fn main() {
             bundle,
             output,
             benchmark,
-        } => run_backtest(RunConfig {
-            algo_file,
-            start,
-            end,
-            capital_base,
-            bundle,
-            output,
-            benchmark,
-            verbose: cli.verbose,
-            config,
-        }),
+            slippage,
+            commission,
+            seed,
+            order_log,
+        } => match algo_file.or_else(|| config.algorithm.clone()) {
+            // Command-line options override the config file
+            Some(algo_file) => run_backtest(RunConfig {
+                algo_file,
+                start: start.or_else(|| config.start.clone()),
+                end: end.or_else(|| config.end.clone()),
+                capital_base: capital_base.unwrap_or(config.default_capital),
+                bundle: bundle
+                    .or_else(|| config.bundle.clone())
+                    .unwrap_or_else(|| "quandl".to_string()),
+                output: output.or_else(|| config.output.clone()),
+                benchmark: benchmark
+                    .or_else(|| config.benchmark.clone())
+                    .unwrap_or_else(|| "SPY".to_string()),
+                slippage,
+                commission,
+                seed: seed.or(config.seed),
+                order_log,
+                verbose: cli.verbose,
+                config,
+            }),
+            None => Err("No algorithm file given on the command line or in the config file".into()),
+        },
 
         Commands::Bundle { action } => handle_bundle_action(action, cli.verbose, &config),
 
@@ -374,6 +669,30 @@ fn main() {
             config,
         }),
 
+        Commands::Cache { action } => handle_cache_action(action, &config),
+
+        Commands::Holdout { action } => handle_holdout_action(action, &config),
+
+        Commands::Migrate {
+            files,
+            kind,
+            dry_run,
+        } => migrate_files(&files, kind.as_deref(), dry_run),
+
+        Commands::Compare { files, html } => compare_runs(&files, html.as_deref()),
+
+        Commands::Report { file, output, title } => write_report(&file, &output, title.as_deref()),
+
+        Commands::FactorTearsheet {
+            factor,
+            returns,
+            quantiles,
+            json,
+        } => factor_tearsheet(&factor, &returns, quantiles, json.as_deref()),
+
+        #[cfg(feature = "plot")]
+        Commands::Plot { file, output_dir, format } => plot_results(&file, &output_dir, &format),
+
         Commands::Info { detailed } => show_info(detailed, cli.verbose, &config),
 
         Commands::Benchmark {
@@ -406,6 +725,10 @@ struct RunConfig {
     bundle: String,
     output: Option<PathBuf>,
     benchmark: String,
+    slippage: Option<ModelSpec>,
+    commission: Option<ModelSpec>,
+    seed: Option<u64>,
+    order_log: Option<PathBuf>,
     verbose: bool,
     config: Config,
 }
@@ -450,6 +773,7 @@ struct BacktestResults {
     sharpe_ratio: f64,
     max_drawdown: f64,
     trades: usize,
+    seed: u64,
 }
 
 // Command implementations
@@ -461,9 +785,49 @@ fn run_backtest(cfg: RunConfig) -> Result<(), Box<dyn std::error::Error>> {
         return Err(format!("Algorithm file not found: {:?}", cfg.algo_file).into());
     }
 
+    let manifest = cfg.config.holdout_manifest();
+    if manifest.exists() {
+        let holdout = Holdout::load(&manifest)?;
+        let start = cfg.start.as_deref().map(parse_date).transpose()?.unwrap_or(NaiveDate::MIN);
+        let end = cfg.end.as_deref().map(parse_date).transpose()?.unwrap_or(NaiveDate::MAX);
+        holdout.check(start, end)?;
+    }
+
+    // Plugins register their models on load, so load before resolving specs
+    let plugin = if is_plugin(&cfg.algo_file) {
+        Some(PluginAlgorithm::load(&cfg.algo_file)?)
+    } else {
+        None
+    };
+
+    // Command-line models override the config file; default to no costs
+    let registry = ModelRegistry::global();
+    let slippage_spec = cfg
+        .slippage
+        .clone()
+        .or_else(|| cfg.config.slippage.clone())
+        .unwrap_or_else(|| ModelSpec::new("none"));
+    let commission_spec = cfg
+        .commission
+        .clone()
+        .or_else(|| cfg.config.commission.clone())
+        .unwrap_or_else(|| ModelSpec::new("none"));
+    let slippage = registry.build_slippage(&slippage_spec)?;
+    let commission = registry.build_commission(&commission_spec)?;
+    let controls = cfg
+        .config
+        .controls
+        .iter()
+        .map(|spec| registry.build_control(spec))
+        .collect::<ZiplineResult<Vec<_>>>()?;
+    let seed = cfg.seed.unwrap_or_else(|| SimulationRng::from_entropy().seed());
+    let calendar_name = cfg.config.calendar.clone().unwrap_or_else(|| "NYSE".to_string());
+    let calendar = get_calendar(&calendar_name)?;
+
     if cfg.verbose {
         println!("  {} {:?}", "Algorithm:".bold(), cfg.algo_file);
         println!("  {} {}", "Bundle:".bold(), cfg.bundle);
+        println!("  {} {}", "Calendar:".bold(), calendar_name.to_uppercase());
         println!("  {} ${:.2}", "Capital:".bold(), cfg.capital_base);
         if let Some(ref start) = cfg.start {
             println!("  {} {}", "Start:".bold(), start);
@@ -472,9 +836,35 @@ fn run_backtest(cfg: RunConfig) -> Result<(), Box<dyn std::error::Error>> {
             println!("  {} {}", "End:".bold(), end);
         }
         println!("  {} {}", "Benchmark:".bold(), cfg.benchmark);
+        println!("  {} {}", "Slippage:".bold(), slippage.name());
+        println!("  {} {}", "Commission:".bold(), commission.name());
+        println!("  {} {}", "Seed:".bold(), seed);
+        if !controls.is_empty() {
+            let names: Vec<&str> = controls.iter().map(|c| c.name()).collect();
+            println!("  {} {}", "Controls:".bold(), names.join(", "));
+        }
         println!();
     }
 
+    let is_wasm = cfg!(feature = "wasm") && cfg.algo_file.extension().and_then(|e| e.to_str()) == Some("wasm");
+    let setup = EngineSetup {
+        seed,
+        calendar,
+        slippage: slippage.clone(),
+        commission,
+        controls,
+    };
+    if let Some(plugin) = plugin {
+        return run_compiled(&cfg, setup, |_| Ok(plugin));
+    }
+    #[cfg(feature = "wasm")]
+    if is_wasm {
+        return run_compiled(&cfg, setup, |bundle| {
+            let assets = bundle.assets().values().cloned().collect();
+            WasmAlgorithm::load(&cfg.algo_file, assets)
+        });
+    }
+
     // Create progress bar
     let pb = ProgressBar::new(100);
     pb.set_style(
@@ -521,6 +911,7 @@ fn run_backtest(cfg: RunConfig) -> Result<(), Box<dyn std::error::Error>> {
         sharpe_ratio: 1.85,
         max_drawdown: -0.15,
         trades: 247,
+        seed,
     };
 
     // Display results
@@ -547,6 +938,7 @@ fn run_backtest(cfg: RunConfig) -> Result<(), Box<dyn std::error::Error>> {
         "  Ending Value:     {}",
         format!("${:.2}", results.ending_value).bright_green()
     );
+    println!("  Seed:             {}", results.seed);
     println!();
 
     // Save results if output specified
@@ -590,6 +982,80 @@ fn run_backtest(cfg: RunConfig) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Check if the algorithm file is a compiled strategy library
+fn is_plugin(algo_file: &Path) -> bool {
+    algo_file.extension().and_then(|e| e.to_str()) == Some(std::env::consts::DLL_EXTENSION)
+}
+
+/// Engine parts chosen by the command line and config file
+struct EngineSetup {
+    seed: u64,
+    calendar: Arc<dyn TradingCalendar>,
+    slippage: Arc<dyn SlippageModel>,
+    commission: Arc<dyn CommissionModel>,
+    controls: Vec<Box<dyn TradingControl>>,
+}
+
+/// Run a strategy compiled as a cdylib or to WebAssembly on a saved bundle
+fn run_compiled<A: Algorithm>(
+    cfg: &RunConfig,
+    setup: EngineSetup,
+    load: impl FnOnce(&BundleData) -> ZiplineResult<A>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bundle_path = cfg.config.data_dir.join(&cfg.bundle);
+    let bundle = BundleData::load(&bundle_path)?;
+    let mut algorithm = load(&bundle)?;
+    let (first, last) = bundle
+        .date_range()
+        .ok_or_else(|| ZiplineError::BundleNotFound(format!("{} has no bars", bundle_path.display())))?;
+    let start = cfg.start.as_deref().map(parse_date).transpose()?.unwrap_or(first);
+    let end = cfg.end.as_deref().map(parse_date).transpose()?.unwrap_or(last);
+
+    let config = EngineConfig::default().with_starting_cash(cfg.capital_base);
+    let market_stats = Arc::new(MarketStatsService::default());
+    let broker = SimulatedBroker::new(
+        Box::new(FinanceSlippage::new(setup.slippage).with_market_stats(market_stats.clone())),
+        Box::new(FinanceCommission(setup.commission)),
+    );
+    let mut controls = ControlManager::new();
+    for control in setup.controls {
+        controls.add_order_control(control);
+    }
+    let mut engine = SimulationEngine::new(config, broker, setup.calendar)
+        .with_seed(setup.seed)
+        .with_market_stats(market_stats)
+        .with_trading_controls(Arc::new(controls));
+    if cfg.config.cash_rate.is_some() || cfg.config.margin_rate.is_some() {
+        let rates = FixedRates::new(cfg.config.cash_rate.unwrap_or(0.0), cfg.config.margin_rate.unwrap_or(0.0));
+        engine = engine.with_interest_model(Arc::new(rates));
+    }
+    if let Some(ref dir) = cfg.order_log {
+        OrderLog::new()
+            .with_output_dir(dir, OrderLogFormat::Jsonl)
+            .attach(engine.events_mut());
+    }
+    let results = engine.run(
+        &mut algorithm,
+        &bundle.to_data_source(),
+        start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        end.and_hms_opt(23, 59, 59).unwrap().and_utc(),
+    )?;
+
+    println!("{}", results.summary());
+    println!("  Trades:             {}", results.transactions.len());
+    println!("  Seed:               {}", setup.seed);
+    println!();
+
+    if let Some(ref output_path) = cfg.output {
+        BacktestResult::from_tracker(&results).export_json(output_path)?;
+        println!("{} Results saved to: {}", "✓".green().bold(), output_path.display());
+    }
+    if let Some(ref dir) = cfg.order_log {
+        println!("{} Order log saved to: {}", "✓".green().bold(), dir.display());
+    }
+    Ok(())
+}
+
 fn handle_bundle_action(
     action: BundleAction,
     verbose: bool,
@@ -834,6 +1300,247 @@ fn clean_data(cfg: CleanConfig) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn source_cache(config: &Config) -> DiskCache {
+    DiskCache::new(config.cache_dir.join("sources"))
+}
+
+fn handle_cache_action(action: CacheAction, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = source_cache(config);
+
+    match action {
+        CacheAction::Info => {
+            let (files, bytes) = cache.usage()?;
+            println!("{}", "Data Source Cache".bold());
+            println!("{}", "=================".dimmed());
+            println!("  {} {}", "Location:".bold(), cache.root().display());
+            println!("  {} {}", "Entries:".bold(), files);
+            println!("  {} {:.2} MB", "Size:".bold(), bytes as f64 / 1_048_576.0);
+        }
+        CacheAction::Purge {
+            source,
+            older_than,
+            force,
+        } => {
+            let scope = source.as_deref().unwrap_or("all sources");
+            println!("{}", format!("Purging cache: {}", scope).yellow().bold());
+            if let Some(hours) = older_than {
+                println!("  {} {} hours", "Older than:".bold(), hours);
+            }
+            println!();
+
+            if !force {
+                println!("{}", "This will remove cached downloads.".yellow());
+                println!("Use {} to confirm.", "--force".bright_yellow());
+                return Ok(());
+            }
+
+            let stats = match (older_than, source.as_deref()) {
+                (Some(hours), None) => cache
+                    .with_ttl(chrono::Duration::hours(hours))
+                    .purge_expired()?,
+                (Some(_), Some(_)) => {
+                    return Err("--older-than cannot be combined with --source".into());
+                }
+                (None, source) => cache.purge(source)?,
+            };
+
+            println!("  {} {}", "Files removed:".bold(), stats.files_removed);
+            println!(
+                "  {} {:.2} MB",
+                "Space freed:".bold(),
+                stats.bytes_freed as f64 / 1_048_576.0
+            );
+            println!();
+            println!("{} Cache purged!", "✓".green().bold());
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, Box<dyn std::error::Error>> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date '{}': {}", value, e).into())
+}
+
+fn handle_holdout_action(action: HoldoutAction, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = config.holdout_manifest();
+
+    match action {
+        HoldoutAction::Status => {
+            if !manifest.exists() {
+                println!("{}", "No holdout period declared.".yellow());
+                return Ok(());
+            }
+            let holdout = Holdout::load(&manifest)?;
+            println!("{}", "Holdout".bold());
+            println!("{}", "=======".dimmed());
+            println!("  {} {}", "Manifest:".bold(), manifest.display());
+            println!("  {} {} to {}", "Period:".bold(), holdout.start, holdout.end);
+            match &holdout.unlock {
+                None => println!("  {} {}", "Status:".bold(), "locked".green().bold()),
+                Some(unlock) => {
+                    println!("  {} {}", "Status:".bold(), "unlocked".red().bold());
+                    println!("  {} {} by {}", "Unlocked:".bold(), unlock.unlocked_at, unlock.by);
+                    println!("  {} {}", "Reason:".bold(), unlock.reason);
+                }
+            }
+        }
+        HoldoutAction::Lock { start, end } => {
+            if manifest.exists() {
+                return Err(format!(
+                    "A holdout is already declared in {}; it cannot be redeclared",
+                    manifest.display()
+                )
+                .into());
+            }
+            let holdout = Holdout::new(parse_date(&start)?, parse_date(&end)?)?;
+            if let Some(parent) = manifest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            holdout.save(&manifest)?;
+            println!(
+                "{} Holdout {} to {} locked ({})",
+                "✓".green().bold(),
+                holdout.start,
+                holdout.end,
+                manifest.display()
+            );
+        }
+        HoldoutAction::Unlock { reason, by } => {
+            let mut holdout = Holdout::load(&manifest)?;
+            let by = by
+                .or_else(|| std::env::var("USER").ok())
+                .unwrap_or_else(|| "unknown".to_string());
+            holdout.unlock(&by, &reason)?;
+            holdout.save(&manifest)?;
+            println!(
+                "{} Holdout {} to {} unlocked by {}",
+                "✓".yellow().bold(),
+                holdout.start,
+                holdout.end,
+                by
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate_files(
+    files: &[PathBuf],
+    kind: Option<&str>,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let verb = if dry_run { "Checking" } else { "Migrating" };
+    println!("{}", format!("{} {} file(s)", verb, files.len()).cyan().bold());
+    println!();
+
+    let mut failed = 0;
+    for path in files {
+        match serialization::migrate_file(path, kind, !dry_run) {
+            Ok(migration) if migration.is_upgrade() => println!(
+                "  {} {} ({} v{} -> v{})",
+                if dry_run { "→".yellow().bold() } else { "✓".green().bold() },
+                path.display(),
+                migration.kind,
+                migration.from_version,
+                migration.to_version
+            ),
+            Ok(migration) => println!(
+                "  {} {} ({} v{}, up to date)",
+                "·".dimmed(),
+                path.display(),
+                migration.kind,
+                migration.to_version
+            ),
+            Err(e) => {
+                failed += 1;
+                println!("  {} {}: {}", "✗".red().bold(), path.display(), e);
+            }
+        }
+    }
+
+    println!();
+    if failed > 0 {
+        return Err(format!("{} file(s) could not be migrated", failed).into());
+    }
+    if dry_run {
+        println!("Run without {} to rewrite the files.", "--dry-run".bright_yellow());
+    }
+    Ok(())
+}
+
+fn compare_runs(files: &[PathBuf], html: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut runs = Vec::with_capacity(files.len());
+    for path in files {
+        let tracker = compact::load_results(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        runs.push((name, tracker));
+    }
+
+    let comparison = RunComparison::new(runs.iter().map(|(name, tracker)| (name.as_str(), tracker)));
+    println!("{}", format!("Comparing {} run(s)", runs.len()).cyan().bold());
+    println!();
+    print!("{}", comparison);
+
+    if let Some(path) = html {
+        fs::write(path, comparison.to_html())?;
+        println!();
+        println!("{} Wrote {}", "✓".green().bold(), path.display());
+    }
+    Ok(())
+}
+
+fn write_report(file: &Path, output: &Path, title: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    // Packets and exported results load as-is; other results files are rebuilt from their tracker
+    let result = BacktestResult::load(file)
+        .or_else(|_| compact::load_results(file).map(|tracker| BacktestResult::from_tracker(&tracker)))
+        .map_err(|e| format!("{}: {}", file.display(), e))?;
+    let title = title.map(str::to_string).unwrap_or_else(|| {
+        file.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file.display().to_string())
+    });
+
+    TearSheet::new(&result).with_title(&title).write(output)?;
+    println!("{} Wrote {}", "✓".green().bold(), output.display());
+    Ok(())
+}
+
+fn factor_tearsheet(
+    factor: &Path,
+    returns: &Path,
+    quantiles: usize,
+    json: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let factor = FactorValues::from_csv(factor)?;
+    let returns = ForwardReturns::from_csv(returns)?;
+    let sheet = FactorAnalysis::new().with_quantiles(quantiles).tearsheet(&factor, &returns)?;
+    print!("{}", sheet);
+
+    if let Some(path) = json {
+        fs::write(path, serde_json::to_string_pretty(&sheet)?)?;
+        println!();
+        println!("{} Wrote {}", "✓".green().bold(), path.display());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "plot")]
+fn plot_results(file: &Path, output_dir: &Path, format: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let tracker = compact::load_results(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+    fs::create_dir_all(output_dir)?;
+    for path in rusty_zipline::performance::plot::plot_run(&tracker, output_dir, format)? {
+        println!("{} Wrote {}", "✓".green().bold(), path.display());
+    }
+    Ok(())
+}
+
 fn show_info(detailed: bool, verbose: bool, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     println!("{} {}", "rusty-zipline".cyan().bold(), format!("v{}", env!("CARGO_PKG_VERSION")).dimmed());
     println!("{}", env!("CARGO_PKG_DESCRIPTION"));
@@ -858,9 +1565,11 @@ fn show_info(detailed: bool, verbose: bool, config: &Config) -> Result<(), Box<d
         println!("{}", "Features".bold());
         println!("{}", "========".dimmed());
         println!("  {} {}", "Parallel execution:".bold(), feature_status(cfg!(feature = "rayon")));
+        println!("  {} {}", "SIMD kernels:".bold(), feature_status(cfg!(feature = "simd")));
         println!("  {} {}", "Async runtime:".bold(), feature_status(cfg!(feature = "async")));
         println!("  {} {}", "SQL support:".bold(), feature_status(cfg!(feature = "sqlx-support")));
         println!("  {} {}", "CLI tools:".bold(), feature_status(cfg!(feature = "cli")));
+        println!("  {} {}", "Charts:".bold(), feature_status(cfg!(feature = "plot")));
         println!();
 
         println!("{}", "Compatibility".bold());
@@ -1011,6 +1720,13 @@ mod tests {
         let _cli = Cli::try_parse_from(args).unwrap();
     }
 
+    #[test]
+    fn test_factor_tearsheet_command() {
+        let args = ["rusty-zipline", "factor-tearsheet", "factor.csv", "returns.csv", "-q", "10"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(cli.command, Commands::FactorTearsheet { quantiles: 10, json: None, .. }));
+    }
+
     #[test]
     fn test_bundle_list() {
         let args = vec!["rusty-zipline", "bundle", "list"];
@@ -1023,6 +1739,28 @@ mod tests {
         let _cli = Cli::try_parse_from(args).unwrap();
     }
 
+    #[test]
+    fn test_backtest_config() {
+        let toml = r#"
+            algorithm = "strategies/momentum.so"
+            bundle = "csv"
+            start = "2021-01-04"
+            capital_base = 50000.0
+            seed = 7
+            cash_rate = 0.04
+            commission = { model = "per_share", cost_per_share = 0.005 }
+        "#;
+        let config = toml::from_str::<Config>(toml).unwrap().relative_to(Path::new("runs"));
+        assert_eq!(config.algorithm, Some(PathBuf::from("runs/strategies/momentum.so")));
+        assert_eq!(config.default_capital, 50000.0);
+        assert_eq!(config.seed, Some(7));
+        assert_eq!((config.cash_rate, config.margin_rate), (Some(0.04), None));
+        assert!(ModelRegistry::global().build_commission(&config.commission.unwrap()).is_ok());
+
+        let cli = Cli::try_parse_from(["rusty-zipline", "run", "--config", "backtest.toml"]).unwrap();
+        assert!(matches!(cli.command, Commands::Run { algo_file: None, .. }));
+    }
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
@@ -1,9 +1,11 @@
 //! Trading calendar implementation
 
 use crate::error::{Result, ZiplineError};
-use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use crate::types::SessionId;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Trading session times
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -24,6 +26,21 @@ pub trait TradingCalendar: Send + Sync {
     /// Get session times for a date
     fn session_times(&self, date: NaiveDate) -> Option<SessionTimes>;
 
+    /// Check if the market is open at an instant (open and close inclusive)
+    fn is_open(&self, dt: DateTime<Utc>) -> bool {
+        let local = dt.with_timezone(&self.timezone());
+        if !self.is_trading_day(local.date_naive()) {
+            return false;
+        }
+        self.session_times(local.date_naive())
+            .is_some_and(|times| (times.market_open..=times.market_close).contains(&local.time()))
+    }
+
+    /// Session an instant falls in, by the exchange-local date
+    fn session_of(&self, dt: DateTime<Utc>) -> SessionId {
+        SessionId::from_date(dt.with_timezone(&self.timezone()).date_naive())
+    }
+
     /// Get the next trading day after the given date
     fn next_trading_day(&self, date: NaiveDate) -> Result<NaiveDate> {
         let mut current = date + Duration::days(1);
@@ -326,6 +343,52 @@ impl TradingCalendar for NYSECalendar {
     }
 }
 
+/// Calendar of a market that never closes, such as a crypto exchange
+///
+/// Every day is a trading day, with one session per UTC day running from
+/// midnight to the last instant before the next midnight.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysOpenCalendar;
+
+impl AlwaysOpenCalendar {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl TradingCalendar for AlwaysOpenCalendar {
+    fn timezone(&self) -> Tz {
+        chrono_tz::UTC
+    }
+
+    fn is_trading_day(&self, _date: NaiveDate) -> bool {
+        true
+    }
+
+    fn session_times(&self, _date: NaiveDate) -> Option<SessionTimes> {
+        Some(SessionTimes {
+            market_open: NaiveTime::MIN,
+            market_close: NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap(),
+            is_half_day: false,
+        })
+    }
+}
+
+/// Names accepted by [`get_calendar`]
+pub const CALENDAR_NAMES: &[&str] = &["NYSE", "XNYS", "24/7", "ALWAYS_OPEN", "CRYPTO"];
+
+/// Look up a calendar by exchange name or MIC, case-insensitively
+pub fn get_calendar(name: &str) -> Result<Arc<dyn TradingCalendar>> {
+    match name.to_ascii_uppercase().as_str() {
+        "NYSE" | "XNYS" => Ok(Arc::new(NYSECalendar::new())),
+        "24/7" | "ALWAYS_OPEN" | "CRYPTO" => Ok(Arc::new(AlwaysOpenCalendar::new())),
+        _ => Err(ZiplineError::InvalidCalendarName {
+            calendar: name.to_string(),
+            available: CALENDAR_NAMES.iter().map(|name| name.to_string()).collect(),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -370,6 +433,7 @@ mod tests {
 
     #[test]
     fn test_session_times() {
+        use chrono::Timelike;
         let calendar = NYSECalendar::new();
         let trading_day = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
 
@@ -4,25 +4,34 @@ pub mod adjustments;
 pub mod bar_reader; // NEW: P1 - Daily and minute bar readers
 pub mod benchmarks; // NEW: P2 - Benchmark data loading
 pub mod bundle;
+pub mod calendar_validation;
 pub mod continuous_futures; // NEW: P2 - Continuous futures with roll logic
 pub mod data_portal; // NEW: Unified data access
 pub mod dispatch_reader;
+pub mod earnings; // Point-in-time earnings announcement dates
 pub mod frequency;
+pub mod fundamentals; // Point-in-time fundamentals store
 pub mod fx; // NEW: P2 - Foreign exchange rates
 pub mod history_loader; // NEW: P1 - Historical window management
 pub mod minute_bars;
+pub mod minute_validation;
 pub mod readers; // NEW: P0 - Bcolz bundle readers (CRITICAL BLOCKER)
 pub mod resample; // NEW: P2 - Data frequency resampling
+pub mod session_frame;
 pub mod sources; // NEW: P2 - External data source integrations
 
 use crate::asset::Asset;
+use crate::calendar::TradingCalendar;
+use crate::data::data_portal::DataFrame;
+use crate::data::history_loader::{Frequency, HistoryField, HistoryLoader};
 use crate::error::{Result, ZiplineError};
 use crate::types::{Bar, Price, Timestamp};
 use hashbrown::HashMap;
 use std::collections::VecDeque;
+use std::sync::Arc;
 
 /// Bar data provider for algorithm
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct BarData {
     /// Current bars by asset ID
     current_bars: HashMap<u64, Bar>,
@@ -30,6 +39,28 @@ pub struct BarData {
     history: HashMap<u64, VecDeque<Bar>>,
     /// Maximum history length
     max_history_len: usize,
+    /// Frequency of the bars fed through `update`
+    frequency: Frequency,
+    /// Loader for history beyond the bars kept in memory
+    history_loader: Option<Arc<HistoryLoader>>,
+    /// Timestamp of the latest bar seen
+    current_dt: Option<Timestamp>,
+    /// Calendar deciding whether the exchange is open
+    calendar: Option<Arc<dyn TradingCalendar>>,
+}
+
+impl std::fmt::Debug for BarData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BarData")
+            .field("current_bars", &self.current_bars)
+            .field("history", &self.history)
+            .field("max_history_len", &self.max_history_len)
+            .field("frequency", &self.frequency)
+            .field("history_loader", &self.history_loader)
+            .field("current_dt", &self.current_dt)
+            .field("calendar", &self.calendar.as_ref().map(|_| "<dyn TradingCalendar>"))
+            .finish()
+    }
 }
 
 impl BarData {
@@ -39,11 +70,37 @@ impl BarData {
             current_bars: HashMap::new(),
             history: HashMap::new(),
             max_history_len,
+            frequency: Frequency::Minute,
+            history_loader: None,
+            current_dt: None,
+            calendar: None,
         }
     }
 
+    /// Check exchange hours against `calendar` in [`can_trade`](Self::can_trade)
+    pub fn with_calendar(mut self, calendar: Arc<dyn TradingCalendar>) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// Set the frequency of the bars fed through `update` (minute by default)
+    pub fn with_frequency(mut self, frequency: Frequency) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Serve [`history_frame`](Self::history_frame) requests from a history loader
+    pub fn with_history_loader(mut self, loader: Arc<HistoryLoader>) -> Self {
+        self.history_loader = Some(loader);
+        self
+    }
+
     /// Update current bar for an asset
     pub fn update(&mut self, asset_id: u64, bar: Bar) {
+        if self.current_dt.is_none_or(|dt| bar.timestamp > dt) {
+            self.current_dt = Some(bar.timestamp);
+        }
+
         // Store in history
         let history = self.history.entry(asset_id).or_insert_with(VecDeque::new);
         history.push_back(bar.clone());
@@ -64,13 +121,113 @@ impl BarData {
             .ok_or_else(|| ZiplineError::DataError(format!("No data for {}", asset.symbol)))
     }
 
+    /// Volume of the current bar for an asset
+    pub(crate) fn current_volume(&self, asset_id: u64) -> Option<f64> {
+        self.current_bars.get(&asset_id).map(|bar| bar.volume)
+    }
+
+    /// Whether an order for `asset` can be filled at the current bar
+    ///
+    /// True when the asset is listed for the current session, the exchange is
+    /// open (per the calendar, if one is attached) and the asset has a bar at
+    /// the current time rather than one carried forward.
+    pub fn can_trade(&self, asset: &Asset) -> bool {
+        let Some(dt) = self.current_dt else {
+            return false;
+        };
+        let session = match &self.calendar {
+            Some(calendar) => dt.with_timezone(&calendar.timezone()).date_naive(),
+            None => dt.date_naive(),
+        };
+        if !asset.is_alive_for_session(session) {
+            return false;
+        }
+
+        let exchange_open = match (&self.calendar, self.frequency) {
+            (None, _) => true,
+            (Some(calendar), Frequency::Daily) => calendar.is_trading_day(session),
+            (Some(calendar), Frequency::Minute) => calendar.is_open(dt),
+        };
+        exchange_open && !self.is_stale(asset) && self.has_data(asset)
+    }
+
+    /// Whether the asset's last price was carried forward from an earlier bar
+    ///
+    /// False for an asset that has never traded.
+    pub fn is_stale(&self, asset: &Asset) -> bool {
+        match (self.current_bars.get(&asset.id), self.current_dt) {
+            (Some(bar), Some(dt)) => bar.timestamp < dt,
+            _ => false,
+        }
+    }
+
     /// Get current price for an asset
     pub fn current_price(&self, asset: &Asset) -> Result<Price> {
         Ok(self.current(asset)?.close)
     }
 
+    /// Trailing window of one field for one or more assets
+    ///
+    /// Mirrors Zipline's `data.history`: the result holds the last `bar_count`
+    /// bar times across `assets`, ending at the current bar, with one aligned
+    /// series per asset. A bar missing for an asset repeats the asset's
+    /// previous value; values before an asset's first bar are NaN.
+    ///
+    /// Requests are served by the attached [`HistoryLoader`], or otherwise from
+    /// the bars kept in memory when `frequency` matches theirs.
+    pub fn history_frame(
+        &self,
+        assets: &[Asset],
+        field: HistoryField,
+        bar_count: usize,
+        frequency: Frequency,
+    ) -> Result<DataFrame> {
+        let mut series = Vec::with_capacity(assets.len());
+        match &self.history_loader {
+            Some(loader) => {
+                let end_dt = self.current_dt.ok_or_else(|| {
+                    ZiplineError::DataError("No bars have been processed yet".to_string())
+                })?;
+                for asset in assets {
+                    let bars = loader.load_bars(asset, bar_count, end_dt, frequency)?;
+                    let values = bars.iter().map(|bar| (bar.dt, field.extract(bar))).collect();
+                    series.push((asset.id, values));
+                }
+            }
+            None if frequency == self.frequency => {
+                for asset in assets {
+                    // Every kept bar, so gaps at the start of the window fill forward
+                    let values = self
+                        .history
+                        .get(&asset.id)
+                        .map(|bars| {
+                            bars.iter()
+                                .map(|bar| (bar.timestamp, field_value(bar, field)))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    series.push((asset.id, values));
+                }
+            }
+            None => {
+                return Err(ZiplineError::UnsupportedFeature(format!(
+                    "{:?} history needs a history loader; bars are {:?}",
+                    frequency, self.frequency
+                )))
+            }
+        }
+
+        Ok(align_history(series, field, bar_count))
+    }
+
     /// Get historical bars for an asset
+    #[deprecated(note = "use `bar_history`, or `history_frame` for aligned multi-asset windows")]
     pub fn history(&self, asset: &Asset, bars: usize) -> Result<Vec<Bar>> {
+        self.bar_history(asset, bars)
+    }
+
+    /// Get historical bars for an asset
+    pub fn bar_history(&self, asset: &Asset, bars: usize) -> Result<Vec<Bar>> {
         let history = self
             .history
             .get(&asset.id)
@@ -87,7 +244,7 @@ impl BarData {
 
     /// Get historical prices for an asset
     pub fn history_prices(&self, asset: &Asset, bars: usize) -> Result<Vec<Price>> {
-        Ok(self.history(asset, bars)?.iter().map(|b| b.close).collect())
+        Ok(self.bar_history(asset, bars)?.iter().map(|b| b.close).collect())
     }
 
     /// Check if asset has data
@@ -104,6 +261,49 @@ impl BarData {
     }
 }
 
+/// Value of a history field on an engine bar
+fn field_value(bar: &Bar, field: HistoryField) -> f64 {
+    match field {
+        HistoryField::Open => bar.open,
+        HistoryField::High => bar.high,
+        HistoryField::Low => bar.low,
+        HistoryField::Close => bar.close,
+        HistoryField::Volume => bar.volume,
+    }
+}
+
+/// Align per-asset series on the last `bar_count` bar times, forward filling gaps
+fn align_history(
+    series: Vec<(u64, Vec<(Timestamp, f64)>)>,
+    field: HistoryField,
+    bar_count: usize,
+) -> DataFrame {
+    let mut index: Vec<Timestamp> = series
+        .iter()
+        .flat_map(|(_, values)| values.iter().map(|(dt, _)| *dt))
+        .collect();
+    index.sort_unstable();
+    index.dedup();
+    let index = index.split_off(index.len().saturating_sub(bar_count));
+
+    let mut frame = DataFrame::new();
+    frame.columns = vec![field.name().to_string()];
+    for (asset_id, values) in series {
+        let mut aligned = Vec::with_capacity(index.len());
+        let mut next = values.iter().peekable();
+        let mut last = f64::NAN;
+        for dt in &index {
+            while let Some((_, value)) = next.next_if(|(bar_dt, _)| bar_dt <= dt) {
+                last = *value;
+            }
+            aligned.push(last);
+        }
+        frame.data.insert(asset_id, aligned);
+    }
+    frame.index = index;
+    frame
+}
+
 /// Data source trait for providing market data
 pub trait DataSource: Send + Sync {
     /// Get bars for a specific timestamp
@@ -183,7 +383,7 @@ impl DataSource for InMemoryDataSource {
 mod tests {
     use super::*;
     use crate::asset::Asset;
-    use chrono::{NaiveDate, Utc};
+    use chrono::{Datelike, NaiveDate, Utc};
 
     #[test]
     fn test_bar_data() {
@@ -210,10 +410,127 @@ mod tests {
             bar_data.update(1, bar);
         }
 
-        let history = bar_data.history(&asset, 5).unwrap();
+        let history = bar_data.bar_history(&asset, 5).unwrap();
         assert_eq!(history.len(), 5);
 
         let prices = bar_data.history_prices(&asset, 5).unwrap();
         assert_eq!(prices.len(), 5);
     }
+
+    #[test]
+    fn test_history_aligns_and_forward_fills() {
+        use chrono::{Duration, TimeZone};
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), listed);
+        let open = Utc.with_ymd_and_hms(2024, 1, 2, 14, 31, 0).unwrap();
+
+        // AAPL trades every minute; MSFT starts late and skips minute 3
+        let mut bar_data = BarData::new(100);
+        for i in 0..5 {
+            let dt = open + Duration::minutes(i);
+            let price = 100.0 + i as f64;
+            bar_data.update(1, Bar::new(dt, price, price, price, price, 10.0));
+            if i == 2 || i == 4 {
+                let price = 200.0 + i as f64;
+                bar_data.update(2, Bar::new(dt, price, price, price, price, 10.0));
+            }
+        }
+
+        let frame = bar_data
+            .history_frame(&[aapl, msft], HistoryField::Close, 4, Frequency::Minute)
+            .unwrap();
+        assert_eq!(frame.columns, vec!["close".to_string()]);
+        assert_eq!(frame.index.first(), Some(&(open + Duration::minutes(1))));
+        assert_eq!(frame.len(), 4);
+        assert_eq!(frame.data[&1], vec![101.0, 102.0, 103.0, 104.0]);
+        assert!(frame.data[&2][0].is_nan());
+        assert_eq!(frame.data[&2][1..], [202.0, 202.0, 204.0]);
+    }
+
+    #[test]
+    fn test_history_from_loader() {
+        use crate::data::bar_reader::{self, DailyBarReader};
+        use chrono::{Duration, TimeZone};
+
+        // Ten weekdays of daily closes, so calendar lookbacks span weekends
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let first = Utc.with_ymd_and_hms(2024, 1, 1, 21, 0, 0).unwrap();
+        let days: Vec<_> = (0..14)
+            .map(|i| first + Duration::days(i))
+            .filter(|dt| dt.weekday().num_days_from_monday() < 5)
+            .collect();
+        let bars = days
+            .iter()
+            .enumerate()
+            .map(|(i, dt)| {
+                let price = 100.0 + i as f64;
+                bar_reader::Bar::new(price, price, price, price, 1000.0, *dt)
+            })
+            .collect();
+        let mut reader = DailyBarReader::new();
+        reader.load_from_memory(1, bars).unwrap();
+
+        let loader = Arc::new(HistoryLoader::new(Arc::new(reader)));
+        let mut bar_data = BarData::new(1).with_history_loader(loader);
+        assert!(bar_data
+            .history_frame(&[asset.clone()], HistoryField::Close, 7, Frequency::Daily)
+            .is_err());
+
+        let last = *days.last().unwrap();
+        bar_data.update(1, Bar::new(last, 109.0, 109.0, 109.0, 109.0, 1000.0));
+        let frame = bar_data
+            .history_frame(&[asset], HistoryField::Close, 7, Frequency::Daily)
+            .unwrap();
+        assert_eq!(frame.data[&1], vec![103.0, 104.0, 105.0, 106.0, 107.0, 108.0, 109.0]);
+    }
+
+    #[test]
+    fn test_history_needs_loader_for_other_frequencies() {
+        let bar_data = BarData::new(10);
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        assert!(matches!(
+            bar_data.history_frame(&[asset], HistoryField::Close, 5, Frequency::Daily),
+            Err(ZiplineError::UnsupportedFeature(_))
+        ));
+    }
+
+    #[test]
+    fn test_can_trade_and_is_stale() {
+        use crate::calendar::NYSECalendar;
+        use chrono::{Duration, TimeZone};
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let thin = Asset::equity(2, "THIN".to_string(), "NASDAQ".to_string(), listed);
+        let mut delisted = Asset::equity(3, "GONE".to_string(), "NASDAQ".to_string(), listed);
+        delisted.end_date = NaiveDate::from_ymd_opt(2023, 12, 29);
+        let never = Asset::equity(4, "NEW".to_string(), "NASDAQ".to_string(), listed);
+
+        // 10:00 New York time on a trading day
+        let dt = Utc.with_ymd_and_hms(2024, 1, 3, 15, 0, 0).unwrap();
+        let bar = |dt| Bar::new(dt, 10.0, 10.0, 10.0, 10.0, 100.0);
+        let mut bar_data = BarData::new(10).with_calendar(Arc::new(NYSECalendar::new()));
+        assert!(!bar_data.can_trade(&aapl));
+
+        bar_data.update(2, bar(dt - Duration::minutes(5)));
+        bar_data.update(1, bar(dt));
+        bar_data.update(3, bar(dt));
+
+        assert!(bar_data.can_trade(&aapl));
+        assert!(!bar_data.is_stale(&aapl));
+        assert!(bar_data.is_stale(&thin));
+        assert!(!bar_data.can_trade(&thin));
+        assert!(!bar_data.can_trade(&delisted));
+        assert!(!bar_data.is_stale(&never));
+        assert!(!bar_data.can_trade(&never));
+
+        // After the close the exchange is shut even with a fresh bar
+        let after_close = Utc.with_ymd_and_hms(2024, 1, 3, 22, 0, 0).unwrap();
+        bar_data.update(1, bar(after_close));
+        assert!(!bar_data.can_trade(&aapl));
+    }
 }
@@ -2,7 +2,7 @@
 
 use crate::error::{Result, ZiplineError};
 use crate::types::{Bar, Price, Quantity};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -131,6 +131,29 @@ impl AdjustmentReader {
             .unwrap_or_default()
     }
 
+    /// Per-share cash dividends on an asset going ex after `after`, up to and
+    /// including `through`
+    pub fn cash_dividends(&self, asset_id: u64, after: NaiveDate, through: NaiveDate) -> Vec<f64> {
+        self.adjustments
+            .get(&asset_id)
+            .map(|adjs| {
+                adjs.iter()
+                    .filter(|adj| {
+                        let ex_date = adj.effective_date.date_naive();
+                        ex_date > after && ex_date <= through
+                    })
+                    .filter_map(|adj| match adj.kind {
+                        AdjustmentKind::Dividend {
+                            amount,
+                            kind: DividendKind::Cash,
+                        } => Some(amount),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Apply all adjustments to a bar
     pub fn apply_adjustments_to_bar(
         &self,
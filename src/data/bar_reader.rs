@@ -4,6 +4,7 @@
 
 use crate::asset::Asset;
 use crate::error::{Result, ZiplineError};
+use crate::types::SessionId;
 use chrono::{DateTime, Datelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -24,6 +25,9 @@ pub struct Bar {
     pub volume: f64,
     /// Timestamp
     pub dt: DateTime<Utc>,
+    /// Open interest (futures only)
+    #[serde(default)]
+    pub open_interest: Option<f64>,
 }
 
 impl Bar {
@@ -35,9 +39,16 @@ impl Bar {
             close,
             volume,
             dt,
+            open_interest: None,
         }
     }
 
+    /// Attach an open interest value to this bar
+    pub fn with_open_interest(mut self, open_interest: f64) -> Self {
+        self.open_interest = Some(open_interest);
+        self
+    }
+
     /// Typical price (HLC/3)
     pub fn typical_price(&self) -> f64 {
         (self.high + self.low + self.close) / 3.0
@@ -95,6 +106,11 @@ impl SessionLabel {
                 ))
             })
     }
+
+    /// The session this label names; labels are UTC dates
+    pub fn session_id(&self) -> Result<SessionId> {
+        Ok(SessionId::utc_label_of(self.to_datetime()?))
+    }
 }
 
 /// Trait for reading bar data
@@ -415,6 +431,10 @@ mod tests {
             symbol: "AAPL".to_string(),
             exchange: "NYSE".to_string(),
             asset_type: crate::asset::AssetType::Equity,
+            name: None,
+            start_date: chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            end_date: None,
+            auto_close_date: None,
         }
     }
 
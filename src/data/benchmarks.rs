@@ -1,13 +1,16 @@
 //! Benchmark data loading for performance comparisons
 //!
 //! Provides benchmark return data for calculating alpha, beta, and other
-//! relative performance metrics against a reference index (e.g., S&P 500).
+//! relative performance metrics against a reference index (e.g., S&P 500),
+//! and the risk-free rate curve Sharpe and Sortino ratios are measured
+//! against.
 
 use crate::asset::Asset;
 use crate::data::bar_reader::BarReader;
 use crate::error::{Result, ZiplineError};
 use chrono::{DateTime, NaiveDate, Utc};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 
 /// Return data point (timestamp, return)
@@ -112,6 +115,11 @@ impl SPYBenchmark {
         }
     }
 
+    /// Create from a CSV of returns or closing prices, see [`load_returns_csv`]
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        Ok(Self::with_returns(load_returns_csv(path)?))
+    }
+
     /// Calculate and cache returns from price data
     fn calculate_returns(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<()> {
         let bars = self.bar_reader.get_bars(&self.spy_asset, start, end)?;
@@ -330,6 +338,162 @@ impl BenchmarkReader for ZeroBenchmark {
     }
 }
 
+/// Load a daily benchmark return series from CSV
+///
+/// The file needs a `date` column and either a `returns` column of daily
+/// simple returns or a `close` column of prices, from which returns are
+/// computed (the first day's return is zero). Dates are keyed at midnight UTC.
+pub fn load_returns_csv(path: &Path) -> Result<Vec<(DateTime<Utc>, f64)>> {
+    let csv_error = |e: csv::Error| {
+        ZiplineError::DataError(format!("Failed to read {}: {}", path.display(), e))
+    };
+    let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let date_col = column("date").ok_or_else(|| {
+        ZiplineError::InvalidData(format!("{} has no date column", path.display()))
+    })?;
+    let (value_col, prices) = match (column("returns"), column("close")) {
+        (Some(col), _) => (col, false),
+        (None, Some(col)) => (col, true),
+        (None, None) => {
+            return Err(ZiplineError::InvalidData(format!(
+                "{} needs a returns or close column",
+                path.display()
+            )))
+        }
+    };
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let date = parse_date(record.get(date_col).unwrap_or(""))?;
+        let field = record.get(value_col).unwrap_or("").trim();
+        let value: f64 = field.parse().map_err(|_| {
+            ZiplineError::InvalidData(format!("Bad value '{}' for {} in {}", field, date, path.display()))
+        })?;
+        rows.push((date, value));
+    }
+    rows.sort_by_key(|(date, _)| *date);
+
+    if prices {
+        let mut returns = Vec::with_capacity(rows.len());
+        for (i, &(date, close)) in rows.iter().enumerate() {
+            let daily = if i == 0 { 0.0 } else { close / rows[i - 1].1 - 1.0 };
+            returns.push((date, daily));
+        }
+        rows = returns;
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|(date, value)| (date.and_hms_opt(0, 0, 0).unwrap().and_utc(), value))
+        .collect())
+}
+
+fn parse_date(field: &str) -> Result<NaiveDate> {
+    let field = field.trim();
+    NaiveDate::parse_from_str(field, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(field, "%Y%m%d"))
+        .map_err(|_| ZiplineError::InvalidData(format!("Bad date '{}'", field)))
+}
+
+/// Annualized risk-free rate over time
+///
+/// Rates are fractions (0.05 is 5%) and hold from their date until the next
+/// one. Dates before the first rate use the first rate.
+#[derive(Debug, Clone, Default)]
+pub struct RiskFreeCurve {
+    rates: BTreeMap<NaiveDate, f64>,
+}
+
+impl RiskFreeCurve {
+    /// The same rate on every date
+    pub fn constant(rate: f64) -> Self {
+        Self::from_rates([(NaiveDate::MIN, rate)])
+    }
+
+    /// Rates effective from each date
+    pub fn from_rates(rates: impl IntoIterator<Item = (NaiveDate, f64)>) -> Self {
+        Self {
+            rates: rates.into_iter().collect(),
+        }
+    }
+
+    /// Load a treasury rate series from CSV
+    ///
+    /// The first column is the date and the second the rate. Rates are read
+    /// as percentages when the column is a FRED treasury series (`DTB3`,
+    /// `DGS10`, ...) or is named `*_pct`, and as fractions otherwise. FRED's
+    /// `.` placeholder for missing days is skipped.
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let csv_error = |e: csv::Error| {
+            ZiplineError::DataError(format!("Failed to read {}: {}", path.display(), e))
+        };
+        let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+        let rate_column = reader
+            .headers()
+            .map_err(csv_error)?
+            .get(1)
+            .map(|h| h.trim().to_uppercase())
+            .ok_or_else(|| {
+                ZiplineError::InvalidData(format!("{} needs a date and a rate column", path.display()))
+            })?;
+        let scale = if rate_column.starts_with("DTB")
+            || rate_column.starts_with("DGS")
+            || rate_column.ends_with("_PCT")
+        {
+            0.01
+        } else {
+            1.0
+        };
+
+        let mut rates = BTreeMap::new();
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+            let field = record.get(1).unwrap_or("").trim();
+            if field.is_empty() || field == "." {
+                continue;
+            }
+            let rate: f64 = field.parse().map_err(|_| {
+                ZiplineError::InvalidData(format!("Bad rate '{}' in {}", field, path.display()))
+            })?;
+            rates.insert(parse_date(record.get(0).unwrap_or(""))?, rate * scale);
+        }
+        Ok(Self { rates })
+    }
+
+    /// Annualized rate in effect on `date`; zero for an empty curve
+    pub fn rate_at(&self, date: NaiveDate) -> f64 {
+        self.rates
+            .range(..=date)
+            .next_back()
+            .or_else(|| self.rates.iter().next())
+            .map(|(_, rate)| *rate)
+            .unwrap_or(0.0)
+    }
+
+    /// Rate for one trading day on `date`, at 252 trading days a year
+    pub fn daily_rate(&self, date: NaiveDate) -> f64 {
+        self.rate_at(date) / 252.0
+    }
+
+    /// Number of rate observations
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    /// Whether the curve has no rates
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+}
+
 /// Calculate cumulative return from daily returns
 pub fn calculate_cumulative_return(returns: &[BenchmarkReturn]) -> f64 {
     returns
@@ -592,4 +756,40 @@ mod tests {
         // Should be approximately 20% annualized
         assert!((annualized - 0.20).abs() < 0.01);
     }
+
+    #[test]
+    fn test_load_returns_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spy.csv");
+        std::fs::write(&path, "date,close\n2024-01-03,110\n2024-01-02,100\n2024-01-04,99\n").unwrap();
+
+        let returns = load_returns_csv(&path).unwrap();
+        assert_eq!(returns.len(), 3);
+        assert_eq!(returns[0].1, 0.0);
+        assert!((returns[1].1 - 0.10).abs() < 1e-12);
+        assert!((returns[2].1 + 0.10).abs() < 1e-12);
+
+        let benchmark = SPYBenchmark::from_csv(&path).unwrap();
+        let start = returns[0].0;
+        let series = benchmark.get_benchmark_returns(start, start + chrono::Duration::days(2)).unwrap();
+        assert_eq!(series.len(), 3);
+    }
+
+    #[test]
+    fn test_risk_free_curve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DTB3.csv");
+        std::fs::write(&path, "DATE,DTB3\n2024-01-02,5.20\n2024-01-03,.\n2024-01-04,5.10\n").unwrap();
+
+        let curve = RiskFreeCurve::from_csv(&path).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        assert_eq!(curve.len(), 2);
+        assert!((curve.rate_at(day(1)) - 0.052).abs() < 1e-12);
+        assert!((curve.rate_at(day(3)) - 0.052).abs() < 1e-12);
+        assert!((curve.rate_at(day(9)) - 0.051).abs() < 1e-12);
+        assert!((curve.daily_rate(day(4)) - 0.051 / 252.0).abs() < 1e-15);
+
+        assert_eq!(RiskFreeCurve::constant(0.02).rate_at(day(1)), 0.02);
+        assert_eq!(RiskFreeCurve::default().rate_at(day(1)), 0.0);
+    }
 }
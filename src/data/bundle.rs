@@ -1,15 +1,29 @@
 //! Data bundle system for ingesting historical data from CSV files
+//!
+//! [`CSVBundleReader::load_csv`] reads one file with every symbol in it and
+//! stops at the first bad row. [`CSVBundleReader::load_directory`] reads a
+//! directory of per-symbol files, inferring each file's columns from header
+//! aliases and its date format from its values, takes listing metadata from an
+//! optional `symbols.csv`, and collects bad rows into an [`IngestReport`]
+//! instead of failing.
 
 use crate::asset::Asset;
+use crate::data::InMemoryDataSource;
 use crate::error::{Result, ZiplineError};
 use crate::types::Bar;
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use csv::ReaderBuilder;
 use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
 /// Bundle metadata and data holder
-#[derive(Debug, Clone)]
+///
+/// Bundles are saved in the crate's native format, a versioned JSON file (see
+/// [`crate::serialization`]), so they can be reloaded without re-ingesting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BundleData {
     /// Asset ID to bars mapping
     data: HashMap<u64, Vec<Bar>>,
@@ -35,6 +49,16 @@ impl BundleData {
         }
     }
 
+    /// Save the bundle in the native versioned format
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::serialization::save(self, path)
+    }
+
+    /// Load a bundle saved by this or an older crate version
+    pub fn load(path: &Path) -> Result<Self> {
+        crate::serialization::load(path)
+    }
+
     /// Add bar data for an asset
     pub fn add_bar(&mut self, asset_id: u64, bar: Bar) {
         self.data.entry(asset_id).or_insert_with(Vec::new).push(bar);
@@ -69,6 +93,36 @@ impl BundleData {
         }
     }
 
+    /// Get the distinct sessions (dates) present in the bundle, sorted ascending
+    pub fn sessions(&self) -> Vec<NaiveDate> {
+        let mut sessions: Vec<NaiveDate> = self
+            .data
+            .values()
+            .flat_map(|bars| bars.iter().map(|b| b.timestamp.date_naive()))
+            .collect();
+        sessions.sort();
+        sessions.dedup();
+        sessions
+    }
+
+    /// Copy the bundle's bars and assets into a data source the engine can run on
+    pub fn to_data_source(&self) -> InMemoryDataSource {
+        let mut source = InMemoryDataSource::new();
+        for asset in self.assets.values() {
+            source.add_asset(asset.clone());
+        }
+        for (asset_id, bars) in &self.data {
+            for bar in bars {
+                source.add_bar(*asset_id, bar.clone());
+            }
+        }
+        let timestamps = self.data.values().flatten().map(|b| b.timestamp);
+        if let (Some(start), Some(end)) = (timestamps.clone().min(), timestamps.max()) {
+            source.set_date_range(start, end);
+        }
+        source
+    }
+
     /// Finalize bundle (sort and validate)
     pub fn finalize(&mut self) -> Result<()> {
         // Sort all bars by timestamp
@@ -236,19 +290,46 @@ impl Default for CSVFormat {
     }
 }
 
+/// Headers accepted for each column when its configured name is absent
+const COLUMN_ALIASES: [(&str, &[&str]); 7] = [
+    ("date", &["date", "timestamp", "datetime", "time", "day"]),
+    ("symbol", &["symbol", "ticker", "code"]),
+    ("open", &["open", "o", "open_price"]),
+    ("high", &["high", "h", "high_price"]),
+    ("low", &["low", "l", "low_price"]),
+    ("close", &["close", "c", "close_price", "last"]),
+    ("volume", &["volume", "vol", "v"]),
+];
+
+/// Date formats tried, in order, after the configured and added ones;
+/// `%m/%d/%Y` comes first so wholly ambiguous files read as US dates
+const DATE_FORMATS: [&str; 6] = ["%Y-%m-%d", "%Y%m%d", "%Y/%m/%d", "%m/%d/%Y", "%d/%m/%Y", "%d.%m.%Y"];
+
+/// Date-time formats tried after the date formats
+const DATETIME_FORMATS: [&str; 5] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+];
+
+/// Listing metadata file read by [`CSVBundleReader::load_directory`]
+const SYMBOLS_FILE: &str = "symbols.csv";
+
 /// CSV bundle reader
 pub struct CSVBundleReader {
     format: CSVFormat,
     next_asset_id: u64,
+    aliases: HashMap<String, Vec<String>>,
+    date_formats: Vec<String>,
+    timezone: Option<Tz>,
 }
 
 impl CSVBundleReader {
     /// Create new CSV reader with default format
     pub fn new() -> Self {
-        Self {
-            format: CSVFormat::default(),
-            next_asset_id: 1,
-        }
+        Self::with_format(CSVFormat::default())
     }
 
     /// Create with custom format
@@ -256,9 +337,33 @@ impl CSVBundleReader {
         Self {
             format,
             next_asset_id: 1,
+            aliases: HashMap::new(),
+            date_formats: Vec::new(),
+            timezone: None,
         }
     }
 
+    /// Accept `alias` as a header for `column` (`date`, `symbol`, `open`,
+    /// `high`, `low`, `close` or `volume`) when reading directories
+    pub fn with_column_alias(mut self, column: &str, alias: &str) -> Self {
+        self.aliases.entry(column.to_lowercase()).or_default().push(alias.to_string());
+        self
+    }
+
+    /// Try `format` (a date or date-time `strftime` pattern) before the
+    /// built-in ones when inferring a file's date format
+    pub fn with_date_format(mut self, format: &str) -> Self {
+        self.date_formats.push(format.to_string());
+        self
+    }
+
+    /// Read date-times without an offset as local times in `timezone`
+    /// (default UTC); plain dates stay session labels at UTC midnight
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+
     /// Load CSV file into bundle
     pub fn load_csv(&mut self, path: &Path) -> Result<BundleData> {
         let mut bundle = BundleData::new();
@@ -376,6 +481,373 @@ impl CSVBundleReader {
             .position(|h| h.eq_ignore_ascii_case(name))
             .ok_or_else(|| ZiplineError::DataError(format!("Column '{}' not found", name)))
     }
+
+    /// Load a directory of per-symbol CSV files into a bundle
+    ///
+    /// Each `*.csv` file holds one symbol, named by its `symbol` column or
+    /// else the file name. Columns are found by their configured name or an
+    /// alias, and each file's date format is the first candidate that parses
+    /// all of its dates. An optional `symbols.csv` with `symbol`, `exchange`,
+    /// `start_date`, `end_date` and `asset_name` columns sets listing details;
+    /// bars outside the listing dates are dropped. Rows that fail validation
+    /// are skipped and recorded in the report, as are files missing a column.
+    pub fn load_directory(&mut self, dir: &Path) -> Result<(BundleData, IngestReport)> {
+        let mut report = IngestReport::default();
+        let metadata_path = dir.join(SYMBOLS_FILE);
+        let metadata = if metadata_path.exists() {
+            self.read_symbols(&metadata_path, &mut report)?
+        } else {
+            HashMap::new()
+        };
+
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                path.is_file() && name.to_lowercase().ends_with(".csv") && name != SYMBOLS_FILE
+            })
+            .collect();
+        files.sort();
+
+        // Bars by symbol, in the order symbols are first seen
+        let mut symbols: Vec<String> = Vec::new();
+        let mut bars: HashMap<String, Vec<(Bar, u64, PathBuf)>> = HashMap::new();
+        for path in &files {
+            report.files += 1;
+            for (symbol, bar, line) in self.read_symbol_file(path, &mut report)? {
+                if !bars.contains_key(&symbol) {
+                    symbols.push(symbol.clone());
+                }
+                bars.entry(symbol).or_default().push((bar, line, path.clone()));
+            }
+        }
+
+        let mut bundle = BundleData::new();
+        for symbol in symbols {
+            let mut rows = bars.remove(&symbol).unwrap_or_default();
+            rows.sort_by_key(|(bar, line, _)| (bar.timestamp, *line));
+            let listing = metadata.get(&symbol);
+            let start = listing.and_then(|m| m.start_date);
+            let end = listing.and_then(|m| m.end_date);
+
+            let mut kept: Vec<Bar> = Vec::with_capacity(rows.len());
+            for (bar, line, path) in rows {
+                let date = bar.timestamp.date_naive();
+                let listed = start.is_none_or(|start| date >= start) && end.is_none_or(|end| date <= end);
+                let message = if !listed {
+                    format!("{} bar on {} is outside its listing dates", symbol, date)
+                } else if kept.last().is_some_and(|last| last.timestamp == bar.timestamp) {
+                    format!("duplicate {} bar at {}", symbol, bar.timestamp)
+                } else {
+                    kept.push(bar);
+                    continue;
+                };
+                report.issue(&path, Some(line), message);
+            }
+            let Some(first) = kept.first() else {
+                continue;
+            };
+
+            let id = self.next_asset_id;
+            self.next_asset_id += 1;
+            let exchange = listing.and_then(|m| m.exchange.clone()).unwrap_or_else(|| "CSV".to_string());
+            let listed = start.unwrap_or(first.timestamp.date_naive());
+            let mut asset = Asset::equity(id, symbol.clone(), exchange, listed);
+            if let Some(end) = end {
+                asset = asset.with_end_date(end);
+            }
+            asset.name = listing.and_then(|m| m.name.clone());
+            bundle.add_asset(symbol, asset);
+            report.rows += kept.len();
+            for bar in kept {
+                bundle.add_bar(id, bar);
+            }
+        }
+
+        bundle.finalize()?;
+        Ok((bundle, report))
+    }
+
+    /// Read the listing metadata file
+    fn read_symbols(
+        &self,
+        path: &Path,
+        report: &mut IngestReport,
+    ) -> Result<HashMap<String, SymbolMetadata>> {
+        let mut rdr = ReaderBuilder::new()
+            .from_path(path)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to open CSV: {}", e)))?;
+        let headers = rdr
+            .headers()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read headers: {}", e)))?
+            .clone();
+        let symbol_idx = self.resolve_column(&headers, "symbol").ok_or_else(|| {
+            ZiplineError::DataError(format!("{} has no symbol column", path.display()))
+        })?;
+        let column = |names: &[&str]| headers.iter().position(|h| names.contains(&normalize(h).as_str()));
+        let exchange_idx = column(&["exchange"]);
+        let start_idx = column(&["startdate", "start", "firsttraded"]);
+        let end_idx = column(&["enddate", "end", "autoclosedate"]);
+        let name_idx = column(&["assetname", "name"]);
+
+        let mut metadata = HashMap::new();
+        for result in rdr.records() {
+            let record = result
+                .map_err(|e| ZiplineError::DataError(format!("Failed to read record: {}", e)))?;
+            let line = record.position().map_or(0, |p| p.line());
+            let field = |idx: Option<usize>| {
+                idx.and_then(|idx| record.get(idx)).map(str::trim).filter(|v| !v.is_empty())
+            };
+            let Some(symbol) = field(Some(symbol_idx)) else {
+                report.issue(path, Some(line), "missing symbol".to_string());
+                continue;
+            };
+            let mut date = |idx: Option<usize>| {
+                let text = field(idx)?;
+                let parsed = self.date_candidates().into_iter().find_map(|parser| parser.parse(text, None));
+                if parsed.is_none() {
+                    report.issue(path, Some(line), format!("invalid date '{}' for {}", text, symbol));
+                }
+                parsed.map(|dt| dt.date_naive())
+            };
+            let (start_date, end_date) = (date(start_idx), date(end_idx));
+            metadata.insert(
+                symbol.to_string(),
+                SymbolMetadata {
+                    exchange: field(exchange_idx).map(str::to_string),
+                    start_date,
+                    end_date,
+                    name: field(name_idx).map(str::to_string),
+                },
+            );
+        }
+        Ok(metadata)
+    }
+
+    /// Read one symbol's file as `(symbol, bar, line)` rows, recording bad rows
+    fn read_symbol_file(&self, path: &Path, report: &mut IngestReport) -> Result<Vec<(String, Bar, u64)>> {
+        let mut rdr = ReaderBuilder::new()
+            .flexible(true)
+            .from_path(path)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to open CSV: {}", e)))?;
+        let headers = rdr
+            .headers()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read headers: {}", e)))?
+            .clone();
+
+        let mut columns = [0; 6];
+        for (slot, column) in columns.iter_mut().zip(["date", "open", "high", "low", "close", "volume"]) {
+            match self.resolve_column(&headers, column) {
+                Some(idx) => *slot = idx,
+                None => {
+                    report.issue(path, None, format!("no {} column in header", column));
+                    return Ok(Vec::new());
+                }
+            }
+        }
+        let [date_idx, open_idx, high_idx, low_idx, close_idx, volume_idx] = columns;
+        let symbol_idx = self.resolve_column(&headers, "symbol");
+        let file_symbol = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+
+        let mut records = Vec::new();
+        for result in rdr.records() {
+            match result {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    let line = e.position().map(|p| p.line());
+                    report.issue(path, line, format!("unreadable row: {}", e));
+                }
+            }
+        }
+
+        // The candidate parsing the most dates wins, the earliest on ties
+        let dates: Vec<&str> = records.iter().map(|r| r.get(date_idx).unwrap_or("").trim()).collect();
+        let parser = self
+            .date_candidates()
+            .into_iter()
+            .enumerate()
+            .max_by_key(|(order, parser)| {
+                let parsed = dates.iter().filter(|d| parser.parse(d, self.timezone).is_some()).count();
+                (parsed, std::cmp::Reverse(*order))
+            })
+            .map(|(_, parser)| parser);
+
+        let mut rows = Vec::with_capacity(records.len());
+        for (record, date) in records.iter().zip(&dates) {
+            let line = record.position().map_or(0, |p| p.line());
+            let symbol = symbol_idx
+                .and_then(|idx| record.get(idx))
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .unwrap_or(&file_symbol)
+                .to_string();
+            let Some(timestamp) = parser.as_ref().and_then(|parser| parser.parse(date, self.timezone)) else {
+                report.issue(path, Some(line), format!("invalid date '{}'", date));
+                continue;
+            };
+            let mut values = [0.0; 5];
+            let mut error = None;
+            for ((value, idx), name) in values
+                .iter_mut()
+                .zip([open_idx, high_idx, low_idx, close_idx, volume_idx])
+                .zip(["open", "high", "low", "close", "volume"])
+            {
+                let text = record.get(idx).unwrap_or("").trim();
+                match text.parse::<f64>() {
+                    Ok(parsed) if parsed.is_finite() && parsed >= 0.0 => *value = parsed,
+                    _ => {
+                        error = Some(format!("invalid {} '{}'", name, text));
+                        break;
+                    }
+                }
+            }
+            let [open, high, low, close, volume] = values;
+            if error.is_none() && (high < low || open > high || open < low || close > high || close < low) {
+                error = Some(format!("inconsistent OHLC: O={} H={} L={} C={}", open, high, low, close));
+            }
+            match error {
+                Some(message) => report.issue(path, Some(line), message),
+                None => rows.push((symbol, Bar::new(timestamp, open, high, low, close, volume), line)),
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Index of `column` by its configured name, then by any alias
+    fn resolve_column(&self, headers: &csv::StringRecord, column: &str) -> Option<usize> {
+        let configured = match column {
+            "date" => &self.format.date_column,
+            "symbol" => &self.format.symbol_column,
+            "open" => &self.format.open_column,
+            "high" => &self.format.high_column,
+            "low" => &self.format.low_column,
+            "close" => &self.format.close_column,
+            _ => &self.format.volume_column,
+        };
+        let built_in = COLUMN_ALIASES
+            .iter()
+            .filter(|(name, _)| *name == column)
+            .flat_map(|(_, aliases)| aliases.iter().map(|alias| alias.to_string()));
+        let names: Vec<String> = std::iter::once(configured.clone())
+            .chain(self.aliases.get(column).into_iter().flatten().cloned())
+            .chain(built_in)
+            .map(|name| normalize(&name))
+            .collect();
+        names
+            .iter()
+            .find_map(|name| headers.iter().position(|h| normalize(h) == *name))
+    }
+
+    /// Date parsers in the order they are tried
+    fn date_candidates(&self) -> Vec<DateParser> {
+        let mut candidates = Vec::new();
+        for format in std::iter::once(&self.format.date_format).chain(&self.date_formats) {
+            candidates.push(DateParser::Date(format.clone()));
+            candidates.push(DateParser::DateTime(format.clone()));
+        }
+        candidates.extend(DATE_FORMATS.iter().map(|f| DateParser::Date(f.to_string())));
+        candidates.extend(DATETIME_FORMATS.iter().map(|f| DateParser::DateTime(f.to_string())));
+        candidates.push(DateParser::Rfc3339);
+        candidates
+    }
+}
+
+/// Header reduced to lower-case letters and digits, so `Adj Close`,
+/// `adj_close` and `AdjClose` match
+fn normalize(header: &str) -> String {
+    header.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// A way of reading a date column
+#[derive(Debug, Clone)]
+enum DateParser {
+    /// Date only, labelled at UTC midnight
+    Date(String),
+    /// Date and time without an offset
+    DateTime(String),
+    /// Date and time with an offset
+    Rfc3339,
+}
+
+impl DateParser {
+    fn parse(&self, text: &str, timezone: Option<Tz>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Date(format) => {
+                Some(NaiveDate::parse_from_str(text, format).ok()?.and_hms_opt(0, 0, 0)?.and_utc())
+            }
+            Self::DateTime(format) => {
+                let local = NaiveDateTime::parse_from_str(text, format).ok()?;
+                match timezone {
+                    Some(tz) => tz.from_local_datetime(&local).single().map(|dt| dt.with_timezone(&Utc)),
+                    None => Some(local.and_utc()),
+                }
+            }
+            Self::Rfc3339 => DateTime::parse_from_rfc3339(text).ok().map(|dt| dt.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Listing details of a symbol from `symbols.csv`
+#[derive(Debug, Clone, Default)]
+struct SymbolMetadata {
+    exchange: Option<String>,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    name: Option<String>,
+}
+
+/// A row or file skipped while loading a directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestIssue {
+    pub file: PathBuf,
+    /// 1-based line, or `None` for problems with the whole file
+    pub line: Option<u64>,
+    pub message: String,
+}
+
+impl fmt::Display for IngestIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// Outcome of [`CSVBundleReader::load_directory`]
+#[derive(Debug, Clone, Default)]
+pub struct IngestReport {
+    /// Symbol files read
+    pub files: usize,
+    /// Bars loaded into the bundle
+    pub rows: usize,
+    /// Skipped rows and files, in the order they were found
+    pub issues: Vec<IngestIssue>,
+}
+
+impl IngestReport {
+    /// Whether every row was loaded
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn issue(&mut self, file: &Path, line: Option<u64>, message: String) {
+        self.issues.push(IngestIssue {
+            file: file.to_path_buf(),
+            line,
+            message,
+        });
+    }
+}
+
+impl fmt::Display for IngestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Loaded {} bars from {} files, {} issues", self.rows, self.files, self.issues.len())?;
+        for issue in &self.issues {
+            writeln!(f, "  {}", issue)?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for CSVBundleReader {
@@ -553,6 +1025,7 @@ mod tests {
 
     #[test]
     fn test_bundle_finalization() {
+        use chrono::DateTime;
         let mut bundle = BundleData::new();
 
         let start_date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
@@ -609,6 +1082,24 @@ mod tests {
         assert_eq!(stats.bar_count, 4);
     }
 
+    #[test]
+    fn test_native_format_round_trip() {
+        let file = create_test_csv();
+        let bundle = CSVBundleReader::new().load_csv(file.path()).unwrap();
+
+        let saved = NamedTempFile::new().unwrap();
+        bundle.save(saved.path()).unwrap();
+        let header: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(saved.path()).unwrap()).unwrap();
+        assert_eq!(header["kind"], "bundle");
+        assert_eq!(header["schema_version"], 1);
+
+        let loaded = BundleData::load(saved.path()).unwrap();
+        assert_eq!(loaded.stats().bar_count, 4);
+        assert_eq!(loaded.date_range(), bundle.date_range());
+        assert_eq!(loaded.get_asset("MSFT"), bundle.get_asset("MSFT"));
+    }
+
     #[test]
     fn test_invalid_ohlc_validation() {
         let mut file = NamedTempFile::new().unwrap();
@@ -655,4 +1146,55 @@ mod tests {
         assert_eq!(stats.asset_count, 1);
         assert_eq!(stats.bar_count, 1);
     }
+
+    #[test]
+    fn test_load_directory_with_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &str| std::fs::write(dir.path().join(name), contents).unwrap();
+        write(
+            "AAPL.csv",
+            "Date,Open,High,Low,Last,Volume\n\
+             01/02/2020,300.35,300.58,298.32,300.35,33911800\n\
+             01/03/2020,297.15,300.58,297.14,297.43,36028600\n\
+             01/06/2020,293.79,299.96,292.75,abc,29596800\n\
+             01/03/2020,297.15,300.58,297.14,297.43,36028600\n\
+             01/07/2020,299.84,300.90,297.48,298.39,27218000\n",
+        );
+        write(
+            "btc.csv",
+            "timestamp,o,h,l,c,vol\n\
+             2024-01-02 09:30:00,42000,42100,41900,42050,1.5\n\
+             2024-01-02 09:31:00,42050,42080,41990,42000,0.75\n",
+        );
+        write("empty.csv", "when,price\n2020-01-02,1.0\n");
+        write(
+            "symbols.csv",
+            "symbol,exchange,start_date,end_date,asset_name\nAAPL,NASDAQ,2020-01-03,,Apple Inc.\n",
+        );
+
+        let mut reader = CSVBundleReader::new()
+            .with_column_alias("close", "Last")
+            .with_timezone(chrono_tz::America::New_York);
+        let (bundle, report) = reader.load_directory(dir.path()).unwrap();
+
+        let aapl = bundle.get_asset("AAPL").unwrap();
+        assert_eq!(aapl.exchange, "NASDAQ");
+        assert_eq!(aapl.name.as_deref(), Some("Apple Inc."));
+        assert_eq!(aapl.start_date, NaiveDate::from_ymd_opt(2020, 1, 3).unwrap());
+        assert_eq!(bundle.get_bars(aapl.id).unwrap().len(), 2);
+
+        // Local exchange times are stored in UTC
+        let btc = bundle.get_asset("btc").unwrap();
+        let bars = bundle.get_bars(btc.id).unwrap();
+        assert_eq!(bars[0].timestamp, Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap());
+        assert_eq!(bars[1].volume, 0.75);
+
+        assert_eq!((report.files, report.rows), (3, 4));
+        let messages: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(report.issues.len(), 4, "{}", report);
+        assert!(messages[0].ends_with("AAPL.csv:4: invalid close 'abc'"));
+        assert!(messages.iter().any(|m| m.contains("outside its listing dates")));
+        assert!(messages.iter().any(|m| m.contains("AAPL.csv:5: duplicate AAPL bar")));
+        assert!(messages.iter().any(|m| m.ends_with("empty.csv: no date column in header")));
+    }
 }
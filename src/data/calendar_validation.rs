@@ -0,0 +1,352 @@
+//! Calendar consistency validation against bundle data
+//!
+//! Compares the sessions actually present in a data bundle with the sessions
+//! expected by a `TradingCalendar`, reporting:
+//! - Sessions present in the data but not in the calendar (unexpected trading days)
+//! - Sessions in the calendar but missing from the data (gaps)
+//!
+//! A `DataDrivenCalendar` can also be derived directly from the bundle, which is
+//! useful for exchanges without a built-in calendar.
+
+use crate::calendar::{SessionTimes, TradingCalendar};
+use crate::data::bundle::BundleData;
+use crate::error::{Result, ZiplineError};
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Result of comparing data sessions against a trading calendar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarValidationReport {
+    /// First session of the compared range
+    pub start: NaiveDate,
+    /// Last session of the compared range
+    pub end: NaiveDate,
+    /// Number of sessions the calendar expects in the range
+    pub expected_sessions: usize,
+    /// Number of sessions found in the data within the range
+    pub actual_sessions: usize,
+    /// Sessions present in the data but not trading days per the calendar
+    pub extra_sessions: Vec<NaiveDate>,
+    /// Calendar trading days with no data
+    pub missing_sessions: Vec<NaiveDate>,
+}
+
+impl CalendarValidationReport {
+    /// Check if data and calendar agree on every session
+    pub fn is_consistent(&self) -> bool {
+        self.extra_sessions.is_empty() && self.missing_sessions.is_empty()
+    }
+
+    /// Total number of mismatched sessions
+    pub fn mismatch_count(&self) -> usize {
+        self.extra_sessions.len() + self.missing_sessions.len()
+    }
+
+    /// Fraction of expected sessions that are present in the data
+    pub fn coverage(&self) -> f64 {
+        if self.expected_sessions == 0 {
+            return 1.0;
+        }
+        let matched = self.expected_sessions - self.missing_sessions.len();
+        matched as f64 / self.expected_sessions as f64
+    }
+}
+
+impl std::fmt::Display for CalendarValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Calendar Validation: {} to {}", self.start, self.end)?;
+        writeln!(f, "  Expected sessions:  {}", self.expected_sessions)?;
+        writeln!(f, "  Actual sessions:    {}", self.actual_sessions)?;
+        writeln!(f, "  Coverage:           {:.2}%", self.coverage() * 100.0)?;
+        writeln!(f, "  Extra sessions:     {}", self.extra_sessions.len())?;
+        for date in &self.extra_sessions {
+            writeln!(f, "    + {}", date)?;
+        }
+        writeln!(f, "  Missing sessions:   {}", self.missing_sessions.len())?;
+        for date in &self.missing_sessions {
+            writeln!(f, "    - {}", date)?;
+        }
+        Ok(())
+    }
+}
+
+/// Validates bundle sessions against a trading calendar
+pub struct CalendarValidator {
+    calendar: Arc<dyn TradingCalendar>,
+}
+
+impl std::fmt::Debug for CalendarValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CalendarValidator")
+            .field("calendar", &"<dyn TradingCalendar>")
+            .finish()
+    }
+}
+
+impl CalendarValidator {
+    /// Create a new validator for the given calendar
+    pub fn new(calendar: Arc<dyn TradingCalendar>) -> Self {
+        Self { calendar }
+    }
+
+    /// Validate all sessions in a bundle over the bundle's own date range
+    pub fn validate_bundle(&self, bundle: &BundleData) -> Result<CalendarValidationReport> {
+        let sessions = bundle.sessions();
+        let (start, end) = match (sessions.first(), sessions.last()) {
+            (Some(start), Some(end)) => (*start, *end),
+            _ => {
+                return Err(ZiplineError::NoDataAvailable);
+            }
+        };
+        self.validate_sessions(&sessions, start, end)
+    }
+
+    /// Validate a bundle over an explicit date range (inclusive)
+    pub fn validate_bundle_range(
+        &self,
+        bundle: &BundleData,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<CalendarValidationReport> {
+        self.validate_sessions(&bundle.sessions(), start, end)
+    }
+
+    /// Validate a list of data sessions over a date range (inclusive)
+    ///
+    /// Sessions outside `[start, end]` are ignored.
+    pub fn validate_sessions(
+        &self,
+        sessions: &[NaiveDate],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<CalendarValidationReport> {
+        if start > end {
+            return Err(ZiplineError::InvalidData(format!(
+                "Invalid validation range: start {} is after end {}",
+                start, end
+            )));
+        }
+
+        let actual: BTreeSet<NaiveDate> = sessions
+            .iter()
+            .copied()
+            .filter(|d| *d >= start && *d <= end)
+            .collect();
+        let expected: BTreeSet<NaiveDate> = self
+            .calendar
+            .trading_days_between(start, end)
+            .into_iter()
+            .collect();
+
+        Ok(CalendarValidationReport {
+            start,
+            end,
+            expected_sessions: expected.len(),
+            actual_sessions: actual.len(),
+            extra_sessions: actual.difference(&expected).copied().collect(),
+            missing_sessions: expected.difference(&actual).copied().collect(),
+        })
+    }
+}
+
+/// Trading calendar derived from the sessions present in data
+///
+/// Every date with at least one bar is a trading day; all others are not.
+#[derive(Debug, Clone)]
+pub struct DataDrivenCalendar {
+    sessions: BTreeSet<NaiveDate>,
+    timezone: Tz,
+    market_open: NaiveTime,
+    market_close: NaiveTime,
+}
+
+impl DataDrivenCalendar {
+    /// Create a calendar from an explicit list of sessions
+    ///
+    /// Session times default to 9:30-16:00 in the given timezone.
+    pub fn new(sessions: impl IntoIterator<Item = NaiveDate>, timezone: Tz) -> Self {
+        Self {
+            sessions: sessions.into_iter().collect(),
+            timezone,
+            market_open: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            market_close: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        }
+    }
+
+    /// Derive a calendar from the sessions present in a bundle
+    pub fn from_bundle(bundle: &BundleData, timezone: Tz) -> Result<Self> {
+        let sessions = bundle.sessions();
+        if sessions.is_empty() {
+            return Err(ZiplineError::NoDataAvailable);
+        }
+        Ok(Self::new(sessions, timezone))
+    }
+
+    /// Set the market open/close times used for every session
+    pub fn with_session_times(mut self, market_open: NaiveTime, market_close: NaiveTime) -> Self {
+        self.market_open = market_open;
+        self.market_close = market_close;
+        self
+    }
+
+    /// Get all sessions in the calendar
+    pub fn sessions(&self) -> Vec<NaiveDate> {
+        self.sessions.iter().copied().collect()
+    }
+
+    /// First session in the calendar
+    pub fn first_session(&self) -> Option<NaiveDate> {
+        self.sessions.iter().next().copied()
+    }
+
+    /// Last session in the calendar
+    pub fn last_session(&self) -> Option<NaiveDate> {
+        self.sessions.iter().next_back().copied()
+    }
+}
+
+impl TradingCalendar for DataDrivenCalendar {
+    fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.sessions.contains(&date)
+    }
+
+    fn session_times(&self, date: NaiveDate) -> Option<SessionTimes> {
+        if self.is_trading_day(date) {
+            Some(SessionTimes {
+                market_open: self.market_open,
+                market_close: self.market_close,
+                is_half_day: false,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn next_trading_day(&self, date: NaiveDate) -> Result<NaiveDate> {
+        self.sessions
+            .range(date.succ_opt().unwrap_or(date)..)
+            .next()
+            .copied()
+            .ok_or_else(|| {
+                ZiplineError::CalendarError(format!("No session in data after {}", date))
+            })
+    }
+
+    fn previous_trading_day(&self, date: NaiveDate) -> Result<NaiveDate> {
+        self.sessions
+            .range(..date)
+            .next_back()
+            .copied()
+            .ok_or_else(|| {
+                ZiplineError::CalendarError(format!("No session in data before {}", date))
+            })
+    }
+
+    fn trading_days_between(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        if start > end {
+            return Vec::new();
+        }
+        self.sessions.range(start..=end).copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::types::Bar;
+
+    fn bundle_with_dates(dates: &[NaiveDate]) -> BundleData {
+        let mut bundle = BundleData::new();
+        let asset = Asset::equity(1, "TEST".to_string(), "NYSE".to_string(), dates[0]);
+        bundle.add_asset("TEST".to_string(), asset);
+        for date in dates {
+            let ts = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            bundle.add_bar(1, Bar::new(ts, 100.0, 101.0, 99.0, 100.5, 1000.0));
+        }
+        bundle.finalize().unwrap();
+        bundle
+    }
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn test_consistent_bundle() {
+        // Mon 2024-01-08 .. Fri 2024-01-12
+        let dates: Vec<NaiveDate> = (8..=12).map(|day| d(2024, 1, day)).collect();
+        let bundle = bundle_with_dates(&dates);
+        let validator = CalendarValidator::new(Arc::new(NYSECalendar::new()));
+
+        let report = validator.validate_bundle(&bundle).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.expected_sessions, 5);
+        assert_eq!(report.actual_sessions, 5);
+        assert_eq!(report.coverage(), 1.0);
+    }
+
+    #[test]
+    fn test_extra_and_missing_sessions() {
+        // Includes Saturday 2024-01-13, skips Wednesday 2024-01-10
+        let dates = vec![d(2024, 1, 8), d(2024, 1, 9), d(2024, 1, 11), d(2024, 1, 12), d(2024, 1, 13)];
+        let bundle = bundle_with_dates(&dates);
+        let validator = CalendarValidator::new(Arc::new(NYSECalendar::new()));
+
+        let report = validator.validate_bundle(&bundle).unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.extra_sessions, vec![d(2024, 1, 13)]);
+        assert_eq!(report.missing_sessions, vec![d(2024, 1, 10)]);
+        assert_eq!(report.mismatch_count(), 2);
+    }
+
+    #[test]
+    fn test_validate_explicit_range() {
+        let dates = vec![d(2024, 1, 9), d(2024, 1, 10)];
+        let bundle = bundle_with_dates(&dates);
+        let validator = CalendarValidator::new(Arc::new(NYSECalendar::new()));
+
+        let report = validator
+            .validate_bundle_range(&bundle, d(2024, 1, 8), d(2024, 1, 12))
+            .unwrap();
+        assert_eq!(report.missing_sessions, vec![d(2024, 1, 8), d(2024, 1, 11), d(2024, 1, 12)]);
+
+        assert!(validator
+            .validate_bundle_range(&bundle, d(2024, 1, 12), d(2024, 1, 8))
+            .is_err());
+    }
+
+    #[test]
+    fn test_empty_bundle_errors() {
+        let validator = CalendarValidator::new(Arc::new(NYSECalendar::new()));
+        assert!(validator.validate_bundle(&BundleData::new()).is_err());
+    }
+
+    #[test]
+    fn test_data_driven_calendar() {
+        let dates = vec![d(2024, 1, 6), d(2024, 1, 8), d(2024, 1, 10)];
+        let bundle = bundle_with_dates(&dates);
+        let calendar = DataDrivenCalendar::from_bundle(&bundle, chrono_tz::UTC).unwrap();
+
+        assert!(calendar.is_trading_day(d(2024, 1, 6)));
+        assert!(!calendar.is_trading_day(d(2024, 1, 7)));
+        assert_eq!(calendar.next_trading_day(d(2024, 1, 8)).unwrap(), d(2024, 1, 10));
+        assert_eq!(calendar.previous_trading_day(d(2024, 1, 8)).unwrap(), d(2024, 1, 6));
+        assert!(calendar.next_trading_day(d(2024, 1, 10)).is_err());
+        assert_eq!(calendar.trading_days_count(d(2024, 1, 1), d(2024, 1, 31)), 3);
+        assert_eq!(calendar.first_session(), Some(d(2024, 1, 6)));
+        assert_eq!(calendar.last_session(), Some(d(2024, 1, 10)));
+
+        // A bundle validates perfectly against its own derived calendar
+        let validator = CalendarValidator::new(Arc::new(calendar));
+        assert!(validator.validate_bundle(&bundle).unwrap().is_consistent());
+    }
+}
@@ -194,8 +194,13 @@ impl ContractChain {
 pub struct RollSchedule {
     /// Style of roll
     pub style: RollStyle,
-    /// Days before expiration to roll (for Calendar style)
+    /// Days before expiration to roll (for Calendar style). For Volume and
+    /// OpenInterest styles this acts as a backstop so a contract is never
+    /// held into expiration when the back month never takes over.
     pub days_before_expiration: i32,
+    /// Number of consecutive sessions the back month must lead the front
+    /// month before a Volume/OpenInterest roll is confirmed
+    pub confirmation_window: usize,
 }
 
 impl Default for RollSchedule {
@@ -203,6 +208,7 @@ impl Default for RollSchedule {
         Self {
             style: RollStyle::Calendar,
             days_before_expiration: 5, // Roll 5 days before expiration
+            confirmation_window: 1,
         }
     }
 }
@@ -213,6 +219,7 @@ impl RollSchedule {
         Self {
             style: RollStyle::Calendar,
             days_before_expiration,
+            confirmation_window: 1,
         }
     }
 
@@ -221,6 +228,7 @@ impl RollSchedule {
         Self {
             style: RollStyle::Volume,
             days_before_expiration: 0,
+            confirmation_window: 1,
         }
     }
 
@@ -229,28 +237,79 @@ impl RollSchedule {
         Self {
             style: RollStyle::OpenInterest,
             days_before_expiration: 0,
+            confirmation_window: 1,
         }
     }
 
-    /// Determine if we should roll at this date
+    /// Set the number of consecutive sessions required to confirm a roll
+    pub fn with_confirmation_window(mut self, sessions: usize) -> Self {
+        self.confirmation_window = sessions.max(1);
+        self
+    }
+
+    /// Determine if we should roll at this date using front month data only
+    ///
+    /// Volume and OpenInterest styles need back month data to detect a
+    /// crossover, so without it only the expiration backstop applies. Use
+    /// [`RollSchedule::should_roll_with_next`] when back month bars are available.
     pub fn should_roll(&self, contract: &FutureContract, dt: DateTime<Utc>, _bars: &[Bar]) -> bool {
+        let days_to_exp = contract.days_until_expiration(dt);
+        days_to_exp <= self.days_before_expiration as i64
+    }
+
+    /// Determine if we should roll at this date given front and back month history
+    ///
+    /// `front_bars` and `back_bars` are the trailing bars of the current and
+    /// next contract, oldest first. Bars are matched by timestamp; a roll is
+    /// signalled once the back month's volume (or open interest) has exceeded
+    /// the front month's on each of the last `confirmation_window` sessions.
+    pub fn should_roll_with_next(
+        &self,
+        contract: &FutureContract,
+        dt: DateTime<Utc>,
+        front_bars: &[Bar],
+        back_bars: &[Bar],
+    ) -> bool {
+        if self.should_roll(contract, dt, front_bars) {
+            return true;
+        }
+
         match self.style {
-            RollStyle::Calendar => {
-                let days_to_exp = contract.days_until_expiration(dt);
-                days_to_exp <= self.days_before_expiration as i64
-            }
-            RollStyle::Volume => {
-                // Would need current and next contract volumes
-                // Simplified: always false for now
-                false
-            }
-            RollStyle::OpenInterest => {
-                // Would need open interest data
-                // Simplified: always false for now
-                false
+            RollStyle::Calendar => false,
+            RollStyle::Volume | RollStyle::OpenInterest => {
+                self.back_month_leads(front_bars, back_bars)
             }
         }
     }
+
+    /// Roll metric for a bar under this schedule's style
+    fn roll_metric(&self, bar: &Bar) -> Option<f64> {
+        match self.style {
+            RollStyle::Calendar => None,
+            RollStyle::Volume => Some(bar.volume),
+            RollStyle::OpenInterest => bar.open_interest,
+        }
+    }
+
+    /// Check whether the back month led the front month over the whole window
+    fn back_month_leads(&self, front_bars: &[Bar], back_bars: &[Bar]) -> bool {
+        let window = self.confirmation_window.max(1);
+        if front_bars.len() < window {
+            return false;
+        }
+
+        front_bars[front_bars.len() - window..].iter().all(|front| {
+            let back = match back_bars.iter().rev().find(|b| b.dt == front.dt) {
+                Some(back) => back,
+                None => return false,
+            };
+
+            match (self.roll_metric(front), self.roll_metric(back)) {
+                (Some(front_value), Some(back_value)) => back_value > front_value,
+                _ => false,
+            }
+        })
+    }
 }
 
 /// Continuous futures reader trait
@@ -327,68 +386,110 @@ impl DefaultContinuousFutureReader {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
         offset: i32,
+        roll_style: RollStyle,
         adjustment: AdjustmentStyle,
     ) -> Result<Vec<Bar>> {
+        let contracts = chain.contracts();
+        let schedule = RollSchedule {
+            style: roll_style,
+            ..self.roll_schedule.clone()
+        };
         let mut result = Vec::new();
         let mut current_date = start;
         let mut adjustment_ratio = 1.0;
 
+        // Index of the front contract after any early rolls
+        let mut rolled_front_idx = 0;
+        let mut tracked_idx = None;
+        let mut front_history: Vec<Bar> = Vec::new();
+        let mut back_history: Vec<Bar> = Vec::new();
+
         while current_date <= end {
-            // Get active contract for this date
-            if let Some(contract) = chain.get_contract_at(current_date, offset) {
-                // Create mock asset for bar reader
-                let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
-                let asset = crate::asset::Asset::new(
-                    contract.asset_id,
-                    contract.symbol.clone(),
-                    "FUTURES".to_string(),
-                    crate::asset::AssetType::Future,
-                    start_date,
-                );
-
-                // Get bar for this contract
-                if let Ok(mut bar) = self.bar_reader.get_bar(&asset, current_date) {
-                    // Apply adjustment if needed
-                    if adjustment != AdjustmentStyle::None {
-                        bar = self.apply_adjustment(bar, adjustment_ratio);
+            let natural_front_idx = contracts.iter().position(|c| !c.is_expired(current_date));
+
+            if let Some(natural_front_idx) = natural_front_idx {
+                let front_idx = natural_front_idx.max(rolled_front_idx);
+                let target_idx = front_idx as i64 + offset as i64;
+
+                if target_idx >= 0 && (target_idx as usize) < contracts.len() {
+                    let target_idx = target_idx as usize;
+                    let contract = &contracts[target_idx];
+
+                    // Reset roll history whenever the active contract changes
+                    if tracked_idx != Some(target_idx) {
+                        tracked_idx = Some(target_idx);
+                        front_history.clear();
+                        back_history.clear();
                     }
 
-                    result.push(bar);
-
-                    // Check if we need to roll
-                    if self.roll_schedule.should_roll(contract, current_date, &[bar]) {
-                        // Calculate adjustment for next contract
-                        if adjustment == AdjustmentStyle::PanamaCanal
-                            || adjustment == AdjustmentStyle::BackwardRatio
-                        {
-                            if let Some(next_contract) = chain.get_contract_at(current_date, offset + 1) {
-                                let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
-                                let next_asset = crate::asset::Asset::new(
-                                    next_contract.asset_id,
-                                    next_contract.symbol.clone(),
-                                    "FUTURES".to_string(),
-                                    crate::asset::AssetType::Future,
-                                    start_date,
-                                );
-
-                                if let Ok(next_bar) = self.bar_reader.get_bar(&next_asset, current_date) {
+                    // Get bar for this contract
+                    if let Ok(raw_bar) = self.bar_reader.get_bar(&Self::contract_asset(contract), current_date) {
+                        let mut bar = raw_bar;
+
+                        // Apply adjustment if needed
+                        if adjustment != AdjustmentStyle::None {
+                            bar = self.apply_adjustment(bar, adjustment_ratio);
+                        }
+
+                        result.push(bar);
+                        front_history.push(raw_bar);
+
+                        let next_bar = contracts.get(target_idx + 1).and_then(|next_contract| {
+                            self.bar_reader
+                                .get_bar(&Self::contract_asset(next_contract), current_date)
+                                .ok()
+                        });
+                        if let Some(next_bar) = next_bar {
+                            back_history.push(next_bar);
+                        }
+
+                        // Check if we need to roll (takes effect from the next session)
+                        if schedule.should_roll_with_next(
+                            contract,
+                            current_date,
+                            &front_history,
+                            &back_history,
+                        ) {
+                            // Calculate adjustment for next contract
+                            if adjustment == AdjustmentStyle::PanamaCanal
+                                || adjustment == AdjustmentStyle::BackwardRatio
+                            {
+                                if let Some(next_bar) = next_bar {
                                     // Calculate ratio
-                                    let ratio = next_bar.close / bar.close;
+                                    let ratio = next_bar.close / raw_bar.close;
                                     adjustment_ratio *= ratio;
                                 }
                             }
+
+                            // Calendar chains hold each contract until it expires; only
+                            // the activity-based styles switch to the back month early
+                            if roll_style != RollStyle::Calendar {
+                                rolled_front_idx = front_idx + 1;
+                            }
                         }
                     }
                 }
             }
 
             // Move to next day (simplified - should use trading calendar)
-            current_date = current_date + chrono::Duration::days(1);
+            current_date += chrono::Duration::days(1);
         }
 
         Ok(result)
     }
 
+    /// Create asset handle for bar reader lookups
+    fn contract_asset(contract: &FutureContract) -> crate::asset::Asset {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        crate::asset::Asset::new(
+            contract.asset_id,
+            contract.symbol.clone(),
+            "FUTURES".to_string(),
+            crate::asset::AssetType::Future,
+            start_date,
+        )
+    }
+
     /// Apply adjustment to a bar
     fn apply_adjustment(&self, mut bar: Bar, ratio: f64) -> Bar {
         bar.open *= ratio;
@@ -431,7 +532,14 @@ impl DefaultContinuousFutureReader {
         // Try to get the first available chain if there's only one
         if self.chains.len() == 1 {
             let (root_symbol, chain) = self.chains.iter().next().unwrap();
-            self.build_continuous_series(chain, start, end, 0, AdjustmentStyle::None)
+            self.build_continuous_series(
+                chain,
+                start,
+                end,
+                0,
+                self.roll_schedule.style,
+                AdjustmentStyle::None,
+            )
         } else {
             Err(ZiplineError::InvalidData(
                 "Multiple chains present. Use ContinuousFutureReader trait method with root_symbol parameter".to_string()
@@ -447,7 +555,7 @@ impl ContinuousFutureReader for DefaultContinuousFutureReader {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
         offset: i32,
-        _roll_style: RollStyle,
+        roll_style: RollStyle,
         adjustment: AdjustmentStyle,
     ) -> Result<Vec<Bar>> {
         let chain = self
@@ -460,7 +568,7 @@ impl ContinuousFutureReader for DefaultContinuousFutureReader {
                 ))
             })?;
 
-        self.build_continuous_series(chain, start, end, offset, adjustment)
+        self.build_continuous_series(chain, start, end, offset, roll_style, adjustment)
     }
 
     fn get_active_contract(
@@ -487,6 +595,7 @@ impl ContinuousFutureReader for DefaultContinuousFutureReader {
 mod tests {
     use super::*;
     use crate::asset::{Asset, AssetType};
+    use chrono::Datelike;
 
     // Mock bar reader for testing
     struct MockBarReader {
@@ -534,6 +643,18 @@ mod tests {
         fn first_available_dt(&self, _asset: &Asset) -> Result<DateTime<Utc>> {
             Ok(Utc::now())
         }
+
+        fn sessions(&self) -> Result<Vec<crate::data::bar_reader::SessionLabel>> {
+            let days: std::collections::BTreeSet<_> =
+                self.data.keys().map(|(_, dt)| dt.date_naive()).collect();
+            Ok(days
+                .into_iter()
+                .map(|day| {
+                    let midnight = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+                    crate::data::bar_reader::SessionLabel::from_datetime(midnight)
+                })
+                .collect())
+        }
     }
 
     fn create_test_bar(dt: DateTime<Utc>, close: f64) -> Bar {
@@ -783,7 +904,7 @@ mod tests {
         reader.add_chain(chain);
 
         let dt = Utc.with_ymd_and_hms(2023, 3, 10, 0, 0, 0).unwrap();
-        let contract = reader.get_active_contract("ES", dt, 0).unwrap();
+        let contract = ContinuousFutureReader::get_active_contract(&reader, "ES", dt, 0).unwrap();
 
         assert!(contract.is_some());
         assert_eq!(contract.unwrap().symbol, "ESH3");
@@ -806,4 +927,164 @@ mod tests {
         assert_eq!(adjusted.close, 113.3);
         assert_eq!(adjusted.volume, 1000.0); // Volume unchanged
     }
+
+    fn create_activity_bar(dt: DateTime<Utc>, close: f64, volume: f64, open_interest: f64) -> Bar {
+        Bar::new(close, close, close, close, volume, dt).with_open_interest(open_interest)
+    }
+
+    /// Two-contract ES chain (H3 expiring 2023-03-17, M3 expiring 2023-06-16)
+    fn create_es_chain() -> ContractChain {
+        use chrono::TimeZone;
+        let mut chain = ContractChain::new("ES".to_string());
+        chain.add_contract(FutureContract::new(
+            "ESH3".to_string(),
+            "ES".to_string(),
+            Utc.with_ymd_and_hms(2023, 3, 17, 0, 0, 0).unwrap(),
+            "H3".to_string(),
+            1001,
+        )).unwrap();
+        chain.add_contract(FutureContract::new(
+            "ESM3".to_string(),
+            "ES".to_string(),
+            Utc.with_ymd_and_hms(2023, 6, 16, 0, 0, 0).unwrap(),
+            "M3".to_string(),
+            1002,
+        )).unwrap();
+        chain
+    }
+
+    /// Daily bars from 2023-03-01 to 2023-03-10 where M3 volume overtakes H3
+    /// on 2023-03-06 and M3 open interest overtakes H3 on 2023-03-08
+    fn create_es_roll_reader() -> MockBarReader {
+        use chrono::TimeZone;
+        let mut reader = MockBarReader::new();
+        for day in 1..=10 {
+            let dt = Utc.with_ymd_and_hms(2023, 3, day, 0, 0, 0).unwrap();
+            let (front_volume, back_volume) = if day >= 6 { (400.0, 900.0) } else { (900.0, 400.0) };
+            let (front_oi, back_oi) = if day >= 8 { (1000.0, 3000.0) } else { (3000.0, 1000.0) };
+            reader.insert(1001, create_activity_bar(dt, 4000.0, front_volume, front_oi));
+            reader.insert(1002, create_activity_bar(dt, 4040.0, back_volume, back_oi));
+        }
+        reader
+    }
+
+    #[test]
+    fn test_roll_schedule_volume_crossover() {
+        use chrono::TimeZone;
+        let schedule = RollSchedule::volume();
+        let chain = create_es_chain();
+        let contract = &chain.contracts()[0];
+        let dt = Utc.with_ymd_and_hms(2023, 3, 6, 0, 0, 0).unwrap();
+
+        let front = [create_activity_bar(dt, 4000.0, 500.0, 0.0)];
+        let back_lagging = [create_activity_bar(dt, 4040.0, 300.0, 0.0)];
+        let back_leading = [create_activity_bar(dt, 4040.0, 700.0, 0.0)];
+
+        assert!(!schedule.should_roll_with_next(contract, dt, &front, &back_lagging));
+        assert!(schedule.should_roll_with_next(contract, dt, &front, &back_leading));
+
+        // Missing back month data never triggers a crossover
+        assert!(!schedule.should_roll_with_next(contract, dt, &front, &[]));
+    }
+
+    #[test]
+    fn test_roll_schedule_confirmation_window() {
+        use chrono::TimeZone;
+        let schedule = RollSchedule::volume().with_confirmation_window(2);
+        let chain = create_es_chain();
+        let contract = &chain.contracts()[0];
+        let day1 = Utc.with_ymd_and_hms(2023, 3, 6, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2023, 3, 7, 0, 0, 0).unwrap();
+
+        let front = [
+            create_activity_bar(day1, 4000.0, 500.0, 0.0),
+            create_activity_bar(day2, 4000.0, 500.0, 0.0),
+        ];
+        let back = [
+            create_activity_bar(day1, 4040.0, 400.0, 0.0),
+            create_activity_bar(day2, 4040.0, 700.0, 0.0),
+        ];
+
+        // Only one session of leadership - not yet confirmed
+        assert!(!schedule.should_roll_with_next(contract, day2, &front, &back));
+
+        let back_confirmed = [
+            create_activity_bar(day1, 4040.0, 600.0, 0.0),
+            create_activity_bar(day2, 4040.0, 700.0, 0.0),
+        ];
+        assert!(schedule.should_roll_with_next(contract, day2, &front, &back_confirmed));
+    }
+
+    #[test]
+    fn test_roll_schedule_expiration_backstop() {
+        use chrono::TimeZone;
+        let schedule = RollSchedule::open_interest();
+        let chain = create_es_chain();
+        let contract = &chain.contracts()[0];
+        let expiry = Utc.with_ymd_and_hms(2023, 3, 17, 0, 0, 0).unwrap();
+
+        // No open interest data at all - roll still happens at expiration
+        assert!(schedule.should_roll(contract, expiry, &[]));
+        assert!(schedule.should_roll_with_next(contract, expiry, &[], &[]));
+    }
+
+    #[test]
+    fn test_volume_roll_known_dates() {
+        use chrono::TimeZone;
+        let mut reader = DefaultContinuousFutureReader::new(Arc::new(create_es_roll_reader()));
+        reader.add_chain(create_es_chain());
+        reader.set_roll_schedule(RollSchedule::volume().with_confirmation_window(2));
+
+        let start = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2023, 3, 10, 0, 0, 0).unwrap();
+        let series = ContinuousFutureReader::get_continuous_prices(
+            &reader, "ES", start, end, 0, RollStyle::Volume, AdjustmentStyle::None,
+        ).unwrap();
+
+        assert_eq!(series.len(), 10);
+        // Crossover on 03-06, confirmed on 03-07, so M3 is held from 03-08
+        for bar in &series {
+            let expected = if bar.dt.day() >= 8 { 4040.0 } else { 4000.0 };
+            assert_eq!(bar.close, expected, "unexpected contract on {}", bar.dt);
+        }
+    }
+
+    #[test]
+    fn test_open_interest_roll_known_dates() {
+        use chrono::TimeZone;
+        let mut reader = DefaultContinuousFutureReader::new(Arc::new(create_es_roll_reader()));
+        reader.add_chain(create_es_chain());
+        reader.set_roll_schedule(RollSchedule::open_interest());
+
+        let start = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2023, 3, 10, 0, 0, 0).unwrap();
+        let series = ContinuousFutureReader::get_continuous_prices(
+            &reader, "ES", start, end, 0, RollStyle::OpenInterest, AdjustmentStyle::None,
+        ).unwrap();
+
+        // Open interest crosses on 03-08, so M3 is held from 03-09
+        for bar in &series {
+            let expected = if bar.dt.day() >= 9 { 4040.0 } else { 4000.0 };
+            assert_eq!(bar.close, expected, "unexpected contract on {}", bar.dt);
+            assert!(bar.open_interest.is_some());
+        }
+    }
+
+    #[test]
+    fn test_calendar_roll_ignores_activity_crossover() {
+        use chrono::TimeZone;
+        let mut reader = DefaultContinuousFutureReader::new(Arc::new(create_es_roll_reader()));
+        reader.add_chain(create_es_chain());
+        reader.set_roll_schedule(RollSchedule::volume());
+
+        let start = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2023, 3, 10, 0, 0, 0).unwrap();
+        let series = ContinuousFutureReader::get_continuous_prices(
+            &reader, "ES", start, end, 0, RollStyle::Calendar, AdjustmentStyle::None,
+        ).unwrap();
+
+        // H3 is held to expiration even though M3 leads on volume and open interest
+        assert_eq!(series.len(), 10);
+        assert!(series.iter().all(|bar| bar.close == 4000.0));
+    }
 }
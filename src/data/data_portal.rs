@@ -2,11 +2,17 @@
 //!
 //! The DataPortal provides a single interface for accessing all market data,
 //! handling multi-frequency data, adjustments, and current/historical queries.
+//!
+//! Minute data is read through immutable per-session frames (see
+//! [`crate::data::session_frame`]), so concurrent readers share one copy of each
+//! session instead of each going back to the reader.
 
 use crate::asset::Asset;
 use crate::data::adjustments::{Adjustment, AdjustmentReader};
 use crate::data::frequency::DataFrequency;
+use crate::data::session_frame::{FrameCache, FrameSnapshot, SessionFrame};
 use crate::error::{Result, ZiplineError};
+use crate::types::{self, MinuteId, SessionId};
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -19,6 +25,24 @@ pub struct Bar {
     pub low: f64,
     pub close: f64,
     pub volume: f64,
+    pub dt: DateTime<Utc>,
+}
+
+/// Which price series a query returns
+///
+/// Raw and adjusted prices must not be mixed: sizing an order from adjusted
+/// history, or computing returns across a split from raw history, silently
+/// produces wrong numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PriceView {
+    /// Prices exactly as traded on each bar's date.
+    /// Use for order sizing, limit/stop prices and tick rounding.
+    Raw,
+    /// Prices adjusted for splits and dividends as of the query datetime,
+    /// so history is comparable with the current price.
+    /// Use for returns, factors and indicators.
+    #[default]
+    Adjusted,
 }
 
 /// DataFrame-like structure for historical data
@@ -89,6 +113,9 @@ pub struct DataPortal {
 
     /// Trading calendar (for business days)
     trading_days: Vec<DateTime<Utc>>,
+
+    /// Minute sessions published for concurrent readers
+    minute_frames: FrameCache,
 }
 
 impl std::fmt::Debug for DataPortal {
@@ -99,11 +126,15 @@ impl std::fmt::Debug for DataPortal {
             .field("adjustment_reader", &if self.adjustment_reader.is_some() { "<Some(AdjustmentReader)>" } else { "None" })
             .field("default_frequency", &self.default_frequency)
             .field("trading_days", &format!("{} days", self.trading_days.len()))
+            .field("minute_frames", &format!("{} frames", self.minute_frames.len()))
             .finish()
     }
 }
 
 impl DataPortal {
+    /// Minute session frames kept published at once
+    pub const MINUTE_FRAME_CAPACITY: usize = 512;
+
     /// Create a new DataPortal
     pub fn new(
         daily_reader: Option<Arc<dyn BarReader>>,
@@ -117,9 +148,63 @@ impl DataPortal {
             adjustment_reader,
             default_frequency: DataFrequency::Daily,
             trading_days,
+            minute_frames: FrameCache::new(Self::MINUTE_FRAME_CAPACITY),
         }
     }
 
+    /// Immutable minute bars for one asset and session
+    ///
+    /// Loaded from the minute reader on first use and published for every
+    /// other caller; the frame can be shared freely between threads.
+    /// Sessions are labelled by UTC date, as in the minute bar stores.
+    pub fn minute_session_frame(&self, asset_id: u64, session: SessionId) -> Result<Arc<SessionFrame>> {
+        let reader = self
+            .minute_reader
+            .as_ref()
+            .ok_or(ZiplineError::PricingDataNotLoaded { assets: vec![asset_id] })?;
+
+        self.minute_frames.get_or_load(asset_id, session, || {
+            let start = session.date().and_hms_opt(0, 0, 0).unwrap().and_utc();
+            let end = start + chrono::Duration::days(1) - chrono::Duration::nanoseconds(1);
+            let bars = reader.get_bars(asset_id, start, end)?;
+            SessionFrame::from_rows(
+                asset_id,
+                session,
+                bars.into_iter()
+                    .filter(|bar| SessionId::utc_label_of(bar.dt) == session)
+                    .map(|bar| (bar.dt, [bar.open, bar.high, bar.low, bar.close, bar.volume])),
+            )
+        })
+    }
+
+    /// The minute session frames published so far, as one consistent view
+    pub fn minute_frames(&self) -> FrameSnapshot {
+        self.minute_frames.snapshot()
+    }
+
+    /// Minute bars with `start <= dt <= end`, read from session frames
+    fn minute_bars(&self, asset_id: u64, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Bar>> {
+        let mut bars = Vec::new();
+        let (first, last) = (MinuteId::containing(start), MinuteId::containing(end));
+        let mut session = SessionId::utc_label_of(start);
+        while session <= SessionId::utc_label_of(end) {
+            let frame = self.minute_session_frame(asset_id, session)?;
+            let column = |field: &str| frame.column(field).unwrap_or_default();
+            let (open, high, low, close, volume) =
+                (column("open"), column("high"), column("low"), column("close"), column("volume"));
+            bars.extend(frame.range(first, last).map(|i| Bar {
+                open: open[i],
+                high: high[i],
+                low: low[i],
+                close: close[i],
+                volume: volume[i],
+                dt: frame.timestamp(i),
+            }));
+            session = session.next_day();
+        }
+        Ok(bars)
+    }
+
     /// Get current value for a single asset and field
     ///
     /// # Arguments
@@ -135,8 +220,15 @@ impl DataPortal {
         // Map "price" to "close"
         let actual_field = if field == "price" { "close" } else { field };
 
-        // Try minute reader first if available
+        // Try minute data first if available, from the session's frame
         if let Some(minute_reader) = &self.minute_reader {
+            let from_frame = self
+                .minute_session_frame(asset.id, SessionId::utc_label_of(dt))
+                .ok()
+                .and_then(|frame| frame.value_at(actual_field, MinuteId::containing(dt)));
+            if let Some(value) = from_frame {
+                return Ok(Some(value));
+            }
             if let Ok(Some(value)) = minute_reader.get_value(asset.id, dt, actual_field) {
                 return Ok(Some(value));
             }
@@ -186,6 +278,9 @@ impl DataPortal {
 
     /// Get historical data for assets
     ///
+    /// Equivalent to [`DataPortal::adjusted_history`]. Prefer calling
+    /// `adjusted_history` or `raw_history` directly to make the view explicit.
+    ///
     /// # Arguments
     /// * `assets` - Assets to query
     /// * `fields` - Field names
@@ -199,6 +294,56 @@ impl DataPortal {
         bar_count: usize,
         frequency: DataFrequency,
         dt: DateTime<Utc>,
+    ) -> Result<HashMap<String, DataFrame>> {
+        self.history_with_view(assets, fields, bar_count, frequency, dt, PriceView::Adjusted)
+    }
+
+    /// Get historical data exactly as traded
+    ///
+    /// Use for order sizing and price rounding, never for returns across
+    /// corporate actions.
+    pub fn raw_history(
+        &self,
+        assets: &[Asset],
+        fields: &[&str],
+        bar_count: usize,
+        frequency: DataFrequency,
+        dt: DateTime<Utc>,
+    ) -> Result<HashMap<String, DataFrame>> {
+        self.history_with_view(assets, fields, bar_count, frequency, dt, PriceView::Raw)
+    }
+
+    /// Get historical data adjusted for splits and dividends as of `dt`
+    ///
+    /// Use for returns, factors and indicators, never for order sizing.
+    pub fn adjusted_history(
+        &self,
+        assets: &[Asset],
+        fields: &[&str],
+        bar_count: usize,
+        frequency: DataFrequency,
+        dt: DateTime<Utc>,
+    ) -> Result<HashMap<String, DataFrame>> {
+        self.history_with_view(assets, fields, bar_count, frequency, dt, PriceView::Adjusted)
+    }
+
+    /// Get historical data for assets in the requested price view
+    ///
+    /// # Arguments
+    /// * `assets` - Assets to query
+    /// * `fields` - Field names
+    /// * `bar_count` - Number of bars to retrieve
+    /// * `frequency` - Data frequency (minute or daily)
+    /// * `dt` - End datetime
+    /// * `view` - Raw or adjusted prices
+    pub fn history_with_view(
+        &self,
+        assets: &[Asset],
+        fields: &[&str],
+        bar_count: usize,
+        frequency: DataFrequency,
+        dt: DateTime<Utc>,
+        view: PriceView,
     ) -> Result<HashMap<String, DataFrame>> {
         let mut result = HashMap::new();
 
@@ -222,14 +367,24 @@ impl DataPortal {
         // Calculate start datetime
         let start = self.get_history_start(dt, bar_count, frequency)?;
 
+        // Load bars once per asset in the requested view
+        let mut asset_bars = Vec::with_capacity(assets.len());
+        for asset in assets {
+            let mut bars = match frequency {
+                DataFrequency::Minute => self.minute_bars(asset.id, start, dt)?,
+                _ => reader.get_bars(asset.id, start, dt)?,
+            };
+            if view == PriceView::Adjusted {
+                self.adjust_bars(&mut bars, asset.id, dt);
+            }
+            asset_bars.push((asset.id, bars));
+        }
+
         for &field in fields {
             let mut df = DataFrame::new();
             df.columns = vec![field.to_string()];
 
-            for asset in assets {
-                // Get bars for this asset
-                let bars = reader.get_bars(asset.id, start, dt)?;
-
+            for (asset_id, bars) in &asset_bars {
                 // Extract field values
                 let values: Vec<f64> = bars
                     .iter()
@@ -243,14 +398,12 @@ impl DataPortal {
                     })
                     .collect();
 
-                df.data.insert(asset.id, values);
+                df.data.insert(*asset_id, values);
             }
 
             // Use first asset's bar timestamps as index
-            if let Some(first_asset) = assets.first() {
-                let bars = reader.get_bars(first_asset.id, start, dt)?;
-                // Timestamps would come from bars - simplified here
-                df.index = vec![dt; bars.len()];
+            if let Some((_, bars)) = asset_bars.first() {
+                df.index = bars.iter().map(|bar| bar.dt).collect();
             }
 
             result.insert(field.to_string(), df);
@@ -259,6 +412,39 @@ impl DataPortal {
         Ok(result)
     }
 
+    /// Get the current price exactly as traded
+    ///
+    /// This is the price to size orders and round limit/stop prices against.
+    pub fn raw_price(&self, asset: &Asset, dt: DateTime<Utc>) -> Result<Option<f64>> {
+        self.current_value(asset, "close", dt)
+    }
+
+    /// Adjust bars for corporate actions effective after each bar and on or before `as_of`
+    fn adjust_bars(&self, bars: &mut [Bar], asset_id: u64, as_of: DateTime<Utc>) {
+        let adjustment_reader = match &self.adjustment_reader {
+            Some(reader) => reader,
+            None => return,
+        };
+
+        for bar in bars.iter_mut() {
+            let mut adjusted = types::Bar {
+                timestamp: bar.dt,
+                open: bar.open,
+                high: bar.high,
+                low: bar.low,
+                close: bar.close,
+                volume: bar.volume,
+            };
+            adjustment_reader.apply_adjustments_to_bar(&mut adjusted, asset_id, as_of);
+
+            bar.open = adjusted.open;
+            bar.high = adjusted.high;
+            bar.low = adjusted.low;
+            bar.close = adjusted.close;
+            bar.volume = adjusted.volume;
+        }
+    }
+
     /// Get spot value (single point-in-time lookup)
     pub fn get_spot_value(
         &self,
@@ -353,10 +539,11 @@ impl DataPortal {
                     .position(|&d| d >= end)
                     .unwrap_or(self.trading_days.len() - 1);
 
-                let start_idx = end_idx.saturating_sub(bar_count);
+                // The window includes the end session itself
+                let start_idx = (end_idx + 1).saturating_sub(bar_count);
 
                 // Check if we have enough data
-                if start_idx == 0 && end_idx < bar_count {
+                if end_idx + 1 < bar_count {
                     let first_available = self.trading_days.first().copied().unwrap_or(end);
                     return Err(ZiplineError::HistoryWindowBeforeFirstData {
                         asset: 0, // Generic - specific asset would be passed in real implementation
@@ -423,6 +610,7 @@ mod tests {
                 low: 99.0,
                 close: 103.0,
                 volume: 1000000.0,
+                dt: Utc::now(),
             }])
         }
     }
@@ -446,6 +634,9 @@ mod tests {
             asset_type: AssetType::Equity,
             exchange: "NASDAQ".to_string(),
             name: None,
+            start_date: chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            end_date: None,
+            auto_close_date: None,
         }
     }
 
@@ -521,4 +712,144 @@ mod tests {
         portal.set_default_frequency(DataFrequency::Minute);
         assert_eq!(portal.default_frequency(), DataFrequency::Minute);
     }
+
+    struct HistoryBarReader {
+        bars: Vec<Bar>,
+    }
+
+    impl BarReader for HistoryBarReader {
+        fn get_value(&self, _asset_id: u64, dt: DateTime<Utc>, field: &str) -> Result<Option<f64>> {
+            Ok(self
+                .bars
+                .iter()
+                .find(|bar| bar.dt == dt)
+                .filter(|_| field == "close")
+                .map(|bar| bar.close))
+        }
+
+        fn get_last_traded_dt(&self, _asset_id: u64, dt: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+            Ok(Some(dt))
+        }
+
+        fn get_bars(&self, _asset_id: u64, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Bar>> {
+            Ok(self
+                .bars
+                .iter()
+                .filter(|bar| bar.dt >= start && bar.dt <= end)
+                .cloned()
+                .collect())
+        }
+    }
+
+    /// Five daily sessions with a 2:1 split effective on the third
+    fn create_split_portal() -> (DataPortal, Vec<DateTime<Utc>>) {
+        use crate::data::adjustments::AdjustmentKind;
+        use chrono::TimeZone;
+
+        let days: Vec<DateTime<Utc>> = (2..=6)
+            .map(|d| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap())
+            .collect();
+        let bars = days
+            .iter()
+            .enumerate()
+            .map(|(i, &dt)| {
+                let close = if i < 2 { 100.0 } else { 50.0 };
+                Bar { open: close, high: close, low: close, close, volume: 1000.0, dt }
+            })
+            .collect();
+
+        let mut adjustments = AdjustmentReader::new();
+        adjustments.add_adjustment(Adjustment::new(1, days[2], AdjustmentKind::Split { ratio: 2.0 }));
+
+        let portal = DataPortal::new(
+            Some(Arc::new(HistoryBarReader { bars })),
+            None,
+            Some(Arc::new(adjustments)),
+            days.clone(),
+        );
+        (portal, days)
+    }
+
+    #[test]
+    fn test_raw_and_adjusted_history() {
+        let (portal, days) = create_split_portal();
+        let asset = create_test_asset();
+        let dt = days[4];
+
+        let raw = portal
+            .raw_history(&[asset.clone()], &["close", "volume"], 4, DataFrequency::Daily, dt)
+            .unwrap();
+        let adjusted = portal
+            .adjusted_history(&[asset.clone()], &["close", "volume"], 4, DataFrequency::Daily, dt)
+            .unwrap();
+
+        assert_eq!(raw["close"].data[&1], vec![100.0, 50.0, 50.0, 50.0]);
+        assert_eq!(adjusted["close"].data[&1], vec![50.0, 50.0, 50.0, 50.0]);
+        assert_eq!(adjusted["volume"].data[&1], vec![2000.0, 1000.0, 1000.0, 1000.0]);
+        assert_eq!(raw["close"].index, days[1..].to_vec());
+
+        // Default history is the adjusted view
+        let default = portal.history(&[asset.clone()], &["close"], 4, DataFrequency::Daily, dt).unwrap();
+        assert_eq!(default["close"].data[&1], adjusted["close"].data[&1]);
+
+        // Current price is always as traded
+        assert_eq!(portal.raw_price(&asset, dt).unwrap(), Some(50.0));
+    }
+
+    #[test]
+    fn test_adjusted_history_as_of_before_split() {
+        let (portal, days) = create_split_portal();
+        let asset = create_test_asset();
+
+        // Before the split takes effect nothing is adjusted
+        let adjusted = portal
+            .history_with_view(&[asset], &["close"], 1, DataFrequency::Daily, days[1], PriceView::Adjusted)
+            .unwrap();
+        assert_eq!(adjusted["close"].data[&1], vec![100.0]);
+    }
+
+    #[test]
+    fn test_minute_reads_share_session_frames() {
+        use chrono::{Duration, TimeZone};
+
+        // Three minute bars on each of two sessions
+        let opens = [
+            Utc.with_ymd_and_hms(2024, 1, 2, 14, 31, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 3, 14, 31, 0).unwrap(),
+        ];
+        let bars = opens
+            .iter()
+            .enumerate()
+            .flat_map(|(day, open)| {
+                (0..3).map(move |i| {
+                    let close = 100.0 + (day * 3 + i) as f64;
+                    let dt = *open + Duration::minutes(i as i64);
+                    Bar { open: close, high: close, low: close, close, volume: 10.0, dt }
+                })
+            })
+            .collect();
+        let portal = DataPortal::new(None, Some(Arc::new(HistoryBarReader { bars })), None, vec![]);
+        let asset = create_test_asset();
+
+        // Between bars the last bar of the session is current
+        let dt = opens[1] + Duration::seconds(90);
+        assert_eq!(portal.current_value(&asset, "price", dt).unwrap(), Some(104.0));
+
+        let history = portal.history(&[asset.clone()], &["close"], 2000, DataFrequency::Minute, dt).unwrap();
+        assert_eq!(history["close"].data[&1], vec![100.0, 101.0, 102.0, 103.0, 104.0]);
+
+        // Readers on other threads get the frames already published
+        let snapshot = portal.minute_frames();
+        let session = SessionId::utc_label_of(opens[1]);
+        let frame = snapshot.get(1, session).unwrap().clone();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let shared = portal.minute_session_frame(1, session).unwrap();
+                    assert!(Arc::ptr_eq(&shared, &frame));
+                });
+            }
+        });
+        assert_eq!(portal.minute_frames().len(), 2);
+    }
 }
@@ -182,7 +182,7 @@ impl DispatchBarReader {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::data::minute_bars::MinuteBarBuilder;
+    use crate::data::minute_bars::{MinuteBar, MinuteBarBuilder};
     use chrono::TimeZone;
 
     fn create_test_minute_bars() -> Vec<MinuteBar> {
@@ -0,0 +1,212 @@
+//! Point-in-time earnings announcement dates
+//!
+//! Companies schedule earnings releases a few weeks ahead, so on any session
+//! only the announcements scheduled by then are known. Each announcement is
+//! stored with the date it became known; queries as of a session ignore
+//! announcements not yet known on it, keeping backtests free of lookahead.
+//!
+//! Files hold one row per announcement with `sid`, `announcement_date` and an
+//! optional `known_date` (also read as `timestamp`, as in zipline's event
+//! datasets). Without a known date an announcement is treated as known from
+//! the start of the data, which is only safe for historical research.
+//!
+//! ```ignore
+//! let earnings = Arc::new(EarningsCalendar::load(Path::new("earnings.csv"))?);
+//! pipeline.add_factor("to_earnings".to_string(), Box::new(BusinessDaysUntilNextEarnings::new(earnings)));
+//! ```
+
+use crate::error::{Result, ZiplineError};
+use chrono::NaiveDate;
+use hashbrown::HashMap;
+use std::path::Path;
+
+/// Earnings announcement dates by sid, with the date each became known
+#[derive(Debug, Clone, Default)]
+pub struct EarningsCalendar {
+    /// sid -> [(announcement date, known date)], by announcement date
+    announcements: HashMap<u64, Vec<(NaiveDate, NaiveDate)>>,
+}
+
+impl EarningsCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `sid` announces earnings on `announcement`, known from
+    /// `known` onwards
+    pub fn insert(&mut self, sid: u64, announcement: NaiveDate, known: NaiveDate) {
+        let history = self.announcements.entry(sid).or_default();
+        let idx = history.partition_point(|(date, _)| *date <= announcement);
+        history.insert(idx, (announcement, known));
+    }
+
+    /// First announcement on or after `date` known by `date`
+    pub fn next_announcement(&self, sid: u64, date: NaiveDate) -> Option<NaiveDate> {
+        let history = self.announcements.get(&sid)?;
+        let idx = history.partition_point(|(announcement, _)| *announcement < date);
+        history[idx..]
+            .iter()
+            .find(|(_, known)| *known <= date)
+            .map(|(announcement, _)| *announcement)
+    }
+
+    /// Last announcement on or before `date` known by `date`
+    pub fn previous_announcement(&self, sid: u64, date: NaiveDate) -> Option<NaiveDate> {
+        let history = self.announcements.get(&sid)?;
+        let idx = history.partition_point(|(announcement, _)| *announcement <= date);
+        history[..idx]
+            .iter()
+            .rev()
+            .find(|(_, known)| *known <= date)
+            .map(|(announcement, _)| *announcement)
+    }
+
+    /// Number of sids with announcements
+    pub fn len(&self) -> usize {
+        self.announcements.len()
+    }
+
+    /// Whether no sids have announcements
+    pub fn is_empty(&self) -> bool {
+        self.announcements.is_empty()
+    }
+
+    /// Load an announcements file, choosing the format by extension (`.csv`
+    /// or `.parquet`)
+    pub fn load(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("parquet") => Self::from_parquet(path),
+            Some("csv") => Self::from_csv(path),
+            _ => Err(ZiplineError::InvalidData(format!(
+                "Unsupported earnings file {}: expected .csv or .parquet",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Load `sid,announcement_date[,known_date]` rows from a CSV file with a
+    /// header
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read CSV: {}", e)))?;
+        let columns = Self::columns(
+            reader
+                .headers()
+                .map_err(|e| ZiplineError::DataError(format!("Failed to read CSV header: {}", e)))?
+                .iter(),
+        )?;
+
+        let mut calendar = Self::new();
+        for result in reader.records() {
+            let record = result
+                .map_err(|e| ZiplineError::DataError(format!("Failed to parse CSV row: {}", e)))?;
+            let field = |idx: Option<usize>| idx.and_then(|idx| record.get(idx)).unwrap_or("");
+            calendar.insert_row(field(Some(columns.0)), field(Some(columns.1)), field(columns.2))?;
+        }
+        Ok(calendar)
+    }
+
+    /// Load `sid`, `announcement_date` and optional `known_date` columns from
+    /// a Parquet file
+    pub fn from_parquet(path: &Path) -> Result<Self> {
+        use polars::prelude::*;
+
+        let file = std::fs::File::open(path)?;
+        let frame = ParquetReader::new(file)
+            .finish()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read Parquet: {}", e)))?;
+        let names: Vec<&str> = frame.get_column_names();
+        let columns = Self::columns(names.iter().copied())?;
+
+        let text = |idx: usize| -> Result<Vec<Option<String>>> {
+            let column = frame.get_columns()[idx]
+                .cast(&DataType::String)
+                .map_err(|e| ZiplineError::DataError(format!("Bad column {}: {}", names[idx], e)))?;
+            let values = column
+                .str()
+                .map_err(|e| ZiplineError::DataError(format!("Bad column {}: {}", names[idx], e)))?;
+            Ok(values.into_iter().map(|v| v.map(str::to_string)).collect())
+        };
+
+        let (sids, announcements) = (text(columns.0)?, text(columns.1)?);
+        let known = match columns.2 {
+            Some(idx) => text(idx)?,
+            None => vec![None; sids.len()],
+        };
+        let mut calendar = Self::new();
+        for ((sid, announcement), known) in sids.iter().zip(&announcements).zip(&known) {
+            calendar.insert_row(
+                sid.as_deref().unwrap_or(""),
+                announcement.as_deref().unwrap_or(""),
+                known.as_deref().unwrap_or(""),
+            )?;
+        }
+        Ok(calendar)
+    }
+
+    /// Positions of the sid, announcement date and known date columns
+    fn columns<'a>(names: impl Iterator<Item = &'a str>) -> Result<(usize, usize, Option<usize>)> {
+        let names: Vec<String> = names.map(|n| n.trim().to_lowercase()).collect();
+        let find = |candidates: &[&str]| names.iter().position(|n| candidates.contains(&n.as_str()));
+        let missing = |column: &str| {
+            ZiplineError::InvalidData(format!("Earnings file is missing a {} column", column))
+        };
+        Ok((
+            find(&["sid", "asset_id"]).ok_or_else(|| missing("sid"))?,
+            find(&["announcement_date", "event_date", "date"]).ok_or_else(|| missing("announcement_date"))?,
+            find(&["known_date", "timestamp", "asof_date"]),
+        ))
+    }
+
+    fn insert_row(&mut self, sid: &str, announcement: &str, known: &str) -> Result<()> {
+        let sid = sid
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| ZiplineError::InvalidData(format!("Invalid sid '{}': {}", sid, e)))?;
+        let date = |field: &str| {
+            let field = field.trim();
+            NaiveDate::parse_from_str(field.get(..10).unwrap_or(field), "%Y-%m-%d")
+                .map_err(|e| ZiplineError::InvalidData(format!("Invalid date '{}': {}", field, e)))
+        };
+        let announcement = date(announcement)?;
+        let known = match known.trim() {
+            "" => NaiveDate::MIN,
+            known => date(known)?,
+        };
+        self.insert(sid, announcement, known);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_point_in_time_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("earnings.csv");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "sid,announcement_date,known_date").unwrap();
+        writeln!(file, "1,2024-01-25,2024-01-02").unwrap();
+        writeln!(file, "1,2024-04-25,2024-04-01").unwrap();
+        writeln!(file, "2,2024-02-01,").unwrap();
+        drop(file);
+
+        let calendar = EarningsCalendar::load(&path).unwrap();
+        assert_eq!(calendar.len(), 2);
+        let day = |m: u32, d: u32| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        assert_eq!(calendar.next_announcement(1, day(1, 10)), Some(day(1, 25)));
+        assert_eq!(calendar.next_announcement(1, day(1, 25)), Some(day(1, 25)));
+        // April's date is not scheduled yet in March
+        assert_eq!(calendar.next_announcement(1, day(3, 15)), None);
+        assert_eq!(calendar.next_announcement(1, day(4, 1)), Some(day(4, 25)));
+        assert_eq!(calendar.previous_announcement(1, day(3, 15)), Some(day(1, 25)));
+        assert_eq!(calendar.previous_announcement(1, day(1, 24)), None);
+        assert_eq!(calendar.next_announcement(2, day(1, 1)), Some(day(2, 1)));
+        assert_eq!(calendar.next_announcement(3, day(1, 1)), None);
+        assert!(EarningsCalendar::load(&dir.path().join("earnings.txt")).is_err());
+    }
+}
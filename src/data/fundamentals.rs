@@ -0,0 +1,313 @@
+//! Point-in-time fundamentals
+//!
+//! Each value belongs to a reporting period (its report date, the fiscal
+//! period end) but only becomes public when it is filed (its effective date),
+//! often weeks later, and may be restated afterwards. Queries as of a session
+//! return the most recent period whose filing was public on that session, using
+//! the latest revision then known, so factors never see a number before the
+//! market could.
+//!
+//! Files are wide: one row per sid and filing with `sid`, `report_date`, an
+//! optional `effective_date` and one numeric column per field (`eps`,
+//! `book_value_per_share`, ...). `asof_date` and `timestamp` are accepted for
+//! the two dates, as in zipline's blaze loaders. Without an effective date a
+//! value is treated as public on its report date, which is optimistic.
+//!
+//! ```ignore
+//! let store = Arc::new(FundamentalsStore::load(Path::new("fundamentals.parquet"))?);
+//! let pe = FundamentalFactor::new(store, FundamentalMetric::PERatio);
+//! ```
+
+use crate::error::{Result, ZiplineError};
+use chrono::NaiveDate;
+use hashbrown::HashMap;
+use std::path::Path;
+
+/// One filed value of a field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FundamentalRecord {
+    /// End of the period the value describes
+    pub report_date: NaiveDate,
+    /// First session the value was public
+    pub effective_date: NaiveDate,
+    pub value: f64,
+}
+
+/// Fundamental values by sid and field, with report and effective dates
+#[derive(Debug, Clone, Default)]
+pub struct FundamentalsStore {
+    /// sid -> field -> records, by report date then effective date
+    records: HashMap<u64, HashMap<String, Vec<FundamentalRecord>>>,
+}
+
+impl FundamentalsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `value` of `field` for the period ending `report_date`, public
+    /// from `effective_date` onwards
+    pub fn insert(
+        &mut self,
+        sid: u64,
+        field: &str,
+        report_date: NaiveDate,
+        effective_date: NaiveDate,
+        value: f64,
+    ) {
+        let history = self.records.entry(sid).or_default().entry(field.to_string()).or_default();
+        let key = (report_date, effective_date);
+        let idx = history.partition_point(|r| (r.report_date, r.effective_date) <= key);
+        history.insert(
+            idx,
+            FundamentalRecord {
+                report_date,
+                effective_date,
+                value,
+            },
+        );
+    }
+
+    /// Add every record of `other`
+    pub fn merge(&mut self, other: FundamentalsStore) {
+        for (sid, fields) in other.records {
+            for (field, records) in fields {
+                for record in records {
+                    self.insert(sid, &field, record.report_date, record.effective_date, record.value);
+                }
+            }
+        }
+    }
+
+    /// Latest record of `field` for `sid` public on `date`
+    pub fn record_at(&self, sid: u64, field: &str, date: NaiveDate) -> Option<FundamentalRecord> {
+        // Newest period first, and within a period the latest revision
+        self.records
+            .get(&sid)?
+            .get(field)?
+            .iter()
+            .rev()
+            .find(|record| record.effective_date <= date)
+            .copied()
+    }
+
+    /// Value of `field` for `sid` as known on `date`
+    pub fn value_at(&self, sid: u64, field: &str, date: NaiveDate) -> Option<f64> {
+        self.record_at(sid, field, date).map(|record| record.value)
+    }
+
+    /// Every field with at least one value, sorted
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = self
+            .records
+            .values()
+            .flat_map(|fields| fields.keys().map(String::as_str))
+            .collect();
+        fields.sort_unstable();
+        fields.dedup();
+        fields
+    }
+
+    /// Number of sids with values
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no sids have values
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Load a fundamentals file, choosing the format by extension (`.csv` or
+    /// `.parquet`)
+    pub fn load(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("parquet") => Self::from_parquet(path),
+            Some("csv") => Self::from_csv(path),
+            _ => Err(ZiplineError::InvalidData(format!(
+                "Unsupported fundamentals file {}: expected .csv or .parquet",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Load `sid,report_date[,effective_date],<field>...` rows from a CSV file
+    /// with a header; empty cells are missing values
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read CSV: {}", e)))?;
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read CSV header: {}", e)))?
+            .iter()
+            .map(str::to_string)
+            .collect();
+        let columns = Columns::find(headers.iter().map(String::as_str))?;
+
+        let mut store = Self::new();
+        for result in reader.records() {
+            let record = result
+                .map_err(|e| ZiplineError::DataError(format!("Failed to parse CSV row: {}", e)))?;
+            let field = |idx: usize| record.get(idx).unwrap_or("");
+            let (sid, report_date, effective_date) = row_keys(
+                field(columns.sid),
+                field(columns.report_date),
+                columns.effective_date.map(field),
+            )?;
+            for &idx in &columns.fields {
+                let text = field(idx).trim();
+                if text.is_empty() {
+                    continue;
+                }
+                let value = text.parse::<f64>().map_err(|e| {
+                    ZiplineError::InvalidData(format!("Invalid {} value '{}': {}", headers[idx], text, e))
+                })?;
+                store.insert(sid, headers[idx].trim(), report_date, effective_date, value);
+            }
+        }
+        Ok(store)
+    }
+
+    /// Load `sid`, `report_date`, optional `effective_date` and numeric field
+    /// columns from a Parquet file; nulls are missing values
+    pub fn from_parquet(path: &Path) -> Result<Self> {
+        use polars::prelude::*;
+
+        let file = std::fs::File::open(path)?;
+        let frame = ParquetReader::new(file)
+            .finish()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read Parquet: {}", e)))?;
+        let names: Vec<&str> = frame.get_column_names();
+        let columns = Columns::find(names.iter().copied())?;
+        let bad = |idx: usize, e: PolarsError| {
+            ZiplineError::DataError(format!("Bad column {}: {}", names[idx], e))
+        };
+
+        let text = |idx: usize| -> Result<Vec<Option<String>>> {
+            let column = frame.get_columns()[idx].cast(&DataType::String).map_err(|e| bad(idx, e))?;
+            let values = column.str().map_err(|e| bad(idx, e))?;
+            Ok(values.into_iter().map(|v| v.map(str::to_string)).collect())
+        };
+        let sids = text(columns.sid)?;
+        let report_dates = text(columns.report_date)?;
+        let effective_dates = match columns.effective_date {
+            Some(idx) => text(idx)?,
+            None => vec![None; sids.len()],
+        };
+        let mut keys = Vec::with_capacity(sids.len());
+        for ((sid, report_date), effective_date) in sids.iter().zip(&report_dates).zip(&effective_dates) {
+            keys.push(row_keys(
+                sid.as_deref().unwrap_or(""),
+                report_date.as_deref().unwrap_or(""),
+                effective_date.as_deref(),
+            )?);
+        }
+
+        let mut store = Self::new();
+        for &idx in &columns.fields {
+            let column = frame.get_columns()[idx].cast(&DataType::Float64).map_err(|e| bad(idx, e))?;
+            let values = column.f64().map_err(|e| bad(idx, e))?;
+            for (&(sid, report_date, effective_date), value) in keys.iter().zip(values) {
+                if let Some(value) = value {
+                    store.insert(sid, names[idx].trim(), report_date, effective_date, value);
+                }
+            }
+        }
+        Ok(store)
+    }
+}
+
+/// Positions of the key and field columns in a fundamentals file
+struct Columns {
+    sid: usize,
+    report_date: usize,
+    effective_date: Option<usize>,
+    fields: Vec<usize>,
+}
+
+impl Columns {
+    fn find<'a>(names: impl Iterator<Item = &'a str>) -> Result<Self> {
+        let names: Vec<String> = names.map(|n| n.trim().to_lowercase()).collect();
+        let find = |candidates: &[&str]| names.iter().position(|n| candidates.contains(&n.as_str()));
+        let missing = |column: &str| {
+            ZiplineError::InvalidData(format!("Fundamentals file is missing a {} column", column))
+        };
+        let sid = find(&["sid", "asset_id"]).ok_or_else(|| missing("sid"))?;
+        let report_date = find(&["report_date", "asof_date", "period_end"])
+            .ok_or_else(|| missing("report_date"))?;
+        let effective_date = find(&["effective_date", "timestamp", "filing_date"]);
+        let fields = (0..names.len())
+            .filter(|&idx| idx != sid && idx != report_date && Some(idx) != effective_date)
+            .collect();
+        Ok(Self {
+            sid,
+            report_date,
+            effective_date,
+            fields,
+        })
+    }
+}
+
+/// Sid, report date and effective date of a row, defaulting the effective
+/// date to the report date
+fn row_keys(
+    sid: &str,
+    report_date: &str,
+    effective_date: Option<&str>,
+) -> Result<(u64, NaiveDate, NaiveDate)> {
+    let sid = sid
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| ZiplineError::InvalidData(format!("Invalid sid '{}': {}", sid, e)))?;
+    let report_date = parse_date(report_date)?;
+    let effective_date = match effective_date.map(str::trim) {
+        Some(text) if !text.is_empty() => parse_date(text)?,
+        _ => report_date,
+    };
+    Ok((sid, report_date, effective_date))
+}
+
+/// `%Y-%m-%d` date, ignoring any time of day after it
+fn parse_date(text: &str) -> Result<NaiveDate> {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text.get(..10).unwrap_or(text), "%Y-%m-%d")
+        .map_err(|e| ZiplineError::InvalidData(format!("Invalid date '{}': {}", text, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_point_in_time_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fundamentals.csv");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "sid,report_date,effective_date,eps,book_value_per_share").unwrap();
+        writeln!(file, "1,2023-12-31,2024-02-10,1.5,20").unwrap();
+        writeln!(file, "1,2024-03-31,2024-05-05,1.8,").unwrap();
+        // Q4 restated after Q1 was filed
+        writeln!(file, "1,2023-12-31,2024-06-01,1.2,19").unwrap();
+        writeln!(file, "2,2023-12-31,,0.4,8").unwrap();
+        drop(file);
+
+        let store = FundamentalsStore::load(&path).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.fields(), vec!["book_value_per_share", "eps"]);
+        let day = |m: u32, d: u32| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        // Q4 is not public until its filing
+        assert_eq!(store.value_at(1, "eps", day(2, 9)), None);
+        assert_eq!(store.value_at(1, "eps", day(2, 10)), Some(1.5));
+        assert_eq!(store.value_at(1, "eps", day(5, 5)), Some(1.8));
+        // The Q4 restatement does not displace the newer Q1 figure
+        assert_eq!(store.value_at(1, "eps", day(6, 2)), Some(1.8));
+        // Q1 has no book value, so the restated Q4 one is the latest
+        assert_eq!(store.value_at(1, "book_value_per_share", day(5, 5)), Some(20.0));
+        assert_eq!(store.value_at(1, "book_value_per_share", day(6, 2)), Some(19.0));
+        let record = store.record_at(2, "eps", day(1, 1)).unwrap();
+        assert_eq!(record.effective_date, record.report_date);
+        assert_eq!(store.value_at(3, "eps", day(6, 2)), None);
+    }
+}
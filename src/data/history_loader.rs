@@ -31,6 +31,17 @@ impl HistoryField {
         }
     }
 
+    /// Field name as used in data frames
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoryField::Open => "open",
+            HistoryField::High => "high",
+            HistoryField::Low => "low",
+            HistoryField::Close => "close",
+            HistoryField::Volume => "volume",
+        }
+    }
+
     /// All available fields
     pub fn all_fields() -> Vec<HistoryField> {
         vec![
@@ -257,6 +268,47 @@ impl HistoryLoader {
         Ok(values.iter().rev().take(window_size).rev().copied().collect())
     }
 
+    /// Load up to the last `bar_count` bars for an asset ending at `end_dt`
+    ///
+    /// Unlike [`load_history`](Self::load_history), this returns the bars
+    /// themselves and does not fail on a short window. The lookback is widened
+    /// until enough bars are found or the asset's first bar is reached, so
+    /// weekends and holidays do not shorten daily windows.
+    pub fn load_bars(
+        &self,
+        asset: &Asset,
+        bar_count: usize,
+        end_dt: DateTime<Utc>,
+        frequency: Frequency,
+    ) -> Result<Vec<Bar>> {
+        if bar_count == 0 {
+            return Ok(Vec::new());
+        }
+        let first_dt = self.bar_reader.first_available_dt(asset).ok();
+        let mut start_dt = self.compute_start_date(end_dt, bar_count, frequency);
+
+        let mut bars = loop {
+            let cache_key = (asset.id, start_dt, end_dt);
+            let bars = match self.get_from_cache(&cache_key) {
+                Some(cached) => cached,
+                None => {
+                    let bars = self.bar_reader.get_bars(asset, start_dt, end_dt)?;
+                    self.update_cache(cache_key, bars.clone());
+                    bars
+                }
+            };
+
+            let exhausted = first_dt.is_none_or(|first| start_dt <= first);
+            if bars.len() >= bar_count || exhausted {
+                break bars;
+            }
+            start_dt = end_dt - (end_dt - start_dt) * 2;
+        };
+
+        let skip = bars.len().saturating_sub(bar_count);
+        Ok(bars.split_off(skip))
+    }
+
     /// Load history for multiple assets
     pub fn load_history_multiple(
         &self,
@@ -453,6 +505,10 @@ mod tests {
             symbol: "AAPL".to_string(),
             exchange: "NYSE".to_string(),
             asset_type: crate::asset::AssetType::Equity,
+            name: None,
+            start_date: chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            end_date: None,
+            auto_close_date: None,
         }
     }
 
@@ -583,12 +639,20 @@ mod tests {
             symbol: "AAPL".to_string(),
             exchange: "NYSE".to_string(),
             asset_type: crate::asset::AssetType::Equity,
+            name: None,
+            start_date: chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            end_date: None,
+            auto_close_date: None,
         };
         let asset2 = Asset {
             id: 2,
             symbol: "GOOGL".to_string(),
             exchange: "NYSE".to_string(),
             asset_type: crate::asset::AssetType::Equity,
+            name: None,
+            start_date: chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            end_date: None,
+            auto_close_date: None,
         };
 
         let bars1 = create_test_bars(10);
@@ -128,6 +128,16 @@ impl MinuteBarReader {
         Ok(result)
     }
 
+    /// Get all minute bars for an asset
+    pub fn bars(&self, asset_id: u64) -> Option<&[MinuteBar]> {
+        self.data.get(&asset_id).map(|bars| bars.as_slice())
+    }
+
+    /// Get IDs of all assets with minute data
+    pub fn asset_ids(&self) -> Vec<u64> {
+        self.data.keys().copied().collect()
+    }
+
     /// Check if we have data for an asset
     pub fn has_data(&self, asset_id: u64) -> bool {
         self.data.contains_key(&asset_id)
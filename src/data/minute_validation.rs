@@ -0,0 +1,582 @@
+//! Intraday bar alignment validation and repair
+//!
+//! Checks minute data against the session minutes defined by a `TradingCalendar`:
+//! - Sessions with fewer (or more) minutes than the calendar expects
+//! - Duplicated timestamps
+//! - Timestamps that go backwards
+//! - Bars outside session hours or on non-trading days
+//!
+//! Data can also be repaired by reindexing each asset onto the canonical session
+//! minutes. Every minute that did not come from the source data is reported with
+//! an explicit fill marker so downstream consumers can tell real bars from filled ones.
+//!
+//! Minute bars are labelled by the start of the minute, so a 9:30-16:00 session
+//! has 390 minutes from 9:30 through 15:59.
+
+use crate::calendar::TradingCalendar;
+use crate::data::minute_bars::{MinuteBar, MinuteBarBuilder, MinuteBarReader};
+use crate::error::{Result, ZiplineError};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Alignment findings for a single session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionAlignment {
+    /// Session date (in the calendar's timezone)
+    pub session: NaiveDate,
+    /// Number of minutes the calendar expects (0 for non-trading days)
+    pub expected_minutes: usize,
+    /// Number of distinct minutes present in the data
+    pub actual_minutes: usize,
+    /// Expected session minutes with no bar
+    pub missing: Vec<DateTime<Utc>>,
+    /// Bars outside the session's minutes
+    pub unexpected: Vec<DateTime<Utc>>,
+    /// Timestamps that appear more than once
+    pub duplicates: Vec<DateTime<Utc>>,
+    /// Timestamps earlier than the bar preceding them
+    pub out_of_order: Vec<DateTime<Utc>>,
+}
+
+impl SessionAlignment {
+    /// Check if the session matches the calendar exactly
+    pub fn is_aligned(&self) -> bool {
+        self.missing.is_empty()
+            && self.unexpected.is_empty()
+            && self.duplicates.is_empty()
+            && self.out_of_order.is_empty()
+    }
+}
+
+/// Minute alignment report for one asset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinuteAlignmentReport {
+    /// Asset ID
+    pub asset_id: u64,
+    /// Per-session findings, ordered by session date
+    pub sessions: Vec<SessionAlignment>,
+}
+
+impl MinuteAlignmentReport {
+    /// Check if every session is aligned
+    pub fn is_aligned(&self) -> bool {
+        self.sessions.iter().all(|s| s.is_aligned())
+    }
+
+    /// Sessions with at least one issue
+    pub fn problem_sessions(&self) -> Vec<&SessionAlignment> {
+        self.sessions.iter().filter(|s| !s.is_aligned()).collect()
+    }
+
+    /// Total number of missing minutes across all sessions
+    pub fn missing_count(&self) -> usize {
+        self.sessions.iter().map(|s| s.missing.len()).sum()
+    }
+
+    /// Total number of duplicated timestamps across all sessions
+    pub fn duplicate_count(&self) -> usize {
+        self.sessions.iter().map(|s| s.duplicates.len()).sum()
+    }
+
+    /// Total number of out-of-order timestamps across all sessions
+    pub fn out_of_order_count(&self) -> usize {
+        self.sessions.iter().map(|s| s.out_of_order.len()).sum()
+    }
+
+    /// Total number of bars outside session minutes
+    pub fn unexpected_count(&self) -> usize {
+        self.sessions.iter().map(|s| s.unexpected.len()).sum()
+    }
+}
+
+impl std::fmt::Display for MinuteAlignmentReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Minute Alignment: asset {}", self.asset_id)?;
+        writeln!(f, "  Sessions checked:   {}", self.sessions.len())?;
+        writeln!(f, "  Missing minutes:    {}", self.missing_count())?;
+        writeln!(f, "  Unexpected minutes: {}", self.unexpected_count())?;
+        writeln!(f, "  Duplicates:         {}", self.duplicate_count())?;
+        writeln!(f, "  Out of order:       {}", self.out_of_order_count())?;
+        for session in self.problem_sessions() {
+            writeln!(
+                f,
+                "    {} {}/{} minutes",
+                session.session, session.actual_minutes, session.expected_minutes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How a repaired minute was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MinuteFill {
+    /// Bar taken from the source data
+    Original,
+    /// Missing minute filled with the previous close and zero volume
+    ForwardFilled,
+    /// Missing minute with no prior close; prices are NaN and volume is zero
+    Empty,
+}
+
+/// Fill marker for a minute that was not present in the source data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FillMarker {
+    /// Minute that was filled
+    pub timestamp: DateTime<Utc>,
+    /// How it was filled
+    pub fill: MinuteFill,
+}
+
+/// A bar on the canonical minute index
+#[derive(Debug, Clone)]
+pub struct RepairedMinute {
+    /// The bar
+    pub bar: MinuteBar,
+    /// How the bar was produced
+    pub fill: MinuteFill,
+}
+
+/// Output of repairing a minute bar reader
+#[derive(Debug, Clone)]
+pub struct MinuteRepairResult {
+    /// Reader containing the reindexed bars
+    pub reader: MinuteBarReader,
+    /// Filled minutes by asset ID
+    pub fill_markers: HashMap<u64, Vec<FillMarker>>,
+    /// Number of duplicate bars dropped
+    pub duplicates_dropped: usize,
+    /// Number of bars dropped for falling outside session minutes
+    pub unexpected_dropped: usize,
+}
+
+/// Validates and repairs minute data against a trading calendar
+pub struct MinuteAlignmentValidator {
+    calendar: Arc<dyn TradingCalendar>,
+}
+
+impl std::fmt::Debug for MinuteAlignmentValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MinuteAlignmentValidator")
+            .field("calendar", &"<dyn TradingCalendar>")
+            .finish()
+    }
+}
+
+impl MinuteAlignmentValidator {
+    /// Create a new validator for the given calendar
+    pub fn new(calendar: Arc<dyn TradingCalendar>) -> Self {
+        Self { calendar }
+    }
+
+    /// Get the canonical minutes for a session (empty for non-trading days)
+    pub fn session_minutes(&self, session: NaiveDate) -> Result<Vec<DateTime<Utc>>> {
+        let times = match self.calendar.session_times(session) {
+            Some(times) => times,
+            None => return Ok(Vec::new()),
+        };
+
+        let open = self.localize(session, times.market_open)?;
+        let close = self.localize(session, times.market_close)?;
+
+        let mut minutes = Vec::new();
+        let mut current = open;
+        while current < close {
+            minutes.push(current);
+            current += Duration::minutes(1);
+        }
+        Ok(minutes)
+    }
+
+    /// Session date a timestamp belongs to, in the calendar's timezone
+    pub fn session_of(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        timestamp.with_timezone(&self.calendar.timezone()).date_naive()
+    }
+
+    /// Validate bars for one asset over the sessions spanned by the data
+    ///
+    /// Bars are checked in the order given, so out-of-order timestamps are
+    /// detected relative to their predecessor.
+    pub fn validate(&self, asset_id: u64, bars: &[MinuteBar]) -> Result<MinuteAlignmentReport> {
+        let (start, end) = self.data_range(bars).ok_or(ZiplineError::NoDataAvailable)?;
+        self.validate_range(asset_id, bars, start, end)
+    }
+
+    /// Validate bars for one asset over an explicit session range (inclusive)
+    ///
+    /// Bars belonging to sessions outside `[start, end]` are ignored.
+    pub fn validate_range(
+        &self,
+        asset_id: u64,
+        bars: &[MinuteBar],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<MinuteAlignmentReport> {
+        if start > end {
+            return Err(ZiplineError::InvalidData(format!(
+                "Invalid validation range: start {} is after end {}",
+                start, end
+            )));
+        }
+
+        // Group timestamps by session, recording duplicates and ordering violations
+        let mut by_session: BTreeMap<NaiveDate, SessionTimestamps> = BTreeMap::new();
+        let mut seen: HashSet<DateTime<Utc>> = HashSet::new();
+        let mut previous: Option<DateTime<Utc>> = None;
+
+        for bar in bars {
+            let session = self.session_of(bar.timestamp);
+            let in_range = session >= start && session <= end;
+
+            if in_range {
+                let entry = by_session.entry(session).or_default();
+                if !seen.insert(bar.timestamp) {
+                    entry.duplicates.push(bar.timestamp);
+                } else {
+                    entry.timestamps.insert(bar.timestamp);
+                }
+                if matches!(previous, Some(prev) if bar.timestamp < prev) {
+                    entry.out_of_order.push(bar.timestamp);
+                }
+            }
+
+            previous = Some(bar.timestamp);
+        }
+
+        // Every calendar session in range is checked, plus any non-sessions with data
+        for session in self.calendar.trading_days_between(start, end) {
+            by_session.entry(session).or_default();
+        }
+
+        let mut sessions = Vec::with_capacity(by_session.len());
+        for (session, found) in by_session {
+            let expected = self.session_minutes(session)?;
+            let expected_set: HashSet<DateTime<Utc>> = expected.iter().copied().collect();
+
+            let missing = expected
+                .iter()
+                .copied()
+                .filter(|m| !found.timestamps.contains(m))
+                .collect();
+            let mut unexpected: Vec<DateTime<Utc>> = found
+                .timestamps
+                .iter()
+                .copied()
+                .filter(|t| !expected_set.contains(t))
+                .collect();
+            unexpected.sort();
+
+            sessions.push(SessionAlignment {
+                session,
+                expected_minutes: expected.len(),
+                actual_minutes: found.timestamps.len(),
+                missing,
+                unexpected,
+                duplicates: found.duplicates,
+                out_of_order: found.out_of_order,
+            });
+        }
+
+        Ok(MinuteAlignmentReport { asset_id, sessions })
+    }
+
+    /// Validate every asset in a minute bar reader
+    pub fn validate_reader(&self, reader: &MinuteBarReader) -> Result<Vec<MinuteAlignmentReport>> {
+        let mut asset_ids = reader.asset_ids();
+        asset_ids.sort_unstable();
+
+        let mut reports = Vec::with_capacity(asset_ids.len());
+        for asset_id in asset_ids {
+            if let Some(bars) = reader.bars(asset_id) {
+                if !bars.is_empty() {
+                    reports.push(self.validate(asset_id, bars)?);
+                }
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Reindex bars onto the canonical session minutes spanned by the data
+    ///
+    /// Duplicates keep the last occurrence, bars outside session minutes are
+    /// dropped, and missing minutes are forward-filled from the previous close
+    /// (or left empty when no prior close exists).
+    pub fn repair(&self, bars: &[MinuteBar]) -> Result<Vec<RepairedMinute>> {
+        let (start, end) = self.data_range(bars).ok_or(ZiplineError::NoDataAvailable)?;
+        self.repair_range(bars, start, end)
+    }
+
+    /// Reindex bars onto the canonical session minutes of an explicit range (inclusive)
+    pub fn repair_range(
+        &self,
+        bars: &[MinuteBar],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<RepairedMinute>> {
+        if start > end {
+            return Err(ZiplineError::InvalidData(format!(
+                "Invalid repair range: start {} is after end {}",
+                start, end
+            )));
+        }
+
+        // Later bars overwrite earlier ones with the same timestamp
+        let by_timestamp: BTreeMap<DateTime<Utc>, &MinuteBar> =
+            bars.iter().map(|bar| (bar.timestamp, bar)).collect();
+
+        let mut repaired = Vec::new();
+        let mut last_close: Option<f64> = None;
+
+        for session in self.calendar.trading_days_between(start, end) {
+            for minute in self.session_minutes(session)? {
+                match by_timestamp.get(&minute) {
+                    Some(bar) => {
+                        last_close = Some(bar.close);
+                        repaired.push(RepairedMinute {
+                            bar: (*bar).clone(),
+                            fill: MinuteFill::Original,
+                        });
+                    }
+                    None => {
+                        let (price, fill) = match last_close {
+                            Some(close) => (close, MinuteFill::ForwardFilled),
+                            None => (f64::NAN, MinuteFill::Empty),
+                        };
+                        repaired.push(RepairedMinute {
+                            bar: MinuteBar::new(minute, price, price, price, price, 0.0),
+                            fill,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(repaired)
+    }
+
+    /// Repair every asset in a minute bar reader
+    pub fn repair_reader(&self, reader: &MinuteBarReader) -> Result<MinuteRepairResult> {
+        let mut builder = MinuteBarBuilder::new();
+        let mut fill_markers = HashMap::new();
+        let mut duplicates_dropped = 0;
+        let mut unexpected_dropped = 0;
+
+        for asset_id in reader.asset_ids() {
+            let bars = match reader.bars(asset_id) {
+                Some(bars) if !bars.is_empty() => bars,
+                _ => continue,
+            };
+
+            let report = self.validate(asset_id, bars)?;
+            duplicates_dropped += report.duplicate_count();
+            unexpected_dropped += report.unexpected_count();
+
+            let repaired = self.repair(bars)?;
+            let markers: Vec<FillMarker> = repaired
+                .iter()
+                .filter(|m| m.fill != MinuteFill::Original)
+                .map(|m| FillMarker {
+                    timestamp: m.bar.timestamp,
+                    fill: m.fill,
+                })
+                .collect();
+
+            builder.add_bars(asset_id, repaired.into_iter().map(|m| m.bar).collect());
+            fill_markers.insert(asset_id, markers);
+        }
+
+        Ok(MinuteRepairResult {
+            reader: builder.build(),
+            fill_markers,
+            duplicates_dropped,
+            unexpected_dropped,
+        })
+    }
+
+    /// First and last session touched by the data
+    fn data_range(&self, bars: &[MinuteBar]) -> Option<(NaiveDate, NaiveDate)> {
+        let first = bars.iter().map(|b| b.timestamp).min()?;
+        let last = bars.iter().map(|b| b.timestamp).max()?;
+        Some((self.session_of(first), self.session_of(last)))
+    }
+
+    /// Convert a session-local time to UTC
+    fn localize(&self, session: NaiveDate, time: NaiveTime) -> Result<DateTime<Utc>> {
+        self.calendar
+            .timezone()
+            .from_local_datetime(&session.and_time(time))
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok_or_else(|| {
+                ZiplineError::CalendarError(format!(
+                    "Invalid local time {} on session {}",
+                    time, session
+                ))
+            })
+    }
+}
+
+/// Timestamps observed for one session while validating
+#[derive(Debug, Default)]
+struct SessionTimestamps {
+    timestamps: HashSet<DateTime<Utc>>,
+    duplicates: Vec<DateTime<Utc>>,
+    out_of_order: Vec<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::NYSECalendar;
+
+    fn validator() -> MinuteAlignmentValidator {
+        MinuteAlignmentValidator::new(Arc::new(NYSECalendar::new()))
+    }
+
+    fn minute(date: NaiveDate, hour: u32, min: u32) -> DateTime<Utc> {
+        // January sessions: New York is UTC-5
+        Utc.from_utc_datetime(&date.and_hms_opt(hour + 5, min, 0).unwrap())
+    }
+
+    fn bar(timestamp: DateTime<Utc>, close: f64) -> MinuteBar {
+        MinuteBar::new(timestamp, close, close, close, close, 100.0)
+    }
+
+    fn full_session(date: NaiveDate) -> Vec<MinuteBar> {
+        validator()
+            .session_minutes(date)
+            .unwrap()
+            .into_iter()
+            .enumerate()
+            .map(|(i, ts)| bar(ts, 100.0 + i as f64))
+            .collect()
+    }
+
+    #[test]
+    fn test_session_minutes() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let minutes = validator().session_minutes(date).unwrap();
+
+        assert_eq!(minutes.len(), 390);
+        assert_eq!(minutes[0], minute(date, 9, 30));
+        assert_eq!(*minutes.last().unwrap(), minute(date, 15, 59));
+
+        // Weekend has no minutes
+        let saturday = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+        assert!(validator().session_minutes(saturday).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_aligned_session() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let report = validator().validate(1, &full_session(date)).unwrap();
+
+        assert!(report.is_aligned());
+        assert_eq!(report.sessions.len(), 1);
+        assert_eq!(report.sessions[0].expected_minutes, 390);
+        assert_eq!(report.sessions[0].actual_minutes, 390);
+    }
+
+    #[test]
+    fn test_detects_gaps_duplicates_and_ordering() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mut bars = full_session(date);
+
+        // Drop 10:00, duplicate 10:01, swap 11:00 and 11:01
+        bars.retain(|b| b.timestamp != minute(date, 10, 0));
+        let dup = bars.iter().find(|b| b.timestamp == minute(date, 10, 1)).unwrap().clone();
+        bars.push(dup);
+        let i = bars.iter().position(|b| b.timestamp == minute(date, 11, 0)).unwrap();
+        bars.swap(i, i + 1);
+        // Pre-market bar
+        bars.insert(0, bar(minute(date, 9, 0), 99.0));
+
+        let report = validator().validate(1, &bars).unwrap();
+        let session = &report.sessions[0];
+
+        assert!(!report.is_aligned());
+        assert_eq!(session.missing, vec![minute(date, 10, 0)]);
+        assert_eq!(session.duplicates, vec![minute(date, 10, 1)]);
+        assert!(session.out_of_order.contains(&minute(date, 11, 0)));
+        assert_eq!(session.unexpected, vec![minute(date, 9, 0)]);
+    }
+
+    #[test]
+    fn test_detects_missing_session_and_non_session_data() {
+        let tuesday = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let thursday = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+
+        let mut bars = full_session(tuesday);
+        bars.extend(full_session(thursday));
+        bars.push(bar(minute(saturday, 10, 0), 100.0));
+
+        let report = validator().validate(1, &bars).unwrap();
+
+        // Wednesday is entirely missing
+        let wednesday = report
+            .sessions
+            .iter()
+            .find(|s| s.session == NaiveDate::from_ymd_opt(2024, 1, 3).unwrap())
+            .unwrap();
+        assert_eq!(wednesday.missing.len(), 390);
+
+        let weekend = report.sessions.iter().find(|s| s.session == saturday).unwrap();
+        assert_eq!(weekend.expected_minutes, 0);
+        assert_eq!(weekend.unexpected.len(), 1);
+    }
+
+    #[test]
+    fn test_repair_reindexes_with_fill_markers() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mut bars = full_session(date);
+        bars.retain(|b| b.timestamp != minute(date, 9, 30) && b.timestamp != minute(date, 12, 0));
+        bars.push(bar(minute(date, 17, 0), 1.0));
+        bars.reverse();
+
+        let repaired = validator().repair(&bars).unwrap();
+
+        assert_eq!(repaired.len(), 390);
+        assert!(repaired.windows(2).all(|w| w[0].bar.timestamp < w[1].bar.timestamp));
+
+        // First minute has no prior close
+        assert_eq!(repaired[0].fill, MinuteFill::Empty);
+        assert!(repaired[0].bar.close.is_nan());
+
+        // Noon is forward-filled from 11:59
+        let noon = repaired.iter().find(|m| m.bar.timestamp == minute(date, 12, 0)).unwrap();
+        let prior = repaired.iter().find(|m| m.bar.timestamp == minute(date, 11, 59)).unwrap();
+        assert_eq!(noon.fill, MinuteFill::ForwardFilled);
+        assert_eq!(noon.bar.close, prior.bar.close);
+        assert_eq!(noon.bar.volume, 0.0);
+    }
+
+    #[test]
+    fn test_repair_reader() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mut bars = full_session(date);
+        bars.retain(|b| b.timestamp != minute(date, 10, 0));
+        let dup = bars[5].clone();
+        bars.push(dup);
+
+        let mut builder = MinuteBarBuilder::new();
+        builder.add_bars(7, bars);
+        let reader = builder.build();
+
+        let result = validator().repair_reader(&reader).unwrap();
+
+        assert_eq!(result.reader.bar_count(7), 390);
+        assert_eq!(result.duplicates_dropped, 1);
+        assert_eq!(
+            result.fill_markers[&7],
+            vec![FillMarker {
+                timestamp: minute(date, 10, 0),
+                fill: MinuteFill::ForwardFilled,
+            }]
+        );
+        assert!(validator().validate_reader(&result.reader).unwrap()[0].is_aligned());
+    }
+}
@@ -246,7 +246,7 @@ impl BcolzDailyBarReader {
             );
 
             if !bar.is_valid() {
-                log::warn!("Invalid bar for asset {} at {:?}", sid, dt);
+                tracing::warn!(sid, ?dt, "Invalid bar");
             }
 
             bars.push(bar);
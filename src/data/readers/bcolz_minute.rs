@@ -2,27 +2,32 @@
 //!
 //! Reads minute-level OHLCV bar data from Zipline bcolz bundles.
 //! Provides efficient access to intraday market data with session-based caching.
+//! Sessions are published as immutable [`SessionFrame`]s, so any number of
+//! threads can read the same session without contending on the cache.
 
 use crate::asset::Asset;
 use crate::calendar::TradingCalendar;
 use crate::data::bar_reader::{Bar, BarReader, SessionLabel};
-use crate::data::readers::bcolz_utils::{find_asset_sids, read_column_f64, read_column_i64};
+use crate::data::readers::bcolz_utils::{find_asset_sids, read_column_i64, ColumnView};
+use crate::data::session_frame::{FrameCache, FrameSnapshot, SessionFrame};
 use crate::error::{Result, ZiplineError};
-use chrono::{NaiveDate, DateTime, Datelike, TimeZone, Utc};
+use crate::types::{MinuteId, SessionId};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
-/// Cached minute bars for a single session (trading day)
-#[derive(Debug, Clone)]
-struct SessionBars {
-    bars: Vec<Bar>,
-    timestamps: Vec<DateTime<Utc>>,
+/// Memory-mapped columns for a single asset
+#[derive(Debug)]
+struct AssetColumns {
+    minutes: ColumnView,
+    open: ColumnView,
+    high: ColumnView,
+    low: ColumnView,
+    close: ColumnView,
+    volume: ColumnView,
 }
 
-/// Cache key: (asset_id, session_date)
-type CacheKey = (u64, SessionLabel);
-
 /// Bcolz minute bar reader
 ///
 /// Reads minute-level OHLCV data from a Zipline bcolz bundle directory structure:
@@ -42,6 +47,9 @@ type CacheKey = (u64, SessionLabel);
 ///
 /// Due to the large volume of minute data (390 bars per day for US equities),
 /// this reader uses session-based caching to minimize memory usage.
+///
+/// Asset columns are memory-mapped, so the bundle must not be rewritten while
+/// a reader over it is alive; re-ingest into a fresh directory instead.
 pub struct BcolzMinuteBarReader {
     /// Root directory of the bundle
     root_dir: PathBuf,
@@ -57,17 +65,16 @@ pub struct BcolzMinuteBarReader {
     first_trading_minute: Option<DateTime<Utc>>,
     /// Last trading minute in the bundle
     last_trading_minute: Option<DateTime<Utc>>,
-    /// Session-based cache: (asset_id, session) -> bars
-    cache: Arc<RwLock<HashMap<CacheKey, SessionBars>>>,
-    /// Maximum cache size (number of session-assets)
-    max_cache_size: usize,
+    /// Published session frames: (asset_id, session) -> bars
+    frames: FrameCache,
+    /// Open column views per asset, kept so repeated queries skip re-mapping
+    columns: RwLock<HashMap<u64, Arc<AssetColumns>>>,
     /// Minutes per session (390 for US equities)
     minutes_per_session: usize,
 }
 
 impl std::fmt::Debug for BcolzMinuteBarReader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let cache_size = self.cache.read().map(|c| c.len()).unwrap_or(0);
         f.debug_struct("BcolzMinuteBarReader")
             .field("root_dir", &self.root_dir)
             .field("calendar", &if self.calendar.is_some() { "<Some(TradingCalendar)>" } else { "None" })
@@ -75,8 +82,8 @@ impl std::fmt::Debug for BcolzMinuteBarReader {
             .field("sessions", &format!("{} sessions", self.sessions.len()))
             .field("first_trading_minute", &self.first_trading_minute)
             .field("last_trading_minute", &self.last_trading_minute)
-            .field("cache_size", &cache_size)
-            .field("max_cache_size", &self.max_cache_size)
+            .field("cache_size", &self.frames.len())
+            .field("max_cache_size", &self.frames.capacity())
             .field("minutes_per_session", &self.minutes_per_session)
             .finish()
     }
@@ -133,8 +140,8 @@ impl BcolzMinuteBarReader {
             session_idx,
             first_trading_minute: Some(first_minute),
             last_trading_minute: Some(last_minute),
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            max_cache_size: 50, // Cache 50 session-assets (e.g., 5 assets * 10 days)
+            frames: FrameCache::new(50), // Cache 50 session-assets (e.g., 5 assets * 10 days)
+            columns: RwLock::new(HashMap::new()),
             minutes_per_session,
         })
     }
@@ -217,120 +224,134 @@ impl BcolzMinuteBarReader {
         }
     }
 
-    /// Load all bars for an asset (not cached - used for range queries)
-    fn load_all_asset_data(&self, sid: u64) -> Result<(Vec<Bar>, Vec<DateTime<Utc>>)> {
+    /// Open (or reuse) the memory-mapped columns for an asset
+    fn asset_columns(&self, sid: u64) -> Result<Arc<AssetColumns>> {
+        if let Some(columns) = self.columns.read().unwrap().get(&sid) {
+            return Ok(columns.clone());
+        }
+
         let asset_path = self.asset_path(sid);
 
         if !asset_path.exists() {
             return Err(ZiplineError::AssetNotFound(sid));
         }
 
-        // Read timestamp column
-        let timestamps = read_column_i64(&asset_path, "minute")
-            .or_else(|_| read_column_i64(&asset_path, "date"))?;
+        // SAFETY: bundle files are written once at ingest time and are never
+        // modified in place while a reader is open (see the type docs).
+        let open = |name: &str| unsafe { ColumnView::open(&asset_path, name) };
 
-        // Read OHLCV columns
-        let opens = read_column_f64(&asset_path, "open")?;
-        let highs = read_column_f64(&asset_path, "high")?;
-        let lows = read_column_f64(&asset_path, "low")?;
-        let closes = read_column_f64(&asset_path, "close")?;
-        let volumes = read_column_f64(&asset_path, "volume")?;
+        // Try 'minute' column first, fallback to 'date'
+        let columns = AssetColumns {
+            minutes: open("minute").or_else(|_| open("date"))?,
+            open: open("open")?,
+            high: open("high")?,
+            low: open("low")?,
+            close: open("close")?,
+            volume: open("volume")?,
+        };
 
         // Validate lengths
-        let n = timestamps.len();
-        if opens.len() != n || highs.len() != n || lows.len() != n || closes.len() != n || volumes.len() != n {
+        let n = columns.minutes.len();
+        let ohlcv = [
+            &columns.open,
+            &columns.high,
+            &columns.low,
+            &columns.close,
+            &columns.volume,
+        ];
+        if ohlcv.iter().any(|c| c.len() != n) {
             return Err(ZiplineError::InvalidData(format!(
                 "Column length mismatch for asset {}",
                 sid
             )));
         }
 
-        // Build bars
-        let mut bars = Vec::with_capacity(n);
-        let mut dts = Vec::with_capacity(n);
+        let columns = Arc::new(columns);
+        self.columns.write().unwrap().insert(sid, columns.clone());
+        Ok(columns)
+    }
 
-        for i in 0..n {
-            let dt = Self::convert_timestamp_to_datetime(timestamps[i])?;
-            dts.push(dt);
+    /// First row whose timestamp is at or after `dt` (timestamps are sorted)
+    fn lower_bound(minutes: &ColumnView, dt: DateTime<Utc>) -> Result<usize> {
+        let (mut lo, mut hi) = (0, minutes.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if Self::convert_timestamp_to_datetime(minutes.get_i64(mid)?)? < dt {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(lo)
+    }
 
+    /// Load the bars in `[start, end)` for an asset as a frame for `session`
+    ///
+    /// Only the rows in range are copied out of the mapped columns.
+    fn load_range(
+        &self,
+        sid: u64,
+        session: SessionId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<SessionFrame> {
+        let columns = self.asset_columns(sid)?;
+        let rows = Self::lower_bound(&columns.minutes, start)?
+            ..Self::lower_bound(&columns.minutes, end)?;
+
+        let timestamps = columns.minutes.i64_range(rows.clone())?;
+        let opens = columns.open.f64_range(rows.clone())?;
+        let highs = columns.high.f64_range(rows.clone())?;
+        let lows = columns.low.f64_range(rows.clone())?;
+        let closes = columns.close.f64_range(rows.clone())?;
+        let volumes = columns.volume.f64_range(rows)?;
+
+        let mut rows = Vec::with_capacity(timestamps.len());
+        for i in 0..timestamps.len() {
+            let dt = Self::convert_timestamp_to_datetime(timestamps[i])?;
             let bar = Bar::new(opens[i], highs[i], lows[i], closes[i], volumes[i], dt);
 
             if !bar.is_valid() {
-                log::warn!("Invalid bar for asset {} at {:?}", sid, dt);
+                tracing::warn!(sid, ?dt, "Invalid bar");
             }
 
-            bars.push(bar);
+            rows.push((dt, [opens[i], highs[i], lows[i], closes[i], volumes[i]]));
         }
 
-        Ok((bars, dts))
+        SessionFrame::from_rows(sid, session, rows)
     }
 
-    /// Load bars for a specific session (cached)
-    fn load_session_data(&self, sid: u64, session: SessionLabel) -> Result<SessionBars> {
-        let cache_key = (sid, session);
-
-        // Check cache first
-        {
-            let cache = self.cache.read().unwrap();
-            if let Some(cached) = cache.get(&cache_key) {
-                return Ok(cached.clone());
-            }
-        }
-
-        // Load all data for the asset
-        let (all_bars, all_timestamps) = self.load_all_asset_data(sid)?;
-
-        // Filter to the requested session
+    /// Immutable bars for one asset and session, loaded on first use
+    ///
+    /// The returned frame can be shared with other threads and stays valid
+    /// after it is evicted from the cache.
+    pub fn session_frame(&self, sid: u64, session: SessionLabel) -> Result<Arc<SessionFrame>> {
         let session_start = session.to_datetime()?;
-        let session_end = session_start + chrono::Duration::days(1);
-
-        let mut session_bars = Vec::new();
-        let mut session_timestamps = Vec::new();
-
-        for (bar, ts) in all_bars.iter().zip(all_timestamps.iter()) {
-            if *ts >= session_start && *ts < session_end {
-                session_bars.push(*bar);
-                session_timestamps.push(*ts);
-            }
-        }
-
-        let session_data = SessionBars {
-            bars: session_bars,
-            timestamps: session_timestamps,
-        };
-
-        // Store in cache
-        {
-            let mut cache = self.cache.write().unwrap();
-
-            // Implement LRU eviction
-            if cache.len() >= self.max_cache_size {
-                // Remove oldest entry (simple FIFO)
-                if let Some(&key) = cache.keys().next() {
-                    cache.remove(&key);
-                }
-            }
+        let id = session.session_id()?;
 
-            cache.insert(cache_key, session_data.clone());
-        }
+        self.frames.get_or_load(sid, id, || {
+            // Read just the requested session from the mapped columns
+            let session_end = session_start + chrono::Duration::days(1);
+            self.load_range(sid, id, session_start, session_end)
+        })
+    }
 
-        Ok(session_data)
+    /// The session frames published so far, as one consistent view
+    pub fn frames(&self) -> FrameSnapshot {
+        self.frames.snapshot()
     }
 
-    /// Find bar index for a given timestamp within a session
-    fn find_bar_index(timestamps: &[DateTime<Utc>], target_dt: DateTime<Utc>) -> Option<usize> {
-        timestamps
-            .binary_search_by(|dt| dt.cmp(&target_dt))
-            .ok()
-            .or_else(|| {
-                // Find closest previous timestamp
-                let idx = timestamps.partition_point(|dt| dt < &target_dt);
-                if idx > 0 {
-                    Some(idx - 1)
-                } else {
-                    None
-                }
-            })
+    /// Bar at row `idx` of a frame
+    fn frame_bar(frame: &SessionFrame, idx: usize) -> Bar {
+        let value = |field: &str| frame.column(field).map_or(f64::NAN, |values| values[idx]);
+        Bar::new(
+            value("open"),
+            value("high"),
+            value("low"),
+            value("close"),
+            value("volume"),
+            frame.timestamp(idx),
+        )
     }
 
     /// Get available asset SIDs
@@ -353,32 +374,31 @@ impl BcolzMinuteBarReader {
         self.minutes_per_session
     }
 
-    /// Clear the cache
+    /// Clear the cache, including open column mappings
     pub fn clear_cache(&self) {
-        let mut cache = self.cache.write().unwrap();
-        cache.clear();
+        self.frames.clear();
+        self.columns.write().unwrap().clear();
     }
 
     /// Get cache size
     pub fn cache_size(&self) -> usize {
-        let cache = self.cache.read().unwrap();
-        cache.len()
+        self.frames.len()
     }
 
     /// Get all bars for a specific session
     pub fn get_session_bars(&self, asset: &Asset, session: SessionLabel) -> Result<Vec<Bar>> {
-        let session_data = self.load_session_data(asset.id, session)?;
-        Ok(session_data.bars)
+        let frame = self.session_frame(asset.id, session)?;
+        Ok((0..frame.len()).map(|i| Self::frame_bar(&frame, i)).collect())
     }
 }
 
 impl BarReader for BcolzMinuteBarReader {
     fn get_bar(&self, asset: &Asset, dt: DateTime<Utc>) -> Result<Bar> {
         let session = SessionLabel::from_datetime(dt);
-        let session_data = self.load_session_data(asset.id, session)?;
+        let frame = self.session_frame(asset.id, session)?;
 
-        if let Some(idx) = Self::find_bar_index(&session_data.timestamps, dt) {
-            Ok(session_data.bars[idx])
+        if let Some(idx) = frame.index_at_or_before(MinuteId::containing(dt)) {
+            Ok(Self::frame_bar(&frame, idx))
         } else {
             Err(ZiplineError::DataNotFound(format!(
                 "No bar data for asset {} at {:?}",
@@ -404,13 +424,9 @@ impl BarReader for BcolzMinuteBarReader {
         // Load bars from each session in the range
         for session_idx in start_idx..=end_idx.min(self.sessions.len().saturating_sub(1)) {
             let session = self.sessions[session_idx];
-            let session_data = self.load_session_data(asset.id, session)?;
-
-            for (bar, ts) in session_data.bars.iter().zip(session_data.timestamps.iter()) {
-                if *ts >= start && *ts <= end {
-                    all_bars.push(*bar);
-                }
-            }
+            let frame = self.session_frame(asset.id, session)?;
+            let minutes = frame.range(MinuteId::containing(start), MinuteId::containing(end));
+            all_bars.extend(minutes.map(|i| Self::frame_bar(&frame, i)));
         }
 
         Ok(all_bars)
@@ -419,8 +435,8 @@ impl BarReader for BcolzMinuteBarReader {
     fn last_available_dt(&self, asset: &Asset) -> Result<DateTime<Utc>> {
         // Load data from last session
         if let Some(&last_session) = self.sessions.last() {
-            let session_data = self.load_session_data(asset.id, last_session)?;
-            session_data.timestamps.last().copied().ok_or_else(|| {
+            let frame = self.session_frame(asset.id, last_session)?;
+            frame.minutes().last().map(|minute| minute.timestamp()).ok_or_else(|| {
                 ZiplineError::DataNotFound(format!("No data for asset {}", asset.symbol))
             })
         } else {
@@ -433,8 +449,8 @@ impl BarReader for BcolzMinuteBarReader {
     fn first_available_dt(&self, asset: &Asset) -> Result<DateTime<Utc>> {
         // Load data from first session
         if let Some(&first_session) = self.sessions.first() {
-            let session_data = self.load_session_data(asset.id, first_session)?;
-            session_data.timestamps.first().copied().ok_or_else(|| {
+            let frame = self.session_frame(asset.id, first_session)?;
+            frame.minutes().first().map(|minute| minute.timestamp()).ok_or_else(|| {
                 ZiplineError::DataNotFound(format!("No data for asset {}", asset.symbol))
             })
         } else {
@@ -467,6 +483,7 @@ mod tests {
         let meta_path = asset_path.join("meta");
         fs::create_dir_all(&meta_path)?;
 
+        use chrono::Timelike;
         let session_dt = session.to_datetime()?;
         let market_open = session_dt
             .with_hour(9)
@@ -530,6 +547,51 @@ mod tests {
         assert_eq!(bars.len(), 390);
     }
 
+    #[test]
+    fn test_session_reads_only_its_rows() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundle_path = temp_dir.path();
+        let minute_path = bundle_path.join("minute_equities");
+        let asset_path = minute_path.join("1");
+        fs::create_dir_all(asset_path.join("meta")).unwrap();
+
+        // Two sessions of 3 minutes, split across two chunks
+        let first = Utc.with_ymd_and_hms(2020, 1, 2, 14, 30, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2020, 1, 3, 14, 30, 0).unwrap();
+        let minutes: Vec<DateTime<Utc>> = (0..3)
+            .map(|i| first + chrono::Duration::minutes(i))
+            .chain((0..3).map(|i| second + chrono::Duration::minutes(i)))
+            .collect();
+
+        for (chunk, rows) in [(0, 0..4), (1, 4..6)] {
+            let name = |column: &str| asset_path.join(format!("{}.{:05}", column, chunk));
+            let ts: Vec<u8> = rows
+                .clone()
+                .flat_map(|i| minutes[i].timestamp().to_le_bytes())
+                .collect();
+            let prices: Vec<u8> = rows
+                .clone()
+                .flat_map(|i| (100.0 + i as f64).to_le_bytes())
+                .collect();
+            fs::write(name("minute"), ts).unwrap();
+            for column in ["open", "high", "low", "close", "volume"] {
+                fs::write(name(column), &prices).unwrap();
+            }
+        }
+
+        let reader = BcolzMinuteBarReader::us_equity(bundle_path, None).unwrap();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "TEST".to_string(), "NYSE".to_string(), start_date);
+
+        let session = SessionLabel::from_datetime(second);
+        let bars = reader.get_session_bars(&asset, session).unwrap();
+        let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![103.0, 104.0, 105.0]);
+
+        let bar = reader.get_bar(&asset, first + chrono::Duration::minutes(1)).unwrap();
+        assert_eq!(bar.close, 101.0);
+    }
+
     #[test]
     fn test_convert_timestamp() {
         // Test seconds
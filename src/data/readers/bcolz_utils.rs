@@ -3,12 +3,16 @@
 //! Utilities for reading and decompressing bcolz compressed columnar storage format.
 //! Bcolz is used by Python Zipline for efficient storage of time series data.
 
+use super::blosc;
 use crate::error::{Result, ZiplineError};
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 /// Bcolz table metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,6 +118,7 @@ impl BcolzChunk {
         if idx >= self.nelements {
             return Err(ZiplineError::IndexOutOfBounds(idx, self.nelements));
         }
+        self.expect_element_size(8)?;
 
         let offset = idx * self.element_size;
         if offset + 8 > self.data.len() {
@@ -134,6 +139,7 @@ impl BcolzChunk {
         if idx >= self.nelements {
             return Err(ZiplineError::IndexOutOfBounds(idx, self.nelements));
         }
+        self.expect_element_size(8)?;
 
         let offset = idx * self.element_size;
         if offset + 8 > self.data.len() {
@@ -148,6 +154,278 @@ impl BcolzChunk {
 
         Ok(i64::from_le_bytes(bytes))
     }
+
+    /// Get element at index as u32 (zipline's scaled OHLCV and day columns)
+    pub fn get_u32(&self, idx: usize) -> Result<u32> {
+        if idx >= self.nelements {
+            return Err(ZiplineError::IndexOutOfBounds(idx, self.nelements));
+        }
+        self.expect_element_size(4)?;
+
+        let offset = idx * 4;
+        let bytes: [u8; 4] = self.data[offset..offset + 4]
+            .try_into()
+            .map_err(|_| ZiplineError::InvalidData("Failed to extract u32 bytes".to_string()))?;
+
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn expect_element_size(&self, size: usize) -> Result<()> {
+        if self.element_size != size {
+            return Err(ZiplineError::InvalidData(format!(
+                "Chunk holds {}-byte elements, not {}-byte",
+                self.element_size, size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Bytes backing one chunk of a column view
+#[derive(Debug)]
+enum ChunkBytes {
+    /// Raw little-endian values, read in place from the page cache
+    Mapped(Mmap),
+    /// Values read from the chunk file, or decompressed from a blosc chunk
+    Owned(Vec<u8>),
+}
+
+impl ChunkBytes {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            ChunkBytes::Mapped(mmap) => mmap,
+            ChunkBytes::Owned(data) => data,
+        }
+    }
+}
+
+/// Memory-mapped view over a bcolz column of 8-byte values
+///
+/// Uncompressed chunk files are mapped rather than read, so opening a column
+/// costs no copies and only the pages actually touched are loaded. Chunks with
+/// a blosc header are decompressed once when the view is opened.
+///
+/// Values can be borrowed per chunk ([`ColumnView::chunks_f64`]), fetched by
+/// row, or copied out for a row range, so readers only materialize the rows a
+/// query needs.
+#[derive(Debug)]
+pub struct ColumnView {
+    path: PathBuf,
+    chunks: Vec<ChunkBytes>,
+    /// First row of each chunk, plus the total row count
+    offsets: Vec<usize>,
+}
+
+impl ColumnView {
+    /// Open a column of a bcolz table, mapping uncompressed chunk files
+    ///
+    /// # Safety
+    ///
+    /// The chunk files are memory-mapped, so they must not be modified or
+    /// truncated (by this or any other process) while the view is alive.
+    /// Use [`ColumnView::read`] when that cannot be guaranteed.
+    pub unsafe fn open(path: &Path, column_name: &str) -> Result<Self> {
+        Self::load(path, column_name, |file| {
+            // SAFETY: the caller guarantees the file outlives the view unchanged.
+            Ok(ChunkBytes::Mapped(unsafe { Mmap::map(file)? }))
+        })
+    }
+
+    /// Open a column of a bcolz table, reading every chunk into memory
+    ///
+    /// Safe counterpart of [`ColumnView::open`] that copies the chunk files
+    /// instead of mapping them.
+    pub fn read(path: &Path, column_name: &str) -> Result<Self> {
+        Self::load(path, column_name, |mut file| {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            Ok(ChunkBytes::Owned(data))
+        })
+    }
+
+    fn load(
+        path: &Path,
+        column_name: &str,
+        load_chunk: impl Fn(&fs::File) -> Result<ChunkBytes>,
+    ) -> Result<Self> {
+        let mut chunks = Vec::new();
+        let mut offsets = vec![0];
+
+        for chunk_idx in 0.. {
+            let chunk_file = path.join(format!("{}.{:05}", column_name, chunk_idx));
+            if !chunk_file.exists() {
+                break;
+            }
+
+            let file = fs::File::open(&chunk_file)?;
+            let raw = load_chunk(&file)?;
+
+            let chunk = if is_compressed(raw.as_bytes()) {
+                let chunk = decompress_chunk(raw.as_bytes())?;
+                if chunk.element_size != 8 {
+                    return Err(ZiplineError::InvalidData(format!(
+                        "Column {} in {:?} holds {}-byte values; column views read 8-byte values",
+                        column_name, path, chunk.element_size
+                    )));
+                }
+                ChunkBytes::Owned(chunk.data)
+            } else {
+                raw
+            };
+
+            let rows = chunk.as_bytes().len() / 8;
+            offsets.push(offsets[offsets.len() - 1] + rows);
+            chunks.push(chunk);
+        }
+
+        if chunks.is_empty() {
+            return Err(ZiplineError::DataNotFound(format!(
+                "No chunks found for column {} at {:?}",
+                column_name, path
+            )));
+        }
+
+        Ok(Self {
+            path: path.join(column_name),
+            chunks,
+            offsets,
+        })
+    }
+
+    /// Number of rows in the column
+    pub fn len(&self) -> usize {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    /// Check if the column has no rows
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether every chunk is read in place (mapped, with no blosc chunks decompressed)
+    pub fn is_zero_copy(&self) -> bool {
+        self.chunks.iter().all(|c| matches!(c, ChunkBytes::Mapped(_)))
+    }
+
+    /// Column values as f64, one slice per chunk
+    ///
+    /// Slices borrow the mapped file directly; a chunk is copied only if its
+    /// bytes are not 8-byte aligned or the target is big-endian.
+    pub fn chunks_f64(&self) -> impl Iterator<Item = Cow<'_, [f64]>> {
+        self.chunks.iter().map(|c| cast_slice(c.as_bytes()))
+    }
+
+    /// Column values as i64, one slice per chunk
+    pub fn chunks_i64(&self) -> impl Iterator<Item = Cow<'_, [i64]>> {
+        self.chunks.iter().map(|c| cast_slice(c.as_bytes()))
+    }
+
+    /// Value at `row` as f64
+    pub fn get_f64(&self, row: usize) -> Result<f64> {
+        self.element(row).map(f64::from_le_bytes)
+    }
+
+    /// Value at `row` as i64
+    pub fn get_i64(&self, row: usize) -> Result<i64> {
+        self.element(row).map(i64::from_le_bytes)
+    }
+
+    /// Copy the values in `rows` as f64
+    pub fn f64_range(&self, rows: Range<usize>) -> Result<Vec<f64>> {
+        self.copy_range(rows)
+    }
+
+    /// Copy the values in `rows` as i64
+    pub fn i64_range(&self, rows: Range<usize>) -> Result<Vec<i64>> {
+        self.copy_range(rows)
+    }
+
+    /// Copy the whole column as f64
+    pub fn to_vec_f64(&self) -> Vec<f64> {
+        self.chunks_f64().flat_map(|c| c.into_owned()).collect()
+    }
+
+    /// Copy the whole column as i64
+    pub fn to_vec_i64(&self) -> Vec<i64> {
+        self.chunks_i64().flat_map(|c| c.into_owned()).collect()
+    }
+
+    /// Chunk holding `row` and the row's index within it
+    fn locate(&self, row: usize) -> Result<(usize, usize)> {
+        if row >= self.len() {
+            return Err(ZiplineError::IndexOutOfBounds(row, self.len()));
+        }
+        let chunk = self.offsets.partition_point(|&start| start <= row) - 1;
+        Ok((chunk, row - self.offsets[chunk]))
+    }
+
+    fn element(&self, row: usize) -> Result<[u8; 8]> {
+        let (chunk, idx) = self.locate(row)?;
+        let bytes = &self.chunks[chunk].as_bytes()[idx * 8..idx * 8 + 8];
+        bytes.try_into().map_err(|_| {
+            ZiplineError::InvalidData(format!("Truncated value in {:?}", self.path))
+        })
+    }
+
+    fn copy_range<T: ColumnValue>(&self, rows: Range<usize>) -> Result<Vec<T>> {
+        if rows.end > self.len() || rows.start > rows.end {
+            return Err(ZiplineError::IndexOutOfBounds(rows.end, self.len()));
+        }
+
+        let mut values = Vec::with_capacity(rows.len());
+        let mut row = rows.start;
+        while row < rows.end {
+            let (chunk, idx) = self.locate(row)?;
+            let bytes = self.chunks[chunk].as_bytes();
+            let take = (self.offsets[chunk + 1] - row).min(rows.end - row);
+            values.extend(
+                bytes[idx * 8..(idx + take) * 8]
+                    .chunks_exact(8)
+                    .map(|b| T::from_le(b.try_into().unwrap())),
+            );
+            row += take;
+        }
+        Ok(values)
+    }
+}
+
+/// 8-byte column value types that are valid for any bit pattern
+trait ColumnValue: Copy {
+    fn from_le(bytes: [u8; 8]) -> Self;
+}
+
+impl ColumnValue for f64 {
+    fn from_le(bytes: [u8; 8]) -> Self {
+        f64::from_le_bytes(bytes)
+    }
+}
+
+impl ColumnValue for i64 {
+    fn from_le(bytes: [u8; 8]) -> Self {
+        i64::from_le_bytes(bytes)
+    }
+}
+
+/// Reinterpret little-endian bytes as 8-byte values, borrowing when possible
+fn cast_slice<T: ColumnValue>(bytes: &[u8]) -> Cow<'_, [T]> {
+    let whole = &bytes[..bytes.len() / 8 * 8];
+
+    if cfg!(target_endian = "little") {
+        // SAFETY: ColumnValue is only implemented for f64 and i64, which are
+        // valid for any bit pattern, and align_to only yields the middle slice
+        // where alignment holds.
+        let (head, values, tail) = unsafe { whole.align_to::<T>() };
+        if head.is_empty() && tail.is_empty() {
+            return Cow::Borrowed(values);
+        }
+    }
+
+    Cow::Owned(
+        whole
+            .chunks_exact(8)
+            .map(|b| T::from_le(b.try_into().unwrap()))
+            .collect(),
+    )
 }
 
 /// Read bcolz table attributes
@@ -197,8 +475,6 @@ pub fn read_bcolz_column(path: &Path, column_name: &str) -> Result<Vec<BcolzChun
         let mut compressed_data = Vec::new();
         file.read_to_end(&mut compressed_data)?;
 
-        // For now, assume uncompressed or handle basic formats
-        // In production, this would use blosc decompression
         let chunk = if is_compressed(&compressed_data) {
             decompress_chunk(&compressed_data)?
         } else {
@@ -220,101 +496,39 @@ pub fn read_bcolz_column(path: &Path, column_name: &str) -> Result<Vec<BcolzChun
 }
 
 /// Read an entire column into a Vec<f64>
+///
+/// Prefer [`ColumnView`] when only part of the column is needed.
 pub fn read_column_f64(path: &Path, column_name: &str) -> Result<Vec<f64>> {
-    let chunks = read_bcolz_column(path, column_name)?;
-    let mut values = Vec::new();
-
-    for chunk in chunks {
-        for i in 0..chunk.nelements {
-            values.push(chunk.get_f64(i)?);
-        }
-    }
-
-    Ok(values)
+    Ok(ColumnView::read(path, column_name)?.to_vec_f64())
 }
 
 /// Read an entire column into a Vec<i64>
 pub fn read_column_i64(path: &Path, column_name: &str) -> Result<Vec<i64>> {
-    let chunks = read_bcolz_column(path, column_name)?;
-    let mut values = Vec::new();
-
-    for chunk in chunks {
-        for i in 0..chunk.nelements {
-            values.push(chunk.get_i64(i)?);
-        }
-    }
-
-    Ok(values)
+    Ok(ColumnView::read(path, column_name)?.to_vec_i64())
 }
 
-/// Check if data is compressed (basic heuristic)
+/// Check if data is a blosc-compressed chunk
 fn is_compressed(data: &[u8]) -> bool {
-    // Blosc format starts with specific magic bytes
-    // Blosc magic: 0x02, 0x01 (version 2)
-    if data.len() < 16 {
-        return false;
-    }
-
-    // Check for blosc header
-    data[0] == 0x02 && (data[1] == 0x01 || data[1] == 0x02)
+    blosc::is_blosc_frame(data)
 }
 
 /// Decompress a bcolz chunk
+///
+/// Frames are decoded natively; with the `python-blosc` feature, frames using
+/// filters the native decoder does not handle (bitshuffle) fall back to the
+/// Python blosc package.
 fn decompress_chunk(compressed_data: &[u8]) -> Result<BcolzChunk> {
-    // NOTE: This is a placeholder. In production, you would use:
-    // - blosc-rs crate for Rust blosc bindings
-    // - Or use pyo3 to call Python blosc
-    // - Or implement blosc decompression
-
-    // For now, we'll assume the data needs blosc decompression
-    // but return an error since we haven't implemented it yet
-
-    #[cfg(feature = "python-blosc")]
-    {
-        decompress_with_python(compressed_data)
-    }
-
-    #[cfg(not(feature = "python-blosc"))]
-    {
-        // Fallback: try to parse header and return raw data
-        if compressed_data.len() < 16 {
-            return Err(ZiplineError::UnsupportedFeature(
-                "Blosc decompression not available - data too short".to_string(),
-            ));
-        }
-
-        // Blosc header structure (16 bytes):
-        // - version (1 byte)
-        // - versionlz (1 byte)
-        // - flags (1 byte)
-        // - typesize (1 byte)
-        // - nbytes (4 bytes) - uncompressed size
-        // - blocksize (4 bytes)
-        // - cbytes (4 bytes) - compressed size
-
-        let nbytes = u32::from_le_bytes([
-            compressed_data[4],
-            compressed_data[5],
-            compressed_data[6],
-            compressed_data[7],
-        ]) as usize;
-
-        let typesize = compressed_data[3] as usize;
-
-        // For testing/simple cases, assume uncompressed data follows header
-        if compressed_data.len() >= 16 + nbytes {
-            let data = compressed_data[16..16 + nbytes].to_vec();
-            Ok(BcolzChunk::new(data, typesize.max(8)))
-        } else {
-            Err(ZiplineError::UnsupportedFeature(
-                "Blosc decompression not available - enable 'python-blosc' feature or use conversion tool".to_string(),
-            ))
-        }
+    let typesize = blosc::BloscHeader::parse(compressed_data)?.typesize;
+    match blosc::decompress(compressed_data) {
+        Ok(data) => Ok(BcolzChunk::new(data, typesize)),
+        #[cfg(feature = "python-blosc")]
+        Err(ZiplineError::UnsupportedFeature(_)) => decompress_with_python(compressed_data, typesize),
+        Err(e) => Err(e),
     }
 }
 
 #[cfg(feature = "python-blosc")]
-fn decompress_with_python(compressed_data: &[u8]) -> Result<BcolzChunk> {
+fn decompress_with_python(compressed_data: &[u8], typesize: usize) -> Result<BcolzChunk> {
     use pyo3::prelude::*;
     use pyo3::types::PyBytes;
 
@@ -326,15 +540,14 @@ fn decompress_with_python(compressed_data: &[u8]) -> Result<BcolzChunk> {
         let decompressed: &PyBytes = decompress.call1((py_bytes,))?.extract()?;
 
         let data = decompressed.as_bytes().to_vec();
-        Ok(BcolzChunk::new(data, 8))
+        Ok(BcolzChunk::new(data, typesize))
     })
     .map_err(|e: PyErr| ZiplineError::DataError(format!("Python blosc error: {}", e)))
 }
 
 /// Estimate number of rows from column files
 fn estimate_rows(path: &Path, column_name: &str) -> Result<usize> {
-    let chunks = read_bcolz_column(path, column_name)?;
-    Ok(chunks.iter().map(|c| c.nelements).sum())
+    Ok(ColumnView::read(path, column_name)?.len())
 }
 
 /// Find all asset directories in a bcolz bundle
@@ -402,6 +615,85 @@ mod tests {
         assert_eq!(chunk.get_i64(9).unwrap(), 9);
     }
 
+    fn write_chunk(dir: &Path, name: &str, values: impl Iterator<Item = f64>) {
+        let mut file = fs::File::create(dir.join(name)).unwrap();
+        for v in values {
+            file.write_all(&v.to_le_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_column_view_across_chunks() {
+        let temp_dir = TempDir::new().unwrap();
+        write_chunk(temp_dir.path(), "close.00000", (0..4).map(|i| i as f64));
+        write_chunk(temp_dir.path(), "close.00001", (4..10).map(|i| i as f64));
+
+        // SAFETY: the temp files are not touched while the view is alive.
+        let view = unsafe { ColumnView::open(temp_dir.path(), "close") }.unwrap();
+        assert_eq!(view.len(), 10);
+        assert!(view.is_zero_copy());
+
+        let chunks: Vec<_> = view.chunks_f64().collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(chunks[0], Cow::Borrowed(_)));
+        assert_eq!(chunks[1].as_ref(), &[4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+
+        assert_eq!(view.get_f64(3).unwrap(), 3.0);
+        assert_eq!(view.get_f64(4).unwrap(), 4.0);
+        assert!(view.get_f64(10).is_err());
+        assert_eq!(view.f64_range(2..7).unwrap(), vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert!(view.f64_range(8..11).is_err());
+
+        assert_eq!(read_column_f64(temp_dir.path(), "close").unwrap().len(), 10);
+
+        let owned = ColumnView::read(temp_dir.path(), "close").unwrap();
+        assert!(!owned.is_zero_copy());
+        assert_eq!(owned.f64_range(2..7).unwrap(), view.f64_range(2..7).unwrap());
+    }
+
+    #[test]
+    fn test_column_view_blosc_header() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Memcpyed blosc frame (version 2, typesize 8, nbytes 24)
+        let mut data = vec![0x02, 0x01, 0x02, 0x08];
+        data.extend_from_slice(&24u32.to_le_bytes());
+        data.extend_from_slice(&24u32.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        for v in [1i64, 2, 3] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        fs::write(temp_dir.path().join("minute.00000"), data).unwrap();
+
+        let view = ColumnView::read(temp_dir.path(), "minute").unwrap();
+        assert!(!view.is_zero_copy());
+        assert_eq!(view.to_vec_i64(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_blosc_chunk_uses_header_typesize() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Memcpyed blosc frame of u32 days (version 2, typesize 4, nbytes 12)
+        let mut data = vec![0x02, 0x01, 0x02, 0x04];
+        data.extend_from_slice(&12u32.to_le_bytes());
+        data.extend_from_slice(&12u32.to_le_bytes());
+        data.extend_from_slice(&28u32.to_le_bytes());
+        for v in [19723u32, 19724, 19725] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        fs::write(temp_dir.path().join("day.00000"), data).unwrap();
+
+        let chunks = read_bcolz_column(temp_dir.path(), "day").unwrap();
+        assert_eq!(chunks[0].element_size, 4);
+        assert_eq!(chunks[0].nelements, 3);
+        assert_eq!(chunks[0].get_u32(2).unwrap(), 19725);
+        assert!(chunks[0].get_i64(0).is_err());
+
+        // Column views only read 8-byte values
+        assert!(ColumnView::read(temp_dir.path(), "day").is_err());
+    }
+
     #[test]
     fn test_read_bcolz_attrs() {
         let temp_dir = TempDir::new().unwrap();
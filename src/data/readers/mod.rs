@@ -6,6 +6,7 @@
 pub mod bcolz_daily;
 pub mod bcolz_minute;
 pub mod bcolz_utils;
+pub mod blosc;
 
 pub use bcolz_daily::BcolzDailyBarReader;
 pub use bcolz_minute::BcolzMinuteBarReader;
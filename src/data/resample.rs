@@ -52,6 +52,7 @@ struct OHLCVAggregator {
     low: Option<f64>,
     close: Option<f64>,
     volume: f64,
+    open_interest: Option<f64>,
     first_dt: Option<DateTime<Utc>>,
     last_dt: Option<DateTime<Utc>>,
 }
@@ -80,6 +81,11 @@ impl OHLCVAggregator {
         self.close = Some(bar.close);
         self.last_dt = Some(bar.dt);
 
+        // Open interest: last reported value
+        if bar.open_interest.is_some() {
+            self.open_interest = bar.open_interest;
+        }
+
         // Volume: sum of all volumes
         self.volume += bar.volume;
     }
@@ -101,6 +107,7 @@ impl OHLCVAggregator {
             })?,
             volume: self.volume,
             dt,
+            open_interest: self.open_interest,
         })
     }
 
@@ -551,6 +558,7 @@ mod tests {
             close,
             volume: 1000.0,
             dt,
+            open_interest: None,
         }
     }
 
@@ -0,0 +1,342 @@
+//! Immutable per-session bar frames shared between threads
+//!
+//! A [`SessionFrame`] holds one asset's minute bars for one session in columns.
+//! Frames are never modified once built, so they are handed out as
+//! `Arc<SessionFrame>` and read concurrently by the pipeline, metrics and
+//! strategy helpers without any locking.
+//!
+//! [`FrameCache`] publishes frames copy-on-write: readers clone the current
+//! frame set under a momentary read lock and do their lookups lock-free, while
+//! a loader builds a frame outside any lock and then publishes a new set. A
+//! [`FrameSnapshot`] pins one published set, so every read through it sees the
+//! same data even while other threads load further sessions.
+//!
+//! Bars are indexed by [`MinuteId`], so the lookups in per-bar loops compare
+//! integers, and frames are keyed by [`SessionId`].
+
+use crate::error::{Result, ZiplineError};
+use crate::types::{MinuteId, SessionId, Timestamp};
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+/// One asset's bars for one session, stored column-wise
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionFrame {
+    asset_id: u64,
+    session: SessionId,
+    minutes: Vec<MinuteId>,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+    volume: Vec<f64>,
+}
+
+impl SessionFrame {
+    /// Build a frame from `(timestamp, [open, high, low, close, volume])` rows
+    ///
+    /// Rows must be in time order. Each bar is indexed by the minute its
+    /// timestamp falls in.
+    pub fn from_rows<I>(asset_id: u64, session: SessionId, rows: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (Timestamp, [f64; 5])>,
+    {
+        let rows = rows.into_iter();
+        let capacity = rows.size_hint().0;
+        let mut frame = Self {
+            asset_id,
+            session,
+            minutes: Vec::with_capacity(capacity),
+            open: Vec::with_capacity(capacity),
+            high: Vec::with_capacity(capacity),
+            low: Vec::with_capacity(capacity),
+            close: Vec::with_capacity(capacity),
+            volume: Vec::with_capacity(capacity),
+        };
+
+        for (dt, [open, high, low, close, volume]) in rows {
+            let minute = MinuteId::containing(dt);
+            if frame.minutes.last().is_some_and(|last| *last > minute) {
+                return Err(ZiplineError::InvalidData(format!(
+                    "Bars for asset {} on {} are out of order at {}",
+                    asset_id, session, dt
+                )));
+            }
+            frame.minutes.push(minute);
+            frame.open.push(open);
+            frame.high.push(high);
+            frame.low.push(low);
+            frame.close.push(close);
+            frame.volume.push(volume);
+        }
+
+        Ok(frame)
+    }
+
+    /// Asset the bars belong to
+    pub fn asset_id(&self) -> u64 {
+        self.asset_id
+    }
+
+    /// Session the bars belong to
+    pub fn session(&self) -> SessionId {
+        self.session
+    }
+
+    /// Number of bars
+    pub fn len(&self) -> usize {
+        self.minutes.len()
+    }
+
+    /// Whether the session has no bars
+    pub fn is_empty(&self) -> bool {
+        self.minutes.is_empty()
+    }
+
+    /// Bar minutes
+    pub fn minutes(&self) -> &[MinuteId] {
+        &self.minutes
+    }
+
+    /// Start of the bar at `idx`
+    pub fn timestamp(&self, idx: usize) -> Timestamp {
+        self.minutes[idx].timestamp()
+    }
+
+    /// Values of an OHLCV field ("price" is an alias for "close")
+    pub fn column(&self, field: &str) -> Option<&[f64]> {
+        match field {
+            "open" => Some(&self.open),
+            "high" => Some(&self.high),
+            "low" => Some(&self.low),
+            "close" | "price" => Some(&self.close),
+            "volume" => Some(&self.volume),
+            _ => None,
+        }
+    }
+
+    /// Index of the last bar at or before `minute`
+    pub fn index_at_or_before(&self, minute: MinuteId) -> Option<usize> {
+        self.minutes.partition_point(|m| *m <= minute).checked_sub(1)
+    }
+
+    /// Value of `field` on the last bar at or before `minute`
+    pub fn value_at(&self, field: &str, minute: MinuteId) -> Option<f64> {
+        let idx = self.index_at_or_before(minute)?;
+        self.column(field).map(|values| values[idx])
+    }
+
+    /// Indices of the bars with `start <= minute <= end`
+    pub fn range(&self, start: MinuteId, end: MinuteId) -> Range<usize> {
+        let from = self.minutes.partition_point(|m| *m < start);
+        let to = self.minutes.partition_point(|m| *m <= end);
+        from..to.max(from)
+    }
+}
+
+type FrameKey = (u64, SessionId);
+
+/// An immutable set of published frames
+#[derive(Debug, Clone, Default)]
+struct FrameSet {
+    frames: HashMap<FrameKey, Arc<SessionFrame>>,
+    /// Publication order, oldest first, for eviction
+    order: VecDeque<FrameKey>,
+}
+
+/// A consistent view of the frames published at one point in time
+#[derive(Debug, Clone)]
+pub struct FrameSnapshot {
+    set: Arc<FrameSet>,
+}
+
+impl FrameSnapshot {
+    /// Frame for an asset and session, if it was published
+    pub fn get(&self, asset_id: u64, session: SessionId) -> Option<&Arc<SessionFrame>> {
+        self.set.frames.get(&(asset_id, session))
+    }
+
+    /// Number of frames in the snapshot
+    pub fn len(&self) -> usize {
+        self.set.frames.len()
+    }
+
+    /// Whether the snapshot holds no frames
+    pub fn is_empty(&self) -> bool {
+        self.set.frames.is_empty()
+    }
+}
+
+/// Bounded copy-on-write cache of session frames
+///
+/// Holds at most `capacity` frames; the oldest published frame is evicted
+/// first. Evicted frames stay valid for anyone still holding them.
+#[derive(Debug)]
+pub struct FrameCache {
+    capacity: usize,
+    published: RwLock<Arc<FrameSet>>,
+}
+
+impl FrameCache {
+    /// Create a cache holding at most `capacity` frames
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            published: RwLock::new(Arc::new(FrameSet::default())),
+        }
+    }
+
+    /// Maximum number of frames kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The currently published frames
+    pub fn snapshot(&self) -> FrameSnapshot {
+        FrameSnapshot {
+            set: self.published.read().unwrap().clone(),
+        }
+    }
+
+    /// Published frame for an asset and session
+    pub fn get(&self, asset_id: u64, session: SessionId) -> Option<Arc<SessionFrame>> {
+        self.snapshot().get(asset_id, session).cloned()
+    }
+
+    /// Published frame for an asset and session, loading it on a miss
+    ///
+    /// `load` runs without any lock held. If another thread publishes the same
+    /// frame first, its frame is returned and this one is dropped.
+    pub fn get_or_load<F>(&self, asset_id: u64, session: SessionId, load: F) -> Result<Arc<SessionFrame>>
+    where
+        F: FnOnce() -> Result<SessionFrame>,
+    {
+        if let Some(frame) = self.get(asset_id, session) {
+            return Ok(frame);
+        }
+        Ok(self.publish(load()?))
+    }
+
+    /// Publish a frame, returning the frame now cached for its key
+    pub fn publish(&self, frame: SessionFrame) -> Arc<SessionFrame> {
+        let key = (frame.asset_id, frame.session);
+        let mut published = self.published.write().unwrap();
+        if let Some(existing) = published.frames.get(&key) {
+            return existing.clone();
+        }
+
+        let mut set = FrameSet::clone(&published);
+        while set.frames.len() >= self.capacity {
+            match set.order.pop_front() {
+                Some(oldest) => {
+                    set.frames.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+
+        let frame = Arc::new(frame);
+        set.frames.insert(key, frame.clone());
+        set.order.push_back(key);
+        *published = Arc::new(set);
+        frame
+    }
+
+    /// Number of cached frames
+    pub fn len(&self) -> usize {
+        self.published.read().unwrap().frames.len()
+    }
+
+    /// Whether no frames are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every cached frame
+    pub fn clear(&self) {
+        *self.published.write().unwrap() = Arc::new(FrameSet::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+    fn session(day: u32) -> SessionId {
+        SessionId::from_date(NaiveDate::from_ymd_opt(2024, 1, day).unwrap())
+    }
+
+    fn frame(asset_id: u64, day: u32) -> SessionFrame {
+        let session = session(day);
+        let open = Utc.with_ymd_and_hms(2024, 1, day, 14, 31, 0).unwrap();
+        let rows = (0..3).map(|i| {
+            let price = 100.0 + i as f64;
+            (open + Duration::minutes(i), [price, price, price, price, 10.0])
+        });
+        SessionFrame::from_rows(asset_id, session, rows).unwrap()
+    }
+
+    #[test]
+    fn test_session_frame_lookups() {
+        let frame = frame(1, 2);
+        let open = frame.timestamp(0);
+        let minute = |offset: Duration| MinuteId::containing(open + offset);
+
+        assert_eq!(frame.len(), 3);
+        assert_eq!(frame.value_at("price", minute(Duration::seconds(90))), Some(101.0));
+        assert_eq!(frame.value_at("close", minute(-Duration::minutes(1))), None);
+        assert_eq!(frame.value_at("vwap", minute(Duration::zero())), None);
+        assert_eq!(frame.range(minute(Duration::minutes(1)), minute(Duration::minutes(5))), 1..3);
+
+        let unordered = [(open, [1.0; 5]), (open - Duration::minutes(1), [1.0; 5])];
+        assert!(SessionFrame::from_rows(1, frame.session(), unordered).is_err());
+    }
+
+    #[test]
+    fn test_frame_cache_publishes_copy_on_write() {
+        let cache = FrameCache::new(2);
+        let day2 = session(2);
+
+        let first = cache.get_or_load(1, day2, || Ok(frame(1, 2))).unwrap();
+        let snapshot = cache.snapshot();
+
+        // A hit does not reload
+        let again = cache.get_or_load(1, day2, || panic!("reloaded")).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        // Publishing more frames evicts the oldest, but the snapshot is unchanged
+        cache.publish(frame(2, 2));
+        cache.publish(frame(1, 3));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(1, day2).is_none());
+        assert_eq!(snapshot.len(), 1);
+        assert!(Arc::ptr_eq(snapshot.get(1, day2).unwrap(), &first));
+    }
+
+    #[test]
+    fn test_frames_read_from_many_threads() {
+        let cache = Arc::new(FrameCache::new(64));
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    let mut total = 0.0;
+                    for day in 2..12 {
+                        let frame = cache
+                            .get_or_load(t % 2, session(day), || Ok(frame(t % 2, day)))
+                            .unwrap();
+                        total += frame.column("close").unwrap().iter().sum::<f64>();
+                    }
+                    total
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 10.0 * 303.0);
+        }
+        assert_eq!(cache.len(), 20);
+    }
+}
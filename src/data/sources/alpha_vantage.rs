@@ -28,7 +28,7 @@ struct AlphaVantageResponse {
     time_series_intraday: HashMap<String, HashMap<String, TimeSeriesData>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct TimeSeriesData {
     #[serde(rename = "1. open")]
     open: String,
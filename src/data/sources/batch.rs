@@ -0,0 +1,507 @@
+//! Concurrent batch downloading for external data sources
+//!
+//! Fetching a large universe one symbol at a time is slow, while firing every
+//! request at once trips provider API limits. `BatchFetcher` sits in between:
+//! - Bounded concurrency (number of requests in flight)
+//! - Token-bucket rate limiting, shareable between fetchers hitting the same source
+//! - Retry with exponential backoff for failed requests
+//! - Progress callbacks as each symbol completes
+//!
+//! # Example
+//! ```ignore
+//! let source = Arc::new(YahooFinanceSource::new()?);
+//! let fetcher = BatchFetcher::new()
+//!     .with_concurrency(8)
+//!     .with_rate_limiter(Arc::new(RateLimiter::per_second(5)))
+//!     .on_progress(|p| println!("{}/{} {}", p.completed, p.total, p.symbol));
+//!
+//! let result = fetcher
+//!     .fetch_all(&symbols, move |symbol| {
+//!         let source = source.clone();
+//!         async move { source.fetch_historical(&symbol, start, end).await }
+//!     })
+//!     .await;
+//! ```
+
+use crate::error::{Result, ZiplineError};
+use crate::types::Bar;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+/// Token-bucket rate limiter
+///
+/// Holds up to `capacity` tokens, refilled continuously at `refill_rate` tokens
+/// per second. Each request consumes one token, waiting if none are available.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_rate: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter with a burst capacity and refill rate (tokens per second)
+    pub fn new(capacity: u32, refill_rate: f64) -> Result<Self> {
+        if capacity == 0 || refill_rate <= 0.0 || !refill_rate.is_finite() {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Rate limiter needs positive capacity and refill rate, got {} and {}",
+                capacity, refill_rate
+            )));
+        }
+
+        Ok(Self {
+            capacity: capacity as f64,
+            refill_rate,
+            state: Mutex::new(BucketState {
+                tokens: capacity as f64,
+                last_refill: Instant::now(),
+            }),
+        })
+    }
+
+    /// Allow `requests` requests per second
+    pub fn per_second(requests: u32) -> Self {
+        Self::per_period(requests, Duration::from_secs(1))
+    }
+
+    /// Allow `requests` requests per minute (e.g. Alpha Vantage free tier: 5)
+    pub fn per_minute(requests: u32) -> Self {
+        Self::per_period(requests, Duration::from_secs(60))
+    }
+
+    /// Allow `requests` requests per `period`, bursting up to `requests`
+    pub fn per_period(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        let seconds = period.as_secs_f64().max(f64::EPSILON);
+        Self {
+            capacity: requests as f64,
+            refill_rate: requests as f64 / seconds,
+            state: Mutex::new(BucketState {
+                tokens: requests as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a token is available and consume it
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                self.refill(&mut state);
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - state.tokens) / self.refill_rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Consume a token if one is available without waiting
+    pub async fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().await;
+        self.refill(&mut state);
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&self, state: &mut BucketState) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill_rate).min(self.capacity);
+        state.last_refill = now;
+    }
+}
+
+/// Retry policy with exponential backoff
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on any single delay
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each retry
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (0-based)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry as i32);
+        let delay = self.initial_backoff.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// Progress update emitted as each symbol completes
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProgress {
+    /// Symbol that just completed
+    pub symbol: String,
+    /// Whether it was fetched successfully
+    pub success: bool,
+    /// Attempts made for this symbol
+    pub attempts: u32,
+    /// Symbols completed so far
+    pub completed: usize,
+    /// Symbols in the batch
+    pub total: usize,
+}
+
+impl BatchProgress {
+    /// Fraction of the batch completed
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.completed as f64 / self.total as f64
+    }
+}
+
+/// Outcome of a batch fetch
+#[derive(Debug, Clone, Default)]
+pub struct BatchResult {
+    /// Bars by symbol for successful fetches
+    pub bars: HashMap<String, Vec<Bar>>,
+    /// Error message by symbol for fetches that failed after all retries
+    pub failures: HashMap<String, String>,
+}
+
+impl BatchResult {
+    /// Check if every symbol was fetched
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Number of symbols fetched successfully
+    pub fn success_count(&self) -> usize {
+        self.bars.len()
+    }
+
+    /// Number of symbols that failed
+    pub fn failure_count(&self) -> usize {
+        self.failures.len()
+    }
+}
+
+type ProgressCallback = Arc<dyn Fn(&BatchProgress) + Send + Sync>;
+
+/// Concurrent, rate-limited batch fetcher
+#[derive(Clone)]
+pub struct BatchFetcher {
+    concurrency: usize,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    retry_policy: RetryPolicy,
+    progress: Option<ProgressCallback>,
+}
+
+impl std::fmt::Debug for BatchFetcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchFetcher")
+            .field("concurrency", &self.concurrency)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("retry_policy", &self.retry_policy)
+            .field("progress", &self.progress.as_ref().map(|_| "<callback>"))
+            .finish()
+    }
+}
+
+impl Default for BatchFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchFetcher {
+    /// Create a fetcher with 4 concurrent requests, no rate limit and the default retry policy
+    pub fn new() -> Self {
+        Self {
+            concurrency: 4,
+            rate_limiter: None,
+            retry_policy: RetryPolicy::default(),
+            progress: None,
+        }
+    }
+
+    /// Set the maximum number of requests in flight
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Rate limit requests (share the same limiter between fetchers for one source)
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Register a callback invoked as each symbol completes
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&BatchProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Fetch bars for every symbol using `fetch`
+    ///
+    /// Every attempt, including retries, waits for a rate limiter token. A
+    /// symbol's failure does not abort the batch; it is reported in
+    /// [`BatchResult::failures`]. Duplicate symbols are fetched once.
+    pub async fn fetch_all<S, F, Fut>(&self, symbols: &[S], fetch: F) -> BatchResult
+    where
+        S: AsRef<str>,
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<Bar>>> + Send + 'static,
+    {
+        let mut unique: Vec<String> = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            let symbol = symbol.as_ref();
+            if !unique.iter().any(|s| s == symbol) {
+                unique.push(symbol.to_string());
+            }
+        }
+
+        let total = unique.len();
+        let fetch = Arc::new(fetch);
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let mut tasks = JoinSet::new();
+
+        for symbol in unique {
+            let fetch = fetch.clone();
+            let semaphore = semaphore.clone();
+            let limiter = self.rate_limiter.clone();
+            let policy = self.retry_policy;
+
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let (outcome, attempts) =
+                    Self::fetch_with_retry(&symbol, fetch.as_ref(), limiter.as_deref(), policy).await;
+                (symbol, outcome, attempts)
+            });
+        }
+
+        let mut result = BatchResult::default();
+        let mut completed = 0;
+
+        while let Some(joined) = tasks.join_next().await {
+            let (symbol, outcome, attempts) = match joined {
+                Ok(done) => done,
+                Err(e) => {
+                    tracing::error!(error = %e, "Batch fetch task failed");
+                    continue;
+                }
+            };
+
+            completed += 1;
+            let success = outcome.is_ok();
+            match outcome {
+                Ok(bars) => {
+                    result.bars.insert(symbol.clone(), bars);
+                }
+                Err(e) => {
+                    tracing::warn!(%symbol, attempts, error = %e, "Failed to fetch data");
+                    result.failures.insert(symbol.clone(), e.to_string());
+                }
+            }
+
+            if let Some(callback) = &self.progress {
+                callback(&BatchProgress {
+                    symbol,
+                    success,
+                    attempts,
+                    completed,
+                    total,
+                });
+            }
+        }
+
+        result
+    }
+
+    async fn fetch_with_retry<F, Fut>(
+        symbol: &str,
+        fetch: &F,
+        limiter: Option<&RateLimiter>,
+        policy: RetryPolicy,
+    ) -> (Result<Vec<Bar>>, u32)
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Vec<Bar>>>,
+    {
+        let mut attempts = 0;
+        loop {
+            if let Some(limiter) = limiter {
+                limiter.acquire().await;
+            }
+
+            attempts += 1;
+            match fetch(symbol.to_string()).await {
+                Ok(bars) => return (Ok(bars), attempts),
+                Err(e) if attempts > policy.max_retries => return (Err(e), attempts),
+                Err(e) => {
+                    let delay = policy.backoff(attempts - 1);
+                    tracing::debug!(%symbol, ?delay, error = %e, "Retrying fetch");
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fast_retry(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            multiplier: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            multiplier: 2.0,
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_bucket() {
+        let limiter = RateLimiter::new(2, 20.0).unwrap();
+
+        assert!(limiter.try_acquire().await);
+        assert!(limiter.try_acquire().await);
+        assert!(!limiter.try_acquire().await);
+
+        // A token refills after ~50ms
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        assert!(RateLimiter::new(0, 1.0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_with_concurrency_limit() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let progress_calls = Arc::new(AtomicUsize::new(0));
+
+        let symbols: Vec<String> = (0..10).map(|i| format!("SYM{}", i)).collect();
+        let progress = progress_calls.clone();
+        let fetcher = BatchFetcher::new()
+            .with_concurrency(3)
+            .on_progress(move |p| {
+                assert!(p.success);
+                assert!(p.completed <= p.total);
+                progress.fetch_add(1, Ordering::SeqCst);
+            });
+
+        let (counter, max_seen) = (in_flight.clone(), peak.clone());
+        let result = fetcher
+            .fetch_all(&symbols, move |_symbol| {
+                let (counter, max_seen) = (counter.clone(), max_seen.clone());
+                async move {
+                    let now = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_seen.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    counter.fetch_sub(1, Ordering::SeqCst);
+                    Ok(Vec::new())
+                }
+            })
+            .await;
+
+        assert!(result.is_complete());
+        assert_eq!(result.success_count(), 10);
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert_eq!(progress_calls.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_retries_then_reports_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let fetcher = BatchFetcher::new()
+            .with_concurrency(1)
+            .with_retry_policy(fast_retry(2));
+        let result = fetcher
+            .fetch_all(&["FLAKY", "BROKEN", "FLAKY"], move |symbol| {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match symbol.as_str() {
+                        // Fails once, then succeeds
+                        "FLAKY" if attempt == 0 => Err(ZiplineError::DataError("timeout".to_string())),
+                        "FLAKY" => Ok(Vec::new()),
+                        _ => Err(ZiplineError::DataError("not found".to_string())),
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(result.success_count(), 1);
+        assert!(result.bars.contains_key("FLAKY"));
+        assert_eq!(result.failure_count(), 1);
+        assert!(result.failures["BROKEN"].contains("not found"));
+    }
+}
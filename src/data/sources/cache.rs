@@ -0,0 +1,512 @@
+//! On-disk cache for external data sources
+//!
+//! Fetched bars are stored as JSON files under
+//! `<root>/<source>/<symbol>/<start>_<end>.json`, so repeat fetches of the same
+//! source/symbol/date range during development are served locally instead of
+//! hitting the network. Entries older than the cache TTL are treated as misses.
+//!
+//! Wrap a source in [`CachedSource`] to have its fetches go through the cache.
+
+use crate::error::{Result, ZiplineError};
+use crate::types::Bar;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+/// Current on-disk entry format
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// Identifies one cached fetch
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub source: String,
+    pub symbol: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl CacheKey {
+    pub fn new(source: &str, symbol: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            source: source.to_string(),
+            symbol: symbol.to_string(),
+            start,
+            end,
+        }
+    }
+
+    /// Path of this entry relative to the cache root
+    fn relative_path(&self) -> PathBuf {
+        PathBuf::from(sanitize(&self.source))
+            .join(sanitize(&self.symbol))
+            .join(format!(
+                "{}_{}.json",
+                self.start.format("%Y%m%dT%H%M%S"),
+                self.end.format("%Y%m%dT%H%M%S")
+            ))
+    }
+}
+
+/// Make a key component safe to use as a single path segment
+///
+/// Bytes outside a small safe set are percent-escaped, so distinct components
+/// ("WIKI/AAPL" and "WIKI_AAPL") never share a path.
+fn sanitize(component: &str) -> String {
+    use std::fmt::Write;
+
+    let mut escaped = String::with_capacity(component.len());
+    for byte in component.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'^' | b'=') {
+            escaped.push(byte as char);
+        } else {
+            let _ = write!(escaped, "%{:02X}", byte);
+        }
+    }
+
+    match escaped.as_str() {
+        "" => "%".to_string(),
+        "." => "%2E".to_string(),
+        ".." => "%2E%2E".to_string(),
+        _ => escaped,
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    version: u32,
+    fetched_at: DateTime<Utc>,
+    bars: Vec<Bar>,
+}
+
+/// Summary of a cache purge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeStats {
+    pub files_removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Disk cache keyed by source, symbol and date range
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    root: PathBuf,
+    /// Maximum entry age; `None` keeps entries until purged
+    ttl: Option<Duration>,
+}
+
+impl DiskCache {
+    /// Create a cache rooted at `root` with a one day TTL
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            ttl: Some(Duration::days(1)),
+        }
+    }
+
+    /// Set the maximum age of a cached entry
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep entries until they are purged explicitly
+    pub fn without_ttl(mut self) -> Self {
+        self.ttl = None;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Full path of the entry for `key`
+    pub fn path_for(&self, key: &CacheKey) -> PathBuf {
+        self.root.join(key.relative_path())
+    }
+
+    /// Read cached bars, returning `None` on a miss or an expired entry
+    ///
+    /// Entries that cannot be parsed (e.g. written by an older format) are
+    /// removed and treated as misses.
+    pub fn get(&self, key: &CacheKey) -> Result<Option<Vec<Bar>>> {
+        let path = self.path_for(key);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let entry: CacheEntry = match serde_json::from_str(&contents) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Discarding unreadable cache entry");
+                fs::remove_file(&path)?;
+                return Ok(None);
+            }
+        };
+
+        if entry.version != CACHE_FORMAT_VERSION {
+            fs::remove_file(&path)?;
+            return Ok(None);
+        }
+
+        if self.is_expired(entry.fetched_at) {
+            tracing::debug!(path = %path.display(), "Cache entry expired");
+            return Ok(None);
+        }
+
+        Ok(Some(entry.bars))
+    }
+
+    /// Store bars for `key`, replacing any existing entry
+    pub fn put(&self, key: &CacheKey, bars: &[Bar]) -> Result<()> {
+        let path = self.path_for(key);
+        let dir = path.parent().ok_or_else(|| {
+            ZiplineError::InvalidData(format!("Invalid cache path {}", path.display()))
+        })?;
+        fs::create_dir_all(dir)?;
+
+        let entry = CacheEntry {
+            version: CACHE_FORMAT_VERSION,
+            fetched_at: Utc::now(),
+            bars: bars.to_vec(),
+        };
+
+        // Write then rename so readers never see a partial entry
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Return cached bars for `key`, calling `fetch` and caching its result on a miss
+    ///
+    /// Failed fetches are not cached.
+    pub async fn get_or_fetch<F, Fut>(&self, key: &CacheKey, fetch: F) -> Result<Vec<Bar>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<Bar>>>,
+    {
+        if let Some(bars) = self.get(key)? {
+            tracing::debug!(source = %key.source, symbol = %key.symbol, "Cache hit");
+            return Ok(bars);
+        }
+
+        let bars = fetch().await?;
+        self.put(key, &bars)?;
+        Ok(bars)
+    }
+
+    /// Remove the entry for `key`, returning whether one existed
+    pub fn invalidate(&self, key: &CacheKey) -> Result<bool> {
+        match fs::remove_file(self.path_for(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Remove every entry, or only those of one source
+    pub fn purge(&self, source: Option<&str>) -> Result<PurgeStats> {
+        let dir = match source {
+            Some(source) => self.root.join(sanitize(source)),
+            None => self.root.clone(),
+        };
+
+        let mut stats = PurgeStats::default();
+        purge_dir(&dir, &mut stats, &|_| true)?;
+        if source.is_some() && dir.exists() {
+            fs::remove_dir(&dir)?;
+        }
+        Ok(stats)
+    }
+
+    /// Remove entries older than the TTL
+    pub fn purge_expired(&self) -> Result<PurgeStats> {
+        let mut stats = PurgeStats::default();
+        purge_dir(&self.root, &mut stats, &|path| {
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|contents| serde_json::from_str::<CacheEntry>(&contents).ok())
+                .is_none_or(|entry| self.is_expired(entry.fetched_at))
+        })?;
+        Ok(stats)
+    }
+
+    /// Number of entries and total bytes on disk
+    pub fn usage(&self) -> Result<(usize, u64)> {
+        let mut files = 0;
+        let mut bytes = 0;
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for entry in entries {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                } else {
+                    files += 1;
+                    bytes += metadata.len();
+                }
+            }
+        }
+        Ok((files, bytes))
+    }
+
+    fn is_expired(&self, fetched_at: DateTime<Utc>) -> bool {
+        match self.ttl {
+            Some(ttl) => Utc::now() - fetched_at > ttl,
+            None => false,
+        }
+    }
+}
+
+/// External data source whose fetches are served from a [`DiskCache`]
+///
+/// Entries are keyed by the wrapped source's name, so several cached sources
+/// can share one cache root.
+#[cfg(feature = "async")]
+pub struct CachedSource<S> {
+    inner: S,
+    cache: DiskCache,
+}
+
+#[cfg(feature = "async")]
+impl<S: super::ExternalDataSource> CachedSource<S> {
+    pub fn new(inner: S, cache: DiskCache) -> Self {
+        Self { inner, cache }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn cache(&self) -> &DiskCache {
+        &self.cache
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: super::ExternalDataSource> super::ExternalDataSource for CachedSource<S> {
+    fn fetch_historical<'a>(
+        &'a self,
+        symbol: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> super::FetchFuture<'a> {
+        Box::pin(async move {
+            let key = CacheKey::new(self.inner.name(), symbol, start, end);
+            self.cache
+                .get_or_fetch(&key, || self.inner.fetch_historical(symbol, start, end))
+                .await
+        })
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+/// Recursively remove files under `dir` accepted by `should_remove`,
+/// pruning directories left empty
+fn purge_dir(dir: &Path, stats: &mut PurgeStats, should_remove: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            purge_dir(&path, stats, should_remove)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        } else if should_remove(&path) {
+            fs::remove_file(&path)?;
+            stats.files_removed += 1;
+            stats.bytes_freed += metadata.len();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn key(source: &str, symbol: &str) -> CacheKey {
+        CacheKey::new(
+            source,
+            symbol,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap(),
+        )
+    }
+
+    fn bars() -> Vec<Bar> {
+        (0..3)
+            .map(|i| {
+                Bar::new(
+                    Utc.with_ymd_and_hms(2024, 1, 2 + i, 0, 0, 0).unwrap(),
+                    100.0,
+                    101.0,
+                    99.0,
+                    100.5,
+                    1000.0,
+                )
+            })
+            .collect()
+    }
+
+    /// Drive a future that never waits on I/O to completion
+    fn block_on<F: Future>(future: F) -> F::Output {
+        use std::task::{Context, Poll, Waker};
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_put_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path());
+        let key = key("yahoo", "AAPL");
+
+        assert!(cache.get(&key).unwrap().is_none());
+        cache.put(&key, &bars()).unwrap();
+
+        let cached = cache.get(&key).unwrap().unwrap();
+        assert_eq!(cached.len(), 3);
+        assert_eq!(cached[2].close, 100.5);
+        assert!(cache.path_for(&key).starts_with(dir.path().join("yahoo").join("AAPL")));
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = key("yahoo", "AAPL");
+
+        let expired = DiskCache::new(dir.path()).with_ttl(Duration::seconds(-1));
+        expired.put(&key, &bars()).unwrap();
+        assert!(expired.get(&key).unwrap().is_none());
+
+        let fresh = DiskCache::new(dir.path()).without_ttl();
+        assert!(fresh.get(&key).unwrap().is_some());
+
+        assert_eq!(fresh.purge_expired().unwrap().files_removed, 0);
+        assert_eq!(expired.purge_expired().unwrap().files_removed, 1);
+        assert!(fresh.get(&key).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_get_or_fetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path());
+        let key = key("quandl", "WIKI/AAPL");
+        let mut calls = 0;
+
+        for _ in 0..2 {
+            let result = block_on(cache.get_or_fetch(&key, || {
+                calls += 1;
+                async { Ok(bars()) }
+            }));
+            assert_eq!(result.unwrap().len(), 3);
+        }
+        assert_eq!(calls, 1);
+
+        // Errors are passed through and not cached
+        let other = self::key("quandl", "WIKI/MSFT");
+        let result = block_on(cache.get_or_fetch(&other, || async {
+            Err(ZiplineError::DataError("offline".to_string()))
+        }));
+        assert!(result.is_err());
+        assert!(cache.get(&other).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path());
+        cache.put(&key("yahoo", "AAPL"), &bars()).unwrap();
+        cache.put(&key("yahoo", "MSFT"), &bars()).unwrap();
+        cache.put(&key("quandl", "AAPL"), &bars()).unwrap();
+        assert_eq!(cache.usage().unwrap().0, 3);
+
+        let stats = cache.purge(Some("yahoo")).unwrap();
+        assert_eq!(stats.files_removed, 2);
+        assert!(stats.bytes_freed > 0);
+        assert!(!dir.path().join("yahoo").exists());
+        assert!(cache.get(&key("quandl", "AAPL")).unwrap().is_some());
+
+        assert!(cache.invalidate(&key("quandl", "AAPL")).unwrap());
+        assert!(!cache.invalidate(&key("quandl", "AAPL")).unwrap());
+        assert_eq!(cache.purge(None).unwrap().files_removed, 0);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("WIKI/AAPL"), "WIKI%2FAAPL");
+        assert_eq!(sanitize("WIKI_AAPL"), "WIKI_AAPL");
+        assert_eq!(sanitize("WIKI%2FAAPL"), "WIKI%252FAAPL");
+        assert_eq!(sanitize("^GSPC"), "^GSPC");
+        assert_eq!(sanitize(".."), "%2E%2E");
+        assert_eq!(sanitize(""), "%");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_cached_source() {
+        use crate::data::sources::{ExternalDataSource, FetchFuture};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountingSource(AtomicUsize);
+
+        impl ExternalDataSource for CountingSource {
+            fn fetch_historical<'a>(
+                &'a self,
+                _symbol: &'a str,
+                _start: DateTime<Utc>,
+                _end: DateTime<Utc>,
+            ) -> FetchFuture<'a> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Ok(bars()) })
+            }
+
+            fn name(&self) -> &str {
+                "counting"
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = CachedSource::new(CountingSource(AtomicUsize::new(0)), DiskCache::new(dir.path()));
+        let key = key("counting", "WIKI/AAPL");
+
+        for symbol in ["WIKI/AAPL", "WIKI/AAPL", "WIKI_AAPL"] {
+            let bars = block_on(source.fetch_historical(symbol, key.start, key.end)).unwrap();
+            assert_eq!(bars.len(), 3);
+        }
+        assert_eq!(source.inner().0.load(Ordering::SeqCst), 2);
+        assert!(source.cache().get(&key).unwrap().is_some());
+    }
+}
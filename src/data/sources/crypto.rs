@@ -0,0 +1,428 @@
+//! Binance and Coinbase crypto OHLCV data sources
+//!
+//! Both exchanges serve candles over public REST endpoints, capped per
+//! request (1000 on Binance, 300 on Coinbase), so longer ranges are fetched
+//! page by page. Candles are labelled by their open time, like the minute bar
+//! stores, and returned oldest first without duplicates.
+//!
+//! Crypto assets are named by pair, e.g. `BTC-USD`, `ETH/BTC` or `SOL_USDT`.
+//! Binance lists dollar pairs against stablecoins, so its source maps a `USD`
+//! quote to `USDT` unless configured otherwise.
+//!
+//! # Example
+//! ```ignore
+//! let source = CryptoDataSource::new(CryptoExchange::Coinbase)?
+//!     .with_interval(CryptoInterval::FiveMinutes);
+//! let bars = source.fetch_asset(&btc, start, end).await?;
+//! ```
+
+use super::batch::{RateLimiter, RetryPolicy};
+use super::{ExternalDataSource, FetchFuture};
+use crate::asset::{Asset, AssetType};
+use crate::error::{Result, ZiplineError};
+use crate::types::Bar;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const BINANCE_BASE_URL: &str = "https://api.binance.com";
+const COINBASE_BASE_URL: &str = "https://api.exchange.coinbase.com";
+
+/// Exchange serving the candles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CryptoExchange {
+    Binance,
+    Coinbase,
+}
+
+impl CryptoExchange {
+    /// Most candles returned by one request
+    pub fn page_limit(&self) -> usize {
+        match self {
+            Self::Binance => 1000,
+            Self::Coinbase => 300,
+        }
+    }
+
+    fn base_url(&self) -> &'static str {
+        match self {
+            Self::Binance => BINANCE_BASE_URL,
+            Self::Coinbase => COINBASE_BASE_URL,
+        }
+    }
+}
+
+/// Candle granularity offered by both exchanges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CryptoInterval {
+    OneMinute,
+    FiveMinutes,
+    FifteenMinutes,
+    OneHour,
+    SixHours,
+    OneDay,
+}
+
+impl CryptoInterval {
+    /// Length of one candle
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.seconds())
+    }
+
+    fn seconds(&self) -> i64 {
+        match self {
+            Self::OneMinute => 60,
+            Self::FiveMinutes => 300,
+            Self::FifteenMinutes => 900,
+            Self::OneHour => 3_600,
+            Self::SixHours => 21_600,
+            Self::OneDay => 86_400,
+        }
+    }
+
+    fn binance_code(&self) -> &'static str {
+        match self {
+            Self::OneMinute => "1m",
+            Self::FiveMinutes => "5m",
+            Self::FifteenMinutes => "15m",
+            Self::OneHour => "1h",
+            Self::SixHours => "6h",
+            Self::OneDay => "1d",
+        }
+    }
+}
+
+/// Base and quote currencies of a trading pair
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CryptoPair {
+    pub base: String,
+    pub quote: String,
+}
+
+impl CryptoPair {
+    /// Parse `BASE-QUOTE`, `BASE/QUOTE` or `BASE_QUOTE`, case-insensitively
+    pub fn parse(symbol: &str) -> Result<Self> {
+        let mut parts = symbol.trim().split(['-', '/', '_']);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(base), Some(quote), None) if !base.is_empty() && !quote.is_empty() => Ok(Self {
+                base: base.to_uppercase(),
+                quote: quote.to_uppercase(),
+            }),
+            _ => Err(ZiplineError::InvalidData(format!(
+                "Crypto symbol '{}' is not a BASE-QUOTE pair",
+                symbol
+            ))),
+        }
+    }
+}
+
+/// Crypto candle source for one exchange and interval
+pub struct CryptoDataSource {
+    exchange: CryptoExchange,
+    interval: CryptoInterval,
+    base_url: String,
+    client: Client,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+    quote_aliases: HashMap<String, String>,
+}
+
+impl CryptoDataSource {
+    /// Create a daily candle source for `exchange`
+    pub fn new(exchange: CryptoExchange) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .user_agent("rusty-zipline")
+            .build()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to create HTTP client: {}", e)))?;
+
+        let mut quote_aliases = HashMap::new();
+        if exchange == CryptoExchange::Binance {
+            quote_aliases.insert("USD".to_string(), "USDT".to_string());
+        }
+        Ok(Self {
+            exchange,
+            interval: CryptoInterval::OneDay,
+            base_url: exchange.base_url().to_string(),
+            client,
+            rate_limiter: Arc::new(RateLimiter::per_second(5)),
+            retry_policy: RetryPolicy::default(),
+            quote_aliases,
+        })
+    }
+
+    /// Set the candle granularity
+    pub fn with_interval(mut self, interval: CryptoInterval) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Point at another deployment of the same API, e.g. `https://api.binance.us`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Rate limit requests (share the limiter between sources for one exchange)
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Request pairs quoted in `quote` against `listed` instead
+    pub fn with_quote_alias(mut self, quote: &str, listed: &str) -> Self {
+        self.quote_aliases.insert(quote.to_uppercase(), listed.to_uppercase());
+        self
+    }
+
+    /// Exchange's name for the pair in `symbol`: `BTCUSDT` on Binance,
+    /// `BTC-USD` on Coinbase
+    pub fn market_symbol(&self, symbol: &str) -> Result<String> {
+        let pair = CryptoPair::parse(symbol)?;
+        let quote = self.quote_aliases.get(&pair.quote).unwrap_or(&pair.quote);
+        Ok(match self.exchange {
+            CryptoExchange::Binance => format!("{}{}", pair.base, quote),
+            CryptoExchange::Coinbase => format!("{}-{}", pair.base, quote),
+        })
+    }
+
+    /// Candles of `symbol` opening in `[start, end)`
+    pub async fn fetch_bars(
+        &self,
+        symbol: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Bar>> {
+        let market = self.market_symbol(symbol)?;
+        let mut bars = Vec::new();
+        for (page_start, page_end) in pages(start, end, self.interval, self.exchange.page_limit()) {
+            let json = self.get_json(&self.candles_url(&market, page_start, page_end)).await?;
+            let page = match self.exchange {
+                CryptoExchange::Binance => parse_binance_klines(&json)?,
+                CryptoExchange::Coinbase => parse_coinbase_candles(&json)?,
+            };
+            let in_page = |bar: &Bar| bar.timestamp >= page_start && bar.timestamp < page_end;
+            bars.extend(page.into_iter().filter(in_page));
+        }
+        bars.sort_by_key(|bar| bar.timestamp);
+        bars.dedup_by_key(|bar| bar.timestamp);
+        Ok(bars)
+    }
+
+    /// Candles of a crypto asset in `[start, end)`, limited to its trading
+    /// dates
+    pub async fn fetch_asset(
+        &self,
+        asset: &Asset,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Bar>> {
+        if asset.asset_type != AssetType::Crypto {
+            return Err(ZiplineError::InvalidData(format!(
+                "{} is {}, not a crypto asset",
+                asset.symbol, asset.asset_type
+            )));
+        }
+        let listed = asset.start_date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc());
+        let delisted = asset
+            .end_date
+            .and_then(|date| date.succ_opt())
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|dt| dt.and_utc());
+        let start = listed.map_or(start, |listed| start.max(listed));
+        let end = delisted.map_or(end, |delisted| end.min(delisted));
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        self.fetch_bars(&asset.symbol, start, end).await
+    }
+
+    fn candles_url(&self, market: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> String {
+        match self.exchange {
+            CryptoExchange::Binance => format!(
+                "{}/api/v3/klines?symbol={}&interval={}&startTime={}&endTime={}&limit={}",
+                self.base_url,
+                market,
+                self.interval.binance_code(),
+                start.timestamp_millis(),
+                end.timestamp_millis() - 1,
+                self.exchange.page_limit()
+            ),
+            CryptoExchange::Coinbase => format!(
+                "{}/products/{}/candles?granularity={}&start={}&end={}",
+                self.base_url,
+                market,
+                self.interval.seconds(),
+                start.to_rfc3339(),
+                end.to_rfc3339()
+            ),
+        }
+    }
+
+    /// GET a JSON document with rate limiting and retries
+    async fn get_json(&self, url: &str) -> Result<Value> {
+        let mut retry = 0;
+        loop {
+            self.rate_limiter.acquire().await;
+            let error = match self.client.get(url).send().await {
+                Ok(response) if response.status().is_success() => {
+                    let text = response
+                        .text()
+                        .await
+                        .map_err(|e| ZiplineError::DataError(format!("Failed to read response: {}", e)))?;
+                    return serde_json::from_str(&text)
+                        .map_err(|e| ZiplineError::DataError(format!("JSON parse error: {}", e)));
+                }
+                // Unknown pairs and bad parameters will not succeed on retry
+                Ok(response) if response.status().is_client_error() && response.status() != 429 => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(ZiplineError::DataError(format!(
+                        "{:?} returned error {}: {}",
+                        self.exchange, status, body
+                    )));
+                }
+                Ok(response) => format!("{:?} returned error: {}", self.exchange, response.status()),
+                Err(e) => format!("HTTP request failed: {}", e),
+            };
+            if retry >= self.retry_policy.max_retries {
+                return Err(ZiplineError::DataError(error));
+            }
+            tokio::time::sleep(self.retry_policy.backoff(retry)).await;
+            retry += 1;
+        }
+    }
+}
+
+impl ExternalDataSource for CryptoDataSource {
+    fn fetch_historical<'a>(
+        &'a self,
+        symbol: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> FetchFuture<'a> {
+        Box::pin(self.fetch_bars(symbol, start, end))
+    }
+
+    fn name(&self) -> &str {
+        match self.exchange {
+            CryptoExchange::Binance => "binance",
+            CryptoExchange::Coinbase => "coinbase",
+        }
+    }
+}
+
+/// Consecutive `[start, end)` windows of at most `limit` candles covering
+/// `[start, end)`
+fn pages(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    interval: CryptoInterval,
+    limit: usize,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let span = interval.duration() * limit.max(1) as i32;
+    let mut windows = Vec::new();
+    let mut cursor = start;
+    while cursor < end {
+        let next = (cursor + span).min(end);
+        windows.push((cursor, next));
+        cursor = next;
+    }
+    windows
+}
+
+/// Binance klines: `[open time ms, "open", "high", "low", "close", "volume", ...]`
+fn parse_binance_klines(json: &Value) -> Result<Vec<Bar>> {
+    let rows = json
+        .as_array()
+        .ok_or_else(|| ZiplineError::DataError(format!("Unexpected Binance response: {}", json)))?;
+    rows.iter()
+        .map(|row| {
+            let field = |idx: usize| -> Option<f64> {
+                let value = row.get(idx)?;
+                value.as_str().and_then(|s| s.parse().ok()).or_else(|| value.as_f64())
+            };
+            let timestamp = row
+                .get(0)
+                .and_then(Value::as_i64)
+                .and_then(DateTime::from_timestamp_millis);
+            match (timestamp, field(1), field(2), field(3), field(4), field(5)) {
+                (Some(timestamp), Some(open), Some(high), Some(low), Some(close), Some(volume)) => {
+                    Ok(Bar::new(timestamp, open, high, low, close, volume))
+                }
+                _ => Err(ZiplineError::DataError(format!("Malformed Binance kline: {}", row))),
+            }
+        })
+        .collect()
+}
+
+/// Coinbase candles, newest first: `[time s, low, high, open, close, volume]`
+fn parse_coinbase_candles(json: &Value) -> Result<Vec<Bar>> {
+    let rows = json
+        .as_array()
+        .ok_or_else(|| ZiplineError::DataError(format!("Unexpected Coinbase response: {}", json)))?;
+    let mut bars = rows
+        .iter()
+        .map(|row| {
+            let field = |idx: usize| row.get(idx).and_then(Value::as_f64);
+            let timestamp = row.get(0).and_then(Value::as_i64).and_then(|s| DateTime::from_timestamp(s, 0));
+            match (timestamp, field(3), field(2), field(1), field(4), field(5)) {
+                (Some(timestamp), Some(open), Some(high), Some(low), Some(close), Some(volume)) => {
+                    Ok(Bar::new(timestamp, open, high, low, close, volume))
+                }
+                _ => Err(ZiplineError::DataError(format!("Malformed Coinbase candle: {}", row))),
+            }
+        })
+        .collect::<Result<Vec<Bar>>>()?;
+    bars.reverse();
+    Ok(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_symbols_pages_and_candles() {
+        let binance = CryptoDataSource::new(CryptoExchange::Binance).unwrap();
+        let coinbase = CryptoDataSource::new(CryptoExchange::Coinbase).unwrap();
+        assert_eq!(binance.market_symbol("btc-usd").unwrap(), "BTCUSDT");
+        assert_eq!(binance.market_symbol("ETH/BTC").unwrap(), "ETHBTC");
+        assert_eq!(coinbase.market_symbol("SOL_USD").unwrap(), "SOL-USD");
+        assert!(CryptoPair::parse("BTCUSD").is_err());
+
+        // 2500 minutes split into Coinbase's 300-candle pages
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::minutes(2500);
+        let windows = pages(start, end, CryptoInterval::OneMinute, 300);
+        assert_eq!(windows.len(), 9);
+        assert_eq!(windows[1].0, windows[0].1);
+        assert_eq!(windows[8], (start + Duration::minutes(2400), end));
+
+        let klines = serde_json::json!([
+            [1704067200000i64, "42000.1", "42100.0", "41950.5", "42050.0", "12.5", 1704067259999i64],
+            [1704067260000i64, "42050.0", "42060.0", "42000.0", "42010.0", "3.0", 1704067319999i64]
+        ]);
+        let bars = parse_binance_klines(&klines).unwrap();
+        assert_eq!(bars[0].timestamp, start);
+        assert_eq!((bars[0].open, bars[0].low, bars[1].close), (42000.1, 41950.5, 42010.0));
+
+        let candles = serde_json::json!([
+            [1704067260i64, 2290.0, 2310.0, 2300.0, 2305.0, 7.5],
+            [1704067200i64, 2280.0, 2302.0, 2295.0, 2300.0, 4.0]
+        ]);
+        let bars = parse_coinbase_candles(&candles).unwrap();
+        assert_eq!(bars[0].timestamp, start);
+        assert_eq!((bars[0].open, bars[0].high, bars[0].low), (2295.0, 2302.0, 2280.0));
+        assert!(parse_binance_klines(&serde_json::json!({"code": -1121})).is_err());
+    }
+}
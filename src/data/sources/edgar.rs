@@ -0,0 +1,370 @@
+//! SEC EDGAR company facts for the fundamentals store
+//!
+//! EDGAR's XBRL API serves every value a company has tagged in its filings.
+//! `EdgarSource` maps a handful of US GAAP concepts onto store fields:
+//! - `revenue`, `net_income`: fiscal-year totals, so values are comparable
+//!   across filings
+//! - `shares_outstanding`, `total_debt`, `total_equity`: balance sheet values
+//!   from every 10-K and 10-Q
+//!
+//! Each value's report date is the end of its period and its effective date
+//! the day after it was filed, since filings often land after the close.
+//! Amendments and later comparatives become restatements of their period.
+//!
+//! Ingestion writes each company's facts to `CIK##########.csv` in the
+//! output directory, in the format [`FundamentalsStore::load`] reads. Rerunning
+//! skips companies that already have a file, so an interrupted run resumes
+//! where it stopped. SEC asks for at most 10 requests per second and a
+//! `User-Agent` naming the requester.
+//!
+//! # Example
+//! ```ignore
+//! let edgar = EdgarSource::new("Example Research admin@example.com")?;
+//! let tickers = edgar.fetch_tickers().await?;
+//! let companies = EdgarSource::universe(&assets, &tickers);
+//! let ingest = edgar.ingest(&companies, Path::new("fundamentals/edgar")).await?;
+//! let store = Arc::new(ingest.store);
+//! ```
+
+use super::batch::{RateLimiter, RetryPolicy};
+use crate::asset::Asset;
+use crate::data::fundamentals::FundamentalsStore;
+use crate::error::{Result, ZiplineError};
+use chrono::NaiveDate;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+const COMPANY_FACTS_URL: &str = "https://data.sec.gov/api/xbrl/companyfacts";
+const COMPANY_TICKERS_URL: &str = "https://www.sec.gov/files/company_tickers.json";
+
+/// Periodic report forms whose facts are ingested, amendments included
+const FORMS: [&str; 4] = ["10-K", "10-K/A", "10-Q", "10-Q/A"];
+
+/// Store field, whether it is a fiscal-year flow, and its `(taxonomy, concept)`
+/// sources by preference
+type FieldConcepts = (&'static str, bool, &'static [(&'static str, &'static str)]);
+
+const FIELDS: [FieldConcepts; 5] = [
+    (
+        "revenue",
+        true,
+        &[
+            ("us-gaap", "Revenues"),
+            ("us-gaap", "RevenueFromContractWithCustomerExcludingAssessedTax"),
+            ("us-gaap", "SalesRevenueNet"),
+        ],
+    ),
+    ("net_income", true, &[("us-gaap", "NetIncomeLoss")]),
+    (
+        "shares_outstanding",
+        false,
+        &[
+            ("dei", "EntityCommonStockSharesOutstanding"),
+            ("us-gaap", "CommonStockSharesOutstanding"),
+        ],
+    ),
+    (
+        "total_debt",
+        false,
+        &[("us-gaap", "LongTermDebt"), ("us-gaap", "LongTermDebtNoncurrent")],
+    ),
+    (
+        "total_equity",
+        false,
+        &[
+            ("us-gaap", "StockholdersEquity"),
+            ("us-gaap", "StockholdersEquityIncludingPortionAttributableToNoncontrollingInterest"),
+        ],
+    ),
+];
+
+/// One value extracted from a company facts document
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgarFact {
+    pub field: &'static str,
+    pub report_date: NaiveDate,
+    pub effective_date: NaiveDate,
+    pub value: f64,
+}
+
+/// Outcome of [`EdgarSource::ingest`]
+#[derive(Debug, Clone, Default)]
+pub struct EdgarIngest {
+    /// Facts of every company ingested, fetched now or earlier
+    pub store: FundamentalsStore,
+    /// Companies downloaded in this run
+    pub fetched: usize,
+    /// Companies read from files of an earlier run
+    pub resumed: usize,
+    /// Error message by CIK for companies that failed after all retries
+    pub failures: HashMap<u64, String>,
+}
+
+impl EdgarIngest {
+    /// Check if every company was ingested
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// SEC EDGAR XBRL company facts source
+pub struct EdgarSource {
+    client: Client,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+}
+
+impl EdgarSource {
+    /// Create a source identifying itself with `user_agent`, as SEC requires
+    /// (e.g. `"Company Name admin@example.com"`)
+    pub fn new(user_agent: &str) -> Result<Self> {
+        if user_agent.trim().is_empty() {
+            return Err(ZiplineError::InvalidConfiguration(
+                "SEC EDGAR requires a User-Agent naming the requester".to_string(),
+            ));
+        }
+        let client = Client::builder()
+            .timeout(Duration::from_secs(60))
+            .user_agent(user_agent)
+            .build()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            rate_limiter: Arc::new(RateLimiter::per_second(10)),
+            retry_policy: RetryPolicy::default(),
+        })
+    }
+
+    /// Rate limit requests (share the limiter with anything else calling SEC)
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// CIK by upper-case ticker for every company SEC lists
+    pub async fn fetch_tickers(&self) -> Result<HashMap<String, u64>> {
+        let json = self
+            .get_json(COMPANY_TICKERS_URL)
+            .await?
+            .ok_or_else(|| ZiplineError::DataError("SEC ticker list not found".to_string()))?;
+        let entries = json
+            .as_object()
+            .ok_or_else(|| ZiplineError::DataError("Unexpected SEC ticker list format".to_string()))?;
+        Ok(entries
+            .values()
+            .filter_map(|entry| {
+                let ticker = entry.get("ticker")?.as_str()?.to_uppercase();
+                Some((ticker, entry.get("cik_str")?.as_u64()?))
+            })
+            .collect())
+    }
+
+    /// `(sid, CIK)` of each asset whose symbol SEC lists
+    pub fn universe(assets: &[Asset], tickers: &HashMap<String, u64>) -> Vec<(u64, u64)> {
+        assets
+            .iter()
+            .filter_map(|asset| Some((asset.id, *tickers.get(&asset.symbol.to_uppercase())?)))
+            .collect()
+    }
+
+    /// Facts of one company; empty if it has never filed XBRL
+    pub async fn fetch_company_facts(&self, cik: u64) -> Result<Vec<EdgarFact>> {
+        let url = format!("{}/CIK{:010}.json", COMPANY_FACTS_URL, cik);
+        Ok(self.get_json(&url).await?.map(|json| parse_company_facts(&json)).unwrap_or_default())
+    }
+
+    /// Ingest `(sid, CIK)` companies into a store, keeping each company's
+    /// facts in `dir` so an interrupted run can resume
+    ///
+    /// A company's failure does not abort the run; it is reported in
+    /// [`EdgarIngest::failures`] and retried on the next run.
+    pub async fn ingest(&self, companies: &[(u64, u64)], dir: &Path) -> Result<EdgarIngest> {
+        std::fs::create_dir_all(dir)?;
+        let mut ingest = EdgarIngest::default();
+        for &(sid, cik) in companies {
+            let path = dir.join(format!("CIK{:010}.csv", cik));
+            if path.exists() {
+                ingest.resumed += 1;
+            } else {
+                match self.fetch_company_facts(cik).await {
+                    Ok(facts) => {
+                        write_facts(&path, sid, &facts)?;
+                        ingest.fetched += 1;
+                    }
+                    Err(e) => {
+                        tracing::warn!(cik, error = %e, "Failed to fetch EDGAR company facts");
+                        ingest.failures.insert(cik, e.to_string());
+                        continue;
+                    }
+                }
+            }
+            ingest.store.merge(FundamentalsStore::from_csv(&path)?);
+        }
+        Ok(ingest)
+    }
+
+    /// GET a JSON document with rate limiting and retries; `None` if it does
+    /// not exist
+    async fn get_json(&self, url: &str) -> Result<Option<Value>> {
+        let mut retry = 0;
+        loop {
+            self.rate_limiter.acquire().await;
+            let error = match self.client.get(url).send().await {
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => return Ok(None),
+                Ok(response) if response.status().is_success() => {
+                    let text = response
+                        .text()
+                        .await
+                        .map_err(|e| ZiplineError::DataError(format!("Failed to read response: {}", e)))?;
+                    return serde_json::from_str(&text)
+                        .map(Some)
+                        .map_err(|e| ZiplineError::DataError(format!("Invalid EDGAR JSON: {}", e)));
+                }
+                Ok(response) => format!("SEC EDGAR returned error: {}", response.status()),
+                Err(e) => format!("HTTP request failed: {}", e),
+            };
+            if retry >= self.retry_policy.max_retries {
+                return Err(ZiplineError::DataError(error));
+            }
+            tokio::time::sleep(self.retry_policy.backoff(retry)).await;
+            retry += 1;
+        }
+    }
+}
+
+/// Extract store fields from a company facts document
+///
+/// Where several concepts report the same period and filing, the most
+/// preferred one wins.
+pub fn parse_company_facts(json: &Value) -> Vec<EdgarFact> {
+    let date = |fact: &Value, key: &str| {
+        NaiveDate::parse_from_str(fact.get(key)?.as_str()?, "%Y-%m-%d").ok()
+    };
+    let mut facts = Vec::new();
+    for (field, annual, concepts) in FIELDS {
+        let mut values = BTreeMap::new();
+        // Least preferred first so better concepts overwrite
+        for (taxonomy, concept) in concepts.iter().rev() {
+            let Some(units) = json.pointer(&format!("/facts/{}/{}/units", taxonomy, concept)) else {
+                continue;
+            };
+            let Some(units) = units.as_object() else {
+                continue;
+            };
+            for fact in units.values().filter_map(Value::as_array).flatten() {
+                let form = fact.get("form").and_then(Value::as_str).unwrap_or("");
+                let (Some(end), Some(filed), Some(value)) =
+                    (date(fact, "end"), date(fact, "filed"), fact.get("val").and_then(Value::as_f64))
+                else {
+                    continue;
+                };
+                let fiscal_year = date(fact, "start")
+                    .map(|start| (350..=380).contains(&(end - start).num_days()))
+                    .unwrap_or(false);
+                if !FORMS.contains(&form) || fiscal_year != annual {
+                    continue;
+                }
+                values.insert((end, filed), value);
+            }
+        }
+        facts.extend(values.into_iter().map(|((report_date, filed), value)| EdgarFact {
+            field,
+            report_date,
+            effective_date: filed + chrono::Duration::days(1),
+            value,
+        }));
+    }
+    facts
+}
+
+/// Write facts as a fundamentals CSV, replacing `path` only once complete
+fn write_facts(path: &Path, sid: u64, facts: &[EdgarFact]) -> Result<()> {
+    let mut rows: BTreeMap<(NaiveDate, NaiveDate), [Option<f64>; FIELDS.len()]> = BTreeMap::new();
+    for fact in facts {
+        let Some(column) = FIELDS.iter().position(|(field, _, _)| *field == fact.field) else {
+            continue;
+        };
+        rows.entry((fact.report_date, fact.effective_date)).or_default()[column] = Some(fact.value);
+    }
+
+    let partial = path.with_extension("csv.partial");
+    let mut writer = csv::Writer::from_path(&partial)
+        .map_err(|e| ZiplineError::DataError(format!("Failed to write CSV: {}", e)))?;
+    let mut header = vec!["sid", "report_date", "effective_date"];
+    header.extend(FIELDS.iter().map(|(field, _, _)| *field));
+    writer
+        .write_record(&header)
+        .map_err(|e| ZiplineError::DataError(format!("Failed to write CSV: {}", e)))?;
+    for ((report_date, effective_date), values) in rows {
+        let mut record = vec![sid.to_string(), report_date.to_string(), effective_date.to_string()];
+        record.extend(values.iter().map(|value| value.map(|v| v.to_string()).unwrap_or_default()));
+        writer
+            .write_record(&record)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to write CSV: {}", e)))?;
+    }
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_company_facts_to_store() {
+        let json = serde_json::json!({
+            "cik": 320193,
+            "facts": {
+                "us-gaap": {
+                    "Revenues": {"units": {"USD": [
+                        {"start": "2022-01-01", "end": "2022-12-31", "val": 100.0,
+                         "form": "10-K", "filed": "2023-02-10"},
+                        // Quarterly and current-report values are skipped
+                        {"start": "2023-01-01", "end": "2023-03-31", "val": 30.0,
+                         "form": "10-Q", "filed": "2023-05-01"},
+                        {"start": "2022-01-01", "end": "2022-12-31", "val": 100.0,
+                         "form": "8-K", "filed": "2023-01-20"},
+                        // Comparative in the next annual report, restated
+                        {"start": "2022-01-01", "end": "2022-12-31", "val": 98.0,
+                         "form": "10-K", "filed": "2024-02-09"}
+                    ]}},
+                    "StockholdersEquity": {"units": {"USD": [
+                        {"end": "2023-03-31", "val": 500.0, "form": "10-Q", "filed": "2023-05-01"}
+                    ]}},
+                    "StockholdersEquityIncludingPortionAttributableToNoncontrollingInterest":
+                        {"units": {"USD": [
+                            {"end": "2023-03-31", "val": 520.0, "form": "10-Q", "filed": "2023-05-01"}
+                        ]}}
+                }
+            }
+        });
+        let facts = parse_company_facts(&json);
+        assert_eq!(facts.len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CIK0000320193.csv");
+        write_facts(&path, 7, &facts).unwrap();
+        let store = FundamentalsStore::load(&path).unwrap();
+        let day = |y: i32, m: u32, d: u32| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        // Filed on the 10th, usable from the next day
+        assert_eq!(store.value_at(7, "revenue", day(2023, 2, 10)), None);
+        assert_eq!(store.value_at(7, "revenue", day(2023, 2, 11)), Some(100.0));
+        assert_eq!(store.value_at(7, "revenue", day(2024, 2, 10)), Some(98.0));
+        assert_eq!(store.value_at(7, "total_equity", day(2023, 6, 1)), Some(500.0));
+        assert!(!dir.path().join("CIK0000320193.csv.partial").exists());
+    }
+}
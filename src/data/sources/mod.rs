@@ -4,20 +4,49 @@
 //! - Quandl: Historical datasets and economic indicators
 //! - Yahoo Finance: Free historical OHLCV data
 //! - Alpha Vantage: Intraday and daily market data
+//! - Binance / Coinbase: Crypto OHLCV candles at minute to daily granularity
+//! - SEC EDGAR: XBRL company facts for the fundamentals store
+//!
+//! `BatchFetcher` downloads many symbols concurrently with rate limiting and retries.
+//! `DiskCache` keeps fetched bars on disk so repeat fetches skip the network;
+//! `CachedSource` puts a source's fetches behind it.
 
+pub mod cache;
 #[cfg(feature = "async")]
 pub mod quandl;
 #[cfg(feature = "async")]
 pub mod yahoo;
 #[cfg(feature = "async")]
 pub mod alpha_vantage;
+#[cfg(feature = "async")]
+pub mod batch;
+#[cfg(feature = "async")]
+pub mod crypto;
+#[cfg(feature = "async")]
+pub mod edgar;
 
+pub use cache::{CacheKey, DiskCache, PurgeStats};
+#[cfg(feature = "async")]
+pub use cache::CachedSource;
 #[cfg(feature = "async")]
 pub use quandl::QuandlDataSource;
 #[cfg(feature = "async")]
 pub use yahoo::YahooFinanceSource;
 #[cfg(feature = "async")]
 pub use alpha_vantage::AlphaVantageSource;
+#[cfg(feature = "async")]
+pub use batch::{BatchFetcher, BatchProgress, BatchResult, RateLimiter, RetryPolicy};
+#[cfg(feature = "async")]
+pub use crypto::{CryptoDataSource, CryptoExchange, CryptoInterval, CryptoPair};
+#[cfg(feature = "async")]
+pub use edgar::{EdgarFact, EdgarIngest, EdgarSource};
+
+#[cfg(feature = "async")]
+use crate::{error::Result, types::Bar};
+#[cfg(feature = "async")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "async")]
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
 
 
 /// Registry for managing multiple data sources
@@ -62,16 +91,23 @@ impl Default for DataSourceRegistry {
     }
 }
 
+/// Future returned by [`ExternalDataSource::fetch_historical`]
+#[cfg(feature = "async")]
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Bar>>> + Send + 'a>>;
+
 /// Trait for external data sources
+///
+/// Fetches are boxed futures so sources can be held as trait objects in a
+/// [`DataSourceRegistry`].
 #[cfg(feature = "async")]
 pub trait ExternalDataSource: Send + Sync {
     /// Fetch historical data for a single symbol
-    fn fetch_historical(
-        &self,
-        symbol: &str,
+    fn fetch_historical<'a>(
+        &'a self,
+        symbol: &'a str,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
-    ) -> impl std::future::Future<Output = Result<Vec<Bar>>> + Send;
+    ) -> FetchFuture<'a>;
 
     /// Get the source name
     fn name(&self) -> &str;
@@ -96,7 +96,7 @@ impl QuandlDataSource {
                     results.insert(dataset.to_string(), bars);
                 }
                 Err(e) => {
-                    log::warn!("Failed to fetch dataset {}: {}", dataset, e);
+                    tracing::warn!(%dataset, error = %e, "Failed to fetch dataset");
                 }
             }
         }
@@ -99,7 +99,7 @@ impl YahooFinanceSource {
                     results.insert(symbol.to_string(), bars);
                 }
                 Err(e) => {
-                    log::warn!("Failed to fetch data for {}: {}", symbol, e);
+                    tracing::warn!(%symbol, error = %e, "Failed to fetch data");
                 }
             }
         }
@@ -136,6 +136,21 @@ impl YahooFinanceSource {
     }
 }
 
+impl super::ExternalDataSource for YahooFinanceSource {
+    fn fetch_historical<'a>(
+        &'a self,
+        symbol: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> super::FetchFuture<'a> {
+        Box::pin(YahooFinanceSource::fetch_historical(self, symbol, start, end))
+    }
+
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+}
+
 impl Default for YahooFinanceSource {
     fn default() -> Self {
         Self::new().expect("Failed to create Yahoo Finance source")
@@ -1,21 +1,74 @@
 //! Backtesting engine with event loop
 
-use crate::algorithm::{Algorithm, Context};
+use crate::algorithm::{Algorithm, Context, UniverseEnforcement};
+use crate::asset::Asset;
 use crate::calendar::TradingCalendar;
+use crate::data::frequency::DataFrequency;
+use crate::data::history_loader::{Frequency, HistoryLoader};
+use crate::data::adjustments::AdjustmentReader;
 use crate::data::{BarData, DataSource};
 use crate::error::Result;
 use crate::execution::{ExecutionResult, SimulatedBroker};
+use crate::finance::controls::ControlManager;
+use crate::finance::{
+    CapacityLimits, DividendPolicy, ExposureTracker, InterestModel, LotSizes, MarketStatsService, Portfolio,
+    RestrictedPositions, Restrictions, TradingHalt, Transaction,
+};
+use crate::order::{Order, OrderSide};
 use crate::performance::PerformanceTracker;
-use crate::types::Timestamp;
+use crate::pipeline::classifiers::ClassificationMap;
+use crate::rng::SimulationRng;
+use crate::types::{AssetId, Bar, OrderId, Price, Quantity, SessionId, Timestamp, QUANTITY_TOLERANCE};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(feature = "async")]
+pub mod async_run;
+pub mod events;
+pub mod holdout;
+pub mod order_log;
+pub mod replay;
+pub mod stepper;
+
+#[cfg(feature = "async")]
+pub use async_run::{AsyncDataSource, BlockingDataSource};
+pub use events::{EngineEvent, EventBus};
+pub use holdout::{Holdout, HoldoutUnlock};
+pub use order_log::{OrderEvent, OrderLog, OrderLogFormat, OrderRecord, SharedOrderLog};
+pub use replay::{SignalReplay, TargetWeights};
+pub use stepper::{Checkpoint, Stepper};
 
 /// Configuration for simulation engine
+///
+/// Built from [`EngineConfig::default`] with the `with_*` setters, so new
+/// options can be added without breaking callers:
+///
+/// ```rust,ignore
+/// let config = EngineConfig::default()
+///     .with_starting_cash(50_000.0)
+///     .with_data_frequency(DataFrequency::Daily);
+/// ```
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct EngineConfig {
     /// Starting capital
     pub starting_cash: f64,
     /// Maximum historical bars to keep
     pub max_history_len: usize,
+    /// Mark the portfolio to market on every bar and track intraday drawdown
+    /// and exposure; standard metrics are then computed on daily closes
+    pub intraday_metrics: bool,
+    /// Record a fingerprint of cash, positions and pending orders on every bar,
+    /// for locating where two runs diverge
+    pub record_fingerprints: bool,
+    /// Bar frequency driving the simulation clock; daily runs call
+    /// `handle_data` once per session, minute runs once per minute bar
+    pub data_frequency: DataFrequency,
+    /// Bars loaded before the start date to fill history windows; the
+    /// algorithm's `warm_up_bars` is used when larger
+    pub warm_up_bars: usize,
 }
 
 impl Default for EngineConfig {
@@ -23,20 +76,165 @@ impl Default for EngineConfig {
         Self {
             starting_cash: 100_000.0,
             max_history_len: 1000,
+            intraday_metrics: false,
+            record_fingerprints: false,
+            data_frequency: DataFrequency::Minute,
+            warm_up_bars: 0,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Set the starting capital
+    pub fn with_starting_cash(mut self, starting_cash: f64) -> Self {
+        self.starting_cash = starting_cash;
+        self
+    }
+
+    /// Set the maximum historical bars to keep
+    pub fn with_max_history_len(mut self, max_history_len: usize) -> Self {
+        self.max_history_len = max_history_len;
+        self
+    }
+
+    /// Mark to market on every bar and track intraday drawdown and exposure
+    pub fn with_intraday_metrics(mut self, intraday_metrics: bool) -> Self {
+        self.intraday_metrics = intraday_metrics;
+        self
+    }
+
+    /// Record a state fingerprint on every bar
+    pub fn with_record_fingerprints(mut self, record_fingerprints: bool) -> Self {
+        self.record_fingerprints = record_fingerprints;
+        self
+    }
+
+    /// Set the bar frequency driving the simulation clock
+    pub fn with_data_frequency(mut self, data_frequency: DataFrequency) -> Self {
+        self.data_frequency = data_frequency;
+        self
+    }
+
+    /// Set the bars loaded before the start date to fill history windows
+    pub fn with_warm_up_bars(mut self, warm_up_bars: usize) -> Self {
+        self.warm_up_bars = warm_up_bars;
+        self
+    }
+}
+
+/// Bars to load ahead of a run so history windows are full on its first bar
+#[derive(Clone)]
+struct WarmUp {
+    /// Bars with data to load
+    bars: usize,
+    /// Earliest time data is available
+    earliest: Timestamp,
+    /// First bar time of the run
+    start: Timestamp,
+    step: Duration,
+    /// Calendar whose sessions give the candidate times of a daily run
+    daily_sessions: Option<Arc<dyn TradingCalendar>>,
+}
+
+impl WarmUp {
+    /// Candidate bar times before the run, newest first
+    fn times(&self) -> Box<dyn Iterator<Item = Timestamp>> {
+        let (earliest, start, step) = (self.earliest, self.start, self.step);
+        match &self.daily_sessions {
+            Some(calendar) => {
+                let mut times = daily_bar_times(calendar.as_ref(), earliest, start);
+                times.retain(|t| *t < start);
+                Box::new(times.into_iter().rev())
+            }
+            None => Box::new(
+                std::iter::successors(Some(start - step), move |t| Some(*t - step))
+                    .take_while(move |t| *t >= earliest),
+            ),
         }
     }
 }
 
+/// Times a daily bar for each session in `[from, to]` may be stamped with
+///
+/// Bar stores differ: some stamp a session at midnight UTC of its date, some
+/// at midnight exchange time, some at the close. Every one of those is a
+/// candidate, so a daily run never steps past a bar stamped at another of them.
+fn daily_bar_times(calendar: &dyn TradingCalendar, from: Timestamp, to: Timestamp) -> Vec<Timestamp> {
+    let tz = calendar.timezone();
+    let first = from.date_naive() - Duration::days(1);
+    let last = to.date_naive() + Duration::days(1);
+
+    let mut times = BTreeSet::new();
+    for date in calendar.trading_days_between(first, last) {
+        let midnight = date.and_time(NaiveTime::MIN);
+        times.insert(Utc.from_utc_datetime(&midnight));
+        if let Some(local) = tz.from_local_datetime(&midnight).earliest() {
+            times.insert(local.with_timezone(&Utc));
+        }
+        let close = calendar
+            .session_times(date)
+            .and_then(|times| tz.from_local_datetime(&date.and_time(times.market_close)).earliest());
+        if let Some(close) = close {
+            times.insert(close.with_timezone(&Utc));
+        }
+    }
+    times.into_iter().filter(|t| *t >= from && *t <= to).collect()
+}
+
 /// Backtesting simulation engine
 pub struct SimulationEngine {
     /// Engine configuration
     config: EngineConfig,
-    /// Simulated broker
-    broker: SimulatedBroker,
+    /// Simulated broker, shared with the context for order previews
+    broker: Arc<SimulatedBroker>,
     /// Trading calendar
     calendar: Arc<dyn TradingCalendar>,
     /// Performance tracker
     performance: PerformanceTracker,
+    /// Pipeline screen restricting orders to the day's universe
+    universe_screen: Option<(String, UniverseEnforcement)>,
+    /// Rolling volatility, ADV and prices, fed from every bar
+    market_stats: Arc<MarketStatsService>,
+    /// Settlement prices for delisted assets, by asset id
+    delist_prices: HashMap<u64, Price>,
+    /// Session of the last processed bar
+    current_session: Option<SessionId>,
+    /// Loader backing `BarData::history_frame` beyond the bars kept in memory
+    history_loader: Option<Arc<HistoryLoader>>,
+    /// Holdout period runs may not touch while it is locked
+    holdout: Option<Holdout>,
+    /// Order and position limits relative to ADV and float
+    capacity: Option<CapacityLimits>,
+    /// Trading controls installed in every run's context
+    trading_controls: Option<Arc<ControlManager>>,
+    /// Lot sizes installed in every run's context
+    lot_sizes: Option<Arc<LotSizes>>,
+    /// Interest paid and charged on cash between sessions
+    interest_model: Option<Arc<dyn InterestModel>>,
+    /// Corporate actions whose cash dividends are paid on held positions
+    dividends: Option<(Arc<AdjustmentReader>, DividendPolicy)>,
+    /// Exposure by side, sector and asset class at each session's close
+    exposure: ExposureTracker,
+    /// Whether the portfolio's ledger records realized gains by tax lot
+    tax_lots: bool,
+    /// Sessions before sale proceeds settle, if settlement is modelled
+    settlement_lag: Option<usize>,
+    /// Assets that may not be traded, and what happens to positions in them
+    restrictions: Option<(Arc<dyn Restrictions>, RestrictedPositions)>,
+    /// Held assets found restricted at the last bar
+    restricted_held: HashSet<AssetId>,
+    /// Whether the portfolio is checked against its ledger after every fill
+    reconcile: bool,
+    /// Span of the session being simulated, parent of its bars' spans
+    session_span: tracing::Span,
+    /// Seed for every run, or `None` to draw a new one per run
+    seed: Option<u64>,
+    /// Randomness for fills and transaction ids, reseeded at the start of each run
+    rng: SimulationRng,
+    /// Observers of order, position and session events
+    events: EventBus,
+    /// Orders announced as submitted and not yet filled or cancelled
+    open_orders: HashMap<OrderId, AssetId>,
 }
 
 impl std::fmt::Debug for SimulationEngine {
@@ -46,6 +244,28 @@ impl std::fmt::Debug for SimulationEngine {
             .field("broker", &self.broker)
             .field("calendar", &"<dyn TradingCalendar>")
             .field("performance", &self.performance)
+            .field("universe_screen", &self.universe_screen)
+            .field("market_stats", &self.market_stats)
+            .field("delist_prices", &self.delist_prices)
+            .field("current_session", &self.current_session)
+            .field("history_loader", &self.history_loader)
+            .field("holdout", &self.holdout)
+            .field("capacity", &self.capacity)
+            .field("trading_controls", &self.trading_controls.is_some())
+            .field("lot_sizes", &self.lot_sizes.is_some())
+            .field("interest_model", &self.interest_model)
+            .field("dividends", &self.dividends.as_ref().map(|(_, policy)| policy))
+            .field("exposure", &self.exposure)
+            .field("tax_lots", &self.tax_lots)
+            .field("settlement_lag", &self.settlement_lag)
+            .field("restrictions", &self.restrictions.as_ref().map(|(r, policy)| (r.name(), policy)))
+            .field("restricted_held", &self.restricted_held)
+            .field("reconcile", &self.reconcile)
+            .field("session_span", &self.session_span)
+            .field("seed", &self.seed)
+            .field("rng", &self.rng)
+            .field("events", &self.events)
+            .field("open_orders", &self.open_orders)
             .finish()
     }
 }
@@ -59,12 +279,207 @@ impl SimulationEngine {
     ) -> Self {
         Self {
             config,
-            broker,
+            broker: Arc::new(broker),
             calendar,
             performance: PerformanceTracker::new(),
+            universe_screen: None,
+            market_stats: Arc::new(MarketStatsService::default()),
+            delist_prices: HashMap::new(),
+            current_session: None,
+            history_loader: None,
+            holdout: None,
+            capacity: None,
+            trading_controls: None,
+            lot_sizes: None,
+            interest_model: None,
+            dividends: None,
+            exposure: ExposureTracker::new(),
+            tax_lots: false,
+            settlement_lag: None,
+            restrictions: None,
+            restricted_held: HashSet::new(),
+            reconcile: false,
+            session_span: tracing::Span::none(),
+            seed: None,
+            rng: SimulationRng::from_entropy(),
+            events: EventBus::new(),
+            open_orders: HashMap::new(),
         }
     }
 
+    /// Restrict `context.order*` calls to assets in a pipeline screen
+    ///
+    /// The algorithm must publish the day's screen output with
+    /// `context.update_universe(pipeline, assets)`. Orders for other assets are
+    /// logged or rejected depending on `enforcement`; orders reducing an
+    /// existing position are always allowed.
+    pub fn with_universe_screen(mut self, pipeline: &str, enforcement: UniverseEnforcement) -> Self {
+        self.universe_screen = Some((pipeline.to_string(), enforcement));
+        self
+    }
+
+    /// Share a market statistics service with the engine
+    ///
+    /// The engine feeds it every bar and hands it to the context, so trading
+    /// controls see the same volatility, ADV and prices as slippage models
+    /// holding the same service.
+    pub fn with_market_stats(mut self, stats: Arc<MarketStatsService>) -> Self {
+        self.market_stats = stats;
+        self
+    }
+
+    /// Settle positions in a delisted asset at `price`
+    ///
+    /// Delisted positions are closed at the last traded price unless the
+    /// delisting has a known settlement price, e.g. a cash acquisition.
+    pub fn with_delist_price(mut self, asset_id: u64, price: Price) -> Self {
+        self.delist_prices.insert(asset_id, price);
+        self
+    }
+
+    /// Serve `BarData::history_frame` requests from a history loader
+    ///
+    /// Without one, history is limited to the bars seen during the run and
+    /// to the engine's data frequency.
+    pub fn with_history_loader(mut self, loader: Arc<HistoryLoader>) -> Self {
+        self.history_loader = Some(loader);
+        self
+    }
+
+    /// Refuse runs touching `holdout` while it is locked
+    pub fn with_holdout(mut self, holdout: Holdout) -> Self {
+        self.holdout = Some(holdout);
+        self
+    }
+
+    /// Scale the algorithm's orders down to capacity limits
+    ///
+    /// Clipped shares are tracked as a shadow position; the resulting
+    /// [`CapacityReport`](crate::finance::CapacityReport) is attached to the
+    /// run's performance.
+    pub fn with_capacity_limits(mut self, limits: CapacityLimits) -> Self {
+        self.capacity = Some(limits);
+        self
+    }
+
+    /// Check every run's orders against trading controls
+    ///
+    /// Installed before `initialize`, so an algorithm can still replace them
+    /// with `Context::set_trading_controls`.
+    pub fn with_trading_controls(mut self, controls: Arc<ControlManager>) -> Self {
+        self.trading_controls = Some(controls);
+        self
+    }
+
+    /// Round every run's orders to whole lots of each asset
+    ///
+    /// Orders below an asset's minimum quantity or notional are rejected.
+    pub fn with_lot_sizes(mut self, lot_sizes: Arc<LotSizes>) -> Self {
+        self.lot_sizes = Some(lot_sizes);
+        self
+    }
+
+    /// Accrue interest on cash at the start of every session
+    ///
+    /// Interest on the previous close's balance, for each calendar day since,
+    /// is credited to (or charged against) cash and recorded in the results.
+    pub fn with_interest_model(mut self, model: Arc<dyn InterestModel>) -> Self {
+        self.interest_model = Some(model);
+        self
+    }
+
+    /// Pay cash dividends from `corporate_actions` on the positions held
+    ///
+    /// Each dividend is paid at the start of its ex-date session on the
+    /// previous close's position; `policy` decides whether it stays as cash
+    /// or buys more of the paying asset. See [`crate::finance::dividends`].
+    pub fn with_dividends(mut self, corporate_actions: Arc<AdjustmentReader>, policy: DividendPolicy) -> Self {
+        self.dividends = Some((corporate_actions, policy));
+        self
+    }
+
+    /// Break each session's exposure out by the GICS sectors in `map`
+    ///
+    /// Exposure by side and asset class is recorded for every run; see
+    /// [`crate::finance::exposure`].
+    pub fn with_sector_classification(mut self, map: Arc<ClassificationMap>) -> Self {
+        self.exposure = ExposureTracker::new().with_sectors(map);
+        self
+    }
+
+    /// Track realized gains by tax lot and report them with the results
+    ///
+    /// Gains are grouped by holding term and losses washed by repurchases are
+    /// flagged; see [`crate::finance::tax_lots`].
+    pub fn with_tax_lots(mut self) -> Self {
+        self.tax_lots = true;
+        self
+    }
+
+    /// Reject orders in assets `restrictions` restrict at the time of the order
+    ///
+    /// Held positions are checked at every bar; under
+    /// [`RestrictedPositions::Liquidate`] a position whose asset becomes
+    /// restricted is closed at market.
+    pub fn with_restrictions(mut self, restrictions: Arc<dyn Restrictions>, positions: RestrictedPositions) -> Self {
+        self.restrictions = Some((restrictions, positions));
+        self
+    }
+
+    /// Settle sale proceeds `lag` sessions after each trade (T+`lag`)
+    ///
+    /// Buys may only spend settled cash and are otherwise rejected with
+    /// [`ZiplineError::InsufficientFunds`](crate::error::ZiplineError::InsufficientFunds);
+    /// see [`crate::finance::settlement`].
+    pub fn with_settlement(mut self, lag: usize) -> Self {
+        self.settlement_lag = Some(lag);
+        self
+    }
+
+    /// Check the portfolio's positions against its ledger after every fill
+    ///
+    /// Positions are derived from the [`Ledger`](crate::finance::Ledger)
+    /// at each fill, so they only diverge if the algorithm edits them directly.
+    /// The run fails with
+    /// [`ZiplineError::LedgerDivergence`](crate::error::ZiplineError::LedgerDivergence)
+    /// on the first fill after which they disagree on a position's quantity
+    /// or cost basis.
+    pub fn with_reconciliation(mut self) -> Self {
+        self.reconcile = true;
+        self
+    }
+
+    /// Seed every run, making runs with the same inputs bit-identical
+    ///
+    /// Slippage draws, order ids and transaction ids all come from the seeded
+    /// [`SimulationRng`]. Without a seed each run draws its own; [`Self::seed`]
+    /// reports it afterwards so the run can be repeated.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Seed of the current or last run
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// Call `handler` with every [`EngineEvent`] of later runs
+    pub fn with_event_handler(mut self, handler: impl FnMut(&EngineEvent) + Send + 'static) -> Self {
+        self.events.subscribe(handler);
+        self
+    }
+
+    /// Event bus, e.g. to subscribe a channel to later runs' events
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    /// Market statistics fed by this engine
+    pub fn market_stats(&self) -> &Arc<MarketStatsService> {
+        &self.market_stats
+    }
+
     /// Create engine with default configuration
     pub fn default_engine(calendar: Arc<dyn TradingCalendar>) -> Self {
         Self::new(
@@ -82,61 +497,301 @@ impl SimulationEngine {
         start: Timestamp,
         end: Timestamp,
     ) -> Result<PerformanceTracker> {
-        // Initialize context
-        let mut context = Context::new(self.config.starting_cash);
-        let mut bar_data = BarData::new(self.config.max_history_len);
+        self.stepper(algorithm, data_source, start, end)?.finish()
+    }
 
-        // Initialize algorithm
-        algorithm.initialize(&mut context);
+    /// Start a backtest that is driven one bar at a time
+    ///
+    /// The algorithm is initialized immediately; bars are only processed as
+    /// the returned [`Stepper`] is advanced. Useful for investigating why a
+    /// strategy traded on a given bar.
+    pub fn stepper<'a, A: Algorithm>(
+        &'a mut self,
+        algorithm: &'a mut A,
+        data_source: &'a dyn DataSource,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Stepper<'a, A>> {
+        let (context, bar_data, warm_up, timestamps) =
+            self.begin_run(algorithm, data_source.get_date_range(), start, end)?;
+        Ok(Stepper::new(self, algorithm, data_source, context, bar_data, warm_up, timestamps))
+    }
 
-        // Get all timestamps in range
-        let mut timestamps: Vec<Timestamp> = vec![];
-        let (data_start, data_end) = data_source.get_date_range();
+    /// Initialize the context and algorithm and list the run's candidate bar times
+    fn begin_run<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        (data_start, data_end): (Timestamp, Timestamp),
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<(Context, BarData, WarmUp, Vec<Timestamp>)> {
+        self.current_session = None;
+        self.open_orders.clear();
+        self.exposure.clear();
+        self.restricted_held.clear();
+        self.rng = match self.seed {
+            Some(seed) => SimulationRng::new(seed),
+            None => SimulationRng::from_entropy(),
+        };
+        self.performance.seed = Some(self.rng.seed());
 
         // Use data range if specified range is outside available data
         let sim_start = if start < data_start { data_start } else { start };
         let sim_end = if end > data_end { data_end } else { end };
 
-        // Collect all available timestamps from data source
-        let mut current_time = sim_start;
-        while current_time <= sim_end {
-            timestamps.push(current_time);
-            current_time = current_time + chrono::Duration::minutes(1); // Adjust based on data frequency
+        // Refuse before the algorithm sees anything of a locked holdout
+        if let Some(holdout) = &self.holdout {
+            let tz = self.calendar.timezone();
+            holdout.check(
+                sim_start.with_timezone(&tz).date_naive(),
+                sim_end.with_timezone(&tz).date_naive(),
+            )?;
+        }
+
+        // Initialize context
+        let mut context = Context::new(self.config.starting_cash);
+        context.rng = self.rng.fork();
+        context.set_broker(self.broker.clone());
+        context.set_market_stats(self.market_stats.clone());
+        if self.tax_lots {
+            context.portfolio.track_tax_lots();
+        }
+        if let Some(lag) = self.settlement_lag {
+            context.set_settlement(lag);
+        }
+        if let Some((restrictions, _)) = &self.restrictions {
+            context.set_restrictions(restrictions.clone());
+        }
+        if let Some(limits) = &self.capacity {
+            context.set_capacity_limits(limits.clone());
+        }
+        if let Some(controls) = &self.trading_controls {
+            context.set_trading_controls(controls.clone());
+        }
+        if let Some(lot_sizes) = &self.lot_sizes {
+            context.set_lot_sizes(lot_sizes.clone());
+        }
+        if let Some((pipeline, enforcement)) = &self.universe_screen {
+            context.set_universe_mask(pipeline, *enforcement);
+        }
+
+        // Initialize algorithm
+        algorithm.initialize(&mut context);
+
+        // Candidate bar times: each session's possible stamps for a daily
+        // run, otherwise one per bar at the configured frequency
+        let step = self.config.data_frequency.duration();
+        let daily = self.config.data_frequency == DataFrequency::Daily;
+        let timestamps = if daily {
+            daily_bar_times(self.calendar.as_ref(), sim_start, sim_end)
+        } else {
+            let mut timestamps: Vec<Timestamp> = vec![];
+            let mut current_time = sim_start;
+            while current_time <= sim_end {
+                timestamps.push(current_time);
+                current_time += step;
+            }
+            timestamps
+        };
+
+        // Keep enough history for the longest window the algorithm asked for
+        let warm_up = WarmUp {
+            bars: self.config.warm_up_bars.max(algorithm.warm_up_bars()),
+            earliest: data_start,
+            start: sim_start,
+            step,
+            daily_sessions: daily.then(|| self.calendar.clone()),
+        };
+        let frequency = match self.config.data_frequency {
+            DataFrequency::Daily => Frequency::Daily,
+            DataFrequency::Minute | DataFrequency::Second => Frequency::Minute,
+        };
+        let mut bar_data = BarData::new(self.config.max_history_len.max(warm_up.bars + 1))
+            .with_frequency(frequency)
+            .with_calendar(self.calendar.clone());
+        if let Some(loader) = &self.history_loader {
+            bar_data = bar_data.with_history_loader(loader.clone());
         }
 
-        log::info!("Starting backtest from {} to {}", sim_start, sim_end);
-        log::info!("Processing {} timestamps", timestamps.len());
+        tracing::info!(%sim_start, %sim_end, timestamps = timestamps.len(), "Starting backtest");
 
-        // Main event loop
-        for timestamp in timestamps {
-            context.timestamp = timestamp;
+        Ok((context, bar_data, warm_up, timestamps))
+    }
 
-            // Get bars for this timestamp
+    /// Load up to `warm_up.bars` bars from before the run, newest first
+    fn load_warm_up(
+        &self,
+        warm_up: WarmUp,
+        data_source: &dyn DataSource,
+    ) -> Result<Vec<Vec<(u64, Bar)>>> {
+        let mut loaded = Vec::with_capacity(warm_up.bars);
+        for timestamp in warm_up.times() {
+            if loaded.len() >= warm_up.bars {
+                break;
+            }
             let bars = data_source.get_bars(timestamp)?;
-
-            if bars.is_empty() {
-                continue;
+            if !bars.is_empty() {
+                loaded.push(bars);
             }
+        }
+        Ok(loaded)
+    }
+
+    /// Feed bars from before the run into history and market statistics
+    ///
+    /// No algorithm callbacks run and no orders are processed; `loaded` is
+    /// newest first, as returned while walking back from the start date.
+    fn apply_warm_up(&self, bar_data: &mut BarData, loaded: Vec<Vec<(u64, Bar)>>) {
+        if loaded.is_empty() {
+            return;
+        }
+        tracing::info!(bars = loaded.len(), "Warming up before the start date");
 
-            // Update bar data
+        for bars in loaded.into_iter().rev() {
             for (asset_id, bar) in bars {
+                let session = self.calendar.session_of(bar.timestamp);
+                self.market_stats.update(asset_id, session, &bar);
                 bar_data.update(asset_id, bar);
             }
+        }
+    }
+
+    /// Run the event loop for a single bar
+    fn process_bar<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        context: &mut Context,
+        bar_data: &mut BarData,
+        timestamp: Timestamp,
+        bars: Vec<(u64, Bar)>,
+    ) -> Result<()> {
+        let previous_bar = std::mem::replace(&mut context.timestamp, timestamp);
+        let fills_before = self.performance.transactions.len();
+
+        // Time the bar within its session's span
+        let session = self.calendar.session_of(timestamp);
+        let new_session = self.current_session != Some(session);
+        if new_session {
+            self.session_span = tracing::info_span!("session", %session);
+        }
+        let bar_span = tracing::debug_span!(
+            parent: &self.session_span,
+            "bar",
+            %timestamp,
+            bars = bars.len(),
+            elapsed_us = tracing::field::Empty,
+        );
+        let _bar = bar_span.enter();
+        let started = Instant::now();
+
+        // Update bar data
+        for (asset_id, bar) in bars {
+            if self.config.intraday_metrics {
+                if let Some(position) = context.portfolio.get_position_mut(asset_id) {
+                    position.update_price(bar.close);
+                }
+            }
+            self.market_stats.update(asset_id, session, &bar);
+            if let Some(capacity) = context.capacity.as_mut() {
+                capacity.mark(asset_id, bar.close);
+            }
+            bar_data.update(asset_id, bar);
+        }
 
-            // Call before_trading_start at market open
-            // (simplified: call on first bar of each day)
-            algorithm.before_trading_start(&mut context, &bar_data)?;
+        // Close the previous session before anything trades in this one, so
+        // interest accrues on the cash held at that close and dividends are
+        // paid on the positions held then
+        let previous_session = self.current_session;
+        if new_session {
+            context.start_session(session);
+            if let (Some(model), Some(previous)) = (&self.interest_model, previous_session) {
+                let days = (session.date() - previous.date()).num_days();
+                let interest = model.accrue(context.portfolio.cash, previous.date(), days);
+                if interest != 0.0 {
+                    context.portfolio.adjust_cash(interest);
+                    context.account.accrued_interest += interest;
+                    self.performance.interest.push((session.date(), interest));
+                }
+            }
+            if let Some(previous) = previous_session {
+                self.pay_dividends(context, previous.date(), session.date());
+            }
+            if let Some(ended) = previous_session {
+                self.events.emit(EngineEvent::SessionEnd {
+                    session: ended,
+                    timestamp: previous_bar,
+                });
+            }
+            self.events.emit(EngineEvent::SessionStart { session, timestamp });
+            self.current_session = Some(session);
+        }
 
-            // Call handle_data
-            algorithm.handle_data(&mut context, &bar_data)?;
+        // Close out positions in assets that are no longer listed
+        self.liquidate_delisted(context, bar_data, session, timestamp)?;
+        self.check_restricted_positions(context);
 
-            // Process pending orders
-            self.process_orders(&mut context, &bar_data)?;
+        // Call before_trading_start on the first bar of each session
+        if new_session {
+            tracing::debug_span!("before_trading_start")
+                .in_scope(|| algorithm.before_trading_start(context, bar_data))?;
+        }
+
+        // Call handle_data
+        tracing::debug_span!("handle_data").in_scope(|| algorithm.handle_data(context, bar_data))?;
+
+        // Run scheduled functions that are due
+        tracing::debug_span!("scheduled").in_scope(|| self.run_scheduled(context, session))?;
+
+        // Process pending orders
+        self.announce_orders(context);
+        tracing::debug_span!("process_orders", orders = context.pending_orders.len())
+            .in_scope(|| self.process_orders(context, bar_data))?;
+
+        // Attribute the bar's P&L to the assets held and traded
+        let market_stats = &self.market_stats;
+        self.performance.attribution.mark(
+            session.date(),
+            &self.performance.transactions[fills_before..],
+            context.portfolio.positions.iter().map(|(id, p)| (*id, p.quantity)),
+            |asset_id| market_stats.last_price(asset_id),
+        );
+
+        // Update portfolio value
+        context.portfolio.update_value(timestamp);
+        let performance = &mut self.performance;
+        for txn in &performance.transactions[fills_before..] {
+            let volume = bar_data.current_volume(txn.asset_id).unwrap_or(0.0);
+            performance
+                .turnover
+                .record_fill(session.date(), txn.asset_id, txn.amount * txn.price, txn.amount, volume);
+        }
+        performance
+            .turnover
+            .record_value(session.date(), context.portfolio.portfolio_value);
+        if context.halted.is_none() {
+            let halt = context.trading_controls.as_ref().and_then(|c| c.check_halt(context));
+            if let Some(halt) = halt {
+                self.halt_trading(context, halt);
+            }
+        }
+        self.exposure.record(session.date(), &context.portfolio);
 
-            // Update portfolio value
-            context.portfolio.update_value(timestamp);
+        if self.config.record_fingerprints {
+            self.performance
+                .record_fingerprint(timestamp, context.state_fingerprint());
+        }
 
-            // Track performance
+        // Track performance
+        if self.config.intraday_metrics {
+            self.performance.record_intraday(
+                timestamp,
+                session,
+                context.portfolio.portfolio_value,
+                context.portfolio.returns,
+                context.portfolio.long_exposure(),
+                context.portfolio.short_exposure(),
+            );
+        } else {
             self.performance.record(
                 timestamp,
                 context.portfolio.portfolio_value,
@@ -144,32 +799,359 @@ impl SimulationEngine {
             );
         }
 
+        bar_span.record("elapsed_us", started.elapsed().as_micros() as u64);
+        Ok(())
+    }
+
+    /// Complete a run once every bar has been processed
+    fn finish_run<A: Algorithm>(
+        &mut self,
+        algorithm: &mut A,
+        context: &mut Context,
+    ) -> Result<PerformanceTracker> {
+        if self.config.intraday_metrics {
+            self.performance.finish_intraday();
+        }
+        self.session_span = tracing::Span::none();
+        if let Some(session) = self.current_session {
+            self.events.emit(EngineEvent::SessionEnd {
+                session,
+                timestamp: context.timestamp,
+            });
+        }
+        self.performance.capacity = context.capacity.as_ref().map(|c| c.report().clone());
+        self.performance.tax = context.portfolio.ledger().realized_gains_report();
+        self.performance.exposure = self.exposure.snapshots().to_vec();
+        context.results = Some(self.performance.clone());
+
         // Analyze results
-        algorithm.analyze(&context)?;
+        algorithm.analyze(context)?;
 
-        log::info!("Backtest complete");
-        log::info!(
-            "Final portfolio value: {:.2}",
-            context.portfolio.portfolio_value
-        );
-        log::info!(
-            "Total return: {:.2}%",
-            context.portfolio.returns * 100.0
+        tracing::info!(
+            portfolio_value = context.portfolio.portfolio_value,
+            returns = context.portfolio.returns,
+            "Backtest complete"
         );
 
         Ok(self.performance.clone())
     }
 
+    /// Session open and close in UTC, or None if `session` is not a trading day
+    fn session_bounds(&self, session: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let times = self.calendar.session_times(session)?;
+        let tz = self.calendar.timezone();
+        let open = tz.from_local_datetime(&session.and_time(times.market_open)).earliest()?;
+        let close = tz.from_local_datetime(&session.and_time(times.market_close)).earliest()?;
+        Some((open.with_timezone(&Utc), close.with_timezone(&Utc)))
+    }
+
+    /// Run the context's scheduled functions due at the current bar
+    fn run_scheduled(&self, context: &mut Context, session: SessionId) -> Result<()> {
+        if context.scheduler.is_empty() {
+            return Ok(());
+        }
+
+        let mut scheduler = std::mem::take(&mut context.scheduler);
+        let result = match (self.config.data_frequency, self.session_bounds(session.date())) {
+            (DataFrequency::Daily, _) => scheduler.execute_daily(context),
+            (_, Some((open, close))) => scheduler.execute_in_session(context, open, close),
+            (_, None) => scheduler.execute_pending(context),
+        };
+        context.scheduler = scheduler;
+        result
+    }
+
+    /// Stop trading for the rest of the run, cancelling open orders
+    ///
+    /// Positions are ordered closed at market if the halt liquidates.
+    fn halt_trading(&mut self, context: &mut Context, halt: TradingHalt) {
+        tracing::warn!(control = %halt.control, reason = %halt.reason, "Trading halted");
+        for order in &mut context.pending_orders {
+            order.cancel(context.timestamp);
+        }
+        context.pending_orders.clear();
+
+        if halt.liquidated {
+            let mut positions: Vec<_> = context
+                .portfolio
+                .positions
+                .values()
+                .filter(|p| p.quantity.abs() >= QUANTITY_TOLERANCE)
+                .map(|p| (p.asset.clone(), p.quantity))
+                .collect();
+            positions.sort_by_key(|(asset, _)| asset.id);
+            for (asset, quantity) in positions {
+                context.queue_closing_order(asset, quantity, "trading halted");
+            }
+        }
+
+        self.events.emit(EngineEvent::TradingHalted { halt: halt.clone() });
+        context.halted = Some(halt);
+        self.announce_orders(context);
+    }
+
+    /// Act on held positions in assets restricted as of this bar
+    ///
+    /// A position is reported when its asset becomes restricted. Under
+    /// [`RestrictedPositions::Liquidate`] the asset's open orders are
+    /// cancelled and the position is ordered closed at market.
+    fn check_restricted_positions(&mut self, context: &mut Context) {
+        let Some((restrictions, policy)) = self.restrictions.clone() else {
+            return;
+        };
+        let mut restricted: Vec<(Asset, Quantity)> = context
+            .portfolio
+            .positions
+            .values()
+            .filter(|p| p.quantity.abs() >= QUANTITY_TOLERANCE)
+            .filter(|p| restrictions.is_restricted(&p.asset, context.timestamp).is_err())
+            .map(|p| (p.asset.clone(), p.quantity))
+            .collect();
+        restricted.sort_by_key(|(asset, _)| asset.id);
+
+        let held = std::mem::take(&mut self.restricted_held);
+        for (asset, quantity) in restricted {
+            self.restricted_held.insert(asset.id);
+            if held.contains(&asset.id) {
+                continue;
+            }
+            tracing::warn!(asset = %asset.symbol, quantity, ?policy, "Held asset is restricted");
+            if policy == RestrictedPositions::Liquidate {
+                let timestamp = context.timestamp;
+                context.pending_orders.retain_mut(|o| {
+                    let keep = o.asset.id != asset.id;
+                    if !keep {
+                        o.cancel(timestamp);
+                    }
+                    keep
+                });
+                context.queue_closing_order(asset, quantity, "restricted");
+            }
+        }
+    }
+
+    /// Pay cash dividends going ex after `previous` and by `session`
+    fn pay_dividends(&mut self, context: &mut Context, previous: NaiveDate, session: NaiveDate) {
+        let Some((actions, policy)) = self.dividends.clone() else {
+            return;
+        };
+        let held: Vec<_> = context
+            .portfolio
+            .positions
+            .values()
+            .map(|p| (p.asset.clone(), p.quantity, p.last_price))
+            .collect();
+
+        for (asset, quantity, last_price) in held {
+            let per_share: f64 = actions.cash_dividends(asset.id, previous, session).iter().sum();
+            if per_share == 0.0 || quantity.abs() < QUANTITY_TOLERANCE {
+                continue;
+            }
+            let amount = per_share * quantity;
+            context.portfolio.adjust_cash(amount);
+            self.performance.attribution.record_dividend(session, asset.id, amount);
+            tracing::info!(asset = %asset.symbol, per_share, amount, %session, "Paid dividend");
+
+            // Shorts pay the dividend; only longs have one to reinvest
+            if policy == DividendPolicy::Reinvest && amount > 0.0 {
+                let price = self.market_stats.last_price(asset.id).unwrap_or(last_price);
+                if price <= 0.0 {
+                    continue;
+                }
+                if let Err(e) = context.reinvest_dividend(asset.clone(), amount / price) {
+                    tracing::warn!(asset = %asset.symbol, amount, error = %e, "Dividend not reinvested");
+                }
+            }
+        }
+    }
+
+    /// Force-close positions in assets delisted as of `session`
+    ///
+    /// An asset is delisted once the session is past its end date or on/after
+    /// its auto-close date. Each position is closed at the asset's last traded
+    /// close, or at the settlement price given with
+    /// [`with_delist_price`](Self::with_delist_price), and recorded as a
+    /// commission-free transaction. Open orders for the asset are cancelled.
+    fn liquidate_delisted(
+        &mut self,
+        context: &mut Context,
+        bar_data: &BarData,
+        session: SessionId,
+        timestamp: Timestamp,
+    ) -> Result<()> {
+        let session = session.date();
+        let delisted: Vec<_> = context
+            .portfolio
+            .positions
+            .values()
+            .filter(|p| session > p.asset.start_date && !p.asset.is_alive_for_session(session))
+            .map(|p| (p.asset.clone(), p.quantity, p.last_price))
+            .collect();
+
+        for (asset, quantity, last_price) in delisted {
+            let price = self
+                .delist_prices
+                .get(&asset.id)
+                .copied()
+                .or_else(|| bar_data.current_price(&asset).ok())
+                .or_else(|| self.market_stats.last_price(asset.id))
+                .unwrap_or(last_price);
+            let side = if quantity > 0.0 {
+                OrderSide::Sell
+            } else {
+                OrderSide::Buy
+            };
+
+            tracing::info!(
+                asset = %asset.symbol,
+                quantity,
+                price,
+                %session,
+                "Liquidating delisted position"
+            );
+
+            let order = Order::market(asset.clone(), side, quantity.abs(), timestamp);
+            let transaction = Transaction::new(asset.id, order.id, timestamp, -quantity, price, 0.0, side)
+                .with_id(self.rng.uuid())
+                .with_note(Some("delisted".to_string()));
+            context.portfolio.record_fill(&asset, transaction.clone());
+            self.record_fill(context, transaction)?;
+
+            context.pending_orders.retain(|o| {
+                let keep = o.asset.id != asset.id;
+                if !keep {
+                    tracing::warn!(order_id = %o.id, asset = %asset.symbol, "Cancelling order for delisted asset");
+                }
+                keep
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Record a fill, reconciling the portfolio with its ledger when enabled
+    ///
+    /// `transaction` is the one already applied to the portfolio, with its id
+    /// from the run's generator, so the results and the ledger's lots and
+    /// realized gains refer to the same id.
+    fn record_fill(&mut self, context: &mut Context, transaction: Transaction) -> Result<()> {
+        context.record_trade(&transaction);
+        let portfolio = &context.portfolio;
+        if self.reconcile {
+            portfolio.ledger().reconcile(portfolio)?;
+        }
+        if !self.events.is_empty() {
+            self.emit_fill(portfolio, &transaction);
+        }
+        self.performance.record_transaction(transaction);
+        Ok(())
+    }
+
+    /// Publish a fill and any position it opened or closed
+    fn emit_fill(&mut self, portfolio: &Portfolio, transaction: &Transaction) {
+        let (asset_id, timestamp) = (transaction.asset_id, transaction.dt);
+        let after = portfolio.get_position(asset_id).map_or(0.0, |p| p.quantity);
+        let before = after - transaction.amount;
+        let flat = |quantity: f64| quantity.abs() < QUANTITY_TOLERANCE;
+
+        self.events.emit(EngineEvent::OrderFilled {
+            transaction: transaction.clone(),
+        });
+        // A fill through zero closes one position and opens the opposite one
+        if !flat(before) && (flat(after) || before.signum() != after.signum()) {
+            self.events.emit(EngineEvent::PositionClosed { asset_id, timestamp });
+        }
+        if !flat(after) && (flat(before) || before.signum() != after.signum()) {
+            self.events.emit(EngineEvent::PositionOpened {
+                asset_id,
+                quantity: after,
+                timestamp,
+            });
+        }
+    }
+
+    /// Publish orders placed, amended and cancelled since the last bar's order processing
+    fn announce_orders(&mut self, context: &mut Context) {
+        let quantized = std::mem::take(&mut context.quantized);
+        let amended = std::mem::take(&mut context.amended);
+        if self.events.is_empty() {
+            return;
+        }
+        for quantization in quantized {
+            self.events.emit(EngineEvent::OrderQuantized {
+                quantization,
+                timestamp: context.timestamp,
+            });
+        }
+
+        // Replacements of announced orders are amendments, not a cancel and a
+        // new order; replacements of orders placed this bar are just new orders
+        for (replaced, order_id) in amended {
+            let Some(order) = context.pending_orders.iter().find(|o| o.id == order_id) else {
+                continue;
+            };
+            if self.open_orders.remove(&replaced).is_some() {
+                self.open_orders.insert(order.id, order.asset.id);
+                self.events.emit(EngineEvent::OrderAmended {
+                    replaced,
+                    order: order.clone(),
+                    timestamp: context.timestamp,
+                });
+            }
+        }
+
+        let pending: HashMap<OrderId, &Order> =
+            context.pending_orders.iter().map(|o| (o.id, o)).collect();
+        let mut cancelled: Vec<(OrderId, AssetId)> = self
+            .open_orders
+            .iter()
+            .filter(|(id, _)| !pending.contains_key(*id))
+            .map(|(id, asset_id)| (*id, *asset_id))
+            .collect();
+        cancelled.sort_unstable();
+        for (order_id, asset_id) in cancelled {
+            self.open_orders.remove(&order_id);
+            self.events.emit(EngineEvent::OrderCancelled {
+                order_id,
+                asset_id,
+                timestamp: context.timestamp,
+            });
+        }
+
+        for order in &context.pending_orders {
+            if self.open_orders.insert(order.id, order.asset.id).is_none() {
+                self.events.emit(EngineEvent::OrderSubmitted {
+                    order: order.clone(),
+                    timestamp: context.timestamp,
+                });
+            }
+        }
+    }
+
     /// Process pending orders
     fn process_orders(&mut self, context: &mut Context, bar_data: &BarData) -> Result<()> {
-        let orders = std::mem::take(&mut context.pending_orders);
+        let mut orders: VecDeque<Order> = std::mem::take(&mut context.pending_orders).into();
+
+        // Exits of brackets whose orders were dropped wholesale (halts,
+        // delistings, restrictions) have nothing left to protect
+        let open: HashSet<OrderId> = orders.iter().map(|o| o.id).collect();
+        context.brackets.retain_open(|order_id| open.contains(&order_id));
+
+        while let Some(mut order) = orders.pop_front() {
+            let _order_span = tracing::debug_span!(
+                "execute_order",
+                order_id = %order.id,
+                asset = %order.asset.symbol,
+                side = ?order.side,
+                quantity = order.quantity,
+            )
+            .entered();
 
-        for mut order in orders {
             // Get current price
             let current_price = match bar_data.current_price(&order.asset) {
                 Ok(price) => price,
                 Err(e) => {
-                    log::warn!("No price data for {}: {}", order.asset.symbol, e);
+                    tracing::warn!(error = %e, "No price data");
                     context.pending_orders.push(order);
                     continue;
                 }
@@ -178,23 +1160,49 @@ impl SimulationEngine {
             // Execute order
             match self
                 .broker
-                .execute_order(&mut order, current_price, context.timestamp)?
+                .execute_order_with_rng(&mut order, current_price, context.timestamp, &mut self.rng)?
             {
                 ExecutionResult::Filled {
                     price,
                     quantity,
                     commission,
                 } => {
-                    log::debug!(
-                        "Filled order: {} {} @ {:.2} (commission: {:.2})",
-                        quantity,
-                        order.asset.symbol,
-                        price,
-                        commission
-                    );
+                    tracing::debug!(quantity, price, commission, "Filled order");
 
                     // Update portfolio
-                    context.portfolio.execute_order(&order, price, commission);
+                    let amount = match order.side {
+                        OrderSide::Buy => quantity,
+                        OrderSide::Sell => -quantity,
+                    };
+                    let transaction = Transaction::new(
+                        order.asset.id,
+                        order.id,
+                        context.timestamp,
+                        amount,
+                        price,
+                        commission,
+                        order.side,
+                    )
+                    .with_id(self.rng.uuid())
+                    .with_note(order.note.clone());
+                    context.portfolio.record_fill(&order.asset, transaction.clone());
+                    self.open_orders.remove(&order.id);
+                    if order.is_filled() {
+                        context.filled_orders.insert(order.id);
+
+                        // A filled bracket exit cancels its sibling, even one
+                        // not yet reached this bar; a filled entry opens its exits
+                        if let Some(sibling_id) = context.brackets.sibling(order.id) {
+                            if let Some(pos) = orders.iter().position(|o| o.id == sibling_id) {
+                                if let Some(mut sibling) = orders.remove(pos) {
+                                    sibling.cancel(context.timestamp);
+                                }
+                            }
+                        }
+                        context.close_bracket_order(&order);
+                    }
+
+                    self.record_fill(context, transaction)?;
                 }
                 ExecutionResult::NotFilled => {
                     // Keep order for next iteration
@@ -264,4 +1272,708 @@ mod tests {
         // Run backtest
         let _performance = engine.run(&mut algorithm, &data_source, start, end).unwrap();
     }
+
+    #[test]
+    fn test_intraday_metrics() {
+        use chrono::TimeZone;
+
+        let mut data_source = InMemoryDataSource::new();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        data_source.add_asset(asset.clone());
+
+        // Day one dips 20% intraday and recovers by the close
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 3, 14, 30, 0).unwrap();
+        let closes = [
+            (day1, 100.0),
+            (day1 + chrono::Duration::minutes(1), 80.0),
+            (day1 + chrono::Duration::minutes(2), 100.0),
+            (day2, 110.0),
+            (day2 + chrono::Duration::minutes(1), 105.0),
+        ];
+        for (timestamp, close) in closes {
+            data_source.add_bar(1, Bar::new(timestamp, close, close, close, close, 10000.0));
+        }
+        let end = day2 + chrono::Duration::minutes(1);
+        data_source.set_date_range(day1, end);
+
+        let config = EngineConfig {
+            starting_cash: 10_000.0,
+            intraday_metrics: true,
+            ..Default::default()
+        };
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::new(config, SimulatedBroker::default_broker(), calendar);
+        let mut algorithm = BuyAndHold::new(asset);
+
+        let performance = engine.run(&mut algorithm, &data_source, day1, end).unwrap();
+
+        assert_eq!(performance.intraday.len(), 5);
+        assert_eq!(performance.values.len(), 2);
+        assert_eq!(performance.values[0].1, 10_000.0);
+        assert_eq!(performance.values[1].1, 10_500.0);
+        assert_eq!(performance.max_drawdown(), 0.0);
+        assert!((performance.intraday_max_drawdown() - 0.2).abs() < 1e-9);
+        assert!((performance.gross_exposure_series()[3].1 - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_record_fingerprints() {
+        let mut data_source = InMemoryDataSource::new();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        data_source.add_asset(asset.clone());
+
+        let start = Utc::now();
+        let end = start + chrono::Duration::minutes(2);
+        for i in 0..3 {
+            let timestamp = start + chrono::Duration::minutes(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let run = |starting_cash: f64| {
+            let config = EngineConfig {
+                starting_cash,
+                record_fingerprints: true,
+                ..Default::default()
+            };
+            let calendar = Arc::new(NYSECalendar::new());
+            let mut engine =
+                SimulationEngine::new(config, SimulatedBroker::default_broker(), calendar);
+            let mut algorithm = BuyAndHold::new(asset.clone());
+            engine.run(&mut algorithm, &data_source, start, end).unwrap()
+        };
+
+        let first = run(10_000.0);
+        assert_eq!(first.fingerprints.len(), 3);
+        assert_eq!(first.first_divergence(&run(10_000.0)), None);
+
+        // Leftover cash differs from the first bar
+        let divergence = first.first_divergence(&run(10_050.0)).unwrap();
+        assert_eq!(divergence.index, 0);
+        assert_eq!(divergence.timestamp, start);
+    }
+
+    struct ScreenedAlgorithm {
+        in_screen: Asset,
+        outside_screen: Asset,
+        rejected: usize,
+    }
+
+    impl Algorithm for ScreenedAlgorithm {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            context.update_universe("universe", vec![self.in_screen.id]);
+            context.order(self.in_screen.clone(), 1.0)?;
+            if context.order(self.outside_screen.clone(), 1.0).is_err() {
+                self.rejected += 1;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_universe_screen_enforcement() {
+        let mut data_source = InMemoryDataSource::new();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let aapl = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let msft = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let start = Utc::now();
+        let end = start + chrono::Duration::minutes(2);
+        for i in 0..3 {
+            let timestamp = start + chrono::Duration::minutes(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 10000.0));
+            data_source.add_bar(2, Bar::new(timestamp, 50.0, 50.0, 50.0, 50.0, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::default_engine(calendar)
+            .with_universe_screen("universe", UniverseEnforcement::Reject);
+        let mut algorithm = ScreenedAlgorithm {
+            in_screen: aapl,
+            outside_screen: msft,
+            rejected: 0,
+        };
+
+        engine.run(&mut algorithm, &data_source, start, end).unwrap();
+        assert_eq!(algorithm.rejected, 3);
+    }
+
+    struct NotedAlgorithm {
+        asset: Asset,
+    }
+
+    impl Algorithm for NotedAlgorithm {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            if context.portfolio.positions.is_empty() && context.pending_orders.is_empty() {
+                context.order(self.asset.clone(), 10.0)?;
+                context.log_trade_note(&self.asset, "entry, signal at 1.5 sigma");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trade_notes_in_transactions_export() {
+        let mut data_source = InMemoryDataSource::new();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let start = Utc::now();
+        let end = start + chrono::Duration::minutes(2);
+        for i in 0..3 {
+            let timestamp = start + chrono::Duration::minutes(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::default_engine(calendar);
+        let mut algorithm = NotedAlgorithm { asset };
+        let performance = engine.run(&mut algorithm, &data_source, start, end).unwrap();
+
+        assert_eq!(performance.transactions.len(), 1);
+        let txn = &performance.transactions[0];
+        assert_eq!(txn.amount, 10.0);
+        assert_eq!(txn.note.as_deref(), Some("entry, signal at 1.5 sigma"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transactions.csv");
+        performance.write_transactions_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("dt,order_id,asset_id,side,amount,price,commission,note")
+        );
+        assert!(lines.next().unwrap().ends_with(",\"entry, signal at 1.5 sigma\""));
+    }
+
+    struct BuyOnce {
+        asset: Asset,
+        ordered: bool,
+    }
+
+    impl Algorithm for BuyOnce {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            if !self.ordered {
+                context.order(self.asset.clone(), 10.0)?;
+                self.ordered = true;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_delisted_position_is_liquidated() {
+        use chrono::TimeZone;
+
+        // Last session is 2024-01-02; the run continues into 2024-01-03 New
+        // York time, so the UTC date changes hours before the session does
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "GONE".to_string(), "NYSE".to_string(), listed)
+            .with_end_date(chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 3, 4, 58, 0).unwrap();
+        let end = start + chrono::Duration::minutes(3);
+        let mut data_source = InMemoryDataSource::new();
+        data_source.add_bar(1, Bar::new(start, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        let last = start + chrono::Duration::minutes(1);
+        data_source.add_bar(1, Bar::new(last, 105.0, 105.0, 105.0, 105.0, 10000.0));
+        // Another asset keeps the clock running after the delisting
+        for i in 0..4 {
+            let timestamp = start + chrono::Duration::minutes(i);
+            data_source.add_bar(2, Bar::new(timestamp, 50.0, 50.0, 50.0, 50.0, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let run = |engine: SimulationEngine| {
+            let mut engine = engine;
+            let mut algorithm = BuyOnce {
+                asset: asset.clone(),
+                ordered: false,
+            };
+            engine.run(&mut algorithm, &data_source, start, end).unwrap()
+        };
+
+        let calendar = Arc::new(NYSECalendar::new());
+        let performance = run(SimulationEngine::default_engine(calendar.clone()));
+
+        assert_eq!(performance.transactions.len(), 2);
+        let liquidation = &performance.transactions[1];
+        assert_eq!(liquidation.amount, -10.0);
+        assert_eq!(liquidation.price, 105.0);
+        assert_eq!(liquidation.commission, 0.0);
+        assert_eq!(liquidation.dt, start + chrono::Duration::minutes(2));
+        assert_eq!(liquidation.note.as_deref(), Some("delisted"));
+
+        // A known settlement price takes precedence over the last trade
+        let performance = run(SimulationEngine::default_engine(calendar.clone()).with_delist_price(1, 120.0));
+        assert_eq!(performance.transactions[1].price, 120.0);
+
+        // The buy and the liquidation both reconcile with the ledger
+        let performance = run(SimulationEngine::default_engine(calendar).with_reconciliation());
+        assert_eq!(performance.transactions.len(), 2);
+    }
+
+    #[test]
+    fn test_interest_accrues_before_delisting() {
+        use crate::finance::{FixedRates, InterestModel};
+        use chrono::TimeZone;
+
+        // Delisted after Friday's session; Monday liquidates it
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let friday = chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let asset = Asset::equity(1, "GONE".to_string(), "NYSE".to_string(), listed).with_end_date(friday);
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 5, 14, 31, 0).unwrap();
+        let monday = Utc.with_ymd_and_hms(2024, 1, 8, 14, 31, 0).unwrap();
+        let mut data_source = InMemoryDataSource::new();
+        data_source.add_bar(1, Bar::new(start, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        data_source.add_bar(2, Bar::new(start, 50.0, 50.0, 50.0, 50.0, 10000.0));
+        data_source.add_bar(2, Bar::new(monday, 50.0, 50.0, 50.0, 50.0, 10000.0));
+        data_source.set_date_range(start, monday);
+
+        let model = Arc::new(FixedRates::new(0.036, 0.0));
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::default_engine(calendar).with_interest_model(model.clone());
+        let mut algorithm = BuyOnce {
+            asset,
+            ordered: false,
+        };
+        let performance = engine.run(&mut algorithm, &data_source, start, monday).unwrap();
+
+        assert_eq!(performance.transactions.len(), 2);
+        assert_eq!(performance.transactions[1].note.as_deref(), Some("delisted"));
+
+        // Friday's closing cash earns three days, without Monday's proceeds
+        let buy = &performance.transactions[0];
+        let friday_cash = 100_000.0 - buy.amount * buy.price - buy.commission;
+        let expected = model.accrue(friday_cash, friday, 3);
+        assert_eq!(performance.interest.len(), 1);
+        assert!((performance.interest[0].1 - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dividend_paid_before_delisting() {
+        use crate::data::adjustments::{Adjustment, AdjustmentKind, AdjustmentReader, DividendKind};
+        use crate::finance::DividendPolicy;
+        use chrono::TimeZone;
+
+        // Held through Friday's close, goes ex on Monday and is delisted
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let friday = chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let asset = Asset::equity(1, "GONE".to_string(), "NYSE".to_string(), listed).with_end_date(friday);
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 5, 14, 31, 0).unwrap();
+        let monday = Utc.with_ymd_and_hms(2024, 1, 8, 14, 31, 0).unwrap();
+        let mut data_source = InMemoryDataSource::new();
+        data_source.add_bar(1, Bar::new(start, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        data_source.add_bar(2, Bar::new(start, 50.0, 50.0, 50.0, 50.0, 10000.0));
+        data_source.add_bar(2, Bar::new(monday, 50.0, 50.0, 50.0, 50.0, 10000.0));
+        data_source.set_date_range(start, monday);
+
+        let mut actions = AdjustmentReader::new();
+        let kind = AdjustmentKind::Dividend { amount: 2.0, kind: DividendKind::Cash };
+        actions.add_adjustment(Adjustment::new(1, monday, kind));
+        let actions = Arc::new(actions);
+
+        for policy in [DividendPolicy::Cash, DividendPolicy::Reinvest] {
+            let calendar = Arc::new(NYSECalendar::new());
+            let mut engine =
+                SimulationEngine::default_engine(calendar).with_dividends(actions.clone(), policy);
+            let mut algorithm = BuyOnce {
+                asset: asset.clone(),
+                ordered: false,
+            };
+            let performance = engine.run(&mut algorithm, &data_source, start, monday).unwrap();
+
+            let dividends: f64 = performance.attribution.table().iter().map(|(_, pnl)| pnl.dividends).sum();
+            assert_eq!(dividends, 20.0);
+            // The reinvestment is cancelled with the delisted asset's orders
+            assert_eq!(performance.transactions.len(), 2);
+            assert_eq!(performance.transactions[1].note.as_deref(), Some("delisted"));
+        }
+    }
+
+    struct LotRecorder {
+        asset: Asset,
+        lot_ids: Vec<uuid::Uuid>,
+    }
+
+    impl Algorithm for LotRecorder {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            context.order(self.asset.clone(), 10.0)?;
+            Ok(())
+        }
+
+        fn analyze(&mut self, context: &Context) -> Result<()> {
+            self.lot_ids = context
+                .portfolio
+                .ledger()
+                .get_position(self.asset.id)
+                .map(|p| p.lots().map(|lot| lot.transaction_id).collect())
+                .unwrap_or_default();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_transaction_ids_match_ledger_lots() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let mut data_source = InMemoryDataSource::new();
+        let start = Utc::now();
+        let end = start + chrono::Duration::minutes(1);
+        for i in 0..2 {
+            let timestamp = start + chrono::Duration::minutes(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let run = || {
+            let calendar = Arc::new(NYSECalendar::new());
+            let mut engine = SimulationEngine::default_engine(calendar).with_seed(7);
+            let mut algorithm = LotRecorder { asset: asset.clone(), lot_ids: Vec::new() };
+            let performance = engine.run(&mut algorithm, &data_source, start, end).unwrap();
+            let ids: Vec<_> = performance.transactions.iter().map(|t| t.id).collect();
+            (ids, algorithm.lot_ids)
+        };
+
+        let (ids, lot_ids) = run();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(lot_ids, ids);
+        assert_eq!(run().0, ids);
+    }
+
+    struct BracketOnce {
+        asset: Asset,
+        ordered: bool,
+    }
+
+    impl Algorithm for BracketOnce {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            if !self.ordered {
+                context.order_bracket(self.asset.clone(), 10.0, None, 95.0, 110.0)?;
+                self.ordered = true;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bracket_exits_run_in_the_engine() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        // Entry fills at 100, take-profit at 112; the stop must not fire at 90
+        let start = Utc::now();
+        let end = start + chrono::Duration::minutes(3);
+        let mut data_source = InMemoryDataSource::new();
+        for (i, close) in [100.0, 100.0, 112.0, 90.0].into_iter().enumerate() {
+            let timestamp = start + chrono::Duration::minutes(i as i64);
+            data_source.add_bar(1, Bar::new(timestamp, close, close, close, close, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::default_engine(calendar);
+        let mut algorithm = BracketOnce { asset, ordered: false };
+        let performance = engine.run(&mut algorithm, &data_source, start, end).unwrap();
+
+        let amounts: Vec<_> = performance.transactions.iter().map(|t| t.amount).collect();
+        assert_eq!(amounts, vec![10.0, -10.0]);
+        assert_eq!(performance.transactions[1].dt, start + chrono::Duration::minutes(2));
+    }
+
+    struct SessionCounter {
+        sessions: usize,
+        bars: usize,
+    }
+
+    impl Algorithm for SessionCounter {
+        fn initialize(&mut self, context: &mut Context) {
+            context.schedule_function(
+                |ctx| {
+                    ctx.record("rebalanced", 1.0);
+                    Ok(())
+                },
+                Box::new(crate::schedule::EveryDay),
+                Box::new(crate::schedule::MarketClose::with_offset(-30)),
+                "rebalance",
+            );
+        }
+
+        fn before_trading_start(&mut self, _context: &mut Context, _data: &BarData) -> Result<()> {
+            self.sessions += 1;
+            Ok(())
+        }
+
+        fn handle_data(&mut self, _context: &mut Context, _data: &BarData) -> Result<()> {
+            self.bars += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_minute_clock_aligns_schedule_to_session() {
+        use chrono::TimeZone;
+
+        // 15:28-15:32 New York time (EDT) on two sessions
+        let mut data_source = InMemoryDataSource::new();
+        let days = [
+            Utc.with_ymd_and_hms(2024, 7, 1, 19, 28, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 2, 19, 28, 0).unwrap(),
+        ];
+        for day in days {
+            for i in 0..5 {
+                let timestamp = day + chrono::Duration::minutes(i);
+                data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 1000.0));
+            }
+        }
+        let (start, end) = (days[0], days[1] + chrono::Duration::minutes(4));
+        data_source.set_date_range(start, end);
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = SessionCounter { sessions: 0, bars: 0 };
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+        while stepper.step().unwrap().is_some() {}
+
+        // Thirty minutes before the 16:00 EDT close, once per session
+        let fired: Vec<_> = stepper.context().recorded_vars["rebalanced"]
+            .iter()
+            .map(|(dt, _)| *dt)
+            .collect();
+        assert_eq!(fired, vec![days[0] + chrono::Duration::minutes(2), days[1] + chrono::Duration::minutes(2)]);
+        stepper.finish().unwrap();
+
+        assert_eq!(algorithm.sessions, 2);
+        assert_eq!(algorithm.bars, 10);
+    }
+
+    #[test]
+    fn test_locked_holdout_refuses_run() {
+        use chrono::TimeZone;
+
+        let start = Utc.with_ymd_and_hms(2024, 7, 1, 14, 30, 0).unwrap();
+        let end = start + chrono::Duration::minutes(2);
+        let mut data_source = InMemoryDataSource::new();
+        for i in 0..3 {
+            let timestamp = start + chrono::Duration::minutes(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 1000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let session = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let mut holdout = Holdout::new(session, session).unwrap();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()))
+            .with_holdout(holdout.clone());
+        let mut algorithm = SessionCounter { sessions: 0, bars: 0 };
+        let result = engine.run(&mut algorithm, &data_source, start, end);
+        assert!(matches!(result, Err(crate::error::ZiplineError::HoldoutLocked { .. })));
+        assert_eq!(algorithm.bars, 0);
+
+        holdout.unlock("tester", "final evaluation").unwrap();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()))
+            .with_holdout(holdout);
+        engine.run(&mut algorithm, &data_source, start, end).unwrap();
+        assert_eq!(algorithm.bars, 3);
+    }
+
+    #[test]
+    fn test_daily_clock() {
+        use chrono::TimeZone;
+
+        // Daily bars stamped at midnight New York time
+        let start = Utc.with_ymd_and_hms(2024, 7, 1, 4, 0, 0).unwrap();
+        let end = start + chrono::Duration::days(2);
+        let mut data_source = InMemoryDataSource::new();
+        for i in 0..3 {
+            let timestamp = start + chrono::Duration::days(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 1000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let config = EngineConfig {
+            data_frequency: DataFrequency::Daily,
+            ..Default::default()
+        };
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::new(config, SimulatedBroker::default_broker(), calendar);
+        let mut algorithm = SessionCounter { sessions: 0, bars: 0 };
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+        while stepper.step().unwrap().is_some() {}
+
+        // Scheduled functions run on each daily bar regardless of time rule
+        assert_eq!(stepper.context().recorded_vars["rebalanced"].len(), 3);
+        stepper.finish().unwrap();
+
+        assert_eq!(algorithm.sessions, 3);
+        assert_eq!(algorithm.bars, 3);
+    }
+
+    #[test]
+    fn test_daily_clock_follows_sessions() {
+        use chrono::TimeZone;
+
+        // Bars at the close, at midnight UTC and at midnight New York time,
+        // across a weekend and the July 4 holiday; the run starts mid-morning
+        let stamps = [
+            Utc.with_ymd_and_hms(2024, 6, 28, 20, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 2, 4, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 3, 17, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 5, 20, 0, 0).unwrap(),
+        ];
+        let mut data_source = InMemoryDataSource::new();
+        for timestamp in stamps {
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 1000.0));
+        }
+        let start = Utc.with_ymd_and_hms(2024, 6, 28, 14, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 7, 6, 0, 0, 0).unwrap();
+        data_source.set_date_range(start, end);
+
+        let config = EngineConfig {
+            data_frequency: DataFrequency::Daily,
+            ..Default::default()
+        };
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::new(config, SimulatedBroker::default_broker(), calendar);
+        let mut algorithm = SessionCounter { sessions: 0, bars: 0 };
+        engine.run(&mut algorithm, &data_source, start, end).unwrap();
+
+        // July 3 closes early at 13:00 New York time
+        assert_eq!(algorithm.bars, 5);
+        assert_eq!(algorithm.sessions, 5);
+    }
+
+    /// Needs a five-bar window and records how much history it saw first
+    struct WindowedAlgorithm {
+        asset: Asset,
+        first_history: Option<Vec<Price>>,
+    }
+
+    impl Algorithm for WindowedAlgorithm {
+        fn handle_data(&mut self, _context: &mut Context, data: &BarData) -> Result<()> {
+            if self.first_history.is_none() {
+                self.first_history = Some(data.history_prices(&self.asset, 5)?);
+            }
+            Ok(())
+        }
+
+        fn warm_up_bars(&self) -> usize {
+            4
+        }
+    }
+
+    #[test]
+    fn test_warm_up_fills_history_before_start() {
+        use chrono::TimeZone;
+
+        // Ten days of bars from Monday July 1; the run starts on Sunday the
+        // 7th, and the weekend and the July 4 holiday are not sessions
+        let first = Utc.with_ymd_and_hms(2024, 7, 1, 4, 0, 0).unwrap();
+        let mut data_source = InMemoryDataSource::new();
+        for i in 0..10 {
+            let price = 100.0 + i as f64;
+            let timestamp = first + chrono::Duration::days(i);
+            data_source.add_bar(1, Bar::new(timestamp, price, price, price, price, 1000.0));
+        }
+        data_source.set_date_range(first, first + chrono::Duration::days(9));
+
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let config = EngineConfig {
+            data_frequency: DataFrequency::Daily,
+            max_history_len: 2,
+            ..Default::default()
+        };
+        let calendar = Arc::new(NYSECalendar::new());
+        let mut engine = SimulationEngine::new(config, SimulatedBroker::default_broker(), calendar);
+        let mut algorithm = WindowedAlgorithm {
+            asset,
+            first_history: None,
+        };
+        let start = first + chrono::Duration::days(6);
+        let performance = engine
+            .run(&mut algorithm, &data_source, start, first + chrono::Duration::days(9))
+            .unwrap();
+
+        // Four sessions before the start plus the first session of the run
+        assert_eq!(
+            algorithm.first_history,
+            Some(vec![100.0, 101.0, 102.0, 104.0, 107.0])
+        );
+        // Warm-up bars are not part of the results
+        assert_eq!(performance.values.len(), 3);
+    }
+
+    struct BuyEveryBar {
+        asset: Asset,
+    }
+
+    impl Algorithm for BuyEveryBar {
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            context.order(self.asset.clone(), 10.0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_seeded_runs_are_identical() {
+        use crate::execution::{NoCommission, RandomSlippage};
+        use chrono::TimeZone;
+
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let end = start + chrono::Duration::days(4);
+        let mut data_source = InMemoryDataSource::new();
+        for i in 0..5 {
+            let timestamp = start + chrono::Duration::days(i);
+            data_source.add_bar(1, Bar::new(timestamp, 100.0, 100.0, 100.0, 100.0, 10000.0));
+        }
+        data_source.set_date_range(start, end);
+
+        let run = |seed: Option<u64>| {
+            let broker = SimulatedBroker::new(Box::new(RandomSlippage::new(20.0)), Box::new(NoCommission));
+            let calendar = Arc::new(NYSECalendar::new());
+            let mut engine = SimulationEngine::new(EngineConfig::default(), broker, calendar);
+            if let Some(seed) = seed {
+                engine = engine.with_seed(seed);
+            }
+            let mut algorithm = BuyEveryBar { asset: asset.clone() };
+            let performance = engine.run(&mut algorithm, &data_source, start, end).unwrap();
+            let fills: Vec<_> = performance
+                .transactions
+                .iter()
+                .map(|t| (t.id, t.order_id, t.price))
+                .collect();
+            (engine.seed(), fills, performance.values)
+        };
+
+        let (seed, fills, values) = run(Some(7));
+        assert_eq!(seed, 7);
+        assert_eq!(fills.len(), 5);
+        assert_eq!(run(Some(7)), (seed, fills.clone(), values));
+        assert_ne!(run(Some(8)).1, fills);
+
+        // An unseeded run reports the seed that repeats it
+        let (drawn, fills, values) = run(None);
+        assert_eq!(run(Some(drawn)), (drawn, fills, values));
+    }
 }
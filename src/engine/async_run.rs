@@ -0,0 +1,196 @@
+//! Async backtest runs for IO-bound data sources
+//!
+//! [`SimulationEngine::run_async`] drives the same event loop as
+//! [`SimulationEngine::run`], but loads bars ahead of the algorithm on tokio
+//! tasks. While one bar is being processed, up to `prefetch` later bars are
+//! already being fetched, so a backtest over remote or lazily loaded data
+//! spends its time computing rather than waiting on IO.
+//!
+//! Bars are still processed strictly in time order; only loading overlaps.
+
+use super::SimulationEngine;
+use crate::algorithm::Algorithm;
+use crate::data::DataSource;
+use crate::error::{Result, ZiplineError};
+use crate::performance::PerformanceTracker;
+use crate::types::{Bar, Timestamp};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// A bar load started ahead of processing
+type BarLoad = (Timestamp, JoinHandle<Result<Vec<(u64, Bar)>>>);
+
+/// A source of bars that loads asynchronously
+pub trait AsyncDataSource: Send + Sync + 'static {
+    /// Get bars for a specific timestamp
+    fn get_bars(
+        &self,
+        timestamp: Timestamp,
+    ) -> impl Future<Output = Result<Vec<(u64, Bar)>>> + Send;
+
+    /// Get date range available
+    fn get_date_range(&self) -> (Timestamp, Timestamp);
+}
+
+/// Runs a blocking [`DataSource`] on tokio's blocking thread pool
+///
+/// Lets readers that do file or database IO synchronously be prefetched by
+/// [`SimulationEngine::run_async`].
+#[derive(Debug)]
+pub struct BlockingDataSource<S> {
+    inner: Arc<S>,
+}
+
+impl<S: DataSource + 'static> BlockingDataSource<S> {
+    /// Wrap a blocking data source
+    pub fn new(source: S) -> Self {
+        Self {
+            inner: Arc::new(source),
+        }
+    }
+}
+
+impl<S: DataSource + 'static> AsyncDataSource for BlockingDataSource<S> {
+    fn get_bars(
+        &self,
+        timestamp: Timestamp,
+    ) -> impl Future<Output = Result<Vec<(u64, Bar)>>> + Send {
+        let inner = Arc::clone(&self.inner);
+        async move {
+            tokio::task::spawn_blocking(move || inner.get_bars(timestamp))
+                .await
+                .map_err(|e| ZiplineError::DataError(format!("Data loading task failed: {}", e)))?
+        }
+    }
+
+    fn get_date_range(&self) -> (Timestamp, Timestamp) {
+        self.inner.get_date_range()
+    }
+}
+
+impl SimulationEngine {
+    /// Bars loaded ahead of the algorithm by [`run_async`](Self::run_async)
+    pub const DEFAULT_PREFETCH: usize = 32;
+
+    /// Run a backtest, loading bars on tokio tasks ahead of processing
+    pub async fn run_async<A: Algorithm, S: AsyncDataSource>(
+        &mut self,
+        algorithm: &mut A,
+        data_source: Arc<S>,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<PerformanceTracker> {
+        self.run_async_with_prefetch(algorithm, data_source, start, end, Self::DEFAULT_PREFETCH)
+            .await
+    }
+
+    /// Run a backtest with up to `prefetch` bar loads in flight
+    pub async fn run_async_with_prefetch<A: Algorithm, S: AsyncDataSource>(
+        &mut self,
+        algorithm: &mut A,
+        data_source: Arc<S>,
+        start: Timestamp,
+        end: Timestamp,
+        prefetch: usize,
+    ) -> Result<PerformanceTracker> {
+        let (mut context, mut bar_data, warm_up, timestamps) =
+            self.begin_run(algorithm, data_source.get_date_range(), start, end)?;
+
+        let mut warm_up_bars = Vec::with_capacity(warm_up.bars);
+        for timestamp in warm_up.times() {
+            if warm_up_bars.len() >= warm_up.bars {
+                break;
+            }
+            let bars = data_source.get_bars(timestamp).await?;
+            if !bars.is_empty() {
+                warm_up_bars.push(bars);
+            }
+        }
+        self.apply_warm_up(&mut bar_data, warm_up_bars);
+
+        let prefetch = prefetch.max(1);
+        let mut pending = timestamps.into_iter();
+        let mut in_flight: VecDeque<BarLoad> = VecDeque::with_capacity(prefetch);
+
+        loop {
+            while in_flight.len() < prefetch {
+                let Some(timestamp) = pending.next() else {
+                    break;
+                };
+                let source = Arc::clone(&data_source);
+                let load = tokio::spawn(async move { source.get_bars(timestamp).await });
+                in_flight.push_back((timestamp, load));
+            }
+
+            let Some((timestamp, load)) = in_flight.pop_front() else {
+                break;
+            };
+            let bars = load
+                .await
+                .map_err(|e| ZiplineError::DataError(format!("Data loading task failed: {}", e)))??;
+            if bars.is_empty() {
+                continue;
+            }
+
+            self.process_bar(algorithm, &mut context, &mut bar_data, timestamp, bars)?;
+        }
+
+        self.finish_run(algorithm, &mut context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::BuyAndHold;
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::InMemoryDataSource;
+    use chrono::Utc;
+
+    fn data_source(start: Timestamp) -> (Asset, InMemoryDataSource) {
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let mut source = InMemoryDataSource::new();
+        for i in 0..10 {
+            let timestamp = start + chrono::Duration::minutes(i * 2);
+            let price = 100.0 + i as f64;
+            source.add_bar(1, Bar::new(timestamp, price, price, price, price, 10000.0));
+        }
+        source.set_date_range(start, start + chrono::Duration::minutes(18));
+        (asset, source)
+    }
+
+    #[tokio::test]
+    async fn test_run_async_matches_run() {
+        let start = Utc::now();
+        let end = start + chrono::Duration::minutes(18);
+
+        let (asset, source) = data_source(start);
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let expected = engine
+            .run(&mut BuyAndHold::new(asset.clone()), &source, start, end)
+            .unwrap();
+
+        let (_, source) = data_source(start);
+        let source = Arc::new(BlockingDataSource::new(source));
+        for prefetch in [1, 4, 64] {
+            let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+            let performance = engine
+                .run_async_with_prefetch(
+                    &mut BuyAndHold::new(asset.clone()),
+                    Arc::clone(&source),
+                    start,
+                    end,
+                    prefetch,
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(performance.transactions.len(), expected.transactions.len());
+            assert_eq!(performance.values, expected.values);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Typed engine events for observers
+//!
+//! The engine publishes what happens during a run on an [`EventBus`]:
+//! sessions starting and ending, orders being submitted, filled and
+//! cancelled, and positions being opened and closed. Handlers run
+//! synchronously in the event loop, so they see events in order and in the
+//! same bar as they happen; for a dashboard or alerting running elsewhere,
+//! [`EventBus::channel`] forwards events to a receiver.
+//!
+//! ```ignore
+//! let mut engine = SimulationEngine::default_engine(calendar)
+//!     .with_event_handler(|event| tracing::info!("{}", event));
+//! let events = engine.events_mut().channel();
+//! ```
+
+use crate::finance::{Quantization, TradingHalt, Transaction};
+use crate::order::Order;
+use crate::types::{AssetId, OrderId, Quantity, SessionId, Timestamp};
+use std::fmt;
+use std::sync::mpsc;
+
+/// Something that happened during a run
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    /// First bar of a session, before `before_trading_start`
+    SessionStart { session: SessionId, timestamp: Timestamp },
+    /// A session's last bar has been processed
+    SessionEnd { session: SessionId, timestamp: Timestamp },
+    /// An order was placed by the algorithm
+    OrderSubmitted { order: Order, timestamp: Timestamp },
+    /// An open order was replaced with new terms under a new id
+    OrderAmended { replaced: OrderId, order: Order, timestamp: Timestamp },
+    /// An order's quantity or limit price was rounded materially to its
+    /// asset's lot or tick size
+    OrderQuantized { quantization: Quantization, timestamp: Timestamp },
+    /// An order was filled, fully or in part
+    OrderFilled { transaction: Transaction },
+    /// An open order was cancelled before it filled
+    OrderCancelled { order_id: OrderId, asset_id: AssetId, timestamp: Timestamp },
+    /// A position went from flat to held
+    PositionOpened { asset_id: AssetId, quantity: Quantity, timestamp: Timestamp },
+    /// A position went back to flat
+    PositionClosed { asset_id: AssetId, timestamp: Timestamp },
+    /// An account control stopped trading for the rest of the run; the
+    /// cancellations (and any liquidation orders) follow
+    TradingHalted { halt: TradingHalt },
+}
+
+impl EngineEvent {
+    /// When the event happened
+    pub fn timestamp(&self) -> Timestamp {
+        match self {
+            EngineEvent::SessionStart { timestamp, .. }
+            | EngineEvent::SessionEnd { timestamp, .. }
+            | EngineEvent::OrderSubmitted { timestamp, .. }
+            | EngineEvent::OrderAmended { timestamp, .. }
+            | EngineEvent::OrderQuantized { timestamp, .. }
+            | EngineEvent::OrderCancelled { timestamp, .. }
+            | EngineEvent::PositionOpened { timestamp, .. }
+            | EngineEvent::PositionClosed { timestamp, .. } => *timestamp,
+            EngineEvent::OrderFilled { transaction } => transaction.dt,
+            EngineEvent::TradingHalted { halt } => halt.timestamp,
+        }
+    }
+}
+
+impl fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineEvent::SessionStart { session, .. } => write!(f, "session {} started", session),
+            EngineEvent::SessionEnd { session, .. } => write!(f, "session {} ended", session),
+            EngineEvent::OrderSubmitted { order, .. } => write!(
+                f,
+                "order {} submitted: {:?} {} {}",
+                order.id, order.side, order.quantity, order.asset.symbol
+            ),
+            EngineEvent::OrderAmended { replaced, order, .. } => write!(
+                f,
+                "order {} amended as {}: {:?} {} {}",
+                replaced, order.id, order.side, order.quantity, order.asset.symbol
+            ),
+            EngineEvent::OrderQuantized { quantization: q, .. } => write!(
+                f,
+                "order {} quantized: {} -> {} of asset {}",
+                q.order_id, q.requested_quantity, q.quantity, q.asset_id
+            ),
+            EngineEvent::OrderFilled { transaction } => write!(
+                f,
+                "order {} filled: {} of asset {} @ {:.2}",
+                transaction.order_id, transaction.amount, transaction.asset_id, transaction.price
+            ),
+            EngineEvent::OrderCancelled { order_id, .. } => write!(f, "order {} cancelled", order_id),
+            EngineEvent::PositionOpened { asset_id, quantity, .. } => {
+                write!(f, "position in asset {} opened: {}", asset_id, quantity)
+            }
+            EngineEvent::PositionClosed { asset_id, .. } => write!(f, "position in asset {} closed", asset_id),
+            EngineEvent::TradingHalted { halt } => {
+                write!(f, "trading halted by {}: {}", halt.control, halt.reason)
+            }
+        }
+    }
+}
+
+/// Handler called with every event
+pub type EventHandler = Box<dyn FnMut(&EngineEvent) + Send>;
+
+/// Subscribers to a run's events
+#[derive(Default)]
+pub struct EventBus {
+    handlers: Vec<EventHandler>,
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `handler` with every event from now on
+    pub fn subscribe(&mut self, handler: impl FnMut(&EngineEvent) + Send + 'static) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    /// Forward every event from now on to the returned receiver
+    ///
+    /// Events are dropped once the receiver is gone.
+    pub fn channel(&mut self) -> mpsc::Receiver<EngineEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribe(move |event| {
+            let _ = sender.send(event.clone());
+        });
+        receiver
+    }
+
+    /// Deliver an event to every subscriber, in subscription order
+    pub fn emit(&mut self, event: EngineEvent) {
+        for handler in &mut self.handlers {
+            handler(&event);
+        }
+    }
+
+    /// Check if anyone is listening
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, BuyAndHold, Context};
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::SimulationEngine;
+    use crate::types::Bar;
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_run_publishes_lifecycle_events() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for day in 0..3 {
+            source.add_bar(1, Bar::new(start + Duration::days(day), 100.0, 101.0, 99.0, 100.0, 1e6));
+        }
+        source.set_date_range(start, start + Duration::days(2));
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let events = engine.events_mut().channel();
+        let mut algorithm = BuyAndHold::new(asset);
+        engine.run(&mut algorithm, &source, start, start + Duration::days(2)).unwrap();
+
+        let names: Vec<String> = events
+            .try_iter()
+            .map(|event| format!("{:?}", event).split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "SessionStart", "OrderSubmitted", "OrderFilled", "PositionOpened", "SessionEnd",
+                "SessionStart", "SessionEnd", "SessionStart", "SessionEnd",
+            ]
+        );
+    }
+
+    /// Bids below the market, raising the bid on the second bar
+    struct RaiseBid {
+        asset: Asset,
+        bid: Option<OrderId>,
+    }
+
+    impl Algorithm for RaiseBid {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> crate::error::Result<()> {
+            self.bid = Some(match self.bid {
+                None => context.order_limit(self.asset.clone(), 10.0, 90.0)?,
+                Some(bid) => context.update_order(bid, None, Some(95.0))?,
+            });
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_amendment_replaces_announced_order() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for day in 0..2 {
+            source.add_bar(1, Bar::new(start + Duration::days(day), 100.0, 101.0, 99.0, 100.0, 1e6));
+        }
+        source.set_date_range(start, start + Duration::days(1));
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let events = engine.events_mut().channel();
+        let mut algorithm = RaiseBid { asset, bid: None };
+        engine.run(&mut algorithm, &source, start, start + Duration::days(1)).unwrap();
+
+        let orders: Vec<EngineEvent> = events
+            .try_iter()
+            .filter(|event| !matches!(event, EngineEvent::SessionStart { .. } | EngineEvent::SessionEnd { .. }))
+            .collect();
+        assert_eq!(orders.len(), 2);
+        let EngineEvent::OrderSubmitted { order: placed, .. } = &orders[0] else { panic!("{:?}", orders[0]) };
+        let EngineEvent::OrderAmended { replaced, order, .. } = &orders[1] else { panic!("{:?}", orders[1]) };
+        assert_eq!(*replaced, placed.id);
+        assert_eq!(Some(order.id), algorithm.bid);
+        assert_eq!(order.limit_price, Some(95.0));
+    }
+}
@@ -0,0 +1,150 @@
+//! Holdout period lockbox
+//!
+//! A [`Holdout`] declares a date range reserved for out-of-sample evaluation.
+//! An engine given a locked holdout refuses any run that touches the range.
+//! Unlocking is deliberate and one-way: it records who unlocked it, when and
+//! why, and the record is kept in the holdout's manifest file so results
+//! evaluated on the range can be traced back to the decision to look.
+
+use crate::error::{Result, ZiplineError};
+use crate::serialization::{self, Versioned};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A recorded decision to evaluate on the holdout period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldoutUnlock {
+    /// When the holdout was unlocked
+    pub unlocked_at: DateTime<Utc>,
+    /// Who unlocked it
+    pub by: String,
+    /// Why it was unlocked
+    pub reason: String,
+}
+
+/// A date range kept out of backtests until explicitly unlocked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Holdout {
+    /// First session of the holdout, inclusive
+    pub start: NaiveDate,
+    /// Last session of the holdout, inclusive
+    pub end: NaiveDate,
+    /// Unlock record, if the holdout has been unlocked
+    #[serde(default)]
+    pub unlock: Option<HoldoutUnlock>,
+}
+
+impl Holdout {
+    /// Declare a locked holdout from `start` to `end`, inclusive
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Result<Self> {
+        if start > end {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Holdout start {} is after its end {}",
+                start, end
+            )));
+        }
+        Ok(Self {
+            start,
+            end,
+            unlock: None,
+        })
+    }
+
+    /// Whether runs over the holdout are still refused
+    pub fn is_locked(&self) -> bool {
+        self.unlock.is_none()
+    }
+
+    /// Whether the sessions `start..=end` touch the holdout
+    pub fn overlaps(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        start <= self.end && end >= self.start
+    }
+
+    /// Refuse a run over `start..=end` while the holdout is locked
+    pub fn check(&self, start: NaiveDate, end: NaiveDate) -> Result<()> {
+        if self.is_locked() && self.overlaps(start, end) {
+            return Err(ZiplineError::HoldoutLocked {
+                start,
+                end,
+                holdout_start: self.start,
+                holdout_end: self.end,
+            });
+        }
+        Ok(())
+    }
+
+    /// Unlock the holdout, recording who did it and why
+    ///
+    /// A holdout can only be unlocked once; the first record is kept.
+    pub fn unlock(&mut self, by: &str, reason: &str) -> Result<&HoldoutUnlock> {
+        if let Some(unlock) = &self.unlock {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Holdout was already unlocked by {} at {}",
+                unlock.by, unlock.unlocked_at
+            )));
+        }
+        if reason.trim().is_empty() {
+            return Err(ZiplineError::InvalidConfiguration(
+                "Unlocking the holdout requires a reason".to_string(),
+            ));
+        }
+
+        Ok(self.unlock.insert(HoldoutUnlock {
+            unlocked_at: Utc::now(),
+            by: by.to_string(),
+            reason: reason.to_string(),
+        }))
+    }
+
+    /// Write the holdout manifest
+    pub fn save(&self, path: &Path) -> Result<()> {
+        serialization::save(self, path)
+    }
+
+    /// Read a holdout manifest
+    pub fn load(path: &Path) -> Result<Self> {
+        serialization::load(path)
+    }
+}
+
+impl crate::sealed::Sealed for Holdout {}
+
+impl Versioned for Holdout {
+    const KIND: &'static str = "holdout";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_holdout_lock_and_unlock() {
+        assert!(Holdout::new(date(2024, 1, 1), date(2023, 1, 1)).is_err());
+
+        let mut holdout = Holdout::new(date(2023, 1, 1), date(2023, 12, 31)).unwrap();
+        assert!(holdout.check(date(2020, 1, 1), date(2022, 12, 31)).is_ok());
+        assert!(matches!(
+            holdout.check(date(2022, 6, 1), date(2023, 1, 1)),
+            Err(ZiplineError::HoldoutLocked { .. })
+        ));
+
+        assert!(holdout.unlock("alice", " ").is_err());
+        holdout.unlock("alice", "final evaluation").unwrap();
+        assert!(!holdout.is_locked());
+        assert!(holdout.check(date(2022, 6, 1), date(2023, 6, 1)).is_ok());
+        assert!(holdout.unlock("bob", "again").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("holdout.json");
+        holdout.save(&path).unwrap();
+        let loaded = Holdout::load(&path).unwrap();
+        assert_eq!(loaded, holdout);
+        assert_eq!(loaded.unlock.unwrap().by, "alice");
+    }
+}
@@ -0,0 +1,400 @@
+//! Persistent order history for audit and debugging
+//!
+//! An [`OrderLog`] listens to a run's [`EngineEvent`]s and keeps one
+//! [`OrderRecord`] per order submitted, amended, filled or cancelled. At the
+//! end of every session it adds an `Open` record for each order still
+//! working, so the log doubles as an end-of-day order report, and, when given
+//! an output directory, writes the session's records to
+//! `orders-YYYY-MM-DD.jsonl` (or `.csv`) there.
+//!
+//! ```ignore
+//! let log = OrderLog::new()
+//!     .with_output_dir("audit/orders", OrderLogFormat::Jsonl)
+//!     .attach(engine.events_mut());
+//! engine.run(&mut algorithm, &data, start, end)?;
+//!
+//! // Everything that happened to AAPL orders in March
+//! let march = log.lock().unwrap().history(Some(aapl.id), Some(march_1), Some(march_31));
+//! ```
+//!
+//! Logs written to disk are read back with [`OrderLog::load`].
+
+use super::events::{EngineEvent, EventBus};
+use crate::error::{Result, ZiplineError};
+use crate::order::{Order, OrderSide};
+use crate::types::{AssetId, Cash, OrderId, Price, Quantity, Timestamp, QUANTITY_TOLERANCE};
+use chrono::NaiveDate;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// What happened to an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderEvent {
+    /// Placed by the algorithm
+    Submitted,
+    /// Replaced with new terms; `replaces` is the old order
+    Amended,
+    /// Filled, fully or in part
+    Filled,
+    /// Cancelled before it filled
+    Cancelled,
+    /// Still working at the end of the session
+    Open,
+}
+
+/// One line of the order history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderRecord {
+    /// Session the event happened in
+    pub session: NaiveDate,
+    pub timestamp: Timestamp,
+    pub event: OrderEvent,
+    pub order_id: OrderId,
+    pub asset_id: AssetId,
+    pub side: Option<OrderSide>,
+    /// Order size, or the filled quantity for fills
+    pub quantity: Option<Quantity>,
+    /// Limit price, or the fill price for fills
+    pub price: Option<Price>,
+    pub commission: Option<Cash>,
+    /// Order an amendment replaced
+    pub replaces: Option<OrderId>,
+    pub note: Option<String>,
+}
+
+/// File format of a persisted order log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderLogFormat {
+    /// One JSON record per line
+    Jsonl,
+    /// Comma-separated, with a header row
+    Csv,
+}
+
+impl OrderLogFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OrderLogFormat::Jsonl => "jsonl",
+            OrderLogFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Order log shared with the event bus it listens to
+pub type SharedOrderLog = Arc<Mutex<OrderLog>>;
+
+/// History of every order in a run
+#[derive(Debug, Default)]
+pub struct OrderLog {
+    records: Vec<OrderRecord>,
+    /// Orders still working, with their unfilled quantity
+    open: HashMap<OrderId, (Order, Quantity)>,
+    session: Option<NaiveDate>,
+    /// Index of the current session's first record
+    session_start: usize,
+    output: Option<(PathBuf, OrderLogFormat)>,
+}
+
+impl OrderLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write each session's records to a file in `dir`
+    pub fn with_output_dir(mut self, dir: impl Into<PathBuf>, format: OrderLogFormat) -> Self {
+        self.output = Some((dir.into(), format));
+        self
+    }
+
+    /// Record every event published on `events` from now on
+    ///
+    /// Failures to write a session file are logged rather than interrupting
+    /// the run.
+    pub fn attach(self, events: &mut EventBus) -> SharedOrderLog {
+        let log = Arc::new(Mutex::new(self));
+        let handle = log.clone();
+        events.subscribe(move |event| {
+            if let Ok(mut log) = handle.lock() {
+                if let Err(e) = log.record(event) {
+                    tracing::error!(error = %e, "Failed to write order log");
+                }
+            }
+        });
+        log
+    }
+
+    /// Add an event to the history
+    ///
+    /// Returns an error only if the end of a session could not be written.
+    pub fn record(&mut self, event: &EngineEvent) -> Result<()> {
+        let timestamp = event.timestamp();
+        let session = self.session.unwrap_or_else(|| timestamp.date_naive());
+        let record = |event: OrderEvent, order_id: OrderId, asset_id: AssetId| OrderRecord {
+            session,
+            timestamp,
+            event,
+            order_id,
+            asset_id,
+            side: None,
+            quantity: None,
+            price: None,
+            commission: None,
+            replaces: None,
+            note: None,
+        };
+        let describe = |event: OrderEvent, order: &Order| OrderRecord {
+            side: Some(order.side),
+            quantity: Some(order.quantity),
+            price: order.limit_price.or(order.stop_price),
+            note: order.note.clone(),
+            ..record(event, order.id, order.asset.id)
+        };
+
+        match event {
+            EngineEvent::SessionStart { session, .. } => {
+                self.session = Some((*session).into());
+                self.session_start = self.records.len();
+            }
+            EngineEvent::OrderSubmitted { order, .. } => {
+                self.records.push(describe(OrderEvent::Submitted, order));
+                self.open.insert(order.id, (order.clone(), order.remaining()));
+            }
+            EngineEvent::OrderAmended { replaced, order, .. } => {
+                self.records.push(OrderRecord {
+                    replaces: Some(*replaced),
+                    ..describe(OrderEvent::Amended, order)
+                });
+                self.open.remove(replaced);
+                self.open.insert(order.id, (order.clone(), order.remaining()));
+            }
+            EngineEvent::OrderFilled { transaction } => {
+                self.records.push(OrderRecord {
+                    side: Some(transaction.side),
+                    quantity: Some(transaction.amount.abs()),
+                    price: Some(transaction.price),
+                    commission: Some(transaction.commission),
+                    note: transaction.note.clone(),
+                    ..record(OrderEvent::Filled, transaction.order_id, transaction.asset_id)
+                });
+                if let Some((_, remaining)) = self.open.get_mut(&transaction.order_id) {
+                    *remaining -= transaction.amount.abs();
+                    if *remaining < QUANTITY_TOLERANCE {
+                        self.open.remove(&transaction.order_id);
+                    }
+                }
+            }
+            EngineEvent::OrderCancelled { order_id, asset_id, .. } => {
+                self.records.push(record(OrderEvent::Cancelled, *order_id, *asset_id));
+                self.open.remove(order_id);
+            }
+            EngineEvent::SessionEnd { .. } => {
+                let mut open: Vec<&(Order, Quantity)> = self.open.values().collect();
+                open.sort_by_key(|(order, _)| (order.created_at, order.id));
+                let snapshot: Vec<OrderRecord> = open
+                    .into_iter()
+                    .map(|(order, remaining)| OrderRecord {
+                        quantity: Some(*remaining),
+                        ..describe(OrderEvent::Open, order)
+                    })
+                    .collect();
+                self.records.extend(snapshot);
+                self.write_session()?;
+                self.session_start = self.records.len();
+            }
+            EngineEvent::PositionOpened { .. }
+            | EngineEvent::PositionClosed { .. }
+            | EngineEvent::OrderQuantized { .. }
+            | EngineEvent::TradingHalted { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Write the current session's records to the output directory
+    fn write_session(&self) -> Result<()> {
+        let (Some((dir, format)), Some(session)) = (&self.output, self.session) else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("orders-{}.{}", session, format.extension()));
+        let records = &self.records[self.session_start..];
+        write_records(&path, *format, records)
+    }
+
+    /// Records in the order they happened
+    pub fn records(&self) -> &[OrderRecord] {
+        &self.records
+    }
+
+    /// Records for one asset, or all, in sessions from `start` to `end` inclusive
+    pub fn history(
+        &self,
+        asset_id: Option<AssetId>,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> Vec<&OrderRecord> {
+        self.records
+            .iter()
+            .filter(|r| asset_id.is_none_or(|id| r.asset_id == id))
+            .filter(|r| start.is_none_or(|start| r.session >= start))
+            .filter(|r| end.is_none_or(|end| r.session <= end))
+            .collect()
+    }
+
+    /// Every record of one order, including the orders it amended
+    pub fn order_history(&self, order_id: OrderId) -> Vec<&OrderRecord> {
+        let mut ids = vec![order_id];
+        for record in self.records.iter().rev() {
+            if ids.contains(&record.order_id) {
+                ids.extend(record.replaces);
+            }
+        }
+        self.records.iter().filter(|r| ids.contains(&r.order_id)).collect()
+    }
+
+    /// Read back the session files written to `dir`, in session order
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                name.starts_with("orders-") && (name.ends_with(".jsonl") || name.ends_with(".csv"))
+            })
+            .collect();
+        files.sort();
+
+        let mut log = Self::new();
+        for path in files {
+            log.records.extend(read_records(&path)?);
+        }
+        log.session_start = log.records.len();
+        Ok(log)
+    }
+}
+
+fn write_records(path: &Path, format: OrderLogFormat, records: &[OrderRecord]) -> Result<()> {
+    match format {
+        OrderLogFormat::Jsonl => {
+            let mut writer = BufWriter::new(fs::File::create(path)?);
+            for record in records {
+                serde_json::to_writer(&mut writer, record)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        OrderLogFormat::Csv => {
+            let csv_error = |e: csv::Error| {
+                ZiplineError::DataError(format!("Failed to write {}: {}", path.display(), e))
+            };
+            let mut writer = csv::Writer::from_path(path).map_err(csv_error)?;
+            for record in records {
+                writer.serialize(record).map_err(csv_error)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn read_records(path: &Path) -> Result<Vec<OrderRecord>> {
+    if path.extension().is_some_and(|ext| ext == "csv") {
+        let csv_error = |e: csv::Error| {
+            ZiplineError::DataError(format!("Failed to read {}: {}", path.display(), e))
+        };
+        let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+        return reader
+            .deserialize()
+            .map(|record| record.map_err(csv_error))
+            .collect();
+    }
+
+    let mut records = Vec::new();
+    for line in BufReader::new(fs::File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            records.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, Context};
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::SimulationEngine;
+    use crate::types::Bar;
+    use chrono::{Duration, TimeZone, Utc};
+
+    /// Bids below the market, raises the bid, then buys at market and cancels
+    struct Trader {
+        asset: Asset,
+        bar: usize,
+        bid: Option<OrderId>,
+    }
+
+    impl Algorithm for Trader {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            match self.bar {
+                0 => self.bid = Some(context.order_limit(self.asset.clone(), 10.0, 90.0)?),
+                1 => self.bid = Some(context.update_order(self.bid.unwrap(), None, Some(95.0))?),
+                _ => {
+                    context.order(self.asset.clone(), 5.0)?;
+                    context.cancel_order(self.bid.unwrap())?;
+                }
+            }
+            self.bar += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sessions_written_and_read_back() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let end = start + Duration::days(2);
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for day in 0..3 {
+            source.add_bar(1, Bar::new(start + Duration::days(day), 100.0, 101.0, 99.0, 100.0, 1e6));
+        }
+        source.set_date_range(start, end);
+
+        for format in [OrderLogFormat::Jsonl, OrderLogFormat::Csv] {
+            let dir = std::env::temp_dir().join(format!("order_log_{}_{}", format.extension(), std::process::id()));
+            let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+            let log = OrderLog::new().with_output_dir(&dir, format).attach(engine.events_mut());
+            let mut algorithm = Trader { asset: asset.clone(), bar: 0, bid: None };
+            engine.run(&mut algorithm, &source, start, end).unwrap();
+
+            let log = log.lock().unwrap();
+            let events: Vec<OrderEvent> = log.records().iter().map(|r| r.event).collect();
+            use OrderEvent::*;
+            assert_eq!(events, [Submitted, Open, Amended, Open, Cancelled, Submitted, Filled]);
+
+            // The bid's whole life, through its amendment
+            let bid = log.order_history(algorithm.bid.unwrap());
+            assert_eq!(bid.len(), 5);
+            assert_eq!(bid[2].replaces, Some(bid[0].order_id));
+
+            let day_two = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+            assert_eq!(log.history(Some(1), Some(day_two), Some(day_two)).len(), 2);
+            assert!(log.history(Some(2), None, None).is_empty());
+
+            // One file per session, read back as written
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+            let loaded = OrderLog::load(&dir).unwrap();
+            assert_eq!(loaded.records(), log.records());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
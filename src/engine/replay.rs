@@ -0,0 +1,255 @@
+//! Execution-only replay of externally generated signals
+//!
+//! Research done elsewhere (a notebook, an ML pipeline) often ends in a table
+//! of target weights per date. [`TargetWeights`] holds such a table and
+//! [`SignalReplay`] trades it: on the first bar at or after each signal date it
+//! rebalances to that date's weights, so the engine simulates only execution,
+//! costs and accounting.
+//!
+//! Each date's rows are the complete target book. Holdings not listed on a
+//! date are closed, and the book is held unchanged until the next date.
+//!
+//! ```ignore
+//! let signals = TargetWeights::from_csv(Path::new("weights.csv"))?;
+//! let results = engine.run_signals(signals, &data_source, start, end)?;
+//! ```
+
+use super::SimulationEngine;
+use crate::algorithm::{Algorithm, Context};
+use crate::asset::Asset;
+use crate::data::{BarData, DataSource};
+use crate::error::{Result, ZiplineError};
+use crate::performance::PerformanceTracker;
+use crate::types::{AssetId, Timestamp};
+use chrono::NaiveDate;
+use hashbrown::HashMap;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Target portfolio weights by date
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetWeights {
+    by_date: BTreeMap<NaiveDate, HashMap<AssetId, f64>>,
+}
+
+impl TargetWeights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an asset's target weight on a date
+    pub fn insert(&mut self, date: NaiveDate, asset_id: AssetId, weight: f64) {
+        self.by_date
+            .entry(date)
+            .or_default()
+            .insert(asset_id, weight);
+    }
+
+    /// Read a CSV file with `date`, `sid` and `weight` columns
+    ///
+    /// Dates are `YYYY-MM-DD`; a listed weight of 0 closes the position.
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let csv_error = |e: csv::Error| {
+            ZiplineError::DataError(format!("Failed to read {}: {}", path.display(), e))
+        };
+        let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+        let headers: Vec<String> = reader
+            .headers()
+            .map_err(csv_error)?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |name: &str| {
+            headers.iter().position(|h| h == name).ok_or_else(|| {
+                ZiplineError::InvalidData(format!("{} has no {} column", path.display(), name))
+            })
+        };
+        let (date_col, sid_col, weight_col) = (column("date")?, column("sid")?, column("weight")?);
+
+        let mut weights = Self::new();
+        for (line, record) in reader.records().enumerate() {
+            let record = record.map_err(csv_error)?;
+            let field = |col: usize| record.get(col).unwrap_or("").trim();
+            let bad = |name: &str, value: &str| {
+                ZiplineError::InvalidData(format!(
+                    "Bad {} '{}' on line {} of {}",
+                    name,
+                    value,
+                    line + 2,
+                    path.display()
+                ))
+            };
+
+            let date = NaiveDate::parse_from_str(field(date_col), "%Y-%m-%d")
+                .map_err(|_| bad("date", field(date_col)))?;
+            let sid: AssetId = field(sid_col)
+                .parse()
+                .map_err(|_| bad("sid", field(sid_col)))?;
+            let weight: f64 = field(weight_col)
+                .parse()
+                .ok()
+                .filter(|w: &f64| w.is_finite())
+                .ok_or_else(|| bad("weight", field(weight_col)))?;
+            weights.insert(date, sid, weight);
+        }
+        Ok(weights)
+    }
+
+    /// Number of signal dates
+    pub fn len(&self) -> usize {
+        self.by_date.len()
+    }
+
+    /// Check if there are no signals
+    pub fn is_empty(&self) -> bool {
+        self.by_date.is_empty()
+    }
+
+    /// Latest signal date on or before `date`, with its weights
+    pub fn latest(&self, date: NaiveDate) -> Option<(NaiveDate, &HashMap<AssetId, f64>)> {
+        self.by_date
+            .range(..=date)
+            .next_back()
+            .map(|(date, weights)| (*date, weights))
+    }
+}
+
+/// An [`Algorithm`] that only rebalances to precomputed target weights
+pub struct SignalReplay {
+    signals: TargetWeights,
+    assets: Vec<Asset>,
+    applied: Option<NaiveDate>,
+}
+
+impl SignalReplay {
+    /// Replay `signals`, resolving their sids among `assets`
+    pub fn new(signals: TargetWeights, assets: Vec<Asset>) -> Self {
+        Self {
+            signals,
+            assets,
+            applied: None,
+        }
+    }
+
+    /// Date of the signal the portfolio was last rebalanced to
+    pub fn applied(&self) -> Option<NaiveDate> {
+        self.applied
+    }
+}
+
+impl Algorithm for SignalReplay {
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        let today = context.timestamp.date_naive();
+        let (date, weights) = match self.signals.latest(today) {
+            Some(signal) if Some(signal.0) != self.applied => signal,
+            _ => return Ok(()),
+        };
+
+        // Only trade what has a price today; the rest waits for the next bar
+        let tradable = weights
+            .keys()
+            .chain(context.portfolio.positions.keys())
+            .all(|asset_id| {
+                self.assets
+                    .iter()
+                    .find(|asset| asset.id == *asset_id)
+                    .is_none_or(|asset| data.has_data(asset))
+            });
+        if !tradable {
+            return Ok(());
+        }
+
+        context.order_optimal_portfolio_with(weights.clone(), &[], &self.assets)?;
+        context.record("signal_assets", weights.len() as f64);
+        self.applied = Some(date);
+        Ok(())
+    }
+}
+
+impl SimulationEngine {
+    /// Run a backtest that trades precomputed target weights
+    ///
+    /// The data source's assets resolve the signals' sids.
+    pub fn run_signals(
+        &mut self,
+        signals: TargetWeights,
+        data_source: &dyn DataSource,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<PerformanceTracker> {
+        let mut replay = SignalReplay::new(signals, data_source.get_assets());
+        self.run(&mut replay, data_source, start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::NYSECalendar;
+    use crate::data::InMemoryDataSource;
+    use crate::types::Bar;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_replay_trades_target_weights() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("weights.csv");
+        std::fs::write(
+            &path,
+            "date,sid,weight\n2024-01-02,1,0.5\n2024-01-02,2,0.25\n2024-01-04,2,0.5\n",
+        )
+        .unwrap();
+        let signals = TargetWeights::from_csv(&path).unwrap();
+        assert_eq!(signals.len(), 2);
+        assert_eq!(
+            signals
+                .latest(NaiveDate::from_ymd_opt(2024, 1, 3).unwrap())
+                .unwrap()
+                .1[&1],
+            0.5
+        );
+
+        std::fs::write(&path, "date,sid,weight\n2024-01-02,x,0.5\n").unwrap();
+        assert!(TargetWeights::from_csv(&path).is_err());
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        for (id, price) in [(1, 100.0), (2, 50.0)] {
+            source.add_asset(Asset::equity(
+                id,
+                format!("A{}", id),
+                "NYSE".to_string(),
+                listed,
+            ));
+            for day in 0..4 {
+                let dt = start + Duration::days(day);
+                source.add_bar(id, Bar::new(dt, price, price, price, price, 1e9));
+            }
+        }
+        source.set_date_range(start, start + Duration::days(3));
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let results = engine
+            .run_signals(signals, &source, start, start + Duration::days(3))
+            .unwrap();
+
+        // Rebalanced on the 2nd, then on the 4th out of asset 1 into asset 2
+        let buys: Vec<(u64, f64)> = results
+            .transactions
+            .iter()
+            .map(|t| (t.asset_id, t.amount))
+            .collect();
+        let capital = engine.config.starting_cash;
+        assert_eq!(
+            buys[0..2],
+            [(1, capital * 0.5 / 100.0), (2, capital * 0.25 / 50.0)]
+        );
+        assert_eq!(results.transactions.len(), 4);
+        assert_eq!(
+            results.positions_at(start + Duration::days(3)).get(&1),
+            None
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Bar-by-bar replay of a backtest for debugging
+//!
+//! A [`Stepper`] drives the same event loop as [`SimulationEngine::run`], but
+//! one bar at a time, so the context, pending orders and recorded values can be
+//! inspected between bars. [`Stepper::repl`] wraps it in a small command
+//! interpreter for interactive use.
+
+use super::{SimulationEngine, WarmUp};
+use crate::algorithm::{Algorithm, Context};
+use crate::data::{BarData, DataSource};
+use crate::error::Result;
+use crate::finance::Portfolio;
+use crate::order::Order;
+use crate::performance::PerformanceTracker;
+use crate::types::{Bar, Timestamp};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use hashbrown::HashMap;
+use std::io::{BufRead, Write};
+
+/// Saved state of a run as of one bar
+///
+/// Written with [`crate::serialization::save`] so a run can be inspected or
+/// compared after the process exits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Timestamp of the last processed bar
+    pub timestamp: Option<Timestamp>,
+    /// Bars processed so far
+    pub bars_processed: usize,
+    /// Portfolio after the bar
+    pub portfolio: Portfolio,
+    /// Orders still open after the bar
+    #[serde(default)]
+    pub pending_orders: Vec<Order>,
+    /// Variables recorded with `context.record`
+    #[serde(default)]
+    pub recorded_vars: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+    /// Performance recorded so far
+    pub performance: PerformanceTracker,
+}
+
+/// Steps a backtest one bar at a time
+pub struct Stepper<'a, A: Algorithm> {
+    engine: &'a mut SimulationEngine,
+    algorithm: &'a mut A,
+    data_source: &'a dyn DataSource,
+    context: Context,
+    bar_data: BarData,
+    /// History to load before the first bar, until it has been loaded
+    warm_up: Option<WarmUp>,
+    /// Candidate bar timestamps for the run
+    timestamps: Vec<Timestamp>,
+    /// Index of the next candidate timestamp to look at
+    cursor: usize,
+    /// Next bar with data, fetched ahead for peeking
+    next: Option<(Timestamp, Vec<(u64, Bar)>)>,
+    /// Timestamp of the last processed bar
+    current: Option<Timestamp>,
+    bars_processed: usize,
+}
+
+impl<'a, A: Algorithm> Stepper<'a, A> {
+    pub(super) fn new(
+        engine: &'a mut SimulationEngine,
+        algorithm: &'a mut A,
+        data_source: &'a dyn DataSource,
+        context: Context,
+        bar_data: BarData,
+        warm_up: WarmUp,
+        timestamps: Vec<Timestamp>,
+    ) -> Self {
+        Self {
+            engine,
+            algorithm,
+            data_source,
+            context,
+            bar_data,
+            warm_up: Some(warm_up),
+            timestamps,
+            cursor: 0,
+            next: None,
+            current: None,
+            bars_processed: 0,
+        }
+    }
+
+    /// Timestamp of the next bar with data, without processing it
+    pub fn peek(&mut self) -> Result<Option<Timestamp>> {
+        if let Some(warm_up) = self.warm_up.take() {
+            let loaded = self.engine.load_warm_up(warm_up, self.data_source)?;
+            self.engine.apply_warm_up(&mut self.bar_data, loaded);
+        }
+
+        while self.next.is_none() && self.cursor < self.timestamps.len() {
+            let timestamp = self.timestamps[self.cursor];
+            self.cursor += 1;
+
+            let bars = self.data_source.get_bars(timestamp)?;
+            if !bars.is_empty() {
+                self.next = Some((timestamp, bars));
+            }
+        }
+
+        Ok(self.next.as_ref().map(|(timestamp, _)| *timestamp))
+    }
+
+    /// Process the next bar, returning its timestamp, or `None` at the end of the run
+    pub fn step(&mut self) -> Result<Option<Timestamp>> {
+        self.peek()?;
+        let (timestamp, bars) = match self.next.take() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+
+        self.engine.process_bar(
+            &mut *self.algorithm,
+            &mut self.context,
+            &mut self.bar_data,
+            timestamp,
+            bars,
+        )?;
+        self.current = Some(timestamp);
+        self.bars_processed += 1;
+
+        Ok(Some(timestamp))
+    }
+
+    /// Process up to `n` bars, returning how many were processed
+    pub fn step_n(&mut self, n: usize) -> Result<usize> {
+        let mut stepped = 0;
+        while stepped < n && self.step()?.is_some() {
+            stepped += 1;
+        }
+        Ok(stepped)
+    }
+
+    /// Process every bar at or before `until`
+    pub fn run_until(&mut self, until: Timestamp) -> Result<usize> {
+        let mut stepped = 0;
+        while matches!(self.peek()?, Some(next) if next <= until) {
+            self.step()?;
+            stepped += 1;
+        }
+        Ok(stepped)
+    }
+
+    /// Process every bar before `date`, stopping ahead of its first bar
+    pub fn run_to_date(&mut self, date: NaiveDate) -> Result<usize> {
+        let mut stepped = 0;
+        while matches!(self.peek()?, Some(next) if next.date_naive() < date) {
+            self.step()?;
+            stepped += 1;
+        }
+        Ok(stepped)
+    }
+
+    /// Process bars until one changes cash, positions or pending orders
+    ///
+    /// Returns the timestamp of that bar, or `None` if the run ended first.
+    pub fn run_until_change(&mut self) -> Result<Option<Timestamp>> {
+        let before = self.context.state_fingerprint();
+        while let Some(timestamp) = self.step()? {
+            if self.context.state_fingerprint() != before {
+                return Ok(Some(timestamp));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether every bar has been processed
+    pub fn is_finished(&mut self) -> Result<bool> {
+        Ok(self.peek()?.is_none())
+    }
+
+    /// Timestamp of the last processed bar
+    pub fn current_timestamp(&self) -> Option<Timestamp> {
+        self.current
+    }
+
+    /// Number of bars processed so far
+    pub fn bars_processed(&self) -> usize {
+        self.bars_processed
+    }
+
+    /// Algorithm context as of the last processed bar
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn portfolio(&self) -> &Portfolio {
+        &self.context.portfolio
+    }
+
+    /// Orders still open after the last processed bar
+    pub fn pending_orders(&self) -> &[Order] {
+        &self.context.pending_orders
+    }
+
+    pub fn bar_data(&self) -> &BarData {
+        &self.bar_data
+    }
+
+    /// Latest value of a variable recorded with `context.record`
+    ///
+    /// Factor values of interest can be recorded from `handle_data` and
+    /// inspected here bar by bar.
+    pub fn recorded(&self, name: &str) -> Option<f64> {
+        self.context
+            .get_recorded(name)
+            .and_then(|values| values.last())
+            .map(|(_, value)| *value)
+    }
+
+    /// Performance recorded so far
+    pub fn performance(&self) -> &PerformanceTracker {
+        &self.engine.performance
+    }
+
+    /// Snapshot of the run as of the last processed bar
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            timestamp: self.current,
+            bars_processed: self.bars_processed,
+            portfolio: self.context.portfolio.clone(),
+            pending_orders: self.context.pending_orders.clone(),
+            recorded_vars: self.context.recorded_vars.clone(),
+            performance: self.engine.performance.clone(),
+        }
+    }
+
+    /// Process the remaining bars and complete the run
+    pub fn finish(mut self) -> Result<PerformanceTracker> {
+        while self.step()?.is_some() {}
+        self.engine.finish_run(&mut *self.algorithm, &mut self.context)
+    }
+
+    /// Drive the stepper from text commands
+    ///
+    /// Reads commands from `input` until `quit`, `continue` or end of input,
+    /// writing results to `output`. Type `help` for the command list. The run
+    /// is not finished on exit; call [`Stepper::finish`] afterwards.
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let arg = words.next();
+
+            match (command, arg) {
+                ("", _) => {}
+                ("quit" | "q", _) => return Ok(()),
+                ("help" | "h", _) => writeln!(output, "{}", REPL_HELP)?,
+                ("step" | "s", n) => {
+                    let n = match n.map(str::parse::<usize>) {
+                        None => 1,
+                        Some(Ok(n)) => n,
+                        Some(Err(_)) => {
+                            writeln!(output, "usage: step [N]")?;
+                            continue;
+                        }
+                    };
+                    let stepped = self.step_n(n)?;
+                    self.write_position(&mut output, stepped)?;
+                }
+                ("date", Some(date)) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                    Ok(date) => {
+                        let stepped = self.run_to_date(date)?;
+                        self.write_position(&mut output, stepped)?;
+                    }
+                    Err(_) => writeln!(output, "usage: date YYYY-MM-DD")?,
+                },
+                ("until", Some(until)) => match DateTime::parse_from_rfc3339(until) {
+                    Ok(until) => {
+                        let stepped = self.run_until(until.with_timezone(&Utc))?;
+                        self.write_position(&mut output, stepped)?;
+                    }
+                    Err(_) => writeln!(output, "usage: until 2024-01-02T15:30:00Z")?,
+                },
+                ("change" | "c", _) => match self.run_until_change()? {
+                    Some(timestamp) => writeln!(output, "state changed at {}", timestamp)?,
+                    None => writeln!(output, "end of run")?,
+                },
+                ("continue", _) => {
+                    while self.step()?.is_some() {}
+                    writeln!(output, "end of run after {} bars", self.bars_processed)?;
+                    return Ok(());
+                }
+                ("portfolio" | "p", _) => self.write_portfolio(&mut output)?,
+                ("orders" | "o", _) => self.write_orders(&mut output)?,
+                ("var" | "v", Some(name)) => match self.recorded(name) {
+                    Some(value) => writeln!(output, "{} = {}", name, value)?,
+                    None => writeln!(output, "{} has not been recorded", name)?,
+                },
+                ("vars", _) => {
+                    let mut names: Vec<_> = self.context.recorded_vars.keys().collect();
+                    names.sort();
+                    for name in names {
+                        writeln!(output, "{}", name)?;
+                    }
+                }
+                _ => writeln!(output, "unknown command '{}'; type 'help'", line.trim())?,
+            }
+
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_position<W: Write>(&mut self, output: &mut W, stepped: usize) -> Result<()> {
+        let current = match self.current {
+            Some(current) => current,
+            None => {
+                writeln!(output, "no bars processed")?;
+                return Ok(());
+            }
+        };
+
+        let end = if self.peek()?.is_none() {
+            " (end of run)"
+        } else {
+            ""
+        };
+        writeln!(output, "stepped {} bar(s), at {}{}", stepped, current, end)?;
+        Ok(())
+    }
+
+    fn write_portfolio<W: Write>(&self, output: &mut W) -> Result<()> {
+        let portfolio = &self.context.portfolio;
+        writeln!(output, "cash:  {:.2}", portfolio.cash)?;
+        writeln!(output, "value: {:.2}", portfolio.portfolio_value)?;
+
+        let mut positions: Vec<_> = portfolio.positions.values().collect();
+        positions.sort_by_key(|p| p.asset.id);
+        for p in positions {
+            writeln!(
+                output,
+                "  {:<8} {:>12.2} @ {:.2} (last {:.2})",
+                p.asset.symbol,
+                p.quantity,
+                if p.quantity != 0.0 {
+                    p.cost_basis / p.quantity
+                } else {
+                    0.0
+                },
+                p.last_price
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_orders<W: Write>(&self, output: &mut W) -> Result<()> {
+        if self.context.pending_orders.is_empty() {
+            writeln!(output, "no pending orders")?;
+        }
+        for order in &self.context.pending_orders {
+            writeln!(
+                output,
+                "  {} {:?} {:?} {} {} (filled {})",
+                order.id,
+                order.side,
+                order.order_type,
+                order.quantity,
+                order.asset.symbol,
+                order.filled
+            )?;
+        }
+        Ok(())
+    }
+}
+
+const REPL_HELP: &str = "\
+commands:
+  step [N]        process the next N bars (default 1)
+  date YYYY-MM-DD run up to the first bar of a date
+  until DATETIME  run through an RFC 3339 timestamp
+  change          run until cash, positions or orders change
+  continue        run to the end and leave
+  portfolio       show cash, value and positions
+  orders          show pending orders
+  var NAME        show the latest recorded value of NAME
+  vars            list recorded variables
+  quit            leave without running further";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::BuyAndHold;
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::InMemoryDataSource;
+    use chrono::{Duration, TimeZone};
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    fn setup() -> (InMemoryDataSource, Asset, Timestamp, Timestamp) {
+        let mut data_source = InMemoryDataSource::new();
+        let start_date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        data_source.add_asset(asset.clone());
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 15, 0, 0).unwrap();
+        let end = start + Duration::days(4);
+        for i in 0..5 {
+            let price = 100.0 + i as f64;
+            let timestamp = start + Duration::days(i);
+            let bar = Bar::new(
+                timestamp,
+                price,
+                price + 2.0,
+                price - 1.0,
+                price + 1.0,
+                10000.0,
+            );
+            data_source.add_bar(1, bar);
+        }
+        data_source.set_date_range(start, end);
+
+        (data_source, asset, start, end)
+    }
+
+    #[test]
+    fn test_step_and_finish() {
+        let (data_source, asset, start, end) = setup();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = BuyAndHold::new(asset);
+
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+        assert_eq!(stepper.peek().unwrap(), Some(start));
+        assert_eq!(stepper.bars_processed(), 0);
+
+        assert_eq!(stepper.step().unwrap(), Some(start));
+        assert_eq!(stepper.current_timestamp(), Some(start));
+        assert_eq!(stepper.step_n(2).unwrap(), 2);
+        assert_eq!(stepper.current_timestamp(), Some(start + Duration::days(2)));
+        assert!(!stepper.is_finished().unwrap());
+
+        let performance = stepper.finish().unwrap();
+        assert_eq!(performance.values.len(), 5);
+    }
+
+    #[test]
+    fn test_matches_run() {
+        let (data_source, asset, start, end) = setup();
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = BuyAndHold::new(asset.clone());
+        let run = engine
+            .run(&mut algorithm, &data_source, start, end)
+            .unwrap();
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = BuyAndHold::new(asset);
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+        while stepper.step().unwrap().is_some() {}
+        let stepped = stepper.finish().unwrap();
+
+        assert_eq!(run.values, stepped.values);
+    }
+
+    #[test]
+    fn test_run_to_date_and_until() {
+        let (data_source, asset, start, end) = setup();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = BuyAndHold::new(asset);
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+
+        let third_day = (start + Duration::days(2)).date_naive();
+        assert_eq!(stepper.run_to_date(third_day).unwrap(), 2);
+        assert_eq!(stepper.peek().unwrap(), Some(start + Duration::days(2)));
+
+        assert_eq!(stepper.run_until(start + Duration::days(3)).unwrap(), 2);
+        assert_eq!(stepper.bars_processed(), 4);
+    }
+
+    #[test]
+    fn test_run_until_change() {
+        let (data_source, asset, start, end) = setup();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = BuyAndHold::new(asset);
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+
+        // The first bar places the buy order
+        assert_eq!(stepper.run_until_change().unwrap(), Some(start));
+        assert!(!stepper.pending_orders().is_empty() || !stepper.portfolio().positions.is_empty());
+    }
+
+    #[test]
+    fn test_repl() {
+        let (data_source, asset, start, end) = setup();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let mut algorithm = BuyAndHold::new(asset);
+        let mut stepper = engine.stepper(&mut algorithm, &data_source, start, end).unwrap();
+
+        let input = Cursor::new("step 2\nportfolio\nbogus\nstep x\nquit\nstep\n");
+        let mut output = Vec::new();
+        stepper.repl(input, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(stepper.bars_processed(), 2);
+        assert!(output.contains("stepped 2 bar(s)"));
+        assert!(output.contains("cash:"));
+        assert!(output.contains("unknown command 'bogus'"));
+        assert!(output.contains("usage: step [N]"));
+
+        let mut output = Vec::new();
+        stepper
+            .repl(Cursor::new("continue\n"), &mut output)
+            .unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("end of run after 5 bars"));
+        assert!(stepper.is_finished().unwrap());
+    }
+}
@@ -98,6 +98,11 @@ pub enum ZiplineError {
         order_id: uuid::Uuid,
     },
 
+    #[error("Order {order_id} has already filled")]
+    OrderAlreadyFilled {
+        order_id: uuid::Uuid,
+    },
+
     #[error("Cannot place order after session end. Session ended at {session_end}, order attempted at {attempted_at}")]
     OrderAfterSessionEnd {
         session_end: DateTime<Utc>,
@@ -120,6 +125,9 @@ pub enum ZiplineError {
         order_volume: f64,
     },
 
+    #[error("{0}")]
+    LedgerDivergence(Box<crate::finance::ledger::LedgerDivergence>),
+
     // ========== Commission/Slippage Errors ==========
     #[error("Unsupported slippage model: {0}")]
     UnsupportedSlippageModel(String),
@@ -162,6 +170,9 @@ pub enum ZiplineError {
     #[error("Account control violation: {0}")]
     AccountControlViolation(String),
 
+    #[error("Trading halted: {reason}")]
+    TradingHalted { reason: String },
+
     // P0 Trading Control Errors
     #[error("Max position size exceeded for asset {asset} ({symbol}): attempted order {attempted_order} shares, max shares: {max_shares:?}, max notional: {max_notional:?}")]
     MaxPositionSizeExceeded {
@@ -179,6 +190,14 @@ pub enum ZiplineError {
         date: DateTime<Utc>,
     },
 
+    #[error("Order loop suspected: {count} orders for asset {asset} at {date}, maximum per bar: {max_count}")]
+    MaxOrdersPerBarExceeded {
+        asset: u64,
+        count: usize,
+        max_count: usize,
+        date: DateTime<Utc>,
+    },
+
     #[error("Max order size exceeded for asset {asset}: order size {order_size}, max allowed: {max_size}")]
     MaxOrderSizeExceeded {
         asset: u64,
@@ -244,6 +263,14 @@ pub enum ZiplineError {
     #[error("Unsupported datetime format: {0}")]
     UnsupportedDatetimeFormat(String),
 
+    #[error("Run from {start} to {end} overlaps the locked holdout period {holdout_start} to {holdout_end}")]
+    HoldoutLocked {
+        start: chrono::NaiveDate,
+        end: chrono::NaiveDate,
+        holdout_start: chrono::NaiveDate,
+        holdout_end: chrono::NaiveDate,
+    },
+
     // ========== Calendar Errors ==========
     #[error("Calendar error: {0}")]
     CalendarError(String),
@@ -327,6 +354,13 @@ pub enum ZiplineError {
     #[error("Incompatible domains: {0}")]
     IncompatibleDomains(String),
 
+    // ========== Plugin Errors ==========
+    #[error("Cannot load plugin {path}: {reason}")]
+    PluginLoad { path: String, reason: String },
+
+    #[error("Strategy sandbox error: {0}")]
+    SandboxError(String),
+
     // ========== Generic Errors ==========
     #[error("Parse error: {0}")]
     ParseError(String),
@@ -341,6 +375,13 @@ pub enum ZiplineError {
     #[error("Invalid data: {0}")]
     InvalidData(String),
 
+    #[error("Unsupported {kind} schema version {found}: this version reads up to {supported}")]
+    UnsupportedSchemaVersion {
+        kind: String,
+        found: u32,
+        supported: u32,
+    },
+
     #[error("Data not found: {0}")]
     DataNotFound(String),
 
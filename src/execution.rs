@@ -1,13 +1,29 @@
 //! Order execution and slippage models
 
-use crate::error::Result;
-use crate::order::{Order, OrderStatus, OrderType};
+use crate::asset::AssetType;
+use crate::error::{Result, ZiplineError};
+use crate::finance::MarketStatsService;
+use crate::order::{ExecutionOverride, Order, OrderStatus, OrderType};
+use crate::rng::SimulationRng;
 use crate::types::{Cash, Price, Timestamp};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Slippage model trait
 pub trait SlippageModel: Send + Sync {
     /// Calculate slippage for an order
+    ///
+    /// Stochastic models return the expected slippage; used for previews.
     fn calculate_slippage(&self, order: &Order, current_price: Price) -> Price;
+
+    /// Draw the slippage for a fill
+    ///
+    /// Stochastic models override this and take all their randomness from
+    /// `rng`, so runs with the same seed fill at the same prices.
+    fn sample_slippage(&self, order: &Order, current_price: Price, _rng: &mut SimulationRng) -> Price {
+        self.calculate_slippage(order, current_price)
+    }
 }
 
 /// No slippage model
@@ -64,6 +80,36 @@ impl SlippageModel for VolumeShareSlippage {
     }
 }
 
+/// Random slippage model: uniform between zero and a maximum, in basis points
+#[derive(Debug, Clone, Copy)]
+pub struct RandomSlippage {
+    pub max_bps: f64,
+}
+
+impl RandomSlippage {
+    pub fn new(max_bps: f64) -> Self {
+        Self { max_bps }
+    }
+
+    fn signed(order: &Order, slippage: Price) -> Price {
+        match order.side {
+            crate::order::OrderSide::Buy => slippage,
+            crate::order::OrderSide::Sell => -slippage,
+        }
+    }
+}
+
+impl SlippageModel for RandomSlippage {
+    fn calculate_slippage(&self, order: &Order, current_price: Price) -> Price {
+        Self::signed(order, current_price * self.max_bps / 2.0 / 10_000.0)
+    }
+
+    fn sample_slippage(&self, order: &Order, current_price: Price, rng: &mut SimulationRng) -> Price {
+        let bps = rng.gen::<f64>() * self.max_bps;
+        Self::signed(order, current_price * bps / 10_000.0)
+    }
+}
+
 /// Commission model trait
 pub trait CommissionModel: Send + Sync {
     /// Calculate commission for an order fill
@@ -116,17 +162,88 @@ impl CommissionModel for PerTradeCommission {
     }
 }
 
+/// Slippage from a [`crate::finance::slippage::SlippageModel`], such as one
+/// built by name from the [`ModelRegistry`](crate::finance::ModelRegistry)
+///
+/// Volume-driven models see the asset's average daily volume from the
+/// attached market statistics; without them, or before the statistics have
+/// any volume, they are given a volume of zero and fall back to their
+/// most conservative estimate.
+#[derive(Clone)]
+pub struct FinanceSlippage {
+    model: Arc<dyn crate::finance::slippage::SlippageModel>,
+    market_stats: Option<Arc<MarketStatsService>>,
+}
+
+impl FinanceSlippage {
+    pub fn new(model: Arc<dyn crate::finance::slippage::SlippageModel>) -> Self {
+        Self {
+            model,
+            market_stats: None,
+        }
+    }
+
+    /// Read volumes from the engine's market statistics
+    pub fn with_market_stats(mut self, stats: Arc<MarketStatsService>) -> Self {
+        self.market_stats = Some(stats);
+        self
+    }
+}
+
+impl SlippageModel for FinanceSlippage {
+    fn calculate_slippage(&self, order: &Order, current_price: Price) -> Price {
+        let volume = self
+            .market_stats
+            .as_ref()
+            .and_then(|stats| stats.average_daily_volume(order.asset.id))
+            .unwrap_or(0.0);
+        self.model.calculate_price(order, current_price, volume) - current_price
+    }
+}
+
+/// Commission charged by a [`crate::finance::commission::CommissionModel`],
+/// such as one built by name from the
+/// [`ModelRegistry`](crate::finance::ModelRegistry)
+#[derive(Clone)]
+pub struct FinanceCommission(pub Arc<dyn crate::finance::commission::CommissionModel>);
+
+impl CommissionModel for FinanceCommission {
+    fn calculate_commission(&self, order: &Order, fill_price: Price) -> Cash {
+        self.0.calculate(order, fill_price, order.filled)
+    }
+}
+
+/// Slippage and commission models used together
+struct ExecutionProfile {
+    slippage_model: Box<dyn SlippageModel>,
+    commission_model: Box<dyn CommissionModel>,
+}
+
 /// Simulated broker for backtesting
+///
+/// Each fill is costed with the models of the order's asset class when some
+/// were registered with [`with_asset_class`](Self::with_asset_class), else
+/// with the broker's default models. An order's [`ExecutionOverride`] takes
+/// precedence over both.
 pub struct SimulatedBroker {
     slippage_model: Box<dyn SlippageModel>,
     commission_model: Box<dyn CommissionModel>,
+    /// Profiles selectable per order with [`ExecutionOverride::Profile`]
+    profiles: HashMap<String, ExecutionProfile>,
+    /// Models for orders in assets of a given class
+    asset_classes: HashMap<AssetType, ExecutionProfile>,
 }
 
 impl std::fmt::Debug for SimulatedBroker {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut profiles: Vec<&String> = self.profiles.keys().collect();
+        profiles.sort();
+        let asset_classes: Vec<&AssetType> = self.asset_classes.keys().collect();
         f.debug_struct("SimulatedBroker")
             .field("slippage_model", &"<dyn SlippageModel>")
             .field("commission_model", &"<dyn CommissionModel>")
+            .field("profiles", &profiles)
+            .field("asset_classes", &asset_classes)
             .finish()
     }
 }
@@ -140,6 +257,8 @@ impl SimulatedBroker {
         Self {
             slippage_model,
             commission_model,
+            profiles: HashMap::new(),
+            asset_classes: HashMap::new(),
         }
     }
 
@@ -148,15 +267,145 @@ impl SimulatedBroker {
         Self::new(Box::new(NoSlippage), Box::new(NoCommission))
     }
 
+    /// Register an execution profile that orders can select by name
+    pub fn with_profile(
+        mut self,
+        name: &str,
+        slippage_model: Box<dyn SlippageModel>,
+        commission_model: Box<dyn CommissionModel>,
+    ) -> Self {
+        self.profiles.insert(
+            name.to_string(),
+            ExecutionProfile {
+                slippage_model,
+                commission_model,
+            },
+        );
+        self
+    }
+
+    /// Cost orders in assets of `asset_type` with their own models
+    pub fn with_asset_class(
+        mut self,
+        asset_type: AssetType,
+        slippage_model: Box<dyn SlippageModel>,
+        commission_model: Box<dyn CommissionModel>,
+    ) -> Self {
+        self.asset_classes.insert(
+            asset_type,
+            ExecutionProfile {
+                slippage_model,
+                commission_model,
+            },
+        );
+        self
+    }
+
+    /// Slippage model for an order without an override
+    fn slippage_model(&self, order: &Order) -> &dyn SlippageModel {
+        match self.asset_classes.get(&order.asset.asset_type) {
+            Some(profile) => profile.slippage_model.as_ref(),
+            None => self.slippage_model.as_ref(),
+        }
+    }
+
+    /// Commission model for an order without an override
+    fn commission_model(&self, order: &Order) -> &dyn CommissionModel {
+        match self.asset_classes.get(&order.asset.asset_type) {
+            Some(profile) => profile.commission_model.as_ref(),
+            None => self.commission_model.as_ref(),
+        }
+    }
+
+    /// Profile named by an order's override
+    fn profile(&self, name: &str) -> Result<&ExecutionProfile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| ZiplineError::ExecutionError(format!("Unknown execution profile '{}'", name)))
+    }
+
+    /// Slippage for an order, honouring its execution override
+    ///
+    /// Drawn from `rng` when given, otherwise the models' expected slippage.
+    fn slippage(&self, order: &Order, current_price: Price, rng: Option<&mut SimulationRng>) -> Result<Price> {
+        let model = match &order.execution {
+            None => self.slippage_model(order),
+            Some(ExecutionOverride::Midpoint) => return Ok(0.0),
+            Some(ExecutionOverride::Costs { slippage_bps, .. }) => {
+                let slippage = current_price * slippage_bps / 10_000.0;
+                return Ok(match order.side {
+                    crate::order::OrderSide::Buy => slippage,
+                    crate::order::OrderSide::Sell => -slippage,
+                });
+            }
+            Some(ExecutionOverride::Profile(name)) => self.profile(name)?.slippage_model.as_ref(),
+        };
+        Ok(match rng {
+            Some(rng) => model.sample_slippage(order, current_price, rng),
+            None => model.calculate_slippage(order, current_price),
+        })
+    }
+
+    /// Commission for an order's fills, honouring its execution override
+    fn commission(&self, order: &Order, fill_price: Price) -> Result<Cash> {
+        Ok(match &order.execution {
+            None => self.commission_model(order).calculate_commission(order, fill_price),
+            Some(ExecutionOverride::Midpoint) => 0.0,
+            Some(ExecutionOverride::Costs {
+                commission_per_share, ..
+            }) => order.filled * commission_per_share,
+            Some(ExecutionOverride::Profile(name)) => self
+                .profile(name)?
+                .commission_model
+                .calculate_commission(order, fill_price),
+        })
+    }
+
+    /// Estimate the fill price and commission for an order without executing it
+    ///
+    /// Assumes the order's remaining quantity fills in full at `current_price`
+    /// plus slippage. An override naming an unknown profile is estimated with
+    /// the broker's own models.
+    pub fn estimate_fill(&self, order: &Order, current_price: Price) -> (Price, Cash) {
+        let slippage = self
+            .slippage(order, current_price, None)
+            .unwrap_or_else(|_| self.slippage_model(order).calculate_slippage(order, current_price));
+        let execution_price = current_price + slippage;
+
+        let mut filled = order.clone();
+        filled.filled = order.quantity;
+        let commission = self
+            .commission(&filled, execution_price)
+            .unwrap_or_else(|_| self.commission_model(&filled).calculate_commission(&filled, execution_price));
+
+        (execution_price, commission)
+    }
+
     /// Execute an order at current price
+    ///
+    /// An order carrying an [`ExecutionOverride`] is filled under it rather
+    /// than the broker's models. Stochastic models draw from a freshly seeded
+    /// generator; use [`execute_order_with_rng`](Self::execute_order_with_rng)
+    /// for reproducible fills.
     pub fn execute_order(
         &self,
         order: &mut Order,
         current_price: Price,
         timestamp: Timestamp,
+    ) -> Result<ExecutionResult> {
+        self.execute_order_with_rng(order, current_price, timestamp, &mut SimulationRng::from_entropy())
+    }
+
+    /// Execute an order at current price, drawing any randomness from `rng`
+    pub fn execute_order_with_rng(
+        &self,
+        order: &mut Order,
+        current_price: Price,
+        timestamp: Timestamp,
+        rng: &mut SimulationRng,
     ) -> Result<ExecutionResult> {
         // Calculate slippage
-        let slippage = self.slippage_model.calculate_slippage(order, current_price);
+        let slippage = self.slippage(order, current_price, Some(rng))?;
         let execution_price = current_price + slippage;
 
         // Check if order can be filled based on type
@@ -202,6 +451,8 @@ impl SimulatedBroker {
                     false
                 }
             }
+            // Becomes a market order when the price crosses the trailed stop
+            OrderType::TrailingStop { .. } => order.update_trail(current_price),
         };
 
         if !can_fill {
@@ -218,7 +469,7 @@ impl SimulatedBroker {
         order.fill(fill_quantity, timestamp);
 
         // Calculate commission
-        let commission = self.commission_model.calculate_commission(order, execution_price);
+        let commission = self.commission(order, execution_price)?;
 
         Ok(ExecutionResult::Filled {
             price: execution_price,
@@ -299,4 +550,63 @@ mod tests {
             ExecutionResult::NotFilled => panic!("Order should have been filled"),
         }
     }
+
+    #[test]
+    fn test_execution_override() {
+        let broker = SimulatedBroker::new(Box::new(FixedSlippage::new(0.10)), Box::new(PerShareCommission::new(0.01)))
+            .with_profile("negotiated", Box::new(NoSlippage), Box::new(PerTradeCommission::new(5.0)));
+        let start_date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let fill = |execution: Option<ExecutionOverride>| {
+            let mut order = Order::market(asset.clone(), OrderSide::Sell, 100.0, Utc::now());
+            order.execution = execution;
+            match broker.execute_order(&mut order, 100.0, Utc::now()) {
+                Ok(ExecutionResult::Filled { price, commission, .. }) => Ok((price, commission)),
+                Ok(ExecutionResult::NotFilled) => panic!("Order should have been filled"),
+                Err(e) => Err(e),
+            }
+        };
+
+        assert_eq!(fill(None).unwrap(), (99.9, 1.0));
+        assert_eq!(fill(Some(ExecutionOverride::Midpoint)).unwrap(), (100.0, 0.0));
+        let costs = ExecutionOverride::Costs {
+            slippage_bps: 5.0,
+            commission_per_share: 0.002,
+        };
+        assert_eq!(fill(Some(costs)).unwrap(), (99.95, 0.2));
+        assert_eq!(fill(Some(ExecutionOverride::Profile("negotiated".to_string()))).unwrap(), (100.0, 5.0));
+        assert!(matches!(
+            fill(Some(ExecutionOverride::Profile("dark".to_string()))),
+            Err(ZiplineError::ExecutionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_trailing_stop_fills_through_gaps() {
+        use crate::order::Trail;
+
+        let broker = SimulatedBroker::default_broker();
+        let start_date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        // Feed prices in turn; the price the order filled at, if any
+        let run = |mut order: Order, prices: &[Price]| {
+            prices.iter().find_map(|&price| match broker.execute_order(&mut order, price, Utc::now()).unwrap() {
+                ExecutionResult::Filled { price, .. } => Some(price),
+                ExecutionResult::NotFilled => None,
+            })
+        };
+
+        // Gap up raises the stop to 105; the gap down through it fills at the open
+        // price, not the stop
+        let sell = Order::trailing_stop(asset.clone(), OrderSide::Sell, 100.0, Trail::Amount(5.0), Utc::now());
+        assert_eq!(run(sell.clone(), &[100.0, 110.0, 106.0, 98.0]), Some(98.0));
+        // A gap down on the bar after placement fills against the first price's stop
+        assert_eq!(run(sell.clone(), &[100.0, 80.0]), Some(80.0));
+        // Prices that never retrace by the trail leave it working
+        assert_eq!(run(sell, &[100.0, 104.0, 108.0, 103.5]), None);
+
+        // Buy stop 10% above the low, gapping up through 88
+        let buy = Order::trailing_stop(asset, OrderSide::Buy, 100.0, Trail::Percent(0.10), Utc::now());
+        assert_eq!(run(buy, &[100.0, 80.0, 120.0]), Some(120.0));
+    }
 }
@@ -0,0 +1,5 @@
+//! Signal filters for estimating quantities that drift over time
+
+pub mod kalman;
+
+pub use kalman::{KalmanFilter1D, KalmanRegression, KalmanStep};
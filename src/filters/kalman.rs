@@ -0,0 +1,318 @@
+//! Kalman filters and smoothers for slowly drifting quantities
+//!
+//! [`KalmanFilter1D`] tracks a single level observed with noise, such as a
+//! spread's mean. [`KalmanRegression`] tracks the intercept and slope of
+//! `y = alpha + beta * x` as both drift, which makes it a dynamic hedge ratio
+//! for pairs: its one-step forecast error is the spread, and the error's
+//! forecast standard deviation scales it into a z-score.
+//!
+//! Both states follow random walks. Filtering uses only observations up to
+//! each step and is safe to trade on; smoothing (Rauch–Tung–Striebel) also
+//! uses later observations and is for research.
+//!
+//! ```ignore
+//! let mut kalman = KalmanRegression::new(1e-4, 1e-3);
+//! let step = kalman.update(x_price, y_price);
+//! if step.zscore() > 2.0 {
+//!     // Short y, long step.hedge_ratio.beta units of x
+//! }
+//! ```
+
+use crate::statistics::HedgeRatio;
+
+type Matrix = [[f64; 2]; 2];
+
+/// Kalman filter of a level following a random walk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KalmanFilter1D {
+    estimate: f64,
+    variance: f64,
+    process_variance: f64,
+    observation_variance: f64,
+}
+
+impl KalmanFilter1D {
+    /// Start at `initial` with uncertainty `initial_variance`; the level moves
+    /// by `process_variance` per step and is observed with
+    /// `observation_variance` noise
+    pub fn new(
+        initial: f64,
+        initial_variance: f64,
+        process_variance: f64,
+        observation_variance: f64,
+    ) -> Self {
+        Self {
+            estimate: initial,
+            variance: initial_variance,
+            process_variance,
+            observation_variance,
+        }
+    }
+
+    /// Filtered level
+    pub fn estimate(&self) -> f64 {
+        self.estimate
+    }
+
+    /// Variance of the filtered level
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    /// Incorporate an observation and return the new estimate; a NaN
+    /// observation only advances the level's uncertainty
+    pub fn update(&mut self, observation: f64) -> f64 {
+        self.variance += self.process_variance;
+        if !observation.is_nan() {
+            let gain = self.variance / (self.variance + self.observation_variance);
+            self.estimate += gain * (observation - self.estimate);
+            self.variance *= 1.0 - gain;
+        }
+        self.estimate
+    }
+
+    /// Filtered level after each observation, advancing this filter
+    pub fn filter(&mut self, observations: &[f64]) -> Vec<f64> {
+        observations.iter().map(|&observation| self.update(observation)).collect()
+    }
+
+    /// Smoothed level at each observation, using the whole series
+    pub fn smooth(&self, observations: &[f64]) -> Vec<f64> {
+        let mut filter = *self;
+        let mut steps = Vec::with_capacity(observations.len());
+        for &observation in observations {
+            let predicted = filter.variance + filter.process_variance;
+            filter.update(observation);
+            steps.push((filter.estimate, filter.variance, predicted));
+        }
+
+        let mut smoothed: Vec<f64> = steps.iter().map(|(estimate, _, _)| *estimate).collect();
+        for t in (0..steps.len().saturating_sub(1)).rev() {
+            // The level predicted for t + 1 is the filtered level at t
+            let (estimate, variance, _) = steps[t];
+            let (_, _, next_predicted) = steps[t + 1];
+            let gain = variance / next_predicted;
+            smoothed[t] = estimate + gain * (smoothed[t + 1] - estimate);
+        }
+        smoothed
+    }
+}
+
+/// One step of a [`KalmanRegression`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KalmanStep {
+    /// Intercept and slope after the observation
+    pub hedge_ratio: HedgeRatio,
+    /// `y` less its forecast from the coefficients before the observation
+    pub forecast_error: f64,
+    /// Standard deviation of the forecast error
+    pub forecast_std: f64,
+}
+
+impl KalmanStep {
+    /// Forecast error in standard deviations
+    pub fn zscore(&self) -> f64 {
+        self.forecast_error / self.forecast_std
+    }
+}
+
+/// Regression of `y` on `x` whose intercept and slope follow random walks
+///
+/// `delta` sets how fast the coefficients may drift: each step they move with
+/// variance `delta / (1 - delta)`. Smaller values give steadier estimates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KalmanRegression {
+    state: [f64; 2],
+    covariance: Matrix,
+    drift: f64,
+    observation_variance: f64,
+}
+
+impl KalmanRegression {
+    /// Start at a zero intercept and slope with unit uncertainty
+    pub fn new(delta: f64, observation_variance: f64) -> Self {
+        Self {
+            state: [0.0, 0.0],
+            covariance: [[1.0, 0.0], [0.0, 1.0]],
+            drift: delta / (1.0 - delta),
+            observation_variance,
+        }
+    }
+
+    /// Start from a known intercept and slope, such as a static fit
+    pub fn with_initial(mut self, initial: HedgeRatio) -> Self {
+        self.state = [initial.intercept, initial.beta];
+        self
+    }
+
+    /// Current intercept and slope
+    pub fn hedge_ratio(&self) -> HedgeRatio {
+        HedgeRatio {
+            intercept: self.state[0],
+            beta: self.state[1],
+        }
+    }
+
+    /// Incorporate one observation of `x` and `y`
+    pub fn update(&mut self, x: f64, y: f64) -> KalmanStep {
+        self.step(x, y).0
+    }
+
+    /// Filtered coefficients after each observation, advancing this filter
+    pub fn filter(&mut self, y: &[f64], x: &[f64]) -> Vec<KalmanStep> {
+        y.iter().zip(x).map(|(&y, &x)| self.update(x, y)).collect()
+    }
+
+    /// Smoothed coefficients at each observation, using the whole series
+    pub fn smooth(&self, y: &[f64], x: &[f64]) -> Vec<HedgeRatio> {
+        let mut filter = *self;
+        let steps: Vec<(([f64; 2], Matrix), Matrix)> = y
+            .iter()
+            .zip(x)
+            .map(|(&y, &x)| {
+                let (_, predicted) = filter.step(x, y);
+                ((filter.state, filter.covariance), predicted)
+            })
+            .collect();
+
+        let mut smoothed: Vec<[f64; 2]> = steps.iter().map(|((state, _), _)| *state).collect();
+        for t in (0..steps.len().saturating_sub(1)).rev() {
+            let ((state, covariance), _) = steps[t];
+            let (_, next_predicted) = steps[t + 1];
+            let Some(inverse) = invert(next_predicted) else {
+                continue;
+            };
+            let gain = multiply(covariance, inverse);
+            let correction = [smoothed[t + 1][0] - state[0], smoothed[t + 1][1] - state[1]];
+            smoothed[t] = [
+                state[0] + gain[0][0] * correction[0] + gain[0][1] * correction[1],
+                state[1] + gain[1][0] * correction[0] + gain[1][1] * correction[1],
+            ];
+        }
+        smoothed
+            .into_iter()
+            .map(|[intercept, beta]| HedgeRatio { intercept, beta })
+            .collect()
+    }
+
+    /// Predict and update on one observation, returning the step and the
+    /// predicted state covariance
+    fn step(&mut self, x: f64, y: f64) -> (KalmanStep, Matrix) {
+        let mut predicted = self.covariance;
+        predicted[0][0] += self.drift;
+        predicted[1][1] += self.drift;
+
+        let h = [1.0, x];
+        let forecast = self.state[0] + self.state[1] * x;
+        let ph = [
+            predicted[0][0] * h[0] + predicted[0][1] * h[1],
+            predicted[1][0] * h[0] + predicted[1][1] * h[1],
+        ];
+        let forecast_variance = h[0] * ph[0] + h[1] * ph[1] + self.observation_variance;
+        let forecast_error = y - forecast;
+
+        self.covariance = predicted;
+        if !forecast_error.is_nan() {
+            let gain = [ph[0] / forecast_variance, ph[1] / forecast_variance];
+            self.state[0] += gain[0] * forecast_error;
+            self.state[1] += gain[1] * forecast_error;
+            for (i, row) in self.covariance.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value = predicted[i][j] - gain[i] * ph[j];
+                }
+            }
+        }
+
+        let step = KalmanStep {
+            hedge_ratio: self.hedge_ratio(),
+            forecast_error,
+            forecast_std: forecast_variance.sqrt(),
+        };
+        (step, predicted)
+    }
+}
+
+fn multiply(a: Matrix, b: Matrix) -> Matrix {
+    [
+        [a[0][0] * b[0][0] + a[0][1] * b[1][0], a[0][0] * b[0][1] + a[0][1] * b[1][1]],
+        [a[1][0] * b[0][0] + a[1][1] * b[1][0], a[1][0] * b[0][1] + a[1][1] * b[1][1]],
+    ]
+}
+
+fn invert(m: Matrix) -> Option<Matrix> {
+    let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+    (det.abs() > f64::EPSILON).then(|| [[m[1][1] / det, -m[0][1] / det], [-m[1][0] / det, m[0][0] / det]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_local_level() {
+        let observations = [1.0, 1.2, f64::NAN, 0.9, 1.1];
+        let mut filter = KalmanFilter1D::new(0.0, 1.0, 0.01, 0.1);
+        let filtered = filter.filter(&observations);
+        assert!(filtered[1] > filtered[0] && filtered[2] == filtered[1]);
+        assert!(filter.variance() < 0.1);
+
+        let smoothed = KalmanFilter1D::new(0.0, 1.0, 0.01, 0.1).smooth(&observations);
+        assert_eq!(smoothed[4], filtered[4]);
+        // Later observations pull the first estimate towards the level
+        assert!((smoothed[0] - 1.0).abs() < (filtered[0] - 1.0).abs());
+    }
+
+    #[test]
+    fn test_dynamic_hedge_ratio_pairs_trade() {
+        // x wanders; y holds a hedge ratio drifting from 1 to 1.5 plus a
+        // mean-reverting spread
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let n = 1000;
+        let mut level = 50.0;
+        let mut spread = 0.0;
+        let beta = |t: usize| 1.0 + 0.5 * t as f64 / n as f64;
+        let (mut x, mut y) = (Vec::with_capacity(n), Vec::with_capacity(n));
+        for t in 0..n {
+            level += rng.gen::<f64>() - 0.5;
+            spread = 0.8 * spread + (rng.gen::<f64>() - 0.5) * 2.0;
+            x.push(level);
+            y.push(3.0 + beta(t) * level + spread);
+        }
+
+        let mut kalman = KalmanRegression::new(1e-6, 0.2);
+        let steps = kalman.filter(&y, &x);
+        assert!((kalman.hedge_ratio().beta - beta(n - 1)).abs() < 0.05);
+
+        // Smoothing sees the whole path, so it tracks the drift more closely
+        let smoothed = KalmanRegression::new(1e-6, 0.2).smooth(&y, &x);
+        let error = |estimates: &mut dyn Iterator<Item = f64>| -> f64 {
+            estimates.enumerate().skip(100).map(|(t, b)| (b - beta(t)).abs()).sum::<f64>()
+        };
+        let filtered_error = error(&mut steps.iter().map(|step| step.hedge_ratio.beta));
+        let smoothed_error = error(&mut smoothed.iter().map(|ratio| ratio.beta));
+        assert!(smoothed_error < filtered_error);
+
+        // Trade the spread: short y against beta units of x when it is rich,
+        // long when cheap, and flatten when it crosses zero
+        let mut position = 0.0;
+        let mut hedge = 0.0;
+        let mut pnl = 0.0;
+        let mut trades = 0;
+        for t in 100..n - 1 {
+            let step = steps[t];
+            let z = step.zscore();
+            if position == 0.0 && z.abs() > 1.0 {
+                position = -z.signum();
+                hedge = step.hedge_ratio.beta;
+                trades += 1;
+            } else if position * z >= 0.0 {
+                position = 0.0;
+            }
+            pnl += position * ((y[t + 1] - y[t]) - hedge * (x[t + 1] - x[t]));
+        }
+        assert!(trades > 10);
+        assert!(pnl > 0.0);
+    }
+}
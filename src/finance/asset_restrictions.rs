@@ -38,6 +38,16 @@ impl RestrictionReason {
     }
 }
 
+/// What the engine does with a position whose asset becomes restricted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RestrictedPositions {
+    /// Keep the position; orders in the asset are still rejected
+    #[default]
+    Hold,
+    /// Cancel the asset's open orders and close the position at market
+    Liquidate,
+}
+
 /// Restrictions trait - defines which assets can be traded
 pub trait Restrictions: Send + Sync {
     /// Check if an asset is restricted for trading at a given time
@@ -309,6 +319,8 @@ impl Restrictions for CompositeRestrictions {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::algorithm::{Algorithm, Context};
+    use crate::data::BarData;
 
     #[test]
     fn test_no_restrictions() {
@@ -358,8 +370,14 @@ mod tests {
         let mut asset = Asset::equity(1, "TEST".to_string(), "NYSE".to_string(), start_date);
         asset.start_date = start_date;
 
-        let before_start = asset.start_date - chrono::Duration::days(1);
-        let after_start = asset.start_date + chrono::Duration::days(1);
+        let before_start = (asset.start_date - chrono::Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        let after_start = (asset.start_date + chrono::Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
 
         assert!(restrictions.is_restricted(&asset, before_start).is_err());
         assert!(restrictions.is_restricted(&asset, after_start).is_ok());
@@ -413,4 +431,85 @@ mod tests {
         assert_eq!(RestrictionReason::Regulatory.as_str(), "regulatory");
         assert_eq!(RestrictionReason::Historical.as_str(), "historical");
     }
+
+    /// Restricts one asset from a given time on
+    struct RestrictedFrom(u64, DateTime<Utc>);
+
+    impl Restrictions for RestrictedFrom {
+        fn is_restricted(&self, asset: &Asset, dt: DateTime<Utc>) -> Result<()> {
+            if asset.id == self.0 && dt >= self.1 {
+                return Err(ZiplineError::RestrictedAsset(format!("Asset {} halted", asset.id)));
+            }
+            Ok(())
+        }
+    }
+
+    /// Buys on the first bar and tries to buy more on the fourth
+    struct Accumulate {
+        asset: Asset,
+        bars: usize,
+        refused: bool,
+        quantity: f64,
+    }
+
+    impl Algorithm for Accumulate {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            self.bars += 1;
+            if self.bars == 1 || self.bars == 4 {
+                let result = context.order(self.asset.clone(), 100.0);
+                self.refused |= matches!(result, Err(ZiplineError::RestrictedAsset(_)));
+                assert_eq!(context.is_restricted(&self.asset), self.bars == 4);
+            }
+            Ok(())
+        }
+
+        fn analyze(&mut self, context: &Context) -> Result<()> {
+            self.quantity = context.portfolio.get_position(self.asset.id).map_or(0.0, |p| p.quantity);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_restrictions_in_order_path() {
+        use crate::calendar::NYSECalendar;
+        use crate::data::InMemoryDataSource;
+        use crate::engine::SimulationEngine;
+        use crate::types::Bar;
+        use chrono::{Duration, TimeZone};
+        use std::sync::Arc;
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "TEST".to_string(), "NYSE".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for offset in 0..4 {
+            source.add_bar(1, Bar::new(start + Duration::days(offset), 10.0, 10.0, 10.0, 10.0, 1e6));
+        }
+        let end = start + Duration::days(3);
+        source.set_date_range(start, end);
+
+        // Restricted from the third bar on
+        let restrictions = Arc::new(RestrictedFrom(1, start + Duration::days(2)));
+        let run = |positions| {
+            let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()))
+                .with_restrictions(restrictions.clone(), positions);
+            let mut algorithm = Accumulate { asset: asset.clone(), bars: 0, refused: false, quantity: 0.0 };
+            let results = engine.run(&mut algorithm, &source, start, end).unwrap();
+            (algorithm, results)
+        };
+
+        let (held, _) = run(RestrictedPositions::Hold);
+        assert!(held.refused);
+        assert_eq!(held.quantity, 100.0);
+
+        let (liquidated, results) = run(RestrictedPositions::Liquidate);
+        assert!(liquidated.refused);
+        assert_eq!(liquidated.quantity, 0.0);
+        let close = results.transactions.last().unwrap();
+        assert_eq!((close.amount, close.note.as_deref()), (-100.0, Some("restricted")));
+        assert_eq!(close.dt, start + Duration::days(2));
+    }
 }
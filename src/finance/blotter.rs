@@ -2,7 +2,7 @@
 
 use crate::asset::Asset;
 use crate::error::{Result, ZiplineError};
-use crate::order::{Order, OrderStatus};
+use crate::order::{BracketBook, BracketOrder, BracketOrderIds, Order, OrderStatus};
 use crate::types::{Cash, OrderId, Price};
 use chrono::{NaiveDate, DateTime, Utc};
 use std::collections::HashMap;
@@ -119,7 +119,7 @@ impl Default for TransactionLog {
 }
 
 /// Fill information for an order
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fill {
     /// Fill price
     pub price: Price,
@@ -154,6 +154,8 @@ pub struct Blotter {
     rejected_orders: HashMap<OrderId, Order>,
     /// Transaction log
     transactions: TransactionLog,
+    /// Bracket exits waiting for their entry, and OCO links between active exits
+    brackets: BracketBook,
 }
 
 impl Blotter {
@@ -164,6 +166,7 @@ impl Blotter {
             cancelled_orders: HashMap::new(),
             rejected_orders: HashMap::new(),
             transactions: TransactionLog::new(),
+            brackets: BracketBook::new(),
         }
     }
 
@@ -174,29 +177,94 @@ impl Blotter {
         order_id
     }
 
+    /// Place a bracket order
+    ///
+    /// The entry order is opened immediately. The stop-loss and take-profit exits
+    /// are held back until the entry closes, then opened as an OCO pair sized to
+    /// what the entry filled.
+    pub fn place_bracket_order(&mut self, bracket: BracketOrder) -> BracketOrderIds {
+        let ids = bracket.ids();
+        self.place_order(bracket.parent);
+        self.brackets
+            .insert(ids.parent, [bracket.stop_loss, bracket.take_profit]);
+        ids
+    }
+
+    /// Get the OCO sibling of an active bracket exit
+    pub fn bracket_sibling(&self, order_id: OrderId) -> Option<OrderId> {
+        self.brackets.sibling(order_id)
+    }
+
     /// Cancel an order
+    ///
+    /// Cancelling an unfilled bracket entry discards its pending exits, while a
+    /// partially filled entry opens them for the filled quantity. Cancelling one
+    /// active bracket exit also cancels its sibling.
     pub fn cancel_order(&mut self, order_id: OrderId, dt: DateTime<Utc>) -> Result<()> {
         if let Some(mut order) = self.open_orders.remove(&order_id) {
             order.cancel(dt);
+            let filled = order.filled;
             self.cancelled_orders.insert(order_id, order);
+            self.close_bracket_order(order_id, filled, dt);
             Ok(())
         } else {
             Err(ZiplineError::OrderIdNotFound { order_id })
         }
     }
 
-    /// Reject an order
-    pub fn reject_order(&mut self, order_id: OrderId, reason: String) -> Result<()> {
+    /// Reject an order at simulation time `dt`
+    ///
+    /// Bracket dependents are handled as for [`cancel_order`](Self::cancel_order).
+    pub fn reject_order(&mut self, order_id: OrderId, reason: String, dt: DateTime<Utc>) -> Result<()> {
         if let Some(mut order) = self.open_orders.remove(&order_id) {
             order.status = OrderStatus::Rejected;
+            order.updated_at = dt;
+            let filled = order.filled;
             self.rejected_orders.insert(order_id, order);
-            log::warn!("Order {} rejected: {}", order_id, reason);
+            tracing::warn!(%order_id, %reason, "Order rejected");
+            self.close_bracket_order(order_id, filled, dt);
             Ok(())
         } else {
             Err(ZiplineError::OrderIdNotFound { order_id })
         }
     }
 
+    /// Release the exits of a closed entry, or cancel the sibling of a closed exit
+    fn close_bracket_order(&mut self, order_id: OrderId, filled: f64, dt: DateTime<Utc>) {
+        if let Some(exits) = self.brackets.close_entry(order_id, filled, dt) {
+            for exit in exits {
+                if exit.is_open() {
+                    self.open_orders.insert(exit.id, exit);
+                } else {
+                    self.cancelled_orders.insert(exit.id, exit);
+                }
+            }
+        }
+
+        if let Some(sibling_id) = self.brackets.close_exit(order_id) {
+            if let Some(mut sibling) = self.open_orders.remove(&sibling_id) {
+                sibling.cancel(dt);
+                self.cancelled_orders.insert(sibling_id, sibling);
+            }
+        }
+    }
+
+    /// Update bracket state after a fill
+    fn process_bracket_fill(&mut self, order_id: OrderId, filled: f64, remaining: f64, is_filled: bool, dt: DateTime<Utc>) {
+        if is_filled {
+            // A filled entry opens its exits; a filled exit cancels its sibling
+            self.close_bracket_order(order_id, filled, dt);
+        } else if let Some(sibling) = self
+            .brackets
+            .sibling(order_id)
+            .and_then(|sibling_id| self.open_orders.get_mut(&sibling_id))
+        {
+            // Partial exit: the sibling only needs to cover what is left
+            sibling.quantity = sibling.filled + remaining;
+            sibling.updated_at = dt;
+        }
+    }
+
     /// Process a fill for an order
     pub fn process_fill(&mut self, order_id: OrderId, fill: Fill) -> Result<Transaction> {
         let order = self.open_orders.get_mut(&order_id).ok_or_else(|| {
@@ -222,12 +290,18 @@ impl Blotter {
         // Record transaction
         self.transactions.record(transaction.clone());
 
+        let is_filled = order.is_filled();
+        let filled = order.filled;
+        let remaining = order.remaining();
+
         // Move order to filled if complete
-        if order.is_filled() {
+        if is_filled {
             let order = self.open_orders.remove(&order_id).unwrap();
             self.filled_orders.insert(order_id, order);
         }
 
+        self.process_bracket_fill(order_id, filled, remaining, is_filled, fill.dt);
+
         Ok(transaction)
     }
 
@@ -238,6 +312,7 @@ impl Blotter {
             .or_else(|| self.filled_orders.get(&order_id))
             .or_else(|| self.cancelled_orders.get(&order_id))
             .or_else(|| self.rejected_orders.get(&order_id))
+            .or_else(|| self.brackets.pending_exit(order_id))
     }
 
     /// Get all open orders
@@ -445,6 +520,7 @@ impl Blotter {
         self.cancelled_orders.clear();
         self.rejected_orders.clear();
         self.transactions = TransactionLog::new();
+        self.brackets.clear();
     }
 }
 
@@ -459,7 +535,7 @@ mod tests {
     use super::*;
     use crate::asset::Asset;
     use crate::order::{Order, OrderSide};
-    use chrono::Utc;
+    use chrono::{TimeZone, Utc};
 
     #[test]
     fn test_transaction_creation() {
@@ -564,4 +640,87 @@ mod tests {
         assert_eq!(blotter.order_counts(), (0, 1, 0, 0)); // Now filled
         assert_eq!(blotter.transactions().count(), 2);
     }
+
+    fn create_bracket() -> BracketOrder {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        BracketOrder::new(asset, OrderSide::Buy, 100.0, None, 95.0, 110.0, Utc::now()).unwrap()
+    }
+
+    #[test]
+    fn test_bracket_exits_activate_on_entry_fill() {
+        let mut blotter = Blotter::new();
+        let ids = blotter.place_bracket_order(create_bracket());
+
+        // Only the entry is working until it fills
+        assert_eq!(blotter.order_counts(), (1, 0, 0, 0));
+        assert!(blotter.get_order(ids.stop_loss).is_some());
+
+        blotter.process_fill(ids.parent, Fill::new(100.0, 50.0, 0.0, Utc::now())).unwrap();
+        assert_eq!(blotter.order_counts(), (1, 0, 0, 0));
+
+        blotter.process_fill(ids.parent, Fill::new(100.0, 50.0, 0.0, Utc::now())).unwrap();
+        assert_eq!(blotter.order_counts(), (2, 1, 0, 0));
+        assert_eq!(blotter.bracket_sibling(ids.stop_loss), Some(ids.take_profit));
+        assert_eq!(blotter.bracket_sibling(ids.take_profit), Some(ids.stop_loss));
+    }
+
+    #[test]
+    fn test_bracket_oco_cancels_sibling() {
+        let mut blotter = Blotter::new();
+        let ids = blotter.place_bracket_order(create_bracket());
+        blotter.process_fill(ids.parent, Fill::new(100.0, 100.0, 0.0, Utc::now())).unwrap();
+
+        // Partial take-profit shrinks the stop-loss to the remaining position
+        blotter.process_fill(ids.take_profit, Fill::new(110.0, 40.0, 0.0, Utc::now())).unwrap();
+        assert_eq!(blotter.get_order(ids.stop_loss).unwrap().remaining(), 60.0);
+
+        blotter.process_fill(ids.take_profit, Fill::new(110.0, 60.0, 0.0, Utc::now())).unwrap();
+        let stop_loss = blotter.get_order(ids.stop_loss).unwrap();
+        assert_eq!(stop_loss.status, OrderStatus::Cancelled);
+        assert_eq!(blotter.bracket_sibling(ids.stop_loss), None);
+        assert!(!blotter.has_open_orders());
+    }
+
+    #[test]
+    fn test_bracket_entry_cancel_discards_exits() {
+        let mut blotter = Blotter::new();
+        let ids = blotter.place_bracket_order(create_bracket());
+
+        blotter.cancel_order(ids.parent, Utc::now()).unwrap();
+
+        assert_eq!(blotter.order_counts(), (0, 0, 3, 0));
+        assert_eq!(blotter.get_order(ids.take_profit).unwrap().status, OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_bracket_partial_entry_cancel_sizes_exits() {
+        let mut blotter = Blotter::new();
+        let ids = blotter.place_bracket_order(create_bracket());
+        blotter.process_fill(ids.parent, Fill::new(100.0, 40.0, 0.0, Utc::now())).unwrap();
+
+        let dt = Utc.with_ymd_and_hms(2024, 1, 2, 15, 0, 0).unwrap();
+        blotter.cancel_order(ids.parent, dt).unwrap();
+
+        // The exits protect the 40 shares that did fill
+        assert_eq!(blotter.order_counts(), (2, 0, 1, 0));
+        for exit_id in [ids.stop_loss, ids.take_profit] {
+            let exit = blotter.get_order(exit_id).unwrap();
+            assert_eq!((exit.quantity, exit.updated_at), (40.0, dt));
+        }
+        assert_eq!(blotter.bracket_sibling(ids.stop_loss), Some(ids.take_profit));
+    }
+
+    #[test]
+    fn test_bracket_reject_uses_simulation_time() {
+        let mut blotter = Blotter::new();
+        let ids = blotter.place_bracket_order(create_bracket());
+
+        let dt = Utc.with_ymd_and_hms(2024, 1, 2, 15, 0, 0).unwrap();
+        blotter.reject_order(ids.parent, "no liquidity".to_string(), dt).unwrap();
+
+        assert_eq!(blotter.order_counts(), (0, 0, 2, 1));
+        assert_eq!(blotter.get_order(ids.parent).unwrap().updated_at, dt);
+        assert_eq!(blotter.get_order(ids.stop_loss).unwrap().updated_at, dt);
+    }
 }
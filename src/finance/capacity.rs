@@ -0,0 +1,241 @@
+//! Capacity-aware order scaling
+//!
+//! [`CapacityLimits`] bound how much of the market a strategy may take: each
+//! order to a fraction of the asset's trailing average daily volume, and each
+//! position to a fraction of the asset's float. Rather than rejecting orders
+//! that exceed them, [`CapacityTracker`] scales them down and keeps the clipped
+//! shares as a shadow position, marked to market on every bar. The shadow
+//! P&L is what the strategy would have earned with unlimited capacity, i.e.
+//! the alpha lost to the constraints.
+
+use crate::error::{Result, ZiplineError};
+use crate::types::{Price, QUANTITY_TOLERANCE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Order and position size limits relative to market size
+#[derive(Debug, Clone, Default)]
+pub struct CapacityLimits {
+    /// Largest order as a fraction of trailing ADV
+    pub max_adv_fraction: Option<f64>,
+    /// Largest position as a fraction of float
+    pub max_float_fraction: Option<f64>,
+    /// Float, in shares, by asset id
+    float_shares: HashMap<u64, f64>,
+}
+
+impl CapacityLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap each order at `fraction` of the asset's trailing ADV
+    pub fn with_max_adv_fraction(mut self, fraction: f64) -> Self {
+        self.max_adv_fraction = Some(fraction.max(0.0));
+        self
+    }
+
+    /// Cap each position at `fraction` of the asset's float
+    pub fn with_max_float_fraction(mut self, fraction: f64) -> Self {
+        self.max_float_fraction = Some(fraction.max(0.0));
+        self
+    }
+
+    /// Set an asset's float in shares
+    pub fn with_float(mut self, asset_id: u64, shares: f64) -> Self {
+        self.float_shares.insert(asset_id, shares);
+        self
+    }
+
+    /// Largest signed change from `current` toward `current + delta` within the limits
+    ///
+    /// Orders that reduce a position are never scaled by the float limit.
+    /// Limits without data (no ADV yet, unknown float) do not apply.
+    pub fn allowed_delta(&self, asset_id: u64, current: f64, delta: f64, adv: Option<f64>) -> f64 {
+        let mut allowed = delta;
+
+        if let (Some(fraction), Some(float)) =
+            (self.max_float_fraction, self.float_shares.get(&asset_id))
+        {
+            let cap = fraction * float;
+            let target = current + delta;
+            let reducing = target.abs() <= current.abs() && target * current >= 0.0;
+            if target.abs() > cap && !reducing {
+                let capped = cap.copysign(target) - current;
+                allowed = if capped * delta > 0.0 { capped } else { 0.0 };
+            }
+        }
+
+        if let (Some(fraction), Some(adv)) = (self.max_adv_fraction, adv) {
+            let cap = fraction * adv;
+            if allowed.abs() > cap {
+                allowed = cap.copysign(allowed);
+            }
+        }
+
+        allowed
+    }
+}
+
+/// Shares and value clipped from one asset's orders
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetCapacity {
+    /// Orders scaled down
+    pub orders_scaled: usize,
+    /// Shares requested but not ordered
+    pub clipped_shares: f64,
+    /// Value of the clipped shares when they were clipped
+    pub clipped_notional: f64,
+    /// P&L the clipped shares would have earned since
+    pub lost_pnl: f64,
+}
+
+/// How much of a strategy's intended trading capacity limits removed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CapacityReport {
+    /// Orders scaled down
+    pub orders_scaled: usize,
+    /// Orders dropped entirely
+    pub orders_dropped: usize,
+    /// Value of all clipped shares when they were clipped
+    pub clipped_notional: f64,
+    /// P&L the clipped shares would have earned, i.e. alpha lost to capacity
+    pub lost_pnl: f64,
+    /// Breakdown by asset id
+    pub by_asset: HashMap<u64, AssetCapacity>,
+}
+
+/// Applies [`CapacityLimits`] to orders and tracks what they cost
+#[derive(Debug, Clone, Default)]
+pub struct CapacityTracker {
+    limits: CapacityLimits,
+    report: CapacityReport,
+    /// Clipped shares held as a shadow position, with their last mark
+    shadow: HashMap<u64, (f64, Price)>,
+}
+
+impl CapacityTracker {
+    pub fn new(limits: CapacityLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Limits applied to orders
+    pub fn limits(&self) -> &CapacityLimits {
+        &self.limits
+    }
+
+    /// Quantity an order of `delta` shares on a position of `current` shares may have
+    ///
+    /// Returns the signed quantity to order, or an error if nothing may be
+    /// ordered. Nothing is recorded; call [`CapacityTracker::commit`] once the
+    /// order is accepted.
+    pub fn allowed_quantity(&self, asset_id: u64, current: f64, delta: f64, adv: Option<f64>) -> Result<f64> {
+        let allowed = self.limits.allowed_delta(asset_id, current, delta, adv);
+        if allowed.abs() < QUANTITY_TOLERANCE && delta.abs() >= QUANTITY_TOLERANCE {
+            return Err(ZiplineError::TradingControlViolation(format!(
+                "Order for {} shares of asset {} exceeds capacity limits",
+                delta, asset_id
+            )));
+        }
+        Ok(allowed)
+    }
+
+    /// Record an order of `requested` shares placed as `ordered` shares
+    ///
+    /// Both are signed. The difference is kept as a shadow position, priced
+    /// at `price` when known; an `ordered` of zero counts as a dropped order.
+    pub fn commit(&mut self, asset_id: u64, requested: f64, ordered: f64, price: Option<Price>) {
+        let clipped = requested - ordered;
+        if clipped.abs() < QUANTITY_TOLERANCE {
+            return;
+        }
+
+        let notional = price.map(|p| (clipped * p).abs()).unwrap_or(0.0);
+        let entry = self.report.by_asset.entry(asset_id).or_default();
+        entry.clipped_shares += clipped.abs();
+        entry.clipped_notional += notional;
+        self.report.clipped_notional += notional;
+        if let Some(price) = price {
+            let shadow = self.shadow.entry(asset_id).or_insert((0.0, price));
+            shadow.0 += clipped;
+        }
+
+        if ordered.abs() < QUANTITY_TOLERANCE {
+            self.report.orders_dropped += 1;
+        } else {
+            entry.orders_scaled += 1;
+            self.report.orders_scaled += 1;
+        }
+    }
+
+    /// Mark the shadow position in an asset to `price`
+    pub fn mark(&mut self, asset_id: u64, price: Price) {
+        if let Some((shares, last)) = self.shadow.get_mut(&asset_id) {
+            let pnl = *shares * (price - *last);
+            *last = price;
+            if pnl != 0.0 {
+                self.report.by_asset.entry(asset_id).or_default().lost_pnl += pnl;
+                self.report.lost_pnl += pnl;
+            }
+        }
+    }
+
+    /// Capacity costs so far
+    pub fn report(&self) -> &CapacityReport {
+        &self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_delta() {
+        let limits = CapacityLimits::new()
+            .with_max_adv_fraction(0.1)
+            .with_max_float_fraction(0.05)
+            .with_float(1, 10_000.0);
+
+        // ADV cap: 10% of 2,000
+        assert_eq!(limits.allowed_delta(1, 0.0, 300.0, Some(2_000.0)), 200.0);
+        assert_eq!(limits.allowed_delta(1, 0.0, -300.0, Some(2_000.0)), -200.0);
+        // Float cap: 5% of 10,000 with 400 held
+        assert_eq!(limits.allowed_delta(1, 400.0, 300.0, None), 100.0);
+        assert_eq!(limits.allowed_delta(1, 500.0, 10.0, None), 0.0);
+        // Reducing an oversized position is allowed
+        assert_eq!(limits.allowed_delta(1, 800.0, -100.0, None), -100.0);
+        // Unknown float and ADV do not limit
+        assert_eq!(limits.allowed_delta(2, 0.0, 1e6, None), 1e6);
+    }
+
+    #[test]
+    fn test_tracker_reports_lost_pnl() {
+        let limits = CapacityLimits::new().with_max_adv_fraction(0.1);
+        let mut tracker = CapacityTracker::new(limits);
+
+        assert_eq!(tracker.allowed_quantity(1, 0.0, 150.0, Some(1_000.0)).unwrap(), 100.0);
+        assert_eq!(tracker.allowed_quantity(1, 0.0, 50.0, Some(1_000.0)).unwrap(), 50.0);
+        assert!(tracker.allowed_quantity(1, 0.0, 50.0, Some(0.0)).is_err());
+        assert_eq!(tracker.report(), &CapacityReport::default());
+
+        tracker.commit(1, 150.0, 100.0, Some(10.0));
+        tracker.commit(1, 50.0, 50.0, Some(10.0));
+        tracker.commit(1, 50.0, 0.0, Some(10.0));
+
+        // 100 shadow shares gain 2.0 each
+        tracker.mark(1, 12.0);
+        tracker.mark(2, 50.0);
+
+        let report = tracker.report();
+        assert_eq!(report.orders_scaled, 1);
+        assert_eq!(report.orders_dropped, 1);
+        assert_eq!(report.clipped_notional, 1_000.0);
+        assert_eq!(report.lost_pnl, 200.0);
+        assert_eq!(report.by_asset[&1].clipped_shares, 100.0);
+    }
+}
@@ -2,6 +2,8 @@
 
 use crate::order::Order;
 use crate::types::Cash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Commission model trait
 pub trait CommissionModel: Send + Sync {
@@ -127,6 +129,111 @@ impl CommissionModel for ZeroCommission {
     }
 }
 
+/// Per-contract fee components for futures
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ContractFees {
+    /// Broker commission per contract
+    #[serde(default)]
+    pub commission: f64,
+    /// Exchange fee per contract
+    #[serde(default)]
+    pub exchange_fee: f64,
+    /// NFA regulatory fee per contract
+    #[serde(default)]
+    pub nfa_fee: f64,
+}
+
+impl ContractFees {
+    pub fn new(commission: f64, exchange_fee: f64, nfa_fee: f64) -> Self {
+        Self {
+            commission,
+            exchange_fee,
+            nfa_fee,
+        }
+    }
+
+    /// Total cost per contract
+    pub fn per_contract(&self) -> f64 {
+        self.commission + self.exchange_fee + self.nfa_fee
+    }
+}
+
+/// Per-contract commission model for futures
+///
+/// Charges the broker commission, exchange fee and NFA fee on every contract
+/// filled. Fees can be overridden per root symbol (e.g. `ES`, `CL`), which is
+/// parsed from contract symbols such as `ESH24` or `CLZ5`.
+#[derive(Debug, Clone)]
+pub struct PerContract {
+    /// Fees applied to roots without an override
+    pub default_fees: ContractFees,
+    /// Fees by root symbol
+    pub root_overrides: HashMap<String, ContractFees>,
+    /// Minimum commission per fill
+    pub min_commission: f64,
+}
+
+impl PerContract {
+    /// Create new per-contract commission model
+    pub fn new(cost_per_contract: f64, exchange_fee: f64, nfa_fee: f64) -> Self {
+        Self {
+            default_fees: ContractFees::new(cost_per_contract, exchange_fee, nfa_fee),
+            root_overrides: HashMap::new(),
+            min_commission: 0.0,
+        }
+    }
+
+    /// Use different fees for contracts of one root symbol
+    pub fn with_root_override(mut self, root_symbol: &str, fees: ContractFees) -> Self {
+        self.root_overrides.insert(root_symbol.to_string(), fees);
+        self
+    }
+
+    /// Set the minimum commission per fill
+    pub fn with_min(mut self, min_commission: f64) -> Self {
+        self.min_commission = min_commission;
+        self
+    }
+
+    /// Fees applying to a contract symbol
+    pub fn fees_for(&self, symbol: &str) -> ContractFees {
+        self.root_overrides
+            .get(symbol)
+            .or_else(|| self.root_overrides.get(Self::root_symbol(symbol)))
+            .copied()
+            .unwrap_or(self.default_fees)
+    }
+
+    /// Root of a futures contract symbol: `ESH24` -> `ES`, `CLZ5` -> `CL`
+    ///
+    /// Symbols not ending in a month code and year are returned unchanged.
+    pub fn root_symbol(symbol: &str) -> &str {
+        const MONTH_CODES: &str = "FGHJKMNQUVXZ";
+
+        let without_year = symbol.trim_end_matches(|c: char| c.is_ascii_digit());
+        if without_year.len() == symbol.len() || without_year.len() < 2 {
+            return symbol;
+        }
+
+        match without_year.chars().last() {
+            Some(code) if MONTH_CODES.contains(code) => &without_year[..without_year.len() - 1],
+            _ => symbol,
+        }
+    }
+}
+
+impl CommissionModel for PerContract {
+    fn calculate(&self, order: &Order, _fill_price: f64, fill_quantity: f64) -> Cash {
+        let fees = self.fees_for(&order.asset.symbol);
+        let commission = fees.per_contract() * fill_quantity.abs();
+        commission.max(self.min_commission)
+    }
+
+    fn name(&self) -> &str {
+        "PerContract"
+    }
+}
+
 /// Tiered commission model based on trade volume
 #[derive(Debug, Clone)]
 pub struct TieredCommission {
@@ -174,6 +281,7 @@ impl CommissionModel for TieredCommission {
 mod tests {
     use super::*;
     use crate::asset::{Asset, AssetType};
+    use crate::order::OrderSide;
     use chrono::Utc;
 use chrono::NaiveDate;
 
@@ -246,4 +354,33 @@ use chrono::NaiveDate;
         let order3 = create_test_order(10000.0);
         assert_eq!(model.calculate(&order3, 50.0, 10000.0), 20.0); // 10000 * 0.002
     }
+
+    #[test]
+    fn test_per_contract_commission() {
+        let model = PerContract::new(0.85, 1.28, 0.02)
+            .with_root_override("CL", ContractFees::new(0.85, 1.50, 0.02));
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let es = Asset::new(1, "ESH24".to_string(), "CME".to_string(), AssetType::Future, start_date);
+        let cl = Asset::new(2, "CLZ4".to_string(), "NYMEX".to_string(), AssetType::Future, start_date);
+
+        let es_order = Order::market(es, OrderSide::Sell, 10.0, Utc::now());
+        assert!((model.calculate(&es_order, 5000.0, -10.0) - 21.5).abs() < 1e-9);
+
+        let cl_order = Order::market(cl, OrderSide::Buy, 2.0, Utc::now());
+        assert!((model.calculate(&cl_order, 75.0, 2.0) - 4.74).abs() < 1e-9);
+
+        let min_model = PerContract::new(0.85, 0.0, 0.0).with_min(2.0);
+        assert_eq!(min_model.calculate(&cl_order, 75.0, 1.0), 2.0);
+    }
+
+    #[test]
+    fn test_root_symbol() {
+        assert_eq!(PerContract::root_symbol("ESH24"), "ES");
+        assert_eq!(PerContract::root_symbol("CLZ5"), "CL");
+        assert_eq!(PerContract::root_symbol("ZNU2024"), "ZN");
+        assert_eq!(PerContract::root_symbol("ES"), "ES");
+        assert_eq!(PerContract::root_symbol("H24"), "H24");
+        assert_eq!(PerContract::root_symbol("ESA24"), "ESA24");
+    }
 }
@@ -1,17 +1,120 @@
 //! Trading controls and restrictions
 
 use crate::algorithm::Context;
+use crate::asset::Asset;
+use crate::data::BarData;
 use crate::error::{Result, ZiplineError};
+use crate::finance::market_stats::MarketStatsService;
+use crate::finance::settlement::{PDT_MAX_DAY_TRADES, PDT_MIN_EQUITY, PDT_WINDOW_SESSIONS};
 use crate::order::Order;
+use crate::types::{Cash, Price};
 use chrono::Duration;
 use chrono::NaiveDate;
 use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Current prices available to trading controls
+///
+/// Passed to [`TradingControl::validate_order`] so notional-based limits are
+/// checked against real prices. Implemented by [`BarData`] (the current bar)
+/// and [`MarketStatsService`] (last traded price).
+pub trait PriceLookup {
+    /// Current price of an asset, if known
+    fn price(&self, asset: &Asset) -> Option<Price>;
+}
+
+impl PriceLookup for BarData {
+    fn price(&self, asset: &Asset) -> Option<Price> {
+        self.current_price(asset).ok()
+    }
+}
+
+impl PriceLookup for MarketStatsService {
+    fn price(&self, asset: &Asset) -> Option<Price> {
+        self.last_price(asset.id)
+    }
+}
+
+impl PriceLookup for std::collections::HashMap<u64, Price> {
+    fn price(&self, asset: &Asset) -> Option<Price> {
+        self.get(&asset.id).copied()
+    }
+}
+
+/// Price lookup that knows no prices
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPrices;
+
+impl PriceLookup for NoPrices {
+    fn price(&self, _asset: &Asset) -> Option<Price> {
+        None
+    }
+}
+
+/// Best available price for valuing an order
+///
+/// The order's own limit or stop price, then the current price, then the last
+/// price of the held position. None when no price is known, in which case
+/// notional limits cannot be checked.
+fn estimated_price(order: &Order, context: &Context, prices: &dyn PriceLookup) -> Option<f64> {
+    order
+        .limit_price
+        .or(order.stop_price)
+        .or_else(|| prices.price(&order.asset))
+        .or_else(|| {
+            context
+                .portfolio
+                .get_position(order.asset.id)
+                .map(|p| p.last_price)
+        })
+        .filter(|p| *p > 0.0)
+}
+
+/// Shares an order adds to its asset's position, negative for a sell
+fn signed_quantity(order: &Order) -> f64 {
+    match order.side {
+        crate::order::OrderSide::Buy => order.quantity,
+        crate::order::OrderSide::Sell => -order.quantity,
+    }
+}
+
+/// Whether going from `current` to `new` shares only shrinks the position
+///
+/// Orders that reduce a position pass the size and concentration limits even
+/// when the position is still over them, so an oversized position can be cut.
+fn reduces_position(current: f64, new: f64) -> bool {
+    new.abs() <= current.abs() && current * new >= 0.0
+}
 
 /// Trait for order-level trading controls
 pub trait TradingControl: Send + Sync {
     /// Validate an order before submission
-    fn validate_order(&self, order: &Order, context: &Context) -> Result<()>;
+    ///
+    /// Controls written before prices were passed in implement this; controls
+    /// that value orders implement [`validate_order_at`](Self::validate_order_at).
+    fn validate_order(&self, _order: &Order, _context: &Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// Validate an order before submission, valuing it at `prices`
+    ///
+    /// Defaults to [`validate_order`](Self::validate_order), ignoring the prices.
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        self.validate_order(order, context)
+    }
+
+    /// Note an order that passed every control and was queued
+    ///
+    /// `validate_order` also runs for previews and for orders a later control
+    /// rejects, so controls that learn from past orders update here instead.
+    fn record_accepted(&self, _order: &Order, _context: &Context, _prices: &dyn PriceLookup) {}
 
     /// Get control name for error messages
     fn name(&self) -> &str;
@@ -24,6 +127,30 @@ pub trait AccountControl: Send + Sync {
 
     /// Get control name
     fn name(&self) -> &str;
+
+    /// Whether the engine halts trading for the rest of the run when the
+    /// account fails this control, checked after every bar
+    fn halts_trading(&self) -> bool {
+        false
+    }
+
+    /// Whether a halt this control causes also closes every position
+    fn liquidates(&self) -> bool {
+        false
+    }
+}
+
+/// Trading stopped by an account control for the rest of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradingHalt {
+    /// Control that halted trading
+    pub control: String,
+    /// Why the account failed the control
+    pub reason: String,
+    /// Bar after which trading stopped
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Whether positions were ordered closed
+    pub liquidated: bool,
 }
 
 /// Restrict maximum order size
@@ -58,7 +185,12 @@ impl MaxOrderSize {
 }
 
 impl TradingControl for MaxOrderSize {
-    fn validate_order(&self, order: &Order, context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Result<()> {
         if let Some(max) = self.max_shares {
             if order.quantity > max {
                 return Err(ZiplineError::MaxOrderSizeExceeded {
@@ -70,8 +202,14 @@ impl TradingControl for MaxOrderSize {
         }
 
         if let Some(max) = self.max_notional {
-            // Estimate notional value (would need current price in real implementation)
-            let estimated_notional = order.quantity * context.portfolio.portfolio_value / 100.0;
+            // An order that cannot be valued cannot be shown to be under the limit
+            let Some(price) = estimated_price(order, context, prices) else {
+                return Err(ZiplineError::TradingControlViolation(format!(
+                    "MaxOrderSize: no price for {} to check the {:.2} notional limit",
+                    order.asset.symbol, max
+                )));
+            };
+            let estimated_notional = order.quantity * price;
             if estimated_notional > max {
                 return Err(ZiplineError::MaxOrderSizeExceeded {
                     asset: order.asset.id,
@@ -115,7 +253,12 @@ impl MaxOrderCount {
 }
 
 impl TradingControl for MaxOrderCount {
-    fn validate_order(&self, _order: &Order, context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        _order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
         let cutoff = context.timestamp - self.period;
         let recent_orders: usize = self
             .order_times
@@ -139,6 +282,63 @@ impl TradingControl for MaxOrderCount {
     }
 }
 
+/// Orders per asset per bar allowed by default
+pub const DEFAULT_MAX_ORDERS_PER_BAR: usize = 100;
+
+/// Limit the orders placed for one asset within a single bar
+///
+/// No sensible strategy orders the same asset hundreds of times in one bar;
+/// when it happens, the order logic is almost always looping. The order that
+/// goes over the limit fails with [`ZiplineError::MaxOrdersPerBarExceeded`].
+/// [`Context`] applies this control with [`DEFAULT_MAX_ORDERS_PER_BAR`] unless
+/// told otherwise.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxOrdersPerBar {
+    pub max_count: usize,
+}
+
+impl MaxOrdersPerBar {
+    pub fn new(max_count: usize) -> Self {
+        Self { max_count }
+    }
+}
+
+impl Default for MaxOrdersPerBar {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ORDERS_PER_BAR)
+    }
+}
+
+impl TradingControl for MaxOrdersPerBar {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        let count = context
+            .pending_orders
+            .iter()
+            .filter(|o| o.asset.id == order.asset.id && o.created_at == context.timestamp)
+            .count();
+
+        if count >= self.max_count {
+            return Err(ZiplineError::MaxOrdersPerBarExceeded {
+                asset: order.asset.id,
+                count: count + 1,
+                max_count: self.max_count,
+                date: context.timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "MaxOrdersPerBar"
+    }
+}
+
 /// Restrict maximum position size
 pub struct MaxPositionSize {
     /// Maximum shares per position
@@ -171,17 +371,22 @@ impl MaxPositionSize {
 }
 
 impl TradingControl for MaxPositionSize {
-    fn validate_order(&self, order: &Order, context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Result<()> {
         let current_position = context
             .portfolio
             .get_position(order.asset.id)
             .map(|p| p.quantity)
             .unwrap_or(0.0);
 
-        let new_position = match order.side {
-            crate::order::OrderSide::Buy => current_position + order.quantity,
-            crate::order::OrderSide::Sell => current_position - order.quantity,
-        };
+        let new_position = current_position + signed_quantity(order);
+        if reduces_position(current_position, new_position) {
+            return Ok(());
+        }
 
         if let Some(max) = self.max_shares {
             if new_position.abs() > max {
@@ -195,9 +400,16 @@ impl TradingControl for MaxPositionSize {
             }
         }
 
-        if let Some(max_pct) = self.max_pct_portfolio {
-            // Estimate position value as percentage of portfolio
-            let estimated_value = new_position.abs() * 100.0; // Placeholder
+        let price = estimated_price(order, context, prices);
+        if let (Some(max_pct), None) = (self.max_pct_portfolio, price) {
+            tracing::warn!(
+                asset = %order.asset.symbol,
+                max_pct,
+                "MaxPositionSize: no price, portfolio percentage limit not checked"
+            );
+        }
+        if let (Some(max_pct), Some(price)) = (self.max_pct_portfolio, price) {
+            let estimated_value = new_position.abs() * price;
             let pct = estimated_value / context.portfolio.portfolio_value;
             let max_notional = max_pct * context.portfolio.portfolio_value;
             if pct > max_pct {
@@ -245,7 +457,12 @@ impl RestrictedList {
 }
 
 impl TradingControl for RestrictedList {
-    fn validate_order(&self, order: &Order, _context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        _context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
         if self.is_restricted(order.asset.id) {
             return Err(ZiplineError::InvalidOrder(format!(
                 "Asset {} ({}) is on the restricted list",
@@ -270,7 +487,12 @@ impl Default for RestrictedList {
 pub struct LongOnly;
 
 impl TradingControl for LongOnly {
-    fn validate_order(&self, order: &Order, context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
         let current_position = context
             .portfolio
             .get_position(order.asset.id)
@@ -354,6 +576,157 @@ impl AccountControl for MinLeverage {
     }
 }
 
+/// Halt trading once the portfolio falls too far from its peak
+///
+/// The kill switch: a halting [`AccountControl`] checked by the engine after
+/// every bar. Once the drawdown from the run's highest portfolio value
+/// exceeds `max_drawdown`, open orders are cancelled, new orders are
+/// rejected with [`ZiplineError::TradingHalted`] and, with
+/// [`with_liquidation`](Self::with_liquidation), every position is closed at
+/// the next bar.
+pub struct MaxDrawdown {
+    /// Largest tolerated fall from peak (0.2 = 20%)
+    pub max_drawdown: f64,
+    /// Whether to close positions on a halt
+    pub liquidate: bool,
+    /// Values seen in the portfolio's history and the peak among them
+    peak: Mutex<(usize, f64)>,
+}
+
+impl MaxDrawdown {
+    pub fn new(max_drawdown: f64) -> Self {
+        Self {
+            max_drawdown,
+            liquidate: false,
+            peak: Mutex::new((0, 0.0)),
+        }
+    }
+
+    /// Close every position when trading halts
+    pub fn with_liquidation(mut self) -> Self {
+        self.liquidate = true;
+        self
+    }
+
+    /// Drawdown of the current portfolio value from the peak of its history
+    fn drawdown(&self, context: &Context) -> f64 {
+        let history = &context.portfolio.value_history;
+        let mut peak = self.peak.lock().unwrap();
+        // A shorter history than last seen is a new run
+        if history.len() < peak.0 {
+            *peak = (0, 0.0);
+        }
+        for (_, value) in &history[peak.0..] {
+            peak.1 = peak.1.max(*value);
+        }
+        peak.0 = history.len();
+
+        let value = context.portfolio.portfolio_value;
+        if peak.1 > 0.0 {
+            (1.0 - value / peak.1).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl AccountControl for MaxDrawdown {
+    fn validate_account(&self, context: &Context) -> Result<()> {
+        let drawdown = self.drawdown(context);
+        if drawdown > self.max_drawdown {
+            return Err(ZiplineError::AccountControlViolation(format!(
+                "drawdown of {:.1}% exceeds maximum of {:.1}%",
+                drawdown * 100.0,
+                self.max_drawdown * 100.0
+            )));
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "MaxDrawdown"
+    }
+
+    fn halts_trading(&self) -> bool {
+        true
+    }
+
+    fn liquidates(&self) -> bool {
+        self.liquidate
+    }
+}
+
+/// Limit day trades in accounts below the pattern day trader equity minimum
+///
+/// An order that would close shares opened earlier in the session is
+/// rejected once the account has made `max_day_trades` day trades in the
+/// last `window_sessions` sessions while its value is under `min_equity`.
+pub struct PatternDayTrader {
+    pub min_equity: Cash,
+    pub max_day_trades: usize,
+    pub window_sessions: usize,
+}
+
+impl PatternDayTrader {
+    /// FINRA's limits: 3 day trades in 5 sessions under $25,000
+    pub fn new() -> Self {
+        Self {
+            min_equity: PDT_MIN_EQUITY,
+            max_day_trades: PDT_MAX_DAY_TRADES,
+            window_sessions: PDT_WINDOW_SESSIONS,
+        }
+    }
+
+    pub fn with_min_equity(mut self, min_equity: Cash) -> Self {
+        self.min_equity = min_equity;
+        self
+    }
+
+    /// Allow `max_day_trades` day trades within any `window_sessions` sessions
+    pub fn with_limit(mut self, max_day_trades: usize, window_sessions: usize) -> Self {
+        self.max_day_trades = max_day_trades;
+        self.window_sessions = window_sessions;
+        self
+    }
+}
+
+impl Default for PatternDayTrader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradingControl for PatternDayTrader {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        let equity = context.portfolio.portfolio_value;
+        if equity >= self.min_equity {
+            return Ok(());
+        }
+        let position = context.portfolio.get_position(order.asset.id).map_or(0.0, |p| p.quantity);
+        let day_trades = context.day_trades();
+        if !day_trades.would_day_trade(order.asset.id, order.side, position) {
+            return Ok(());
+        }
+        let made = day_trades.count(self.window_sessions);
+        if made >= self.max_day_trades {
+            return Err(ZiplineError::InvalidOrder(format!(
+                "Pattern day trader: {} day trades in {} sessions with equity ${:.2} below ${:.2}",
+                made, self.window_sessions, equity, self.min_equity
+            )));
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "PatternDayTrader"
+    }
+}
+
 /// Manager for all trading controls
 pub struct ControlManager {
     order_controls: Vec<Box<dyn TradingControl>>,
@@ -378,14 +751,46 @@ impl ControlManager {
         self.account_controls.push(control);
     }
 
-    /// Validate an order against all controls
+    /// Validate an order against all controls, valued at the context's last
+    /// traded prices
     pub fn validate_order(&self, order: &Order, context: &Context) -> Result<()> {
+        self.validate_order_at(order, context, context.price_lookup())
+    }
+
+    /// Validate an order against all controls, valuing it at `prices`
+    pub fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Result<()> {
         for control in &self.order_controls {
-            control.validate_order(order, context)?;
+            control.validate_order_at(order, context, prices)?;
         }
         Ok(())
     }
 
+    /// Tell every order control that an order was accepted and queued
+    pub fn record_accepted(&self, order: &Order, context: &Context, prices: &dyn PriceLookup) {
+        for control in &self.order_controls {
+            control.record_accepted(order, context, prices);
+        }
+    }
+
+    /// Reasons every order control would reject an order, without stopping at the first
+    pub fn order_violations(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Vec<String> {
+        self.order_controls
+            .iter()
+            .filter_map(|control| control.validate_order_at(order, context, prices).err())
+            .map(|e| e.to_string())
+            .collect()
+    }
+
     /// Validate account state against all controls
     pub fn validate_account(&self, context: &Context) -> Result<()> {
         for control in &self.account_controls {
@@ -394,6 +799,26 @@ impl ControlManager {
         Ok(())
     }
 
+    /// First halting account control the account fails, as a halt at `context.timestamp`
+    pub fn check_halt(&self, context: &Context) -> Option<TradingHalt> {
+        self.account_controls
+            .iter()
+            .filter(|control| control.halts_trading())
+            .find_map(|control| {
+                let reason = match control.validate_account(context) {
+                    Err(ZiplineError::AccountControlViolation(reason)) => reason,
+                    Err(e) => e.to_string(),
+                    Ok(()) => return None,
+                };
+                Some(TradingHalt {
+                    control: control.name().to_string(),
+                    reason,
+                    timestamp: context.timestamp,
+                    liquidated: control.liquidates(),
+                })
+            })
+    }
+
     /// Get count of active controls
     pub fn control_count(&self) -> (usize, usize) {
         (self.order_controls.len(), self.account_controls.len())
@@ -433,12 +858,14 @@ impl SectorExposure {
     }
 
     /// Calculate current sector exposures
+    ///
+    /// Exposure is gross: long and short positions both add to their sector.
     pub fn calculate_exposures(&self, context: &Context) -> std::collections::HashMap<String, f64> {
         let mut sector_values: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
 
         for position in context.portfolio.positions.values() {
             if let Some(sector) = self.asset_sectors.get(&position.asset.id) {
-                let value = position.quantity * position.cost_basis; // Approximation
+                let value = position.market_value().abs();
                 *sector_values.entry(sector.clone()).or_insert(0.0) += value;
             }
         }
@@ -453,14 +880,37 @@ impl SectorExposure {
 }
 
 impl TradingControl for SectorExposure {
-    fn validate_order(&self, order: &Order, context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Result<()> {
         if let Some(sector) = self.get_sector(order.asset.id) {
-            let exposures = self.calculate_exposures(context);
-            let current_exposure = exposures.get(sector).copied().unwrap_or(0.0);
+            let position = context.portfolio.get_position(order.asset.id);
+            let current_position = position.map_or(0.0, |p| p.quantity);
+            let new_position = current_position + signed_quantity(order);
+            if reduces_position(current_position, new_position) {
+                return Ok(());
+            }
 
-            // Estimate new exposure (simplified)
-            let order_value = order.quantity * 100.0; // Placeholder price
-            let new_exposure = current_exposure + (order_value / context.portfolio.portfolio_value);
+            let price = match estimated_price(order, context, prices) {
+                Some(price) => price,
+                None => {
+                    tracing::warn!(
+                        asset = %order.asset.symbol,
+                        %sector,
+                        "SectorExposure: no price, sector limit not checked"
+                    );
+                    return Ok(());
+                }
+            };
+            // The rest of the sector at its marks, this asset at the order's price
+            let portfolio_value = context.portfolio.portfolio_value;
+            let exposures = self.calculate_exposures(context);
+            let others = exposures.get(sector).copied().unwrap_or(0.0) * portfolio_value
+                - position.map_or(0.0, |p| p.market_value().abs());
+            let new_exposure = (others + new_position.abs() * price) / portfolio_value;
 
             if new_exposure > self.max_sector_exposure {
                 return Err(ZiplineError::InvalidOrder(format!(
@@ -481,6 +931,9 @@ impl TradingControl for SectorExposure {
 }
 
 /// Limit trading based on asset volatility
+///
+/// Uses volatilities set with [`VolatilityLimit::update_volatility`], falling
+/// back to the context's market statistics for other assets.
 pub struct VolatilityLimit {
     /// Maximum allowed volatility (annualized)
     max_volatility: f64,
@@ -508,8 +961,20 @@ impl VolatilityLimit {
 }
 
 impl TradingControl for VolatilityLimit {
-    fn validate_order(&self, order: &Order, _context: &Context) -> Result<()> {
-        if let Some(volatility) = self.get_volatility(order.asset.id) {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        let volatility = self.get_volatility(order.asset.id).or_else(|| {
+            context
+                .market_stats
+                .as_ref()
+                .and_then(|stats| stats.volatility(order.asset.id))
+        });
+
+        if let Some(volatility) = volatility {
             if volatility > self.max_volatility {
                 return Err(ZiplineError::InvalidOrder(format!(
                     "Asset {} has volatility {:.2}%, exceeding maximum of {:.2}%",
@@ -541,21 +1006,34 @@ impl PositionConcentration {
 }
 
 impl TradingControl for PositionConcentration {
-    fn validate_order(&self, order: &Order, context: &Context) -> Result<()> {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        let price = match estimated_price(order, context, prices) {
+            Some(price) => price,
+            None => {
+                tracing::warn!(
+                    asset = %order.asset.symbol,
+                    "PositionConcentration: no price, concentration limit not checked"
+                );
+                return Ok(());
+            }
+        };
         let current_position = context
             .portfolio
             .get_position(order.asset.id)
-            .map(|p| p.quantity * p.cost_basis)
+            .map(|p| p.quantity)
             .unwrap_or(0.0);
+        let new_position = current_position + signed_quantity(order);
+        if reduces_position(current_position, new_position) {
+            return Ok(());
+        }
 
-        // Estimate new position value
-        let order_value = order.quantity * 100.0; // Placeholder
-        let new_position_value = match order.side {
-            crate::order::OrderSide::Buy => current_position + order_value,
-            crate::order::OrderSide::Sell => current_position - order_value,
-        };
-
-        let concentration = new_position_value / context.portfolio.portfolio_value;
+        // Long and short positions are equally concentrated
+        let concentration = new_position.abs() * price / context.portfolio.portfolio_value;
 
         if concentration > self.max_concentration {
             return Err(ZiplineError::InvalidOrder(format!(
@@ -573,6 +1051,284 @@ impl TradingControl for PositionConcentration {
     }
 }
 
+/// What a control does when an order trips it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlAction {
+    /// Log a warning and let the order through
+    Warn,
+    /// Reject the order
+    #[default]
+    Reject,
+}
+
+impl ControlAction {
+    fn apply(self, control: &str, reason: String) -> Result<()> {
+        match self {
+            ControlAction::Warn => {
+                tracing::warn!(control, "{}", reason);
+                Ok(())
+            }
+            ControlAction::Reject => Err(ZiplineError::TradingControlViolation(reason)),
+        }
+    }
+}
+
+/// Catch duplicate orders submitted within the same bar
+///
+/// An order is a duplicate when an order already queued in the current bar has
+/// the same asset, side, type, quantity and prices - usually a sign that a
+/// strategy's order logic ran twice.
+#[derive(Debug, Default)]
+pub struct DuplicateOrder {
+    action: ControlAction,
+}
+
+impl DuplicateOrder {
+    pub fn new(action: ControlAction) -> Self {
+        Self { action }
+    }
+
+    fn is_duplicate(order: &Order, other: &Order) -> bool {
+        other.id != order.id
+            && other.asset.id == order.asset.id
+            && other.side == order.side
+            && other.order_type == order.order_type
+            && other.quantity == order.quantity
+            && other.limit_price == order.limit_price
+            && other.stop_price == order.stop_price
+    }
+}
+
+impl TradingControl for DuplicateOrder {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        _prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        let duplicate = context
+            .pending_orders
+            .iter()
+            .filter(|o| o.created_at == context.timestamp)
+            .any(|o| Self::is_duplicate(order, o));
+
+        if duplicate {
+            return self.action.apply(
+                self.name(),
+                format!(
+                    "Duplicate {:?} order for {} {} submitted at {}",
+                    order.side, order.quantity, order.asset.symbol, context.timestamp
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "DuplicateOrder"
+    }
+}
+
+/// Fat-finger protection against abnormally large orders
+///
+/// Flags orders larger than a multiple of the asset's average daily volume, or
+/// whose notional is a multiple of the strategy's typical (median) order
+/// notional over its recent orders. Volumes and prices not set explicitly are
+/// taken from the context's market statistics.
+pub struct FatFinger {
+    /// Maximum order size as a multiple of average daily volume
+    max_adv_multiple: Option<f64>,
+    /// Maximum order notional as a multiple of the typical order notional
+    max_typical_multiple: Option<f64>,
+    /// Number of recent orders defining the typical order notional
+    window: usize,
+    /// Orders required before the typical-size check applies
+    min_history: usize,
+    action: ControlAction,
+    /// Average daily volume (shares) per asset
+    average_daily_volumes: std::collections::HashMap<u64, f64>,
+    /// Reference prices per asset, used when an order carries no price
+    reference_prices: std::collections::HashMap<u64, f64>,
+    /// Notionals of recently accepted orders
+    recent_notionals: Mutex<VecDeque<f64>>,
+}
+
+impl FatFinger {
+    pub fn new() -> Self {
+        Self {
+            max_adv_multiple: None,
+            max_typical_multiple: None,
+            window: 100,
+            min_history: 10,
+            action: ControlAction::Reject,
+            average_daily_volumes: std::collections::HashMap::new(),
+            reference_prices: std::collections::HashMap::new(),
+            recent_notionals: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Flag orders larger than `multiple` times the asset's average daily volume
+    pub fn with_max_adv_multiple(mut self, multiple: f64) -> Self {
+        self.max_adv_multiple = Some(multiple);
+        self
+    }
+
+    /// Flag orders whose notional exceeds `multiple` times the typical order notional
+    pub fn with_max_typical_multiple(mut self, multiple: f64) -> Self {
+        self.max_typical_multiple = Some(multiple);
+        self
+    }
+
+    /// Set how many recent orders define the typical order, and how many are
+    /// needed before the check applies
+    pub fn with_history(mut self, window: usize, min_history: usize) -> Self {
+        self.window = window.max(1);
+        self.min_history = min_history.clamp(1, self.window);
+        self
+    }
+
+    pub fn with_action(mut self, action: ControlAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Update average daily volume (in shares) for an asset
+    pub fn update_adv(&mut self, asset_id: u64, average_daily_volume: f64) {
+        self.average_daily_volumes.insert(asset_id, average_daily_volume);
+    }
+
+    /// Update the reference price for an asset
+    pub fn update_price(&mut self, asset_id: u64, price: f64) {
+        self.reference_prices.insert(asset_id, price);
+    }
+
+    /// Median notional of the recent orders, once enough have been seen
+    pub fn typical_notional(&self) -> Option<f64> {
+        let recent = self.recent_notionals.lock().unwrap();
+        if recent.len() < self.min_history {
+            return None;
+        }
+
+        let mut sorted: Vec<f64> = recent.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mid = sorted.len() / 2;
+        Some(if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        })
+    }
+
+    /// Price for an order: its own limit/stop price, the reference price, or
+    /// the current price
+    fn order_price(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Option<f64> {
+        order
+            .limit_price
+            .or(order.stop_price)
+            .or_else(|| self.reference_prices.get(&order.asset.id).copied())
+            .filter(|p| *p > 0.0)
+            .or_else(|| estimated_price(order, context, prices))
+    }
+
+    /// ADV set with `update_adv`, else from the context's market statistics
+    fn order_adv(&self, order: &Order, context: &Context) -> Option<f64> {
+        self.average_daily_volumes
+            .get(&order.asset.id)
+            .copied()
+            .or_else(|| {
+                context
+                    .market_stats
+                    .as_ref()
+                    .and_then(|stats| stats.average_daily_volume(order.asset.id))
+            })
+    }
+
+    fn check(&self, order: &Order, adv: Option<f64>, notional: Option<f64>) -> Option<String> {
+        if let Some(multiple) = self.max_adv_multiple {
+            if let Some(adv) = adv {
+                if adv > 0.0 && order.quantity > multiple * adv {
+                    return Some(format!(
+                        "Order for {} {} is {:.1}x its average daily volume of {:.0} (limit {:.1}x)",
+                        order.quantity,
+                        order.asset.symbol,
+                        order.quantity / adv,
+                        adv,
+                        multiple
+                    ));
+                }
+            }
+        }
+
+        if let (Some(multiple), Some(notional)) = (self.max_typical_multiple, notional) {
+            if let Some(typical) = self.typical_notional() {
+                if typical > 0.0 && notional > multiple * typical {
+                    return Some(format!(
+                        "Order for {} {} has notional {:.2}, {:.1}x the typical order of {:.2} (limit {:.1}x)",
+                        order.quantity,
+                        order.asset.symbol,
+                        notional,
+                        notional / typical,
+                        typical,
+                        multiple
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Default for FatFinger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradingControl for FatFinger {
+    fn validate_order_at(
+        &self,
+        order: &Order,
+        context: &Context,
+        prices: &dyn PriceLookup,
+    ) -> Result<()> {
+        let notional = self
+            .order_price(order, context, prices)
+            .map(|price| order.quantity * price);
+
+        let adv = self.order_adv(order, context);
+
+        if let Some(reason) = self.check(order, adv, notional) {
+            self.action.apply(self.name(), reason)?;
+        }
+
+        Ok(())
+    }
+
+    /// Only orders that were queued shape the typical order size
+    fn record_accepted(&self, order: &Order, context: &Context, prices: &dyn PriceLookup) {
+        let Some(price) = self.order_price(order, context, prices) else {
+            return;
+        };
+        let mut recent = self.recent_notionals.lock().unwrap();
+        recent.push_back(order.quantity * price);
+        while recent.len() > self.window {
+            recent.pop_front();
+        }
+    }
+
+    fn name(&self) -> &str {
+        "FatFinger"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -589,10 +1345,10 @@ mod tests {
         let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
 
         let valid_order = Order::market(asset.clone(), OrderSide::Buy, 50.0, Utc::now());
-        assert!(control.validate_order(&valid_order, &context).is_ok());
+        assert!(control.validate_order_at(&valid_order, &context, &NoPrices).is_ok());
 
         let invalid_order = Order::market(asset, OrderSide::Buy, 200.0, Utc::now());
-        assert!(control.validate_order(&invalid_order, &context).is_err());
+        assert!(control.validate_order_at(&invalid_order, &context, &NoPrices).is_err());
     }
 
     #[test]
@@ -604,11 +1360,11 @@ mod tests {
 
         // Buying is allowed
         let buy_order = Order::market(asset.clone(), OrderSide::Buy, 100.0, Utc::now());
-        assert!(control.validate_order(&buy_order, &context).is_ok());
+        assert!(control.validate_order_at(&buy_order, &context, &NoPrices).is_ok());
 
         // Selling more than owned is not allowed (short selling)
         let short_order = Order::market(asset, OrderSide::Sell, 100.0, Utc::now());
-        assert!(control.validate_order(&short_order, &context).is_err());
+        assert!(control.validate_order_at(&short_order, &context, &NoPrices).is_err());
     }
 
     #[test]
@@ -623,10 +1379,10 @@ mod tests {
         let allowed_asset = Asset::equity(2, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
 
         let restricted_order = Order::market(restricted_asset, OrderSide::Buy, 100.0, Utc::now());
-        assert!(control.validate_order(&restricted_order, &context).is_err());
+        assert!(control.validate_order_at(&restricted_order, &context, &NoPrices).is_err());
 
         let allowed_order = Order::market(allowed_asset, OrderSide::Buy, 100.0, Utc::now());
-        assert!(control.validate_order(&allowed_order, &context).is_ok());
+        assert!(control.validate_order_at(&allowed_order, &context, &NoPrices).is_ok());
     }
 
     #[test]
@@ -643,7 +1399,7 @@ mod tests {
         let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
         let order = Order::market(asset, OrderSide::Buy, 50.0, Utc::now());
 
-        assert!(manager.validate_order(&order, &context).is_ok());
+        assert!(manager.validate_order_at(&order, &context, &NoPrices).is_ok());
         assert!(manager.validate_account(&context).is_ok());
     }
 
@@ -659,7 +1415,7 @@ mod tests {
 
         let order = Order::market(tech_asset, OrderSide::Buy, 100.0, Utc::now());
         // Should pass for reasonable order
-        assert!(control.validate_order(&order, &context).is_ok());
+        assert!(control.validate_order_at(&order, &context, &NoPrices).is_ok());
 
         assert_eq!(control.get_sector(1), Some("Technology"));
         assert_eq!(control.get_sector(2), Some("Healthcare"));
@@ -679,10 +1435,10 @@ mod tests {
         let high_vol_asset = Asset::equity(2, "VOLATILE".to_string(), "NYSE".to_string(), start_date);
 
         let low_vol_order = Order::market(low_vol_asset, OrderSide::Buy, 100.0, Utc::now());
-        assert!(control.validate_order(&low_vol_order, &context).is_ok());
+        assert!(control.validate_order_at(&low_vol_order, &context, &NoPrices).is_ok());
 
         let high_vol_order = Order::market(high_vol_asset, OrderSide::Buy, 100.0, Utc::now());
-        assert!(control.validate_order(&high_vol_order, &context).is_err());
+        assert!(control.validate_order_at(&high_vol_order, &context, &NoPrices).is_err());
 
         assert_eq!(control.get_volatility(1), Some(0.30));
         assert_eq!(control.get_volatility(2), Some(0.60));
@@ -697,6 +1453,314 @@ mod tests {
 
         let order = Order::market(asset, OrderSide::Buy, 100.0, Utc::now());
         // Should validate concentration limits
-        assert!(control.validate_order(&order, &context).is_ok());
+        assert!(control.validate_order_at(&order, &context, &NoPrices).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_order() {
+        let control = DuplicateOrder::default();
+        let mut context = Context::new(100000.0);
+        context.timestamp = Utc::now();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        let first = Order::market(asset.clone(), OrderSide::Buy, 100.0, context.timestamp);
+        assert!(control.validate_order_at(&first, &context, &NoPrices).is_ok());
+        context.pending_orders.push(first);
+
+        let duplicate = Order::market(asset.clone(), OrderSide::Buy, 100.0, context.timestamp);
+        assert!(control.validate_order_at(&duplicate, &context, &NoPrices).is_err());
+
+        let different = Order::market(asset.clone(), OrderSide::Buy, 101.0, context.timestamp);
+        assert!(control.validate_order_at(&different, &context, &NoPrices).is_ok());
+
+        // The same order in a later bar is not a duplicate
+        context.timestamp += Duration::minutes(1);
+        let next_bar = Order::market(asset, OrderSide::Buy, 100.0, context.timestamp);
+        assert!(control.validate_order_at(&next_bar, &context, &NoPrices).is_ok());
+
+        let warn = DuplicateOrder::new(ControlAction::Warn);
+        context.pending_orders.push(next_bar.clone());
+        let repeat = Order::market(next_bar.asset.clone(), OrderSide::Buy, 100.0, context.timestamp);
+        assert!(warn.validate_order_at(&repeat, &context, &NoPrices).is_ok());
+    }
+
+    #[test]
+    fn test_fat_finger_adv() {
+        let mut control = FatFinger::new().with_max_adv_multiple(0.1);
+        control.update_adv(1, 10_000.0);
+
+        let context = Context::new(100000.0);
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let other = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+
+        let ok = Order::market(asset.clone(), OrderSide::Buy, 1_000.0, Utc::now());
+        assert!(control.validate_order_at(&ok, &context, &NoPrices).is_ok());
+
+        let fat = Order::market(asset, OrderSide::Sell, 1_001.0, Utc::now());
+        assert!(control.validate_order_at(&fat, &context, &NoPrices).is_err());
+
+        // No ADV known for the asset
+        let unknown = Order::market(other, OrderSide::Buy, 1_000_000.0, Utc::now());
+        assert!(control.validate_order_at(&unknown, &context, &NoPrices).is_ok());
+    }
+
+    #[test]
+    fn test_controls_read_market_stats() {
+        use crate::finance::MarketStatsService;
+        use crate::types::Bar;
+        use std::sync::Arc;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        // Three sessions at $50 with 10,000 shares, alternating 0% / 10% moves
+        let stats = Arc::new(MarketStatsService::new(5));
+        let day0 = Utc::now() - Duration::days(10);
+        for (i, close) in [50.0, 55.0, 50.0, 50.0].into_iter().enumerate() {
+            let ts = day0 + Duration::days(i as i64);
+            let bar = Bar::new(ts, close, close, close, close, 10_000.0);
+            stats.update(1, crate::types::SessionId::utc_label_of(ts), &bar);
+        }
+
+        let mut context = Context::new(100000.0);
+        context.set_market_stats(stats.clone());
+        let order = Order::market(asset, OrderSide::Buy, 600.0, Utc::now());
+
+        let vol = stats.volatility(1).unwrap();
+        let calm = VolatilityLimit::new(vol * 0.9);
+        assert!(calm.validate_order_at(&order, &context, &NoPrices).is_err());
+        let loose = VolatilityLimit::new(vol * 1.1);
+        assert!(loose.validate_order_at(&order, &context, &NoPrices).is_ok());
+
+        let fat_finger = FatFinger::new().with_max_adv_multiple(0.05);
+        assert!(fat_finger.validate_order_at(&order, &context, &NoPrices).is_err());
+    }
+
+    #[test]
+    fn test_notional_limits_use_current_prices() {
+        use crate::types::Bar;
+
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let context = Context::new(100000.0);
+
+        // 600 shares at $50 = $30,000, 30% of the portfolio
+        let mut data = BarData::new(10);
+        data.update(1, Bar::new(Utc::now(), 50.0, 50.0, 50.0, 50.0, 1_000.0));
+        let order = Order::market(asset.clone(), OrderSide::Buy, 600.0, Utc::now());
+
+        let position_limit = MaxPositionSize::percent(0.25);
+        assert!(position_limit.validate_order_at(&order, &context, &data).is_err());
+        let loose = MaxPositionSize::percent(0.35);
+        assert!(loose.validate_order_at(&order, &context, &data).is_ok());
+
+        let concentration = PositionConcentration::new(0.25);
+        assert!(concentration.validate_order_at(&order, &context, &data).is_err());
+
+        let mut sectors = SectorExposure::new(0.25);
+        sectors.register_asset(1, "Technology".to_string());
+        assert!(sectors.validate_order_at(&order, &context, &data).is_err());
+
+        let notional = MaxOrderSize::notional(25_000.0);
+        assert!(notional.validate_order_at(&order, &context, &data).is_err());
+
+        // A cheaper quote from another lookup passes
+        let prices: std::collections::HashMap<u64, Price> = [(1, 20.0)].into_iter().collect();
+        assert!(notional.validate_order_at(&order, &context, &prices).is_ok());
+        assert!(position_limit.validate_order_at(&order, &context, &prices).is_ok());
+
+        // Without a known price, percentage limits are skipped but a notional
+        // cap rejects the order it cannot value
+        assert!(position_limit.validate_order_at(&order, &context, &NoPrices).is_ok());
+        assert!(notional.validate_order_at(&order, &context, &NoPrices).is_err());
+        assert!(MaxOrderSize::shares(1_000.0).validate_order_at(&order, &context, &NoPrices).is_ok());
+    }
+
+    #[test]
+    fn test_fat_finger_typical_size() {
+        let mut control = FatFinger::new()
+            .with_max_typical_multiple(10.0)
+            .with_history(20, 3);
+        control.update_price(1, 50.0);
+
+        let context = Context::new(100000.0);
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        // Not enough history yet
+        let big = Order::market(asset.clone(), OrderSide::Buy, 1_000.0, Utc::now());
+        assert!(control.validate_order_at(&big, &context, &NoPrices).is_ok());
+        control.record_accepted(&big, &context, &NoPrices);
+        assert_eq!(control.typical_notional(), None);
+
+        for _ in 0..2 {
+            let order = Order::market(asset.clone(), OrderSide::Buy, 10.0, Utc::now());
+            assert!(control.validate_order_at(&order, &context, &NoPrices).is_ok());
+            control.record_accepted(&order, &context, &NoPrices);
+        }
+        assert_eq!(control.typical_notional(), Some(500.0));
+
+        // 100 x 50 = 5000 = 10x typical, allowed; 1000 shares is not
+        let edge = Order::limit(asset.clone(), OrderSide::Buy, 100.0, 50.0, Utc::now());
+        assert!(control.validate_order_at(&edge, &context, &NoPrices).is_ok());
+        assert!(control.validate_order_at(&big, &context, &NoPrices).is_err());
+
+        // Validation alone does not count toward the typical order
+        assert_eq!(control.typical_notional(), Some(500.0));
+
+        let warn = FatFinger::new()
+            .with_max_typical_multiple(10.0)
+            .with_history(20, 1)
+            .with_action(ControlAction::Warn);
+        let small = Order::limit(asset.clone(), OrderSide::Buy, 1.0, 50.0, Utc::now());
+        assert!(warn.validate_order_at(&small, &context, &NoPrices).is_ok());
+        warn.record_accepted(&small, &context, &NoPrices);
+        let big = Order::limit(asset, OrderSide::Buy, 1_000.0, 50.0, Utc::now());
+        assert!(warn.validate_order_at(&big, &context, &NoPrices).is_ok());
+    }
+
+    #[test]
+    fn test_pattern_day_trader() {
+        let control = PatternDayTrader::new();
+        let mut context = Context::new(20_000.0);
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let sell = Order::market(asset.clone(), OrderSide::Sell, 10.0, Utc::now());
+
+        // Three round trips on three sessions, then a fourth position opened
+        for day in 0..4 {
+            context.day_trades.start_session(start_date + Duration::days(day));
+            context.day_trades.record(1, 0.0, 10.0);
+            if day < 3 {
+                context.day_trades.record(1, 10.0, -10.0);
+            }
+        }
+        let position = crate::finance::Position::new(asset.clone(), 10.0, 1_000.0, 100.0);
+        context.portfolio.positions.insert(1, position);
+        assert_eq!(context.day_trades().count(PDT_WINDOW_SESSIONS), 3);
+        assert!(control.validate_order_at(&sell, &context, &NoPrices).is_err());
+
+        // Buying more is not a day trade, and neither is selling tomorrow
+        let buy = Order::market(asset, OrderSide::Buy, 10.0, Utc::now());
+        assert!(control.validate_order_at(&buy, &context, &NoPrices).is_ok());
+        context.day_trades.start_session(start_date + Duration::days(4));
+        assert!(control.validate_order_at(&sell, &context, &NoPrices).is_ok());
+
+        // Shares opened today are limited again, unless the account is above the minimum
+        context.day_trades.record(1, 10.0, 10.0);
+        assert!(control.validate_order_at(&sell, &context, &NoPrices).is_err());
+        assert!(control.with_min_equity(10_000.0).validate_order_at(&sell, &context, &NoPrices).is_ok());
+    }
+
+    #[test]
+    fn test_notional_controls_are_side_aware() {
+        let mut context = Context::new(100_000.0);
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let other = Asset::equity(2, "MSFT".to_string(), "NASDAQ".to_string(), start_date);
+        // 400 shares marked at $50, now trading at $100: 40% of the portfolio
+        let position = crate::finance::Position::new(asset.clone(), 400.0, 20_000.0, 50.0);
+        context.portfolio.positions.insert(1, position);
+        let prices: std::collections::HashMap<u64, Price> = [(1, 100.0), (2, 100.0)].into_iter().collect();
+
+        let concentration = PositionConcentration::new(0.25);
+        let mut sectors = SectorExposure::new(0.30);
+        sectors.register_asset(1, "Technology".to_string());
+        sectors.register_asset(2, "Technology".to_string());
+        let size = MaxPositionSize::both(300.0, 0.25);
+        let controls: [&dyn TradingControl; 3] = [&concentration, &sectors, &size];
+
+        // Trimming is allowed even though the position stays over every limit
+        let trim = Order::market(asset.clone(), OrderSide::Sell, 50.0, Utc::now());
+        let add = Order::market(asset.clone(), OrderSide::Buy, 10.0, Utc::now());
+        // Selling through zero to a 400 share short is as concentrated as the long
+        let flip = Order::market(asset, OrderSide::Sell, 800.0, Utc::now());
+        for control in controls {
+            assert!(control.validate_order_at(&trim, &context, &prices).is_ok(), "{}", control.name());
+            assert!(control.validate_order_at(&add, &context, &prices).is_err(), "{}", control.name());
+            assert!(control.validate_order_at(&flip, &context, &prices).is_err(), "{}", control.name());
+        }
+
+        // Shorting a second tech name adds to the sector's gross exposure
+        let short = Order::market(other, OrderSide::Sell, 200.0, Utc::now());
+        context.portfolio.positions.get_mut(&1).unwrap().quantity = 250.0;
+        assert!(sectors.validate_order_at(&short, &context, &prices).is_err());
+        assert!(concentration.validate_order_at(&short, &context, &prices).is_ok());
+    }
+
+    /// Keeps trying to buy one share a bar and records what happens
+    struct Persistent {
+        asset: Asset,
+        bars: usize,
+        rejected: Vec<usize>,
+        quantity: f64,
+        halted: bool,
+    }
+
+    impl crate::algorithm::Algorithm for Persistent {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            self.bars += 1;
+            let quantity = if self.bars == 1 { 500.0 } else { 1.0 };
+            if let Err(ZiplineError::TradingHalted { .. }) = context.order(self.asset.clone(), quantity) {
+                self.rejected.push(self.bars);
+            }
+            Ok(())
+        }
+
+        fn analyze(&mut self, context: &Context) -> Result<()> {
+            self.quantity = context.portfolio.positions.get(&self.asset.id).map_or(0.0, |p| p.quantity);
+            self.halted = context.trading_halt().is_some();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_max_drawdown_halts_and_liquidates() {
+        use crate::calendar::NYSECalendar;
+        use crate::data::InMemoryDataSource;
+        use crate::engine::{EngineEvent, SimulationEngine};
+        use crate::types::Bar;
+        use chrono::{Duration, TimeZone};
+        use std::sync::Arc;
+
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        // 500 shares bought at 100 lose $25,000 (25%) on the third bar
+        for (offset, price) in [(0, 100.0), (1, 100.0), (2, 50.0), (3, 50.0), (6, 60.0)] {
+            source.add_bar(1, Bar::new(start + Duration::days(offset), price, price, price, price, 1e6));
+        }
+        let end = start + Duration::days(6);
+        source.set_date_range(start, end);
+
+        let mut controls = ControlManager::new();
+        controls.add_account_control(Box::new(MaxDrawdown::new(0.2).with_liquidation()));
+        let halts = Arc::new(Mutex::new(Vec::new()));
+        let seen = halts.clone();
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()))
+            .with_trading_controls(Arc::new(controls))
+            .with_event_handler(move |event| {
+                if let EngineEvent::TradingHalted { halt } = event {
+                    seen.lock().unwrap().push(halt.clone());
+                }
+            });
+        let mut algorithm = Persistent { asset, bars: 0, rejected: Vec::new(), quantity: 0.0, halted: false };
+        engine.run(&mut algorithm, &source, start, end).unwrap();
+
+        // The third bar's order was queued before the halt and cancelled by it
+        assert_eq!(algorithm.rejected, vec![4, 5]);
+        assert_eq!(algorithm.quantity, 0.0);
+        assert!(algorithm.halted);
+        let halts = halts.lock().unwrap();
+        assert_eq!(halts.len(), 1);
+        assert_eq!(halts[0].control, "MaxDrawdown");
+        assert!(halts[0].liquidated);
+        assert_eq!(halts[0].timestamp, start + Duration::days(2));
     }
 }
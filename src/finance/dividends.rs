@@ -0,0 +1,112 @@
+//! Cash dividends on held positions
+//!
+//! With corporate actions installed, the engine pays each cash dividend at
+//! the start of its ex-date session on the position held at the previous
+//! close: long positions are credited, short positions charged. Under
+//! [`DividendPolicy::Reinvest`] a long position's dividend is then spent on
+//! the paying asset at that session's first bar, so the new shares open
+//! their own lot in the ledger at the reinvestment price.
+//!
+//! ```ignore
+//! let engine = SimulationEngine::default_engine(calendar)
+//!     .with_dividends(Arc::new(adjustments), DividendPolicy::Reinvest);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// What happens to cash dividends once paid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DividendPolicy {
+    /// Keep dividends as cash
+    #[default]
+    Cash,
+    /// Buy more of the paying asset with each dividend (DRIP)
+    Reinvest,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, Context};
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::adjustments::{Adjustment, AdjustmentKind, AdjustmentReader, DividendKind};
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::SimulationEngine;
+    use crate::error::Result;
+    use crate::types::{Bar, Cash};
+    use chrono::{Duration, TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Buys 100 shares on the first bar and records cash and lots at the end
+    struct Income {
+        asset: Asset,
+        bought: bool,
+        cash: Cash,
+        lots: Vec<(f64, f64)>,
+    }
+
+    impl Algorithm for Income {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            if !self.bought {
+                context.order(self.asset.clone(), 100.0)?;
+                self.bought = true;
+            }
+            Ok(())
+        }
+
+        fn analyze(&mut self, context: &Context) -> Result<()> {
+            self.cash = context.portfolio.cash;
+            self.lots = context
+                .portfolio
+                .ledger()
+                .get_position(self.asset.id)
+                .map(|p| p.lots().map(|lot| (lot.quantity, lot.cost_basis)).collect())
+                .unwrap_or_default();
+            Ok(())
+        }
+    }
+
+    fn run(policy: DividendPolicy) -> Income {
+        let listed = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "KO".to_string(), "NYSE".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for (offset, price) in [(0, 50.0), (1, 50.0), (2, 40.0), (3, 40.0)] {
+            source.add_bar(1, Bar::new(start + Duration::days(offset), price, price, price, price, 1e6));
+        }
+        let end = start + Duration::days(3);
+        source.set_date_range(start, end);
+
+        // $2 a share, ex on the third session
+        let mut actions = AdjustmentReader::new();
+        let kind = AdjustmentKind::Dividend { amount: 2.0, kind: DividendKind::Cash };
+        actions.add_adjustment(Adjustment::new(1, start + Duration::days(2), kind));
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()))
+            .with_dividends(Arc::new(actions), policy);
+        let mut algorithm = Income { asset, bought: false, cash: 0.0, lots: Vec::new() };
+        let results = engine.run(&mut algorithm, &source, start, end).unwrap();
+        let dividends: f64 = results.attribution.table().iter().map(|(_, pnl)| pnl.dividends).sum();
+        assert_eq!(dividends, 200.0);
+        algorithm
+    }
+
+    #[test]
+    fn test_dividends_paid_as_cash_or_reinvested() {
+        let cash = run(DividendPolicy::Cash);
+        assert_eq!(cash.cash, 100_000.0 - 5_000.0 + 200.0);
+        assert_eq!(cash.lots, vec![(100.0, 50.0)]);
+
+        // The $200 buys 5 shares at the ex-date price in a lot of their own
+        let drip = run(DividendPolicy::Reinvest);
+        assert!((drip.cash - 95_000.0).abs() < 1e-6);
+        assert_eq!(drip.lots.len(), 2);
+        assert_eq!(drip.lots[0], (100.0, 50.0));
+        assert!((drip.lots[1].0 - 5.0).abs() < 1e-9);
+        assert_eq!(drip.lots[1].1, 40.0);
+    }
+}
@@ -0,0 +1,188 @@
+//! Daily exposure by side, sector and asset class
+//!
+//! The engine's [`ExposureTracker`] marks the portfolio after every bar and
+//! keeps the last mark of each session, so results carry one
+//! [`ExposureSnapshot`] per day. Sectors come from a point-in-time
+//! [`ClassificationMap`] of GICS codes when one is installed; positions in
+//! unclassified assets are grouped under [`UNCLASSIFIED`].
+//!
+//! ```ignore
+//! let engine = SimulationEngine::default_engine(calendar)
+//!     .with_sector_classification(Arc::new(ClassificationMap::load(path)?));
+//! let results = engine.run(&mut algorithm, &source, start, end)?;
+//! let tech = results.exposure.last().unwrap().by_sector["Information Technology"];
+//! ```
+
+use crate::asset::AssetType;
+use crate::finance::portfolio::Portfolio;
+use crate::pipeline::classifiers::{gics_sector_name, ClassificationMap};
+use crate::types::Cash;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Sector of positions whose asset has no classification
+pub const UNCLASSIFIED: &str = "Unclassified";
+
+/// Long and short market value of a group of positions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Exposure {
+    /// Market value of long positions
+    pub long: Cash,
+    /// Absolute market value of short positions
+    pub short: Cash,
+}
+
+impl Exposure {
+    fn add(&mut self, value: Cash) {
+        if value >= 0.0 {
+            self.long += value;
+        } else {
+            self.short -= value;
+        }
+    }
+
+    /// Long plus short market value
+    pub fn gross(&self) -> Cash {
+        self.long + self.short
+    }
+
+    /// Long less short market value
+    pub fn net(&self) -> Cash {
+        self.long - self.short
+    }
+}
+
+/// Exposure at the close of one session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExposureSnapshot {
+    pub session: NaiveDate,
+    pub portfolio_value: Cash,
+    /// Whole portfolio
+    pub total: Exposure,
+    /// By GICS sector name, or the code if it names no sector
+    pub by_sector: BTreeMap<String, Exposure>,
+    pub by_asset_class: BTreeMap<AssetType, Exposure>,
+}
+
+impl ExposureSnapshot {
+    /// Gross exposure as a fraction of portfolio value
+    pub fn gross_leverage(&self) -> f64 {
+        self.leverage(self.total.gross())
+    }
+
+    /// Net exposure as a fraction of portfolio value
+    pub fn net_leverage(&self) -> f64 {
+        self.leverage(self.total.net())
+    }
+
+    fn leverage(&self, exposure: Cash) -> f64 {
+        if self.portfolio_value == 0.0 {
+            0.0
+        } else {
+            exposure / self.portfolio_value
+        }
+    }
+}
+
+/// Records a portfolio's exposure once per session
+#[derive(Debug, Clone, Default)]
+pub struct ExposureTracker {
+    /// Sector codes by asset, if sectors are broken out
+    sectors: Option<Arc<ClassificationMap>>,
+    snapshots: Vec<ExposureSnapshot>,
+}
+
+impl ExposureTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Break exposure out by the GICS sector in `map`
+    pub fn with_sectors(mut self, map: Arc<ClassificationMap>) -> Self {
+        self.sectors = Some(map);
+        self
+    }
+
+    /// Mark `portfolio` on `session`, replacing an earlier mark of the same session
+    pub fn record(&mut self, session: NaiveDate, portfolio: &Portfolio) {
+        let mut snapshot = ExposureSnapshot {
+            session,
+            portfolio_value: portfolio.portfolio_value,
+            ..Default::default()
+        };
+        for position in portfolio.positions.values() {
+            let value = position.market_value();
+            snapshot.total.add(value);
+            snapshot
+                .by_asset_class
+                .entry(position.asset.asset_type)
+                .or_default()
+                .add(value);
+            if let Some(map) = &self.sectors {
+                let sector = map
+                    .code_at(position.asset.id, session)
+                    .and_then(|code| code.get(..2))
+                    .map(|code| gics_sector_name(code).unwrap_or(code))
+                    .unwrap_or(UNCLASSIFIED);
+                snapshot.by_sector.entry(sector.to_string()).or_default().add(value);
+            }
+        }
+
+        match self.snapshots.last_mut() {
+            Some(last) if last.session == session => *last = snapshot,
+            _ => self.snapshots.push(snapshot),
+        }
+    }
+
+    /// One snapshot per session recorded, oldest first
+    pub fn snapshots(&self) -> &[ExposureSnapshot] {
+        &self.snapshots
+    }
+
+    /// Forget every snapshot, keeping the sector map
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+
+    #[test]
+    fn test_exposure_by_side_sector_and_class() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let mut map = ClassificationMap::new();
+        map.insert(1, listed, "45102010");
+        map.insert(2, listed, "45202030");
+
+        let mut portfolio = Portfolio::new(100_000.0);
+        for (id, quantity, price) in [(1, 100.0, 50.0), (2, -50.0, 40.0), (3, 10.0, 100.0)] {
+            let asset = Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed);
+            let position = crate::finance::Position::new(asset, quantity, quantity * price, price);
+            portfolio.positions.insert(id, position);
+        }
+        portfolio.positions.get_mut(&3).unwrap().asset.asset_type = AssetType::Crypto;
+        portfolio.update_value(chrono::Utc::now());
+
+        let mut tracker = ExposureTracker::new().with_sectors(Arc::new(map));
+        tracker.record(day(2), &portfolio);
+        tracker.record(day(2), &portfolio);
+        tracker.record(day(3), &portfolio);
+        assert_eq!(tracker.snapshots().len(), 2);
+
+        let snapshot = &tracker.snapshots()[1];
+        assert_eq!(snapshot.total, Exposure { long: 6_000.0, short: 2_000.0 });
+        assert_eq!((snapshot.total.gross(), snapshot.total.net()), (8_000.0, 4_000.0));
+        let tech = snapshot.by_sector["Information Technology"];
+        assert_eq!((tech.long, tech.short, tech.net()), (5_000.0, 2_000.0, 3_000.0));
+        assert_eq!(snapshot.by_sector[UNCLASSIFIED].long, 1_000.0);
+        assert_eq!(snapshot.by_asset_class[&AssetType::Crypto].gross(), 1_000.0);
+        assert_eq!(snapshot.by_asset_class[&AssetType::Equity].gross(), 7_000.0);
+        assert!((snapshot.gross_leverage() - 8_000.0 / snapshot.portfolio_value).abs() < 1e-12);
+    }
+}
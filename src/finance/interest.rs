@@ -0,0 +1,169 @@
+//! Interest on cash balances
+//!
+//! An [`InterestModel`] gives the annualized rate paid on idle (positive)
+//! cash and the rate charged on borrowed (negative) cash. The engine accrues
+//! interest once per session on the cash held at the previous session's
+//! close, for every calendar day in between, so Friday's balance earns three
+//! days over a weekend. Rates are simple ACT/360, the money-market convention.
+//!
+//! ```ignore
+//! // 4% on cash, 7% on margin loans
+//! let engine = SimulationEngine::default_engine(calendar)
+//!     .with_interest_model(Arc::new(FixedRates::new(0.04, 0.07)));
+//! ```
+
+use crate::types::Cash;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// Days in the interest year
+pub const INTEREST_DAYS_PER_YEAR: f64 = 360.0;
+
+/// Rates paid and charged on cash
+pub trait InterestModel: Debug + Send + Sync {
+    /// Annualized rates in effect on `date`: (on positive cash, on negative cash)
+    fn rates(&self, date: NaiveDate) -> (f64, f64);
+
+    /// Interest on holding `cash` for `days` days from `date`
+    ///
+    /// Positive on credit balances, negative (a charge) on debit balances.
+    fn accrue(&self, cash: Cash, date: NaiveDate, days: i64) -> Cash {
+        let (cash_rate, margin_rate) = self.rates(date);
+        let rate = if cash >= 0.0 { cash_rate } else { margin_rate };
+        cash * rate * days as f64 / INTEREST_DAYS_PER_YEAR
+    }
+}
+
+/// Constant rates for the whole run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedRates {
+    /// Annualized rate paid on positive cash
+    pub cash_rate: f64,
+    /// Annualized rate charged on negative cash
+    pub margin_rate: f64,
+}
+
+impl FixedRates {
+    pub fn new(cash_rate: f64, margin_rate: f64) -> Self {
+        Self {
+            cash_rate,
+            margin_rate,
+        }
+    }
+}
+
+impl InterestModel for FixedRates {
+    fn rates(&self, _date: NaiveDate) -> (f64, f64) {
+        (self.cash_rate, self.margin_rate)
+    }
+}
+
+/// Rates that change over time, e.g. following a policy rate
+///
+/// Each rate holds from its date until the next; dates before the first use
+/// the first rate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateCurve {
+    rates: BTreeMap<NaiveDate, (f64, f64)>,
+}
+
+impl RateCurve {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the rates in effect from `date`
+    pub fn with_rate(mut self, date: NaiveDate, cash_rate: f64, margin_rate: f64) -> Self {
+        self.rates.insert(date, (cash_rate, margin_rate));
+        self
+    }
+
+    /// Curve whose margin rate is the cash rate plus a fixed spread
+    pub fn from_cash_rates(rates: impl IntoIterator<Item = (NaiveDate, f64)>, margin_spread: f64) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|(date, rate)| (date, (rate, rate + margin_spread)))
+                .collect(),
+        }
+    }
+}
+
+impl InterestModel for RateCurve {
+    fn rates(&self, date: NaiveDate) -> (f64, f64) {
+        self.rates
+            .range(..=date)
+            .next_back()
+            .or_else(|| self.rates.iter().next())
+            .map(|(_, rates)| *rates)
+            .unwrap_or((0.0, 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, Context};
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::SimulationEngine;
+    use crate::error::Result;
+    use crate::types::Bar;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Borrows to buy on the second bar
+    struct Leverage {
+        asset: Asset,
+        cash: Vec<Cash>,
+    }
+
+    impl Algorithm for Leverage {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            self.cash.push(context.portfolio.cash);
+            if self.cash.len() == 2 {
+                context.order(self.asset.clone(), 1_500.0)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_interest_accrues_daily_in_both_directions() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let curve = RateCurve::from_cash_rates([(day(1), 0.036), (day(10), 0.072)], 0.036);
+        assert_eq!(curve.rates(day(9)), (0.036, 0.072));
+        assert_eq!(curve.rates(day(12)).0, 0.072);
+        assert!((curve.accrue(-100_000.0, day(12), 10) - -300.0).abs() < 1e-9);
+
+        // Thursday to Tuesday: the Friday balance earns over the weekend
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 4, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for offset in [0, 1, 4, 5] {
+            source.add_bar(1, Bar::new(start + Duration::days(offset), 100.0, 100.0, 100.0, 100.0, 1e6));
+        }
+        let end = start + Duration::days(5);
+        source.set_date_range(start, end);
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()))
+            .with_interest_model(Arc::new(FixedRates::new(0.036, 0.072)));
+        let mut algorithm = Leverage { asset, cash: Vec::new() };
+        let results = engine.run(&mut algorithm, &source, start, end).unwrap();
+
+        // $100,000 earns $10 a day; after buying $150,000 of stock on Friday
+        // the $50,000 loan costs $10 a day
+        let cash = &algorithm.cash;
+        assert!((cash[1] - (100_000.0 + 10.0)).abs() < 1e-6);
+        let loan = cash[1] - 150_000.0;
+        assert!((cash[2] - loan * (1.0 + 0.072 * 3.0 / 360.0)).abs() < 1e-6);
+        assert!((results.total_interest() - (10.0 + (cash[3] - loan))).abs() < 1e-6);
+        assert!(results.total_interest() < 0.0);
+    }
+}
@@ -2,13 +2,22 @@
 //!
 //! This module provides a comprehensive ledger system for tracking all
 //! trading activity, calculating P&L, and maintaining cost basis.
+//!
+//! Every [`Portfolio`] keeps a ledger of its fills and derives its positions
+//! and cash from it. [`Ledger::reconcile`] checks that a portfolio's positions
+//! agree with a ledger on quantity and cost basis and reports the first
+//! [`LedgerDivergence`] it finds.
 
 use crate::error::{Result, ZiplineError};
+use crate::finance::portfolio::Portfolio;
+use crate::finance::tax_lots::{ClosedLot, TaxLots, TaxReport};
 use crate::finance::transaction::Transaction;
 use crate::order::OrderSide;
+use crate::types::QUANTITY_TOLERANCE;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::fmt;
 
 /// Cost basis calculation method
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -21,6 +30,23 @@ pub enum CostBasisMethod {
     Average,
 }
 
+/// Identifies a lot: the id of the transaction that opened it
+pub type LotId = uuid::Uuid;
+
+/// Shares taken from one lot, as chosen for or closed by a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LotSelection {
+    pub lot_id: LotId,
+    /// Shares, unsigned
+    pub quantity: f64,
+}
+
+impl LotSelection {
+    pub fn new(lot_id: LotId, quantity: f64) -> Self {
+        Self { lot_id, quantity }
+    }
+}
+
 /// Lot - represents a purchase of shares with specific cost basis
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lot {
@@ -48,9 +74,16 @@ impl Lot {
     pub fn total_cost(&self) -> f64 {
         self.quantity * self.cost_basis
     }
+
+    /// Id by which the lot can be sold specifically
+    pub fn id(&self) -> LotId {
+        self.transaction_id
+    }
 }
 
 /// Position with lot tracking for cost basis
+///
+/// Short positions hold lots of negative quantity.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LedgerPosition {
     /// Asset ID
@@ -63,6 +96,10 @@ pub struct LedgerPosition {
     average_cost: f64,
     /// Cost basis method
     cost_basis_method: CostBasisMethod,
+    /// Cash paid for buys less cash received from sells since the position
+    /// was last flat, the cost basis [`Portfolio`] positions carry
+    #[serde(default)]
+    net_cost: f64,
 }
 
 impl LedgerPosition {
@@ -73,23 +110,13 @@ impl LedgerPosition {
             lots: VecDeque::new(),
             average_cost: 0.0,
             cost_basis_method,
+            net_cost: 0.0,
         }
     }
 
     /// Add a buy transaction (creates new lot)
     pub fn add_shares(&mut self, quantity: f64, price: f64, dt: DateTime<Utc>, txn_id: uuid::Uuid) {
-        let lot = Lot::new(quantity, price, dt, txn_id);
-
-        // Update average cost
-        let total_cost = self.quantity * self.average_cost + lot.total_cost();
-        self.quantity += quantity;
-        self.average_cost = if self.quantity > 0.0 {
-            total_cost / self.quantity
-        } else {
-            0.0
-        };
-
-        self.lots.push_back(lot);
+        self.open(quantity, price, dt, txn_id);
     }
 
     /// Remove shares (sell transaction) and calculate realized P&L
@@ -101,62 +128,193 @@ impl LedgerPosition {
             )));
         }
 
+        Ok(self.close(quantity, sale_price, &mut Vec::new()))
+    }
+
+    /// Remove shares from one lot (specific identification), returning realized P&L
+    ///
+    /// The P&L is measured against the lot's own cost, whatever the
+    /// position's cost basis method.
+    pub fn remove_shares_from_lot(&mut self, lot_id: LotId, quantity: f64, sale_price: f64) -> Result<f64> {
+        self.check_selection(&[LotSelection::new(lot_id, quantity)])?;
+        Ok(self.close_lot(lot_id, quantity, sale_price, &mut Vec::new()))
+    }
+
+    /// Method used to choose the lots a trade closes
+    pub fn cost_basis_method(&self) -> CostBasisMethod {
+        self.cost_basis_method
+    }
+
+    /// Change the method used for later trades
+    pub fn set_cost_basis_method(&mut self, method: CostBasisMethod) {
+        self.cost_basis_method = method;
+    }
+
+    /// Check that every selected lot is held with at least the shares selected
+    fn check_selection(&self, selection: &[LotSelection]) -> Result<()> {
+        for chosen in selection {
+            let held = self
+                .lots
+                .iter()
+                .find(|lot| lot.id() == chosen.lot_id)
+                .map(|lot| lot.quantity.abs())
+                .ok_or_else(|| {
+                    ZiplineError::InvalidOrder(format!(
+                        "No lot {} in position {}",
+                        chosen.lot_id, self.asset_id
+                    ))
+                })?;
+            let selected: f64 = selection
+                .iter()
+                .filter(|other| other.lot_id == chosen.lot_id)
+                .map(|other| other.quantity)
+                .sum();
+            if chosen.quantity <= 0.0 || selected > held + QUANTITY_TOLERANCE {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "Cannot take {} shares from lot {}, it holds {}",
+                    selected, chosen.lot_id, held
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reduce the position by `quantity` shares of one checked lot, returning realized P&L
+    fn close_lot(&mut self, lot_id: LotId, quantity: f64, price: f64, closed: &mut Vec<ClosedLot>) -> f64 {
+        let side = self.quantity.signum();
+        let Some(index) = self.lots.iter().position(|lot| lot.id() == lot_id) else {
+            return 0.0;
+        };
+        let lot = &mut self.lots[index];
+        let quantity = quantity.min(lot.quantity.abs());
+        closed.push(ClosedLot::part(lot, side * quantity));
+        let realized_pnl = side * quantity * (price - lot.cost_basis);
+        lot.quantity -= side * quantity;
+        if lot.quantity.abs() <= QUANTITY_TOLERANCE {
+            self.lots.remove(index);
+        }
+        self.reduce(side, quantity, price);
+        realized_pnl
+    }
+
+    /// Apply a signed trade, returning the realized P&L if it reduced the position
+    ///
+    /// A trade larger than the position it reduces closes it and opens the
+    /// remainder on the other side, so sells past zero open a short.
+    pub fn apply(&mut self, amount: f64, price: f64, dt: DateTime<Utc>, txn_id: uuid::Uuid) -> Option<f64> {
+        self.apply_lots(amount, price, dt, txn_id, &mut Vec::new())
+    }
+
+    /// [`Self::apply`], adding the lots the trade closed to `closed`
+    fn apply_lots(
+        &mut self,
+        amount: f64,
+        price: f64,
+        dt: DateTime<Utc>,
+        txn_id: uuid::Uuid,
+        closed: &mut Vec<ClosedLot>,
+    ) -> Option<f64> {
+        let mut remaining = amount;
+        let mut realized_pnl = None;
+
+        if self.quantity * amount < 0.0 {
+            let closing = amount.abs().min(self.quantity.abs());
+            realized_pnl = Some(self.close(closing, price, closed));
+            remaining -= closing * amount.signum();
+        }
+        if remaining.abs() > QUANTITY_TOLERANCE {
+            self.open(remaining, price, dt, txn_id);
+        }
+
+        realized_pnl
+    }
+
+    /// Extend the position by `amount` signed shares
+    fn open(&mut self, amount: f64, price: f64, dt: DateTime<Utc>, txn_id: uuid::Uuid) {
+        let lot = Lot::new(amount, price, dt, txn_id);
+
+        // Update average cost
+        let total_cost = self.quantity.abs() * self.average_cost + amount.abs() * price;
+        self.quantity += amount;
+        self.average_cost = if self.quantity != 0.0 {
+            total_cost / self.quantity.abs()
+        } else {
+            0.0
+        };
+
+        self.net_cost += amount * price;
+        self.lots.push_back(lot);
+    }
+
+    /// Reduce the position by `quantity` shares toward zero, returning realized P&L
+    ///
+    /// The parts of lots closed are added to `closed`.
+    fn close(&mut self, quantity: f64, price: f64, closed: &mut Vec<ClosedLot>) -> f64 {
+        let side = self.quantity.signum();
         let mut realized_pnl = 0.0;
         let mut remaining = quantity;
 
         match self.cost_basis_method {
-            CostBasisMethod::FIFO => {
-                // Remove from front (oldest first)
+            CostBasisMethod::FIFO | CostBasisMethod::LIFO => {
+                // FIFO removes the oldest lots first, LIFO the newest
                 while remaining > 0.0 && !self.lots.is_empty() {
-                    let lot = self.lots.front_mut().unwrap();
-
-                    if lot.quantity <= remaining {
-                        // Use entire lot
-                        realized_pnl += lot.quantity * (sale_price - lot.cost_basis);
-                        remaining -= lot.quantity;
-                        self.lots.pop_front();
+                    let lot = if self.cost_basis_method == CostBasisMethod::FIFO {
+                        self.lots.front_mut().unwrap()
                     } else {
-                        // Partial lot
-                        realized_pnl += remaining * (sale_price - lot.cost_basis);
-                        lot.quantity -= remaining;
-                        remaining = 0.0;
-                    }
-                }
-            }
-            CostBasisMethod::LIFO => {
-                // Remove from back (newest first)
-                while remaining > 0.0 && !self.lots.is_empty() {
-                    let lot = self.lots.back_mut().unwrap();
+                        self.lots.back_mut().unwrap()
+                    };
 
-                    if lot.quantity <= remaining {
+                    if lot.quantity.abs() <= remaining {
                         // Use entire lot
-                        realized_pnl += lot.quantity * (sale_price - lot.cost_basis);
-                        remaining -= lot.quantity;
-                        self.lots.pop_back();
+                        closed.push(ClosedLot::part(lot, lot.quantity));
+                        realized_pnl += lot.quantity * (price - lot.cost_basis);
+                        remaining -= lot.quantity.abs();
+                        if self.cost_basis_method == CostBasisMethod::FIFO {
+                            self.lots.pop_front();
+                        } else {
+                            self.lots.pop_back();
+                        }
                     } else {
                         // Partial lot
-                        realized_pnl += remaining * (sale_price - lot.cost_basis);
-                        lot.quantity -= remaining;
+                        closed.push(ClosedLot::part(lot, side * remaining));
+                        realized_pnl += side * remaining * (price - lot.cost_basis);
+                        lot.quantity -= side * remaining;
                         remaining = 0.0;
                     }
                 }
             }
             CostBasisMethod::Average => {
                 // Use average cost
-                realized_pnl = quantity * (sale_price - self.average_cost);
+                realized_pnl = side * quantity * (price - self.average_cost);
 
                 // Remove quantity proportionally from lots
-                let removal_ratio = quantity / self.quantity;
+                let removal_ratio = quantity / self.quantity.abs();
                 for lot in &mut self.lots {
+                    closed.push(ClosedLot {
+                        cost_basis: self.average_cost,
+                        ..ClosedLot::part(lot, lot.quantity * removal_ratio)
+                    });
                     lot.quantity *= 1.0 - removal_ratio;
                 }
                 // Clean up zero-quantity lots
-                self.lots.retain(|lot| lot.quantity > f64::EPSILON);
+                self.lots.retain(|lot| lot.quantity.abs() > QUANTITY_TOLERANCE);
             }
         }
 
-        self.quantity -= quantity;
-        Ok(realized_pnl)
+        self.reduce(side, quantity, price);
+        realized_pnl
+    }
+
+    /// Take `quantity` shares already removed from the lots off the position
+    fn reduce(&mut self, side: f64, quantity: f64, price: f64) {
+        self.quantity -= side * quantity;
+        if self.quantity.abs() < QUANTITY_TOLERANCE {
+            self.quantity = 0.0;
+            self.net_cost = 0.0;
+            self.lots.clear();
+        } else {
+            self.net_cost -= side * quantity * price;
+        }
     }
 
     /// Calculate unrealized P&L at current price
@@ -173,6 +331,72 @@ impl LedgerPosition {
     pub fn market_value(&self, current_price: f64) -> f64 {
         self.quantity * current_price
     }
+
+    /// Net cash paid for the position since it was last flat
+    pub fn net_cost(&self) -> f64 {
+        self.net_cost
+    }
+
+    /// Open lots, oldest first
+    pub fn lots(&self) -> impl Iterator<Item = &Lot> {
+        self.lots.iter()
+    }
+
+    /// Shares held across all lots
+    pub fn lot_quantity(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.quantity).sum()
+    }
+}
+
+/// Position field on which a ledger and portfolio disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconcileField {
+    Quantity,
+    CostBasis,
+}
+
+/// First disagreement found by [`Ledger::reconcile`]
+#[derive(Debug, Clone)]
+pub struct LedgerDivergence {
+    /// Asset whose position disagrees
+    pub asset_id: u64,
+    /// Field that disagrees
+    pub field: ReconcileField,
+    /// Ledger's value
+    pub ledger: f64,
+    /// Portfolio's value
+    pub portfolio: f64,
+    /// Transactions recorded in the ledger so far
+    pub transaction_count: usize,
+    /// The asset's most recent transaction, if any
+    pub last_transaction: Option<Transaction>,
+}
+
+impl fmt::Display for LedgerDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self.field {
+            ReconcileField::Quantity => "quantity",
+            ReconcileField::CostBasis => "cost basis",
+        };
+        write!(
+            f,
+            "Ledger and portfolio disagree on the {} of asset {}: ledger {}, portfolio {} (difference {}) after {} transaction(s)",
+            field,
+            self.asset_id,
+            self.ledger,
+            self.portfolio,
+            self.portfolio - self.ledger,
+            self.transaction_count
+        )?;
+        if let Some(txn) = &self.last_transaction {
+            write!(
+                f,
+                "; last trade in the asset: {:?} {} @ {} at {} (order {})",
+                txn.side, txn.amount, txn.price, txn.dt, txn.order_id
+            )?;
+        }
+        Ok(())
+    }
 }
 
 /// P&L Summary
@@ -251,6 +475,18 @@ pub struct Ledger {
     pnl_summary: PnLSummary,
     /// Transaction index by asset
     transactions_by_asset: HashMap<u64, Vec<usize>>,
+    /// Cash received from trades less cash paid, net of commissions
+    #[serde(default)]
+    trade_cash: f64,
+    /// Cash adjustments made outside of trades
+    #[serde(default)]
+    cash_adjustments: f64,
+    /// Realized gains by tax lot, when tracked
+    #[serde(default)]
+    tax_lots: Option<TaxLots>,
+    /// Cost basis methods of assets that do not use the ledger's own
+    #[serde(default)]
+    cost_basis_overrides: HashMap<u64, CostBasisMethod>,
 }
 
 impl Ledger {
@@ -262,38 +498,116 @@ impl Ledger {
             cost_basis_method,
             pnl_summary: PnLSummary::new(),
             transactions_by_asset: HashMap::new(),
+            trade_cash: 0.0,
+            cash_adjustments: 0.0,
+            tax_lots: None,
+            cost_basis_overrides: HashMap::new(),
         }
     }
 
+    /// Use `method` for `asset_id` instead of the ledger's own, from the next trade on
+    pub fn set_cost_basis_method(&mut self, asset_id: u64, method: CostBasisMethod) {
+        self.cost_basis_overrides.insert(asset_id, method);
+        if let Some(position) = self.positions.get_mut(&asset_id) {
+            position.set_cost_basis_method(method);
+        }
+    }
+
+    /// Cost basis method used for `asset_id`
+    pub fn cost_basis_method(&self, asset_id: u64) -> CostBasisMethod {
+        self.cost_basis_overrides
+            .get(&asset_id)
+            .copied()
+            .unwrap_or(self.cost_basis_method)
+    }
+
+    /// Track realized gains by tax lot from the next transaction on
+    pub fn with_tax_lots(mut self) -> Self {
+        self.enable_tax_lots();
+        self
+    }
+
+    /// Start tracking realized gains by tax lot, if not already
+    pub fn enable_tax_lots(&mut self) {
+        self.tax_lots.get_or_insert_with(TaxLots::new);
+    }
+
+    /// Realized gains by tax lot, if tracked
+    pub fn tax_lots(&self) -> Option<&TaxLots> {
+        self.tax_lots.as_ref()
+    }
+
+    /// Realized gains grouped by holding term, if tax lots are tracked
+    pub fn realized_gains_report(&self) -> Option<TaxReport> {
+        self.tax_lots.as_ref().map(TaxLots::report)
+    }
+
     /// Record a transaction
-    pub fn record_transaction(&mut self, transaction: Transaction) -> Result<()> {
+    ///
+    /// The side determines the direction of the trade, so `amount` may be
+    /// given with or without its sign. If the transaction selects lots, the
+    /// shares it closes come from those lots first and the rest by the
+    /// asset's cost basis method. Either way the ledger's copy lists the
+    /// lots it closed.
+    pub fn record_transaction(&mut self, mut transaction: Transaction) -> Result<()> {
         let asset_id = transaction.asset_id;
         let txn_index = self.transactions.len();
 
         // Get or create position
+        let method = self.cost_basis_method(asset_id);
         let position = self
             .positions
             .entry(asset_id)
-            .or_insert_with(|| LedgerPosition::new(asset_id, self.cost_basis_method));
+            .or_insert_with(|| LedgerPosition::new(asset_id, method));
 
         // Process transaction
-        match transaction.side {
-            OrderSide::Buy => {
-                position.add_shares(
-                    transaction.amount,
-                    transaction.price,
-                    transaction.dt,
+        let mut amount = match transaction.side {
+            OrderSide::Buy => transaction.amount.abs(),
+            OrderSide::Sell => -transaction.amount.abs(),
+        };
+        let mut closed = Vec::new();
+        let mut realized = None;
+        if !transaction.lots.is_empty() {
+            let selected: f64 = transaction.lots.iter().map(|chosen| chosen.quantity).sum();
+            if position.quantity * amount >= 0.0 || selected > amount.abs() + QUANTITY_TOLERANCE {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "Transaction {} selects {} shares of lots but closes {}",
                     transaction.id,
-                );
-            }
-            OrderSide::Sell => {
-                let realized_pnl = position.remove_shares(
-                    transaction.amount.abs(),
-                    transaction.price,
-                )?;
-                self.pnl_summary.add_trade(realized_pnl);
+                    selected,
+                    amount.abs().min(position.quantity.abs())
+                )));
             }
+            position.check_selection(&transaction.lots)?;
+            let specific: f64 = transaction
+                .lots
+                .iter()
+                .map(|chosen| position.close_lot(chosen.lot_id, chosen.quantity, transaction.price, &mut closed))
+                .sum();
+            realized = Some(specific);
+            amount -= selected * amount.signum();
         }
+        if let Some(pnl) = position.apply_lots(amount, transaction.price, transaction.dt, transaction.id, &mut closed) {
+            realized = Some(realized.unwrap_or(0.0) + pnl);
+        }
+        if let Some(realized_pnl) = realized {
+            self.pnl_summary.add_trade(realized_pnl);
+        }
+        transaction.lots = closed
+            .iter()
+            .map(|lot| LotSelection::new(lot.lot_id, lot.quantity.abs()))
+            .collect();
+        if let Some(tax_lots) = self.tax_lots.as_mut() {
+            tax_lots.record(&transaction, &closed);
+        }
+        self.trade_cash -= amount * transaction.price + transaction.commission;
+
+        debug_assert!(
+            (position.quantity - position.lot_quantity()).abs() <= 1e-6 * position.quantity.abs().max(1.0),
+            "Lots of asset {} hold {} shares but the position holds {}",
+            asset_id,
+            position.lot_quantity(),
+            position.quantity
+        );
 
         // Record transaction
         self.transactions.push(transaction);
@@ -305,6 +619,28 @@ impl Ledger {
         Ok(())
     }
 
+    /// Replace a position with a single opening lot, outside of any trade
+    ///
+    /// Used to bring positions created without a fill, such as restored or
+    /// hand-built state, into the ledger.
+    pub fn set_opening_position(&mut self, asset_id: u64, quantity: f64, net_cost: f64, dt: DateTime<Utc>) {
+        let mut position = LedgerPosition::new(asset_id, self.cost_basis_method(asset_id));
+        if quantity.abs() > QUANTITY_TOLERANCE {
+            position.open(quantity, net_cost / quantity, dt, uuid::Uuid::nil());
+        }
+        self.positions.insert(asset_id, position);
+    }
+
+    /// Record a change in cash that is not a trade
+    pub fn adjust_cash(&mut self, amount: f64) {
+        self.cash_adjustments += amount;
+    }
+
+    /// Net change in cash from trades and adjustments
+    pub fn cash_flow(&self) -> f64 {
+        self.trade_cash + self.cash_adjustments
+    }
+
     /// Get position for an asset
     pub fn get_position(&self, asset_id: u64) -> Option<&LedgerPosition> {
         self.positions.get(&asset_id)
@@ -381,7 +717,7 @@ impl Ledger {
 
     /// Get number of open positions
     pub fn open_position_count(&self) -> usize {
-        self.positions.iter().filter(|(_, pos)| pos.quantity > f64::EPSILON).count()
+        self.positions.iter().filter(|(_, pos)| pos.quantity.abs() > QUANTITY_TOLERANCE).count()
     }
 
     /// Update P&L summary with current prices
@@ -389,6 +725,58 @@ impl Ledger {
         let unrealized = self.total_unrealized_pnl(prices);
         self.pnl_summary.update_unrealized(unrealized);
     }
+
+    /// Check that `portfolio` holds the same positions as the ledger
+    ///
+    /// Compares each asset's quantity and net cost basis, in asset id order,
+    /// to a relative tolerance of 1e-6. The first mismatch is returned as
+    /// [`ZiplineError::LedgerDivergence`].
+    pub fn reconcile(&self, portfolio: &Portfolio) -> Result<()> {
+        let mut asset_ids: Vec<u64> = self
+            .positions
+            .keys()
+            .chain(portfolio.positions.keys())
+            .copied()
+            .collect();
+        asset_ids.sort_unstable();
+        asset_ids.dedup();
+
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-6 * a.abs().max(b.abs()).max(1.0);
+
+        for asset_id in asset_ids {
+            let (ledger_quantity, ledger_cost) = self
+                .positions
+                .get(&asset_id)
+                .map(|p| (p.quantity, p.net_cost))
+                .unwrap_or((0.0, 0.0));
+            let (portfolio_quantity, portfolio_cost) = portfolio
+                .positions
+                .get(&asset_id)
+                .map(|p| (p.quantity, p.cost_basis))
+                .unwrap_or((0.0, 0.0));
+
+            let mismatch = if !close(ledger_quantity, portfolio_quantity) {
+                Some((ReconcileField::Quantity, ledger_quantity, portfolio_quantity))
+            } else if !close(ledger_cost, portfolio_cost) {
+                Some((ReconcileField::CostBasis, ledger_cost, portfolio_cost))
+            } else {
+                None
+            };
+
+            if let Some((field, ledger, portfolio)) = mismatch {
+                return Err(ZiplineError::LedgerDivergence(Box::new(LedgerDivergence {
+                    asset_id,
+                    field,
+                    ledger,
+                    portfolio,
+                    transaction_count: self.transactions.len(),
+                    last_transaction: self.get_transactions_for_asset(asset_id).last().map(|t| (*t).clone()),
+                })));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Ledger {
@@ -562,4 +950,85 @@ mod tests {
 
         assert_eq!(ledger.average_entry_price(1), Some(55.0));
     }
+
+    #[test]
+    fn test_specific_lots_and_per_asset_method() {
+        let mut ledger = Ledger::new(CostBasisMethod::FIFO);
+        ledger.set_cost_basis_method(2, CostBasisMethod::LIFO);
+        for asset_id in [1, 2] {
+            ledger.record_transaction(create_test_transaction(asset_id, 100.0, 50.0, OrderSide::Buy)).unwrap();
+            ledger.record_transaction(create_test_transaction(asset_id, 100.0, 60.0, OrderSide::Buy)).unwrap();
+            ledger.record_transaction(create_test_transaction(asset_id, 50.0, 70.0, OrderSide::Sell)).unwrap();
+        }
+        // FIFO sells from the $50 lot, LIFO from the $60 lot
+        assert_eq!(ledger.get_pnl_summary().realized_pnl, 50.0 * 20.0 + 50.0 * 10.0);
+        assert_eq!(ledger.cost_basis_method(2), CostBasisMethod::LIFO);
+
+        // Sell 60 shares naming 30 of the $60 lot; the rest go FIFO
+        let lots: Vec<LotId> = ledger.get_position(1).unwrap().lots().map(|lot| lot.id()).collect();
+        let sell = create_test_transaction(1, 60.0, 70.0, OrderSide::Sell)
+            .with_lots(vec![LotSelection::new(lots[1], 30.0)]);
+        ledger.record_transaction(sell).unwrap();
+        let recorded = ledger.get_all_transactions().last().unwrap();
+        assert_eq!(
+            recorded.lots,
+            vec![LotSelection::new(lots[1], 30.0), LotSelection::new(lots[0], 30.0)]
+        );
+        let remaining: Vec<f64> = ledger.get_position(1).unwrap().lots().map(|lot| lot.quantity).collect();
+        assert_eq!(remaining, vec![20.0, 70.0]);
+
+        // Unknown lots and oversized selections are rejected untouched
+        let bad = create_test_transaction(1, 10.0, 70.0, OrderSide::Sell)
+            .with_lots(vec![LotSelection::new(lots[0], 25.0)]);
+        assert!(ledger.record_transaction(bad).is_err());
+        let mut position = ledger.get_position(1).unwrap().clone();
+        assert!(position.remove_shares_from_lot(uuid::Uuid::nil(), 1.0, 70.0).is_err());
+        assert_eq!(position.remove_shares_from_lot(lots[1], 70.0, 65.0).unwrap(), 350.0);
+        assert_eq!(position.quantity, 20.0);
+
+        // An opening position keeps the asset's method
+        ledger.set_opening_position(2, 200.0, 11_000.0, Utc::now());
+        assert_eq!(ledger.get_position(2).unwrap().cost_basis_method(), CostBasisMethod::LIFO);
+        ledger.set_opening_position(3, 200.0, 11_000.0, Utc::now());
+        assert_eq!(ledger.get_position(3).unwrap().cost_basis_method(), CostBasisMethod::FIFO);
+    }
+
+    #[test]
+    fn test_reconcile_with_portfolio() {
+        use crate::asset::Asset;
+        use crate::order::Order;
+
+        let asset = Asset::equity(
+            1,
+            "AAPL".to_string(),
+            "NASDAQ".to_string(),
+            chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        );
+        let mut ledger = Ledger::new(CostBasisMethod::Average);
+        let mut portfolio = Portfolio::new(100_000.0);
+
+        for (side, quantity, price) in [(OrderSide::Buy, 100.0, 10.0), (OrderSide::Sell, 40.0, 12.0)] {
+            let mut order = Order::market(asset.clone(), side, quantity, Utc::now());
+            order.fill(quantity, Utc::now());
+            portfolio.execute_order(&order, price, 1.0);
+            let amount = if side == OrderSide::Buy { quantity } else { -quantity };
+            ledger.record_transaction(create_test_transaction(1, amount, price, side)).unwrap();
+            ledger.reconcile(&portfolio).unwrap();
+        }
+        assert_eq!(ledger.get_position(1).unwrap().net_cost(), 520.0);
+
+        // A fill the ledger never saw
+        portfolio.get_position_mut(1).unwrap().quantity += 5.0;
+        match ledger.reconcile(&portfolio) {
+            Err(ZiplineError::LedgerDivergence(divergence)) => {
+                assert_eq!(divergence.asset_id, 1);
+                assert_eq!(divergence.field, ReconcileField::Quantity);
+                assert_eq!(divergence.ledger, 60.0);
+                assert_eq!(divergence.portfolio, 65.0);
+                assert_eq!(divergence.transaction_count, 2);
+                assert!(divergence.to_string().contains("last trade in the asset: Sell -40 @ 12"));
+            }
+            other => panic!("expected a divergence, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,338 @@
+//! Lot sizes, tick sizes and minimum order sizes
+//!
+//! Exchanges trade each instrument in multiples of a lot (one share, one
+//! contract, 0.00001 BTC), quote it in multiples of a tick, and refuse orders
+//! below a minimum quantity or notional. [`LotSizes`] holds these rules by
+//! asset, falling back to rules by asset class. With them installed in the
+//! context, every order is quantized as it is placed: its quantity to a whole
+//! number of lots, its limit price to a whole number of ticks, and it is
+//! rejected if what remains is below the minimums. Orders whose quantity or
+//! limit price moves by more than the warning threshold are reported, as
+//! `OrderQuantized` engine events. Assets without a rule trade in any
+//! fractional quantity at any price.
+
+use crate::asset::{Asset, AssetType};
+use crate::error::{Result, ZiplineError};
+use crate::order::{Order, OrderSide};
+use crate::types::{AssetId, Cash, OrderId, Price, Quantity, QUANTITY_TOLERANCE};
+use std::collections::HashMap;
+
+/// Default relative change above which quantization is reported (1%)
+pub const DEFAULT_QUANTIZATION_WARNING: f64 = 0.01;
+
+/// How quantities and limit prices are rounded to lots and ticks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Never trade more or at a worse price than asked: quantities round
+    /// toward zero, buy limits down and sell limits up
+    #[default]
+    Down,
+    /// Round to the nearest lot and tick
+    Nearest,
+}
+
+/// Trading increments and minimum order size of an instrument
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LotSize {
+    /// Quantities are whole multiples of this
+    pub increment: Quantity,
+    /// Limit prices are whole multiples of this
+    pub tick_size: Option<Price>,
+    /// Smallest quantity an order may be for
+    pub min_quantity: Quantity,
+    /// Smallest order value, at the current price
+    pub min_notional: Cash,
+}
+
+impl LotSize {
+    /// Trade in multiples of `increment`, with a minimum of one lot
+    pub fn new(increment: Quantity) -> Self {
+        Self {
+            increment,
+            tick_size: None,
+            min_quantity: increment,
+            min_notional: 0.0,
+        }
+    }
+
+    /// Quote limit prices in multiples of `tick_size`
+    pub fn with_tick_size(mut self, tick_size: Price) -> Self {
+        self.tick_size = (tick_size > 0.0).then_some(tick_size);
+        self
+    }
+
+    /// Set the smallest quantity an order may be for
+    pub fn with_min_quantity(mut self, quantity: Quantity) -> Self {
+        self.min_quantity = quantity.max(0.0);
+        self
+    }
+
+    /// Set the smallest order value
+    pub fn with_min_notional(mut self, notional: Cash) -> Self {
+        self.min_notional = notional.max(0.0);
+        self
+    }
+
+    /// Round a quantity to a whole number of lots
+    ///
+    /// Quantities within a rounding error of a whole number of lots count as
+    /// that number, so 0.003 with a 0.001 increment stays 0.003.
+    pub fn round(&self, quantity: Quantity, rounding: Rounding) -> Quantity {
+        if self.increment <= 0.0 {
+            return quantity;
+        }
+        let lots = quantity.abs() / self.increment;
+        let lots = match rounding {
+            Rounding::Down => (lots + QUANTITY_TOLERANCE).trunc(),
+            Rounding::Nearest => lots.round(),
+        };
+        quantity.signum() * lots * self.increment
+    }
+
+    /// Round a limit price to a whole number of ticks
+    pub fn round_price(&self, price: Price, side: OrderSide, rounding: Rounding) -> Price {
+        let Some(tick) = self.tick_size else {
+            return price;
+        };
+        let ticks = price / tick;
+        let ticks = match (rounding, side) {
+            (Rounding::Nearest, _) => ticks.round(),
+            (Rounding::Down, OrderSide::Buy) => (ticks + QUANTITY_TOLERANCE).floor(),
+            (Rounding::Down, OrderSide::Sell) => (ticks - QUANTITY_TOLERANCE).ceil(),
+        };
+        ticks * tick
+    }
+
+    /// Check a rounded quantity against the minimums
+    pub fn check(&self, asset: &Asset, quantity: Quantity, price: Option<Price>) -> Result<()> {
+        let quantity = quantity.abs();
+        if quantity + QUANTITY_TOLERANCE < self.min_quantity {
+            return Err(ZiplineError::InvalidOrder(format!(
+                "{} quantity {} is below the minimum of {}",
+                asset.symbol, quantity, self.min_quantity
+            )));
+        }
+        if let Some(price) = price {
+            let notional = quantity * price;
+            if notional + QUANTITY_TOLERANCE < self.min_notional {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "{} order value {:.2} is below the minimum of {:.2}",
+                    asset.symbol, notional, self.min_notional
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An order whose quantity or limit price quantization changed materially
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantization {
+    pub order_id: OrderId,
+    pub asset_id: AssetId,
+    /// Quantity as placed
+    pub requested_quantity: Quantity,
+    /// Quantity after rounding to lots
+    pub quantity: Quantity,
+    /// Limit price as placed
+    pub requested_limit_price: Option<Price>,
+    /// Limit price after rounding to ticks
+    pub limit_price: Option<Price>,
+}
+
+/// Lot and tick sizes by asset and by asset class
+#[derive(Debug, Clone)]
+pub struct LotSizes {
+    by_asset: HashMap<AssetId, LotSize>,
+    by_class: HashMap<AssetType, LotSize>,
+    rounding: Rounding,
+    warning_threshold: f64,
+}
+
+impl Default for LotSizes {
+    fn default() -> Self {
+        Self {
+            by_asset: HashMap::new(),
+            by_class: HashMap::new(),
+            rounding: Rounding::default(),
+            warning_threshold: DEFAULT_QUANTIZATION_WARNING,
+        }
+    }
+}
+
+impl LotSizes {
+    /// No rules: every quantity and price is allowed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the lot size of one asset
+    pub fn with_asset(mut self, asset_id: AssetId, lot: LotSize) -> Self {
+        self.by_asset.insert(asset_id, lot);
+        self
+    }
+
+    /// Set the lot size of every asset of a class without its own rule
+    pub fn with_asset_class(mut self, asset_type: AssetType, lot: LotSize) -> Self {
+        self.by_class.insert(asset_type, lot);
+        self
+    }
+
+    /// Set how quantities and limit prices are rounded
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Report orders whose quantity or limit price moves by more than
+    /// `fraction` of its requested value
+    pub fn with_warning_threshold(mut self, fraction: f64) -> Self {
+        self.warning_threshold = fraction.max(0.0);
+        self
+    }
+
+    /// Lot size that applies to an asset
+    pub fn get(&self, asset: &Asset) -> Option<&LotSize> {
+        self.by_asset
+            .get(&asset.id)
+            .or_else(|| self.by_class.get(&asset.asset_type))
+    }
+
+    /// Round a quantity to whole lots and check it against the minimums
+    ///
+    /// Returns the rounded quantity; assets without a rule get `quantity` back.
+    pub fn apply(&self, asset: &Asset, quantity: Quantity, price: Option<Price>) -> Result<Quantity> {
+        match self.get(asset) {
+            Some(lot) => {
+                let rounded = lot.round(quantity, self.rounding);
+                lot.check(asset, rounded, price)?;
+                Ok(rounded)
+            }
+            None => Ok(quantity),
+        }
+    }
+
+    /// Round an order's quantity to lots and limit price to ticks
+    ///
+    /// The order is checked against the minimums at its limit price, or at
+    /// `price` for orders without one. Returns the change if it exceeds the
+    /// warning threshold.
+    pub fn quantize(&self, order: &mut Order, price: Option<Price>) -> Result<Option<Quantization>> {
+        let Some(lot) = self.get(&order.asset) else {
+            return Ok(None);
+        };
+        let requested_quantity = order.quantity;
+        let requested_limit_price = order.limit_price;
+
+        order.limit_price = order
+            .limit_price
+            .map(|limit| lot.round_price(limit, order.side, self.rounding));
+        order.quantity = lot.round(order.quantity, self.rounding);
+        lot.check(&order.asset, order.quantity, order.limit_price.or(price))?;
+
+        let moved = |from: f64, to: f64| from != 0.0 && ((to - from) / from).abs() > self.warning_threshold;
+        let material = moved(requested_quantity, order.quantity)
+            || matches!((requested_limit_price, order.limit_price), (Some(from), Some(to)) if moved(from, to));
+        Ok(material.then_some(Quantization {
+            order_id: order.id,
+            asset_id: order.asset.id,
+            requested_quantity,
+            quantity: order.quantity,
+            requested_limit_price,
+            limit_price: order.limit_price,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, Context};
+    use crate::calendar::{AlwaysOpenCalendar, TradingCalendar};
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::{EngineEvent, SimulationEngine};
+    use crate::types::Bar;
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Buys 0.0035 BTC at a limit on the first bar, then sells 0.001 a bar
+    struct Accumulate {
+        btc: Asset,
+        bar: usize,
+        rejected: Vec<String>,
+        held: Option<Quantity>,
+    }
+
+    impl Algorithm for Accumulate {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            if self.bar == 0 {
+                context.order_limit(self.btc.clone(), 0.0035, 40_000.004)?;
+            } else {
+                context.order(self.btc.clone(), -0.001)?;
+            }
+            self.bar += 1;
+            if let Err(e) = context.order(self.btc.clone(), 0.0004) {
+                self.rejected.push(e.to_string());
+            }
+            Ok(())
+        }
+
+        fn analyze(&mut self, context: &Context) -> Result<()> {
+            self.held = context.portfolio.get_position(1).map(|p| p.quantity);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fractional_crypto_orders_on_weekends() {
+        let listed = NaiveDate::from_ymd_opt(2010, 1, 1).unwrap();
+        let btc = Asset::new(1, "BTC".to_string(), "COINBASE".to_string(), AssetType::Crypto, listed);
+        let lots = LotSizes::new().with_asset_class(
+            AssetType::Crypto,
+            LotSize::new(0.001).with_tick_size(0.01).with_min_notional(10.0),
+        );
+        assert_eq!(lots.apply(&btc, 0.0035, None).unwrap(), 0.003);
+        assert!(lots.apply(&btc, 0.001, Some(5_000.0)).is_err());
+        let lot = lots.get(&btc).unwrap();
+        assert_eq!(lot.round(0.0035, Rounding::Nearest), 0.004);
+        assert_eq!(lot.round_price(100.017, OrderSide::Buy, Rounding::Down), 100.01);
+        assert_eq!(lot.round_price(100.013, OrderSide::Sell, Rounding::Down), 100.02);
+
+        // Saturday through Tuesday, all trading days
+        let calendar = AlwaysOpenCalendar;
+        let start = Utc.with_ymd_and_hms(2024, 1, 6, 12, 0, 0).unwrap();
+        assert!(calendar.is_trading_day(start.date_naive()));
+
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(btc.clone());
+        for day in 0..4 {
+            source.add_bar(1, Bar::new(start + Duration::days(day), 40_000.0, 40_000.0, 40_000.0, 40_000.0, 1e3));
+        }
+        let end = start + Duration::days(3);
+        source.set_date_range(start, end);
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(calendar))
+            .with_lot_sizes(Arc::new(lots));
+        let events = engine.events_mut().channel();
+        let mut algorithm = Accumulate { btc, bar: 0, rejected: Vec::new(), held: None };
+        engine.run(&mut algorithm, &source, start, end).unwrap();
+
+        // Three lots bought at a whole tick on Saturday, one sold on each of
+        // the next three days
+        let quantized: Vec<Quantization> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                EngineEvent::OrderQuantized { quantization, .. } => Some(quantization),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(quantized.len(), 1);
+        assert_eq!(quantized[0].quantity, 0.003);
+        assert_eq!(quantized[0].limit_price, Some(40_000.0));
+        assert_eq!(algorithm.bar, 4);
+        assert_eq!(algorithm.rejected.len(), 4);
+        assert_eq!(algorithm.held, None);
+    }
+}
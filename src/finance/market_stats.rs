@@ -0,0 +1,318 @@
+//! Rolling market statistics shared across controls and cost models
+//!
+//! [`MarketStatsService`] keeps the last price, rolling daily volatility and
+//! average daily volume (ADV) per asset. It is updated incrementally with every
+//! bar the engine sees, so controls, slippage models and capacity analysis all
+//! read the same numbers instead of keeping their own copies or guessing.
+//!
+//! Statistics are computed over completed sessions only: a session's close and
+//! total volume enter the window when the first bar of the next session
+//! arrives, so nothing looks ahead of the current bar.
+
+use crate::asset::Asset;
+use crate::data::data_portal::DataPortal;
+use crate::data::frequency::DataFrequency;
+use crate::error::Result;
+use crate::finance::constants::TRADING_DAYS_PER_YEAR;
+use crate::types::{Bar, Price, SessionId};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+/// Statistics for one asset at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketStats {
+    /// Most recent traded price
+    pub last_price: Price,
+    /// Annualized volatility of daily returns, once two returns are known
+    pub volatility: Option<f64>,
+    /// Average daily volume in shares, once a session has completed
+    pub average_daily_volume: Option<f64>,
+    /// Completed sessions in the window
+    pub sessions: usize,
+}
+
+impl MarketStats {
+    /// Average daily dollar volume at the last price
+    pub fn average_dollar_volume(&self) -> Option<f64> {
+        self.average_daily_volume.map(|adv| adv * self.last_price)
+    }
+}
+
+/// Rolling state for one asset
+#[derive(Debug, Default)]
+struct AssetState {
+    /// Session currently accumulating
+    session: Option<NaiveDate>,
+    session_close: f64,
+    session_volume: f64,
+    last_price: f64,
+    /// Close of the last completed session
+    prev_close: Option<f64>,
+    returns: VecDeque<f64>,
+    return_sum: f64,
+    return_sq_sum: f64,
+    volumes: VecDeque<f64>,
+    volume_sum: f64,
+}
+
+impl AssetState {
+    fn observe(&mut self, session: NaiveDate, close: f64, volume: f64, window: usize) {
+        if self.session != Some(session) {
+            if self.session.is_some() {
+                self.complete_session(window);
+            }
+            self.session = Some(session);
+            self.session_volume = 0.0;
+        }
+
+        if close.is_finite() && close > 0.0 {
+            self.session_close = close;
+            self.last_price = close;
+        }
+        if volume.is_finite() {
+            self.session_volume += volume;
+        }
+    }
+
+    /// Roll the accumulating session into the windows
+    fn complete_session(&mut self, window: usize) {
+        let close = self.session_close;
+        if close > 0.0 {
+            if let Some(prev) = self.prev_close {
+                let r = close / prev - 1.0;
+                self.returns.push_back(r);
+                self.return_sum += r;
+                self.return_sq_sum += r * r;
+                if self.returns.len() > window {
+                    let old = self.returns.pop_front().unwrap();
+                    self.return_sum -= old;
+                    self.return_sq_sum -= old * old;
+                }
+            }
+            self.prev_close = Some(close);
+        }
+
+        self.volumes.push_back(self.session_volume);
+        self.volume_sum += self.session_volume;
+        if self.volumes.len() > window {
+            self.volume_sum -= self.volumes.pop_front().unwrap();
+        }
+    }
+
+    fn volatility(&self) -> Option<f64> {
+        let n = self.returns.len();
+        if n < 2 {
+            return None;
+        }
+        let n = n as f64;
+        let variance = (self.return_sq_sum - self.return_sum * self.return_sum / n) / (n - 1.0);
+        Some(variance.max(0.0).sqrt() * TRADING_DAYS_PER_YEAR.sqrt())
+    }
+
+    fn average_daily_volume(&self) -> Option<f64> {
+        if self.volumes.is_empty() {
+            None
+        } else {
+            Some(self.volume_sum / self.volumes.len() as f64)
+        }
+    }
+
+    fn stats(&self) -> Option<MarketStats> {
+        if self.last_price <= 0.0 {
+            return None;
+        }
+        Some(MarketStats {
+            last_price: self.last_price,
+            volatility: self.volatility(),
+            average_daily_volume: self.average_daily_volume(),
+            sessions: self.volumes.len(),
+        })
+    }
+}
+
+/// Rolling per-asset volatility, ADV and last price
+///
+/// Cheap to share behind an `Arc`; updates take a write lock per bar.
+///
+/// # Example
+/// ```ignore
+/// let stats = Arc::new(MarketStatsService::new(20));
+/// let engine = SimulationEngine::default_engine(calendar).with_market_stats(stats.clone());
+/// let slippage = SquareRootImpact::new(0.1).with_market_stats(stats);
+/// ```
+#[derive(Debug)]
+pub struct MarketStatsService {
+    /// Sessions in the rolling window
+    window: usize,
+    assets: RwLock<HashMap<u64, AssetState>>,
+}
+
+impl MarketStatsService {
+    /// Sessions used when no window is given
+    pub const DEFAULT_WINDOW: usize = 20;
+
+    /// Create a service with a rolling window of `window` sessions
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(2),
+            assets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Sessions in the rolling window
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Record a bar for an asset in the trading session it belongs to
+    pub fn update(&self, asset_id: u64, session: SessionId, bar: &Bar) {
+        let session = session.date();
+        self.assets
+            .write()
+            .unwrap()
+            .entry(asset_id)
+            .or_default()
+            .observe(session, bar.close, bar.volume, self.window);
+    }
+
+    /// Seed the windows from daily history ending before `dt`
+    ///
+    /// Useful at the start of a backtest so the statistics are meaningful on
+    /// the first bar instead of after `window` sessions.
+    pub fn warm_up(&self, portal: &DataPortal, assets: &[Asset], dt: DateTime<Utc>) -> Result<()> {
+        let history = portal.adjusted_history(
+            assets,
+            &["close", "volume"],
+            self.window + 1,
+            DataFrequency::Daily,
+            dt,
+        )?;
+        let (closes, volumes) = match (history.get("close"), history.get("volume")) {
+            (Some(closes), Some(volumes)) => (closes, volumes),
+            _ => return Ok(()),
+        };
+
+        let today = dt.date_naive();
+        let mut states = self.assets.write().unwrap();
+        for asset in assets {
+            let (Some(asset_closes), Some(asset_volumes)) =
+                (closes.data.get(&asset.id), volumes.data.get(&asset.id))
+            else {
+                continue;
+            };
+
+            let state = states.entry(asset.id).or_default();
+            let rows = closes.index.iter().zip(asset_closes).zip(asset_volumes);
+            for ((ts, close), volume) in rows {
+                // The session in progress is fed by the engine
+                if ts.date_naive() < today {
+                    state.observe(ts.date_naive(), *close, *volume, self.window);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Most recent traded price
+    pub fn last_price(&self, asset_id: u64) -> Option<Price> {
+        self.stats(asset_id).map(|s| s.last_price)
+    }
+
+    /// Annualized volatility of daily returns over the window
+    pub fn volatility(&self, asset_id: u64) -> Option<f64> {
+        self.stats(asset_id).and_then(|s| s.volatility)
+    }
+
+    /// Average daily volume in shares over the window
+    pub fn average_daily_volume(&self, asset_id: u64) -> Option<f64> {
+        self.stats(asset_id).and_then(|s| s.average_daily_volume)
+    }
+
+    /// Average daily dollar volume at the last price
+    pub fn average_dollar_volume(&self, asset_id: u64) -> Option<f64> {
+        self.stats(asset_id).and_then(|s| s.average_dollar_volume())
+    }
+
+    /// All statistics for an asset; None until a priced bar has been seen
+    pub fn stats(&self, asset_id: u64) -> Option<MarketStats> {
+        self.assets.read().unwrap().get(&asset_id).and_then(|s| s.stats())
+    }
+
+    /// Forget everything, e.g. between backtests
+    pub fn clear(&self) {
+        self.assets.write().unwrap().clear();
+    }
+}
+
+impl Default for MarketStatsService {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn bar(day: i64, minute: i64, close: f64, volume: f64) -> Bar {
+        let open = Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap();
+        Bar::new(
+            open + Duration::days(day) + Duration::minutes(minute),
+            close,
+            close,
+            close,
+            close,
+            volume,
+        )
+    }
+
+    fn session(day: i64) -> SessionId {
+        SessionId::from_date(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap() + Duration::days(day))
+    }
+
+    #[test]
+    fn test_adv_and_last_price_use_completed_sessions() {
+        let stats = MarketStatsService::new(3);
+        assert!(stats.stats(1).is_none());
+
+        // Two minute bars per session
+        for day in 0..4 {
+            stats.update(1, session(day), &bar(day, 0, 100.0, 1_000.0 * (day + 1) as f64));
+            stats.update(1, session(day), &bar(day, 1, 101.0, 1_000.0 * (day + 1) as f64));
+        }
+
+        // Sessions 0..=2 are complete (2k, 4k, 6k shares); session 3 is not
+        assert_eq!(stats.average_daily_volume(1), Some(4_000.0));
+        assert_eq!(stats.last_price(1), Some(101.0));
+        assert_eq!(stats.stats(1).unwrap().sessions, 3);
+
+        // The window drops the oldest session
+        stats.update(1, session(4), &bar(4, 0, 101.0, 0.0));
+        assert_eq!(stats.average_daily_volume(1), Some(6_000.0));
+        assert!((stats.average_dollar_volume(1).unwrap() - 606_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_volatility_matches_sample_std() {
+        let stats = MarketStatsService::new(4);
+        let closes = [100.0, 102.0, 99.0, 101.0, 103.0, 100.0, 100.0];
+        for (day, close) in closes.iter().enumerate() {
+            stats.update(7, session(day as i64), &bar(day as i64, 0, *close, 500.0));
+        }
+
+        // Returns of the last four completed sessions (closes[1..=5])
+        let returns: Vec<f64> = closes[1..6].windows(2).map(|w| w[1] / w[0] - 1.0).collect();
+        let mean = returns.iter().sum::<f64>() / 4.0;
+        let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 3.0;
+        let expected = var.sqrt() * TRADING_DAYS_PER_YEAR.sqrt();
+
+        assert!((stats.volatility(7).unwrap() - expected).abs() < 1e-9);
+        assert_eq!(stats.volatility(8), None);
+
+        stats.clear();
+        assert!(stats.stats(7).is_none());
+    }
+}
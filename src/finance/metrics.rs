@@ -1,10 +1,17 @@
 //! Comprehensive performance metrics and analytics
 
-use chrono::{DateTime, Utc, Duration};
+use crate::data::benchmarks::{BenchmarkReader, RiskFreeCurve};
+use crate::error::Result;
+use crate::finance::transaction::Transaction;
+use chrono::{DateTime, Utc, Duration, NaiveDate};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 /// Complete set of performance metrics
+///
+/// Metrics missing from older result files read as their defaults.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PerformanceMetrics {
     /// Total return (final value / starting value - 1)
     pub total_return: f64,
@@ -40,6 +47,13 @@ pub struct PerformanceMetrics {
     pub profit_factor: f64,
     /// Total number of trades
     pub trades_count: usize,
+    /// Mean daily traded notional as a fraction of portfolio value
+    pub avg_daily_turnover: f64,
+    /// Largest fraction of a bar's volume taken by one fill
+    pub max_participation: f64,
+    /// Portfolio value at which fills would stay within the capacity
+    /// participation of bar volume, if any fill had a volume to measure
+    pub capacity: Option<f64>,
 }
 
 impl Default for PerformanceMetrics {
@@ -62,6 +76,9 @@ impl Default for PerformanceMetrics {
             avg_loss: 0.0,
             profit_factor: 0.0,
             trades_count: 0,
+            avg_daily_turnover: 0.0,
+            max_participation: 0.0,
+            capacity: None,
         }
     }
 }
@@ -115,10 +132,230 @@ impl Trade {
     }
 }
 
+/// One asset's contribution to P&L, split by source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PnlBreakdown {
+    /// From price moves on the quantity held coming into the bar
+    pub price: f64,
+    /// From fills against the bar's closing price, net of commission
+    pub trading: f64,
+    /// Cash dividends received
+    pub dividends: f64,
+}
+
+impl PnlBreakdown {
+    /// Sum of all sources
+    pub fn total(&self) -> f64 {
+        self.price + self.trading + self.dividends
+    }
+
+    fn add(&mut self, other: &PnlBreakdown) {
+        self.price += other.price;
+        self.trading += other.trading;
+        self.dividends += other.dividends;
+    }
+}
+
+/// Per-asset P&L contributions by session
+///
+/// Feed it once per bar with [`Attribution::mark`]; a position's P&L is then
+/// measured from one bar's close to the next, and the contributions over all
+/// assets add up to the change in marked-to-market portfolio value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Attribution {
+    /// Contributions by session and asset
+    pub daily: BTreeMap<NaiveDate, BTreeMap<u64, PnlBreakdown>>,
+    /// Quantity held and price marked at the end of the last bar
+    #[serde(skip)]
+    marks: HashMap<u64, (f64, f64)>,
+}
+
+impl Attribution {
+    /// Add a contribution for an asset on a session
+    pub fn record(&mut self, session: NaiveDate, asset_id: u64, pnl: PnlBreakdown) {
+        self.daily
+            .entry(session)
+            .or_default()
+            .entry(asset_id)
+            .or_default()
+            .add(&pnl);
+    }
+
+    /// Add a cash dividend received on a session
+    pub fn record_dividend(&mut self, session: NaiveDate, asset_id: u64, amount: f64) {
+        let pnl = PnlBreakdown {
+            dividends: amount,
+            ..Default::default()
+        };
+        self.record(session, asset_id, pnl);
+    }
+
+    /// Attribute one bar's P&L
+    ///
+    /// `fills` are the bar's transactions, `positions` the quantities held
+    /// after them and `price` each asset's closing price for the bar. Assets
+    /// without a price stay marked at their previous close.
+    pub fn mark(
+        &mut self,
+        session: NaiveDate,
+        fills: &[Transaction],
+        positions: impl IntoIterator<Item = (u64, f64)>,
+        price: impl Fn(u64) -> Option<f64>,
+    ) {
+        let held: HashMap<u64, f64> = positions.into_iter().collect();
+        let mut assets: Vec<u64> = self
+            .marks
+            .keys()
+            .chain(held.keys())
+            .copied()
+            .chain(fills.iter().map(|t| t.asset_id))
+            .collect();
+        assets.sort_unstable();
+        assets.dedup();
+
+        for asset_id in assets {
+            let (quantity, previous) = self.marks.get(&asset_id).copied().unwrap_or((0.0, 0.0));
+            let last_fill = fills.iter().rev().find(|t| t.asset_id == asset_id).map(|t| t.price);
+            let close = match price(asset_id).or(last_fill) {
+                Some(close) => close,
+                None => previous,
+            };
+
+            let mut pnl = PnlBreakdown {
+                price: quantity * (close - previous),
+                ..Default::default()
+            };
+            for fill in fills.iter().filter(|t| t.asset_id == asset_id) {
+                pnl.trading += fill.amount * (close - fill.price) - fill.commission;
+            }
+            if pnl.total() != 0.0 || last_fill.is_some() {
+                self.record(session, asset_id, pnl);
+            }
+
+            match held.get(&asset_id) {
+                Some(&quantity) if quantity != 0.0 => {
+                    self.marks.insert(asset_id, (quantity, close));
+                }
+                _ => {
+                    self.marks.remove(&asset_id);
+                }
+            }
+        }
+    }
+
+    /// Total contribution of each asset over the run, largest first
+    pub fn table(&self) -> Vec<(u64, PnlBreakdown)> {
+        let mut totals: BTreeMap<u64, PnlBreakdown> = BTreeMap::new();
+        for assets in self.daily.values() {
+            for (asset_id, pnl) in assets {
+                totals.entry(*asset_id).or_default().add(pnl);
+            }
+        }
+        let mut table: Vec<(u64, PnlBreakdown)> = totals.into_iter().collect();
+        table.sort_by(|a, b| b.1.total().total_cmp(&a.1.total()));
+        table
+    }
+
+    /// Check if nothing has been attributed
+    pub fn is_empty(&self) -> bool {
+        self.daily.is_empty()
+    }
+}
+
+/// Share of bar volume a strategy is assumed to be able to take when
+/// estimating its capacity
+pub const DEFAULT_CAPACITY_PARTICIPATION: f64 = 0.05;
+
+/// Trading in one session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionTurnover {
+    /// Absolute notional traded
+    pub traded: f64,
+    /// Portfolio value at the session's last mark
+    pub portfolio_value: f64,
+    /// Largest fraction of a bar's volume filled in one fill, by asset
+    pub participation: BTreeMap<u64, f64>,
+}
+
+impl SessionTurnover {
+    /// Notional traded as a fraction of portfolio value
+    pub fn turnover(&self) -> f64 {
+        if self.portfolio_value > 0.0 {
+            self.traded / self.portfolio_value
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Daily turnover and participation in bar volume
+///
+/// Participation scales with assets under management, so the AUM at which a
+/// run's heaviest fill would have taken a given share of its bar is an
+/// estimate of the strategy's capacity.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Turnover {
+    /// Trading by session
+    pub daily: BTreeMap<NaiveDate, SessionTurnover>,
+}
+
+impl Turnover {
+    /// Record a fill of `quantity` shares worth `notional` in a bar of `bar_volume`
+    ///
+    /// Fills without a bar volume count towards turnover only.
+    pub fn record_fill(&mut self, session: NaiveDate, asset_id: u64, notional: f64, quantity: f64, bar_volume: f64) {
+        let day = self.daily.entry(session).or_default();
+        day.traded += notional.abs();
+        if bar_volume > 0.0 {
+            let participation = day.participation.entry(asset_id).or_insert(0.0);
+            *participation = participation.max(quantity.abs() / bar_volume);
+        }
+    }
+
+    /// Record the portfolio value at a mark, the last of a session counting
+    pub fn record_value(&mut self, session: NaiveDate, value: f64) {
+        self.daily.entry(session).or_default().portfolio_value = value;
+    }
+
+    /// Mean of the daily turnover over every session marked
+    pub fn average_turnover(&self) -> f64 {
+        if self.daily.is_empty() {
+            return 0.0;
+        }
+        self.daily.values().map(SessionTurnover::turnover).sum::<f64>() / self.daily.len() as f64
+    }
+
+    /// Largest fraction of a bar's volume any fill took
+    pub fn max_participation(&self) -> f64 {
+        self.daily
+            .values()
+            .flat_map(|day| day.participation.values())
+            .fold(0.0, |max: f64, p| max.max(*p))
+    }
+
+    /// Portfolio value at which no fill would have exceeded `participation` of its bar
+    ///
+    /// `None` if no fill had a bar volume to measure against.
+    pub fn capacity(&self, participation: f64) -> Option<f64> {
+        self.daily
+            .values()
+            .filter(|day| day.portfolio_value > 0.0)
+            .flat_map(|day| {
+                day.participation
+                    .values()
+                    .filter(|p| **p > 0.0)
+                    .map(move |p| day.portfolio_value * participation / p)
+            })
+            .reduce(f64::min)
+    }
+}
+
 /// Tracks performance metrics throughout backtest
 pub struct MetricsTracker {
     /// Daily returns
     returns: Vec<f64>,
+    /// Date of each return
+    return_dates: Vec<DateTime<Utc>>,
     /// Portfolio values over time
     portfolio_values: Vec<(DateTime<Utc>, f64)>,
     /// Benchmark returns (if provided)
@@ -129,6 +366,14 @@ pub struct MetricsTracker {
     trades: Vec<Trade>,
     /// Risk-free rate for Sharpe calculation
     risk_free_rate: f64,
+    /// Time-varying risk-free rates, used instead of `risk_free_rate` when set
+    risk_free_curve: Option<RiskFreeCurve>,
+    /// Per-asset P&L contributions
+    attribution: Attribution,
+    /// Traded notional and participation by session
+    turnover: Turnover,
+    /// Share of bar volume assumed tradable for the capacity estimate
+    capacity_participation: f64,
 }
 
 impl MetricsTracker {
@@ -136,37 +381,117 @@ impl MetricsTracker {
     pub fn new(starting_cash: f64) -> Self {
         Self {
             returns: Vec::new(),
+            return_dates: Vec::new(),
             portfolio_values: Vec::new(),
             benchmark_returns: None,
             starting_cash,
             trades: Vec::new(),
             risk_free_rate: 0.02, // Default 2% annual
+            risk_free_curve: None,
+            attribution: Attribution::default(),
+            turnover: Turnover::default(),
+            capacity_participation: DEFAULT_CAPACITY_PARTICIPATION,
         }
     }
 
+    /// Estimate capacity at `participation` of bar volume instead of the default 5%
+    pub fn set_capacity_participation(&mut self, participation: f64) {
+        self.capacity_participation = participation;
+    }
+
     /// Set risk-free rate for Sharpe calculation
     pub fn set_risk_free_rate(&mut self, rate: f64) {
         self.risk_free_rate = rate;
+        self.risk_free_curve = None;
+    }
+
+    /// Measure Sharpe and Sortino against a time-varying risk-free rate
+    ///
+    /// Each day's return is compared with the rate in effect that day.
+    pub fn set_risk_free_curve(&mut self, curve: RiskFreeCurve) {
+        self.risk_free_curve = Some(curve);
+    }
+
+    /// Set benchmark returns from a benchmark reader, aligned to recorded values
+    ///
+    /// Each recorded return is paired with the benchmark's return on the same
+    /// date; days the benchmark has no data for count as zero. Call after the
+    /// run's values have been recorded.
+    pub fn set_benchmark_from(&mut self, benchmark: &dyn BenchmarkReader) -> Result<()> {
+        let (Some(first), Some(last)) = (self.return_dates.first(), self.return_dates.last()) else {
+            self.benchmark_returns = Some(Vec::new());
+            return Ok(());
+        };
+        let by_date: std::collections::HashMap<_, _> = benchmark
+            .get_benchmark_returns(*first, *last)?
+            .into_iter()
+            .map(|r| (r.timestamp.date_naive(), r.returns))
+            .collect();
+
+        self.benchmark_returns = Some(
+            self.return_dates
+                .iter()
+                .map(|dt| by_date.get(&dt.date_naive()).copied().unwrap_or(0.0))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// Mean daily return in excess of the risk-free rate
+    fn mean_excess_return(&self) -> f64 {
+        let excess: f64 = match &self.risk_free_curve {
+            Some(curve) => self
+                .returns
+                .iter()
+                .zip(&self.return_dates)
+                .map(|(r, dt)| r - curve.daily_rate(dt.date_naive()))
+                .sum(),
+            None => self.returns.iter().map(|r| r - self.risk_free_rate / 252.0).sum(),
+        };
+        excess / self.returns.len() as f64
     }
 
     /// Record portfolio value at timestamp
     pub fn record_value(&mut self, timestamp: DateTime<Utc>, value: f64) {
         self.portfolio_values.push((timestamp, value));
+        self.turnover.record_value(timestamp.date_naive(), value);
 
         // Calculate daily return
         if let Some((_, prev_value)) = self.portfolio_values.get(self.portfolio_values.len().saturating_sub(2)) {
             if *prev_value > 0.0 {
                 let daily_return = (value - prev_value) / prev_value;
                 self.returns.push(daily_return);
+                self.return_dates.push(timestamp);
             }
         }
     }
 
+    /// Record a fill against the volume of the bar it traded in
+    pub fn record_fill(&mut self, transaction: &Transaction, bar_volume: f64) {
+        self.turnover.record_fill(
+            transaction.dt.date_naive(),
+            transaction.asset_id,
+            transaction.amount * transaction.price,
+            transaction.amount,
+            bar_volume,
+        );
+    }
+
     /// Record a completed trade
     pub fn record_trade(&mut self, trade: Trade) {
         self.trades.push(trade);
     }
 
+    /// Record an asset's contribution to the P&L of the session at `timestamp`
+    pub fn record_position_pnl(&mut self, timestamp: DateTime<Utc>, asset_id: u64, pnl: PnlBreakdown) {
+        self.attribution.record(timestamp.date_naive(), asset_id, pnl);
+    }
+
+    /// Record a cash dividend received from an asset
+    pub fn record_dividend(&mut self, timestamp: DateTime<Utc>, asset_id: u64, amount: f64) {
+        self.attribution.record_dividend(timestamp.date_naive(), asset_id, amount);
+    }
+
     /// Set benchmark returns for alpha/beta calculation
     pub fn set_benchmark(&mut self, returns: Vec<f64>) {
         self.benchmark_returns = Some(returns);
@@ -192,6 +517,9 @@ impl MetricsTracker {
             avg_loss: self.calculate_avg_loss(),
             profit_factor: self.calculate_profit_factor(),
             trades_count: self.trades.len(),
+            avg_daily_turnover: self.turnover.average_turnover(),
+            max_participation: self.turnover.max_participation(),
+            capacity: self.turnover.capacity(self.capacity_participation),
         }
     }
 
@@ -224,17 +552,13 @@ impl MetricsTracker {
             return 0.0;
         }
 
-        let mean_return = self.returns.iter().sum::<f64>() / self.returns.len() as f64;
         let volatility = self.calculate_volatility();
 
         if volatility == 0.0 {
             return 0.0;
         }
 
-        let daily_rf = self.risk_free_rate / 252.0;
-        let excess_return = mean_return - daily_rf;
-
-        (excess_return / volatility) * (252.0_f64).sqrt()
+        (self.mean_excess_return() / volatility) * (252.0_f64).sqrt()
     }
 
     fn calculate_sortino_ratio(&self) -> f64 {
@@ -242,17 +566,13 @@ impl MetricsTracker {
             return 0.0;
         }
 
-        let mean_return = self.returns.iter().sum::<f64>() / self.returns.len() as f64;
         let downside_risk = self.calculate_downside_risk();
 
         if downside_risk == 0.0 {
             return 0.0;
         }
 
-        let daily_rf = self.risk_free_rate / 252.0;
-        let excess_return = mean_return - daily_rf;
-
-        (excess_return / downside_risk) * (252.0_f64).sqrt()
+        (self.mean_excess_return() / downside_risk) * (252.0_f64).sqrt()
     }
 
     fn calculate_volatility(&self) -> f64 {
@@ -450,12 +770,17 @@ impl MetricsTracker {
     pub fn trades(&self) -> &[Trade] {
         &self.trades
     }
+
+    /// Get per-asset P&L contributions
+    pub fn attribution(&self) -> &Attribution {
+        &self.attribution
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use chrono::{TimeZone, Utc};
 
     #[test]
     fn test_trade_creation() {
@@ -526,4 +851,97 @@ mod tests {
         assert!(metrics.sharpe_ratio > 0.0);
         assert!(metrics.volatility > 0.0);
     }
+
+    #[test]
+    fn test_risk_free_curve_and_benchmark() {
+        use crate::data::benchmarks::ConstantBenchmark;
+        use chrono::NaiveDate;
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut tracker = MetricsTracker::new(100000.0);
+        for i in 0..=60 {
+            let wobble = if i % 2 == 0 { 150.0 } else { -50.0 };
+            tracker.record_value(start + Duration::days(i), 100000.0 + i as f64 * 100.0 + wobble);
+        }
+
+        tracker.set_risk_free_rate(0.05);
+        let flat = tracker.calculate_metrics();
+        tracker.set_risk_free_curve(RiskFreeCurve::constant(0.05));
+        let constant = tracker.calculate_metrics();
+        assert!((flat.sharpe_ratio - constant.sharpe_ratio).abs() < 1e-9);
+        assert!((flat.sortino_ratio - constant.sortino_ratio).abs() < 1e-9);
+
+        // Rates rise to 20% halfway through, lowering the excess return
+        let midpoint = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        tracker.set_risk_free_curve(RiskFreeCurve::from_rates([
+            (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 0.05),
+            (midpoint, 0.20),
+        ]));
+        assert!(tracker.calculate_metrics().sharpe_ratio < constant.sharpe_ratio);
+
+        tracker.set_benchmark_from(&ConstantBenchmark::new(0.001)).unwrap();
+        let benchmark = tracker.benchmark_returns.as_ref().unwrap();
+        assert_eq!(benchmark.len(), tracker.returns().len());
+        assert!(tracker.calculate_metrics().beta.is_some());
+    }
+
+    #[test]
+    fn test_attribution_splits_price_and_trading_pnl() {
+        use crate::order::OrderSide;
+        use uuid::Uuid;
+
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let dt = Utc::now();
+        let buy = Transaction::new(1, Uuid::new_v4(), dt, 10.0, 100.0, 1.0, OrderSide::Buy);
+        let sell = Transaction::new(1, Uuid::new_v4(), dt, -4.0, 108.0, 1.0, OrderSide::Sell);
+        let prices = |closes: &'static [(u64, f64)]| {
+            move |id: u64| closes.iter().find(|(asset, _)| *asset == id).map(|(_, p)| *p)
+        };
+
+        let mut attribution = Attribution::default();
+        attribution.mark(day(2), &[buy], [(1, 10.0)], prices(&[(1, 102.0)]));
+        attribution.mark(day(3), &[sell], [(1, 6.0)], prices(&[(1, 105.0)]));
+        attribution.mark(day(4), &[], [(1, 6.0)], prices(&[]));
+        attribution.record_dividend(day(4), 1, 3.0);
+
+        let first = attribution.daily[&day(2)][&1];
+        assert_eq!((first.price, first.trading), (0.0, 19.0));
+        let second = attribution.daily[&day(3)][&1];
+        assert_eq!((second.price, second.trading), (30.0, 11.0));
+        assert_eq!(attribution.daily[&day(4)][&1], PnlBreakdown { dividends: 3.0, ..Default::default() });
+
+        let table = attribution.table();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table[0].1.total(), 19.0 + 41.0 + 3.0);
+
+        let mut tracker = MetricsTracker::new(10_000.0);
+        tracker.record_dividend(dt, 2, 5.0);
+        assert_eq!(tracker.attribution().table()[0].1.dividends, 5.0);
+    }
+
+    #[test]
+    fn test_turnover_participation_and_capacity() {
+        use crate::order::OrderSide;
+        use uuid::Uuid;
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut tracker = MetricsTracker::new(100_000.0);
+        tracker.record_value(start, 100_000.0);
+        // $20,000 traded on a $100,000 book, 1,000 shares of a 50,000 share bar
+        let buy = Transaction::new(1, Uuid::new_v4(), start, 1_000.0, 10.0, 0.0, OrderSide::Buy);
+        let sell = Transaction::new(2, Uuid::new_v4(), start, -500.0, 20.0, 0.0, OrderSide::Sell);
+        tracker.record_fill(&buy, 50_000.0);
+        tracker.record_fill(&sell, 0.0);
+        tracker.record_value(start + Duration::days(1), 100_000.0);
+
+        let metrics = tracker.calculate_metrics();
+        assert!((metrics.avg_daily_turnover - 0.1).abs() < 1e-12);
+        assert_eq!(metrics.max_participation, 0.02);
+        // 2% participation now, so 5% allows 2.5x the book
+        assert!((metrics.capacity.unwrap() - 250_000.0).abs() < 1e-6);
+
+        tracker.set_capacity_participation(0.01);
+        assert!((tracker.calculate_metrics().capacity.unwrap() - 50_000.0).abs() < 1e-6);
+        assert_eq!(MetricsTracker::new(1.0).calculate_metrics().capacity, None);
+    }
 }
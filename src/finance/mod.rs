@@ -4,25 +4,38 @@ pub mod account; // NEW: P0 - Account object for account-level metrics
 pub mod asset_restrictions; // NEW: Asset trading restrictions
 pub mod blotter;
 pub mod cancel_policy; // NEW: Order cancellation policies
+pub mod capacity; // Capacity-aware order scaling
 pub mod commission;
 pub mod constants; // NEW: Trading constants and defaults
 pub mod controls;
+pub mod dividends;
+pub mod exposure;
+pub mod interest;
 pub mod ledger; // NEW: P1 - Transaction tracking and P&L system
+pub mod lot_size;
+pub mod market_stats;
 pub mod metrics;
+pub mod model_registry;
+pub mod optimizer;
 pub mod portfolio;
+pub mod position_sizing;
+pub mod settlement;
 pub mod slippage;
+pub mod tax_lots;
 pub mod trading; // NEW: Trading controls and validations
 pub mod transaction; // NEW: Transaction type
 
 pub use account::Account;
 pub use asset_restrictions::{
-    CompositeRestrictions, HistoricalRestrictions, NoRestrictions, RestrictionReason,
-    Restrictions, SecurityListRestrictions, StaticRestrictions,
+    CompositeRestrictions, HistoricalRestrictions, NoRestrictions, RestrictedPositions,
+    RestrictionReason, Restrictions, SecurityListRestrictions, StaticRestrictions,
 };
 pub use blotter::{Blotter, Fill, TransactionLog};
 pub use cancel_policy::{CancelPolicy, EODCancel, EODCancelNext, NeverCancel};
+pub use capacity::{AssetCapacity, CapacityLimits, CapacityReport, CapacityTracker};
 pub use commission::{
-    CommissionModel, PerDollar, PerShare, PerTrade, TieredCommission, ZeroCommission,
+    CommissionModel, ContractFees, PerContract, PerDollar, PerShare, PerTrade, TieredCommission,
+    ZeroCommission,
 };
 pub use constants::{
     DEFAULT_CAPITAL, DEFAULT_COMMISSION_PER_SHARE, DEFAULT_MAX_LEVERAGE,
@@ -30,17 +43,36 @@ pub use constants::{
     MIN_PRICE_INCREMENT, TRADING_DAYS_PER_YEAR, TRADING_HOURS_PER_DAY, ZERO_TOLERANCE,
 };
 pub use controls::{
-    AccountControl, ControlManager, LongOnly, MaxLeverage as ControlMaxLeverage, MaxOrderCount,
+    AccountControl, ControlAction, ControlManager, DuplicateOrder, FatFinger, LongOnly, MaxDrawdown,
+    MaxLeverage as ControlMaxLeverage, MaxOrderCount, MaxOrdersPerBar, DEFAULT_MAX_ORDERS_PER_BAR,
     MaxOrderSize as ControlMaxOrderSize, MaxPositionSize as ControlMaxPositionSize, MinLeverage,
-    PositionConcentration, RestrictedList, SectorExposure, TradingControl as ControlTradingControl,
-    VolatilityLimit,
+    NoPrices, PatternDayTrader, PositionConcentration, PriceLookup, RestrictedList, SectorExposure, TradingControl as ControlTradingControl,
+    TradingHalt, VolatilityLimit,
 };
-pub use ledger::{CostBasisMethod, Ledger, LedgerPosition, Lot, PnLSummary};
-pub use metrics::{MetricsTracker, PerformanceMetrics, Trade};
+pub use dividends::DividendPolicy;
+pub use exposure::{Exposure, ExposureSnapshot, ExposureTracker, UNCLASSIFIED};
+pub use interest::{FixedRates, InterestModel, RateCurve, INTEREST_DAYS_PER_YEAR};
+pub use ledger::{
+    CostBasisMethod, Ledger, LedgerDivergence, LedgerPosition, Lot, LotId, LotSelection, PnLSummary,
+    ReconcileField,
+};
+pub use lot_size::{LotSize, LotSizes, Quantization, Rounding, DEFAULT_QUANTIZATION_WARNING};
+pub use market_stats::{MarketStats, MarketStatsService};
+pub use metrics::{
+    Attribution, MetricsTracker, PerformanceMetrics, PnlBreakdown, SessionTurnover, Trade, Turnover,
+    DEFAULT_CAPACITY_PARTICIPATION,
+};
+pub use model_registry::{ModelParams, ModelRegistry, ModelSpec};
+pub use optimizer::{hierarchical_risk_parity, optimize, Constraint, Covariance, Objective};
 pub use slippage::{
     FixedBasisPointsSlippage, LinearImpact, NoSlippage, SlippageModel, SquareRootImpact,
     VolumeShareSlippage,
 };
 pub use portfolio::{Portfolio, Position};
+pub use position_sizing::{
+    realized_volatility, FixedFractional, KellySizing, VolatilitySource, VolatilityTargeting,
+};
+pub use settlement::{DayTrades, Settlement, PDT_MAX_DAY_TRADES, PDT_MIN_EQUITY, PDT_WINDOW_SESSIONS};
+pub use tax_lots::{HoldingTerm, RealizedGain, TaxLots, TaxReport, TermSummary};
 pub use trading::{MaxLeverage, MaxOrderSize, MaxPositionSize, TradingControl};
 pub use transaction::Transaction;
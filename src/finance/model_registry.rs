@@ -0,0 +1,706 @@
+//! Registry of slippage and commission models selectable by name
+//!
+//! Run configurations describe models as a name plus parameters, e.g. in TOML:
+//!
+//! ```toml
+//! slippage = { model = "volume_share", volume_limit = 0.025, price_impact = 0.1 }
+//! commission = { model = "per_share", cost_per_share = 0.001, min_commission = 1.0 }
+//! ```
+//!
+//! or on the command line as `volume_share:volume_limit=0.025,price_impact=0.1`.
+//! [`ModelRegistry`] turns such a [`ModelSpec`] into an instantiated model. All
+//! built-in models and trading controls are pre-registered.
+//!
+//! Downstream crates add their own implementations to the process-wide registry
+//! (see [`ModelRegistry::global`]) with [`register_models!`](crate::register_models),
+//! after which they can be selected by name like any built-in:
+//!
+//! ```rust,ignore
+//! rusty_zipline::register_models! {
+//!     pub fn register_my_models {
+//!         slippage "spread" => |p| Ok(Arc::new(HalfSpread::new(p.require("spread")?))),
+//!         control "max_gross" => |p| Ok(Box::new(MaxGross::new(p.f64_or("limit", 2.0)?))),
+//!     }
+//! }
+//!
+//! fn main() {
+//!     register_my_models();
+//!     // ...
+//! }
+//! ```
+//!
+//! Strategy plugins cannot reach the host's registry this way, since a
+//! `cdylib` has its own copy of it; they list their models in
+//! [`export_algorithm!`](crate::export_algorithm) instead.
+
+use crate::error::{Result, ZiplineError};
+use crate::finance::commission::{
+    CommissionModel, ContractFees, PerContract, PerDollar, PerShare, PerTrade, TieredCommission,
+    ZeroCommission,
+};
+use crate::finance::controls::{
+    ControlAction, DuplicateOrder, FatFinger, LongOnly, MaxOrderCount, MaxOrderSize, MaxOrdersPerBar,
+    MaxPositionSize, PositionConcentration, RestrictedList, TradingControl,
+};
+use crate::finance::slippage::{
+    FixedBasisPointsSlippage, LinearImpact, NoSlippage, SlippageModel, SquareRootImpact,
+    VolumeShareSlippage,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+/// A model name and its parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSpec {
+    /// Registered model name
+    pub model: String,
+    /// Model parameters
+    #[serde(flatten)]
+    pub params: BTreeMap<String, serde_json::Value>,
+}
+
+impl ModelSpec {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Add a parameter
+    pub fn with_param(mut self, name: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.params.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+impl FromStr for ModelSpec {
+    type Err = ZiplineError;
+
+    /// Parse `name` or `name:key=value,key=value`
+    ///
+    /// Values are read as JSON where possible (numbers, booleans, arrays) and
+    /// as plain strings otherwise.
+    fn from_str(s: &str) -> Result<Self> {
+        let (model, params) = match s.split_once(':') {
+            Some((model, params)) => (model.trim(), params),
+            None => (s.trim(), ""),
+        };
+        if model.is_empty() {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Missing model name in '{}'",
+                s
+            )));
+        }
+
+        let mut spec = ModelSpec::new(model);
+        for param in split_top_level(params) {
+            let (key, value) = param.split_once('=').ok_or_else(|| {
+                ZiplineError::InvalidConfiguration(format!(
+                    "Expected key=value for model parameter, got '{}'",
+                    param
+                ))
+            })?;
+            let value = value.trim();
+            let value = serde_json::from_str(value)
+                .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+            spec.params.insert(key.trim().to_string(), value);
+        }
+
+        Ok(spec)
+    }
+}
+
+/// Split on commas that are not inside brackets
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
+
+/// Parameters handed to a model factory
+///
+/// Every parameter must be read by the factory; leftovers are reported as
+/// errors so that typos in a config file don't silently fall back to defaults.
+#[derive(Debug)]
+pub struct ModelParams<'a> {
+    model: &'a str,
+    values: &'a BTreeMap<String, serde_json::Value>,
+    used: HashSet<&'a str>,
+}
+
+impl<'a> ModelParams<'a> {
+    fn new(spec: &'a ModelSpec) -> Self {
+        Self {
+            model: &spec.model,
+            values: &spec.params,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Read an optional parameter
+    pub fn get<T: DeserializeOwned>(&mut self, name: &str) -> Result<Option<T>> {
+        let (key, value) = match self.values.get_key_value(name) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.used.insert(key.as_str());
+
+        serde_json::from_value(value.clone()).map(Some).map_err(|e| {
+            ZiplineError::InvalidConfiguration(format!(
+                "Invalid parameter '{}' for model '{}': {}",
+                name, self.model, e
+            ))
+        })
+    }
+
+    /// Read a required parameter
+    pub fn require<T: DeserializeOwned>(&mut self, name: &str) -> Result<T> {
+        self.get(name)?.ok_or_else(|| {
+            ZiplineError::InvalidConfiguration(format!(
+                "Model '{}' requires parameter '{}'",
+                self.model, name
+            ))
+        })
+    }
+
+    /// Read a numeric parameter, falling back to `default`
+    pub fn f64_or(&mut self, name: &str, default: f64) -> Result<f64> {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    fn finish(self) -> Result<()> {
+        let mut unknown: Vec<&str> = self
+            .values
+            .keys()
+            .map(String::as_str)
+            .filter(|k| !self.used.contains(k))
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        unknown.sort_unstable();
+        Err(ZiplineError::InvalidConfiguration(format!(
+            "Unknown parameter(s) for model '{}': {}",
+            self.model,
+            unknown.join(", ")
+        )))
+    }
+}
+
+/// Builds a slippage model from its parameters
+pub type SlippageFactory =
+    Arc<dyn Fn(&mut ModelParams) -> Result<Arc<dyn SlippageModel>> + Send + Sync>;
+
+/// Builds a commission model from its parameters
+pub type CommissionFactory =
+    Arc<dyn Fn(&mut ModelParams) -> Result<Arc<dyn CommissionModel>> + Send + Sync>;
+
+/// Builds a trading control from its parameters
+pub type ControlFactory =
+    Arc<dyn Fn(&mut ModelParams) -> Result<Box<dyn TradingControl>> + Send + Sync>;
+
+/// Name-to-factory registry for slippage, commission and trading control models
+#[derive(Clone)]
+pub struct ModelRegistry {
+    slippage: HashMap<String, SlippageFactory>,
+    commission: HashMap<String, CommissionFactory>,
+    controls: HashMap<String, ControlFactory>,
+}
+
+static GLOBAL_REGISTRY: OnceLock<RwLock<ModelRegistry>> = OnceLock::new();
+
+fn global_registry() -> &'static RwLock<ModelRegistry> {
+    GLOBAL_REGISTRY.get_or_init(|| RwLock::new(ModelRegistry::new()))
+}
+
+impl std::fmt::Debug for ModelRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelRegistry")
+            .field("slippage", &self.slippage_models())
+            .field("commission", &self.commission_models())
+            .field("controls", &self.control_models())
+            .finish()
+    }
+}
+
+impl ModelRegistry {
+    /// Create a registry with all built-in models registered
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register_slippage("none", |_| Ok(Arc::new(NoSlippage)));
+        registry.register_slippage("fixed_basis_points", |p| {
+            Ok(Arc::new(FixedBasisPointsSlippage::new(p.f64_or("basis_points", 5.0)?)))
+        });
+        registry.register_slippage("volume_share", |p| {
+            let price_impact = p.f64_or("price_impact", 0.1)?;
+            let volume_limit = p.f64_or("volume_limit", 0.025)?;
+            if !(0.0..=1.0).contains(&volume_limit) {
+                return Err(ZiplineError::InvalidConfiguration(format!(
+                    "volume_limit must be between 0 and 1, got {}",
+                    volume_limit
+                )));
+            }
+            Ok(Arc::new(VolumeShareSlippage::new(price_impact, volume_limit)))
+        });
+        registry.register_slippage("square_root", |p| {
+            Ok(Arc::new(SquareRootImpact::new(p.f64_or("coefficient", 0.1)?)))
+        });
+        registry.register_slippage("linear", |p| {
+            Ok(Arc::new(LinearImpact::new(p.f64_or("coefficient", 0.1)?)))
+        });
+
+        registry.register_commission("none", |_| Ok(Arc::new(ZeroCommission)));
+        registry.register_commission("per_share", |p| {
+            Ok(Arc::new(PerShare::with_min(
+                p.f64_or("cost_per_share", 0.001)?,
+                p.f64_or("min_commission", 0.0)?,
+            )))
+        });
+        registry.register_commission("per_trade", |p| {
+            Ok(Arc::new(PerTrade::new(p.require("cost")?)))
+        });
+        registry.register_commission("per_dollar", |p| {
+            Ok(Arc::new(PerDollar::with_min(
+                p.require("cost_per_dollar")?,
+                p.f64_or("min_commission", 0.0)?,
+            )))
+        });
+        registry.register_commission("per_contract", |p| {
+            let mut model = PerContract::new(
+                p.f64_or("cost_per_contract", 0.85)?,
+                p.f64_or("exchange_fee", 0.0)?,
+                p.f64_or("nfa_fee", 0.0)?,
+            )
+            .with_min(p.f64_or("min_commission", 0.0)?);
+            let overrides: HashMap<String, ContractFees> =
+                p.get("root_overrides")?.unwrap_or_default();
+            model.root_overrides.extend(overrides);
+            Ok(Arc::new(model))
+        });
+        registry.register_commission("tiered", |p| {
+            Ok(Arc::new(TieredCommission::new(
+                p.require("tiers")?,
+                p.f64_or("min_commission", 0.0)?,
+            )))
+        });
+
+        registry.register_control("long_only", |_| Ok(Box::new(LongOnly)));
+        registry.register_control("max_order_size", |p| {
+            let control = MaxOrderSize {
+                max_shares: p.get("max_shares")?,
+                max_notional: p.get("max_notional")?,
+            };
+            if control.max_shares.is_none() && control.max_notional.is_none() {
+                return Err(ZiplineError::InvalidConfiguration(
+                    "max_order_size requires max_shares or max_notional".to_string(),
+                ));
+            }
+            Ok(Box::new(control))
+        });
+        registry.register_control("max_position_size", |p| {
+            let control = MaxPositionSize {
+                max_shares: p.get("max_shares")?,
+                max_pct_portfolio: p.get("max_pct_portfolio")?,
+            };
+            if control.max_shares.is_none() && control.max_pct_portfolio.is_none() {
+                return Err(ZiplineError::InvalidConfiguration(
+                    "max_position_size requires max_shares or max_pct_portfolio".to_string(),
+                ));
+            }
+            Ok(Box::new(control))
+        });
+        registry.register_control("max_order_count", |p| {
+            let period_hours: i64 = p.get("period_hours")?.unwrap_or(24);
+            Ok(Box::new(MaxOrderCount::new(
+                p.require("max_count")?,
+                chrono::Duration::hours(period_hours),
+            )))
+        });
+        registry.register_control("max_orders_per_bar", |p| {
+            Ok(Box::new(MaxOrdersPerBar::new(p.require("max_count")?)))
+        });
+        registry.register_control("restricted_list", |p| {
+            let mut control = RestrictedList::new();
+            for asset_id in p.require::<Vec<u64>>("assets")? {
+                control.add_asset(asset_id);
+            }
+            Ok(Box::new(control))
+        });
+        registry.register_control("position_concentration", |p| {
+            Ok(Box::new(PositionConcentration::new(p.require("max_concentration")?)))
+        });
+        registry.register_control("duplicate_order", |p| {
+            let action: ControlAction = p.get("action")?.unwrap_or_default();
+            Ok(Box::new(DuplicateOrder::new(action)))
+        });
+        registry.register_control("fat_finger", |p| {
+            let mut control = FatFinger::new();
+            if let Some(multiple) = p.get("max_adv_multiple")? {
+                control = control.with_max_adv_multiple(multiple);
+            }
+            if let Some(multiple) = p.get("max_typical_multiple")? {
+                control = control.with_max_typical_multiple(multiple);
+            }
+            let window = p.get("window")?.unwrap_or(100);
+            let min_history = p.get("min_history")?.unwrap_or(10);
+            let action: ControlAction = p.get("action")?.unwrap_or_default();
+            Ok(Box::new(control.with_history(window, min_history).with_action(action)))
+        });
+
+        registry
+    }
+
+    /// Create a registry with no models registered
+    pub fn empty() -> Self {
+        Self {
+            slippage: HashMap::new(),
+            commission: HashMap::new(),
+            controls: HashMap::new(),
+        }
+    }
+
+    /// Snapshot of the process-wide registry
+    ///
+    /// Holds the built-in models plus everything added through
+    /// [`ModelRegistry::register_global`] or
+    /// [`register_models!`](crate::register_models). Config files and the CLI
+    /// resolve model names against this registry.
+    pub fn global() -> ModelRegistry {
+        global_registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Add models to the process-wide registry
+    pub fn register_global(register: impl FnOnce(&mut ModelRegistry)) {
+        let mut registry = global_registry()
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        register(&mut registry);
+    }
+
+    /// Register a slippage model, replacing any model of the same name
+    pub fn register_slippage<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&mut ModelParams) -> Result<Arc<dyn SlippageModel>> + Send + Sync + 'static,
+    {
+        self.slippage.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Register a commission model, replacing any model of the same name
+    pub fn register_commission<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&mut ModelParams) -> Result<Arc<dyn CommissionModel>> + Send + Sync + 'static,
+    {
+        self.commission.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Register a trading control, replacing any control of the same name
+    pub fn register_control<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&mut ModelParams) -> Result<Box<dyn TradingControl>> + Send + Sync + 'static,
+    {
+        self.controls.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Instantiate the slippage model described by `spec`
+    pub fn build_slippage(&self, spec: &ModelSpec) -> Result<Arc<dyn SlippageModel>> {
+        let factory = self.slippage.get(&spec.model).ok_or_else(|| {
+            ZiplineError::InvalidConfiguration(format!(
+                "Unknown slippage model '{}' (available: {})",
+                spec.model,
+                self.slippage_models().join(", ")
+            ))
+        })?;
+
+        let mut params = ModelParams::new(spec);
+        let model = factory(&mut params)?;
+        params.finish()?;
+        Ok(model)
+    }
+
+    /// Instantiate the commission model described by `spec`
+    pub fn build_commission(&self, spec: &ModelSpec) -> Result<Arc<dyn CommissionModel>> {
+        let factory = self.commission.get(&spec.model).ok_or_else(|| {
+            ZiplineError::InvalidConfiguration(format!(
+                "Unknown commission model '{}' (available: {})",
+                spec.model,
+                self.commission_models().join(", ")
+            ))
+        })?;
+
+        let mut params = ModelParams::new(spec);
+        let model = factory(&mut params)?;
+        params.finish()?;
+        Ok(model)
+    }
+
+    /// Instantiate the trading control described by `spec`
+    pub fn build_control(&self, spec: &ModelSpec) -> Result<Box<dyn TradingControl>> {
+        let factory = self.controls.get(&spec.model).ok_or_else(|| {
+            ZiplineError::InvalidConfiguration(format!(
+                "Unknown trading control '{}' (available: {})",
+                spec.model,
+                self.control_models().join(", ")
+            ))
+        })?;
+
+        let mut params = ModelParams::new(spec);
+        let control = factory(&mut params)?;
+        params.finish()?;
+        Ok(control)
+    }
+
+    /// Registered slippage model names, sorted
+    pub fn slippage_models(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.slippage.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Registered commission model names, sorted
+    pub fn commission_models(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.commission.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Registered trading control names, sorted
+    pub fn control_models(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.controls.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define a function registering models into the process-wide [`ModelRegistry`]
+///
+/// Each entry is `slippage`, `commission` or `control`, followed by the model
+/// name and a factory closure taking `&mut ModelParams`. Call the generated
+/// function once at startup, before config files are resolved.
+#[macro_export]
+macro_rules! register_models {
+    ($vis:vis fn $name:ident { $($kind:ident $model:literal => $factory:expr),* $(,)? }) => {
+        $vis fn $name() {
+            $crate::finance::ModelRegistry::register_global(|registry| {
+                $($crate::register_models!(@register registry, $kind, $model, $factory);)*
+            });
+        }
+    };
+    (@register $registry:ident, slippage, $model:literal, $factory:expr) => {
+        $registry.register_slippage($model, $factory)
+    };
+    (@register $registry:ident, commission, $model:literal, $factory:expr) => {
+        $registry.register_commission($model, $factory)
+    };
+    (@register $registry:ident, control, $model:literal, $factory:expr) => {
+        $registry.register_control($model, $factory)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::order::{Order, OrderSide};
+    use chrono::Utc;
+
+    fn order(quantity: f64) -> Order {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "TEST".to_string(), "TEST".to_string(), start_date);
+        Order::market(asset, OrderSide::Buy, quantity, Utc::now())
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let spec: ModelSpec = "volume_share:volume_limit=0.025,price_impact=0.1".parse().unwrap();
+        assert_eq!(
+            spec,
+            ModelSpec::new("volume_share")
+                .with_param("volume_limit", 0.025)
+                .with_param("price_impact", 0.1)
+        );
+
+        let spec: ModelSpec = "tiered:tiers=[[0,0.01],[1000,0.005]],min_commission=1".parse().unwrap();
+        assert_eq!(spec.params.len(), 2);
+        assert!(spec.params["tiers"].is_array());
+
+        assert_eq!("none".parse::<ModelSpec>().unwrap(), ModelSpec::new("none"));
+        assert!(":price_impact=0.1".parse::<ModelSpec>().is_err());
+        assert!("volume_share:price_impact".parse::<ModelSpec>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_spec() {
+        let json = r#"{"model": "volume_share", "volume_limit": 0.025, "price_impact": 0.1}"#;
+        let spec: ModelSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(spec.model, "volume_share");
+        assert_eq!(spec.params.len(), 2);
+    }
+
+    #[test]
+    fn test_build_builtin_models() {
+        let registry = ModelRegistry::new();
+
+        let spec = ModelSpec::new("volume_share")
+            .with_param("volume_limit", 0.025)
+            .with_param("price_impact", 0.1);
+        let slippage = registry.build_slippage(&spec).unwrap();
+        assert_eq!(slippage.name(), "VolumeShareSlippage");
+        // 1000 shares against 10,000 volume is capped at the 2.5% limit
+        let price = slippage.calculate_price(&order(1000.0), 100.0, 10_000.0);
+        assert!((price - 100.25).abs() < 1e-9);
+
+        let spec: ModelSpec = "per_share:cost_per_share=0.01,min_commission=1".parse().unwrap();
+        let commission = registry.build_commission(&spec).unwrap();
+        assert_eq!(commission.calculate(&order(50.0), 10.0, 50.0), 1.0);
+        assert_eq!(commission.calculate(&order(500.0), 10.0, 500.0), 5.0);
+
+        let spec: ModelSpec = "tiered:tiers=[[0,0.01],[1000,0.005]]".parse().unwrap();
+        let commission = registry.build_commission(&spec).unwrap();
+        assert_eq!(commission.calculate(&order(2000.0), 10.0, 2000.0), 10.0);
+
+        let json = r#"{"model": "per_contract", "cost_per_contract": 0.85, "exchange_fee": 1.28,
+            "nfa_fee": 0.02, "root_overrides": {"CL": {"exchange_fee": 1.5}}}"#;
+        let spec: ModelSpec = serde_json::from_str(json).unwrap();
+        let commission = registry.build_commission(&spec).unwrap();
+        assert_eq!(commission.name(), "PerContract");
+
+        for name in registry.slippage_models() {
+            assert!(registry.build_slippage(&ModelSpec::new(&name)).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_build_builtin_controls() {
+        use crate::algorithm::Context;
+        use crate::finance::NoPrices;
+
+        let registry = ModelRegistry::new();
+        let context = Context::new(100000.0);
+
+        let control = registry
+            .build_control(&"max_order_size:max_shares=100".parse().unwrap())
+            .unwrap();
+        assert!(control.validate_order_at(&order(50.0), &context, &NoPrices).is_ok());
+        assert!(control.validate_order_at(&order(200.0), &context, &NoPrices).is_err());
+
+        let control = registry
+            .build_control(&"restricted_list:assets=[1,2]".parse().unwrap())
+            .unwrap();
+        assert!(control.validate_order_at(&order(1.0), &context, &NoPrices).is_err());
+
+        let spec: ModelSpec = "fat_finger:max_adv_multiple=0.1,action=warn".parse().unwrap();
+        assert_eq!(registry.build_control(&spec).unwrap().name(), "FatFinger");
+        assert!(registry
+            .build_control(&"duplicate_order:action=ignore".parse().unwrap())
+            .is_err());
+        assert!(registry.build_control(&ModelSpec::new("max_order_size")).is_err());
+        assert!(registry.build_control(&ModelSpec::new("long_only")).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        let registry = ModelRegistry::new();
+
+        assert!(registry.build_slippage(&ModelSpec::new("magic")).is_err());
+        assert!(registry.build_commission(&ModelSpec::new("per_trade")).is_err());
+
+        // Typos are rejected rather than silently defaulted
+        let spec = ModelSpec::new("volume_share").with_param("volume_limt", 0.025);
+        assert!(registry.build_slippage(&spec).is_err());
+
+        let spec = ModelSpec::new("volume_share").with_param("volume_limit", "lots");
+        assert!(registry.build_slippage(&spec).is_err());
+
+        let spec = ModelSpec::new("volume_share").with_param("volume_limit", 2.0);
+        assert!(registry.build_slippage(&spec).is_err());
+    }
+
+    #[test]
+    fn test_register_user_model() {
+        struct HalfCent;
+
+        impl CommissionModel for HalfCent {
+            fn calculate(&self, _order: &Order, _fill_price: f64, fill_quantity: f64) -> f64 {
+                fill_quantity.abs() * 0.005
+            }
+
+            fn name(&self) -> &str {
+                "HalfCent"
+            }
+        }
+
+        let mut registry = ModelRegistry::new();
+        registry.register_commission("half_cent", |_| Ok(Arc::new(HalfCent)));
+        assert!(registry.commission_models().contains(&"half_cent".to_string()));
+
+        let model = registry.build_commission(&"half_cent".parse().unwrap()).unwrap();
+        assert_eq!(model.calculate(&order(200.0), 10.0, 200.0), 1.0);
+    }
+
+    #[test]
+    fn test_register_models_macro() {
+        struct NeverTrade;
+
+        impl TradingControl for NeverTrade {
+            fn validate_order(&self, _order: &Order, _context: &crate::algorithm::Context) -> Result<()> {
+                Err(ZiplineError::TradingControlViolation("never".to_string()))
+            }
+
+            fn name(&self) -> &str {
+                "NeverTrade"
+            }
+        }
+
+        crate::register_models! {
+            fn register_test_models {
+                slippage "test_macro_none" => |_| Ok(Arc::new(NoSlippage)),
+                commission "test_macro_flat" => |p| Ok(Arc::new(PerTrade::new(p.f64_or("cost", 1.0)?))),
+                control "test_macro_never" => |_| Ok(Box::new(NeverTrade)),
+            }
+        }
+
+        let never = "test_macro_never".to_string();
+        assert!(!ModelRegistry::global().control_models().contains(&never));
+        register_test_models();
+
+        let registry = ModelRegistry::global();
+        assert!(registry.build_slippage(&ModelSpec::new("test_macro_none")).is_ok());
+        let commission = registry
+            .build_commission(&"test_macro_flat:cost=2.5".parse().unwrap())
+            .unwrap();
+        assert_eq!(commission.calculate(&order(10.0), 10.0, 10.0), 2.5);
+        let control = registry.build_control(&ModelSpec::new("test_macro_never")).unwrap();
+        assert_eq!(control.name(), "NeverTrade");
+
+        // Built-ins remain available alongside user models
+        assert!(registry.build_slippage(&ModelSpec::new("volume_share")).is_ok());
+    }
+}
@@ -0,0 +1,667 @@
+//! Portfolio optimization for `order_optimal_portfolio_with`
+//!
+//! An [`Objective`] says what the portfolio should achieve, and
+//! [`Constraint`]s bound the weights it may take. [`optimize`] solves for the
+//! weights; `Context::order_optimal_portfolio_with` solves and trades to them
+//! in one call, in the manner of Quantopian's Optimize API:
+//!
+//! ```ignore
+//! let covariance = Covariance::from_history(&universe, data, 120)?;
+//! // or, from a pipeline window of returns
+//! let covariance = Covariance::estimate(ids, window.view(), CovarianceEstimator::LedoitWolf)?;
+//! context.order_optimal_portfolio_with(
+//!     Objective::MinimumVariance(covariance),
+//!     &[Constraint::LongOnly, Constraint::MaxWeight(0.1)],
+//!     &universe,
+//! )?;
+//! ```
+//!
+//! Variance objectives are fully invested: the weights add up to 1. Target
+//! weights are moved to the nearest weights meeting the constraints, so with
+//! no constraints they are traded as given.
+//!
+//! Problems are solved by accelerated projected gradient descent, projecting
+//! onto the constraints with Dykstra's algorithm; the maximum Sharpe ratio
+//! portfolio is found by searching the constrained efficient frontier.
+//!
+//! [`hierarchical_risk_parity`] needs no expected returns and no matrix
+//! inversion, so it stays stable when the covariance is noisy or nearly
+//! singular; as [`Objective::HierarchicalRiskParity`] its weights are moved to
+//! the nearest fully invested weights meeting the constraints.
+
+use crate::asset::Asset;
+use crate::data::BarData;
+use crate::error::{Result, ZiplineError};
+use crate::statistics::CovarianceEstimator;
+use crate::types::AssetId;
+use hashbrown::HashMap;
+use ndarray::{Array2, ArrayView2};
+
+/// Iterations allowed for a gradient descent or projection to converge
+const MAX_ITERATIONS: usize = 10_000;
+
+/// Change in weights below which an iteration has converged
+const TOLERANCE: f64 = 1e-12;
+
+/// Violation of a constraint tolerated in a solution
+const FEASIBILITY_TOLERANCE: f64 = 1e-6;
+
+/// Risk aversions searched for the maximum Sharpe ratio
+const FRONTIER_POINTS: usize = 25;
+
+/// Covariance of asset returns
+#[derive(Debug, Clone, PartialEq)]
+pub struct Covariance {
+    assets: Vec<AssetId>,
+    matrix: Vec<Vec<f64>>,
+}
+
+impl Covariance {
+    /// Covariance `matrix` of `assets`' returns, in the same order
+    pub fn new(assets: Vec<AssetId>, matrix: Vec<Vec<f64>>) -> Result<Self> {
+        let n = assets.len();
+        if matrix.len() != n || matrix.iter().any(|row| row.len() != n) {
+            return Err(ZiplineError::InvalidData(format!(
+                "Covariance of {} assets must be a {}x{} matrix",
+                n, n, n
+            )));
+        }
+        if matrix.iter().flatten().any(|v| !v.is_finite()) {
+            return Err(ZiplineError::InvalidData("Covariance matrix is not finite".to_string()));
+        }
+        Ok(Self { assets, matrix })
+    }
+
+    /// Covariance of `assets`' returns estimated from a window of them
+    ///
+    /// `returns` has one row per observation and one column per asset, in
+    /// the order of `assets`.
+    pub fn estimate(assets: Vec<AssetId>, returns: ArrayView2<f64>, estimator: CovarianceEstimator) -> Result<Self> {
+        if returns.ncols() != assets.len() {
+            return Err(ZiplineError::InvalidData(format!(
+                "Returns of {} assets given for {} assets",
+                returns.ncols(),
+                assets.len()
+            )));
+        }
+        let matrix = estimator.estimate(returns)?;
+        Self::new(assets, matrix.rows().into_iter().map(|row| row.to_vec()).collect())
+    }
+
+    /// Sample covariance of equally long return series
+    pub fn sample(returns: &[(AssetId, Vec<f64>)]) -> Result<Self> {
+        let observations = returns.first().map_or(0, |(_, series)| series.len());
+        if returns.iter().any(|(_, series)| series.len() != observations) {
+            return Err(ZiplineError::InvalidData(
+                "Sample covariance needs the same number of returns for every asset".to_string(),
+            ));
+        }
+        let window = Array2::from_shape_fn((observations, returns.len()), |(t, i)| returns[i].1[t]);
+        Self::estimate(
+            returns.iter().map(|(id, _)| *id).collect(),
+            window.view(),
+            CovarianceEstimator::Sample,
+        )
+    }
+
+    /// Sample covariance of the daily returns of the last `bars` closes
+    ///
+    /// Every asset's window is cut to the shortest one.
+    pub fn from_history(assets: &[Asset], data: &BarData, bars: usize) -> Result<Self> {
+        let mut returns = Vec::with_capacity(assets.len());
+        for asset in assets {
+            let prices = data.history_prices(asset, bars)?;
+            let series: Vec<f64> = prices.windows(2).map(|pair| pair[1] / pair[0] - 1.0).collect();
+            returns.push((asset.id, series));
+        }
+        let shortest = returns.iter().map(|(_, series)| series.len()).min().unwrap_or(0);
+        for (_, series) in &mut returns {
+            series.drain(..series.len() - shortest);
+        }
+        Self::sample(&returns)
+    }
+
+    /// Covariance implied by a factor model: `B F Bᵀ + D`
+    ///
+    /// `exposures` gives each asset's loading on every factor, `factors` the
+    /// factors' covariance and `specific` each asset's idiosyncratic variance
+    /// (zero where missing).
+    pub fn from_factor_model(
+        exposures: &[(AssetId, Vec<f64>)],
+        factors: &[Vec<f64>],
+        specific: &HashMap<AssetId, f64>,
+    ) -> Result<Self> {
+        let k = factors.len();
+        if factors.iter().any(|row| row.len() != k) || exposures.iter().any(|(_, b)| b.len() != k) {
+            return Err(ZiplineError::InvalidData(format!(
+                "Factor model needs a {}x{} factor covariance and {} exposures per asset",
+                k, k, k
+            )));
+        }
+        let n = exposures.len();
+        let mut matrix = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                let (bi, bj) = (&exposures[i].1, &exposures[j].1);
+                matrix[i][j] = (0..k)
+                    .map(|f| (0..k).map(|g| bi[f] * factors[f][g] * bj[g]).sum::<f64>())
+                    .sum();
+            }
+            matrix[i][i] += specific.get(&exposures[i].0).copied().unwrap_or(0.0);
+        }
+        Self::new(exposures.iter().map(|(id, _)| *id).collect(), matrix)
+    }
+
+    /// Assets covered, in matrix order
+    pub fn assets(&self) -> &[AssetId] {
+        &self.assets
+    }
+
+    pub fn matrix(&self) -> &[Vec<f64>] {
+        &self.matrix
+    }
+
+    /// Variance of a portfolio with `weights` in matrix order
+    pub fn portfolio_variance(&self, weights: &[f64]) -> f64 {
+        weights
+            .iter()
+            .zip(self.times(weights))
+            .map(|(w, sw)| w * sw)
+            .sum()
+    }
+
+    /// The matrix times `weights`
+    fn times(&self, weights: &[f64]) -> Vec<f64> {
+        self.matrix
+            .iter()
+            .map(|row| row.iter().zip(weights).map(|(c, w)| c * w).sum())
+            .collect()
+    }
+
+    /// Bound on the largest eigenvalue: the largest absolute row sum
+    fn spectral_bound(&self) -> f64 {
+        self.matrix
+            .iter()
+            .map(|row| row.iter().map(|c| c.abs()).sum::<f64>())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// What an optimal portfolio achieves
+#[derive(Debug, Clone)]
+pub enum Objective {
+    /// Weights as close as the constraints allow to these
+    TargetWeights(HashMap<AssetId, f64>),
+    /// Least variance, fully invested
+    MinimumVariance(Covariance),
+    /// Highest ratio of expected excess return to volatility, fully invested
+    MaximumSharpe {
+        expected_returns: HashMap<AssetId, f64>,
+        covariance: Covariance,
+        risk_free_rate: f64,
+    },
+    /// Hierarchical risk parity weights, fully invested
+    HierarchicalRiskParity(Covariance),
+}
+
+impl From<HashMap<AssetId, f64>> for Objective {
+    fn from(weights: HashMap<AssetId, f64>) -> Self {
+        Objective::TargetWeights(weights)
+    }
+}
+
+/// A bound on optimal weights
+#[derive(Debug, Clone)]
+pub enum Constraint {
+    /// No short positions
+    LongOnly,
+    /// No asset's weight above this, long or short
+    MaxWeight(f64),
+    /// Net weight of each sector within ±`max`; unlabelled assets are free
+    SectorCap {
+        sectors: HashMap<AssetId, String>,
+        max: f64,
+    },
+}
+
+/// The set of weights meeting every constraint, for assets in a fixed order
+struct Feasible {
+    /// Total weight, if fully invested
+    budget: Option<f64>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    /// Members and cap of every sector
+    groups: Vec<(Vec<usize>, f64)>,
+}
+
+impl Feasible {
+    fn new(assets: &[AssetId], constraints: &[Constraint], budget: Option<f64>) -> Result<Self> {
+        let n = assets.len();
+        let mut feasible = Self {
+            budget,
+            lower: vec![f64::NEG_INFINITY; n],
+            upper: vec![f64::INFINITY; n],
+            groups: Vec::new(),
+        };
+        for constraint in constraints {
+            match constraint {
+                Constraint::LongOnly => feasible.lower.iter_mut().for_each(|l| *l = l.max(0.0)),
+                Constraint::MaxWeight(max) => {
+                    if max.is_nan() || *max < 0.0 {
+                        return Err(ZiplineError::InvalidConfiguration(format!(
+                            "Maximum weight must be non-negative, got {}",
+                            max
+                        )));
+                    }
+                    feasible.lower.iter_mut().for_each(|l| *l = l.max(-max));
+                    feasible.upper.iter_mut().for_each(|u| *u = u.min(*max));
+                }
+                Constraint::SectorCap { sectors, max } => {
+                    if max.is_nan() || *max < 0.0 {
+                        return Err(ZiplineError::InvalidConfiguration(format!(
+                            "Sector cap must be non-negative, got {}",
+                            max
+                        )));
+                    }
+                    let mut members: HashMap<&str, Vec<usize>> = HashMap::new();
+                    for (index, asset) in assets.iter().enumerate() {
+                        if let Some(sector) = sectors.get(asset) {
+                            members.entry(sector.as_str()).or_default().push(index);
+                        }
+                    }
+                    let mut members: Vec<(&str, Vec<usize>)> = members.into_iter().collect();
+                    members.sort_unstable();
+                    feasible.groups.extend(members.into_iter().map(|(_, m)| (m, *max)));
+                }
+            }
+        }
+        Ok(feasible)
+    }
+
+    /// Nearest point to `point` meeting every constraint (Dykstra's algorithm)
+    fn project(&self, point: &[f64]) -> Vec<f64> {
+        let sets = 1 + usize::from(self.budget.is_some()) + self.groups.len();
+        let mut increments = vec![vec![0.0; point.len()]; sets];
+        let mut x = point.to_vec();
+        for _ in 0..MAX_ITERATIONS {
+            let previous = x.clone();
+            for (set, increment) in increments.iter_mut().enumerate() {
+                let y: Vec<f64> = x.iter().zip(increment.iter()).map(|(a, b)| a + b).collect();
+                let projected = self.project_onto(set, &y);
+                for i in 0..y.len() {
+                    increment[i] = y[i] - projected[i];
+                }
+                x = projected;
+            }
+            if sets == 1 || max_change(&x, &previous) < TOLERANCE {
+                break;
+            }
+        }
+        x
+    }
+
+    fn project_onto(&self, set: usize, y: &[f64]) -> Vec<f64> {
+        if set == 0 {
+            return y
+                .iter()
+                .zip(self.lower.iter().zip(&self.upper))
+                .map(|(v, (l, u))| v.clamp(*l, *u))
+                .collect();
+        }
+        let mut x = y.to_vec();
+        let (members, target) = match (set, self.budget) {
+            (1, Some(budget)) => {
+                let shift = (budget - y.iter().sum::<f64>()) / y.len() as f64;
+                x.iter_mut().for_each(|v| *v += shift);
+                return x;
+            }
+            _ => &self.groups[set - 1 - usize::from(self.budget.is_some())],
+        };
+        let total: f64 = members.iter().map(|i| y[*i]).sum();
+        let excess = total - total.clamp(-target, *target);
+        for i in members {
+            x[*i] -= excess / members.len() as f64;
+        }
+        x
+    }
+
+    /// Whether `weights` meet every constraint
+    fn contains(&self, weights: &[f64]) -> bool {
+        let tolerance = FEASIBILITY_TOLERANCE;
+        let within_bounds = weights
+            .iter()
+            .zip(self.lower.iter().zip(&self.upper))
+            .all(|(w, (l, u))| *w >= l - tolerance && *w <= u + tolerance);
+        let within_budget = self
+            .budget
+            .is_none_or(|budget| (weights.iter().sum::<f64>() - budget).abs() <= tolerance);
+        let within_groups = self
+            .groups
+            .iter()
+            .all(|(members, max)| members.iter().map(|i| weights[*i]).sum::<f64>().abs() <= max + tolerance);
+        within_bounds && within_budget && within_groups
+    }
+}
+
+fn max_change(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f64::max)
+}
+
+/// Minimize `wᵀ Σ w / 2 · risk_aversion - μᵀ w` over the feasible set (FISTA)
+fn mean_variance(covariance: &Covariance, expected: &[f64], risk_aversion: f64, feasible: &Feasible) -> Vec<f64> {
+    let n = expected.len();
+    let lipschitz = (risk_aversion * covariance.spectral_bound()).max(f64::MIN_POSITIVE);
+    let step = 1.0 / lipschitz;
+    let mut weights = feasible.project(&vec![1.0 / n as f64; n]);
+    let mut momentum_point = weights.clone();
+    let mut t = 1.0_f64;
+    for _ in 0..MAX_ITERATIONS {
+        let gradient: Vec<f64> = covariance
+            .times(&momentum_point)
+            .iter()
+            .zip(expected)
+            .map(|(sw, mu)| risk_aversion * sw - mu)
+            .collect();
+        let descended: Vec<f64> = momentum_point.iter().zip(&gradient).map(|(w, g)| w - step * g).collect();
+        let next = feasible.project(&descended);
+        let t_next = (1.0 + (1.0 + 4.0 * t * t).sqrt()) / 2.0;
+        momentum_point = next
+            .iter()
+            .zip(&weights)
+            .map(|(a, b)| a + (t - 1.0) / t_next * (a - b))
+            .collect();
+        let change = max_change(&next, &weights);
+        weights = next;
+        t = t_next;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+    weights
+}
+
+/// Weights meeting `constraints` that best achieve `objective`
+///
+/// # Errors
+/// * `InvalidData` - If expected returns are missing for a covaried asset, or
+///   an asset has no variance under hierarchical risk parity
+/// * `InvalidConfiguration` - If the constraints cannot all be met
+pub fn optimize(objective: &Objective, constraints: &[Constraint]) -> Result<HashMap<AssetId, f64>> {
+    let (assets, weights, feasible) = match objective {
+        Objective::TargetWeights(targets) => {
+            let mut assets: Vec<AssetId> = targets.keys().copied().collect();
+            assets.sort_unstable();
+            let feasible = Feasible::new(&assets, constraints, None)?;
+            let targets: Vec<f64> = assets.iter().map(|id| targets[id]).collect();
+            (assets, feasible.project(&targets), feasible)
+        }
+        Objective::MinimumVariance(covariance) => {
+            let assets = covariance.assets().to_vec();
+            let feasible = Feasible::new(&assets, constraints, Some(1.0))?;
+            let weights = mean_variance(covariance, &vec![0.0; assets.len()], 1.0, &feasible);
+            (assets, weights, feasible)
+        }
+        Objective::MaximumSharpe {
+            expected_returns,
+            covariance,
+            risk_free_rate,
+        } => {
+            let assets = covariance.assets().to_vec();
+            let expected = assets
+                .iter()
+                .map(|id| {
+                    expected_returns.get(id).copied().ok_or_else(|| {
+                        ZiplineError::InvalidData(format!("No expected return for asset {}", id))
+                    })
+                })
+                .collect::<Result<Vec<f64>>>()?;
+            let feasible = Feasible::new(&assets, constraints, Some(1.0))?;
+            let weights = maximum_sharpe(covariance, &expected, *risk_free_rate, &feasible);
+            (assets, weights, feasible)
+        }
+        Objective::HierarchicalRiskParity(covariance) => {
+            let assets = covariance.assets().to_vec();
+            let feasible = Feasible::new(&assets, constraints, Some(1.0))?;
+            let weights = feasible.project(&hrp_weights(covariance)?);
+            (assets, weights, feasible)
+        }
+    };
+
+    if !feasible.contains(&weights) {
+        return Err(ZiplineError::InvalidConfiguration(
+            "Portfolio constraints cannot all be met".to_string(),
+        ));
+    }
+    Ok(assets.into_iter().zip(weights).collect())
+}
+
+/// Frontier portfolio with the best Sharpe ratio
+///
+/// A log-spaced grid of risk aversions is searched, then the best point is
+/// refined by golden-section search between its neighbours.
+fn maximum_sharpe(covariance: &Covariance, expected: &[f64], risk_free_rate: f64, feasible: &Feasible) -> Vec<f64> {
+    let solve = |log_aversion: f64| {
+        let weights = mean_variance(covariance, expected, 10f64.powf(log_aversion), feasible);
+        let excess: f64 = weights.iter().zip(expected).map(|(w, mu)| w * mu).sum::<f64>() - risk_free_rate;
+        let volatility = covariance.portfolio_variance(&weights).max(0.0).sqrt();
+        let sharpe = if volatility > 0.0 { excess / volatility } else { f64::NEG_INFINITY };
+        (sharpe, weights)
+    };
+
+    let (low, high) = (-2.0, 4.0);
+    let grid: Vec<f64> = (0..FRONTIER_POINTS)
+        .map(|i| low + (high - low) * i as f64 / (FRONTIER_POINTS - 1) as f64)
+        .collect();
+    let scores: Vec<f64> = grid.iter().map(|x| solve(*x).0).collect();
+    let best = (0..grid.len()).fold(0, |best, i| if scores[i] > scores[best] { i } else { best });
+
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (grid[best.saturating_sub(1)], grid[(best + 1).min(grid.len() - 1)]);
+    for _ in 0..40 {
+        let c = b - ratio * (b - a);
+        let d = a + ratio * (b - a);
+        if solve(c).0 >= solve(d).0 {
+            b = d;
+        } else {
+            a = c;
+        }
+    }
+    let (refined, weights) = solve((a + b) / 2.0);
+    if refined >= scores[best] {
+        weights
+    } else {
+        solve(grid[best]).1
+    }
+}
+
+/// Hierarchical risk parity weights (López de Prado)
+///
+/// Assets are clustered by the distance between their correlations, ordered
+/// so that similar assets sit together, and the weight is split recursively
+/// between the two halves of the order in inverse proportion to each half's
+/// variance. Weights are long only and add up to 1.
+pub fn hierarchical_risk_parity(covariance: &Covariance) -> Result<HashMap<AssetId, f64>> {
+    let weights = hrp_weights(covariance)?;
+    Ok(covariance.assets().iter().copied().zip(weights).collect())
+}
+
+fn hrp_weights(covariance: &Covariance) -> Result<Vec<f64>> {
+    let matrix = covariance.matrix();
+    let n = matrix.len();
+    if let Some(i) = (0..n).find(|i| matrix[*i][*i].is_nan() || matrix[*i][*i] <= 0.0) {
+        return Err(ZiplineError::InvalidData(format!(
+            "Hierarchical risk parity needs a positive variance for asset {}",
+            covariance.assets()[i]
+        )));
+    }
+
+    // Correlation distance, then the distance between assets' distance profiles
+    let distance: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    let correlation = matrix[i][j] / (matrix[i][i] * matrix[j][j]).sqrt();
+                    ((1.0 - correlation.clamp(-1.0, 1.0)) / 2.0).sqrt()
+                })
+                .collect()
+        })
+        .collect();
+    let profile: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    distance[i]
+                        .iter()
+                        .zip(&distance[j])
+                        .map(|(a, b)| (a - b).powi(2))
+                        .sum::<f64>()
+                        .sqrt()
+                })
+                .collect()
+        })
+        .collect();
+
+    let order = quasi_diagonal_order(&profile);
+    let mut weights = vec![1.0; n];
+    let mut clusters = vec![order.as_slice()];
+    while let Some(cluster) = clusters.pop() {
+        if cluster.len() < 2 {
+            continue;
+        }
+        let (left, right) = cluster.split_at(cluster.len() / 2);
+        let (left_variance, right_variance) = (cluster_variance(matrix, left), cluster_variance(matrix, right));
+        let alpha = 1.0 - left_variance / (left_variance + right_variance);
+        left.iter().for_each(|i| weights[*i] *= alpha);
+        right.iter().for_each(|i| weights[*i] *= 1.0 - alpha);
+        clusters.push(left);
+        clusters.push(right);
+    }
+    Ok(weights)
+}
+
+/// Leaves of the single-linkage dendrogram over `distance`, left to right
+fn quasi_diagonal_order(distance: &[Vec<f64>]) -> Vec<usize> {
+    // Every cluster is the ordered list of its leaves; merging concatenates
+    let mut clusters: Vec<Vec<usize>> = (0..distance.len()).map(|i| vec![i]).collect();
+    while clusters.len() > 1 {
+        let linkage = |a: &[usize], b: &[usize]| {
+            a.iter()
+                .flat_map(|i| b.iter().map(move |j| distance[*i][*j]))
+                .fold(f64::INFINITY, f64::min)
+        };
+        let mut closest = (0, 1, f64::INFINITY);
+        for a in 0..clusters.len() {
+            for b in a + 1..clusters.len() {
+                let d = linkage(&clusters[a], &clusters[b]);
+                if d < closest.2 {
+                    closest = (a, b, d);
+                }
+            }
+        }
+        let merged = clusters.remove(closest.1);
+        clusters[closest.0].extend(merged);
+    }
+    clusters.pop().unwrap_or_default()
+}
+
+/// Variance of the inverse-variance portfolio of `members`
+fn cluster_variance(matrix: &[Vec<f64>], members: &[usize]) -> f64 {
+    let inverse: Vec<f64> = members.iter().map(|i| 1.0 / matrix[*i][*i]).collect();
+    let total: f64 = inverse.iter().sum();
+    let weights: Vec<f64> = inverse.iter().map(|w| w / total).collect();
+    members
+        .iter()
+        .zip(&weights)
+        .map(|(i, wi)| {
+            members
+                .iter()
+                .zip(&weights)
+                .map(|(j, wj)| wi * matrix[*i][*j] * wj)
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_variance_and_max_sharpe_under_constraints() {
+        // Uncorrelated assets with 20%, 10% and 10% volatility
+        let covariance = Covariance::new(
+            vec![1, 2, 3],
+            vec![vec![0.04, 0.0, 0.0], vec![0.0, 0.01, 0.0], vec![0.0, 0.0, 0.01]],
+        )
+        .unwrap();
+        let weights = optimize(&Objective::MinimumVariance(covariance.clone()), &[]).unwrap();
+        for (id, expected) in [(1, 1.0 / 9.0), (2, 4.0 / 9.0), (3, 4.0 / 9.0)] {
+            assert!((weights[&id] - expected).abs() < 1e-6, "{} {}", id, weights[&id]);
+        }
+
+        // Assets 2 and 3 share a sector capped at 60%
+        let sectors: HashMap<AssetId, String> =
+            [(2, "Tech".to_string()), (3, "Tech".to_string())].into_iter().collect();
+        let constraints = [Constraint::LongOnly, Constraint::SectorCap { sectors, max: 0.6 }];
+        let weights = optimize(&Objective::MinimumVariance(covariance.clone()), &constraints).unwrap();
+        assert!((weights[&1] - 0.4).abs() < 1e-6 && (weights[&2] - 0.3).abs() < 1e-6);
+
+        // Tangency weights are proportional to Σ⁻¹μ: 2.5, 5 and 2.5
+        let expected_returns: HashMap<AssetId, f64> = [(1, 0.1), (2, 0.05), (3, 0.025)].into_iter().collect();
+        let objective = Objective::MaximumSharpe { expected_returns, covariance, risk_free_rate: 0.0 };
+        let weights = optimize(&objective, &[Constraint::LongOnly]).unwrap();
+        for (id, expected) in [(1, 0.25), (2, 0.5), (3, 0.25)] {
+            assert!((weights[&id] - expected).abs() < 1e-3, "{} {}", id, weights[&id]);
+        }
+        let weights = optimize(&objective, &[Constraint::LongOnly, Constraint::MaxWeight(0.4)]).unwrap();
+        assert!(weights.values().all(|w| *w <= 0.4 + 1e-6));
+        assert!(optimize(&objective, &[Constraint::MaxWeight(0.3)]).is_err());
+
+        // Target weights pass through, clipped to the constraints
+        let targets: HashMap<AssetId, f64> = [(1, 0.7), (2, -0.2)].into_iter().collect();
+        let weights = optimize(&targets.clone().into(), &[]).unwrap();
+        assert_eq!(weights, targets);
+        let weights = optimize(&targets.into(), &[Constraint::LongOnly, Constraint::MaxWeight(0.5)]).unwrap();
+        assert_eq!((weights[&1], weights[&2]), (0.5, 0.0));
+
+        let factor = Covariance::from_factor_model(
+            &[(1, vec![1.0]), (2, vec![0.5])],
+            &[vec![0.04]],
+            &[(1, 0.01)].into_iter().collect(),
+        )
+        .unwrap();
+        let implied = [[0.05, 0.02], [0.02, 0.01]];
+        for (row, expected) in factor.matrix().iter().zip(implied) {
+            assert!(row.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_hierarchical_risk_parity() {
+        // Two pairs of highly correlated assets, interleaved, the first pair
+        // twice as volatile as the second
+        let covariance = Covariance::new(
+            vec![1, 2, 3, 4],
+            vec![
+                vec![0.04, 0.0, 0.036, 0.0],
+                vec![0.0, 0.01, 0.0, 0.009],
+                vec![0.036, 0.0, 0.04, 0.0],
+                vec![0.0, 0.009, 0.0, 0.01],
+            ],
+        )
+        .unwrap();
+        // The pairs are split 0.038 to 0.0095 in variance, so 20% to 80%
+        let weights = hierarchical_risk_parity(&covariance).unwrap();
+        for (id, expected) in [(1, 0.1), (2, 0.4), (3, 0.1), (4, 0.4)] {
+            assert!((weights[&id] - expected).abs() < 1e-12, "{} {}", id, weights[&id]);
+        }
+
+        let objective = Objective::HierarchicalRiskParity(covariance);
+        let capped = optimize(&objective, &[Constraint::MaxWeight(0.3)]).unwrap();
+        assert!(capped.values().all(|w| *w <= 0.3 + 1e-6));
+        assert!((capped.values().sum::<f64>() - 1.0).abs() < 1e-6);
+
+        let flat = Covariance::new(vec![1, 2], vec![vec![0.04, 0.0], vec![0.0, 0.0]]).unwrap();
+        assert!(hierarchical_risk_parity(&flat).is_err());
+    }
+}
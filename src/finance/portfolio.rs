@@ -1,8 +1,16 @@
 //! Portfolio and position tracking
+//!
+//! A [`Portfolio`] records every fill in its [`Ledger`] and derives its
+//! positions and cash from it, so the two cannot drift apart. The public
+//! `positions` and `cash` fields remain as a view: they are rewritten from the
+//! ledger after each fill. Edits made to them directly, such as seeding a
+//! position, are taken into the ledger as opening balances at the next fill.
 
 use crate::asset::Asset;
+use crate::finance::ledger::{CostBasisMethod, Ledger};
+use crate::finance::transaction::Transaction;
 use crate::order::{Order, OrderSide};
-use crate::types::{Cash, Price, Quantity, Timestamp};
+use crate::types::{Cash, Price, Quantity, Timestamp, QUANTITY_TOLERANCE};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
@@ -66,7 +74,7 @@ impl Position {
 
     /// Check if position is flat (closed)
     pub fn is_flat(&self) -> bool {
-        self.quantity.abs() < f64::EPSILON
+        self.quantity.abs() < QUANTITY_TOLERANCE
     }
 }
 
@@ -80,15 +88,23 @@ pub struct Portfolio {
     /// Current positions
     pub positions: HashMap<u64, Position>,
     /// Portfolio value history
+    #[serde(default)]
     pub value_history: Vec<(Timestamp, Cash)>,
     /// Positions value (market value of all positions)
+    #[serde(default)]
     pub positions_value: Cash,
     /// Total portfolio value (cash + positions)
+    #[serde(default)]
     pub portfolio_value: Cash,
     /// Total profit/loss
+    #[serde(default)]
     pub pnl: Cash,
     /// Total returns percentage
+    #[serde(default)]
     pub returns: f64,
+    /// Fills from which positions and cash are derived
+    #[serde(default)]
+    ledger: Ledger,
 }
 
 impl Portfolio {
@@ -103,9 +119,15 @@ impl Portfolio {
             portfolio_value: starting_cash,
             pnl: 0.0,
             returns: 0.0,
+            ledger: Ledger::default(),
         }
     }
 
+    /// Ledger of fills behind the positions and cash
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
     /// Get position for an asset
     pub fn get_position(&self, asset_id: u64) -> Option<&Position> {
         self.positions.get(&asset_id)
@@ -118,37 +140,84 @@ impl Portfolio {
 
     /// Execute a fill on an order
     pub fn execute_order(&mut self, order: &Order, fill_price: Price, commission: Cash) {
-        let cost = fill_price * order.filled;
-        let total_cost = match order.side {
-            OrderSide::Buy => cost + commission,
-            OrderSide::Sell => -(cost - commission),
+        let amount = match order.side {
+            OrderSide::Buy => order.filled,
+            OrderSide::Sell => -order.filled,
         };
+        let transaction = Transaction::new(
+            order.asset.id,
+            order.id,
+            order.updated_at,
+            amount,
+            fill_price,
+            commission,
+            order.side,
+        );
+        self.record_fill(&order.asset, transaction);
+    }
 
-        // Update cash
-        self.cash -= total_cost;
-
-        // Update or create position
-        let position = self
-            .positions
-            .entry(order.asset.id)
-            .or_insert_with(|| Position::new(order.asset.clone(), 0.0, 0.0, fill_price));
-
-        match order.side {
-            OrderSide::Buy => {
-                position.quantity += order.filled;
-                position.cost_basis += cost;
+    /// Apply a fill in `asset` to the ledger, then the position and cash
+    pub(crate) fn record_fill(&mut self, asset: &Asset, transaction: Transaction) {
+        let asset_id = asset.id;
+        let fill_price = transaction.price;
+        self.absorb_direct_edits(asset_id, transaction.dt);
+        self.ledger
+            .record_transaction(transaction)
+            .expect("ledger accepts long and short fills");
+
+        // Rewrite the view of the traded asset and cash from the ledger
+        self.cash = self.starting_cash + self.ledger.cash_flow();
+        match self.ledger.get_position(asset_id).filter(|p| p.quantity.abs() >= QUANTITY_TOLERANCE) {
+            Some(held) => {
+                let position = self
+                    .positions
+                    .entry(asset_id)
+                    .or_insert_with(|| Position::new(asset.clone(), 0.0, 0.0, fill_price));
+                position.quantity = held.quantity;
+                position.cost_basis = held.net_cost();
+                position.last_price = fill_price;
             }
-            OrderSide::Sell => {
-                position.quantity -= order.filled;
-                position.cost_basis -= cost;
+            None => {
+                self.positions.remove(&asset_id);
             }
         }
+    }
+
+    /// Choose the lots sales of `asset_id` close by `method` rather than FIFO
+    pub fn set_cost_basis_method(&mut self, asset_id: u64, method: CostBasisMethod) {
+        self.ledger.set_cost_basis_method(asset_id, method);
+    }
+
+    /// Record realized gains by tax lot in the ledger from the next fill on
+    pub fn track_tax_lots(&mut self) {
+        self.ledger.enable_tax_lots();
+    }
+
+    /// Credit (or, if negative, debit) cash outside of trading, e.g. interest
+    pub fn adjust_cash(&mut self, amount: Cash) {
+        self.cash += amount;
+        self.ledger.adjust_cash(amount);
+    }
 
-        position.last_price = fill_price;
+    /// Take direct edits of `cash` or the position in `asset_id` into the ledger
+    fn absorb_direct_edits(&mut self, asset_id: u64, dt: Timestamp) {
+        let ledger_cash = self.starting_cash + self.ledger.cash_flow();
+        if self.cash != ledger_cash {
+            self.ledger.adjust_cash(self.cash - ledger_cash);
+        }
 
-        // Remove flat positions
-        if position.is_flat() {
-            self.positions.remove(&order.asset.id);
+        let (quantity, cost_basis) = self
+            .positions
+            .get(&asset_id)
+            .map(|p| (p.quantity, p.cost_basis))
+            .unwrap_or((0.0, 0.0));
+        let (ledger_quantity, ledger_cost) = self
+            .ledger
+            .get_position(asset_id)
+            .map(|p| (p.quantity, p.net_cost()))
+            .unwrap_or((0.0, 0.0));
+        if quantity != ledger_quantity || cost_basis != ledger_cost {
+            self.ledger.set_opening_position(asset_id, quantity, cost_basis, dt);
         }
     }
 
@@ -181,6 +250,25 @@ impl Portfolio {
         self.positions.len()
     }
 
+    /// Market value of long positions
+    pub fn long_exposure(&self) -> Cash {
+        self.positions
+            .values()
+            .map(|p| p.market_value())
+            .filter(|v| *v > 0.0)
+            .sum()
+    }
+
+    /// Absolute market value of short positions
+    pub fn short_exposure(&self) -> Cash {
+        -self
+            .positions
+            .values()
+            .map(|p| p.market_value())
+            .filter(|v| *v < 0.0)
+            .sum::<Cash>()
+    }
+
     /// Get total leverage
     pub fn leverage(&self) -> f64 {
         if self.portfolio_value == 0.0 {
@@ -256,4 +344,38 @@ use chrono::NaiveDate;
         assert_eq!(portfolio.pnl, 1000.0);
         assert_eq!(portfolio.returns, 0.01);
     }
+
+    #[test]
+    fn test_positions_derived_from_ledger() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let fill = |side, quantity| {
+            let mut order = Order::market(asset.clone(), side, quantity, Utc::now());
+            order.fill(quantity, Utc::now());
+            order
+        };
+
+        // Sell through zero into a short, then cover
+        let mut portfolio = Portfolio::new(10_000.0);
+        portfolio.execute_order(&fill(OrderSide::Buy, 10.0), 100.0, 1.0);
+        portfolio.execute_order(&fill(OrderSide::Sell, 30.0), 110.0, 1.0);
+        assert_eq!(portfolio.get_position(1).unwrap().quantity, -20.0);
+        assert_eq!(portfolio.get_position(1).unwrap().cost_basis, -2200.0);
+        assert_eq!(portfolio.cash, 10_000.0 - 1000.0 + 3300.0 - 2.0);
+        portfolio.ledger().reconcile(&portfolio).unwrap();
+
+        portfolio.execute_order(&fill(OrderSide::Buy, 20.0), 105.0, 1.0);
+        assert_eq!(portfolio.num_positions(), 0);
+        assert_eq!(portfolio.cash, 10_000.0 + 200.0 - 3.0);
+        assert_eq!(portfolio.ledger().get_pnl_summary().total_trades, 2);
+
+        // A hand-seeded position and cash become opening balances
+        let mut seeded = Portfolio::new(10_000.0);
+        seeded.cash = 5_000.0;
+        seeded.positions.insert(1, Position::new(asset.clone(), 50.0, 5_000.0, 100.0));
+        seeded.execute_order(&fill(OrderSide::Sell, 20.0), 120.0, 0.0);
+        assert_eq!(seeded.get_position(1).unwrap().quantity, 30.0);
+        assert_eq!(seeded.cash, 7_400.0);
+        seeded.ledger().reconcile(&seeded).unwrap();
+    }
 }
@@ -0,0 +1,345 @@
+//! Sizing positions from risk and edge estimates
+//!
+//! [`VolatilityTargeting`] turns each asset's annualized volatility into
+//! portfolio weights aiming at a target portfolio volatility: every asset is
+//! weighted inversely to its volatility so each contributes the same risk,
+//! and the weights are scaled so the portfolio's volatility, under an assumed
+//! average correlation between the assets, is the target. Gross exposure and
+//! single-asset weights can be capped, which only lowers the volatility.
+//!
+//! The weights feed straight into `order_optimal_portfolio_with`:
+//!
+//! ```ignore
+//! let sizer = VolatilityTargeting::new(0.10).with_max_leverage(1.5);
+//! let source = VolatilitySource::History { assets: &universe, data, bars: 60 };
+//! let weights = context.size_for_target_vol(&sizer, source)?;
+//! context.order_optimal_portfolio_with(weights, &[], &universe)?;
+//! ```
+//!
+//! [`KellySizing`] sizes a single bet or position from its edge, either the
+//! win rate and payoff of a trading rule or the expected return and variance
+//! of an asset, and [`FixedFractional`] risks a fixed fraction of equity
+//! between entry and a stop:
+//!
+//! ```ignore
+//! let fraction = KellySizing::half().from_win_rate(0.55, 1.2)?;
+//! let shares = FixedFractional::new(0.01).quantity(context.portfolio.portfolio_value, 50.0, 48.0)?;
+//! ```
+
+use crate::asset::Asset;
+use crate::data::BarData;
+use crate::error::{Result, ZiplineError};
+use crate::finance::constants::TRADING_DAYS_PER_YEAR;
+use crate::types::{AssetId, Cash, Price, Quantity};
+use hashbrown::HashMap;
+
+/// Where the volatility of each asset comes from
+#[derive(Clone, Copy)]
+pub enum VolatilitySource<'a> {
+    /// Realized volatility of the last `bars` daily closes of each asset
+    History {
+        assets: &'a [Asset],
+        data: &'a BarData,
+        bars: usize,
+    },
+    /// A column of pipeline output already holding annualized volatility
+    Pipeline {
+        output: &'a HashMap<AssetId, HashMap<String, f64>>,
+        column: &'a str,
+    },
+}
+
+/// Annualized volatility of daily closes, if there are at least three
+pub fn realized_volatility(prices: &[Price]) -> Option<f64> {
+    let returns: Vec<f64> = prices
+        .windows(2)
+        .filter(|pair| pair[0] > 0.0)
+        .map(|pair| pair[1] / pair[0] - 1.0)
+        .filter(|r| r.is_finite())
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt() * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// Inverse-volatility weights scaled to a target portfolio volatility
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolatilityTargeting {
+    /// Annualized portfolio volatility aimed at
+    target: f64,
+    /// Average pairwise correlation assumed between assets
+    correlation: f64,
+    /// Largest gross exposure, as a fraction of portfolio value
+    max_leverage: f64,
+    /// Largest weight of any one asset
+    max_weight: Option<f64>,
+}
+
+impl VolatilityTargeting {
+    /// Aim at `target` annualized volatility, assuming uncorrelated assets and
+    /// at most 1x gross exposure
+    pub fn new(target: f64) -> Self {
+        Self {
+            target,
+            correlation: 0.0,
+            max_leverage: 1.0,
+            max_weight: None,
+        }
+    }
+
+    /// Assume `correlation` between every pair of assets
+    pub fn with_correlation(mut self, correlation: f64) -> Self {
+        self.correlation = correlation.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Allow gross exposure up to `max_leverage` times portfolio value
+    pub fn with_max_leverage(mut self, max_leverage: f64) -> Self {
+        self.max_leverage = max_leverage;
+        self
+    }
+
+    /// Cap every asset's weight at `max_weight`
+    pub fn with_max_weight(mut self, max_weight: f64) -> Self {
+        self.max_weight = Some(max_weight);
+        self
+    }
+
+    pub fn target(&self) -> f64 {
+        self.target
+    }
+
+    /// Long weight of every asset with a positive, finite volatility
+    ///
+    /// Assets with no usable volatility are left out.
+    pub fn weights(&self, volatilities: &HashMap<AssetId, f64>) -> Result<HashMap<AssetId, f64>> {
+        if !(self.target.is_finite() && self.target > 0.0) {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Target volatility must be positive, got {}",
+                self.target
+            )));
+        }
+        if !(self.max_leverage.is_finite() && self.max_leverage > 0.0) {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Maximum leverage must be positive, got {}",
+                self.max_leverage
+            )));
+        }
+
+        let usable: Vec<(AssetId, f64)> = volatilities
+            .iter()
+            .filter(|(_, vol)| vol.is_finite() && **vol > 0.0)
+            .map(|(id, vol)| (*id, *vol))
+            .collect();
+        if usable.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // With weights k / vol, every asset's volatility contribution is k and
+        // the portfolio variance is k² (n + n (n - 1) correlation)
+        let n = usable.len() as f64;
+        let scale = self.target / (n + n * (n - 1.0) * self.correlation).sqrt();
+        let mut weights: HashMap<AssetId, f64> = usable
+            .into_iter()
+            .map(|(id, vol)| {
+                let weight = scale / vol;
+                (id, self.max_weight.map_or(weight, |cap| weight.min(cap)))
+            })
+            .collect();
+
+        let gross: f64 = weights.values().sum();
+        if gross > self.max_leverage {
+            let shrink = self.max_leverage / gross;
+            weights.values_mut().for_each(|w| *w *= shrink);
+        }
+        Ok(weights)
+    }
+}
+
+/// Fraction of equity the Kelly criterion stakes, scaled and capped
+///
+/// Full Kelly maximizes long-run growth but is very sensitive to errors in
+/// the estimated edge, so it is usually scaled down; [`half`](Self::half)
+/// stakes half of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KellySizing {
+    /// Share of the Kelly fraction staked
+    multiplier: f64,
+    /// Largest fraction of equity staked, long or short
+    max_fraction: f64,
+}
+
+impl Default for KellySizing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KellySizing {
+    /// Full Kelly, staking at most all of equity
+    pub fn new() -> Self {
+        Self {
+            multiplier: 1.0,
+            max_fraction: 1.0,
+        }
+    }
+
+    /// Half Kelly, staking at most all of equity
+    pub fn half() -> Self {
+        Self::new().with_multiplier(0.5)
+    }
+
+    /// Stake `multiplier` times the Kelly fraction
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Never stake more than `max_fraction` of equity
+    pub fn with_max_fraction(mut self, max_fraction: f64) -> Self {
+        self.max_fraction = max_fraction;
+        self
+    }
+
+    /// Fraction to stake on a bet won with probability `win_rate`, paying
+    /// `payoff` times what is lost when it loses
+    ///
+    /// A bet without an edge gets nothing.
+    pub fn from_win_rate(&self, win_rate: f64, payoff: f64) -> Result<f64> {
+        if !(0.0..=1.0).contains(&win_rate) {
+            return Err(ZiplineError::InvalidData(format!(
+                "Win rate must be between 0 and 1, got {}",
+                win_rate
+            )));
+        }
+        if !(payoff.is_finite() && payoff > 0.0) {
+            return Err(ZiplineError::InvalidData(format!(
+                "Payoff ratio must be positive, got {}",
+                payoff
+            )));
+        }
+        let kelly = win_rate - (1.0 - win_rate) / payoff;
+        Ok(self.scale(kelly.max(0.0)))
+    }
+
+    /// Fraction of equity to hold in an asset with the given expected
+    /// return and variance of return over the same period
+    ///
+    /// A negative expected return gives a short fraction.
+    pub fn from_moments(&self, expected_return: f64, variance: f64) -> Result<f64> {
+        if !(variance.is_finite() && variance > 0.0 && expected_return.is_finite()) {
+            return Err(ZiplineError::InvalidData(format!(
+                "Kelly sizing needs a finite return and positive variance, got {} and {}",
+                expected_return, variance
+            )));
+        }
+        Ok(self.scale(expected_return / variance))
+    }
+
+    fn scale(&self, kelly: f64) -> f64 {
+        (kelly * self.multiplier).clamp(-self.max_fraction, self.max_fraction)
+    }
+}
+
+/// Risks a fixed fraction of equity on every position
+///
+/// The position is sized so that being stopped out loses `risk_fraction` of
+/// equity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedFractional {
+    risk_fraction: f64,
+}
+
+impl FixedFractional {
+    /// Risk `risk_fraction` of equity, e.g. 0.01 for 1%
+    pub fn new(risk_fraction: f64) -> Self {
+        Self { risk_fraction }
+    }
+
+    pub fn risk_fraction(&self) -> f64 {
+        self.risk_fraction
+    }
+
+    /// Shares to trade entering at `entry` with a stop at `stop`
+    ///
+    /// Positive for a long (stop below entry), negative for a short.
+    pub fn quantity(&self, equity: Cash, entry: Price, stop: Price) -> Result<Quantity> {
+        if !(self.risk_fraction.is_finite() && self.risk_fraction > 0.0) {
+            return Err(ZiplineError::InvalidConfiguration(format!(
+                "Risk fraction must be positive, got {}",
+                self.risk_fraction
+            )));
+        }
+        let risk_per_share = entry - stop;
+        if !risk_per_share.is_finite() || risk_per_share == 0.0 {
+            return Err(ZiplineError::InvalidData(format!(
+                "Stop {} must differ from entry {}",
+                stop, entry
+            )));
+        }
+        Ok(equity.max(0.0) * self.risk_fraction / risk_per_share)
+    }
+
+    /// Shares to trade risking `risk_fraction` of equity on a move of
+    /// `volatility` (e.g. a multiple of ATR) per share
+    pub fn quantity_for_volatility(&self, equity: Cash, volatility: Price) -> Result<Quantity> {
+        self.quantity(equity, volatility, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_hit_target_volatility() {
+        assert!(realized_volatility(&[100.0, 101.0]).is_none());
+        let vol = realized_volatility(&[100.0, 101.0, 100.0, 101.0]).unwrap();
+        assert!(vol > 0.1 && vol < 0.3);
+
+        let volatilities: HashMap<AssetId, f64> = [(1, 0.2), (2, 0.4), (3, f64::NAN)].into_iter().collect();
+        let sizer = VolatilityTargeting::new(0.1).with_max_leverage(2.0);
+        let weights = sizer.weights(&volatilities).unwrap();
+        assert_eq!(weights.len(), 2);
+        // Each contributes the same risk, together the target volatility
+        assert!((weights[&1] * 0.2 - weights[&2] * 0.4).abs() < 1e-12);
+        let portfolio_vol = ((weights[&1] * 0.2).powi(2) + (weights[&2] * 0.4).powi(2)).sqrt();
+        assert!((portfolio_vol - 0.1).abs() < 1e-12);
+
+        // Perfectly correlated assets split the target between them
+        let weights = sizer.with_correlation(1.0).weights(&volatilities).unwrap();
+        assert!((weights[&1] - 0.25).abs() < 1e-12 && (weights[&2] - 0.125).abs() < 1e-12);
+
+        // Caps scale the book down rather than up
+        let capped = VolatilityTargeting::new(0.5).with_max_weight(0.6).weights(&volatilities).unwrap();
+        assert!((capped.values().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(VolatilityTargeting::new(0.0).weights(&volatilities).is_err());
+    }
+
+    #[test]
+    fn test_kelly_and_fixed_fractional() {
+        // 60% wins paying 1:1 stakes 20%, half Kelly 10%
+        assert!((KellySizing::new().from_win_rate(0.6, 1.0).unwrap() - 0.2).abs() < 1e-12);
+        assert!((KellySizing::half().from_win_rate(0.6, 1.0).unwrap() - 0.1).abs() < 1e-12);
+        assert_eq!(KellySizing::new().from_win_rate(0.4, 1.0).unwrap(), 0.0);
+        assert!(KellySizing::new().from_win_rate(1.2, 1.0).is_err());
+
+        // 8% a year over a variance of 4% gives 2x, capped at 1.5x either way
+        let capped = KellySizing::new().with_max_fraction(1.5);
+        assert_eq!(capped.from_moments(0.08, 0.04).unwrap(), 1.5);
+        assert_eq!(capped.from_moments(-0.08, 0.04).unwrap(), -1.5);
+        assert!((KellySizing::half().from_moments(0.02, 0.04).unwrap() - 0.25).abs() < 1e-12);
+        assert!(capped.from_moments(0.08, 0.0).is_err());
+
+        // Risking 1% of $100,000 with $2 a share at risk
+        let sizer = FixedFractional::new(0.01);
+        assert!((sizer.quantity(100_000.0, 50.0, 48.0).unwrap() - 500.0).abs() < 1e-9);
+        assert!((sizer.quantity(100_000.0, 50.0, 52.0).unwrap() + 500.0).abs() < 1e-9);
+        assert!((sizer.quantity_for_volatility(100_000.0, 4.0).unwrap() - 250.0).abs() < 1e-9);
+        assert!(sizer.quantity(100_000.0, 50.0, 50.0).is_err());
+    }
+}
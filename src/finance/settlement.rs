@@ -0,0 +1,252 @@
+//! Cash settlement and day trades
+//!
+//! With a settlement lag installed, the proceeds of a sale only become
+//! settled cash once that many sessions have started after the trade date,
+//! and buys may only spend settled cash, as in a retail cash account. T+1
+//! settles the next session, T+2 the one after.
+//!
+//! Day trades are counted whether or not settlement is modelled: a trade
+//! that reduces a position opened or added to earlier in the same session is
+//! a day trade. The [`PatternDayTrader`](crate::finance::PatternDayTrader)
+//! control limits them in accounts under [`PDT_MIN_EQUITY`].
+//!
+//! ```ignore
+//! let mut controls = ControlManager::new();
+//! controls.add_order_control(Box::new(PatternDayTrader::new()));
+//! let engine = SimulationEngine::default_engine(calendar)
+//!     .with_settlement(2)
+//!     .with_trading_controls(Arc::new(controls));
+//! ```
+
+use crate::finance::transaction::Transaction;
+use crate::order::OrderSide;
+use crate::types::{AssetId, Cash, Quantity, QUANTITY_TOLERANCE};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Equity below which an account is limited in day trades
+pub const PDT_MIN_EQUITY: Cash = 25_000.0;
+
+/// Day trades allowed within [`PDT_WINDOW_SESSIONS`] below the equity minimum
+pub const PDT_MAX_DAY_TRADES: usize = 3;
+
+/// Sessions over which day trades are counted
+pub const PDT_WINDOW_SESSIONS: usize = 5;
+
+/// Sale proceeds not yet settled
+#[derive(Debug, Clone, PartialEq)]
+struct Unsettled {
+    amount: Cash,
+    /// Sessions still to start before the proceeds settle
+    sessions: usize,
+}
+
+/// Sale proceeds waiting out a settlement lag
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settlement {
+    lag: usize,
+    pending: Vec<Unsettled>,
+}
+
+impl Settlement {
+    /// Settle proceeds `lag` sessions after the trade (T+`lag`)
+    pub fn new(lag: usize) -> Self {
+        Self {
+            lag,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Sessions between a trade and its settlement
+    pub fn lag(&self) -> usize {
+        self.lag
+    }
+
+    /// Proceeds of sales still to settle
+    pub fn unsettled(&self) -> Cash {
+        self.pending.iter().map(|u| u.amount).sum()
+    }
+
+    /// Count a session start against every pending sale, returning what settled
+    pub(crate) fn start_session(&mut self) -> Cash {
+        let mut settled = 0.0;
+        self.pending.retain_mut(|u| {
+            u.sessions = u.sessions.saturating_sub(1);
+            if u.sessions == 0 {
+                settled += u.amount;
+            }
+            u.sessions > 0
+        });
+        settled
+    }
+
+    /// Hold back the net proceeds of a sale
+    pub(crate) fn record(&mut self, transaction: &Transaction) {
+        if transaction.side != OrderSide::Sell || self.lag == 0 {
+            return;
+        }
+        let amount = transaction.amount.abs() * transaction.price - transaction.commission;
+        if amount > 0.0 {
+            self.pending.push(Unsettled {
+                amount,
+                sessions: self.lag,
+            });
+        }
+    }
+}
+
+/// Round trips made within a session, over a run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DayTrades {
+    /// Sessions started, oldest first
+    sessions: Vec<NaiveDate>,
+    /// Session of every day trade
+    trades: Vec<NaiveDate>,
+    /// Signed quantity opened this session by asset
+    opened: HashMap<AssetId, Quantity>,
+}
+
+impl DayTrades {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Begin `session`, forgetting the positions opened in the last one
+    pub(crate) fn start_session(&mut self, session: NaiveDate) {
+        if self.sessions.last() != Some(&session) {
+            self.sessions.push(session);
+            self.opened.clear();
+        }
+    }
+
+    /// Record a fill of `amount` shares into a position of `before` shares
+    ///
+    /// Returns whether the fill was a day trade.
+    pub(crate) fn record(&mut self, asset_id: AssetId, before: Quantity, amount: Quantity) -> bool {
+        let opened = self.opened.entry(asset_id).or_insert(0.0);
+        if before * amount >= 0.0 {
+            *opened += amount;
+            return false;
+        }
+
+        // Shares opened this session are closed first
+        let day_trade = *opened * before > 0.0 && opened.abs() > QUANTITY_TOLERANCE;
+        let closed = amount.abs().min(before.abs());
+        *opened = opened.signum() * (opened.abs() - closed).max(0.0);
+        let after = before + amount;
+        if after * amount > 0.0 {
+            *opened = after;
+        }
+        if day_trade {
+            if let Some(session) = self.sessions.last() {
+                self.trades.push(*session);
+            }
+        }
+        day_trade
+    }
+
+    /// Whether an order on `side` against a position of `position` shares
+    /// would close shares opened this session
+    pub fn would_day_trade(&self, asset_id: AssetId, side: OrderSide, position: Quantity) -> bool {
+        let reduces = match side {
+            OrderSide::Buy => position < 0.0,
+            OrderSide::Sell => position > 0.0,
+        };
+        reduces
+            && self
+                .opened
+                .get(&asset_id)
+                .is_some_and(|opened| opened * position > 0.0 && opened.abs() > QUANTITY_TOLERANCE)
+    }
+
+    /// Day trades made in the last `sessions` sessions, including this one
+    pub fn count(&self, sessions: usize) -> usize {
+        let Some(first) = self.sessions.len().checked_sub(sessions.max(1)).map(|i| self.sessions[i]) else {
+            return self.trades.len();
+        };
+        self.trades.iter().filter(|session| **session >= first).count()
+    }
+
+    /// Day trades made over the run
+    pub fn total(&self) -> usize {
+        self.trades.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Algorithm, Context};
+    use crate::asset::Asset;
+    use crate::calendar::NYSECalendar;
+    use crate::data::{BarData, InMemoryDataSource};
+    use crate::engine::SimulationEngine;
+    use crate::error::{Result, ZiplineError};
+    use crate::types::Bar;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Sells everything on the second bar and tries to buy some back on the next two
+    struct Rotation {
+        asset: Asset,
+        bars: usize,
+        refused: Vec<usize>,
+        settled: Vec<Cash>,
+    }
+
+    impl Algorithm for Rotation {
+        fn initialize(&mut self, _context: &mut Context) {}
+
+        fn handle_data(&mut self, context: &mut Context, _data: &BarData) -> Result<()> {
+            self.bars += 1;
+            self.settled.push(context.settled_cash());
+            let result = match self.bars {
+                1 => context.order(self.asset.clone(), 1_000.0),
+                2 => context.order(self.asset.clone(), -1_000.0),
+                3 | 4 => context.order(self.asset.clone(), 100.0),
+                _ => return Ok(()),
+            };
+            if let Err(ZiplineError::InsufficientFunds { .. }) = result {
+                self.refused.push(self.bars);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_proceeds_settle_after_lag() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for offset in [0, 1, 2, 3, 6] {
+            source.add_bar(1, Bar::new(start + Duration::days(offset), 100.0, 100.0, 100.0, 100.0, 1e6));
+        }
+        let end = start + Duration::days(6);
+        source.set_date_range(start, end);
+
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new())).with_settlement(2);
+        let mut algorithm = Rotation { asset, bars: 0, refused: Vec::new(), settled: Vec::new() };
+        engine.run(&mut algorithm, &source, start, end).unwrap();
+
+        // $100,000 buys 1,000 shares at $100; the sale on the second bar
+        // settles two sessions later, so the third bar's buy is refused
+        assert_eq!(algorithm.refused, vec![3]);
+        let settled = &algorithm.settled;
+        assert!(settled[1].abs() < 1e-6 && settled[2].abs() < 1e-6);
+        assert_eq!((settled[3], settled[4]), (100_000.0, 90_000.0));
+
+        let mut day_trades = DayTrades::new();
+        day_trades.start_session(listed);
+        assert!(!day_trades.record(1, 0.0, 100.0));
+        assert!(day_trades.would_day_trade(1, OrderSide::Sell, 100.0));
+        assert!(day_trades.record(1, 100.0, -150.0));
+        // The sale opened a 50 share short, which buying back closes
+        assert!(day_trades.record(1, -50.0, 50.0));
+        day_trades.start_session(listed + Duration::days(1));
+        assert!(!day_trades.record(1, 0.0, 10.0));
+        assert!(!day_trades.record(2, 500.0, -100.0));
+        assert_eq!((day_trades.count(1), day_trades.count(5)), (0, 2));
+    }
+}
@@ -1,7 +1,9 @@
 //! Slippage models for realistic trade simulation
 
+use crate::finance::market_stats::MarketStatsService;
 use crate::order::{Order, OrderSide};
 use crate::types::Price;
+use std::sync::Arc;
 
 /// Slippage model trait
 pub trait SlippageModel: Send + Sync {
@@ -125,16 +127,31 @@ impl SlippageModel for NoSlippage {
 }
 
 /// Square root impact model - realistic for large orders
+///
+/// With market statistics attached, impact is measured against the asset's
+/// average daily volume rather than the volume passed in (usually the current
+/// bar's).
 #[derive(Debug, Clone)]
 pub struct SquareRootImpact {
     /// Impact coefficient
     coefficient: f64,
+    /// Source of average daily volume
+    market_stats: Option<Arc<MarketStatsService>>,
 }
 
 impl SquareRootImpact {
     /// Create new square root impact model
     pub fn new(coefficient: f64) -> Self {
-        Self { coefficient }
+        Self {
+            coefficient,
+            market_stats: None,
+        }
+    }
+
+    /// Measure impact against average daily volume from `stats`
+    pub fn with_market_stats(mut self, stats: Arc<MarketStatsService>) -> Self {
+        self.market_stats = Some(stats);
+        self
     }
 }
 
@@ -146,6 +163,11 @@ impl SlippageModel for SquareRootImpact {
         daily_volume: f64,
     ) -> Price {
         let order_size = order.quantity.abs();
+        let daily_volume = self
+            .market_stats
+            .as_ref()
+            .and_then(|stats| stats.average_daily_volume(order.asset.id))
+            .unwrap_or(daily_volume);
 
         // Square root impact: slippage = coefficient * sqrt(order_size / daily_volume)
         let volume_share = if daily_volume > 0.0 {
@@ -274,6 +296,27 @@ use chrono::NaiveDate;
         assert_eq!(price, 101.0);
     }
 
+    #[test]
+    fn test_square_root_impact_uses_adv() {
+        use crate::types::Bar;
+
+        // Two completed sessions of 40,000 shares
+        let stats = Arc::new(MarketStatsService::new(5));
+        let day0 = Utc::now() - chrono::Duration::days(5);
+        for i in 0..3 {
+            let ts = day0 + chrono::Duration::days(i);
+            let bar = Bar::new(ts, 100.0, 100.0, 100.0, 100.0, 40_000.0);
+            stats.update(1, crate::types::SessionId::utc_label_of(ts), &bar);
+        }
+
+        let model = SquareRootImpact::new(0.1).with_market_stats(stats);
+        let order = create_buy_order(100.0);
+
+        // The 10,000-share bar volume is ignored: sqrt(100/40000) * 0.1 = 0.5%
+        let price = model.calculate_price(&order, 100.0, 10000.0);
+        assert!((price - 100.5).abs() < 1e-9);
+    }
+
     #[test]
     fn test_linear_impact() {
         let model = LinearImpact::new(0.001);
@@ -0,0 +1,329 @@
+//! Realized gains by tax lot
+//!
+//! A [`Ledger`](crate::finance::Ledger) with tax lots enabled records every
+//! part of a lot a trade closes as a [`RealizedGain`]: what it cost, what it
+//! fetched and whether it was held short or long term. A loss on a long lot
+//! is a wash sale to the extent the asset is bought again within
+//! [`WASH_SALE_DAYS`] of the sale; the disallowed part of the loss is
+//! reported, but the replacement lot keeps its own cost so the ledger still
+//! agrees with the portfolio.
+//!
+//! ```ignore
+//! let engine = SimulationEngine::default_engine(calendar).with_tax_lots();
+//! let results = engine.run(&mut algorithm, &source, start, end)?;
+//! println!("{}", results.tax.unwrap());
+//! ```
+
+use crate::finance::ledger::{Lot, LotId};
+use crate::finance::transaction::Transaction;
+use crate::order::OrderSide;
+use crate::types::QUANTITY_TOLERANCE;
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Days after a loss within which buying the asset again washes the loss
+pub const WASH_SALE_DAYS: i64 = 30;
+
+/// Months a lot must be held beyond for its gain to be long term
+pub const LONG_TERM_MONTHS: u32 = 12;
+
+/// Holding period classification of a realized gain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HoldingTerm {
+    /// Held for a year or less
+    ShortTerm,
+    /// Held for more than a year
+    LongTerm,
+}
+
+impl HoldingTerm {
+    /// Term of a lot acquired at `acquired` and closed at `disposed`
+    pub fn of(acquired: DateTime<Utc>, disposed: DateTime<Utc>) -> Self {
+        let year_later = acquired.date_naive() + Months::new(LONG_TERM_MONTHS);
+        if disposed.date_naive() > year_later {
+            HoldingTerm::LongTerm
+        } else {
+            HoldingTerm::ShortTerm
+        }
+    }
+}
+
+/// Part of a lot closed by a trade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ClosedLot {
+    pub lot_id: LotId,
+    /// Shares closed, negative for a short lot
+    pub quantity: f64,
+    /// Cost per share
+    pub cost_basis: f64,
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl ClosedLot {
+    /// `quantity` shares of `lot`
+    pub fn part(lot: &Lot, quantity: f64) -> Self {
+        Self {
+            lot_id: lot.id(),
+            quantity,
+            cost_basis: lot.cost_basis,
+            acquired_at: lot.acquired_at,
+        }
+    }
+}
+
+/// Gain or loss realized on part of one lot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub asset_id: u64,
+    /// Transaction that closed the lot
+    pub transaction_id: uuid::Uuid,
+    /// Shares closed, negative for a short lot
+    pub quantity: f64,
+    pub acquired_at: DateTime<Utc>,
+    pub disposed_at: DateTime<Utc>,
+    /// Cost of the shares closed
+    pub cost: f64,
+    /// Value of the shares at the closing price
+    pub proceeds: f64,
+    pub term: HoldingTerm,
+    /// Shares of a loss replaced by buys within the wash-sale window
+    pub wash_sale_quantity: f64,
+}
+
+impl RealizedGain {
+    /// Gain, negative for a loss
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+
+    /// Whether any of the loss was washed by a repurchase
+    pub fn is_wash_sale(&self) -> bool {
+        self.wash_sale_quantity > 0.0
+    }
+
+    /// Part of the loss that may not be claimed because of a wash sale
+    pub fn disallowed_loss(&self) -> f64 {
+        if self.gain() >= 0.0 || self.quantity == 0.0 {
+            return 0.0;
+        }
+        -self.gain() * self.wash_sale_quantity / self.quantity.abs()
+    }
+
+    /// Shares of a loss not yet replaced by a repurchase
+    fn unwashed(&self) -> f64 {
+        if self.quantity > 0.0 && self.gain() < 0.0 {
+            self.quantity - self.wash_sale_quantity
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Realized gains of one holding term
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TermSummary {
+    /// Lots (or parts of lots) closed
+    pub lots: usize,
+    pub proceeds: f64,
+    pub cost: f64,
+    /// Sum of gains on lots closed at a gain
+    pub gains: f64,
+    /// Sum of losses on lots closed at a loss, negative
+    pub losses: f64,
+    /// Losses disallowed by wash sales, positive
+    pub disallowed_loss: f64,
+}
+
+impl TermSummary {
+    fn add(&mut self, gain: &RealizedGain) {
+        self.lots += 1;
+        self.proceeds += gain.proceeds;
+        self.cost += gain.cost;
+        if gain.gain() >= 0.0 {
+            self.gains += gain.gain();
+        } else {
+            self.losses += gain.gain();
+        }
+        self.disallowed_loss += gain.disallowed_loss();
+    }
+
+    /// Net realized gain
+    pub fn net(&self) -> f64 {
+        self.gains + self.losses
+    }
+
+    /// Net gain once wash-sale losses are added back
+    pub fn taxable(&self) -> f64 {
+        self.net() + self.disallowed_loss
+    }
+}
+
+/// Realized gains grouped by holding term
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaxReport {
+    pub short_term: TermSummary,
+    pub long_term: TermSummary,
+    /// Losses at least partly washed by repurchases
+    pub wash_sales: usize,
+}
+
+impl TaxReport {
+    /// Summary for one holding term
+    pub fn term(&self, term: HoldingTerm) -> &TermSummary {
+        match term {
+            HoldingTerm::ShortTerm => &self.short_term,
+            HoldingTerm::LongTerm => &self.long_term,
+        }
+    }
+}
+
+impl fmt::Display for TaxReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Realized Gains")?;
+        for (name, term) in [("Short term", &self.short_term), ("Long term", &self.long_term)] {
+            writeln!(
+                f,
+                "  {:<11} {:>4} lots  gains ${:.2}  losses ${:.2}  net ${:.2}  taxable ${:.2}",
+                name,
+                term.lots,
+                term.gains,
+                term.losses,
+                term.net(),
+                term.taxable()
+            )?;
+        }
+        if self.wash_sales > 0 {
+            writeln!(f, "  Wash sales:  {}", self.wash_sales)?;
+        }
+        Ok(())
+    }
+}
+
+/// Realized gains of a ledger, lot by lot
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaxLots {
+    realized: Vec<RealizedGain>,
+}
+
+impl TaxLots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the lots `transaction` closed and any repurchase it makes
+    pub(crate) fn record(&mut self, transaction: &Transaction, closed: &[ClosedLot]) {
+        for lot in closed {
+            self.realized.push(RealizedGain {
+                asset_id: transaction.asset_id,
+                transaction_id: transaction.id,
+                quantity: lot.quantity,
+                acquired_at: lot.acquired_at,
+                disposed_at: transaction.dt,
+                cost: lot.quantity * lot.cost_basis,
+                proceeds: lot.quantity * transaction.price,
+                term: HoldingTerm::of(lot.acquired_at, transaction.dt),
+                wash_sale_quantity: 0.0,
+            });
+        }
+
+        // Shares bought beyond any short they cover replace earlier losses
+        if transaction.side == OrderSide::Buy {
+            let covered: f64 = closed.iter().map(|lot| lot.quantity.abs()).sum();
+            let bought = transaction.amount.abs() - covered;
+            if bought > QUANTITY_TOLERANCE {
+                self.wash(transaction.asset_id, bought, transaction.dt);
+            }
+        }
+    }
+
+    /// Assign `bought` shares to unwashed losses sold in the window before `dt`
+    fn wash(&mut self, asset_id: u64, mut bought: f64, dt: DateTime<Utc>) {
+        let window = Duration::days(WASH_SALE_DAYS);
+        for gain in self
+            .realized
+            .iter_mut()
+            .filter(|g| g.asset_id == asset_id && g.disposed_at <= dt && dt - g.disposed_at <= window)
+        {
+            let washed = gain.unwashed().min(bought);
+            if washed <= 0.0 {
+                continue;
+            }
+            gain.wash_sale_quantity += washed;
+            bought -= washed;
+            if bought <= QUANTITY_TOLERANCE {
+                break;
+            }
+        }
+    }
+
+    /// Every gain realized, in the order closed
+    pub fn realized(&self) -> &[RealizedGain] {
+        &self.realized
+    }
+
+    /// Losses washed by repurchases
+    pub fn wash_sales(&self) -> impl Iterator<Item = &RealizedGain> {
+        self.realized.iter().filter(|g| g.is_wash_sale())
+    }
+
+    /// Realized gains grouped by holding term
+    pub fn report(&self) -> TaxReport {
+        let mut report = TaxReport::default();
+        for gain in &self.realized {
+            match gain.term {
+                HoldingTerm::ShortTerm => report.short_term.add(gain),
+                HoldingTerm::LongTerm => report.long_term.add(gain),
+            }
+            if gain.is_wash_sale() {
+                report.wash_sales += 1;
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finance::ledger::Ledger;
+    use chrono::TimeZone;
+
+    fn trade(ledger: &mut Ledger, day: (i32, u32, u32), side: OrderSide, amount: f64, price: f64) {
+        let dt = Utc.with_ymd_and_hms(day.0, day.1, day.2, 21, 0, 0).unwrap();
+        let txn = Transaction::new(1, uuid::Uuid::new_v4(), dt, amount, price, 0.0, side);
+        ledger.record_transaction(txn).unwrap();
+    }
+
+    #[test]
+    fn test_gains_by_term_and_wash_sales() {
+        let mut ledger = Ledger::default().with_tax_lots();
+        trade(&mut ledger, (2022, 3, 1), OrderSide::Buy, 100.0, 10.0);
+        trade(&mut ledger, (2023, 2, 1), OrderSide::Buy, 100.0, 20.0);
+
+        // FIFO: the 2022 lot is long term, half the 2023 lot short term
+        trade(&mut ledger, (2023, 6, 1), OrderSide::Sell, 150.0, 15.0);
+        // Buying 20 shares back within 30 days washes 20 of the 50 lost
+        trade(&mut ledger, (2023, 6, 20), OrderSide::Buy, 20.0, 14.0);
+        // A buy after the window washes nothing
+        trade(&mut ledger, (2023, 8, 1), OrderSide::Buy, 20.0, 14.0);
+
+        let lots = ledger.tax_lots().unwrap();
+        assert_eq!(lots.realized().len(), 2);
+        assert_eq!(lots.realized()[0].term, HoldingTerm::LongTerm);
+        assert_eq!(lots.realized()[1].term, HoldingTerm::ShortTerm);
+        assert_eq!(lots.wash_sales().count(), 1);
+
+        let report = ledger.realized_gains_report().unwrap();
+        assert_eq!(report.long_term.net(), 500.0);
+        assert_eq!(report.short_term.losses, -250.0);
+        assert_eq!(report.short_term.disallowed_loss, 100.0);
+        assert_eq!(report.short_term.taxable(), -150.0);
+        assert_eq!(report.wash_sales, 1);
+
+        // A year to the day is still short term
+        let anniversary = Utc.with_ymd_and_hms(2023, 3, 1, 21, 0, 0).unwrap();
+        assert_eq!(HoldingTerm::of(lots.realized()[0].acquired_at, anniversary), HoldingTerm::ShortTerm);
+        assert!(Ledger::default().realized_gains_report().is_none());
+    }
+}
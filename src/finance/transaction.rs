@@ -3,6 +3,7 @@
 //! A Transaction is created when an Order is filled (executed).
 //! It records the actual price, quantity, and costs of the trade.
 
+use crate::finance::ledger::LotSelection;
 use crate::order::OrderSide;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -30,6 +31,13 @@ pub struct Transaction {
     pub commission: f64,
     /// Order side (Buy/Sell)
     pub side: OrderSide,
+    /// Trade journal note carried over from the order
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Lots the trade takes shares from, for specific identification; the
+    /// ledger's copy lists every lot the trade closed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lots: Vec<LotSelection>,
 }
 
 impl Transaction {
@@ -52,9 +60,29 @@ impl Transaction {
             price,
             commission,
             side,
+            note: None,
+            lots: Vec::new(),
         }
     }
 
+    /// Use a given transaction ID instead of a random one
+    pub fn with_id(mut self, id: TransactionId) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Attach a trade journal note
+    pub fn with_note(mut self, note: Option<String>) -> Self {
+        self.note = note;
+        self
+    }
+
+    /// Close shares of these lots before any chosen by cost basis method
+    pub fn with_lots(mut self, lots: Vec<LotSelection>) -> Self {
+        self.lots = lots;
+        self
+    }
+
     /// Get total transaction value (price * amount)
     pub fn value(&self) -> f64 {
         self.price * self.amount.abs()
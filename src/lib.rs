@@ -24,8 +24,30 @@
 //!     }
 //! }
 //! ```
+//!
+//! ## API stability
+//!
+//! Items re-exported from [`prelude`] are the stable surface that strategy
+//! crates should build on. Traits meant to be implemented downstream, such as
+//! [`Algorithm`](algorithm::Algorithm), [`Factor`](pipeline::Factor) or
+//! [`SlippageModel`](finance::SlippageModel), are open; traits describing the
+//! crate's own closed sets of types, such as
+//! [`Versioned`](serialization::Versioned) and [`Term`](pipeline::term::Term),
+//! are sealed so that adding methods to them is not a breaking change. Items
+//! hidden from the documentation are implementation details and may change in
+//! any release.
+//!
+//! ## Profiling
+//!
+//! The engine is instrumented with [`tracing`] spans: one per session, a
+//! `bar` span per bar carrying its `elapsed_us`, and child spans for
+//! `before_trading_start`, `handle_data`, scheduled functions, order execution
+//! and pipeline runs. Install any tracing subscriber to profile where a
+//! strategy spends its time. Without one, events are forwarded to the `log`
+//! crate as before.
 
 pub mod algorithm;
+pub mod analysis; // Factor research tools
 pub mod asset;
 pub mod assets; // Asset database and management
 pub mod calendar;
@@ -33,24 +55,64 @@ pub mod data;
 pub mod engine;
 pub mod error;
 pub mod execution; // Execution styles (Market, Limit, Stop orders)
+pub mod filters; // Kalman filters for dynamic estimates
 pub mod finance;
 pub mod order;
 pub mod performance;
 pub mod pipeline;
+pub mod plugin; // Strategies compiled as cdylibs
+pub mod rng; // Seeded randomness for reproducible runs
 pub mod schedule;
+pub mod serialization; // Versioned result files
+pub mod statistics; // Covariance and other estimators
 pub mod types;
 
+mod sealed {
+    /// Supertrait of traits that only this crate implements
+    pub trait Sealed {}
+}
+
 pub mod prelude {
     //! Commonly used types and traits
-    pub use crate::algorithm::{Algorithm, Context};
+    //!
+    //! `use rusty_zipline::prelude::*;` brings in what a strategy needs: the
+    //! algorithm API, the engine and its configuration, the built-in finance
+    //! models and controls, results, and the pipeline DSL with its most common
+    //! factors.
+
+    // Strategy API
+    pub use crate::algorithm::{Algorithm, Conditional, Context, Ensemble, PipelineGated};
     pub use crate::asset::{Asset, AssetType};
     pub use crate::data::BarData;
     pub use crate::data::fx::{Currency, CurrencyPair, FXRateReader, InMemoryFXRateReader};
-    pub use crate::engine::SimulationEngine;
     pub use crate::error::{Result, ZiplineError};
-    pub use crate::finance::{Portfolio, Position};
-    pub use crate::order::{Order, OrderSide, OrderType};
+    pub use crate::order::{ExecutionOverride, Order, OrderSide, OrderType, Trail};
+    pub use crate::schedule::{EveryDay, MarketClose, MarketOpen, MonthEnd, MonthStart, WeekEnd, WeekStart};
     pub use crate::types::*;
+
+    // Engine
+    pub use crate::calendar::{AlwaysOpenCalendar, NYSECalendar, TradingCalendar};
+    pub use crate::engine::{EngineConfig, SimulationEngine};
+    pub use crate::execution::SimulatedBroker;
+
+    // Finance models and controls
+    pub use crate::finance::{
+        CapacityLimits, CommissionModel, ControlAction, ControlManager,
+        ControlTradingControl as TradingControl, CostBasisMethod, DuplicateOrder, FatFinger,
+        FixedBasisPointsSlippage, Ledger, LongOnly, LotSize, LotSizes, MaxOrdersPerBar,
+        ModelRegistry, ModelSpec, NoSlippage, PerShare, PerTrade, Portfolio, Position, Rounding,
+        SlippageModel, Transaction, VolumeShareSlippage, ZeroCommission,
+    };
+
+    // Results
+    pub use crate::performance::{BacktestResult, CurveFrequency, PerformanceTracker, RunComparison};
+
+    // Pipeline DSL
+    pub use crate::pipeline::{
+        demean_by, rank_by, zscore_by, AverageDollarVolume, Classifier, CustomFactor,
+        DataProvider, ExponentialMovingAverage, Factor, FactorInput, Filter, OHLCVBar, Pipeline,
+        PipelineOutput, Returns, SimpleMovingAverage, StatefulFactor, RSI,
+    };
 }
 
 #[cfg(test)]
@@ -61,4 +123,17 @@ mod tests {
     fn test_lib_compile() {
         // Smoke test to ensure library compiles
     }
+
+    #[test]
+    fn test_prelude_covers_strategy_setup() {
+        use crate::prelude::*;
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_stateful_factor("sma".to_string(), SimpleMovingAverage::new(20));
+        let mut controls = ControlManager::new();
+        controls.add_order_control(Box::new(LongOnly));
+        controls.add_order_control(Box::new(MaxOrdersPerBar::default()));
+        let config = EngineConfig::default();
+        assert!(config.starting_cash > 0.0);
+    }
 }
@@ -1,9 +1,11 @@
 //! Order types and management
 
 use crate::asset::Asset;
-use crate::types::{Cash, OrderId, Price, Quantity, Timestamp};
+use crate::error::{Result, ZiplineError};
+use crate::types::{Cash, OrderId, Price, Quantity, Timestamp, QUANTITY_TOLERANCE};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 
 /// Order side (buy or sell)
@@ -24,6 +26,70 @@ pub enum OrderType {
     Stop,
     /// Stop-limit order - trigger limit order when price reached
     StopLimit,
+    /// Trailing stop - stop that follows the best price since placement,
+    /// becoming a market order when the price falls back through it
+    TrailingStop { trail: Trail },
+}
+
+/// Distance a trailing stop keeps from the best price seen
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Trail {
+    /// Fixed distance in price
+    Amount(Price),
+    /// Fraction of the best price (0.05 = 5%)
+    Percent(f64),
+}
+
+// Compared bitwise so `OrderType` stays `Eq`: a trail is a configured value,
+// never the result of arithmetic, so bit equality is value equality.
+impl PartialEq for Trail {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Trail::Amount(a), Trail::Amount(b)) | (Trail::Percent(a), Trail::Percent(b)) => {
+                a.to_bits() == b.to_bits()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Trail {}
+
+impl Trail {
+    /// Stop price for a trailing `side` order whose best price so far is `mark`
+    ///
+    /// A sell stop trails below the high-water mark, a buy stop above the
+    /// low-water mark.
+    pub fn stop_price(&self, mark: Price, side: OrderSide) -> Price {
+        let distance = match *self {
+            Trail::Amount(amount) => amount,
+            Trail::Percent(fraction) => mark * fraction,
+        };
+        match side {
+            OrderSide::Sell => mark - distance,
+            OrderSide::Buy => mark + distance,
+        }
+    }
+}
+
+/// Execution assumptions for a single order
+///
+/// Takes precedence over the broker's slippage and commission models, e.g. to
+/// model a hedge crossed at mid with no fees, or a flow executed on negotiated
+/// terms.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExecutionOverride {
+    /// Fill at the quoted price with no slippage or commission
+    Midpoint,
+    /// Fixed costs in place of the broker's models
+    Costs {
+        /// Slippage against the order, in basis points of the price
+        slippage_bps: f64,
+        /// Commission per share filled
+        commission_per_share: Cash,
+    },
+    /// An execution profile registered with the broker by name
+    Profile(String),
 }
 
 /// Order status
@@ -71,7 +137,17 @@ pub struct Order {
     /// Last update timestamp
     pub updated_at: Timestamp,
     /// Order amount in cash terms
+    #[serde(default)]
     pub amount: Cash,
+    /// Trade journal note explaining the order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Execution assumptions overriding the broker's models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution: Option<ExecutionOverride>,
+    /// Best price seen by a trailing stop: the high for sells, the low for buys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trail_mark: Option<Price>,
 }
 
 impl Order {
@@ -91,6 +167,9 @@ impl Order {
             created: timestamp,
             updated_at: timestamp,
             amount: 0.0, // Will be calculated when order is filled
+            note: None,
+            execution: None,
+            trail_mark: None,
         }
     }
 
@@ -116,6 +195,9 @@ impl Order {
             created: timestamp,
             updated_at: timestamp,
             amount: quantity * limit_price, // Calculate expected amount
+            note: None,
+            execution: None,
+            trail_mark: None,
         }
     }
 
@@ -141,6 +223,9 @@ impl Order {
             created: timestamp,
             updated_at: timestamp,
             amount: quantity * stop_price, // Calculate expected amount
+            note: None,
+            execution: None,
+            trail_mark: None,
         }
     }
 
@@ -167,7 +252,60 @@ impl Order {
             created: timestamp,
             updated_at: timestamp,
             amount: quantity * limit_price, // Calculate expected amount at limit price
+            note: None,
+            execution: None,
+            trail_mark: None,
+        }
+    }
+
+    /// Create a new trailing stop order
+    ///
+    /// The stop is set from the first price the order sees, and then only
+    /// moves in the order's favour.
+    pub fn trailing_stop(
+        asset: Asset,
+        side: OrderSide,
+        quantity: Quantity,
+        trail: Trail,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            order_type: OrderType::TrailingStop { trail },
+            ..Self::market(asset, side, quantity, timestamp)
+        }
+    }
+
+    /// Move a trailing stop with a new price
+    ///
+    /// If `price` has crossed the stop set by earlier prices the order becomes
+    /// a market order, to be filled at `price` (not at the stop, when the
+    /// price gapped through it), and `true` is returned. Otherwise the
+    /// high-water (or low-water) mark and stop are updated. Other order types
+    /// are left alone.
+    pub fn update_trail(&mut self, price: Price) -> bool {
+        let OrderType::TrailingStop { trail } = self.order_type else {
+            return false;
+        };
+
+        if let Some(stop) = self.stop_price {
+            let breached = match self.side {
+                OrderSide::Sell => price <= stop,
+                OrderSide::Buy => price >= stop,
+            };
+            if breached {
+                self.order_type = OrderType::Market;
+                return true;
+            }
         }
+
+        let mark = match (self.side, self.trail_mark) {
+            (OrderSide::Sell, Some(high)) => high.max(price),
+            (OrderSide::Buy, Some(low)) => low.min(price),
+            (_, None) => price,
+        };
+        self.trail_mark = Some(mark);
+        self.stop_price = Some(trail.stop_price(mark, self.side));
+        false
     }
 
     /// Get remaining quantity to fill
@@ -177,7 +315,7 @@ impl Order {
 
     /// Check if order is completely filled
     pub fn is_filled(&self) -> bool {
-        self.filled >= self.quantity
+        self.remaining() < QUANTITY_TOLERANCE
     }
 
     /// Check if order is open (can still be filled)
@@ -223,6 +361,23 @@ impl Order {
     pub fn open_quantity(&self) -> Quantity {
         self.quantity - self.filled
     }
+
+    /// Execute this order under `execution` instead of the broker's models
+    pub fn with_execution(mut self, execution: ExecutionOverride) -> Self {
+        self.execution = Some(execution);
+        self
+    }
+
+    /// Append a trade journal note, separating multiple notes with "; "
+    pub fn add_note(&mut self, text: &str) {
+        match &mut self.note {
+            Some(note) => {
+                note.push_str("; ");
+                note.push_str(text);
+            }
+            None => self.note = Some(text.to_string()),
+        }
+    }
 }
 
 impl fmt::Display for Order {
@@ -235,6 +390,208 @@ impl fmt::Display for Order {
     }
 }
 
+/// Bracket order: an entry order with attached stop-loss and take-profit exits
+///
+/// The exits are placed on the opposite side of the entry and only become active
+/// once the entry is completely filled, or is cancelled after a partial fill, in
+/// which case they cover only the filled quantity. They form a one-cancels-other
+/// (OCO) pair: when one fills, the other is cancelled. [`BracketBook`] tracks
+/// this lifecycle for both the blotter and the simulation context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BracketOrder {
+    /// Entry order
+    pub parent: Order,
+    /// Protective stop order
+    pub stop_loss: Order,
+    /// Profit-taking limit order
+    pub take_profit: Order,
+}
+
+impl BracketOrder {
+    /// Create a bracket order
+    ///
+    /// The entry is a market order when `entry_limit` is `None` and a limit order
+    /// otherwise. For a buy entry the stop-loss must be below the take-profit
+    /// (and vice versa for a sell entry).
+    pub fn new(
+        asset: Asset,
+        side: OrderSide,
+        quantity: Quantity,
+        entry_limit: Option<Price>,
+        stop_loss_price: Price,
+        take_profit_price: Price,
+        timestamp: Timestamp,
+    ) -> Result<Self> {
+        if quantity <= 0.0 {
+            return Err(ZiplineError::InvalidOrder(format!(
+                "Bracket order quantity must be positive, got {}",
+                quantity
+            )));
+        }
+
+        let prices_valid = match side {
+            OrderSide::Buy => stop_loss_price < take_profit_price,
+            OrderSide::Sell => stop_loss_price > take_profit_price,
+        };
+        if !prices_valid {
+            return Err(ZiplineError::InvalidOrder(format!(
+                "Invalid bracket for {:?} entry: stop-loss {} and take-profit {}",
+                side, stop_loss_price, take_profit_price
+            )));
+        }
+
+        if let Some(limit) = entry_limit {
+            let limit_inside = match side {
+                OrderSide::Buy => limit > stop_loss_price && limit < take_profit_price,
+                OrderSide::Sell => limit < stop_loss_price && limit > take_profit_price,
+            };
+            if !limit_inside {
+                return Err(ZiplineError::InvalidOrder(format!(
+                    "Entry limit {} must lie between stop-loss {} and take-profit {}",
+                    limit, stop_loss_price, take_profit_price
+                )));
+            }
+        }
+
+        let exit_side = match side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+
+        let parent = match entry_limit {
+            Some(limit) => Order::limit(asset.clone(), side, quantity, limit, timestamp),
+            None => Order::market(asset.clone(), side, quantity, timestamp),
+        };
+        let stop_loss = Order::stop(asset.clone(), exit_side, quantity, stop_loss_price, timestamp);
+        let take_profit = Order::limit(asset, exit_side, quantity, take_profit_price, timestamp);
+
+        Ok(Self {
+            parent,
+            stop_loss,
+            take_profit,
+        })
+    }
+
+    /// IDs of the entry, stop-loss and take-profit orders
+    pub fn ids(&self) -> BracketOrderIds {
+        BracketOrderIds {
+            parent: self.parent.id,
+            stop_loss: self.stop_loss.id,
+            take_profit: self.take_profit.id,
+        }
+    }
+}
+
+/// Bracket exits waiting on their entries, and the OCO links between active exits
+///
+/// The book holds no open orders itself: callers keep entries and active exits
+/// in their own order lists and report fills and cancellations here.
+#[derive(Debug, Clone, Default)]
+pub struct BracketBook {
+    /// Exits waiting for their entry to close (parent ID -> [stop-loss, take-profit])
+    pending: HashMap<OrderId, [Order; 2]>,
+    /// One-cancels-other links between active exits, in both directions
+    siblings: HashMap<OrderId, OrderId>,
+}
+
+impl BracketBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold `exits` until the entry `parent_id` closes
+    pub fn insert(&mut self, parent_id: OrderId, exits: [Order; 2]) {
+        self.pending.insert(parent_id, exits);
+    }
+
+    /// Check whether an order is a bracket entry with exits still pending
+    pub fn is_entry(&self, order_id: OrderId) -> bool {
+        self.pending.contains_key(&order_id)
+    }
+
+    /// Find a pending (not yet active) exit by ID
+    pub fn pending_exit(&self, order_id: OrderId) -> Option<&Order> {
+        self.pending
+            .values()
+            .flat_map(|exits| exits.iter())
+            .find(|exit| exit.id == order_id)
+    }
+
+    /// Get the OCO sibling of an active exit
+    pub fn sibling(&self, order_id: OrderId) -> Option<OrderId> {
+        self.siblings.get(&order_id).copied()
+    }
+
+    /// Release the exits of an entry that has closed with `filled` shares
+    ///
+    /// The exits are sized to the filled quantity and linked as an OCO pair;
+    /// they are returned for the caller to open. An entry that closes unfilled
+    /// has its exits cancelled at `dt` and returned as well, so callers can tell
+    /// the two cases apart by [`Order::is_open`].
+    pub fn close_entry(&mut self, parent_id: OrderId, filled: Quantity, dt: Timestamp) -> Option<[Order; 2]> {
+        let mut exits = self.pending.remove(&parent_id)?;
+        if filled < QUANTITY_TOLERANCE {
+            for exit in &mut exits {
+                exit.cancel(dt);
+            }
+            return Some(exits);
+        }
+
+        for exit in &mut exits {
+            exit.quantity = filled;
+            exit.updated_at = dt;
+        }
+        let [stop_loss, take_profit] = &exits;
+        self.siblings.insert(stop_loss.id, take_profit.id);
+        self.siblings.insert(take_profit.id, stop_loss.id);
+        Some(exits)
+    }
+
+    /// Drop the OCO link of an exit that closed, returning the sibling to cancel
+    pub fn close_exit(&mut self, order_id: OrderId) -> Option<OrderId> {
+        let sibling_id = self.siblings.remove(&order_id)?;
+        self.siblings.remove(&sibling_id);
+        Some(sibling_id)
+    }
+
+    /// Follow an entry or exit that was replaced by an amended order
+    pub fn replace(&mut self, old_id: OrderId, new_id: OrderId) {
+        if let Some(exits) = self.pending.remove(&old_id) {
+            self.pending.insert(new_id, exits);
+        }
+        if let Some(sibling_id) = self.siblings.remove(&old_id) {
+            self.siblings.insert(new_id, sibling_id);
+            self.siblings.insert(sibling_id, new_id);
+        }
+    }
+
+    /// Forget entries and exits that are no longer open
+    ///
+    /// Used when orders are dropped wholesale, e.g. on a trading halt.
+    pub fn retain_open(&mut self, mut is_open: impl FnMut(OrderId) -> bool) {
+        self.pending.retain(|parent_id, _| is_open(*parent_id));
+        self.siblings
+            .retain(|exit_id, sibling_id| is_open(*exit_id) && is_open(*sibling_id));
+    }
+
+    /// Forget every bracket
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.siblings.clear();
+    }
+}
+
+/// Order IDs making up a bracket order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BracketOrderIds {
+    /// Entry order ID
+    pub parent: OrderId,
+    /// Stop-loss order ID
+    pub stop_loss: OrderId,
+    /// Take-profit order ID
+    pub take_profit: OrderId,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,4 +629,54 @@ mod tests {
         assert!(order.is_filled());
         assert!(order.is_closed());
     }
+
+    #[test]
+    fn test_bracket_order() {
+        let start_date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+        let bracket =
+            BracketOrder::new(asset.clone(), OrderSide::Buy, 100.0, None, 95.0, 110.0, Utc::now())
+                .unwrap();
+
+        assert_eq!(bracket.parent.order_type, OrderType::Market);
+        assert_eq!(bracket.stop_loss.side, OrderSide::Sell);
+        assert_eq!(bracket.stop_loss.stop_price, Some(95.0));
+        assert_eq!(bracket.take_profit.side, OrderSide::Sell);
+        assert_eq!(bracket.take_profit.limit_price, Some(110.0));
+
+        // Stop-loss above take-profit is invalid for a long entry
+        assert!(BracketOrder::new(asset.clone(), OrderSide::Buy, 100.0, None, 110.0, 95.0, Utc::now()).is_err());
+        // Entry limit must sit inside the bracket
+        assert!(BracketOrder::new(asset, OrderSide::Buy, 100.0, Some(120.0), 95.0, 110.0, Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_trailing_stop_follows_the_mark() {
+        let start_date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), start_date);
+
+        // Sell $5 below the high: a gap up drags the stop along, pullbacks
+        // above it leave it in place
+        let mut sell = Order::trailing_stop(asset.clone(), OrderSide::Sell, 100.0, Trail::Amount(5.0), Utc::now());
+        assert!(!sell.update_trail(100.0));
+        assert_eq!(sell.stop_price, Some(95.0));
+        assert!(!sell.update_trail(110.0));
+        assert!(!sell.update_trail(106.0));
+        assert_eq!((sell.trail_mark, sell.stop_price), (Some(110.0), Some(105.0)));
+
+        // Touching the stop triggers it
+        assert!(sell.update_trail(105.0));
+        assert_eq!(sell.order_type, OrderType::Market);
+
+        // Buy 10% above the low, to cover a short
+        let mut buy = Order::trailing_stop(asset, OrderSide::Buy, 100.0, Trail::Percent(0.10), Utc::now());
+        assert!(!buy.update_trail(100.0));
+        assert!(!buy.update_trail(80.0));
+        assert_eq!(buy.stop_price, Some(88.0));
+        assert!(!buy.update_trail(87.0));
+        assert!(!buy.update_trail(70.0));
+        assert_eq!((buy.trail_mark, buy.stop_price), (Some(70.0), Some(77.0)));
+        assert_eq!(buy.order_type, OrderType::TrailingStop { trail: Trail::Percent(0.10) });
+        assert_ne!(Trail::Percent(0.10), Trail::Amount(0.10));
+    }
 }
@@ -1,10 +1,29 @@
 //! Performance analytics and metrics
 
-use crate::types::Timestamp;
-use chrono::{DateTime, Utc};
+use crate::error::{Result, ZiplineError};
+use crate::finance::{
+    Attribution, CapacityReport, ExposureSnapshot, PnlBreakdown, TaxReport, Transaction, Turnover,
+};
+use crate::types::{Cash, SessionId, Timestamp};
+use chrono::{DateTime, NaiveDate, Utc};
 use std::collections::HashMap;
+use std::path::Path;
 use serde::{Deserialize, Serialize};
 
+pub mod compact;
+pub mod compare;
+pub mod packet;
+#[cfg(feature = "plot")]
+pub mod plot;
+pub mod query;
+pub mod report;
+
+pub use compact::CompactResults;
+pub use compare::{PairStats, RunComparison, RunMetrics};
+pub use packet::{BacktestResult, DailyPositions};
+pub use query::{CurveFrequency, OrderFills};
+pub use report::{DrawdownPeriod, TearSheet};
+
 /// Performance metrics tracker
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceTracker {
@@ -14,6 +33,97 @@ pub struct PerformanceTracker {
     pub returns: Vec<(Timestamp, f64)>,
     /// Custom recorded variables (name -> [(timestamp, value)])
     pub recorded_vars: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+    /// Minute-level marks, populated in intraday mode
+    #[serde(default)]
+    pub intraday: Vec<IntradayMark>,
+    /// Running peak portfolio value across intraday marks
+    #[serde(default)]
+    intraday_peak: f64,
+    /// Session of the latest intraday mark
+    #[serde(default)]
+    intraday_session: Option<SessionId>,
+    /// Per-bar state fingerprints, when enabled
+    #[serde(default)]
+    pub fingerprints: Vec<(Timestamp, u64)>,
+    /// Executed trades, with any trade journal notes
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+    /// Orders scaled down by capacity limits and the alpha lost to them
+    #[serde(default)]
+    pub capacity: Option<CapacityReport>,
+    /// Each asset's contribution to daily P&L
+    #[serde(default)]
+    pub attribution: Attribution,
+    /// Seed of the run's random number generator
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Interest credited (positive) or charged (negative) on cash, by session
+    #[serde(default)]
+    pub interest: Vec<(NaiveDate, Cash)>,
+    /// Traded notional and participation in bar volume by session
+    #[serde(default)]
+    pub turnover: Turnover,
+    /// Exposure by side, sector and asset class at each session's close
+    #[serde(default)]
+    pub exposure: Vec<ExposureSnapshot>,
+    /// Realized gains by holding term, when tax lots are tracked
+    #[serde(default)]
+    pub tax: Option<TaxReport>,
+}
+
+/// Executed trades with their journal notes, saved apart from the full results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeJournal {
+    /// Trades in execution order
+    pub transactions: Vec<Transaction>,
+}
+
+/// First bar at which two runs' state fingerprints differ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the bar in the fingerprint series
+    pub index: usize,
+    /// Bar timestamp (from the first run, or the second if the first ended)
+    pub timestamp: Timestamp,
+    /// Fingerprint in the first run, `None` if it ended earlier
+    pub left: Option<u64>,
+    /// Fingerprint in the second run, `None` if it ended earlier
+    pub right: Option<u64>,
+}
+
+/// Portfolio state marked to market at one intraday bar
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IntradayMark {
+    pub timestamp: Timestamp,
+    pub portfolio_value: f64,
+    /// Cumulative return at this bar
+    pub returns: f64,
+    /// Drawdown from the running peak portfolio value (0.05 = 5%)
+    pub drawdown: f64,
+    /// Market value of long positions
+    pub long_exposure: f64,
+    /// Absolute market value of short positions
+    pub short_exposure: f64,
+}
+
+impl IntradayMark {
+    /// Gross exposure as a fraction of portfolio value
+    pub fn gross_leverage(&self) -> f64 {
+        if self.portfolio_value == 0.0 {
+            0.0
+        } else {
+            (self.long_exposure + self.short_exposure) / self.portfolio_value
+        }
+    }
+
+    /// Net exposure as a fraction of portfolio value
+    pub fn net_leverage(&self) -> f64 {
+        if self.portfolio_value == 0.0 {
+            0.0
+        } else {
+            (self.long_exposure - self.short_exposure) / self.portfolio_value
+        }
+    }
 }
 
 impl PerformanceTracker {
@@ -23,9 +133,26 @@ impl PerformanceTracker {
             values: Vec::new(),
             returns: Vec::new(),
             recorded_vars: HashMap::new(),
+            intraday: Vec::new(),
+            intraday_peak: 0.0,
+            intraday_session: None,
+            fingerprints: Vec::new(),
+            transactions: Vec::new(),
+            capacity: None,
+            attribution: Attribution::default(),
+            seed: None,
+            interest: Vec::new(),
+            turnover: Turnover::default(),
+            exposure: Vec::new(),
+            tax: None,
         }
     }
 
+    /// Net interest earned on cash over the run
+    pub fn total_interest(&self) -> Cash {
+        self.interest.iter().map(|(_, amount)| amount).sum()
+    }
+
     /// Update recorded variables from context
     ///
     /// This merges the recorded variables from an algorithm context into
@@ -69,6 +196,174 @@ impl PerformanceTracker {
         self.returns.push((timestamp, returns));
     }
 
+    /// Record an intraday mark
+    ///
+    /// The standard series (`values`/`returns`) are downsampled to one point
+    /// per trading session: when a mark falls in a new session, the previous
+    /// session's last mark is recorded. Sessions rather than UTC dates decide
+    /// the split, so a session spanning UTC midnight stays one point. Call [`PerformanceTracker::finish_intraday`] after the final
+    /// bar to record the last day.
+    pub fn record_intraday(
+        &mut self,
+        timestamp: Timestamp,
+        session: SessionId,
+        portfolio_value: f64,
+        returns: f64,
+        long_exposure: f64,
+        short_exposure: f64,
+    ) {
+        if let Some(last) = self.intraday.last().copied() {
+            if self.intraday_session != Some(session) {
+                self.record(last.timestamp, last.portfolio_value, last.returns);
+            }
+        }
+        self.intraday_session = Some(session);
+
+        self.intraday_peak = self.intraday_peak.max(portfolio_value);
+        let drawdown = if self.intraday_peak > 0.0 {
+            (self.intraday_peak - portfolio_value) / self.intraday_peak
+        } else {
+            0.0
+        };
+
+        self.intraday.push(IntradayMark {
+            timestamp,
+            portfolio_value,
+            returns,
+            drawdown,
+            long_exposure,
+            short_exposure,
+        });
+    }
+
+    /// Record the last intraday mark's day in the daily series
+    pub fn finish_intraday(&mut self) {
+        if let Some(last) = self.intraday.last().copied() {
+            if self.values.last().map(|(t, _)| *t) != Some(last.timestamp) {
+                self.record(last.timestamp, last.portfolio_value, last.returns);
+            }
+        }
+    }
+
+    /// Whether intraday marks were recorded
+    pub fn is_intraday(&self) -> bool {
+        !self.intraday.is_empty()
+    }
+
+    /// Maximum drawdown measured on every intraday mark
+    ///
+    /// Captures drawdowns that recover before the close and so are invisible
+    /// in the daily series.
+    pub fn intraday_max_drawdown(&self) -> f64 {
+        self.intraday.iter().map(|m| m.drawdown).fold(0.0, f64::max)
+    }
+
+    /// Intraday drawdown series
+    pub fn intraday_drawdowns(&self) -> Vec<(Timestamp, f64)> {
+        self.intraday.iter().map(|m| (m.timestamp, m.drawdown)).collect()
+    }
+
+    /// Intraday gross exposure series, as a fraction of portfolio value
+    pub fn gross_exposure_series(&self) -> Vec<(Timestamp, f64)> {
+        self.intraday
+            .iter()
+            .map(|m| (m.timestamp, m.gross_leverage()))
+            .collect()
+    }
+
+    /// Intraday net exposure series, as a fraction of portfolio value
+    pub fn net_exposure_series(&self) -> Vec<(Timestamp, f64)> {
+        self.intraday
+            .iter()
+            .map(|m| (m.timestamp, m.net_leverage()))
+            .collect()
+    }
+
+    /// Record an executed trade
+    pub fn record_transaction(&mut self, transaction: Transaction) {
+        self.transactions.push(transaction);
+    }
+
+    /// Each asset's total P&L over the run by source, largest first
+    pub fn attribution_table(&self) -> Vec<(u64, PnlBreakdown)> {
+        self.attribution.table()
+    }
+
+    /// Executed trades as a trade journal
+    pub fn journal(&self) -> TradeJournal {
+        TradeJournal {
+            transactions: self.transactions.clone(),
+        }
+    }
+
+    /// Export executed trades, including trade journal notes, to CSV
+    ///
+    /// CSV format: dt,order_id,asset_id,side,amount,price,commission,note
+    pub fn write_transactions_csv(&self, path: &Path) -> Result<()> {
+        let csv_error = |e: csv::Error| {
+            ZiplineError::DataError(format!("Failed to write transactions: {}", e))
+        };
+        let mut writer = csv::Writer::from_path(path).map_err(csv_error)?;
+
+        writer
+            .write_record([
+                "dt",
+                "order_id",
+                "asset_id",
+                "side",
+                "amount",
+                "price",
+                "commission",
+                "note",
+            ])
+            .map_err(csv_error)?;
+
+        for txn in &self.transactions {
+            writer
+                .write_record([
+                    txn.dt.to_rfc3339(),
+                    txn.order_id.to_string(),
+                    txn.asset_id.to_string(),
+                    format!("{:?}", txn.side),
+                    txn.amount.to_string(),
+                    txn.price.to_string(),
+                    txn.commission.to_string(),
+                    txn.note.clone().unwrap_or_default(),
+                ])
+                .map_err(csv_error)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Record the state fingerprint for a bar
+    pub fn record_fingerprint(&mut self, timestamp: Timestamp, fingerprint: u64) {
+        self.fingerprints.push((timestamp, fingerprint));
+    }
+
+    /// Find the first bar where this run's fingerprints differ from `other`'s
+    ///
+    /// Returns `None` if both runs recorded identical fingerprint series.
+    pub fn first_divergence(&self, other: &PerformanceTracker) -> Option<Divergence> {
+        let len = self.fingerprints.len().max(other.fingerprints.len());
+        (0..len).find_map(|index| {
+            let left = self.fingerprints.get(index);
+            let right = other.fingerprints.get(index);
+            if left == right {
+                return None;
+            }
+
+            let timestamp = left.or(right).map(|(t, _)| *t)?;
+            Some(Divergence {
+                index,
+                timestamp,
+                left: left.map(|(_, f)| *f),
+                right: right.map(|(_, f)| *f),
+            })
+        })
+    }
+
     /// Calculate total return
     pub fn total_return(&self) -> f64 {
         self.returns.last().map(|(_, r)| *r).unwrap_or(0.0)
@@ -191,6 +486,13 @@ impl PerformanceTracker {
             max_drawdown: self.max_drawdown(),
             volatility: self.volatility(),
             num_periods: self.values.len(),
+            interest: self.total_interest(),
+            turnover: self.turnover.average_turnover(),
+            intraday_max_drawdown: if self.is_intraday() {
+                Some(self.intraday_max_drawdown())
+            } else {
+                None
+            },
         }
     }
 }
@@ -211,6 +513,15 @@ pub struct PerformanceSummary {
     pub max_drawdown: f64,
     pub volatility: f64,
     pub num_periods: usize,
+    /// Net interest earned on cash
+    #[serde(default)]
+    pub interest: f64,
+    /// Mean daily traded notional as a fraction of portfolio value
+    #[serde(default)]
+    pub turnover: f64,
+    /// Maximum drawdown across minute bars, when run in intraday mode
+    #[serde(default)]
+    pub intraday_max_drawdown: Option<f64>,
 }
 
 impl std::fmt::Display for PerformanceSummary {
@@ -225,8 +536,17 @@ impl std::fmt::Display for PerformanceSummary {
         writeln!(f, "  Sharpe Ratio:       {:.2}", self.sharpe_ratio)?;
         writeln!(f, "  Sortino Ratio:      {:.2}", self.sortino_ratio)?;
         writeln!(f, "  Max Drawdown:       {:.2}%", self.max_drawdown * 100.0)?;
+        if let Some(intraday_dd) = self.intraday_max_drawdown {
+            writeln!(f, "  Intraday Max DD:    {:.2}%", intraday_dd * 100.0)?;
+        }
         writeln!(f, "  Volatility:         {:.2}%", self.volatility * 100.0)?;
         writeln!(f, "  Periods:            {}", self.num_periods)?;
+        if self.turnover != 0.0 {
+            writeln!(f, "  Daily Turnover:     {:.2}%", self.turnover * 100.0)?;
+        }
+        if self.interest != 0.0 {
+            writeln!(f, "  Interest:           ${:.2}", self.interest)?;
+        }
         Ok(())
     }
 }
@@ -276,4 +596,83 @@ mod tests {
         assert_eq!(summary.total_return, 0.20);
         assert!(summary.annualized_return > 0.0);
     }
+
+    #[test]
+    fn test_intraday_downsampling() {
+        use chrono::TimeZone;
+
+        let mut tracker = PerformanceTracker::new();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 3, 14, 30, 0).unwrap();
+        let session1 = SessionId::from_date(day1.date_naive());
+        let session2 = SessionId::from_date(day2.date_naive());
+
+        // Day 1 dips 10% intraday but closes flat
+        tracker.record_intraday(day1, session1, 100000.0, 0.0, 50000.0, 0.0);
+        tracker.record_intraday(day1 + chrono::Duration::minutes(1), session1, 90000.0, -0.10, 40000.0, 0.0);
+        tracker.record_intraday(day1 + chrono::Duration::minutes(2), session1, 100000.0, 0.0, 50000.0, 0.0);
+        assert!(tracker.values.is_empty());
+
+        tracker.record_intraday(day2, session2, 101000.0, 0.01, 60000.0, 20000.0);
+        assert_eq!(tracker.values.len(), 1);
+        assert_eq!(tracker.values[0], (day1 + chrono::Duration::minutes(2), 100000.0));
+
+        tracker.finish_intraday();
+        tracker.finish_intraday();
+        assert_eq!(tracker.values.len(), 2);
+        assert_eq!(tracker.total_return(), 0.01);
+
+        // The intraday dip never shows up in the daily series
+        assert_eq!(tracker.max_drawdown(), 0.0);
+        assert!((tracker.intraday_max_drawdown() - 0.10).abs() < 1e-12);
+        assert_eq!(tracker.summary().intraday_max_drawdown, Some(tracker.intraday_max_drawdown()));
+
+        let gross = tracker.gross_exposure_series();
+        let net = tracker.net_exposure_series();
+        assert_eq!(gross.len(), 4);
+        assert!((gross[3].1 - 80000.0 / 101000.0).abs() < 1e-12);
+        assert!((net[3].1 - 40000.0 / 101000.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_intraday_session_spans_utc_midnight() {
+        use chrono::TimeZone;
+
+        // A session opening the evening before its trade date, as on Globex
+        let mut tracker = PerformanceTracker::new();
+        let session = SessionId::from_date(chrono::NaiveDate::from_ymd_opt(2024, 1, 3).unwrap());
+        let evening = Utc.with_ymd_and_hms(2024, 1, 2, 23, 0, 0).unwrap();
+        tracker.record_intraday(evening, session, 100000.0, 0.0, 0.0, 0.0);
+        tracker.record_intraday(evening + chrono::Duration::hours(2), session, 101000.0, 0.01, 0.0, 0.0);
+        assert!(tracker.values.is_empty());
+
+        tracker.finish_intraday();
+        assert_eq!(tracker.values, vec![(evening + chrono::Duration::hours(2), 101000.0)]);
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let start = Utc::now();
+        let mut left = PerformanceTracker::new();
+        let mut right = PerformanceTracker::new();
+        for i in 0..3 {
+            let t = start + chrono::Duration::minutes(i);
+            left.record_fingerprint(t, i as u64);
+            right.record_fingerprint(t, i as u64);
+        }
+        assert_eq!(left.first_divergence(&right), None);
+
+        let t = start + chrono::Duration::minutes(3);
+        left.record_fingerprint(t, 3);
+        right.record_fingerprint(t, 99);
+        let divergence = left.first_divergence(&right).unwrap();
+        assert_eq!(divergence.index, 3);
+        assert_eq!(divergence.timestamp, t);
+        assert_eq!((divergence.left, divergence.right), (Some(3), Some(99)));
+
+        // A run that stops early diverges where it ends
+        right.fingerprints.truncate(2);
+        let divergence = left.first_divergence(&right).unwrap();
+        assert_eq!((divergence.index, divergence.right), (2, None));
+    }
 }
@@ -0,0 +1,341 @@
+//! Compact storage for long intraday results
+//!
+//! Minute backtests record hundreds of thousands of marks, most of them on an
+//! even clock and many of them unchanged. [`CompactResults`] stores a
+//! [`PerformanceTracker`] with its timestamps as runs of equal steps, every
+//! series as runs of repeated values, and positions only as the trades that
+//! change them. Nothing is lost: [`CompactResults::to_tracker`] rebuilds the
+//! full tracker, and the position series is reconstructed on demand from the
+//! trades with [`CompactResults::position_series`].
+//!
+//! ```ignore
+//! compact::save(&performance, Path::new("minute_run.json"))?;
+//! let performance = compact::load_results(Path::new("minute_run.json"))?;
+//! ```
+
+use super::{BacktestResult, IntradayMark, PerformanceTracker};
+use crate::error::Result;
+use crate::finance::{CapacityReport, Transaction};
+use crate::serialization::{self, Envelope, Versioned};
+use crate::types::Timestamp;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// `count` timestamps starting at `start`, `step_ms` apart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TimeRun {
+    start: Timestamp,
+    step_ms: i64,
+    count: usize,
+}
+
+/// Timestamps as runs of equal steps
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct TimeRuns(Vec<TimeRun>);
+
+impl TimeRuns {
+    fn encode(times: impl IntoIterator<Item = Timestamp>) -> Self {
+        let mut runs: Vec<TimeRun> = Vec::new();
+        for time in times {
+            match runs.last_mut() {
+                Some(run) if run.count == 1 => {
+                    run.step_ms = (time - run.start).num_milliseconds();
+                    run.count = 2;
+                }
+                Some(run)
+                    if run.start + Duration::milliseconds(run.step_ms * run.count as i64)
+                        == time =>
+                {
+                    run.count += 1;
+                }
+                _ => runs.push(TimeRun {
+                    start: time,
+                    step_ms: 0,
+                    count: 1,
+                }),
+            }
+        }
+        Self(runs)
+    }
+
+    fn decode(&self) -> Vec<Timestamp> {
+        self.0
+            .iter()
+            .flat_map(|run| {
+                (0..run.count).map(move |i| run.start + Duration::milliseconds(run.step_ms * i as i64))
+            })
+            .collect()
+    }
+}
+
+/// Values as `(value, repeat)` runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct ValueRuns(Vec<(f64, usize)>);
+
+impl ValueRuns {
+    fn encode(values: impl IntoIterator<Item = f64>) -> Self {
+        let mut runs: Vec<(f64, usize)> = Vec::new();
+        for value in values {
+            match runs.last_mut() {
+                // Compare bits so NaN runs and signed zeros round-trip
+                Some((last, count)) if last.to_bits() == value.to_bits() => *count += 1,
+                _ => runs.push((value, 1)),
+            }
+        }
+        Self(runs)
+    }
+
+    fn decode(&self) -> Vec<f64> {
+        self.0
+            .iter()
+            .flat_map(|&(value, count)| std::iter::repeat_n(value, count))
+            .collect()
+    }
+}
+
+/// Intraday marks stored column-wise
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct IntradayColumns {
+    times: TimeRuns,
+    portfolio_value: ValueRuns,
+    returns: ValueRuns,
+    drawdown: ValueRuns,
+    long_exposure: ValueRuns,
+    short_exposure: ValueRuns,
+}
+
+/// A [`PerformanceTracker`] in compact form
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactResults {
+    times: TimeRuns,
+    values: ValueRuns,
+    returns: ValueRuns,
+    #[serde(default)]
+    intraday: IntradayColumns,
+    #[serde(default)]
+    recorded_vars: HashMap<String, Vec<(DateTime<Utc>, f64)>>,
+    #[serde(default)]
+    fingerprints: Vec<(Timestamp, u64)>,
+    /// Trades, from which positions are reconstructed
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+    #[serde(default)]
+    capacity: Option<CapacityReport>,
+}
+
+impl CompactResults {
+    /// Compact a tracker
+    pub fn from_tracker(tracker: &PerformanceTracker) -> Self {
+        let marks = &tracker.intraday;
+        Self {
+            times: TimeRuns::encode(tracker.values.iter().map(|(t, _)| *t)),
+            values: ValueRuns::encode(tracker.values.iter().map(|(_, v)| *v)),
+            returns: ValueRuns::encode(tracker.returns.iter().map(|(_, r)| *r)),
+            intraday: IntradayColumns {
+                times: TimeRuns::encode(marks.iter().map(|m| m.timestamp)),
+                portfolio_value: ValueRuns::encode(marks.iter().map(|m| m.portfolio_value)),
+                returns: ValueRuns::encode(marks.iter().map(|m| m.returns)),
+                drawdown: ValueRuns::encode(marks.iter().map(|m| m.drawdown)),
+                long_exposure: ValueRuns::encode(marks.iter().map(|m| m.long_exposure)),
+                short_exposure: ValueRuns::encode(marks.iter().map(|m| m.short_exposure)),
+            },
+            recorded_vars: tracker.recorded_vars.clone(),
+            fingerprints: tracker.fingerprints.clone(),
+            transactions: tracker.transactions.clone(),
+            capacity: tracker.capacity.clone(),
+        }
+    }
+
+    /// Rebuild the full tracker
+    pub fn to_tracker(&self) -> PerformanceTracker {
+        let times = self.times.decode();
+        let columns = &self.intraday;
+        let intraday: Vec<IntradayMark> = columns
+            .times
+            .decode()
+            .into_iter()
+            .zip(columns.portfolio_value.decode())
+            .zip(columns.returns.decode())
+            .zip(columns.drawdown.decode())
+            .zip(columns.long_exposure.decode())
+            .zip(columns.short_exposure.decode())
+            .map(
+                |(((((timestamp, portfolio_value), returns), drawdown), long_exposure), short_exposure)| {
+                    IntradayMark {
+                        timestamp,
+                        portfolio_value,
+                        returns,
+                        drawdown,
+                        long_exposure,
+                        short_exposure,
+                    }
+                },
+            )
+            .collect();
+
+        let mut tracker = PerformanceTracker::new();
+        tracker.values = times.iter().copied().zip(self.values.decode()).collect();
+        tracker.returns = times.into_iter().zip(self.returns.decode()).collect();
+        tracker.intraday_peak = intraday.iter().map(|m| m.portfolio_value).fold(0.0, f64::max);
+        tracker.intraday = intraday;
+        tracker.recorded_vars = self.recorded_vars.clone();
+        tracker.fingerprints = self.fingerprints.clone();
+        tracker.transactions = self.transactions.clone();
+        tracker.capacity = self.capacity.clone();
+        tracker
+    }
+
+    /// Timestamps of the portfolio value series
+    pub fn timestamps(&self) -> Vec<Timestamp> {
+        self.times.decode()
+    }
+
+    /// Portfolio value series
+    pub fn values(&self) -> Vec<(Timestamp, f64)> {
+        self.times.decode().into_iter().zip(self.values.decode()).collect()
+    }
+
+    /// Number of entries in the portfolio value series
+    pub fn len(&self) -> usize {
+        self.times.0.iter().map(|run| run.count).sum()
+    }
+
+    /// Whether no values were recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Trades in execution order
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Shares held in each asset after every trade up to and including `dt`
+    pub fn positions_at(&self, dt: Timestamp) -> HashMap<u64, f64> {
+        super::query::positions_at(&self.transactions, dt)
+    }
+
+    /// Shares held in `asset_id` at each timestamp of the value series
+    pub fn position_series(&self, asset_id: u64) -> Vec<(Timestamp, f64)> {
+        let mut trades = self
+            .transactions
+            .iter()
+            .filter(|txn| txn.asset_id == asset_id)
+            .peekable();
+        let mut shares = 0.0;
+
+        self.times
+            .decode()
+            .into_iter()
+            .map(|time| {
+                while let Some(txn) = trades.next_if(|txn| txn.dt <= time) {
+                    shares += txn.amount;
+                }
+                (time, shares)
+            })
+            .collect()
+    }
+}
+
+impl crate::sealed::Sealed for CompactResults {}
+
+impl Versioned for CompactResults {
+    const KIND: &'static str = "performance_compact";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// Save a tracker in compact form
+pub fn save(tracker: &PerformanceTracker, path: &Path) -> Result<()> {
+    serialization::save(&CompactResults::from_tracker(tracker), path)
+}
+
+/// Load a results file saved in full, in compact form, or as a perf packet
+pub fn load_results(path: &Path) -> Result<PerformanceTracker> {
+    if !super::packet::is_json(path)? {
+        return Ok(BacktestResult::read_packet(path)?.to_tracker());
+    }
+
+    let envelope = Envelope::from_value(serde_json::from_str(&std::fs::read_to_string(path)?)?)?;
+    if envelope.kind == CompactResults::KIND {
+        Ok(envelope.unwrap::<CompactResults>()?.to_tracker())
+    } else if envelope.kind == BacktestResult::KIND {
+        Ok(envelope.unwrap::<BacktestResult>()?.to_tracker())
+    } else {
+        envelope.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::OrderSide;
+    use chrono::TimeZone;
+
+    fn minute_run() -> PerformanceTracker {
+        let open = Utc.with_ymd_and_hms(2024, 1, 2, 14, 30, 0).unwrap();
+        let mut tracker = PerformanceTracker::new();
+        for day in 0..2 {
+            for minute in 0..390 {
+                let t = open + Duration::days(day) + Duration::minutes(minute);
+                // Flat until the first trade at 10:00
+                let value = if day == 0 && minute < 30 { 100_000.0 } else { 100_000.0 + minute as f64 };
+                let session = crate::types::SessionId::from_date(t.date_naive());
+                tracker.record_intraday(t, session, value, value / 100_000.0 - 1.0, value - 50_000.0, 0.0);
+            }
+        }
+        tracker.finish_intraday();
+
+        let buy = Transaction::new(1, uuid::Uuid::new_v4(), open + Duration::minutes(30), 100.0, 500.0, 1.0, OrderSide::Buy);
+        let sell = Transaction::new(1, uuid::Uuid::new_v4(), open + Duration::days(1), -40.0, 510.0, 1.0, OrderSide::Sell);
+        tracker.record_transaction(buy);
+        tracker.record_transaction(sell);
+        tracker
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let tracker = minute_run();
+        let compact = CompactResults::from_tracker(&tracker);
+
+        // Two sessions of evenly spaced minutes
+        assert_eq!(compact.intraday.times.0.len(), 2);
+        assert!(compact.intraday.portfolio_value.0.len() < tracker.intraday.len());
+
+        let rebuilt = compact.to_tracker();
+        assert_eq!(rebuilt.values, tracker.values);
+        assert_eq!(rebuilt.returns, tracker.returns);
+        assert_eq!(rebuilt.intraday, tracker.intraday);
+        assert_eq!(rebuilt.transactions.len(), 2);
+        assert_eq!(rebuilt.intraday_max_drawdown(), tracker.intraday_max_drawdown());
+
+        let dir = tempfile::tempdir().unwrap();
+        let full_path = dir.path().join("full.json");
+        let compact_path = dir.path().join("compact.json");
+        serialization::save(&tracker, &full_path).unwrap();
+        save(&tracker, &compact_path).unwrap();
+        assert!(
+            std::fs::metadata(&compact_path).unwrap().len()
+                < std::fs::metadata(&full_path).unwrap().len()
+        );
+        let full = load_results(&full_path).unwrap();
+        let compacted = load_results(&compact_path).unwrap();
+        assert_eq!(compacted.intraday, full.intraday);
+        assert_eq!(compacted.values, full.values);
+    }
+
+    #[test]
+    fn test_positions_reconstructed_from_trades() {
+        let tracker = minute_run();
+        let compact = CompactResults::from_tracker(&tracker);
+        let first_trade = tracker.transactions[0].dt;
+
+        let series = compact.position_series(1);
+        assert_eq!(series.len(), compact.len());
+        assert_eq!(series[0].1, 100.0);
+        assert_eq!(series.last().unwrap().1, 60.0);
+        assert_eq!(compact.positions_at(first_trade - Duration::minutes(1)).len(), 0);
+        assert_eq!(compact.positions_at(first_trade)[&1], 100.0);
+    }
+}
@@ -0,0 +1,405 @@
+//! Side-by-side comparison of backtest runs
+//!
+//! [`RunComparison`] lines up the key metrics of several runs and, for each
+//! pair of runs, the correlation of their daily returns, how much more one
+//! trades than the other, and how often they were in drawdown at the same time. Runs are aligned on the dates they
+//! share, using each run's last portfolio value of the day.
+
+use super::PerformanceTracker;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+/// Key metrics of one run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunMetrics {
+    /// Name shown in the table, usually the results file stem
+    pub name: String,
+    pub total_return: f64,
+    pub annualized_return: f64,
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub volatility: f64,
+    pub max_drawdown: f64,
+    /// Number of executed trades
+    pub trades: usize,
+    /// Average daily traded notional as a share of the day's closing value
+    pub turnover: f64,
+    /// Number of trading days covered
+    pub days: usize,
+}
+
+/// How two runs moved together
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairStats {
+    /// Index of the first run in [`RunComparison::runs`]
+    pub left: usize,
+    /// Index of the second run in [`RunComparison::runs`]
+    pub right: usize,
+    /// Days with a portfolio value in both runs
+    pub common_days: usize,
+    /// Pearson correlation of daily returns, if both runs varied
+    pub correlation: Option<f64>,
+    /// Turnover of the second run less that of the first
+    pub turnover_difference: f64,
+    /// Share of the days either run was in drawdown on which both were
+    pub drawdown_overlap: Option<f64>,
+}
+
+/// Metrics and pairwise statistics for a set of runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunComparison {
+    pub runs: Vec<RunMetrics>,
+    pub pairs: Vec<PairStats>,
+}
+
+/// End-of-day portfolio values of a run
+fn daily_values(tracker: &PerformanceTracker) -> BTreeMap<NaiveDate, f64> {
+    tracker
+        .values
+        .iter()
+        .map(|(ts, value)| (ts.date_naive(), *value))
+        .collect()
+}
+
+/// Average of each day's traded notional over its closing value
+fn daily_turnover(tracker: &PerformanceTracker, values: &BTreeMap<NaiveDate, f64>) -> f64 {
+    let mut traded: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for txn in &tracker.transactions {
+        *traded.entry(txn.dt.date_naive()).or_insert(0.0) += (txn.amount * txn.price).abs();
+    }
+
+    let ratios: Vec<f64> = values
+        .iter()
+        .filter(|(_, value)| **value > 0.0)
+        .map(|(day, value)| traded.get(day).copied().unwrap_or(0.0) / value)
+        .collect();
+    if ratios.is_empty() {
+        0.0
+    } else {
+        ratios.iter().sum::<f64>() / ratios.len() as f64
+    }
+}
+
+/// Drawdown from the running peak on each day
+fn daily_drawdowns(values: &BTreeMap<NaiveDate, f64>) -> BTreeMap<NaiveDate, f64> {
+    let mut peak = f64::MIN;
+    values
+        .iter()
+        .map(|(day, value)| {
+            peak = peak.max(*value);
+            let drawdown = if peak > 0.0 { (peak - value) / peak } else { 0.0 };
+            (*day, drawdown)
+        })
+        .collect()
+}
+
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len().min(ys.len());
+    if n < 2 {
+        return None;
+    }
+    let mean_x = xs.iter().sum::<f64>() / n as f64;
+    let mean_y = ys.iter().sum::<f64>() / n as f64;
+
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+
+    if var_x == 0.0 || var_y == 0.0 {
+        None
+    } else {
+        Some(cov / (var_x * var_y).sqrt())
+    }
+}
+
+fn pair_stats(
+    runs: &[RunMetrics],
+    left: usize,
+    right: usize,
+    a: &BTreeMap<NaiveDate, f64>,
+    b: &BTreeMap<NaiveDate, f64>,
+) -> PairStats {
+    let common: Vec<(f64, f64)> = a
+        .iter()
+        .filter_map(|(day, x)| b.get(day).map(|y| (*x, *y)))
+        .collect();
+
+    let (mut xs, mut ys) = (Vec::new(), Vec::new());
+    for pair in common.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x0 != 0.0 && y0 != 0.0 {
+            xs.push(x1 / x0 - 1.0);
+            ys.push(y1 / y0 - 1.0);
+        }
+    }
+
+    let (dd_a, dd_b) = (daily_drawdowns(a), daily_drawdowns(b));
+    let (mut either, mut both) = (0usize, 0usize);
+    for (day, x) in &dd_a {
+        if let Some(y) = dd_b.get(day) {
+            let (in_a, in_b) = (*x > 0.0, *y > 0.0);
+            either += (in_a || in_b) as usize;
+            both += (in_a && in_b) as usize;
+        }
+    }
+
+    PairStats {
+        left,
+        right,
+        common_days: common.len(),
+        correlation: correlation(&xs, &ys),
+        turnover_difference: runs[right].turnover - runs[left].turnover,
+        drawdown_overlap: (either > 0).then(|| both as f64 / either as f64),
+    }
+}
+
+fn percent(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{:.1}%", v * 100.0))
+}
+
+impl RunComparison {
+    /// Compare named runs, in the order given
+    pub fn new<'a, I>(runs: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a PerformanceTracker)>,
+    {
+        let runs: Vec<(&str, &PerformanceTracker)> = runs.into_iter().collect();
+        let daily: Vec<_> = runs.iter().map(|(_, tracker)| daily_values(tracker)).collect();
+
+        let metrics: Vec<RunMetrics> = runs
+            .iter()
+            .zip(&daily)
+            .map(|((name, tracker), days)| RunMetrics {
+                name: name.to_string(),
+                total_return: tracker.total_return(),
+                annualized_return: tracker.annualized_return(),
+                sharpe_ratio: tracker.sharpe_ratio(),
+                sortino_ratio: tracker.sortino_ratio(),
+                volatility: tracker.volatility(),
+                max_drawdown: tracker.max_drawdown(),
+                trades: tracker.transactions.len(),
+                turnover: daily_turnover(tracker, days),
+                days: days.len(),
+            })
+            .collect();
+
+        let mut pairs = Vec::new();
+        for left in 0..daily.len() {
+            for right in left + 1..daily.len() {
+                pairs.push(pair_stats(&metrics, left, right, &daily[left], &daily[right]));
+            }
+        }
+
+        Self {
+            runs: metrics,
+            pairs,
+        }
+    }
+
+    /// Metric rows as `(label, one cell per run)`
+    fn metric_rows(&self) -> Vec<(&'static str, Vec<String>)> {
+        let cells = |f: &dyn Fn(&RunMetrics) -> String| self.runs.iter().map(f).collect();
+        vec![
+            ("Total Return", cells(&|r| format!("{:.2}%", r.total_return * 100.0))),
+            ("Annualized Return", cells(&|r| format!("{:.2}%", r.annualized_return * 100.0))),
+            ("Sharpe Ratio", cells(&|r| format!("{:.2}", r.sharpe_ratio))),
+            ("Sortino Ratio", cells(&|r| format!("{:.2}", r.sortino_ratio))),
+            ("Volatility", cells(&|r| format!("{:.2}%", r.volatility * 100.0))),
+            ("Max Drawdown", cells(&|r| format!("{:.2}%", r.max_drawdown * 100.0))),
+            ("Trades", cells(&|r| r.trades.to_string())),
+            ("Daily Turnover", cells(&|r| format!("{:.2}%", r.turnover * 100.0))),
+            ("Days", cells(&|r| r.days.to_string())),
+        ]
+    }
+
+    /// Render the comparison as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let escape = |s: &str| {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        };
+
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Backtest Comparison</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; margin-bottom: 2em; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: right; }\n\
+             th:first-child, td:first-child { text-align: left; }\n\
+             </style>\n</head>\n<body>\n<h1>Backtest Comparison</h1>\n",
+        );
+
+        html.push_str("<h2>Metrics</h2>\n<table>\n<tr><th>Metric</th>");
+        for run in &self.runs {
+            let _ = write!(html, "<th>{}</th>", escape(&run.name));
+        }
+        html.push_str("</tr>\n");
+        for (label, cells) in self.metric_rows() {
+            let _ = write!(html, "<tr><td>{}</td>", label);
+            for cell in cells {
+                let _ = write!(html, "<td>{}</td>", cell);
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+
+        if !self.pairs.is_empty() {
+            html.push_str(
+                "<h2>Pairs</h2>\n<table>\n<tr><th>Runs</th><th>Common Days</th>\
+                 <th>Return Correlation</th><th>Turnover Difference</th>\
+                 <th>Drawdown Overlap</th></tr>\n",
+            );
+            for pair in &self.pairs {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{} / {}</td><td>{}</td><td>{}</td><td>{:+.2}%</td><td>{}</td></tr>",
+                    escape(&self.runs[pair.left].name),
+                    escape(&self.runs[pair.right].name),
+                    pair.common_days,
+                    pair.correlation.map_or_else(|| "-".to_string(), |c| format!("{:.3}", c)),
+                    pair.turnover_difference * 100.0,
+                    percent(pair.drawdown_overlap)
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl fmt::Display for RunComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .runs
+            .iter()
+            .map(|r| r.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(10);
+
+        write!(f, "{:<20}", "Metric")?;
+        for run in &self.runs {
+            write!(f, " {:>width$}", run.name)?;
+        }
+        writeln!(f)?;
+        for (label, cells) in self.metric_rows() {
+            write!(f, "{:<20}", label)?;
+            for cell in cells {
+                write!(f, " {:>width$}", cell)?;
+            }
+            writeln!(f)?;
+        }
+
+        if !self.pairs.is_empty() {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{:<width$} {:>11} {:>12} {:>13} {:>12}",
+                "Runs",
+                "Common Days",
+                "Correlation",
+                "Turnover Diff",
+                "DD Overlap",
+                width = 2 * width + 3
+            )?;
+            for pair in &self.pairs {
+                let runs = format!(
+                    "{} / {}",
+                    self.runs[pair.left].name, self.runs[pair.right].name
+                );
+                writeln!(
+                    f,
+                    "{:<width$} {:>11} {:>12} {:>13} {:>12}",
+                    runs,
+                    pair.common_days,
+                    pair.correlation.map_or_else(|| "-".to_string(), |c| format!("{:.3}", c)),
+                    format!("{:+.2}%", pair.turnover_difference * 100.0),
+                    percent(pair.drawdown_overlap),
+                    width = 2 * width + 3
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn tracker(values: &[f64]) -> PerformanceTracker {
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut tracker = PerformanceTracker::new();
+        for (i, value) in values.iter().enumerate() {
+            let returns = value / values[0] - 1.0;
+            tracker.record(start + Duration::days(i as i64), *value, returns);
+        }
+        tracker
+    }
+
+    #[test]
+    fn test_compare_runs() {
+        let base = tracker(&[100.0, 110.0, 99.0, 120.0, 115.0]);
+        let mut scaled = tracker(&[200.0, 220.0, 198.0, 240.0, 230.0]);
+        // Trades 22 of 220 on the second day
+        scaled.record_transaction(crate::finance::Transaction::new(
+            1,
+            uuid::Uuid::new_v4(),
+            scaled.values[1].0,
+            -2.0,
+            11.0,
+            0.0,
+            crate::order::OrderSide::Sell,
+        ));
+        let inverse = tracker(&[100.0, 90.0, 99.0, 80.0, 84.0]);
+
+        let comparison =
+            RunComparison::new([("base", &base), ("scaled", &scaled), ("inverse", &inverse)]);
+
+        assert_eq!(comparison.runs.len(), 3);
+        assert_eq!(comparison.runs[1].days, 5);
+        assert_eq!(comparison.pairs.len(), 3);
+
+        // Same daily returns, same drawdown days
+        let same = &comparison.pairs[0];
+        assert_eq!((same.left, same.right, same.common_days), (0, 1, 5));
+        assert!((same.correlation.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(same.drawdown_overlap, Some(1.0));
+        assert!((comparison.runs[1].turnover - 0.02).abs() < 1e-12);
+        assert!((same.turnover_difference - 0.02).abs() < 1e-12);
+
+        // Moves against the base run
+        let opposed = &comparison.pairs[1];
+        assert!(opposed.correlation.unwrap() < -0.9);
+        assert!(opposed.drawdown_overlap.unwrap() < 1.0);
+
+        let table = comparison.to_string();
+        assert!(table.contains("Sharpe Ratio"));
+        assert!(table.contains("base / inverse"));
+        let html = comparison.to_html();
+        assert!(html.contains("<th>scaled</th>"));
+        assert!(html.contains("Drawdown Overlap"));
+        assert!(html.contains("+2.00%"));
+    }
+
+    #[test]
+    fn test_compare_without_overlap() {
+        let early = tracker(&[100.0, 101.0]);
+        let mut late = PerformanceTracker::new();
+        late.record(Utc.with_ymd_and_hms(2025, 1, 2, 21, 0, 0).unwrap(), 100.0, 0.0);
+
+        let comparison = RunComparison::new([("early", &early), ("late", &late)]);
+        let pair = &comparison.pairs[0];
+        assert_eq!(pair.common_days, 0);
+        assert_eq!(pair.correlation, None);
+        assert_eq!(pair.drawdown_overlap, None);
+    }
+}
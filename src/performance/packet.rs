@@ -0,0 +1,197 @@
+//! Complete backtest results in a single file
+//!
+//! A [`BacktestResult`] gathers what later analysis of a run needs: daily
+//! values and returns, the positions held at each close, every transaction
+//! and order, recorded variables, per-asset P&L attribution and summary
+//! metrics. It is written as a MessagePack "perf packet" for archiving, or
+//! exported as versioned JSON for other tools. Both carry the usual envelope,
+//! so either is checked for kind and schema version when read back.
+//!
+//! ```ignore
+//! let result = BacktestResult::from_tracker(&performance);
+//! result.write_packet(Path::new("run.perf"))?;
+//! result.export_json(Path::new("run.json"))?;
+//! let archived = BacktestResult::load(Path::new("run.perf"))?;
+//! ```
+
+use super::query::{CurveFrequency, OrderFills};
+use super::{PerformanceSummary, PerformanceTracker};
+use crate::error::{Result, ZiplineError};
+use crate::finance::{Attribution, Transaction};
+use crate::serialization::{self, Envelope, Versioned};
+use crate::types::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Shares held in each asset at a day's close
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyPositions {
+    pub timestamp: Timestamp,
+    /// Shares per asset id, flat positions left out
+    pub positions: BTreeMap<u64, f64>,
+}
+
+/// Everything recorded about a run, in a stable versioned form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestResult {
+    /// Portfolio value at each day's last mark
+    pub values: Vec<(Timestamp, f64)>,
+    /// Daily returns, one per value after the first
+    pub returns: Vec<(Timestamp, f64)>,
+    /// Positions at each day's last mark
+    pub positions: Vec<DailyPositions>,
+    /// Trades in execution order
+    pub transactions: Vec<Transaction>,
+    /// Orders with fills, in order of first fill
+    pub orders: Vec<OrderFills>,
+    /// Values recorded by the algorithm
+    #[serde(default)]
+    pub recorded_vars: BTreeMap<String, Vec<(Timestamp, f64)>>,
+    /// Each asset's contribution to daily P&L
+    #[serde(default)]
+    pub attribution: Attribution,
+    /// Summary metrics of the run
+    pub summary: PerformanceSummary,
+}
+
+impl BacktestResult {
+    /// Collect the results of a run
+    pub fn from_tracker(tracker: &PerformanceTracker) -> Self {
+        let values = tracker.equity_curve(CurveFrequency::Daily);
+        let returns = values
+            .windows(2)
+            .filter(|pair| pair[0].1 != 0.0)
+            .map(|pair| (pair[1].0, pair[1].1 / pair[0].1 - 1.0))
+            .collect();
+        let positions = values
+            .iter()
+            .map(|(timestamp, _)| DailyPositions {
+                timestamp: *timestamp,
+                positions: tracker.positions_at(*timestamp).into_iter().collect(),
+            })
+            .collect();
+        let orders = match (tracker.transactions.first(), tracker.transactions.last()) {
+            (Some(first), Some(last)) => tracker.orders_between(first.dt, last.dt),
+            _ => Vec::new(),
+        };
+
+        Self {
+            values,
+            returns,
+            positions,
+            transactions: tracker.transactions.clone(),
+            orders,
+            recorded_vars: tracker
+                .recorded_vars
+                .iter()
+                .map(|(name, series)| (name.clone(), series.clone()))
+                .collect(),
+            attribution: tracker.attribution.clone(),
+            summary: tracker.summary(),
+        }
+    }
+
+    /// Rebuild a tracker at daily resolution, e.g. to compare with other runs
+    pub fn to_tracker(&self) -> PerformanceTracker {
+        let mut tracker = PerformanceTracker::new();
+        tracker.values = self.values.clone();
+        tracker.returns = self.returns.clone();
+        tracker.transactions = self.transactions.clone();
+        tracker.attribution = self.attribution.clone();
+        tracker.recorded_vars = self
+            .recorded_vars
+            .iter()
+            .map(|(name, series)| (name.clone(), series.clone()))
+            .collect();
+        tracker
+    }
+
+    /// Write a MessagePack perf packet
+    pub fn write_packet(&self, path: &Path) -> Result<()> {
+        let bytes = rmp_serde::to_vec_named(&Envelope::wrap(self)?)
+            .map_err(|e| ZiplineError::InvalidData(format!("Cannot encode perf packet: {}", e)))?;
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Read a MessagePack perf packet
+    pub fn read_packet(path: &Path) -> Result<Self> {
+        let envelope: Envelope = rmp_serde::from_slice(&fs::read(path)?).map_err(|e| {
+            ZiplineError::InvalidData(format!("{} is not a perf packet: {}", path.display(), e))
+        })?;
+        envelope.unwrap()
+    }
+
+    /// Export as versioned JSON
+    pub fn export_json(&self, path: &Path) -> Result<()> {
+        serialization::save(self, path)
+    }
+
+    /// Read results written by [`write_packet`](Self::write_packet) or
+    /// [`export_json`](Self::export_json)
+    pub fn load(path: &Path) -> Result<Self> {
+        if is_json(path)? {
+            serialization::load(path)
+        } else {
+            Self::read_packet(path)
+        }
+    }
+}
+
+impl crate::sealed::Sealed for BacktestResult {}
+
+impl Versioned for BacktestResult {
+    const KIND: &'static str = "backtest_result";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// Whether a results file is JSON rather than a binary packet
+pub(crate) fn is_json(path: &Path) -> Result<bool> {
+    let bytes = fs::read(path)?;
+    Ok(bytes
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|b| *b == b'{'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::OrderSide;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_packet_and_json_round_trip() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 4, 21, 0, 0).unwrap();
+        let mut tracker = PerformanceTracker::new();
+        for day in 0..5 {
+            tracker.record(start + Duration::days(day), 1000.0 + 10.0 * day as f64, 0.0);
+        }
+        tracker.record_transaction(Transaction::new(7, uuid::Uuid::new_v4(), start, 5.0, 20.0, 1.0, OrderSide::Buy));
+        tracker.update_recorded_vars(&[("signal".to_string(), vec![(start, 0.5)])].into_iter().collect());
+
+        let result = BacktestResult::from_tracker(&tracker);
+        assert_eq!(result.returns.len(), 4);
+        assert!((result.returns[0].1 - 0.01).abs() < 1e-12);
+        assert_eq!(result.positions[4].positions[&7], 5.0);
+        assert_eq!(result.orders.len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let packet = dir.path().join("run.perf");
+        let json = dir.path().join("run.json");
+        result.write_packet(&packet).unwrap();
+        result.export_json(&json).unwrap();
+        assert!(fs::metadata(&packet).unwrap().len() < fs::metadata(&json).unwrap().len());
+
+        for path in [&packet, &json] {
+            let loaded = BacktestResult::load(path).unwrap();
+            assert_eq!(loaded.values, result.values);
+            assert_eq!(loaded.positions, result.positions);
+            assert_eq!(loaded.orders, result.orders);
+            assert_eq!(loaded.recorded_vars["signal"], vec![(start, 0.5)]);
+            assert_eq!(loaded.to_tracker().transactions.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Charts of a run's results
+//!
+//! Available with the `plot` feature. Each function renders one chart from a
+//! [`PerformanceTracker`] to a file whose extension picks the format: `.svg`
+//! or `.png`. Call them from `Algorithm::analyze` via [`Context::results`] or
+//! on saved results with `rusty-zipline plot`.
+//!
+//! PNG output is drawn without text: rendering text into bitmaps needs system
+//! fonts, which this build does not link. SVG charts carry a title and axis
+//! labels.
+//!
+//! ```ignore
+//! fn analyze(&mut self, context: &Context) -> Result<()> {
+//!     if let Some(results) = context.results() {
+//!         plot::plot_equity_curve(results, Path::new("equity.svg"))?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`Context::results`]: crate::algorithm::Context::results
+
+use super::query::CurveFrequency;
+use super::PerformanceTracker;
+use crate::error::{Result, ZiplineError};
+use crate::types::Timestamp;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Chart size in pixels
+const CHART_SIZE: (u32, u32) = (1000, 400);
+
+/// Trading days per year, for annualizing the rolling Sharpe ratio
+const TRADING_DAYS: f64 = 252.0;
+
+/// Default rolling Sharpe window: about six months
+pub const DEFAULT_SHARPE_WINDOW: usize = 126;
+
+/// Portfolio value at each day's last mark
+pub fn plot_equity_curve(tracker: &PerformanceTracker, path: &Path) -> Result<()> {
+    let values = tracker.equity_curve(CurveFrequency::Daily);
+    draw(path, "Portfolio Value", &values, BLUE, false)
+}
+
+/// Annualized Sharpe ratio of daily returns over a trailing window
+pub fn plot_rolling_sharpe(tracker: &PerformanceTracker, window: usize, path: &Path) -> Result<()> {
+    let title = format!("Rolling Sharpe Ratio ({} days)", window);
+    draw(path, &title, &rolling_sharpe(tracker, window), GREEN, false)
+}
+
+/// Percentage below the running peak value
+pub fn plot_drawdown(tracker: &PerformanceTracker, path: &Path) -> Result<()> {
+    draw(path, "Drawdown (%)", &underwater(tracker), RED, true)
+}
+
+/// Write all three charts into `dir` as `equity`, `rolling_sharpe` and
+/// `drawdown` files with the given extension
+pub fn plot_run(tracker: &PerformanceTracker, dir: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    let equity = dir.join(format!("equity.{}", extension));
+    let sharpe = dir.join(format!("rolling_sharpe.{}", extension));
+    let drawdown = dir.join(format!("drawdown.{}", extension));
+    plot_equity_curve(tracker, &equity)?;
+    plot_rolling_sharpe(tracker, DEFAULT_SHARPE_WINDOW, &sharpe)?;
+    plot_drawdown(tracker, &drawdown)?;
+    Ok(vec![equity, sharpe, drawdown])
+}
+
+/// Rolling annualized Sharpe ratio, one point per full window
+pub fn rolling_sharpe(tracker: &PerformanceTracker, window: usize) -> Vec<(Timestamp, f64)> {
+    let values = tracker.equity_curve(CurveFrequency::Daily);
+    let returns: Vec<(Timestamp, f64)> = values
+        .windows(2)
+        .filter(|pair| pair[0].1 != 0.0)
+        .map(|pair| (pair[1].0, pair[1].1 / pair[0].1 - 1.0))
+        .collect();
+    if window < 2 {
+        return Vec::new();
+    }
+
+    returns
+        .windows(window)
+        .filter_map(|slice| {
+            let n = slice.len() as f64;
+            let mean = slice.iter().map(|(_, r)| r).sum::<f64>() / n;
+            let variance = slice.iter().map(|(_, r)| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            let timestamp = slice[slice.len() - 1].0;
+            (variance > 0.0).then(|| (timestamp, mean / variance.sqrt() * TRADING_DAYS.sqrt()))
+        })
+        .collect()
+}
+
+/// Drawdown from the running peak, in percent
+fn underwater(tracker: &PerformanceTracker) -> Vec<(Timestamp, f64)> {
+    let mut peak = f64::MIN;
+    tracker
+        .equity_curve(CurveFrequency::Daily)
+        .into_iter()
+        .map(|(timestamp, value)| {
+            peak = peak.max(value);
+            let drawdown = if peak > 0.0 { value / peak - 1.0 } else { 0.0 };
+            (timestamp, drawdown * 100.0)
+        })
+        .collect()
+}
+
+fn draw(path: &Path, title: &str, series: &[(Timestamp, f64)], colour: RGBColor, area: bool) -> Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("svg") => draw_on(SVGBackend::new(path, CHART_SIZE).into_drawing_area(), Some(title), series, colour, area),
+        Some("png") => draw_on(BitMapBackend::new(path, CHART_SIZE).into_drawing_area(), None, series, colour, area),
+        _ => Err(ZiplineError::UnsupportedFeature(format!(
+            "chart format of {}: use .svg or .png",
+            path.display()
+        ))),
+    }
+}
+
+fn draw_on<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: Option<&str>,
+    series: &[(Timestamp, f64)],
+    colour: RGBColor,
+    area: bool,
+) -> Result<()> {
+    let failed = |e: DrawingAreaErrorKind<DB::ErrorType>| {
+        ZiplineError::InvalidOperation(format!("Cannot draw chart: {}", e))
+    };
+
+    let (first, last) = match (series.first(), series.last()) {
+        (Some(first), Some(last)) if first.0 < last.0 => (first.0, last.0),
+        _ => return Err(ZiplineError::NoDataAvailable),
+    };
+    let (mut low, mut high) = series
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), (_, v)| (low.min(*v), high.max(*v)));
+    if area {
+        high = high.max(0.0);
+    }
+    if high <= low {
+        low -= 1.0;
+        high += 1.0;
+    }
+
+    root.fill(&WHITE).map_err(failed)?;
+    let mut builder = ChartBuilder::on(&root);
+    builder.margin(10);
+    if let Some(title) = title {
+        builder
+            .caption(title, ("sans-serif", 20))
+            .x_label_area_size(30)
+            .y_label_area_size(60);
+    }
+    let mut chart = builder.build_cartesian_2d(first..last, low..high).map_err(failed)?;
+
+    // The mesh comes with axis labels, so bitmaps go without it
+    if title.is_some() {
+        chart.configure_mesh().draw().map_err(failed)?;
+    }
+
+    let points = series.iter().copied();
+    if area {
+        chart
+            .draw_series(AreaSeries::new(points, 0.0, colour.mix(0.3)).border_style(colour))
+            .map_err(failed)?;
+    } else {
+        chart.draw_series(LineSeries::new(points, &colour)).map_err(failed)?;
+    }
+
+    root.present().map_err(failed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_plot_run_writes_charts() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut tracker = PerformanceTracker::new();
+        for day in 0..200 {
+            let value = 1000.0 + day as f64 + 20.0 * (day as f64 / 7.0).sin();
+            tracker.record(start + Duration::days(day), value, 0.0);
+        }
+
+        let sharpe = rolling_sharpe(&tracker, 20);
+        assert_eq!(sharpe.len(), 199 - 19);
+
+        let dir = tempfile::tempdir().unwrap();
+        for extension in ["svg", "png"] {
+            let written = plot_run(&tracker, dir.path(), extension).unwrap();
+            assert_eq!(written.len(), 3);
+            for path in written {
+                assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            }
+        }
+        let svg = std::fs::read_to_string(dir.path().join("drawdown.svg")).unwrap();
+        assert!(svg.contains("Drawdown"));
+
+        assert!(plot_equity_curve(&tracker, &dir.path().join("equity.pdf")).is_err());
+        assert!(plot_equity_curve(&PerformanceTracker::new(), &dir.path().join("empty.svg")).is_err());
+    }
+}
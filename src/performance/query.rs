@@ -0,0 +1,249 @@
+//! Point-in-time queries over a run's results
+//!
+//! A [`PerformanceTracker`] keeps the portfolio value series and every
+//! executed trade. These queries answer the usual analysis questions from
+//! them directly: what was held at a given moment, which orders filled in a
+//! window, and the equity curve at a coarser frequency.
+//!
+//! ```ignore
+//! let held = results.positions_at(dt);
+//! let orders = results.orders_between(start, end);
+//! let weekly = results.equity_curve(CurveFrequency::Weekly);
+//! ```
+
+use super::PerformanceTracker;
+use crate::finance::Transaction;
+use crate::types::Timestamp;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Sampling frequency of an equity curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveFrequency {
+    /// Every recorded mark: intraday marks when present, else daily values
+    Bar,
+    /// Last mark of each day
+    Daily,
+    /// Last mark of each ISO week
+    Weekly,
+    /// Last mark of each month
+    Monthly,
+}
+
+/// An order's fills within a query window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderFills {
+    pub order_id: Uuid,
+    pub asset_id: u64,
+    /// Time of the first fill
+    pub first_fill: Timestamp,
+    /// Time of the last fill
+    pub last_fill: Timestamp,
+    /// Number of fills
+    pub fills: usize,
+    /// Signed shares filled
+    pub amount: f64,
+    /// Share-weighted average fill price
+    pub average_price: f64,
+    /// Total commission
+    pub commission: f64,
+}
+
+/// Net shares per asset after every trade up to and including `dt`
+pub(crate) fn positions_at(transactions: &[Transaction], dt: Timestamp) -> HashMap<u64, f64> {
+    let mut positions: HashMap<u64, f64> = HashMap::new();
+    for txn in transactions.iter().filter(|txn| txn.dt <= dt) {
+        *positions.entry(txn.asset_id).or_insert(0.0) += txn.amount;
+    }
+    positions.retain(|_, shares| *shares != 0.0);
+    positions
+}
+
+impl PerformanceTracker {
+    /// Shares held in each asset as of `dt`, including trades at `dt`
+    ///
+    /// Flat positions are left out.
+    pub fn positions_at(&self, dt: Timestamp) -> HashMap<u64, f64> {
+        positions_at(&self.transactions, dt)
+    }
+
+    /// Portfolio value as of `dt`: the latest mark at or before it
+    pub fn value_at(&self, dt: Timestamp) -> Option<f64> {
+        self.equity_curve(CurveFrequency::Bar)
+            .into_iter()
+            .take_while(|(t, _)| *t <= dt)
+            .last()
+            .map(|(_, value)| value)
+    }
+
+    /// Trades executed in `[start, end]`
+    pub fn transactions_between(&self, start: Timestamp, end: Timestamp) -> Vec<&Transaction> {
+        self.transactions
+            .iter()
+            .filter(|txn| txn.dt >= start && txn.dt <= end)
+            .collect()
+    }
+
+    /// Orders with fills in `[start, end]`, in order of first fill
+    ///
+    /// Only fills inside the window are counted, so an order filled across
+    /// the window's edge reports its partial fills.
+    pub fn orders_between(&self, start: Timestamp, end: Timestamp) -> Vec<OrderFills> {
+        let mut orders: Vec<OrderFills> = Vec::new();
+        let mut index: HashMap<Uuid, usize> = HashMap::new();
+
+        for txn in self.transactions_between(start, end) {
+            let position = *index.entry(txn.order_id).or_insert_with(|| {
+                orders.push(OrderFills {
+                    order_id: txn.order_id,
+                    asset_id: txn.asset_id,
+                    first_fill: txn.dt,
+                    last_fill: txn.dt,
+                    fills: 0,
+                    amount: 0.0,
+                    average_price: 0.0,
+                    commission: 0.0,
+                });
+                orders.len() - 1
+            });
+
+            let order = &mut orders[position];
+            let filled = order.amount.abs() + txn.amount.abs();
+            if filled > 0.0 {
+                order.average_price = (order.average_price * order.amount.abs()
+                    + txn.price * txn.amount.abs())
+                    / filled;
+            }
+            order.first_fill = order.first_fill.min(txn.dt);
+            order.last_fill = order.last_fill.max(txn.dt);
+            order.fills += 1;
+            order.amount += txn.amount;
+            order.commission += txn.commission;
+        }
+
+        orders.sort_by_key(|order| order.first_fill);
+        orders
+    }
+
+    /// Portfolio value sampled at `frequency`
+    ///
+    /// Each period is represented by its last mark. Intraday marks are used
+    /// when recorded, so a daily curve from a minute run ends each day at
+    /// the close.
+    pub fn equity_curve(&self, frequency: CurveFrequency) -> Vec<(Timestamp, f64)> {
+        let marks: Vec<(Timestamp, f64)> = if self.is_intraday() {
+            self.intraday.iter().map(|m| (m.timestamp, m.portfolio_value)).collect()
+        } else {
+            self.values.clone()
+        };
+
+        let period = |t: &Timestamp| -> (i32, u32, u32) {
+            let date: NaiveDate = t.date_naive();
+            match frequency {
+                CurveFrequency::Bar | CurveFrequency::Daily => (date.year(), date.month(), date.day()),
+                CurveFrequency::Weekly => {
+                    let week = date.iso_week();
+                    (week.year(), week.week(), 0)
+                }
+                CurveFrequency::Monthly => (date.year(), date.month(), 0),
+            }
+        };
+
+        if frequency == CurveFrequency::Bar {
+            return marks;
+        }
+
+        let mut curve: Vec<(Timestamp, f64)> = Vec::new();
+        for mark in marks {
+            match curve.last_mut() {
+                Some(last) if period(&last.0) == period(&mark.0) => *last = mark,
+                _ => curve.push(mark),
+            }
+        }
+        curve
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::OrderSide;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn results() -> (PerformanceTracker, Timestamp, Uuid) {
+        let start = Utc.with_ymd_and_hms(2024, 1, 29, 21, 0, 0).unwrap();
+        let mut tracker = PerformanceTracker::new();
+        for day in 0..10 {
+            tracker.record(start + Duration::days(day), 100.0 + day as f64, 0.0);
+        }
+
+        let order = Uuid::new_v4();
+        for (day, amount, price) in [(0, 60.0, 10.0), (1, 40.0, 12.5)] {
+            let fill = Transaction::new(1, order, start + Duration::days(day), amount, price, 1.0, OrderSide::Buy);
+            tracker.record_transaction(fill);
+        }
+        tracker.record_transaction(Transaction::new(
+            2,
+            Uuid::new_v4(),
+            start + Duration::days(3),
+            -25.0,
+            40.0,
+            0.5,
+            OrderSide::Sell,
+        ));
+        tracker.record_transaction(Transaction::new(
+            1,
+            Uuid::new_v4(),
+            start + Duration::days(5),
+            -100.0,
+            11.0,
+            1.0,
+            OrderSide::Sell,
+        ));
+        (tracker, start, order)
+    }
+
+    #[test]
+    fn test_positions_and_orders_as_of() {
+        let (tracker, start, order) = results();
+
+        assert!(tracker.positions_at(start - Duration::days(1)).is_empty());
+        assert_eq!(tracker.positions_at(start)[&1], 60.0);
+        let mid = tracker.positions_at(start + Duration::days(4));
+        assert_eq!(mid[&1], 100.0);
+        assert_eq!(mid[&2], -25.0);
+        // Asset 1 closed out on day 5
+        assert!(!tracker.positions_at(start + Duration::days(5)).contains_key(&1));
+
+        let orders = tracker.orders_between(start, start + Duration::days(3));
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].order_id, order);
+        assert_eq!(orders[0].fills, 2);
+        assert_eq!(orders[0].amount, 100.0);
+        assert_eq!(orders[0].average_price, 11.0);
+        assert_eq!(orders[0].commission, 2.0);
+        assert_eq!(orders[1].amount, -25.0);
+
+        // Only the second fill falls in the window
+        let partial = tracker.orders_between(start + Duration::hours(1), start + Duration::days(1));
+        assert_eq!(partial[0].amount, 40.0);
+    }
+
+    #[test]
+    fn test_equity_curve_frequencies() {
+        let (tracker, start, _) = results();
+
+        // Mon 29 Jan to Wed 7 Feb 2024
+        assert_eq!(tracker.equity_curve(CurveFrequency::Daily).len(), 10);
+        let weekly = tracker.equity_curve(CurveFrequency::Weekly);
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0], (start + Duration::days(6), 106.0));
+        let monthly = tracker.equity_curve(CurveFrequency::Monthly);
+        assert_eq!(monthly, vec![(start + Duration::days(2), 102.0), (start + Duration::days(9), 109.0)]);
+
+        assert_eq!(tracker.value_at(start + Duration::hours(30)), Some(101.0));
+        assert_eq!(tracker.value_at(start - Duration::days(1)), None);
+    }
+}
@@ -0,0 +1,400 @@
+//! HTML tear sheets
+//!
+//! A [`TearSheet`] renders a [`BacktestResult`] as a single self-contained
+//! HTML page: summary metrics, the equity curve, the underwater (drawdown)
+//! chart, a heatmap of monthly returns, gross and net exposure over time and
+//! a table of the worst drawdowns. Charts are inline SVG, so the page needs no
+//! scripts or network access to view.
+//!
+//! ```ignore
+//! let result = BacktestResult::load(Path::new("run.perf"))?;
+//! TearSheet::new(&result).with_title("Momentum").write(Path::new("report.html"))?;
+//! ```
+
+use super::packet::BacktestResult;
+use crate::error::Result;
+use crate::types::Timestamp;
+use chrono::Datelike;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Chart size in SVG user units
+const CHART_WIDTH: f64 = 800.0;
+const CHART_HEIGHT: f64 = 220.0;
+const CHART_MARGIN: f64 = 40.0;
+
+/// Drawdowns listed in the table
+const TOP_DRAWDOWNS: usize = 5;
+
+/// One peak-to-recovery drawdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawdownPeriod {
+    /// Last high before the decline
+    pub peak: Timestamp,
+    /// Lowest value of the period
+    pub trough: Timestamp,
+    /// First value back at the peak, if the run recovered
+    pub recovery: Option<Timestamp>,
+    /// Decline from peak to trough as a positive fraction
+    pub depth: f64,
+}
+
+/// Drawdown periods of a value series, worst first
+pub fn drawdown_periods(values: &[(Timestamp, f64)]) -> Vec<DrawdownPeriod> {
+    let mut periods = Vec::new();
+    let mut current: Option<DrawdownPeriod> = None;
+    let mut peak = match values.first() {
+        Some(first) => *first,
+        None => return periods,
+    };
+
+    for &(timestamp, value) in &values[1..] {
+        if value >= peak.1 {
+            if let Some(mut period) = current.take() {
+                period.recovery = Some(timestamp);
+                periods.push(period);
+            }
+            peak = (timestamp, value);
+            continue;
+        }
+        if peak.1 <= 0.0 {
+            continue;
+        }
+        let depth = 1.0 - value / peak.1;
+        let period = current.get_or_insert(DrawdownPeriod {
+            peak: peak.0,
+            trough: timestamp,
+            recovery: None,
+            depth,
+        });
+        if depth > period.depth {
+            period.depth = depth;
+            period.trough = timestamp;
+        }
+    }
+    periods.extend(current);
+
+    periods.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+    periods
+}
+
+/// Compounded return per calendar month, keyed by `(year, month)`
+pub fn monthly_returns(values: &[(Timestamp, f64)]) -> BTreeMap<(i32, u32), f64> {
+    let mut month_ends: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+    for (timestamp, value) in values {
+        month_ends.insert((timestamp.year(), timestamp.month()), *value);
+    }
+
+    let mut returns = BTreeMap::new();
+    let mut previous = values.first().map(|(_, value)| *value);
+    for (month, value) in month_ends {
+        if let Some(base) = previous.filter(|base| *base != 0.0) {
+            returns.insert(month, value / base - 1.0);
+        }
+        previous = Some(value);
+    }
+    returns
+}
+
+/// Gross and net exposure as fractions of portfolio value, per day
+///
+/// Results don't carry closing prices, so positions are marked at each
+/// asset's latest fill price on or before the day.
+pub fn exposure(result: &BacktestResult) -> Vec<(Timestamp, f64, f64)> {
+    let mut last_price: HashMap<u64, f64> = HashMap::new();
+    let mut fills = result.transactions.iter().peekable();
+
+    result
+        .positions
+        .iter()
+        .zip(&result.values)
+        .map(|(day, (timestamp, value))| {
+            while let Some(txn) = fills.next_if(|txn| txn.dt <= day.timestamp) {
+                last_price.insert(txn.asset_id, txn.price);
+            }
+            let (gross, net) = day.positions.iter().fold((0.0, 0.0), |(gross, net), (asset, shares)| {
+                let notional = shares * last_price.get(asset).copied().unwrap_or(0.0);
+                (gross + notional.abs(), net + notional)
+            });
+            if *value == 0.0 {
+                (*timestamp, 0.0, 0.0)
+            } else {
+                (*timestamp, gross / value, net / value)
+            }
+        })
+        .collect()
+}
+
+/// An HTML report of one backtest
+pub struct TearSheet<'a> {
+    result: &'a BacktestResult,
+    title: String,
+}
+
+impl<'a> TearSheet<'a> {
+    pub fn new(result: &'a BacktestResult) -> Self {
+        Self {
+            result,
+            title: "Backtest Tear Sheet".to_string(),
+        }
+    }
+
+    /// Set the page title
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Render the report as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let summary = &self.result.summary;
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>\n\
+             body {{ font-family: sans-serif; margin: 2em; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 2em; }}\n\
+             th, td {{ border: 1px solid #ccc; padding: 4px 10px; text-align: right; }}\n\
+             th:first-child, td:first-child {{ text-align: left; }}\n\
+             svg {{ display: block; margin-bottom: 2em; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+            title = escape(&self.title)
+        );
+
+        html.push_str("<h2>Summary</h2>\n<table>\n");
+        let rows = [
+            ("Total Return", format!("{:.2}%", summary.total_return * 100.0)),
+            ("Annualized Return", format!("{:.2}%", summary.annualized_return * 100.0)),
+            ("Sharpe Ratio", format!("{:.2}", summary.sharpe_ratio)),
+            ("Sortino Ratio", format!("{:.2}", summary.sortino_ratio)),
+            ("Volatility", format!("{:.2}%", summary.volatility * 100.0)),
+            ("Max Drawdown", format!("{:.2}%", summary.max_drawdown * 100.0)),
+            ("Trades", self.result.transactions.len().to_string()),
+            ("Days", self.result.values.len().to_string()),
+        ];
+        for (label, value) in rows {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", label, value);
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Equity Curve</h2>\n");
+        html.push_str(&line_chart(&[("#1f77b4", self.result.values.clone())], false));
+
+        html.push_str("<h2>Drawdown</h2>\n");
+        let mut peak = f64::MIN;
+        let underwater: Vec<(Timestamp, f64)> = self
+            .result
+            .values
+            .iter()
+            .map(|(timestamp, value)| {
+                peak = peak.max(*value);
+                let drawdown = if peak > 0.0 { value / peak - 1.0 } else { 0.0 };
+                (*timestamp, drawdown * 100.0)
+            })
+            .collect();
+        html.push_str(&line_chart(&[("#d62728", underwater)], true));
+
+        html.push_str("<h2>Monthly Returns (%)</h2>\n");
+        html.push_str(&monthly_heatmap(&monthly_returns(&self.result.values)));
+
+        html.push_str("<h2>Exposure (% of portfolio)</h2>\n");
+        let exposure = exposure(self.result);
+        let gross = exposure.iter().map(|(t, gross, _)| (*t, gross * 100.0)).collect();
+        let net = exposure.iter().map(|(t, _, net)| (*t, net * 100.0)).collect();
+        html.push_str(&line_chart(&[("#1f77b4", gross), ("#ff7f0e", net)], false));
+        html.push_str("<p>Gross exposure in blue, net in orange.</p>\n");
+
+        html.push_str(
+            "<h2>Top Drawdowns</h2>\n<table>\n<tr><th>Rank</th><th>Depth</th><th>Peak</th>\
+             <th>Trough</th><th>Recovery</th></tr>\n",
+        );
+        let date = |t: &Timestamp| t.format("%Y-%m-%d").to_string();
+        for (rank, period) in drawdown_periods(&self.result.values).iter().take(TOP_DRAWDOWNS).enumerate() {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{:.2}%</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                rank + 1,
+                period.depth * 100.0,
+                date(&period.peak),
+                date(&period.trough),
+                period.recovery.as_ref().map_or_else(|| "-".to_string(), date)
+            );
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Write the report to an HTML file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_html())?;
+        Ok(())
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// SVG line chart of one or more `(colour, series)` sharing the time axis
+fn line_chart(series: &[(&str, Vec<(Timestamp, f64)>)], fill_to_zero: bool) -> String {
+    let points = series.iter().flat_map(|(_, s)| s.iter());
+    let (mut t_min, mut t_max) = (i64::MAX, i64::MIN);
+    let (mut y_min, mut y_max) = (f64::INFINITY, f64::NEG_INFINITY);
+    for (timestamp, value) in points {
+        t_min = t_min.min(timestamp.timestamp());
+        t_max = t_max.max(timestamp.timestamp());
+        y_min = y_min.min(*value);
+        y_max = y_max.max(*value);
+    }
+    if fill_to_zero {
+        y_max = y_max.max(0.0);
+    }
+
+    let mut svg = format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" xmlns=\"http://www.w3.org/2000/svg\">\n",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT
+    );
+    if t_min > t_max {
+        svg.push_str("<text x=\"10\" y=\"20\">No data</text>\n</svg>\n");
+        return svg;
+    }
+
+    let plot_width = CHART_WIDTH - 2.0 * CHART_MARGIN;
+    let plot_height = CHART_HEIGHT - 2.0 * CHART_MARGIN;
+    let t_span = (t_max - t_min).max(1) as f64;
+    let y_span = if y_max > y_min { y_max - y_min } else { 1.0 };
+    let x = |t: &Timestamp| CHART_MARGIN + (t.timestamp() - t_min) as f64 / t_span * plot_width;
+    let y = |v: f64| CHART_MARGIN + (y_max - v) / y_span * plot_height;
+
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{m}\" y=\"{m}\" width=\"{pw}\" height=\"{ph}\" fill=\"none\" stroke=\"#ccc\"/>",
+        m = CHART_MARGIN,
+        pw = plot_width,
+        ph = plot_height
+    );
+    let _ = writeln!(svg, "<text x=\"2\" y=\"{:.1}\" font-size=\"10\">{:.2}</text>", CHART_MARGIN, y_max);
+    let _ = writeln!(
+        svg,
+        "<text x=\"2\" y=\"{:.1}\" font-size=\"10\">{:.2}</text>",
+        CHART_HEIGHT - CHART_MARGIN,
+        y_min
+    );
+
+    for (colour, values) in series {
+        let path: Vec<String> = values
+            .iter()
+            .map(|(t, v)| format!("{:.1},{:.1}", x(t), y(*v)))
+            .collect();
+        if path.is_empty() {
+            continue;
+        }
+        if fill_to_zero {
+            let (first, last) = (&values[0].0, &values[values.len() - 1].0);
+            let _ = writeln!(
+                svg,
+                "<polygon points=\"{:.1},{:.1} {} {:.1},{:.1}\" fill=\"{}\" fill-opacity=\"0.3\"/>",
+                x(first),
+                y(0.0),
+                path.join(" "),
+                x(last),
+                y(0.0),
+                colour
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\"/>",
+            path.join(" "),
+            colour
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Year-by-month table of returns, green for gains and red for losses
+fn monthly_heatmap(returns: &BTreeMap<(i32, u32), f64>) -> String {
+    let scale = returns.values().fold(0.0_f64, |max, r| max.max(r.abs())).max(1e-9);
+    let mut html = String::from("<table>\n<tr><th>Year</th>");
+    for month in ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"] {
+        let _ = write!(html, "<th>{}</th>", month);
+    }
+    html.push_str("</tr>\n");
+
+    let years: std::collections::BTreeSet<i32> = returns.keys().map(|(year, _)| *year).collect();
+    for year in years {
+        let _ = write!(html, "<tr><td>{}</td>", year);
+        for month in 1..=12 {
+            match returns.get(&(year, month)) {
+                Some(r) => {
+                    let (red, green) = if *r >= 0.0 { (0, 160) } else { (200, 0) };
+                    let _ = write!(
+                        html,
+                        "<td style=\"background: rgba({}, {}, 0, {:.2})\">{:.1}</td>",
+                        red,
+                        green,
+                        (r.abs() / scale).min(1.0) * 0.8,
+                        r * 100.0
+                    );
+                }
+                None => html.push_str("<td></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finance::Transaction;
+    use crate::order::OrderSide;
+    use crate::performance::PerformanceTracker;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_tear_sheet_sections() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 30, 21, 0, 0).unwrap();
+        let closes = [100.0, 110.0, 99.0, 104.5, 115.0, 112.7, 115.0, 120.0];
+        let mut tracker = PerformanceTracker::new();
+        for (day, value) in closes.iter().enumerate() {
+            tracker.record(start + Duration::days(day as i64), *value, 0.0);
+        }
+        tracker.record_transaction(Transaction::new(3, uuid::Uuid::new_v4(), start, 5.0, 10.0, 0.0, OrderSide::Buy));
+        let result = BacktestResult::from_tracker(&tracker);
+
+        let drawdowns = drawdown_periods(&result.values);
+        assert_eq!(drawdowns.len(), 2);
+        assert!((drawdowns[0].depth - 0.1).abs() < 1e-12);
+        assert_eq!(drawdowns[0].peak, start + Duration::days(1));
+        assert_eq!(drawdowns[0].trough, start + Duration::days(2));
+        assert_eq!(drawdowns[0].recovery, Some(start + Duration::days(4)));
+        assert!((drawdowns[1].depth - 0.02).abs() < 1e-12);
+
+        // January closes at 110 from 100, February runs on to 120
+        let monthly = monthly_returns(&result.values);
+        assert!((monthly[&(2024, 1)] - 0.1).abs() < 1e-12);
+        assert!((monthly[&(2024, 2)] - (120.0 / 110.0 - 1.0)).abs() < 1e-12);
+
+        let exposure = exposure(&result);
+        assert!((exposure[0].1 - 0.5).abs() < 1e-12);
+        assert_eq!(exposure[0].1, exposure[0].2);
+
+        let html = TearSheet::new(&result).with_title("A <test>").to_html();
+        for section in ["Equity Curve", "Drawdown", "Monthly Returns", "Exposure", "Top Drawdowns"] {
+            assert!(html.contains(section), "missing {}", section);
+        }
+        assert!(html.contains("A &lt;test&gt;"));
+        assert_eq!(html.matches("<svg").count(), 3);
+    }
+}
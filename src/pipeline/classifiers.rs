@@ -3,8 +3,13 @@
 //! Classifiers are Pipeline computations that produce categorical outputs,
 //! used to group or label assets.
 
+use super::engine::PipelineContext;
 use crate::error::{Result, ZiplineError};
+use chrono::{DateTime, NaiveDate, Utc};
+use hashbrown::HashMap as HashbrownMap;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 
 /// Classifier trait - produces categorical labels for assets
 pub trait Classifier: Send + Sync {
@@ -23,6 +28,30 @@ pub trait Classifier: Send + Sync {
     }
 }
 
+/// Bin `(asset_id, value)` pairs into `bins` equal-sized groups labeled 0..bins
+///
+/// NaN values are left unlabeled. Values are ordered by value, then asset id,
+/// so labels do not depend on input order, and tied values share the label
+/// of the first of them.
+pub fn quantile_labels(
+    values: impl IntoIterator<Item = (u64, f64)>,
+    bins: usize,
+) -> HashMap<u64, i64> {
+    let mut values: Vec<(u64, f64)> = values.into_iter().filter(|(_, v)| !v.is_nan()).collect();
+    values.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+
+    let n = values.len();
+    let mut result = HashMap::with_capacity(n);
+    let mut label = 0;
+    for (idx, &(asset_id, value)) in values.iter().enumerate() {
+        if idx == 0 || value != values[idx - 1].1 {
+            label = (idx * bins / n).min(bins - 1) as i64;
+        }
+        result.insert(asset_id, label);
+    }
+    result
+}
+
 /// Quantiles - Classify assets into quantile buckets
 ///
 /// Divides assets into N equal-sized groups based on their values.
@@ -59,10 +88,8 @@ impl Quantiles {
 
 impl Classifier for Quantiles {
     fn compute(&self, data: &HashMap<u64, Vec<f64>>) -> Result<HashMap<u64, i64>> {
-        let mut result = HashMap::new();
-
         // Get latest values for assets (filtered by mask if provided)
-        let mut values: Vec<(u64, f64)> = data
+        let values: Vec<(u64, f64)> = data
             .iter()
             .filter(|(asset_id, _)| {
                 if let Some(ref mask) = self.mask {
@@ -75,22 +102,7 @@ impl Classifier for Quantiles {
             .filter(|(_, v)| !v.is_nan())
             .collect();
 
-        if values.is_empty() {
-            return Ok(result);
-        }
-
-        // Sort by value
-        values.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Assign quantile labels (0-indexed)
-        let assets_per_bin = (values.len() + self.bins - 1) / self.bins; // Round up
-
-        for (idx, (asset_id, _)) in values.iter().enumerate() {
-            let quantile = (idx / assets_per_bin).min(self.bins - 1) as i64;
-            result.insert(*asset_id, quantile);
-        }
-
-        Ok(result)
+        Ok(quantile_labels(values, self.bins))
     }
 
     fn name(&self) -> &str {
@@ -302,6 +314,389 @@ impl Classifier for SimpleClassifier {
     }
 }
 
+/// Point-in-time sid -> classification code mapping
+///
+/// Each sid holds a history of codes with the date they took effect, so a
+/// reclassified company is grouped by the code that applied on each session.
+/// Codes are stored as given; [`Sector`] and [`Industry`] read GICS levels
+/// from them.
+#[derive(Debug, Clone, Default)]
+pub struct ClassificationMap {
+    /// sid -> [(effective date, code)], oldest first
+    codes: HashMap<u64, Vec<(NaiveDate, String)>>,
+}
+
+impl ClassificationMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `sid` is classified as `code` from `effective` onwards
+    pub fn insert(&mut self, sid: u64, effective: NaiveDate, code: impl Into<String>) {
+        let history = self.codes.entry(sid).or_default();
+        let idx = history.partition_point(|(date, _)| *date <= effective);
+        history.insert(idx, (effective, code.into()));
+    }
+
+    /// Code in effect for `sid` on `date`
+    pub fn code_at(&self, sid: u64, date: NaiveDate) -> Option<&str> {
+        let history = self.codes.get(&sid)?;
+        let idx = history.partition_point(|(effective, _)| *effective <= date);
+        idx.checked_sub(1).map(|i| history[i].1.as_str())
+    }
+
+    /// Number of sids with a classification
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Whether no sids are classified
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Load a mapping file, choosing the format by extension (`.csv` or `.parquet`)
+    pub fn load(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("parquet") => Self::from_parquet(path),
+            Some("csv") => Self::from_csv(path),
+            _ => Err(ZiplineError::InvalidData(format!(
+                "Unsupported classification file {}: expected .csv or .parquet",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Load `sid,code,effective_date` rows from a CSV file with a header
+    pub fn from_csv(path: &Path) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read CSV: {}", e)))?;
+        let columns = Self::columns(
+            reader
+                .headers()
+                .map_err(|e| ZiplineError::DataError(format!("Failed to read CSV header: {}", e)))?
+                .iter(),
+        )?;
+
+        let mut map = Self::new();
+        for result in reader.records() {
+            let record = result
+                .map_err(|e| ZiplineError::DataError(format!("Failed to parse CSV row: {}", e)))?;
+            let field = |idx: usize| record.get(idx).unwrap_or("");
+            map.insert_row(field(columns[0]), field(columns[1]), field(columns[2]))?;
+        }
+        Ok(map)
+    }
+
+    /// Load `sid`, `code` and `effective_date` columns from a Parquet file
+    pub fn from_parquet(path: &Path) -> Result<Self> {
+        use polars::prelude::*;
+
+        let file = std::fs::File::open(path)?;
+        let frame = ParquetReader::new(file)
+            .finish()
+            .map_err(|e| ZiplineError::DataError(format!("Failed to read Parquet: {}", e)))?;
+        let names: Vec<&str> = frame.get_column_names();
+        let columns = Self::columns(names.iter().copied())?;
+
+        let text = |idx: usize| -> Result<Vec<Option<String>>> {
+            let column = frame.get_columns()[idx]
+                .cast(&DataType::String)
+                .map_err(|e| ZiplineError::DataError(format!("Bad column {}: {}", names[idx], e)))?;
+            let values = column
+                .str()
+                .map_err(|e| ZiplineError::DataError(format!("Bad column {}: {}", names[idx], e)))?;
+            Ok(values.into_iter().map(|v| v.map(str::to_string)).collect())
+        };
+
+        let (sids, codes, dates) = (text(columns[0])?, text(columns[1])?, text(columns[2])?);
+        let mut map = Self::new();
+        for ((sid, code), date) in sids.iter().zip(&codes).zip(&dates) {
+            map.insert_row(
+                sid.as_deref().unwrap_or(""),
+                code.as_deref().unwrap_or(""),
+                date.as_deref().unwrap_or(""),
+            )?;
+        }
+        Ok(map)
+    }
+
+    /// Positions of the sid, code and effective date columns
+    fn columns<'a>(names: impl Iterator<Item = &'a str>) -> Result<[usize; 3]> {
+        let names: Vec<String> = names.map(|n| n.trim().to_lowercase()).collect();
+        let find = |candidates: &[&str]| {
+            names
+                .iter()
+                .position(|n| candidates.contains(&n.as_str()))
+                .ok_or_else(|| {
+                    ZiplineError::InvalidData(format!(
+                        "Classification file is missing a {} column",
+                        candidates[0]
+                    ))
+                })
+        };
+        Ok([
+            find(&["sid", "asset_id"])?,
+            find(&["code", "gics", "gics_code"])?,
+            find(&["effective_date", "date", "start_date"])?,
+        ])
+    }
+
+    fn insert_row(&mut self, sid: &str, code: &str, date: &str) -> Result<()> {
+        let sid = sid
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| ZiplineError::InvalidData(format!("Invalid sid '{}': {}", sid, e)))?;
+        let date = date.trim();
+        let effective = NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d")
+            .map_err(|e| ZiplineError::InvalidData(format!("Invalid effective date '{}': {}", date, e)))?;
+        let code = code.trim();
+        // Integer codes read back from Parquet as floats, e.g. "45102010.0"
+        let code = code.strip_suffix(".0").unwrap_or(code);
+        self.insert(sid, effective, code);
+        Ok(())
+    }
+}
+
+/// GICS sector codes and names
+pub const GICS_SECTORS: [(&str, &str); 11] = [
+    ("10", "Energy"),
+    ("15", "Materials"),
+    ("20", "Industrials"),
+    ("25", "Consumer Discretionary"),
+    ("30", "Consumer Staples"),
+    ("35", "Health Care"),
+    ("40", "Financials"),
+    ("45", "Information Technology"),
+    ("50", "Communication Services"),
+    ("55", "Utilities"),
+    ("60", "Real Estate"),
+];
+
+/// Name of a GICS sector from its two-digit code
+pub fn gics_sector_name(code: &str) -> Option<&'static str> {
+    GICS_SECTORS
+        .iter()
+        .find(|(sector, _)| *sector == code)
+        .map(|(_, name)| *name)
+}
+
+/// Labels each asset in the pipeline universe with a prefix of its code
+fn classify_prefix(
+    map: &ClassificationMap,
+    digits: usize,
+    timestamp: DateTime<Utc>,
+    context: &PipelineContext,
+) -> HashbrownMap<u64, String> {
+    let session = timestamp.date_naive();
+    context
+        .assets()
+        .iter()
+        .filter_map(|asset| {
+            let code = map.code_at(asset.id, session)?;
+            code.get(..digits).map(|prefix| (asset.id, prefix.to_string()))
+        })
+        .collect()
+}
+
+/// Sector - GICS sector (first two digits of the code) of each asset
+///
+/// Assets without a classification on the session are left out.
+#[derive(Clone)]
+pub struct Sector {
+    map: Arc<ClassificationMap>,
+}
+
+impl Sector {
+    pub fn new(map: Arc<ClassificationMap>) -> Self {
+        Self { map }
+    }
+}
+
+impl super::engine::Classifier for Sector {
+    fn classify(
+        &self,
+        timestamp: DateTime<Utc>,
+        context: &PipelineContext,
+    ) -> Result<HashbrownMap<u64, String>> {
+        Ok(classify_prefix(&self.map, 2, timestamp, context))
+    }
+
+    fn name(&self) -> &str {
+        "Sector"
+    }
+
+    fn clone_box(&self) -> Box<dyn super::engine::Classifier> {
+        Box::new(self.clone())
+    }
+}
+
+/// Industry - GICS industry (first six digits of the code) of each asset
+///
+/// Assets without a classification on the session are left out.
+#[derive(Clone)]
+pub struct Industry {
+    map: Arc<ClassificationMap>,
+}
+
+impl Industry {
+    pub fn new(map: Arc<ClassificationMap>) -> Self {
+        Self { map }
+    }
+}
+
+impl super::engine::Classifier for Industry {
+    fn classify(
+        &self,
+        timestamp: DateTime<Utc>,
+        context: &PipelineContext,
+    ) -> Result<HashbrownMap<u64, String>> {
+        Ok(classify_prefix(&self.map, 6, timestamp, context))
+    }
+
+    fn name(&self) -> &str {
+        "Industry"
+    }
+
+    fn clone_box(&self) -> Box<dyn super::engine::Classifier> {
+        Box::new(self.clone())
+    }
+}
+
+/// FactorQuantiles - Bins a pipeline factor's values into quantiles each run
+///
+/// Labels are `0..bins`, lowest values first, as computed by
+/// [`quantile_labels`]. Assets with a NaN or missing factor value are left
+/// unlabeled. Use [`FactorQuantiles::eq`] to select one bucket as a filter:
+///
+/// ```ignore
+/// pipeline.add_filter("top_decile".to_string(), Box::new(deciles("momentum").eq(9)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FactorQuantiles {
+    name: String,
+    factor: String,
+    bins: usize,
+}
+
+impl FactorQuantiles {
+    pub fn new(factor: &str, bins: usize) -> Result<Self> {
+        if bins < 2 {
+            return Err(ZiplineError::PipelineError(
+                "bins must be at least 2".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            name: format!("quantiles({}, {})", factor, bins),
+            factor: factor.to_string(),
+            bins,
+        })
+    }
+
+    /// Number of buckets
+    pub fn bins(&self) -> usize {
+        self.bins
+    }
+
+    /// Integer label of each asset with a factor value
+    pub fn labels(&self, context: &PipelineContext) -> Result<HashbrownMap<u64, i64>> {
+        let values = context.get_cached(&self.factor).ok_or_else(|| {
+            ZiplineError::PipelineError(format!("Factor {} not found", self.factor))
+        })?;
+        Ok(quantile_labels(values.iter().map(|(&id, &v)| (id, v)), self.bins)
+            .into_iter()
+            .collect())
+    }
+
+    /// Filter passing assets in bucket `label`
+    pub fn eq(self, label: i64) -> QuantileFilter {
+        QuantileFilter {
+            name: format!("{} == {}", self.name, label),
+            quantiles: self,
+            label,
+        }
+    }
+}
+
+impl super::engine::Classifier for FactorQuantiles {
+    fn classify(
+        &self,
+        _timestamp: DateTime<Utc>,
+        context: &PipelineContext,
+    ) -> Result<HashbrownMap<u64, String>> {
+        Ok(self
+            .labels(context)?
+            .into_iter()
+            .map(|(asset_id, label)| (asset_id, label.to_string()))
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn clone_box(&self) -> Box<dyn super::engine::Classifier> {
+        Box::new(self.clone())
+    }
+}
+
+/// Quantiles of `factor` with `bins` buckets
+pub fn quantiles(factor: &str, bins: usize) -> Result<FactorQuantiles> {
+    FactorQuantiles::new(factor, bins)
+}
+
+/// Quartiles (labels 0-3) of `factor`
+pub fn quartiles(factor: &str) -> FactorQuantiles {
+    FactorQuantiles::new(factor, 4).expect("4 bins is valid")
+}
+
+/// Quintiles (labels 0-4) of `factor`
+pub fn quintiles(factor: &str) -> FactorQuantiles {
+    FactorQuantiles::new(factor, 5).expect("5 bins is valid")
+}
+
+/// Deciles (labels 0-9) of `factor`
+pub fn deciles(factor: &str) -> FactorQuantiles {
+    FactorQuantiles::new(factor, 10).expect("10 bins is valid")
+}
+
+/// Passes assets whose factor quantile equals a label
+///
+/// Unlabeled assets, e.g. with a NaN factor value, fail the filter.
+#[derive(Debug, Clone)]
+pub struct QuantileFilter {
+    name: String,
+    quantiles: FactorQuantiles,
+    label: i64,
+}
+
+impl super::engine::Filter for QuantileFilter {
+    fn evaluate(
+        &self,
+        _timestamp: DateTime<Utc>,
+        context: &PipelineContext,
+    ) -> Result<HashbrownMap<u64, bool>> {
+        let labels = self.quantiles.labels(context)?;
+        let values = context.get_cached(&self.quantiles.factor).ok_or_else(|| {
+            ZiplineError::PipelineError(format!("Factor {} not found", self.quantiles.factor))
+        })?;
+
+        Ok(values
+            .keys()
+            .map(|&asset_id| (asset_id, labels.get(&asset_id) == Some(&self.label)))
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn clone_box(&self) -> Box<dyn super::engine::Filter> {
+        Box::new(self.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -476,4 +871,159 @@ mod tests {
         assert!(result.contains_key(&3));
         assert!(!result.contains_key(&2));
     }
+
+    #[test]
+    fn test_classification_map_is_point_in_time() {
+        let day = |d| NaiveDate::from_ymd_opt(2020, 1, d).unwrap();
+        let mut map = ClassificationMap::new();
+        map.insert(1, day(10), "45102010");
+        map.insert(1, day(2), "50101010");
+
+        assert_eq!(map.code_at(1, day(1)), None);
+        assert_eq!(map.code_at(1, day(5)), Some("50101010"));
+        assert_eq!(map.code_at(1, day(10)), Some("45102010"));
+        assert_eq!(map.code_at(2, day(10)), None);
+        assert_eq!(gics_sector_name("45"), Some("Information Technology"));
+        assert_eq!(gics_sector_name("99"), None);
+    }
+
+    #[test]
+    fn test_load_classification_files() {
+        use polars::prelude::*;
+
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("gics.csv");
+        std::fs::write(
+            &csv_path,
+            "sid,code,effective_date\n1,45102010,2020-01-01\n1,50101010,2021-06-01\n2,10102010,2020-01-01\n",
+        )
+        .unwrap();
+        let from_csv = ClassificationMap::load(&csv_path).unwrap();
+
+        let parquet_path = dir.path().join("gics.parquet");
+        let mut frame = df!(
+            "sid" => [1u64, 1, 2],
+            "gics_code" => [45102010i64, 50101010, 10102010],
+            "effective_date" => ["2020-01-01", "2021-06-01", "2020-01-01"]
+        )
+        .unwrap();
+        ParquetWriter::new(std::fs::File::create(&parquet_path).unwrap())
+            .finish(&mut frame)
+            .unwrap();
+        let from_parquet = ClassificationMap::load(&parquet_path).unwrap();
+
+        let before = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let after = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap();
+        for map in [&from_csv, &from_parquet] {
+            assert_eq!(map.len(), 2);
+            assert_eq!(map.code_at(1, before), Some("45102010"));
+            assert_eq!(map.code_at(1, after), Some("50101010"));
+            assert_eq!(map.code_at(2, after), Some("10102010"));
+        }
+
+        assert!(ClassificationMap::load(&dir.path().join("gics.txt")).is_err());
+    }
+
+    #[test]
+    fn test_sector_and_industry() {
+        use super::super::engine::{Classifier as _, DataProvider, OHLCVBar};
+        use crate::asset::Asset;
+        use chrono::TimeZone;
+
+        struct NoData;
+        impl DataProvider for NoData {
+            fn get_prices(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_volumes(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_ohlcv(&self, _: u64, _: usize) -> Result<Vec<OHLCVBar>> {
+                Ok(Vec::new())
+            }
+            fn get_latest_price(&self, _: u64) -> Result<f64> {
+                Ok(0.0)
+            }
+        }
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let assets: Vec<Asset> = (1..=3)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+            .collect();
+        let mut map = ClassificationMap::new();
+        map.insert(1, listed, "45102010");
+        map.insert(2, listed, "45301020");
+        let map = Arc::new(map);
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let context = PipelineContext::new(assets, Arc::new(NoData), timestamp);
+
+        let sectors = Sector::new(map.clone()).classify(timestamp, &context).unwrap();
+        assert_eq!(sectors.len(), 2);
+        assert_eq!(sectors[&1], "45");
+        assert_eq!(sectors[&2], "45");
+
+        let industries = Industry::new(map).classify(timestamp, &context).unwrap();
+        assert_eq!(industries[&1], "451020");
+        assert_eq!(industries[&2], "453010");
+    }
+
+    #[test]
+    fn test_quantile_labels_ties_and_nan() {
+        // Input order does not matter; equal values share a bucket
+        let values = [(5, 3.0), (1, 1.0), (4, 2.0), (2, 2.0), (3, f64::NAN), (6, 4.0)];
+        let labels = quantile_labels(values, 2);
+        assert_eq!(labels.len(), 5);
+        assert_eq!(labels[&1], 0);
+        assert_eq!(labels[&2], 0);
+        assert_eq!(labels[&4], 0);
+        assert_eq!(labels[&5], 1);
+        assert_eq!(labels[&6], 1);
+
+        let mut reversed = values;
+        reversed.reverse();
+        assert_eq!(quantile_labels(reversed, 2), labels);
+    }
+
+    #[test]
+    fn test_factor_deciles_filter() {
+        use super::super::engine::{Classifier as _, DataProvider, Filter as _, OHLCVBar};
+        use chrono::TimeZone;
+
+        struct NoData;
+        impl DataProvider for NoData {
+            fn get_prices(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_volumes(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_ohlcv(&self, _: u64, _: usize) -> Result<Vec<OHLCVBar>> {
+                Ok(Vec::new())
+            }
+            fn get_latest_price(&self, _: u64) -> Result<f64> {
+                Ok(0.0)
+            }
+        }
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut context = PipelineContext::new(Vec::new(), Arc::new(NoData), timestamp);
+        let mut values: HashbrownMap<u64, f64> = (1..=20).map(|id| (id, id as f64)).collect();
+        values.insert(21, f64::NAN);
+        context.cache_result("momentum".to_string(), values);
+
+        assert!(quantiles("momentum", 1).is_err());
+        let labels = deciles("momentum").classify(timestamp, &context).unwrap();
+        assert_eq!(labels.len(), 20);
+        assert_eq!(labels[&1], "0");
+        assert_eq!(labels[&20], "9");
+
+        let top = deciles("momentum").eq(9);
+        assert_eq!(top.name(), "quantiles(momentum, 10) == 9");
+        let passed = top.evaluate(timestamp, &context).unwrap();
+        let mut selected: Vec<u64> = passed.iter().filter(|(_, &p)| p).map(|(&id, _)| id).collect();
+        selected.sort_unstable();
+        assert_eq!(selected, vec![19, 20]);
+        assert_eq!(passed[&21], false);
+    }
 }
@@ -1,6 +1,6 @@
 //! Composite factors - combining and transforming factors
 
-use super::engine::{Factor, FactorOutput, PipelineContext};
+use super::engine::{Classifier, Factor, FactorOutput, PipelineContext};
 use crate::error::Result;
 use chrono::{DateTime, Utc};
 use hashbrown::HashMap;
@@ -215,12 +215,36 @@ impl Factor for DivideFactors {
     }
 }
 
-/// Rank factor values across assets
-#[derive(Clone)]
+/// Split a factor's values into the groups labeled by `groupby`
+///
+/// Without a classifier every asset is in one group. Assets the classifier
+/// leaves unlabeled are dropped.
+fn grouped_values(
+    groupby: Option<&dyn Classifier>,
+    values: &FactorOutput,
+    timestamp: DateTime<Utc>,
+    context: &PipelineContext,
+) -> Result<Vec<Vec<(u64, f64)>>> {
+    let Some(classifier) = groupby else {
+        return Ok(vec![values.iter().map(|(&id, &val)| (id, val)).collect()]);
+    };
+
+    let labels = classifier.classify(timestamp, context)?;
+    let mut groups: HashMap<&str, Vec<(u64, f64)>> = HashMap::new();
+    for (&asset_id, &val) in values {
+        if let Some(label) = labels.get(&asset_id) {
+            groups.entry(label.as_str()).or_default().push((asset_id, val));
+        }
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// Rank factor values across assets, optionally within classifier groups
 pub struct RankFactor {
     name: String,
     factor: String,
     ascending: bool,
+    groupby: Option<Box<dyn Classifier>>,
 }
 
 impl RankFactor {
@@ -230,29 +254,48 @@ impl RankFactor {
             name,
             factor,
             ascending,
+            groupby: None,
+        }
+    }
+
+    /// Rank within the groups labeled by `classifier`
+    pub fn with_groupby(mut self, classifier: Box<dyn Classifier>) -> Self {
+        self.name = format!("rank({}, {})", self.factor, classifier.name());
+        self.groupby = Some(classifier);
+        self
+    }
+}
+
+impl Clone for RankFactor {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            factor: self.factor.clone(),
+            ascending: self.ascending,
+            groupby: self.groupby.as_ref().map(|c| c.clone_box()),
         }
     }
 }
 
 impl Factor for RankFactor {
-    fn compute(&self, _timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
         let values = context.get_cached(&self.factor).ok_or_else(|| {
             crate::error::ZiplineError::PipelineError(format!("Factor {} not found", self.factor))
         })?;
 
-        // Sort assets by value
-        let mut sorted: Vec<(u64, f64)> = values.iter().map(|(&id, &val)| (id, val)).collect();
-
-        if self.ascending {
-            sorted.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        } else {
-            sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        }
-
-        // Assign ranks
         let mut output = HashMap::new();
-        for (rank, (asset_id, _)) in sorted.iter().enumerate() {
-            output.insert(*asset_id, rank as f64);
+        for mut sorted in grouped_values(self.groupby.as_deref(), values, timestamp, context)? {
+            // Sort assets by value
+            if self.ascending {
+                sorted.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+            } else {
+                sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            }
+
+            // Assign ranks
+            for (rank, (asset_id, _)) in sorted.iter().enumerate() {
+                output.insert(*asset_id, rank as f64);
+            }
         }
 
         Ok(output)
@@ -271,41 +314,133 @@ impl Factor for RankFactor {
     }
 }
 
-/// Z-score normalization
-#[derive(Clone)]
+/// Z-score normalization, optionally within classifier groups
 pub struct ZScoreFactor {
     name: String,
     factor: String,
+    groupby: Option<Box<dyn Classifier>>,
 }
 
 impl ZScoreFactor {
     pub fn new(factor: String) -> Self {
         let name = format!("zscore({})", factor);
-        Self { name, factor }
+        Self {
+            name,
+            factor,
+            groupby: None,
+        }
+    }
+
+    /// Normalize within the groups labeled by `classifier`
+    pub fn with_groupby(mut self, classifier: Box<dyn Classifier>) -> Self {
+        self.name = format!("zscore({}, {})", self.factor, classifier.name());
+        self.groupby = Some(classifier);
+        self
+    }
+}
+
+impl Clone for ZScoreFactor {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            factor: self.factor.clone(),
+            groupby: self.groupby.as_ref().map(|c| c.clone_box()),
+        }
     }
 }
 
 impl Factor for ZScoreFactor {
-    fn compute(&self, _timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
         let values = context.get_cached(&self.factor).ok_or_else(|| {
             crate::error::ZiplineError::PipelineError(format!("Factor {} not found", self.factor))
         })?;
 
-        // Calculate mean and std dev
-        let vals: Vec<f64> = values.values().copied().collect();
-        let mean = vals.iter().sum::<f64>() / vals.len() as f64;
-        let variance = vals.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / vals.len() as f64;
-        let std_dev = variance.sqrt();
+        let mut output = HashMap::new();
+        for group in grouped_values(self.groupby.as_deref(), values, timestamp, context)? {
+            // Calculate mean and std dev
+            let n = group.len() as f64;
+            let mean = group.iter().map(|(_, v)| v).sum::<f64>() / n;
+            let variance = group.iter().map(|(_, v)| (v - mean).powi(2)).sum::<f64>() / n;
+            let std_dev = variance.sqrt();
+
+            // Calculate z-scores
+            for (asset_id, val) in group {
+                let z_score = if std_dev > f64::EPSILON {
+                    (val - mean) / std_dev
+                } else {
+                    0.0
+                };
+                output.insert(asset_id, z_score);
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![self.factor.clone()]
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+/// Subtract the mean of a factor, optionally within classifier groups
+///
+/// With a `groupby` classifier each asset is demeaned against the other
+/// assets sharing its label, e.g. its sector, which makes a subsequent rank
+/// sector-neutral. Assets the classifier leaves unlabeled are dropped.
+pub struct DemeanFactor {
+    name: String,
+    factor: String,
+    groupby: Option<Box<dyn Classifier>>,
+}
+
+impl DemeanFactor {
+    pub fn new(factor: String) -> Self {
+        let name = format!("demean({})", factor);
+        Self {
+            name,
+            factor,
+            groupby: None,
+        }
+    }
+
+    /// Demean within the groups labeled by `classifier`
+    pub fn with_groupby(mut self, classifier: Box<dyn Classifier>) -> Self {
+        self.name = format!("demean({}, {})", self.factor, classifier.name());
+        self.groupby = Some(classifier);
+        self
+    }
+}
+
+impl Clone for DemeanFactor {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            factor: self.factor.clone(),
+            groupby: self.groupby.as_ref().map(|c| c.clone_box()),
+        }
+    }
+}
+
+impl Factor for DemeanFactor {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let values = context.get_cached(&self.factor).ok_or_else(|| {
+            crate::error::ZiplineError::PipelineError(format!("Factor {} not found", self.factor))
+        })?;
 
-        // Calculate z-scores
         let mut output = HashMap::new();
-        for (&asset_id, &val) in values {
-            let z_score = if std_dev > f64::EPSILON {
-                (val - mean) / std_dev
-            } else {
-                0.0
-            };
-            output.insert(asset_id, z_score);
+        for group in grouped_values(self.groupby.as_deref(), values, timestamp, context)? {
+            let mean = group.iter().map(|(_, v)| v).sum::<f64>() / group.len() as f64;
+            for (asset_id, val) in group {
+                output.insert(asset_id, val - mean);
+            }
         }
 
         Ok(output)
@@ -324,6 +459,21 @@ impl Factor for ZScoreFactor {
     }
 }
 
+/// Demean `factor` within the groups labeled by `classifier`
+pub fn demean_by(factor: &str, classifier: Box<dyn Classifier>) -> DemeanFactor {
+    DemeanFactor::new(factor.to_string()).with_groupby(classifier)
+}
+
+/// Z-score `factor` within the groups labeled by `classifier`
+pub fn zscore_by(factor: &str, classifier: Box<dyn Classifier>) -> ZScoreFactor {
+    ZScoreFactor::new(factor.to_string()).with_groupby(classifier)
+}
+
+/// Rank `factor` within the groups labeled by `classifier`
+pub fn rank_by(factor: &str, classifier: Box<dyn Classifier>, ascending: bool) -> RankFactor {
+    RankFactor::new(factor.to_string(), ascending).with_groupby(classifier)
+}
+
 /// Top N filter based on factor values
 #[derive(Clone)]
 pub struct TopNFilter {
@@ -397,4 +547,95 @@ mod tests {
         let factor = ZScoreFactor::new("test".to_string());
         assert_eq!(factor.name(), "zscore(test)");
     }
+
+    /// Context holding a "value" factor over five assets: 1 and 2 are
+    /// energy, 3 and 4 tech, 5 is unclassified
+    fn sector_context() -> (PipelineContext, Box<dyn Classifier>) {
+        use crate::asset::Asset;
+        use crate::pipeline::classifiers::{ClassificationMap, Sector};
+        use crate::pipeline::engine::{DataProvider, OHLCVBar};
+        use chrono::{NaiveDate, TimeZone};
+        use std::sync::Arc;
+
+        struct NoData;
+        impl DataProvider for NoData {
+            fn get_prices(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_volumes(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_ohlcv(&self, _: u64, _: usize) -> Result<Vec<OHLCVBar>> {
+                Ok(Vec::new())
+            }
+            fn get_latest_price(&self, _: u64) -> Result<f64> {
+                Ok(0.0)
+            }
+        }
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let assets: Vec<Asset> = (1..=5)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+            .collect();
+
+        let mut map = ClassificationMap::new();
+        map.insert(1, listed, "10102010");
+        map.insert(2, listed, "10101020");
+        map.insert(3, listed, "45102010");
+        map.insert(4, listed, "45301020");
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut context = PipelineContext::new(assets, Arc::new(NoData), timestamp);
+        let values: FactorOutput = [(1, 1.0), (2, 3.0), (3, 10.0), (4, 20.0), (5, 7.0)]
+            .into_iter()
+            .collect();
+        context.cache_result("value".to_string(), values);
+
+        (context, Box::new(Sector::new(Arc::new(map))))
+    }
+
+    #[test]
+    fn test_demean_by_sector() {
+        let (context, sector) = sector_context();
+        let timestamp = context.timestamp();
+
+        let global = DemeanFactor::new("value".to_string());
+        let output = global.compute(timestamp, &context).unwrap();
+        assert_eq!(output.len(), 5);
+        assert!((output[&5] + 1.2).abs() < 1e-9);
+
+        let by_sector = demean_by("value", sector);
+        assert_eq!(by_sector.name(), "demean(value, Sector)");
+        let output = by_sector.compute(timestamp, &context).unwrap();
+        assert_eq!(output.len(), 4);
+        assert_eq!(output[&1], -1.0);
+        assert_eq!(output[&2], 1.0);
+        assert_eq!(output[&3], -5.0);
+        assert_eq!(output[&4], 5.0);
+    }
+
+    #[test]
+    fn test_zscore_and_rank_by_sector() {
+        let (context, sector) = sector_context();
+        let timestamp = context.timestamp();
+
+        let zscore = zscore_by("value", sector.clone_box());
+        assert_eq!(zscore.name(), "zscore(value, Sector)");
+        let output = zscore.compute(timestamp, &context).unwrap();
+        assert_eq!(output.len(), 4);
+        assert_eq!(output[&1], -1.0);
+        assert_eq!(output[&4], 1.0);
+
+        // Ranks restart in each sector; the clone keeps the grouping
+        let rank = rank_by("value", sector, false).clone();
+        let output = rank.compute(timestamp, &context).unwrap();
+        assert_eq!((output[&1], output[&2]), (1.0, 0.0));
+        assert_eq!((output[&3], output[&4]), (1.0, 0.0));
+        assert!(!output.contains_key(&5));
+
+        let global = RankFactor::new("value".to_string(), true)
+            .compute(timestamp, &context)
+            .unwrap();
+        assert_eq!(global[&4], 4.0);
+    }
 }
@@ -0,0 +1,254 @@
+//! User-defined factors over windowed inputs
+//!
+//! Like Zipline's `CustomFactor`, a [`CustomFactor`] declares the columns it
+//! reads and how many bars of each it needs. The pipeline loads those windows
+//! for every asset in the universe and calls `compute` once per run with one
+//! 2-D array per input, shaped `(window_length, assets)` with the oldest bar
+//! in the first row. Bars missing from a short history are NaN.
+//!
+//! ```ignore
+//! struct MeanRange;
+//!
+//! impl CustomFactor for MeanRange {
+//!     fn inputs(&self) -> Vec<FactorInput> {
+//!         vec![FactorInput::High, FactorInput::Low]
+//!     }
+//!
+//!     fn window_length(&self) -> usize {
+//!         10
+//!     }
+//!
+//!     fn compute(&self, _today: DateTime<Utc>, _assets: &[u64], out: &mut [f64], inputs: &[ArrayView2<f64>]) -> Result<()> {
+//!         let range = &inputs[0] - &inputs[1];
+//!         for (value, column) in out.iter_mut().zip(range.columns()) {
+//!             *value = column.mean().unwrap_or(f64::NAN);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! pipeline.add_custom_factor("mean_range".to_string(), MeanRange);
+//! ```
+
+use super::engine::{Factor, FactorOutput, Pipeline, PipelineContext};
+use crate::error::{Result, ZiplineError};
+use chrono::{DateTime, Utc};
+use ndarray::Array2;
+use std::sync::Arc;
+
+pub use ndarray::ArrayView2;
+
+/// A column a custom factor can read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FactorInput {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+/// A factor computed from windows of its declared inputs
+pub trait CustomFactor: Send + Sync {
+    /// Columns passed to `compute`, in order
+    fn inputs(&self) -> Vec<FactorInput>;
+
+    /// Bars of each input passed to `compute`
+    fn window_length(&self) -> usize;
+
+    /// Write one value per asset into `out`
+    ///
+    /// `out[i]` belongs to `assets[i]` and column `i` of every input; it
+    /// starts as NaN.
+    fn compute(
+        &self,
+        today: DateTime<Utc>,
+        assets: &[u64],
+        out: &mut [f64],
+        inputs: &[ArrayView2<f64>],
+    ) -> Result<()>;
+
+    /// Get factor name
+    fn name(&self) -> &str {
+        "CustomFactor"
+    }
+}
+
+/// Runs a [`CustomFactor`] as a pipeline [`Factor`]
+#[derive(Clone)]
+pub struct CustomFactorAdapter {
+    name: String,
+    factor: Arc<dyn CustomFactor>,
+}
+
+impl CustomFactorAdapter {
+    pub fn new(factor: impl CustomFactor + 'static) -> Self {
+        Self {
+            name: factor.name().to_string(),
+            factor: Arc::new(factor),
+        }
+    }
+
+    /// Load the `(window_length, assets)` window of each input
+    fn load_inputs(&self, assets: &[u64], context: &PipelineContext) -> Result<Vec<Array2<f64>>> {
+        let window = self.factor.window_length();
+        let inputs = self.factor.inputs();
+        let mut arrays = vec![Array2::from_elem((window, assets.len()), f64::NAN); inputs.len()];
+        let provider = context.data_provider();
+
+        for (col, &asset_id) in assets.iter().enumerate() {
+            let bars = provider.get_ohlcv(asset_id, window)?;
+            let bars = &bars[bars.len().saturating_sub(window)..];
+            // Right-align short histories so the last row is always the latest bar
+            let offset = window - bars.len();
+
+            for (input, array) in inputs.iter().zip(arrays.iter_mut()) {
+                for (i, bar) in bars.iter().enumerate() {
+                    array[[offset + i, col]] = match input {
+                        FactorInput::Open => bar.open,
+                        FactorInput::High => bar.high,
+                        FactorInput::Low => bar.low,
+                        FactorInput::Close => bar.close,
+                        FactorInput::Volume => bar.volume,
+                    };
+                }
+            }
+        }
+
+        Ok(arrays)
+    }
+}
+
+impl Factor for CustomFactorAdapter {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        if self.factor.window_length() == 0 {
+            return Err(ZiplineError::PipelineError(format!(
+                "Custom factor {} must declare a window_length of at least 1",
+                self.name
+            )));
+        }
+
+        let assets: Vec<u64> = context.assets().iter().map(|asset| asset.id).collect();
+        let arrays = self.load_inputs(&assets, context)?;
+        let views: Vec<ArrayView2<f64>> = arrays.iter().map(|array| array.view()).collect();
+
+        let mut out = vec![f64::NAN; assets.len()];
+        self.factor.compute(timestamp, &assets, &mut out, &views)?;
+
+        Ok(assets.into_iter().zip(out).collect())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn window_length(&self) -> usize {
+        self.factor.window_length()
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+impl Pipeline {
+    /// Add a user-defined windowed factor to the pipeline
+    pub fn add_custom_factor(&mut self, name: String, factor: impl CustomFactor + 'static) -> &mut Self {
+        self.add_factor(name, Box::new(CustomFactorAdapter::new(factor)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::pipeline::engine::{DataProvider, OHLCVBar};
+    use chrono::{NaiveDate, TimeZone};
+
+    /// Asset `n` has `n + 1` bars with closes 1, 2, ... and opens one lower
+    struct Staircase;
+
+    impl DataProvider for Staircase {
+        fn get_prices(&self, asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(self.get_ohlcv(asset_id, lookback)?.iter().map(|b| b.close).collect())
+        }
+
+        fn get_volumes(&self, asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(self.get_ohlcv(asset_id, lookback)?.iter().map(|b| b.volume).collect())
+        }
+
+        fn get_ohlcv(&self, asset_id: u64, lookback: usize) -> Result<Vec<OHLCVBar>> {
+            let bars = (asset_id as usize + 1).min(lookback);
+            Ok((0..bars)
+                .map(|i| {
+                    let close = (i + 1) as f64;
+                    OHLCVBar {
+                        timestamp: Utc::now(),
+                        open: close - 1.0,
+                        high: close,
+                        low: close - 1.0,
+                        close,
+                        volume: 100.0,
+                    }
+                })
+                .collect())
+        }
+
+        fn get_latest_price(&self, asset_id: u64) -> Result<f64> {
+            Ok(asset_id as f64 + 1.0)
+        }
+    }
+
+    /// Mean close-to-open change over the window, NaN without a full window
+    struct MeanGain;
+
+    impl CustomFactor for MeanGain {
+        fn inputs(&self) -> Vec<FactorInput> {
+            vec![FactorInput::Open, FactorInput::Close]
+        }
+
+        fn window_length(&self) -> usize {
+            3
+        }
+
+        fn compute(
+            &self,
+            _today: DateTime<Utc>,
+            assets: &[u64],
+            out: &mut [f64],
+            inputs: &[ArrayView2<f64>],
+        ) -> Result<()> {
+            assert_eq!(inputs[0].dim(), (3, assets.len()));
+            let gain = &inputs[1] - &inputs[0];
+            for (value, column) in out.iter_mut().zip(gain.columns()) {
+                *value = column.mean().unwrap();
+            }
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "MeanGain"
+        }
+    }
+
+    #[test]
+    fn test_custom_factor_in_pipeline() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let assets = (1..=3)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+            .collect();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_universe(assets);
+        pipeline.add_custom_factor("gain".to_string(), MeanGain);
+        assert_eq!(pipeline.max_window_length(), 3);
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let output = pipeline.run(timestamp, Arc::new(Staircase)).unwrap();
+
+        // Asset 1 has two bars, so its first row is NaN
+        assert!(output.get_factor_value("gain", 1).unwrap().is_nan());
+        assert_eq!(output.get_factor_value("gain", 2), Some(1.0));
+        assert_eq!(output.get_factor_value("gain", 3), Some(1.0));
+    }
+}
@@ -50,6 +50,16 @@ pub trait Domain: Send + Sync + fmt::Debug {
     fn clone_arc(&self) -> Arc<dyn Domain>;
 }
 
+/// Assets listed and not yet delisted on the session containing `dt`
+fn alive_at(assets: &[Asset], dt: DateTime<Utc>) -> Vec<Asset> {
+    let session = dt.date_naive();
+    assets
+        .iter()
+        .filter(|a| a.is_alive_for_session(session))
+        .cloned()
+        .collect()
+}
+
 /// Universe of all available assets
 #[derive(Debug, Clone)]
 pub struct EquityUniverse {
@@ -94,8 +104,8 @@ impl Domain for EquityUniverse {
         &self.name
     }
 
-    fn assets_at(&self, _dt: DateTime<Utc>) -> Result<Vec<Asset>> {
-        Ok(self.assets.clone())
+    fn assets_at(&self, dt: DateTime<Utc>) -> Result<Vec<Asset>> {
+        Ok(alive_at(&self.assets, dt))
     }
 
     fn country_code(&self) -> Option<&str> {
@@ -164,7 +174,7 @@ impl Domain for StaticDomain {
                 return Ok(Vec::new());
             }
         }
-        Ok(self.assets.clone())
+        Ok(alive_at(&self.assets, dt))
     }
 
     fn start_date(&self) -> Option<DateTime<Utc>> {
@@ -373,6 +383,10 @@ mod tests {
             symbol: symbol.to_string(),
             exchange: "NYSE".to_string(),
             asset_type: crate::asset::AssetType::Equity,
+            name: None,
+            start_date: chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            end_date: None,
+            auto_close_date: None,
         }
     }
 
@@ -405,6 +419,20 @@ mod tests {
         assert_eq!(result.len(), 2);
     }
 
+    #[test]
+    fn test_domain_excludes_dead_assets() {
+        use chrono::{NaiveDate, TimeZone};
+
+        let delisted = create_test_asset(1, "GONE")
+            .with_end_date(NaiveDate::from_ymd_opt(2015, 6, 30).unwrap());
+        let domain = EquityUniverse::generic(1, vec![delisted, create_test_asset(2, "AAPL")]);
+
+        let before = Utc.with_ymd_and_hms(2015, 6, 30, 15, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2015, 7, 1, 15, 0, 0).unwrap();
+        assert_eq!(domain.assets_at(before).unwrap().len(), 2);
+        assert_eq!(domain.assets_at(after).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_static_domain_with_dates() {
         let assets = vec![create_test_asset(1, "AAPL")];
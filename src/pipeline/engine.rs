@@ -4,6 +4,7 @@ use crate::asset::Asset;
 use crate::error::{Result, ZiplineError};
 use chrono::{NaiveDate, DateTime, Utc};
 use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 /// Factor computation result for a single asset
@@ -25,6 +26,13 @@ pub trait Factor: Send + Sync {
         Vec::new()
     }
 
+    /// Bars of history needed to compute the factor
+    ///
+    /// Factors computed only from other factors need none of their own.
+    fn window_length(&self) -> usize {
+        0
+    }
+
     /// Clone as trait object
     fn clone_box(&self) -> Box<dyn Factor>;
 }
@@ -183,6 +191,71 @@ impl Pipeline {
         self
     }
 
+    /// Longest history window of any factor in the pipeline
+    pub fn max_window_length(&self) -> usize {
+        self.factors
+            .values()
+            .map(|factor| factor.window_length())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Stable hash of the pipeline's term graph and universe
+    ///
+    /// Covers every term's registered name, its own name, window length and
+    /// dependencies, plus the universe's asset ids, so it identifies a
+    /// pipeline's outputs across processes. Term parameters are only covered
+    /// through their names.
+    pub fn graph_hash(&self) -> u64 {
+        // FNV-1a, reproducible between runs unlike the std hasher
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes.iter().chain(&[0xff]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        let mut factors: Vec<_> = self.factors.iter().collect();
+        factors.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, factor) in factors {
+            write(b"factor");
+            write(name.as_bytes());
+            write(factor.name().as_bytes());
+            write(&(factor.window_length() as u64).to_le_bytes());
+            let mut deps = factor.dependencies();
+            deps.sort();
+            for dep in deps {
+                write(dep.as_bytes());
+            }
+        }
+
+        let mut filters: Vec<_> = self.filters.iter().collect();
+        filters.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, filter) in filters {
+            write(b"filter");
+            write(name.as_bytes());
+            write(filter.name().as_bytes());
+        }
+
+        let mut classifiers: Vec<_> = self.classifiers.iter().collect();
+        classifiers.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, classifier) in classifiers {
+            write(b"classifier");
+            write(name.as_bytes());
+            write(classifier.name().as_bytes());
+        }
+
+        let mut assets: Vec<u64> = self.universe.iter().map(|asset| asset.id).collect();
+        assets.sort_unstable();
+        write(b"universe");
+        for id in assets {
+            write(&id.to_le_bytes());
+        }
+
+        hash
+    }
+
     /// Rebuild execution order based on dependencies
     fn rebuild_execution_order(&mut self) {
         // Simple topological sort
@@ -219,13 +292,31 @@ impl Pipeline {
         timestamp: DateTime<Utc>,
         data_provider: Arc<dyn DataProvider>,
     ) -> Result<PipelineOutput> {
-        let mut context = PipelineContext::new(self.universe.clone(), data_provider, timestamp);
+        let _span = tracing::debug_span!(
+            "pipeline",
+            %timestamp,
+            factors = self.factors.len(),
+            filters = self.filters.len(),
+            classifiers = self.classifiers.len(),
+        )
+        .entered();
+
+        // Only assets listed on this session are in scope
+        let session = timestamp.date_naive();
+        let universe = self
+            .universe
+            .iter()
+            .filter(|a| a.is_alive_for_session(session))
+            .cloned()
+            .collect();
+        let mut context = PipelineContext::new(universe, data_provider, timestamp);
 
         // Execute factors in dependency order
         let mut factor_results = HashMap::new();
         for factor_name in &self.execution_order {
             if let Some(factor) = self.factors.get(factor_name) {
-                let output = factor.compute(timestamp, &context)?;
+                let output = tracing::trace_span!("factor", name = %factor_name)
+                    .in_scope(|| factor.compute(timestamp, &context))?;
                 context.cache_result(factor_name.clone(), output.clone());
                 factor_results.insert(factor_name.clone(), output);
             }
@@ -293,15 +384,17 @@ impl Default for Pipeline {
 }
 
 /// Pipeline execution output
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineOutput {
     /// Timestamp of execution
     pub timestamp: DateTime<Utc>,
     /// Factor results (factor_name -> asset_id -> value)
     pub factors: HashMap<String, FactorOutput>,
     /// Filter results (filter_name -> asset_id -> bool)
+    #[serde(default)]
     pub filters: HashMap<String, HashMap<u64, bool>>,
     /// Classifier results (classifier_name -> asset_id -> category)
+    #[serde(default)]
     pub classifiers: HashMap<String, HashMap<u64, String>>,
 }
 
@@ -422,6 +515,48 @@ mod tests {
         assert!(pipeline.get_factor("test").is_ok());
     }
 
+    #[test]
+    fn test_max_window_length() {
+        use crate::pipeline::factors_returns::MaxDrawdown;
+        use crate::pipeline::factors_volume::AverageDollarVolume;
+
+        let mut pipeline = Pipeline::new();
+        assert_eq!(pipeline.max_window_length(), 0);
+
+        let constant = ConstantFactor {
+            name: "constant".to_string(),
+            value: 1.0,
+        };
+        pipeline.add_factor("constant".to_string(), Box::new(constant));
+        pipeline.add_factor("drawdown".to_string(), Box::new(MaxDrawdown::new(63)));
+        pipeline.add_factor("adv".to_string(), Box::new(AverageDollarVolume::new(20)));
+        assert_eq!(pipeline.max_window_length(), 63);
+    }
+
+    #[test]
+    fn test_graph_hash() {
+        let constant = |value| {
+            Box::new(ConstantFactor {
+                name: "constant".to_string(),
+                value,
+            })
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "TEST".to_string(), "TEST".to_string(), start_date);
+
+        let mut a = Pipeline::new();
+        a.add_factor("x".to_string(), constant(1.0));
+        a.add_factor("y".to_string(), constant(2.0));
+        let mut b = Pipeline::new();
+        b.add_factor("y".to_string(), constant(2.0));
+        b.add_factor("x".to_string(), constant(1.0));
+        assert_eq!(a.graph_hash(), b.graph_hash());
+
+        b.set_universe(vec![asset]);
+        assert_ne!(a.graph_hash(), b.graph_hash());
+        assert_ne!(Pipeline::new().graph_hash(), a.graph_hash());
+    }
+
     #[test]
     fn test_pipeline_execution() {
         let mut pipeline = Pipeline::new();
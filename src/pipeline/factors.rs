@@ -1,13 +1,84 @@
 //! Technical analysis factors for pipeline system
 
+use super::kernels;
 use statrs::statistics::{Data, Distribution};
 use std::collections::VecDeque;
 
+/// Updates between full re-summations of a [`WindowSum`]
+const RESUM_INTERVAL: usize = 1024;
+
+/// Sliding-window sum maintained in O(1) per update
+///
+/// Non-finite values are counted rather than added to the running total, so
+/// a NaN or inf affects the sum only while it is inside the window. The total
+/// is recomputed from the buffer once the last of them leaves, and every
+/// [`RESUM_INTERVAL`] updates to bound floating-point drift.
+#[derive(Debug, Clone)]
+struct WindowSum {
+    window: usize,
+    values: VecDeque<f64>,
+    finite_sum: f64,
+    non_finite: usize,
+    since_resum: usize,
+}
+
+impl WindowSum {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            values: VecDeque::with_capacity(window),
+            finite_sum: 0.0,
+            non_finite: 0,
+            since_resum: 0,
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.values.push_back(value);
+        if value.is_finite() {
+            self.finite_sum += value;
+        } else {
+            self.non_finite += 1;
+        }
+
+        let mut resum = false;
+        if self.values.len() > self.window {
+            if let Some(dropped) = self.values.pop_front() {
+                if dropped.is_finite() {
+                    self.finite_sum -= dropped;
+                } else {
+                    self.non_finite -= 1;
+                    resum = self.non_finite == 0;
+                }
+            }
+        }
+
+        self.since_resum += 1;
+        if resum || self.since_resum >= RESUM_INTERVAL {
+            self.finite_sum = self.values.iter().filter(|v| v.is_finite()).sum();
+            self.since_resum = 0;
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.values.len() == self.window
+    }
+
+    /// Sum of the window; NaN or inf while a non-finite value is inside it
+    fn sum(&self) -> f64 {
+        if self.non_finite > 0 {
+            self.values.iter().sum()
+        } else {
+            self.finite_sum
+        }
+    }
+}
+
 /// Simple Moving Average (SMA)
 #[derive(Debug, Clone)]
 pub struct SimpleMovingAverage {
     window: usize,
-    values: VecDeque<f64>,
+    values: WindowSum,
 }
 
 impl SimpleMovingAverage {
@@ -18,35 +89,37 @@ impl SimpleMovingAverage {
         }
         Self {
             window,
-            values: VecDeque::with_capacity(window),
+            values: WindowSum::new(window),
         }
     }
 
+    /// Window size
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
     /// Add a value and compute current SMA
     pub fn update(&mut self, value: f64) -> Option<f64> {
-        self.values.push_back(value);
-
-        if self.values.len() > self.window {
-            self.values.pop_front();
-        }
-
-        if self.values.len() == self.window {
-            Some(self.values.iter().sum::<f64>() / self.window as f64)
-        } else {
-            None
-        }
+        self.values.push(value);
+        self.current()
     }
 
     /// Compute SMA for a slice of values
     pub fn compute(window: usize, values: &[f64]) -> Vec<Option<f64>> {
-        let mut sma = Self::new(window);
-        values.iter().map(|&v| sma.update(v)).collect()
+        if window == 0 {
+            panic!("Window size must be greater than 0");
+        }
+        kernels::rolling_mean(values, window)
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (i + 1 >= window).then_some(v))
+            .collect()
     }
 
     /// Get current value (if window is full)
     pub fn current(&self) -> Option<f64> {
-        if self.values.len() == self.window {
-            Some(self.values.iter().sum::<f64>() / self.window as f64)
+        if self.values.is_full() {
+            Some(self.values.sum() / self.window as f64)
         } else {
             None
         }
@@ -75,6 +148,11 @@ impl ExponentialMovingAverage {
         }
     }
 
+    /// Span the smoothing factor is derived from
+    pub fn span(&self) -> usize {
+        self.span
+    }
+
     /// Update with new value
     pub fn update(&mut self, value: f64) -> f64 {
         match self.current_ema {
@@ -92,8 +170,10 @@ impl ExponentialMovingAverage {
 
     /// Compute EMA for a slice of values
     pub fn compute(span: usize, values: &[f64]) -> Vec<f64> {
-        let mut ema = Self::new(span);
-        values.iter().map(|&v| ema.update(v)).collect()
+        if span == 0 {
+            panic!("Span must be greater than 0");
+        }
+        kernels::ewm_mean(values, span)
     }
 
     /// Get current EMA value
@@ -161,8 +241,14 @@ impl RSI {
 
     /// Compute RSI for a slice of values
     pub fn compute(period: usize, values: &[f64]) -> Vec<Option<f64>> {
-        let mut rsi = Self::new(period);
-        values.iter().map(|&v| rsi.update(v)).collect()
+        if period == 0 {
+            panic!("Period must be greater than 0");
+        }
+        kernels::rsi(values, period)
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (i >= period).then_some(v))
+            .collect()
     }
 }
 
@@ -257,7 +343,7 @@ impl BollingerBands {
 #[derive(Debug, Clone)]
 pub struct AverageTrueRange {
     period: usize,
-    tr_values: VecDeque<f64>,
+    tr_values: WindowSum,
     prev_close: Option<f64>,
 }
 
@@ -269,11 +355,16 @@ impl AverageTrueRange {
         }
         Self {
             period,
-            tr_values: VecDeque::with_capacity(period),
+            tr_values: WindowSum::new(period),
             prev_close: None,
         }
     }
 
+    /// Averaging period
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
     /// Update with new OHLC values
     pub fn update(&mut self, high: f64, low: f64, close: f64) -> Option<f64> {
         let tr = if let Some(prev_close) = self.prev_close {
@@ -285,15 +376,16 @@ impl AverageTrueRange {
             high - low
         };
 
-        self.tr_values.push_back(tr);
-        if self.tr_values.len() > self.period {
-            self.tr_values.pop_front();
-        }
+        self.tr_values.push(tr);
 
         self.prev_close = Some(close);
+        self.current()
+    }
 
-        if self.tr_values.len() == self.period {
-            Some(self.tr_values.iter().sum::<f64>() / self.period as f64)
+    /// Get current value (if the period is full)
+    pub fn current(&self) -> Option<f64> {
+        if self.tr_values.is_full() {
+            Some(self.tr_values.sum() / self.period as f64)
         } else {
             None
         }
@@ -443,6 +535,39 @@ mod tests {
         assert_eq!(sma.update(4.0), Some(3.0)); // (2+3+4)/3 = 3
     }
 
+    #[test]
+    fn test_sma_recovers_after_nan_leaves_window() {
+        let mut sma = SimpleMovingAverage::new(3);
+
+        sma.update(1.0);
+        sma.update(2.0);
+        assert!(sma.update(f64::NAN).unwrap().is_nan());
+        assert!(sma.update(4.0).unwrap().is_nan());
+        assert!(sma.update(5.0).unwrap().is_nan());
+        assert_eq!(sma.update(6.0), Some(5.0)); // (4+5+6)/3
+
+        let mut atr = AverageTrueRange::new(2);
+        atr.update(11.0, 9.0, 10.0);
+        assert!(atr.update(f64::INFINITY, 9.0, 10.0).unwrap().is_infinite());
+        atr.update(12.0, 10.0, 11.0);
+        assert_eq!(atr.update(13.0, 11.0, 12.0), Some(2.0));
+    }
+
+    #[test]
+    fn test_sma_matches_batch_over_long_runs() {
+        let values: Vec<f64> = (0..5_000).map(|i| 1e6 + (i as f64 * 0.37).sin() * 1e-3).collect();
+        let mut sma = SimpleMovingAverage::new(20);
+        let batch = SimpleMovingAverage::compute(20, &values);
+
+        for (value, expected) in values.iter().zip(batch) {
+            let streamed = sma.update(*value);
+            match (streamed, expected) {
+                (Some(s), Some(e)) => assert_relative_eq!(s, e, max_relative = 1e-12),
+                (s, e) => assert_eq!(s, e),
+            }
+        }
+    }
+
     #[test]
     fn test_sma_compute() {
         let values = vec![1.0, 2.0, 3.0, 4.0, 5.0];
@@ -484,8 +609,8 @@ mod tests {
     fn test_macd() {
         let mut macd = MACD::new();
 
-        for _ in 0..50 {
-            let price = 100.0 + (rand::random::<f64>() * 10.0);
+        for i in 0..50 {
+            let price = 100.0 + ((i as f64 * 0.37).sin() * 10.0);
             let (macd_line, signal, histogram) = macd.update(price);
             assert_eq!(histogram, macd_line - signal);
         }
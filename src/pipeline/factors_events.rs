@@ -0,0 +1,159 @@
+//! Earnings and other event-date factors
+//!
+//! Distances to scheduled events count business days (weekdays), as zipline's
+//! `BusinessDaysUntilNextEarnings` does, so a Monday announcement is one day
+//! away on the preceding Friday. Assets without a known announcement are NaN.
+
+use super::engine::{Factor, FactorOutput, PipelineContext};
+use crate::data::earnings::EarningsCalendar;
+use crate::error::Result;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use std::sync::Arc;
+
+/// Weekdays in `[start, end)`, negative when `end` is before `start`
+fn business_days_between(start: NaiveDate, end: NaiveDate) -> i64 {
+    if end < start {
+        return -business_days_between(end, start);
+    }
+    let weekday = |date: NaiveDate| i64::from(date.weekday().num_days_from_monday());
+    // Count whole weeks from the Monday on or before each date
+    let monday = |date: NaiveDate| date - chrono::Duration::days(weekday(date));
+    let weeks = (monday(end) - monday(start)).num_days() / 7;
+    let weekdays_before = |date: NaiveDate| weekday(date).min(5);
+    weeks * 5 + weekdays_before(end) - weekdays_before(start)
+}
+
+/// BusinessDaysUntilNextEarnings - business days from each session to the
+/// asset's next known earnings announcement, zero on the announcement day
+#[derive(Clone)]
+pub struct BusinessDaysUntilNextEarnings {
+    calendar: Arc<EarningsCalendar>,
+}
+
+impl BusinessDaysUntilNextEarnings {
+    pub fn new(calendar: Arc<EarningsCalendar>) -> Self {
+        Self { calendar }
+    }
+}
+
+impl Factor for BusinessDaysUntilNextEarnings {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let session = timestamp.date_naive();
+        Ok(context
+            .assets()
+            .iter()
+            .map(|asset| {
+                let days = self
+                    .calendar
+                    .next_announcement(asset.id, session)
+                    .map_or(f64::NAN, |next| business_days_between(session, next) as f64);
+                (asset.id, days)
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        "BusinessDaysUntilNextEarnings"
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+/// BusinessDaysSincePreviousEarnings - business days since the asset's most
+/// recent earnings announcement, zero on the announcement day
+#[derive(Clone)]
+pub struct BusinessDaysSincePreviousEarnings {
+    calendar: Arc<EarningsCalendar>,
+}
+
+impl BusinessDaysSincePreviousEarnings {
+    pub fn new(calendar: Arc<EarningsCalendar>) -> Self {
+        Self { calendar }
+    }
+}
+
+impl Factor for BusinessDaysSincePreviousEarnings {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let session = timestamp.date_naive();
+        Ok(context
+            .assets()
+            .iter()
+            .map(|asset| {
+                let days = self
+                    .calendar
+                    .previous_announcement(asset.id, session)
+                    .map_or(f64::NAN, |previous| business_days_between(previous, session) as f64);
+                (asset.id, days)
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        "BusinessDaysSincePreviousEarnings"
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::pipeline::engine::{DataProvider, OHLCVBar};
+    use chrono::TimeZone;
+
+    struct NoData;
+    impl DataProvider for NoData {
+        fn get_prices(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+            Ok(Vec::new())
+        }
+        fn get_volumes(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+            Ok(Vec::new())
+        }
+        fn get_ohlcv(&self, _: u64, _: usize) -> Result<Vec<OHLCVBar>> {
+            Ok(Vec::new())
+        }
+        fn get_latest_price(&self, _: u64) -> Result<f64> {
+            Ok(0.0)
+        }
+    }
+
+    #[test]
+    fn test_business_days_around_earnings() {
+        let day = |m: u32, d: u32| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        // Friday to the following Monday, and across a whole week
+        assert_eq!(business_days_between(day(1, 5), day(1, 8)), 1);
+        assert_eq!(business_days_between(day(1, 6), day(1, 8)), 0);
+        assert_eq!(business_days_between(day(1, 3), day(1, 17)), 10);
+        assert_eq!(business_days_between(day(1, 17), day(1, 3)), -10);
+
+        let mut calendar = EarningsCalendar::new();
+        calendar.insert(1, day(1, 25), day(1, 2));
+        calendar.insert(1, day(4, 25), day(4, 1));
+        let calendar = Arc::new(calendar);
+
+        let listed = day(1, 1);
+        let assets: Vec<Asset> = (1..=2)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+            .collect();
+        let at = |m: u32, d: u32| Utc.with_ymd_and_hms(2024, m, d, 21, 0, 0).unwrap();
+        let context = |m: u32, d: u32| PipelineContext::new(assets.clone(), Arc::new(NoData), at(m, d));
+
+        let until = BusinessDaysUntilNextEarnings::new(calendar.clone());
+        let since = BusinessDaysSincePreviousEarnings::new(calendar);
+
+        let output = until.compute(at(1, 22), &context(1, 22)).unwrap();
+        assert_eq!(output[&1], 3.0);
+        assert!(output[&2].is_nan());
+        assert_eq!(until.compute(at(1, 25), &context(1, 25)).unwrap()[&1], 0.0);
+        assert!(since.compute(at(1, 22), &context(1, 22)).unwrap()[&1].is_nan());
+
+        // In March the April date is not yet scheduled
+        assert!(until.compute(at(3, 1), &context(3, 1)).unwrap()[&1].is_nan());
+        assert_eq!(since.compute(at(2, 1), &context(2, 1)).unwrap()[&1], 5.0);
+    }
+}
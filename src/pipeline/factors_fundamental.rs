@@ -1,6 +1,15 @@
 //! Fundamental analysis factors
 //!
-//! This module provides fundamental metrics for stock valuation
+//! This module provides fundamental metrics for stock valuation. The ratio
+//! calculators work on plain numbers; [`Fundamental`] and [`FundamentalFactor`]
+//! feed them point-in-time values from a
+//! [`FundamentalsStore`](crate::data::fundamentals::FundamentalsStore).
+
+use super::engine::{Factor, FactorOutput, PipelineContext};
+use crate::data::fundamentals::FundamentalsStore;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
 
 /// PE Ratio - Price to Earnings Ratio
 #[derive(Debug, Clone)]
@@ -211,6 +220,173 @@ impl PayoutRatio {
     }
 }
 
+/// Fundamental - latest point-in-time value of a stored field, such as `eps`
+#[derive(Clone)]
+pub struct Fundamental {
+    store: Arc<FundamentalsStore>,
+    field: String,
+}
+
+impl Fundamental {
+    pub fn new(store: Arc<FundamentalsStore>, field: impl Into<String>) -> Self {
+        Self {
+            store,
+            field: field.into(),
+        }
+    }
+}
+
+impl Factor for Fundamental {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let session = timestamp.date_naive();
+        Ok(context
+            .assets()
+            .iter()
+            .map(|asset| {
+                let value = self.store.value_at(asset.id, &self.field, session);
+                (asset.id, value.unwrap_or(f64::NAN))
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        &self.field
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+/// Ratio computed by a [`FundamentalFactor`]
+///
+/// Each reads the store fields listed by [`FundamentalMetric::fields`];
+/// `price` is the latest price from the pipeline's data provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundamentalMetric {
+    PERatio,
+    PBRatio,
+    PSRatio,
+    ROE,
+    ROA,
+    ROIC,
+    DividendYield,
+    EVToEBITDA,
+    DebtToEquity,
+    CurrentRatio,
+    QuickRatio,
+    EarningsYield,
+    PayoutRatio,
+}
+
+impl FundamentalMetric {
+    /// Inputs of the ratio, in the order its calculator takes them
+    pub fn fields(&self) -> &'static [&'static str] {
+        match self {
+            Self::PERatio => &["price", "eps"],
+            Self::PBRatio => &["price", "book_value_per_share"],
+            Self::PSRatio => &["price", "sales_per_share"],
+            Self::ROE => &["net_income", "total_equity"],
+            Self::ROA => &["net_income", "total_assets"],
+            Self::ROIC => &["nopat", "invested_capital"],
+            Self::DividendYield => &["annual_dividend", "price"],
+            Self::EVToEBITDA => &["enterprise_value", "ebitda"],
+            Self::DebtToEquity => &["total_debt", "total_equity"],
+            Self::CurrentRatio => &["current_assets", "current_liabilities"],
+            Self::QuickRatio => &["current_assets", "inventory", "current_liabilities"],
+            Self::EarningsYield => &["eps", "price"],
+            Self::PayoutRatio => &["dividends", "net_income"],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PERatio => "PERatio",
+            Self::PBRatio => "PBRatio",
+            Self::PSRatio => "PSRatio",
+            Self::ROE => "ROE",
+            Self::ROA => "ROA",
+            Self::ROIC => "ROIC",
+            Self::DividendYield => "DividendYield",
+            Self::EVToEBITDA => "EVToEBITDA",
+            Self::DebtToEquity => "DebtToEquity",
+            Self::CurrentRatio => "CurrentRatio",
+            Self::QuickRatio => "QuickRatio",
+            Self::EarningsYield => "EarningsYield",
+            Self::PayoutRatio => "PayoutRatio",
+        }
+    }
+
+    /// Ratio from inputs ordered as [`fields`](Self::fields)
+    pub fn calculate(&self, inputs: &[f64]) -> Option<f64> {
+        match (self, inputs) {
+            (Self::PERatio, &[price, eps]) => PERatio::calculate(price, eps),
+            (Self::PBRatio, &[price, book]) => PBRatio::calculate(price, book),
+            (Self::PSRatio, &[price, sales]) => PSRatio::calculate(price, sales),
+            (Self::ROE, &[income, equity]) => ROE::calculate(income, equity),
+            (Self::ROA, &[income, assets]) => ROA::calculate(income, assets),
+            (Self::ROIC, &[nopat, capital]) => ROIC::calculate(nopat, capital),
+            (Self::DividendYield, &[dividend, price]) => DividendYield::calculate(dividend, price),
+            (Self::EVToEBITDA, &[ev, ebitda]) => EVToEBITDA::calculate(ev, ebitda),
+            (Self::DebtToEquity, &[debt, equity]) => DebtToEquity::calculate(debt, equity),
+            (Self::CurrentRatio, &[assets, liabilities]) => CurrentRatio::calculate(assets, liabilities),
+            (Self::QuickRatio, &[assets, inventory, liabilities]) => {
+                QuickRatio::calculate(assets, inventory, liabilities)
+            }
+            (Self::EarningsYield, &[eps, price]) => EarningsYield::calculate(eps, price),
+            (Self::PayoutRatio, &[dividends, income]) => PayoutRatio::calculate(dividends, income),
+            _ => None,
+        }
+    }
+}
+
+/// FundamentalFactor - a fundamental ratio from point-in-time store values
+///
+/// Assets missing any input on the session, or whose ratio is undefined, are
+/// NaN.
+#[derive(Clone)]
+pub struct FundamentalFactor {
+    store: Arc<FundamentalsStore>,
+    metric: FundamentalMetric,
+}
+
+impl FundamentalFactor {
+    pub fn new(store: Arc<FundamentalsStore>, metric: FundamentalMetric) -> Self {
+        Self { store, metric }
+    }
+}
+
+impl Factor for FundamentalFactor {
+    fn compute(&self, timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let session = timestamp.date_naive();
+        Ok(context
+            .assets()
+            .iter()
+            .map(|asset| {
+                let inputs: Option<Vec<f64>> = self
+                    .metric
+                    .fields()
+                    .iter()
+                    .map(|&field| match field {
+                        "price" => context.data_provider().get_latest_price(asset.id).ok(),
+                        field => self.store.value_at(asset.id, field, session),
+                    })
+                    .collect();
+                let value = inputs.and_then(|inputs| self.metric.calculate(&inputs));
+                (asset.id, value.unwrap_or(f64::NAN))
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        self.metric.name()
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -303,4 +479,51 @@ mod tests {
         assert_eq!(PBRatio::calculate(100.0, 0.0), None);
         assert_eq!(DividendYield::calculate(5.0, 0.0), None);
     }
+
+    #[test]
+    fn test_fundamental_factor_without_lookahead() {
+        use crate::asset::Asset;
+        use crate::pipeline::engine::{DataProvider, OHLCVBar};
+        use chrono::{NaiveDate, TimeZone};
+
+        struct FlatPrice;
+        impl DataProvider for FlatPrice {
+            fn get_prices(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(vec![50.0])
+            }
+            fn get_volumes(&self, _: u64, _: usize) -> Result<Vec<f64>> {
+                Ok(Vec::new())
+            }
+            fn get_ohlcv(&self, _: u64, _: usize) -> Result<Vec<OHLCVBar>> {
+                Ok(Vec::new())
+            }
+            fn get_latest_price(&self, _: u64) -> Result<f64> {
+                Ok(50.0)
+            }
+        }
+
+        let day = |m: u32, d: u32| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let mut store = FundamentalsStore::new();
+        store.insert(1, "eps", day(3, 31), day(5, 1), 2.5);
+        store.insert(2, "eps", day(3, 31), day(4, 20), 0.0);
+        let store = Arc::new(store);
+
+        let assets: Vec<Asset> = (1..=3)
+            .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), day(1, 1)))
+            .collect();
+        let at = |m: u32, d: u32| Utc.with_ymd_and_hms(2024, m, d, 21, 0, 0).unwrap();
+        let pe = FundamentalFactor::new(store.clone(), FundamentalMetric::PERatio);
+        assert_eq!(pe.name(), "PERatio");
+
+        let before = PipelineContext::new(assets.clone(), Arc::new(FlatPrice), at(4, 30));
+        assert!(pe.compute(at(4, 30), &before).unwrap()[&1].is_nan());
+
+        let after = PipelineContext::new(assets, Arc::new(FlatPrice), at(5, 1));
+        let output = pe.compute(at(5, 1), &after).unwrap();
+        assert_relative_eq!(output[&1], 20.0);
+        // Zero earnings and missing data are both undefined
+        assert!(output[&2].is_nan() && output[&3].is_nan());
+        let eps = Fundamental::new(store, "eps").compute(at(5, 1), &after).unwrap();
+        assert_eq!(eps[&1], 2.5);
+    }
 }
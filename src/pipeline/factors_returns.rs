@@ -338,6 +338,10 @@ impl Factor for MaxDrawdown {
         "MaxDrawdown"
     }
 
+    fn window_length(&self) -> usize {
+        self.window
+    }
+
     fn clone_box(&self) -> Box<dyn Factor> {
         Box::new(self.clone())
     }
@@ -385,7 +389,7 @@ mod tests {
 
         assert_eq!(returns[0], None);
         assert!(returns[1].is_some());
-        assert_relative_eq!(returns[1].unwrap(), (105.0 / 100.0).ln(), epsilon = 1e-10);
+        assert_relative_eq!(returns[1].unwrap(), (105.0_f64 / 100.0).ln(), epsilon = 1e-10);
     }
 
     #[test]
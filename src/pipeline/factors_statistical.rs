@@ -1,7 +1,15 @@
 //! Statistical factors for pipeline analysis
 //!
-//! This module provides statistical and risk-adjusted performance indicators
-
+//! This module provides statistical and risk-adjusted performance indicators,
+//! [`RollingRegression`] for vectorized alpha/beta/R² against a benchmark, and
+//! [`SpreadZScore`] for pairs
+
+use super::kernels;
+use crate::error::Result;
+use crate::pipeline::engine::{Factor, FactorOutput, PipelineContext};
+use crate::types::AssetId;
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
 use statrs::statistics::{Data, Distribution};
 use std::collections::VecDeque;
 
@@ -322,6 +330,160 @@ impl SortinoRatio {
     }
 }
 
+/// Returns an asset's returns are regressed on
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegressionTarget {
+    /// Daily returns of a benchmark asset, such as an index ETF
+    Asset(AssetId),
+    /// A return series, such as a factor portfolio's, oldest first and ending
+    /// on the pipeline's session
+    Returns(Vec<f64>),
+}
+
+/// Which regression statistic a [`RollingRegression`] outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegressionOutput {
+    Alpha,
+    Beta,
+    RSquared,
+}
+
+/// RollingRegression - OLS alpha, beta or R² of returns against a target
+///
+/// Regresses each asset's daily returns over the last `window` sessions on
+/// the target's returns over the same sessions. Outputs beta unless
+/// configured otherwise; alpha is per period, not annualized.
+#[derive(Debug, Clone)]
+pub struct RollingRegression {
+    window: usize,
+    target: RegressionTarget,
+    output: RegressionOutput,
+}
+
+impl RollingRegression {
+    /// Create new RollingRegression over `window` returns
+    pub fn new(target: RegressionTarget, window: usize) -> Self {
+        if window < 2 {
+            panic!("Window must be at least 2");
+        }
+        Self {
+            window,
+            target,
+            output: RegressionOutput::Beta,
+        }
+    }
+
+    /// Output `output` instead of beta
+    pub fn with_output(mut self, output: RegressionOutput) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Rolling output of `returns` regressed on `target_returns`
+    ///
+    /// The series are aligned on their last values; the result is as long as
+    /// the shorter one, NaN until a window is full.
+    pub fn compute(&self, returns: &[f64], target_returns: &[f64]) -> Vec<f64> {
+        let n = returns.len().min(target_returns.len());
+        let (alpha, beta, r_squared) = kernels::rolling_regression(
+            &returns[returns.len() - n..],
+            &target_returns[target_returns.len() - n..],
+            self.window,
+        );
+        match self.output {
+            RegressionOutput::Alpha => alpha,
+            RegressionOutput::Beta => beta,
+            RegressionOutput::RSquared => r_squared,
+        }
+    }
+
+    /// Daily returns over the window from an asset's prices
+    fn window_returns(&self, context: &PipelineContext, asset_id: AssetId) -> Result<Vec<f64>> {
+        let prices = context.data_provider().get_prices(asset_id, self.window + 1)?;
+        Ok(kernels::pct_change(&prices, 1).into_iter().skip(1).collect())
+    }
+}
+
+impl Factor for RollingRegression {
+    fn compute(&self, _timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let target = match &self.target {
+            RegressionTarget::Asset(asset_id) => self.window_returns(context, *asset_id)?,
+            RegressionTarget::Returns(returns) => returns.clone(),
+        };
+
+        let mut output = HashMap::new();
+        for asset in context.assets() {
+            let returns = self.window_returns(context, asset.id)?;
+            let value = RollingRegression::compute(self, &returns, &target).last().copied();
+            output.insert(asset.id, value.unwrap_or(f64::NAN));
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &str {
+        "RollingRegression"
+    }
+
+    fn window_length(&self) -> usize {
+        self.window + 1
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+/// SpreadZScore - Z-score of each pair's price spread
+///
+/// For each `(y, x)` pair, regresses `y`'s prices on `x`'s over the window
+/// and outputs the latest residual in standard deviations of the window's
+/// residuals, keyed by `y`. See [`crate::statistics::cointegration`].
+#[derive(Debug, Clone)]
+pub struct SpreadZScore {
+    window: usize,
+    pairs: Vec<(AssetId, AssetId)>,
+}
+
+impl SpreadZScore {
+    /// Create new SpreadZScore over `window` prices
+    pub fn new(pairs: Vec<(AssetId, AssetId)>, window: usize) -> Self {
+        if window < 3 {
+            panic!("Window must be at least 3");
+        }
+        Self { window, pairs }
+    }
+}
+
+impl Factor for SpreadZScore {
+    fn compute(&self, _timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let mut output = HashMap::new();
+        for &(y, x) in &self.pairs {
+            let y_prices = context.data_provider().get_prices(y, self.window)?;
+            let x_prices = context.data_provider().get_prices(x, self.window)?;
+            let n = y_prices.len().min(x_prices.len());
+            let z = crate::statistics::rolling_spread_zscore(
+                &y_prices[y_prices.len() - n..],
+                &x_prices[x_prices.len() - n..],
+                self.window,
+            );
+            output.insert(y, z.last().copied().unwrap_or(f64::NAN));
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &str {
+        "SpreadZScore"
+    }
+
+    fn window_length(&self) -> usize {
+        self.window
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -398,4 +560,67 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_rolling_regression_factor() {
+        use crate::asset::Asset;
+        use crate::pipeline::engine::{DataProvider, OHLCVBar};
+        use chrono::NaiveDate;
+        use std::sync::Arc;
+
+        /// Prices compounded from each asset's returns
+        struct Prices(HashMap<u64, Vec<f64>>);
+
+        impl DataProvider for Prices {
+            fn get_prices(&self, asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+                let prices = &self.0[&asset_id];
+                Ok(prices[prices.len().saturating_sub(lookback)..].to_vec())
+            }
+
+            fn get_volumes(&self, _asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+                Ok(vec![0.0; lookback])
+            }
+
+            fn get_ohlcv(&self, _asset_id: u64, _lookback: usize) -> Result<Vec<OHLCVBar>> {
+                Ok(Vec::new())
+            }
+
+            fn get_latest_price(&self, asset_id: u64) -> Result<f64> {
+                Ok(*self.0[&asset_id].last().unwrap())
+            }
+        }
+
+        let benchmark: Vec<f64> = (0..60).map(|t| 0.01 * (t as f64 * 0.7).sin()).collect();
+        let compound = |returns: Vec<f64>| -> Vec<f64> {
+            let mut price = 100.0;
+            std::iter::once(price)
+                .chain(returns.into_iter().map(|r| {
+                    price *= 1.0 + r;
+                    price
+                }))
+                .collect()
+        };
+        let provider = Prices(HashMap::from([
+            (0, compound(benchmark.clone())),
+            (1, compound(benchmark.iter().map(|r| 0.0005 + 2.0 * r).collect())),
+        ]));
+        let listed = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let assets = vec![Asset::equity(1, "A".to_string(), "NYSE".to_string(), listed)];
+        let context = PipelineContext::new(assets, Arc::new(provider), Utc::now());
+
+        let factor = RollingRegression::new(RegressionTarget::Asset(0), 20);
+        assert_eq!(factor.window_length(), 21);
+        let beta = Factor::compute(&factor, Utc::now(), &context).unwrap();
+        assert_relative_eq!(beta[&1], 2.0, epsilon = 1e-9);
+        let alpha = factor.clone().with_output(RegressionOutput::Alpha);
+        let alpha = Factor::compute(&alpha, Utc::now(), &context).unwrap();
+        assert_relative_eq!(alpha[&1], 0.0005, epsilon = 1e-9);
+
+        // Against a supplied series, where only the last 10 returns are known
+        let target = RegressionTarget::Returns(benchmark[50..].to_vec());
+        let fit = RollingRegression::new(target, 10).with_output(RegressionOutput::RSquared);
+        assert_relative_eq!(Factor::compute(&fit, Utc::now(), &context).unwrap()[&1], 1.0, epsilon = 1e-9);
+        let short = RollingRegression::new(RegressionTarget::Returns(benchmark[55..].to_vec()), 10);
+        assert!(Factor::compute(&short, Utc::now(), &context).unwrap()[&1].is_nan());
+    }
 }
@@ -381,6 +381,10 @@ impl Factor for AverageDollarVolume {
         "AverageDollarVolume"
     }
 
+    fn window_length(&self) -> usize {
+        self.window
+    }
+
     fn clone_box(&self) -> Box<dyn Factor> {
         Box::new(self.clone())
     }
@@ -0,0 +1,581 @@
+//! Vectorized rolling window kernels for factor computation
+//!
+//! The streaming factors in [`crate::pipeline::factors`] recompute each window
+//! from scratch on every update. The kernels here work on a whole series at
+//! once in O(n), independent of the window length:
+//!
+//! - Window sums are built from per-block prefix and suffix scans (blocks the
+//!   size of the window), so every window is the sum of one suffix and one
+//!   prefix. Partial sums never span more than one block, which keeps the
+//!   accuracy of a direct summation.
+//! - Combining the scans into means, deviations and ratios is element-wise and
+//!   runs four lanes at a time with the `simd` feature.
+//! - [`compute_columns`] and [`compute_many`] fan out over assets (and
+//!   kernels) with the `rayon` feature.
+//!
+//! Every kernel returns one value per input, with `NaN` until the window is
+//! full. A window containing a `NaN` input produces `NaN`, as the streaming
+//! factors do.
+
+/// Apply an element-wise expression over aligned slices
+///
+/// `lanes!((a => x, b => y) -> out, [c] expr)` evaluates `expr` for every
+/// index, binding `x = a[i]`, `y = b[i]` and writing `out[i]`. Identifiers in
+/// the brackets are scalar constants splatted across lanes. With the `simd`
+/// feature four elements are processed per step.
+macro_rules! lanes {
+    (($($input:expr => $arg:ident),+) -> $out:expr, [$($c:ident),*] $body:expr) => {{
+        let out: &mut [f64] = $out;
+        let len = out.len();
+        #[allow(unused_mut)]
+        let mut start = 0;
+
+        #[cfg(feature = "simd")]
+        {
+            use wide::f64x4;
+            $(let $c = f64x4::splat($c);)*
+            while start + 4 <= len {
+                let i = start;
+                $(let $arg = f64x4::from([
+                    $input[i],
+                    $input[i + 1],
+                    $input[i + 2],
+                    $input[i + 3],
+                ]);)+
+                out[i..i + 4].copy_from_slice(&($body).to_array());
+                start += 4;
+            }
+        }
+
+        for i in start..len {
+            $(let $arg = $input[i];)+
+            out[i] = $body;
+        }
+    }};
+}
+
+/// Rolling sum over `window` values
+pub fn rolling_sum(values: &[f64], window: usize) -> Vec<f64> {
+    let (sums, _) = masked_window_sums(values, window, |x| x);
+    sums
+}
+
+/// Rolling mean over `window` values
+pub fn rolling_mean(values: &[f64], window: usize) -> Vec<f64> {
+    let (sums, _) = masked_window_sums(values, window, |x| x);
+    let mut out = vec![f64::NAN; values.len()];
+    let scale = 1.0 / window as f64;
+    lanes!((sums => s) -> &mut out, [scale] s * scale);
+    out
+}
+
+/// Rolling standard deviation with `ddof` delta degrees of freedom
+///
+/// `ddof = 1` gives the sample standard deviation used by
+/// [`BollingerBands`](crate::pipeline::BollingerBands).
+pub fn rolling_std(values: &[f64], window: usize, ddof: usize) -> Vec<f64> {
+    let n = values.len();
+    if window <= ddof || n < window {
+        return vec![f64::NAN; n];
+    }
+
+    // Center on the first finite value so the sum of squares does not cancel
+    let shift = values.iter().copied().find(|x| x.is_finite()).unwrap_or(0.0);
+    let (sums, nan_counts) = masked_window_sums(values, window, |x| x - shift);
+    let squares = window_sums(
+        &values
+            .iter()
+            .map(|&x| if x.is_nan() { 0.0 } else { (x - shift) * (x - shift) })
+            .collect::<Vec<_>>(),
+        window,
+    );
+
+    let mut out = vec![f64::NAN; n];
+    let count = window as f64;
+    let dof = (window - ddof) as f64;
+    let zero = 0.0;
+    let first = window - 1;
+    lanes!(
+        (sums[first..] => s, squares[first..] => s2) -> &mut out[first..],
+        [count, dof, zero]
+        ((s2 - s * s / count) / dof).max(zero).sqrt()
+    );
+
+    apply_nan_mask(&mut out, &nan_counts, window);
+    out
+}
+
+/// Exponentially weighted moving average with `alpha = 2 / (span + 1)`
+///
+/// Seeded with the first value, matching
+/// [`ExponentialMovingAverage`](crate::pipeline::ExponentialMovingAverage).
+pub fn ewm_mean(values: &[f64], span: usize) -> Vec<f64> {
+    let alpha = 2.0 / (span as f64 + 1.0);
+    let mut out = Vec::with_capacity(values.len());
+    let mut current: Option<f64> = None;
+    for &value in values {
+        let ema = match current {
+            None => value,
+            Some(prev) => alpha * value + (1.0 - alpha) * prev,
+        };
+        current = Some(ema);
+        out.push(ema);
+    }
+    out
+}
+
+/// Relative strength index from simple averages of gains and losses
+///
+/// Matches [`RSI`](crate::pipeline::RSI): the first value is at index `period`,
+/// and a window with no losses is 100.
+pub fn rsi(values: &[f64], period: usize) -> Vec<f64> {
+    let n = values.len();
+    let mut out = vec![f64::NAN; n];
+    if period == 0 || n <= period {
+        return out;
+    }
+
+    let mut gains = Vec::with_capacity(n - 1);
+    let mut losses = Vec::with_capacity(n - 1);
+    for pair in values.windows(2) {
+        let change = pair[1] - pair[0];
+        gains.push(if change > 0.0 { change } else { 0.0 });
+        losses.push(if change < 0.0 { -change } else { 0.0 });
+    }
+
+    let gain_sums = window_sums(&gains, period);
+    let loss_sums = window_sums(&losses, period);
+
+    let hundred = 100.0;
+    let one = 1.0;
+    let first = period - 1;
+    lanes!(
+        (gain_sums[first..] => g, loss_sums[first..] => l) -> &mut out[period..],
+        [hundred, one]
+        hundred - hundred / (one + g / l)
+    );
+
+    // g / 0 is inf, which already yields 100; only 0 / 0 needs fixing up
+    for (i, value) in out.iter_mut().enumerate().skip(period) {
+        if loss_sums[i - 1] == 0.0 {
+            *value = 100.0;
+        }
+    }
+    out
+}
+
+/// Rolling middle, upper and lower Bollinger bands
+pub fn bollinger(values: &[f64], window: usize, num_std_dev: f64) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let middle = rolling_mean(values, window);
+    let std_dev = rolling_std(values, window, 1);
+
+    let mut upper = vec![f64::NAN; values.len()];
+    let mut lower = vec![f64::NAN; values.len()];
+    let k = num_std_dev;
+    lanes!((middle => m, std_dev => s) -> &mut upper, [k] m + k * s);
+    lanes!((middle => m, std_dev => s) -> &mut lower, [k] m - k * s);
+
+    (middle, upper, lower)
+}
+
+/// Fractional change over `periods` values
+pub fn pct_change(values: &[f64], periods: usize) -> Vec<f64> {
+    let n = values.len();
+    let mut out = vec![f64::NAN; n];
+    if periods == 0 || n <= periods {
+        return out;
+    }
+
+    let one = 1.0;
+    lanes!(
+        (values[periods..] => current, values[..n - periods] => previous) -> &mut out[periods..],
+        [one]
+        current / previous - one
+    );
+    out
+}
+
+/// Rolling least-squares regression of `y` on `x`
+///
+/// Returns the intercept (alpha), slope (beta) and R² of each window. A
+/// window where either series holds a NaN is NaN, as is every output of a
+/// window where `x` does not vary, and R² where `y` does not.
+pub fn rolling_regression(y: &[f64], x: &[f64], window: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let n = y.len().min(x.len());
+    let (mut alpha, mut beta, mut r_squared) = (vec![f64::NAN; n], vec![f64::NAN; n], vec![f64::NAN; n]);
+    if window < 2 || n < window {
+        return (alpha, beta, r_squared);
+    }
+
+    // A missing value in either series masks the pair, and both are centered
+    // on their first complete pair so the sums of squares do not cancel
+    let (y, x) = (&y[..n], &x[..n]);
+    let missing = |i: usize| y[i].is_nan() || x[i].is_nan();
+    let first = (0..n).find(|&i| !missing(i)).unwrap_or(0);
+    let (shift_y, shift_x) = (y[first], x[first]);
+    let ys: Vec<f64> = (0..n).map(|i| if missing(i) { f64::NAN } else { y[i] - shift_y }).collect();
+    let xs: Vec<f64> = (0..n).map(|i| if missing(i) { f64::NAN } else { x[i] - shift_x }).collect();
+    let (sum_y, _) = masked_window_sums(&ys, window, |v| v);
+    let (sum_x, _) = masked_window_sums(&xs, window, |v| v);
+    let products = |f: fn(f64, f64) -> f64| -> Vec<f64> {
+        window_sums(
+            &ys.iter().zip(&xs).map(|(&a, &b)| if a.is_nan() { 0.0 } else { f(a, b) }).collect::<Vec<_>>(),
+            window,
+        )
+    };
+    let (sum_xx, sum_yy, sum_xy) = (products(|_, b| b * b), products(|a, _| a * a), products(|a, b| a * b));
+
+    let count = window as f64;
+    for i in window - 1..n {
+        let (sx, sy) = (sum_x[i], sum_y[i]);
+        if sx.is_nan() || sy.is_nan() {
+            continue;
+        }
+        let var_x = sum_xx[i] - sx * sx / count;
+        let var_y = sum_yy[i] - sy * sy / count;
+        let cov = sum_xy[i] - sx * sy / count;
+        if var_x > 0.0 {
+            beta[i] = cov / var_x;
+            alpha[i] = (sy / count + shift_y) - beta[i] * (sx / count + shift_x);
+            r_squared[i] = if var_y > 0.0 { (cov * cov / (var_x * var_y)).min(1.0) } else { f64::NAN };
+        }
+    }
+    (alpha, beta, r_squared)
+}
+
+/// A rolling computation that can be applied to many series at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollingKernel {
+    Sum(usize),
+    Mean(usize),
+    /// Sample standard deviation
+    Std(usize),
+    Ema(usize),
+    Rsi(usize),
+    PctChange(usize),
+}
+
+impl RollingKernel {
+    /// Apply the kernel to one series
+    pub fn apply(&self, values: &[f64]) -> Vec<f64> {
+        match *self {
+            RollingKernel::Sum(window) => rolling_sum(values, window),
+            RollingKernel::Mean(window) => rolling_mean(values, window),
+            RollingKernel::Std(window) => rolling_std(values, window, 1),
+            RollingKernel::Ema(span) => ewm_mean(values, span),
+            RollingKernel::Rsi(period) => rsi(values, period),
+            RollingKernel::PctChange(periods) => pct_change(values, periods),
+        }
+    }
+
+    /// Number of leading values before the first output
+    pub fn warmup(&self) -> usize {
+        match *self {
+            RollingKernel::Sum(window)
+            | RollingKernel::Mean(window)
+            | RollingKernel::Std(window) => window.saturating_sub(1),
+            RollingKernel::Ema(_) => 0,
+            RollingKernel::Rsi(periods) | RollingKernel::PctChange(periods) => periods,
+        }
+    }
+}
+
+/// Apply a kernel to every asset's series
+///
+/// Runs across assets in parallel with the `rayon` feature.
+pub fn compute_columns(columns: &[Vec<f64>], kernel: RollingKernel) -> Vec<Vec<f64>> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        columns.par_iter().map(|column| kernel.apply(column)).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        columns.iter().map(|column| kernel.apply(column)).collect()
+    }
+}
+
+/// Apply several kernels to every asset's series
+///
+/// The result is indexed `[kernel][asset]`. With the `rayon` feature every
+/// (kernel, asset) pair is a separate task, so a few kernels over many assets
+/// and many kernels over a few assets both spread across the pool.
+pub fn compute_many(columns: &[Vec<f64>], kernels: &[RollingKernel]) -> Vec<Vec<Vec<f64>>> {
+    let tasks: Vec<(usize, usize)> = (0..kernels.len())
+        .flat_map(|k| (0..columns.len()).map(move |a| (k, a)))
+        .collect();
+
+    #[cfg(feature = "rayon")]
+    let results: Vec<Vec<f64>> = {
+        use rayon::prelude::*;
+        tasks
+            .par_iter()
+            .map(|&(k, a)| kernels[k].apply(&columns[a]))
+            .collect()
+    };
+
+    #[cfg(not(feature = "rayon"))]
+    let results: Vec<Vec<f64>> = tasks
+        .iter()
+        .map(|&(k, a)| kernels[k].apply(&columns[a]))
+        .collect();
+
+    let mut results = results.into_iter();
+    kernels
+        .iter()
+        .map(|_| results.by_ref().take(columns.len()).collect())
+        .collect()
+}
+
+/// Window sums of `transform(x)`, with NaN inputs masked out
+///
+/// Returns the sums (NaN where a window holds a NaN input or is not yet full)
+/// and the per-window NaN counts.
+fn masked_window_sums(
+    values: &[f64],
+    window: usize,
+    transform: impl Fn(f64) -> f64,
+) -> (Vec<f64>, Vec<f64>) {
+    let has_nan = values.iter().any(|x| x.is_nan());
+    let cleaned: Vec<f64> = values
+        .iter()
+        .map(|&x| if x.is_nan() { 0.0 } else { transform(x) })
+        .collect();
+    let mut sums = window_sums(&cleaned, window);
+
+    if !has_nan {
+        return (sums, Vec::new());
+    }
+
+    let flags: Vec<f64> = values.iter().map(|x| if x.is_nan() { 1.0 } else { 0.0 }).collect();
+    let nan_counts = window_sums(&flags, window);
+    apply_nan_mask(&mut sums, &nan_counts, window);
+    (sums, nan_counts)
+}
+
+/// Set outputs whose window held a NaN input back to NaN
+fn apply_nan_mask(out: &mut [f64], nan_counts: &[f64], window: usize) {
+    for (i, &count) in nan_counts.iter().enumerate().skip(window.saturating_sub(1)) {
+        if count > 0.0 {
+            out[i] = f64::NAN;
+        }
+    }
+}
+
+/// Sums of each `window` consecutive values, aligned to the window's last index
+fn window_sums(values: &[f64], window: usize) -> Vec<f64> {
+    let n = values.len();
+    let mut out = vec![f64::NAN; n];
+    if window == 0 || n < window {
+        return out;
+    }
+
+    // prefix[i]: sum from the start of i's block through i
+    // head[j]: sum from j through the end of j's block, or 0 when j starts a
+    // block (a window starting there lies entirely in the prefix)
+    let mut prefix = vec![0.0; n];
+    let mut head = vec![0.0; n];
+    for block in (0..n).step_by(window) {
+        let end = (block + window).min(n);
+
+        let mut acc = 0.0;
+        for i in block..end {
+            acc += values[i];
+            prefix[i] = acc;
+        }
+
+        let mut acc = 0.0;
+        for j in (block + 1..end).rev() {
+            acc += values[j];
+            head[j] = acc;
+        }
+    }
+
+    let first = window - 1;
+    lanes!(
+        (head[..n - first] => h, prefix[first..] => p) -> &mut out[first..],
+        []
+        h + p
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::factors::{BollingerBands, ExponentialMovingAverage, RSI};
+    use crate::pipeline::factors_statistical::{Beta, Correlation};
+    use approx::assert_relative_eq;
+
+    fn prices(n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| 100.0 + (i as f64 * 0.37).sin() * 5.0 + i as f64 * 0.05)
+            .collect()
+    }
+
+    fn naive_mean(values: &[f64], window: usize) -> Vec<f64> {
+        (0..values.len())
+            .map(|i| {
+                if i + 1 < window {
+                    f64::NAN
+                } else {
+                    values[i + 1 - window..=i].iter().sum::<f64>() / window as f64
+                }
+            })
+            .collect()
+    }
+
+    fn assert_series_eq(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            if e.is_nan() {
+                assert!(a.is_nan(), "expected NaN, got {}", a);
+            } else {
+                assert_relative_eq!(*a, *e, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rolling_sum_and_mean() {
+        let values = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        assert_series_eq(
+            &rolling_sum(&values, 3),
+            &[f64::NAN, f64::NAN, 6.0, 9.0, 12.0, 15.0, 18.0],
+        );
+
+        let values = prices(500);
+        for window in [1, 2, 3, 7, 20, 63, 252] {
+            assert_series_eq(&rolling_mean(&values, window), &naive_mean(&values, window));
+        }
+    }
+
+    #[test]
+    fn test_short_series() {
+        assert!(rolling_mean(&[1.0, 2.0], 3).iter().all(|x| x.is_nan()));
+        assert!(rolling_mean(&[], 3).is_empty());
+        assert!(rsi(&[1.0, 2.0], 2).iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn test_nan_only_poisons_its_windows() {
+        let values = vec![1.0, 2.0, f64::NAN, 4.0, 5.0, 6.0, 7.0];
+        let mean = rolling_mean(&values, 2);
+        assert!(mean[2].is_nan());
+        assert!(mean[3].is_nan());
+        assert_eq!(mean[4], 4.5);
+        assert_eq!(mean[6], 6.5);
+
+        let std_dev = rolling_std(&values, 2, 1);
+        assert!(std_dev[3].is_nan());
+        assert_relative_eq!(std_dev[5], 0.5f64.sqrt(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_bollinger_matches_streaming() {
+        let values = prices(300);
+        let (middle, upper, lower) = bollinger(&values, 20, 2.0);
+
+        let mut bands = BollingerBands::new(20, 2.0);
+        for (i, &value) in values.iter().enumerate() {
+            match bands.update(value) {
+                None => assert!(middle[i].is_nan()),
+                Some((m, u, l)) => {
+                    assert_relative_eq!(middle[i], m, epsilon = 1e-9);
+                    assert_relative_eq!(upper[i], u, epsilon = 1e-9);
+                    assert_relative_eq!(lower[i], l, epsilon = 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rsi_matches_streaming() {
+        let mut values = prices(200);
+        // A run without losses
+        for value in values.iter_mut().skip(150).take(20) {
+            *value = 200.0;
+        }
+
+        let mut streaming = RSI::new(14);
+        let actual = rsi(&values, 14);
+        for (a, &value) in actual.iter().zip(&values) {
+            match streaming.update(value) {
+                None => assert!(a.is_nan()),
+                Some(e) => assert_relative_eq!(*a, e, epsilon = 1e-9),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ewm_and_pct_change() {
+        let values = prices(50);
+        let mut ema = ExponentialMovingAverage::new(10);
+        let expected: Vec<f64> = values.iter().map(|&v| ema.update(v)).collect();
+        assert_eq!(ewm_mean(&values, 10), expected);
+
+        let change = pct_change(&[100.0, 110.0, 99.0], 1);
+        assert!(change[0].is_nan());
+        assert_relative_eq!(change[1], 0.1, epsilon = 1e-12);
+        assert_relative_eq!(change[2], -0.1, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_rolling_regression_matches_streaming() {
+        let x = pct_change(&prices(300), 1);
+        let y: Vec<f64> = x
+            .iter()
+            .enumerate()
+            .map(|(i, r)| 0.001 + 1.5 * r + (i as f64).cos() * 1e-3)
+            .collect();
+        let (alpha, beta, r_squared) = rolling_regression(&y, &x, 20);
+
+        let mut streaming_beta = Beta::new(20);
+        let mut streaming_corr = Correlation::new(20);
+        for i in 1..y.len() {
+            let b = streaming_beta.update(y[i], x[i]);
+            let c = streaming_corr.update(y[i], x[i]);
+            match (b, c) {
+                (Some(b), Some(c)) => {
+                    assert_relative_eq!(beta[i], b, epsilon = 1e-9);
+                    assert_relative_eq!(r_squared[i], c * c, epsilon = 1e-9);
+                    let mean = |s: &[f64]| s[i - 19..=i].iter().sum::<f64>() / 20.0;
+                    assert_relative_eq!(alpha[i], mean(&y) - b * mean(&x), epsilon = 1e-9);
+                }
+                _ => assert!(beta[i].is_nan()),
+            }
+        }
+
+        // An exact line, with a missing value poisoning only its windows
+        let x = vec![1.0, 2.0, 3.0, f64::NAN, 5.0, 6.0, 7.0];
+        let y: Vec<f64> = x.iter().map(|v| 2.0 * v - 1.0).collect();
+        let (alpha, beta, r_squared) = rolling_regression(&y, &x, 3);
+        assert_series_eq(&beta, &[f64::NAN, f64::NAN, 2.0, f64::NAN, f64::NAN, f64::NAN, 2.0]);
+        assert_relative_eq!(alpha[6], -1.0, epsilon = 1e-12);
+        assert_relative_eq!(r_squared[2], 1.0, epsilon = 1e-12);
+        assert!(rolling_regression(&[1.0, 2.0, 3.0], &[1.0, 1.0, 1.0], 3).1[2].is_nan());
+    }
+
+    #[test]
+    fn test_compute_many() {
+        let columns: Vec<Vec<f64>> = (0..5)
+            .map(|a| prices(100).iter().map(|p| p * (a + 1) as f64).collect())
+            .collect();
+        let kernels = [RollingKernel::Mean(10), RollingKernel::Rsi(14), RollingKernel::Std(5)];
+
+        let results = compute_many(&columns, &kernels);
+        assert_eq!(results.len(), 3);
+        for (k, kernel) in kernels.iter().enumerate() {
+            assert_eq!(results[k].len(), 5);
+            let bits = |series: &Vec<Vec<f64>>| -> Vec<Vec<u64>> {
+                series.iter().map(|c| c.iter().map(|x| x.to_bits()).collect()).collect()
+            };
+            assert_eq!(bits(&results[k]), bits(&compute_columns(&columns, *kernel)));
+            for column in &results[k] {
+                assert!(column[..kernel.warmup()].iter().all(|x| x.is_nan()));
+                assert!(!column[kernel.warmup()].is_nan());
+            }
+        }
+    }
+}
@@ -2,9 +2,11 @@
 
 pub mod classifiers; // Asset categorization
 pub mod composite;
+pub mod custom; // User-defined windowed factors
 pub mod domain; // NEW: P1 - Asset universe definitions
 pub mod engine;
 pub mod factors;
+pub mod factors_events; // Earnings and other event-date factors
 pub mod factors_fundamental; // NEW: Fundamental analysis factors
 pub mod factors_returns; // NEW: Returns-based factors
 pub mod factors_statistical; // NEW: Statistical factors
@@ -12,13 +14,24 @@ pub mod factors_technical; // NEW: Advanced technical indicators
 pub mod factors_volume; // NEW: Volume-based indicators
 pub mod filters; // Asset screening
 pub mod graph; // NEW: P1 - Computational dependency graph
+#[doc(hidden)]
+pub mod kernels; // Vectorized rolling window kernels, used by the built-in factors
+pub mod precompute; // Persistent pipeline output cache
+pub mod stateful; // Incrementally updated factors
 pub mod term; // NEW: P1 - Pipeline computation terms
 
-pub use classifiers::{Classifier as PipelineClassifier, Everything, Quantiles, Relabel};
+pub use classifiers::{
+    deciles, quantile_labels, quantiles, quartiles, quintiles, Classifier as PipelineClassifier,
+    ClassificationMap, Everything, FactorQuantiles, Industry, QuantileFilter, Quantiles, Relabel,
+    Sector,
+};
 pub use composite::{
-    AddFactors, DivideFactors, MultiplyFactors, RankFactor, SubtractFactors, TopNFilter,
-    ZScoreFactor,
+    demean_by, rank_by, zscore_by, AddFactors, DemeanFactor, DivideFactors, MultiplyFactors,
+    RankFactor, SubtractFactors, TopNFilter, ZScoreFactor,
 };
+pub use custom::{ArrayView2, CustomFactor, CustomFactorAdapter, FactorInput};
+pub use precompute::{PipelinePrecomputer, PrecomputedPipeline};
+pub use stateful::{IncrementalFactor, StatefulFactor};
 pub use engine::{
     Classifier, DataProvider, Factor, Filter, FactorOutput, OHLCVBar, Pipeline, PipelineContext,
     PipelineOutput,
@@ -45,12 +58,19 @@ pub use factors_volume::{
 };
 
 // Statistical factors
-pub use factors_statistical::{Alpha, Beta, Correlation, SharpeRatio, SortinoRatio};
+pub use factors_statistical::{
+    Alpha, Beta, Correlation, RegressionOutput, RegressionTarget, RollingRegression, SharpeRatio,
+    SortinoRatio, SpreadZScore,
+};
+
+// Event-date factors
+pub use factors_events::{BusinessDaysSincePreviousEarnings, BusinessDaysUntilNextEarnings};
 
 // Fundamental factors
 pub use factors_fundamental::{
-    CurrentRatio, DebtToEquity, DividendYield, EarningsYield, EVToEBITDA, PayoutRatio, PBRatio,
-    PERatio, PSRatio, QuickRatio, ROA, ROE, ROIC,
+    CurrentRatio, DebtToEquity, DividendYield, EarningsYield, EVToEBITDA, Fundamental,
+    FundamentalFactor, FundamentalMetric, PayoutRatio, PBRatio, PERatio, PSRatio, QuickRatio, ROA,
+    ROE, ROIC,
 };
 
 // Filters
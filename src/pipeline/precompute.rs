@@ -0,0 +1,388 @@
+//! Persistent pipeline output cache
+//!
+//! Factor computation usually dominates a pipeline backtest, yet while a
+//! strategy's trading logic is being iterated on its pipeline rarely changes.
+//! [`PipelinePrecomputer`] runs a pipeline over a range of sessions once and
+//! writes every output to a Parquet file named by the pipeline's
+//! [`Pipeline::graph_hash`]. Later runs of the same pipeline load that file
+//! instead of recomputing; changing the pipeline changes the hash, so stale
+//! outputs are never picked up.
+//!
+//! ```ignore
+//! let precomputer = PipelinePrecomputer::new("cache/pipelines");
+//! let outputs = precomputer.load_or_precompute(&pipeline, &sessions, |session| {
+//!     Ok(bundle.provider_at(session))
+//! })?;
+//! algo.update_pipeline_output("factors", outputs.factor_table(session));
+//! ```
+
+use super::engine::{DataProvider, Pipeline, PipelineOutput};
+use crate::error::{Result, ZiplineError};
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Pipeline outputs for a range of sessions
+#[derive(Debug, Clone, Default)]
+pub struct PrecomputedPipeline {
+    graph_hash: u64,
+    outputs: BTreeMap<DateTime<Utc>, PipelineOutput>,
+}
+
+impl PrecomputedPipeline {
+    /// Hash of the pipeline these outputs belong to
+    pub fn graph_hash(&self) -> u64 {
+        self.graph_hash
+    }
+
+    /// Output computed at `timestamp`
+    pub fn output(&self, timestamp: DateTime<Utc>) -> Option<&PipelineOutput> {
+        self.outputs.get(&timestamp)
+    }
+
+    /// Most recent output at or before `timestamp`
+    pub fn latest(&self, timestamp: DateTime<Utc>) -> Option<&PipelineOutput> {
+        self.outputs.range(..=timestamp).next_back().map(|(_, output)| output)
+    }
+
+    /// Timestamps with an output, in order
+    pub fn timestamps(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        self.outputs.keys().copied()
+    }
+
+    /// Whether every timestamp in `sessions` has an output
+    pub fn covers(&self, sessions: &[DateTime<Utc>]) -> bool {
+        sessions.iter().all(|session| self.outputs.contains_key(session))
+    }
+
+    /// Number of sessions with an output
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Whether there are no outputs
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// Factor values at `timestamp` by asset, as taken by
+    /// [`TradingAlgorithm::update_pipeline_output`](crate::algorithm::TradingAlgorithm::update_pipeline_output)
+    pub fn factor_table(&self, timestamp: DateTime<Utc>) -> HashMap<u64, HashMap<String, f64>> {
+        let mut table: HashMap<u64, HashMap<String, f64>> = HashMap::new();
+        if let Some(output) = self.output(timestamp) {
+            for (factor, values) in &output.factors {
+                for (&asset_id, &value) in values {
+                    table.entry(asset_id).or_default().insert(factor.clone(), value);
+                }
+            }
+        }
+        table
+    }
+
+    /// Write the outputs to a Parquet file in long format
+    ///
+    /// One row per timestamp, term and asset: factor values in `value`,
+    /// filter results as 1.0/0.0 in `value`, classifier labels in `label`.
+    pub fn write_parquet(&self, path: &Path) -> Result<()> {
+        let mut timestamps = Vec::new();
+        let mut kinds = Vec::new();
+        let mut terms = Vec::new();
+        let mut assets = Vec::new();
+        let mut values: Vec<Option<f64>> = Vec::new();
+        let mut labels: Vec<Option<String>> = Vec::new();
+
+        for (timestamp, output) in &self.outputs {
+            let mut push = |kind: &'static str, term: &str, asset_id: u64, value, label| {
+                timestamps.push(timestamp.timestamp_millis());
+                kinds.push(kind);
+                terms.push(term.to_string());
+                assets.push(asset_id);
+                values.push(value);
+                labels.push(label);
+            };
+            for (term, values) in &output.factors {
+                for (&asset_id, &value) in values {
+                    push("factor", term, asset_id, Some(value), None);
+                }
+            }
+            for (term, results) in &output.filters {
+                for (&asset_id, &passed) in results {
+                    push("filter", term, asset_id, Some(if passed { 1.0 } else { 0.0 }), None);
+                }
+            }
+            for (term, classes) in &output.classifiers {
+                for (&asset_id, label) in classes {
+                    push("classifier", term, asset_id, None, Some(label.clone()));
+                }
+            }
+        }
+
+        let mut frame = DataFrame::new(vec![
+            Series::new("timestamp", timestamps),
+            Series::new("kind", kinds),
+            Series::new("term", terms),
+            Series::new("asset_id", assets),
+            Series::new("value", values),
+            Series::new("label", labels),
+        ])
+        .map_err(|e| ZiplineError::DataError(format!("Failed to build pipeline frame: {}", e)))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        ParquetWriter::new(std::fs::File::create(path)?)
+            .finish(&mut frame)
+            .map_err(|e| ZiplineError::DataError(format!("Failed to write Parquet: {}", e)))?;
+        Ok(())
+    }
+
+    /// Read outputs written by [`PrecomputedPipeline::write_parquet`]
+    pub fn read_parquet(path: &Path, graph_hash: u64) -> Result<Self> {
+        let bad = |e: PolarsError| {
+            ZiplineError::DataError(format!("Bad pipeline cache {}: {}", path.display(), e))
+        };
+        let frame = ParquetReader::new(std::fs::File::open(path)?)
+            .finish()
+            .map_err(bad)?;
+
+        let timestamps = frame.column("timestamp").and_then(|c| c.i64()).map_err(bad)?;
+        let kinds = frame.column("kind").and_then(|c| c.str()).map_err(bad)?;
+        let terms = frame.column("term").and_then(|c| c.str()).map_err(bad)?;
+        let assets = frame.column("asset_id").and_then(|c| c.u64()).map_err(bad)?;
+        let values = frame.column("value").and_then(|c| c.f64()).map_err(bad)?;
+        let labels = frame.column("label").and_then(|c| c.str()).map_err(bad)?;
+
+        let mut outputs: BTreeMap<DateTime<Utc>, PipelineOutput> = BTreeMap::new();
+        let rows = timestamps
+            .into_iter()
+            .zip(kinds)
+            .zip(terms)
+            .zip(assets)
+            .zip(values)
+            .zip(labels);
+        for (((((millis, kind), term), asset_id), value), label) in rows {
+            let (Some(millis), Some(kind), Some(term), Some(asset_id)) = (millis, kind, term, asset_id)
+            else {
+                return Err(ZiplineError::DataError(format!(
+                    "Pipeline cache {} has a row with missing keys",
+                    path.display()
+                )));
+            };
+            let timestamp = DateTime::from_timestamp_millis(millis).ok_or_else(|| {
+                ZiplineError::DataError(format!("Bad timestamp {} in {}", millis, path.display()))
+            })?;
+            let output = outputs.entry(timestamp).or_insert_with(|| PipelineOutput {
+                timestamp,
+                factors: HashMap::new(),
+                filters: HashMap::new(),
+                classifiers: HashMap::new(),
+            });
+
+            match (kind, value, label) {
+                ("factor", Some(value), _) => {
+                    output.factors.entry(term.to_string()).or_default().insert(asset_id, value);
+                }
+                ("filter", Some(value), _) => {
+                    output.filters.entry(term.to_string()).or_default().insert(asset_id, value != 0.0);
+                }
+                ("classifier", _, Some(label)) => {
+                    output
+                        .classifiers
+                        .entry(term.to_string())
+                        .or_default()
+                        .insert(asset_id, label.to_string());
+                }
+                _ => {
+                    return Err(ZiplineError::DataError(format!(
+                        "Pipeline cache {} has a malformed {} row for {}",
+                        path.display(),
+                        kind,
+                        term
+                    )))
+                }
+            }
+        }
+
+        Ok(Self { graph_hash, outputs })
+    }
+}
+
+/// Runs pipelines ahead of time and caches their outputs on disk
+#[derive(Debug, Clone)]
+pub struct PipelinePrecomputer {
+    cache_dir: PathBuf,
+}
+
+impl PipelinePrecomputer {
+    /// Cache outputs under `cache_dir`
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+        }
+    }
+
+    /// File holding `pipeline`'s outputs
+    pub fn cache_path(&self, pipeline: &Pipeline) -> PathBuf {
+        self.cache_dir
+            .join(format!("pipeline-{:016x}.parquet", pipeline.graph_hash()))
+    }
+
+    /// Run `pipeline` on every session and cache the outputs
+    ///
+    /// `provider` supplies the point-in-time data for each session. Any
+    /// outputs already cached for the pipeline are replaced.
+    pub fn precompute<F>(
+        &self,
+        pipeline: &Pipeline,
+        sessions: &[DateTime<Utc>],
+        mut provider: F,
+    ) -> Result<PrecomputedPipeline>
+    where
+        F: FnMut(DateTime<Utc>) -> Result<Arc<dyn DataProvider>>,
+    {
+        let mut outputs = BTreeMap::new();
+        for &session in sessions {
+            outputs.insert(session, pipeline.run(session, provider(session)?)?);
+        }
+
+        let precomputed = PrecomputedPipeline {
+            graph_hash: pipeline.graph_hash(),
+            outputs,
+        };
+        precomputed.write_parquet(&self.cache_path(pipeline))?;
+        Ok(precomputed)
+    }
+
+    /// Cached outputs for `pipeline`, if any
+    pub fn load(&self, pipeline: &Pipeline) -> Result<Option<PrecomputedPipeline>> {
+        let path = self.cache_path(pipeline);
+        if !path.exists() {
+            return Ok(None);
+        }
+        PrecomputedPipeline::read_parquet(&path, pipeline.graph_hash()).map(Some)
+    }
+
+    /// Cached outputs for `pipeline` if they cover `sessions`, else precompute them
+    pub fn load_or_precompute<F>(
+        &self,
+        pipeline: &Pipeline,
+        sessions: &[DateTime<Utc>],
+        provider: F,
+    ) -> Result<PrecomputedPipeline>
+    where
+        F: FnMut(DateTime<Utc>) -> Result<Arc<dyn DataProvider>>,
+    {
+        match self.load(pipeline)? {
+            Some(cached) if cached.covers(sessions) => Ok(cached),
+            _ => self.precompute(pipeline, sessions, provider),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::pipeline::classifiers::quartiles;
+    use crate::pipeline::engine::{Factor, FactorOutput, OHLCVBar, PipelineContext};
+    use chrono::{Duration, NaiveDate, TimeZone};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Latest price is the session's day of month
+    struct DayProvider(f64);
+
+    impl DataProvider for DayProvider {
+        fn get_prices(&self, _asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(vec![self.0; lookback])
+        }
+
+        fn get_volumes(&self, _asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(vec![0.0; lookback])
+        }
+
+        fn get_ohlcv(&self, _asset_id: u64, _lookback: usize) -> Result<Vec<OHLCVBar>> {
+            Ok(Vec::new())
+        }
+
+        fn get_latest_price(&self, _asset_id: u64) -> Result<f64> {
+            Ok(self.0)
+        }
+    }
+
+    /// Latest price times asset id, counting how often it runs
+    #[derive(Clone)]
+    struct Scaled(Arc<AtomicUsize>);
+
+    impl Factor for Scaled {
+        fn compute(&self, _timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            context
+                .assets()
+                .iter()
+                .map(|asset| {
+                    let price = context.data_provider().get_latest_price(asset.id)?;
+                    Ok((asset.id, price * asset.id as f64))
+                })
+                .collect()
+        }
+
+        fn name(&self) -> &str {
+            "Scaled"
+        }
+
+        fn clone_box(&self) -> Box<dyn Factor> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_precompute_then_load() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .set_universe(
+                (1..=4)
+                    .map(|id| Asset::equity(id, format!("A{}", id), "NYSE".to_string(), listed))
+                    .collect(),
+            )
+            .add_factor("scaled".to_string(), Box::new(Scaled(runs.clone())))
+            .add_classifier("quartile".to_string(), Box::new(quartiles("scaled")))
+            .add_filter("top".to_string(), Box::new(quartiles("scaled").eq(3)));
+
+        let first = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let sessions: Vec<_> = (0..3).map(|d| first + Duration::days(d)).collect();
+        let provider = |session: DateTime<Utc>| -> Result<Arc<dyn DataProvider>> {
+            Ok(Arc::new(DayProvider(session.format("%d").to_string().parse().unwrap())))
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let precomputer = PipelinePrecomputer::new(dir.path());
+        assert!(precomputer.load(&pipeline).unwrap().is_none());
+
+        let computed = precomputer.load_or_precompute(&pipeline, &sessions, provider).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert!(precomputer.cache_path(&pipeline).exists());
+
+        let loaded = precomputer.load_or_precompute(&pipeline, &sessions, provider).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.graph_hash(), computed.graph_hash());
+
+        let last = loaded.output(sessions[2]).unwrap();
+        assert_eq!(last.get_factor_value("scaled", 4), Some(16.0));
+        assert_eq!(last.get_classifier_result("quartile", 4), Some("3"));
+        assert_eq!(last.get_filter_result("top", 4), Some(true));
+        assert_eq!(last.get_filter_result("top", 1), Some(false));
+        assert_eq!(loaded.factor_table(sessions[0])[&2]["scaled"], 4.0);
+        assert!(loaded.latest(sessions[2] + Duration::hours(1)).is_some());
+
+        // A longer range is not covered and is recomputed
+        let more: Vec<_> = (0..4).map(|d| first + Duration::days(d)).collect();
+        precomputer.load_or_precompute(&pipeline, &more, provider).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 7);
+    }
+}
@@ -0,0 +1,306 @@
+//! Incrementally updated pipeline factors
+//!
+//! A pipeline normally recomputes every factor from its full window each
+//! session, costing O(assets × window) per day. Moving-average-type factors
+//! can instead fold in one new bar at a time. A [`StatefulFactor`] describes
+//! such an update; [`IncrementalFactor`] runs it in the pipeline, seeding each
+//! asset's state from a full window once and afterwards reading only the
+//! latest bars, so each session costs O(assets).
+//!
+//! If an asset's previous bar is not among the latest bars (a gap, or the
+//! first session it is seen), its state is reseeded from a full window.
+//!
+//! ```ignore
+//! pipeline.add_stateful_factor("sma_20".to_string(), SimpleMovingAverage::new(20));
+//! ```
+
+use super::engine::{Factor, FactorOutput, OHLCVBar, Pipeline, PipelineContext};
+use super::factors::{AverageTrueRange, ExponentialMovingAverage, SimpleMovingAverage};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A factor whose value can be updated one bar at a time
+pub trait StatefulFactor: Clone + Send + Sync {
+    /// Bars used to seed the state
+    fn window_length(&self) -> usize;
+
+    /// Fold in the next bar, returning the current value if there is one
+    fn update_bar(&mut self, bar: &OHLCVBar) -> Option<f64>;
+
+    /// Get factor name
+    fn name(&self) -> &str;
+}
+
+impl StatefulFactor for SimpleMovingAverage {
+    fn window_length(&self) -> usize {
+        self.window()
+    }
+
+    fn update_bar(&mut self, bar: &OHLCVBar) -> Option<f64> {
+        self.update(bar.close)
+    }
+
+    fn name(&self) -> &str {
+        "SimpleMovingAverage"
+    }
+}
+
+/// Seeded from `span` bars; afterwards it carries all history since seeding
+impl StatefulFactor for ExponentialMovingAverage {
+    fn window_length(&self) -> usize {
+        self.span()
+    }
+
+    fn update_bar(&mut self, bar: &OHLCVBar) -> Option<f64> {
+        Some(self.update(bar.close))
+    }
+
+    fn name(&self) -> &str {
+        "ExponentialMovingAverage"
+    }
+}
+
+impl StatefulFactor for AverageTrueRange {
+    fn window_length(&self) -> usize {
+        // One extra bar for the first true range's previous close
+        self.period() + 1
+    }
+
+    fn update_bar(&mut self, bar: &OHLCVBar) -> Option<f64> {
+        self.update(bar.high, bar.low, bar.close)
+    }
+
+    fn name(&self) -> &str {
+        "AverageTrueRange"
+    }
+}
+
+/// One asset's state and the last bar folded into it
+#[derive(Clone)]
+struct AssetState<F> {
+    factor: F,
+    last_bar: DateTime<Utc>,
+    value: Option<f64>,
+}
+
+/// Runs a [`StatefulFactor`] as a pipeline [`Factor`], keeping state per asset
+pub struct IncrementalFactor<F: StatefulFactor> {
+    name: String,
+    template: F,
+    states: Arc<Mutex<HashMap<u64, AssetState<F>>>>,
+}
+
+impl<F: StatefulFactor> IncrementalFactor<F> {
+    pub fn new(factor: F) -> Self {
+        Self {
+            name: factor.name().to_string(),
+            template: factor,
+            states: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Drop all state, so every asset is reseeded on the next run
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, AssetState<F>>> {
+        // State is only ever replaced whole, so a poisoned lock is still consistent
+        self.states.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn seed(&self, bars: &[OHLCVBar]) -> Option<AssetState<F>> {
+        let mut factor = self.template.clone();
+        let mut value = None;
+        for bar in bars {
+            value = factor.update_bar(bar);
+        }
+        bars.last().map(|last| AssetState {
+            factor,
+            last_bar: last.timestamp,
+            value,
+        })
+    }
+}
+
+impl<F: StatefulFactor> Clone for IncrementalFactor<F> {
+    /// Clones start without state and seed on their first run
+    fn clone(&self) -> Self {
+        Self::new(self.template.clone())
+    }
+}
+
+impl<F: StatefulFactor + 'static> Factor for IncrementalFactor<F> {
+    fn compute(&self, _timestamp: DateTime<Utc>, context: &PipelineContext) -> Result<FactorOutput> {
+        let provider = context.data_provider();
+        let mut states = self.lock();
+        let mut output = FactorOutput::new();
+
+        for asset in context.assets() {
+            // The previous bar plus today's is enough to advance a daily state
+            let latest = provider.get_ohlcv(asset.id, 2)?;
+            let resume = states.get(&asset.id).and_then(|state| {
+                latest
+                    .iter()
+                    .position(|bar| bar.timestamp == state.last_bar)
+                    .map(|seen| seen + 1)
+            });
+
+            match resume {
+                Some(next) => {
+                    let state = states.get_mut(&asset.id).expect("state was found above");
+                    for bar in &latest[next..] {
+                        state.value = state.factor.update_bar(bar);
+                        state.last_bar = bar.timestamp;
+                    }
+                }
+                None => {
+                    let window = provider.get_ohlcv(asset.id, self.template.window_length())?;
+                    match self.seed(&window) {
+                        Some(state) => {
+                            states.insert(asset.id, state);
+                        }
+                        None => {
+                            states.remove(&asset.id);
+                        }
+                    }
+                }
+            }
+
+            let value = states.get(&asset.id).and_then(|state| state.value);
+            output.insert(asset.id, value.unwrap_or(f64::NAN));
+        }
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn window_length(&self) -> usize {
+        self.template.window_length()
+    }
+
+    fn clone_box(&self) -> Box<dyn Factor> {
+        Box::new(self.clone())
+    }
+}
+
+impl Pipeline {
+    /// Add a factor updated incrementally from session to session
+    pub fn add_stateful_factor<F: StatefulFactor + 'static>(&mut self, name: String, factor: F) -> &mut Self {
+        self.add_factor(name, Box::new(IncrementalFactor::new(factor)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::pipeline::engine::DataProvider;
+    use chrono::{Duration, NaiveDate, TimeZone};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Daily bars up to a movable "today", counting bars served
+    struct Bars {
+        closes: Vec<f64>,
+        today: AtomicUsize,
+        served: AtomicUsize,
+    }
+
+    impl Bars {
+        fn bar(&self, index: usize) -> OHLCVBar {
+            let close = self.closes[index];
+            OHLCVBar {
+                timestamp: Utc.with_ymd_and_hms(2024, 1, 1, 21, 0, 0).unwrap() + Duration::days(index as i64),
+                open: close,
+                high: close + 1.0,
+                low: close - 1.0,
+                close,
+                volume: 1000.0,
+            }
+        }
+    }
+
+    impl DataProvider for Bars {
+        fn get_prices(&self, asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(self.get_ohlcv(asset_id, lookback)?.iter().map(|b| b.close).collect())
+        }
+
+        fn get_volumes(&self, asset_id: u64, lookback: usize) -> Result<Vec<f64>> {
+            Ok(self.get_ohlcv(asset_id, lookback)?.iter().map(|b| b.volume).collect())
+        }
+
+        fn get_ohlcv(&self, _asset_id: u64, lookback: usize) -> Result<Vec<OHLCVBar>> {
+            let end = self.today.load(Ordering::SeqCst) + 1;
+            let start = end.saturating_sub(lookback);
+            self.served.fetch_add(end - start, Ordering::SeqCst);
+            Ok((start..end).map(|i| self.bar(i)).collect())
+        }
+
+        fn get_latest_price(&self, _asset_id: u64) -> Result<f64> {
+            Ok(self.closes[self.today.load(Ordering::SeqCst)])
+        }
+    }
+
+    #[test]
+    fn test_incremental_matches_full_recompute() {
+        let closes: Vec<f64> = (0..40).map(|i| 100.0 + (i as f64 * 0.7).sin() * 5.0).collect();
+        let bars = Arc::new(Bars {
+            closes: closes.clone(),
+            today: AtomicUsize::new(25),
+            served: AtomicUsize::new(0),
+        });
+
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .set_universe(vec![Asset::equity(1, "A".to_string(), "NYSE".to_string(), listed)])
+            .add_stateful_factor("sma".to_string(), SimpleMovingAverage::new(20))
+            .add_stateful_factor("atr".to_string(), AverageTrueRange::new(14));
+        assert_eq!(pipeline.max_window_length(), 20);
+
+        let provider: Arc<dyn DataProvider> = bars.clone();
+        for today in 25..40 {
+            bars.today.store(today, Ordering::SeqCst);
+            bars.served.store(0, Ordering::SeqCst);
+            let timestamp = bars.bar(today).timestamp;
+            let output = pipeline.run(timestamp, provider.clone()).unwrap();
+
+            let full = SimpleMovingAverage::compute(20, &closes[..=today]);
+            let sma = output.get_factor_value("sma", 1).unwrap();
+            assert!((sma - full[today].unwrap()).abs() < 1e-9);
+
+            let mut atr = AverageTrueRange::new(14);
+            let mut expected = None;
+            for i in today - 14..=today {
+                let bar = bars.bar(i);
+                expected = atr.update(bar.high, bar.low, bar.close);
+            }
+            assert!((output.get_factor_value("atr", 1).unwrap() - expected.unwrap()).abs() < 1e-9);
+
+            // After seeding, each factor reads two bars per asset
+            if today > 25 {
+                assert_eq!(bars.served.load(Ordering::SeqCst), 4);
+            }
+        }
+
+        // A gap of several sessions reseeds
+        bars.today.store(39, Ordering::SeqCst);
+        let reseeded = IncrementalFactor::new(SimpleMovingAverage::new(20));
+        let context = PipelineContext::new(
+            vec![Asset::equity(1, "A".to_string(), "NYSE".to_string(), listed)],
+            provider.clone(),
+            bars.bar(39).timestamp,
+        );
+        bars.today.store(30, Ordering::SeqCst);
+        reseeded.compute(bars.bar(30).timestamp, &context).unwrap();
+        bars.today.store(39, Ordering::SeqCst);
+        let value = reseeded.compute(bars.bar(39).timestamp, &context).unwrap()[&1];
+        let full = SimpleMovingAverage::compute(20, &closes);
+        assert!((value - full[39].unwrap()).abs() < 1e-9);
+    }
+}
@@ -96,7 +96,9 @@ pub enum NDim {
 }
 
 /// Trait for all pipeline terms
-pub trait Term: Send + Sync {
+///
+/// Sealed: the graph only knows how to schedule the term types defined here.
+pub trait Term: crate::sealed::Sealed + Send + Sync {
     /// Unique identifier for this term
     fn id(&self) -> TermId;
 
@@ -172,6 +174,8 @@ impl BaseTerm {
     }
 }
 
+impl crate::sealed::Sealed for BaseTerm {}
+
 impl Term for BaseTerm {
     fn id(&self) -> TermId {
         self.id
@@ -484,6 +488,8 @@ impl BinaryOpTerm {
     }
 }
 
+impl crate::sealed::Sealed for BinaryOpTerm {}
+
 impl Term for BinaryOpTerm {
     fn id(&self) -> TermId {
         self.base.id()
@@ -539,6 +545,8 @@ impl UnaryOpTerm {
     }
 }
 
+impl crate::sealed::Sealed for UnaryOpTerm {}
+
 impl Term for UnaryOpTerm {
     fn id(&self) -> TermId {
         self.base.id()
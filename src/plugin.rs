@@ -0,0 +1,234 @@
+//! Strategies compiled as dynamic libraries
+//!
+//! A strategy crate built as a `cdylib` against this crate exports its
+//! algorithm with [`export_algorithm!`]. The CLI (or any host built with the
+//! `plugins` feature) loads the library with [`PluginAlgorithm::load`] and
+//! runs it through the engine like any other [`Algorithm`].
+//!
+//! ```ignore
+//! // In the strategy crate, with `crate-type = ["cdylib"]`
+//! use rusty_zipline::prelude::*;
+//!
+//! struct MyStrategy { /* ... */ }
+//! impl Algorithm for MyStrategy { /* ... */ }
+//!
+//! rusty_zipline::export_algorithm!(MyStrategy::new());
+//! ```
+//!
+//! A plugin can also bring its own slippage, commission and control models.
+//! They are registered into the host's
+//! [`ModelRegistry`](crate::finance::ModelRegistry) when the library is
+//! loaded, so config files can name them like built-ins:
+//!
+//! ```ignore
+//! rusty_zipline::export_algorithm!(MyStrategy::new(), models {
+//!     slippage "spread" => |p| Ok(Arc::new(HalfSpread::new(p.require("spread")?))),
+//! });
+//! ```
+//!
+//! ```bash
+//! cargo build --release
+//! rusty-zipline run -f target/release/libmy_strategy.so -b quandl
+//! ```
+//!
+//! Algorithms cross the library boundary as Rust trait objects, which have
+//! no stable ABI. The plugin must be built with the same compiler and the
+//! same version of this crate as the host; the crate version is checked on
+//! load, the compiler is not.
+//!
+//! Strategies that are not trusted to run in-process can be compiled to
+//! WebAssembly instead and run in the [`wasm`] sandbox (`wasm` feature).
+
+#[cfg(feature = "plugins")]
+use crate::algorithm::Context;
+use crate::algorithm::Algorithm;
+#[cfg(feature = "plugins")]
+use crate::data::BarData;
+#[cfg(feature = "plugins")]
+use crate::error::{Result, ZiplineError};
+#[cfg(feature = "plugins")]
+use crate::finance::ModelRegistry;
+#[cfg(feature = "plugins")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// Version tag a plugin reports from `zipline_plugin_abi`, NUL-terminated
+pub const PLUGIN_ABI: &str = concat!("rusty_zipline ", env!("CARGO_PKG_VERSION"), "\0");
+
+/// Box an algorithm for `create_algorithm`
+///
+/// Used by [`export_algorithm!`]; the host takes ownership of the returned
+/// pointer.
+#[doc(hidden)]
+pub fn into_raw(algorithm: Box<dyn Algorithm>) -> *mut std::ffi::c_void {
+    Box::into_raw(Box::new(algorithm)).cast()
+}
+
+/// Export an algorithm from a `cdylib` strategy crate
+///
+/// Generates the `create_algorithm` entry point, which builds the algorithm
+/// from the given expression, and the `zipline_plugin_abi` version tag.
+///
+/// Models listed after `models`, in the syntax of
+/// [`register_models!`](crate::register_models), are exported through a
+/// `zipline_register_models` entry point that adds them to the registry the
+/// host passes in. A plugin's own copy of the process-wide registry is never
+/// seen by the host, so models must be exported this way.
+#[macro_export]
+macro_rules! export_algorithm {
+    ($constructor:expr, models { $($kind:ident $model:literal => $factory:expr),* $(,)? }) => {
+        $crate::export_algorithm!($constructor);
+
+        /// # Safety
+        ///
+        /// `registry` must point to a `ModelRegistry` of the same crate version.
+        #[no_mangle]
+        pub unsafe extern "C" fn zipline_register_models(registry: *mut ::std::ffi::c_void) {
+            let registry = &mut *registry.cast::<$crate::finance::ModelRegistry>();
+            $($crate::register_models!(@register registry, $kind, $model, $factory);)*
+        }
+    };
+    ($constructor:expr) => {
+        #[no_mangle]
+        pub extern "C" fn zipline_plugin_abi() -> *const ::std::os::raw::c_char {
+            $crate::plugin::PLUGIN_ABI.as_ptr().cast()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn create_algorithm() -> *mut ::std::ffi::c_void {
+            $crate::plugin::into_raw(::std::boxed::Box::new($constructor))
+        }
+    };
+}
+
+/// An algorithm loaded from a dynamic library
+#[cfg(feature = "plugins")]
+pub struct PluginAlgorithm {
+    // Declared before the library so it is dropped while its code is loaded
+    algorithm: Box<dyn Algorithm>,
+    _library: libloading::Library,
+    path: PathBuf,
+}
+
+#[cfg(feature = "plugins")]
+impl PluginAlgorithm {
+    /// Load a strategy library and create its algorithm
+    pub fn load(path: &Path) -> Result<Self> {
+        let failed = |reason: String| ZiplineError::PluginLoad {
+            path: path.display().to_string(),
+            reason,
+        };
+
+        // SAFETY: loading runs the library's initializers, and the symbols are
+        // trusted to have the signatures `export_algorithm!` gives them.
+        unsafe {
+            let library = libloading::Library::new(path).map_err(|e| failed(e.to_string()))?;
+
+            let abi = library
+                .get::<unsafe extern "C" fn() -> *const std::os::raw::c_char>(b"zipline_plugin_abi\0")
+                .map_err(|_| failed("not a strategy plugin: no zipline_plugin_abi".to_string()))?;
+            let abi = std::ffi::CStr::from_ptr(abi()).to_string_lossy().into_owned();
+            let expected = PLUGIN_ABI.trim_end_matches('\0');
+            if abi != expected {
+                return Err(failed(format!("built against {}, this is {}", abi, expected)));
+            }
+
+            if let Ok(register) =
+                library.get::<unsafe extern "C" fn(*mut std::ffi::c_void)>(b"zipline_register_models\0")
+            {
+                ModelRegistry::register_global(|registry| {
+                    register((registry as *mut ModelRegistry).cast());
+                });
+                // The registered factories run the library's code, so it must
+                // stay loaded after this handle is dropped
+                std::mem::forget(libloading::Library::new(path).map_err(|e| failed(e.to_string()))?);
+            }
+
+            let create = library
+                .get::<unsafe extern "C" fn() -> *mut std::ffi::c_void>(b"create_algorithm\0")
+                .map_err(|e| failed(e.to_string()))?;
+            let raw = create();
+            if raw.is_null() {
+                return Err(failed("create_algorithm returned null".to_string()));
+            }
+            let algorithm = *Box::from_raw(raw.cast::<Box<dyn Algorithm>>());
+
+            Ok(Self {
+                algorithm,
+                _library: library,
+                path: path.to_path_buf(),
+            })
+        }
+    }
+
+    /// Library the algorithm was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(feature = "plugins")]
+impl Algorithm for PluginAlgorithm {
+    fn initialize(&mut self, context: &mut Context) {
+        self.algorithm.initialize(context)
+    }
+
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.algorithm.handle_data(context, data)
+    }
+
+    fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.algorithm.before_trading_start(context, data)
+    }
+
+    fn analyze(&mut self, context: &Context) -> Result<()> {
+        self.algorithm.analyze(context)
+    }
+
+    fn warm_up_bars(&self) -> usize {
+        self.algorithm.warm_up_bars()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm::{Algorithm, BuyAndHold};
+    use crate::asset::Asset;
+    use chrono::NaiveDate;
+
+    fn strategy() -> BuyAndHold {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        BuyAndHold::new(Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed))
+    }
+
+    crate::export_algorithm!(strategy(), models {
+        commission "plugin_test_flat" => |p| Ok(std::sync::Arc::new(crate::finance::PerTrade::new(p.f64_or("cost", 1.0)?))),
+    });
+
+    #[test]
+    fn test_exported_entry_points() {
+        let abi = unsafe { std::ffi::CStr::from_ptr(zipline_plugin_abi()) };
+        assert_eq!(abi.to_str().unwrap(), super::PLUGIN_ABI.trim_end_matches('\0'));
+
+        let raw = create_algorithm();
+        assert!(!raw.is_null());
+        let algorithm = unsafe { *Box::from_raw(raw.cast::<Box<dyn Algorithm>>()) };
+        assert_eq!(algorithm.warm_up_bars(), 0);
+
+        // Models go into the registry the host hands over
+        let mut registry = crate::finance::ModelRegistry::empty();
+        unsafe { zipline_register_models((&mut registry as *mut crate::finance::ModelRegistry).cast()) };
+        assert_eq!(registry.commission_models(), ["plugin_test_flat"]);
+        assert!(!crate::finance::ModelRegistry::global()
+            .commission_models()
+            .contains(&"plugin_test_flat".to_string()));
+
+        #[cfg(feature = "plugins")]
+        {
+            let missing = super::PluginAlgorithm::load(std::path::Path::new("/nonexistent/libnone.so"));
+            assert!(matches!(missing, Err(crate::error::ZiplineError::PluginLoad { .. })));
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Sandboxed strategies compiled to WebAssembly
+//!
+//! A [`WasmAlgorithm`] runs a strategy module in a wasmtime sandbox: the
+//! module has no filesystem, network or clock, each call into it is metered
+//! with fuel, and its memory is capped. This makes it safe to run strategies
+//! submitted by untrusted users, e.g. in a hosted research service.
+//!
+//! The module exports `handle_data`, and optionally `initialize` and
+//! `before_trading_start`, all taking and returning nothing, plus its
+//! `memory`. It imports what it needs from the `zipline` module:
+//!
+//! | Import | Signature | Meaning |
+//! |--------|-----------|---------|
+//! | `price` | `(sid: i64) -> f64` | Current price, NaN without data |
+//! | `history` | `(sid: i64, bars: i32, out: i32) -> i32` | Writes up to `bars` past closes, oldest first, as `f64`s at `out`; returns how many |
+//! | `position` | `(sid: i64) -> f64` | Shares held |
+//! | `cash` | `() -> f64` | Available cash |
+//! | `portfolio_value` | `() -> f64` | Cash plus positions |
+//! | `order` | `(sid: i64, amount: f64)` | Order shares; negative sells |
+//! | `order_target_percent` | `(sid: i64, percent: f64)` | Rebalance to a fraction of the portfolio |
+//! | `record` | `(name: i32, len: i32, value: f64)` | Record a UTF-8 named value |
+//!
+//! The guest sees a snapshot of the portfolio and prices taken before the
+//! call. Its orders and records are applied to the [`Context`] after it
+//! returns, so an order is not reflected in `position` until the next bar,
+//! as with orders placed natively.
+//!
+//! ```ignore
+//! let algorithm = WasmAlgorithm::load(Path::new("strategy.wasm"), assets)?
+//!     .with_fuel(50_000_000)
+//!     .with_memory_limit(16 << 20);
+//! engine.run(&mut algorithm, &data_source, start, end)?;
+//! ```
+
+use crate::algorithm::{Algorithm, Context};
+use crate::asset::Asset;
+use crate::data::BarData;
+use crate::error::{Result, ZiplineError};
+use crate::types::AssetId;
+use hashbrown::HashMap;
+use std::path::Path;
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Default fuel for one call into the module, roughly one unit per instruction
+pub const DEFAULT_FUEL: u64 = 100_000_000;
+
+/// Default cap on the module's linear memory
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 << 20;
+
+/// Default number of past closes kept for `history`
+pub const DEFAULT_HISTORY_LEN: usize = 252;
+
+/// An [`Algorithm`] running a WebAssembly strategy in a sandbox
+pub struct WasmAlgorithm {
+    engine: Engine,
+    module: Module,
+    assets: Vec<Asset>,
+    fuel: u64,
+    memory_limit: usize,
+    history_len: usize,
+    sandbox: Option<Sandbox>,
+    /// Why instantiation in `initialize` failed, reported on the next call
+    failure: Option<String>,
+}
+
+struct Sandbox {
+    store: Store<HostState>,
+    instance: Instance,
+}
+
+/// What the guest can read, and what it asked for, during one call
+struct HostState {
+    limits: StoreLimits,
+    prices: HashMap<AssetId, f64>,
+    history: HashMap<AssetId, Vec<f64>>,
+    positions: HashMap<AssetId, f64>,
+    cash: f64,
+    portfolio_value: f64,
+    requests: Vec<Request>,
+}
+
+enum Request {
+    Order { sid: AssetId, amount: f64 },
+    OrderTargetPercent { sid: AssetId, percent: f64 },
+    Record { name: String, value: f64 },
+}
+
+impl WasmAlgorithm {
+    /// Compile a strategy from a `.wasm` (or `.wat`) file
+    ///
+    /// `assets` resolve the sids the strategy trades.
+    pub fn load(path: &Path, assets: Vec<Asset>) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes, assets)
+    }
+
+    /// Compile a strategy from module bytes or text
+    pub fn from_bytes(bytes: &[u8], assets: Vec<Asset>) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(sandbox_error)?;
+        let module = Module::new(&engine, bytes).map_err(sandbox_error)?;
+        if module.get_export("handle_data").is_none() {
+            return Err(ZiplineError::SandboxError("module does not export handle_data".to_string()));
+        }
+
+        Ok(Self {
+            engine,
+            module,
+            assets,
+            fuel: DEFAULT_FUEL,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            history_len: DEFAULT_HISTORY_LEN,
+            sandbox: None,
+            failure: None,
+        })
+    }
+
+    /// Set the fuel available to each call into the module
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Set the cap on the module's linear memory, in bytes
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
+    /// Set how many past closes `history` can return
+    pub fn with_history_len(mut self, bars: usize) -> Self {
+        self.history_len = bars;
+        self
+    }
+
+    fn instantiate(&self) -> Result<Sandbox> {
+        let state = HostState {
+            limits: StoreLimitsBuilder::new()
+                .memory_size(self.memory_limit)
+                .instances(1)
+                .build(),
+            prices: HashMap::new(),
+            history: HashMap::new(),
+            positions: HashMap::new(),
+            cash: 0.0,
+            portfolio_value: 0.0,
+            requests: Vec::new(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.fuel).map_err(sandbox_error)?;
+
+        let linker = host_functions(&self.engine)?;
+        let instance = linker.instantiate(&mut store, &self.module).map_err(sandbox_error)?;
+        Ok(Sandbox { store, instance })
+    }
+
+    /// Call an export with a fresh snapshot, then apply what it requested
+    fn call(&mut self, export: &str, context: &mut Context, data: Option<&BarData>) -> Result<()> {
+        if let Some(reason) = &self.failure {
+            return Err(ZiplineError::SandboxError(reason.clone()));
+        }
+        if self.sandbox.is_none() {
+            self.sandbox = Some(self.instantiate()?);
+        }
+        let sandbox = self.sandbox.as_mut().expect("sandbox instantiated above");
+
+        let state = sandbox.store.data_mut();
+        state.prices.clear();
+        state.history.clear();
+        if let Some(data) = data {
+            for asset in self.assets.iter().filter(|asset| data.has_data(asset)) {
+                if let Ok(price) = data.current_price(asset) {
+                    state.prices.insert(asset.id, price);
+                }
+                let bars = self.history_len.min(data.history_len(asset));
+                if let Ok(closes) = data.history_prices(asset, bars) {
+                    state.history.insert(asset.id, closes);
+                }
+            }
+        }
+        state.positions = context
+            .portfolio
+            .positions
+            .iter()
+            .map(|(sid, position)| (*sid, position.quantity))
+            .collect();
+        state.cash = context.portfolio.cash;
+        state.portfolio_value = context.portfolio.portfolio_value;
+        state.requests.clear();
+
+        let Some(function) = sandbox.instance.get_func(&mut sandbox.store, export) else {
+            return Ok(());
+        };
+        let function = function.typed::<(), ()>(&sandbox.store).map_err(sandbox_error)?;
+        sandbox.store.set_fuel(self.fuel).map_err(sandbox_error)?;
+        function.call(&mut sandbox.store, ()).map_err(sandbox_error)?;
+
+        let state = sandbox.store.data_mut();
+        let (requests, prices) = (std::mem::take(&mut state.requests), std::mem::take(&mut state.prices));
+        for request in requests {
+            match request {
+                Request::Order { sid, amount } => {
+                    context.order(self.asset(sid)?, amount)?;
+                }
+                Request::OrderTargetPercent { sid, percent } => match prices.get(&sid) {
+                    Some(&price) if price > 0.0 => {
+                        context.order_target_percent(self.asset(sid)?, percent, price)?;
+                    }
+                    _ => {
+                        return Err(ZiplineError::DataError(format!(
+                            "No price for asset {} to size its target percent order",
+                            sid
+                        )))
+                    }
+                },
+                Request::Record { name, value } => context.record(&name, value),
+            }
+        }
+        Ok(())
+    }
+
+    fn asset(&self, sid: AssetId) -> Result<Asset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.id == sid)
+            .cloned()
+            .ok_or(ZiplineError::SidNotFound(sid))
+    }
+}
+
+impl Algorithm for WasmAlgorithm {
+    fn initialize(&mut self, context: &mut Context) {
+        self.failure = None;
+        self.sandbox = None;
+        if let Err(e) = self.call("initialize", context, None) {
+            self.failure = Some(e.to_string());
+        }
+    }
+
+    fn handle_data(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.call("handle_data", context, Some(data))
+    }
+
+    fn before_trading_start(&mut self, context: &mut Context, data: &BarData) -> Result<()> {
+        self.call("before_trading_start", context, Some(data))
+    }
+
+    fn warm_up_bars(&self) -> usize {
+        self.history_len
+    }
+}
+
+fn sandbox_error(e: impl std::fmt::Display) -> ZiplineError {
+    ZiplineError::SandboxError(e.to_string())
+}
+
+/// Bytes of the guest's exported memory at `offset..offset + len`
+fn guest_bytes<'a>(caller: &'a mut Caller<'_, HostState>, offset: i32, len: i32) -> wasmtime::Result<&'a mut [u8]> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| wasmtime::Error::msg("module does not export memory"))?;
+    let start = usize::try_from(offset)?;
+    let end = start + usize::try_from(len)?;
+    memory
+        .data_mut(caller)
+        .get_mut(start..end)
+        .ok_or_else(|| wasmtime::Error::msg("guest pointer out of bounds"))
+}
+
+/// The `zipline` imports, mirroring the parts of [`Context`] and [`BarData`]
+/// a strategy uses
+fn host_functions(engine: &Engine) -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+    (|| -> wasmtime::Result<()> {
+        linker.func_wrap("zipline", "price", |caller: Caller<'_, HostState>, sid: i64| {
+            caller.data().prices.get(&(sid as AssetId)).copied().unwrap_or(f64::NAN)
+        })?;
+        linker.func_wrap(
+            "zipline",
+            "history",
+            |mut caller: Caller<'_, HostState>, sid: i64, bars: i32, out: i32| -> wasmtime::Result<i32> {
+                let closes = caller.data().history.get(&(sid as AssetId)).cloned().unwrap_or_default();
+                let closes = &closes[closes.len().saturating_sub(usize::try_from(bars)?)..];
+                let bytes: Vec<u8> = closes.iter().flat_map(|close| close.to_le_bytes()).collect();
+                guest_bytes(&mut caller, out, bytes.len() as i32)?.copy_from_slice(&bytes);
+                Ok(closes.len() as i32)
+            },
+        )?;
+        linker.func_wrap("zipline", "position", |caller: Caller<'_, HostState>, sid: i64| {
+            caller.data().positions.get(&(sid as AssetId)).copied().unwrap_or(0.0)
+        })?;
+        linker.func_wrap("zipline", "cash", |caller: Caller<'_, HostState>| caller.data().cash)?;
+        linker.func_wrap("zipline", "portfolio_value", |caller: Caller<'_, HostState>| {
+            caller.data().portfolio_value
+        })?;
+        linker.func_wrap("zipline", "order", |mut caller: Caller<'_, HostState>, sid: i64, amount: f64| {
+            let sid = sid as AssetId;
+            caller.data_mut().requests.push(Request::Order { sid, amount });
+        })?;
+        linker.func_wrap(
+            "zipline",
+            "order_target_percent",
+            |mut caller: Caller<'_, HostState>, sid: i64, percent: f64| {
+                let sid = sid as AssetId;
+                caller.data_mut().requests.push(Request::OrderTargetPercent { sid, percent });
+            },
+        )?;
+        linker.func_wrap(
+            "zipline",
+            "record",
+            |mut caller: Caller<'_, HostState>, name: i32, len: i32, value: f64| -> wasmtime::Result<()> {
+                let name = std::str::from_utf8(guest_bytes(&mut caller, name, len)?)?.to_string();
+                caller.data_mut().requests.push(Request::Record { name, value });
+                Ok(())
+            },
+        )?;
+        Ok(())
+    })()
+    .map_err(sandbox_error)?;
+    Ok(linker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::NYSECalendar;
+    use crate::data::InMemoryDataSource;
+    use crate::engine::SimulationEngine;
+    use crate::types::Bar;
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Buys 10 shares of sid 1 while flat and records the previous close
+    const STRATEGY: &str = r#"
+        (module
+          (import "zipline" "position" (func $position (param i64) (result f64)))
+          (import "zipline" "history" (func $history (param i64 i32 i32) (result i32)))
+          (import "zipline" "order" (func $order (param i64 f64)))
+          (import "zipline" "record" (func $record (param i32 i32 f64)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "close")
+          (func (export "handle_data")
+            (if (f64.eq (call $position (i64.const 1)) (f64.const 0))
+              (then (call $order (i64.const 1) (f64.const 10))))
+            (if (i32.eq (call $history (i64.const 1) (i32.const 1) (i32.const 64)) (i32.const 1))
+              (then (call $record (i32.const 0) (i32.const 5) (f64.load (i32.const 64)))))))
+    "#;
+
+    #[test]
+    fn test_sandboxed_strategy_trades_and_is_metered() {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let asset = Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed);
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+        let mut source = InMemoryDataSource::new();
+        source.add_asset(asset.clone());
+        for day in 0..3 {
+            let price = 100.0 + day as f64;
+            source.add_bar(1, Bar::new(start + Duration::days(day), price, price, price, price, 1e6));
+        }
+        source.set_date_range(start, start + Duration::days(2));
+
+        let mut algorithm = WasmAlgorithm::from_bytes(STRATEGY.as_bytes(), vec![asset.clone()])
+            .unwrap()
+            .with_history_len(1);
+        let mut engine = SimulationEngine::default_engine(Arc::new(NYSECalendar::new()));
+        let results = engine
+            .run(&mut algorithm, &source, start, start + Duration::days(2))
+            .unwrap();
+        assert_eq!(results.transactions.len(), 1);
+        assert_eq!(results.transactions[0].amount, 10.0);
+
+        let spin = r#"(module (func (export "handle_data") (loop $l (br $l))))"#;
+        let mut algorithm = WasmAlgorithm::from_bytes(spin.as_bytes(), vec![asset]).unwrap().with_fuel(10_000);
+        let result = engine.run(&mut algorithm, &source, start, start + Duration::days(2));
+        assert!(matches!(result, Err(ZiplineError::SandboxError(_))));
+    }
+}
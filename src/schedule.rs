@@ -180,6 +180,15 @@ pub trait TimeRule: Send + Sync {
     /// Get the time for this rule
     fn get_time(&self, date: DateTime<Utc>) -> NaiveTime;
 
+    /// Time to fire in a session that opens at `open` and closes at `close`
+    ///
+    /// Defaults to [`get_time`](Self::get_time) on the session's date; rules
+    /// relative to the open or close use the calendar's actual session times,
+    /// so half days and daylight saving are handled.
+    fn time_in_session(&self, open: DateTime<Utc>, _close: DateTime<Utc>) -> DateTime<Utc> {
+        open.date_naive().and_time(self.get_time(open)).and_utc()
+    }
+
     /// Get name of this time rule
     fn name(&self) -> &str;
 }
@@ -222,6 +231,10 @@ impl TimeRule for MarketOpen {
         }
     }
 
+    fn time_in_session(&self, open: DateTime<Utc>, _close: DateTime<Utc>) -> DateTime<Utc> {
+        open + chrono::Duration::minutes(self.offset_minutes as i64)
+    }
+
     fn name(&self) -> &str {
         "MarketOpen"
     }
@@ -265,6 +278,10 @@ impl TimeRule for MarketClose {
         }
     }
 
+    fn time_in_session(&self, _open: DateTime<Utc>, close: DateTime<Utc>) -> DateTime<Utc> {
+        close + chrono::Duration::minutes(self.offset_minutes as i64)
+    }
+
     fn name(&self) -> &str {
         "MarketClose"
     }
@@ -342,6 +359,17 @@ impl ScheduledFunction {
         current_naive_time >= target_time
     }
 
+    /// Check if should trigger at `current_time` in a session with the given open and close
+    pub fn should_trigger_in_session(
+        &self,
+        current_time: DateTime<Utc>,
+        open: DateTime<Utc>,
+        close: DateTime<Utc>,
+    ) -> bool {
+        self.event_rule.should_trigger(current_time, self.last_trigger)
+            && current_time >= self.time_rule.time_in_session(open, close)
+    }
+
     /// Execute the callback
     pub fn execute(&mut self, context: &mut crate::algorithm::Context) -> crate::error::Result<()> {
         self.last_trigger = Some(context.timestamp);
@@ -416,6 +444,41 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Execute functions due at the current minute of a session
+    ///
+    /// Time rules are resolved against the session's `open` and `close`.
+    pub fn execute_in_session(
+        &mut self,
+        context: &mut crate::algorithm::Context,
+        open: DateTime<Utc>,
+        close: DateTime<Utc>,
+    ) -> crate::error::Result<()> {
+        let now = context.timestamp;
+        for func in &mut self.functions {
+            if func.should_trigger_in_session(now, open, close) {
+                func.execute(context)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute functions due on the current session's daily bar
+    ///
+    /// With daily data there is one bar per session, so only the event rule
+    /// is checked and time rules are ignored.
+    pub fn execute_daily(
+        &mut self,
+        context: &mut crate::algorithm::Context,
+    ) -> crate::error::Result<()> {
+        let now = context.timestamp;
+        for func in &mut self.functions {
+            if func.event_rule.should_trigger(now, func.last_trigger) {
+                func.execute(context)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Get number of scheduled functions
     pub fn len(&self) -> usize {
         self.functions.len()
@@ -436,7 +499,7 @@ impl Default for Scheduler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::TimeZone;
+    use chrono::{TimeZone, Timelike};
 
     #[test]
     fn test_every_day_rule() {
@@ -0,0 +1,379 @@
+//! Versioned serialization of backtest results
+//!
+//! Result types are written as JSON inside an envelope recording what they are
+//! and which schema version wrote them:
+//!
+//! ```json
+//! { "kind": "portfolio", "schema_version": 1, "data": { ... } }
+//! ```
+//!
+//! Reading accepts any schema version up to the current one. Older versions
+//! are upgraded one step at a time by [`Versioned::migrate`] before being
+//! deserialized, and plain JSON written before envelopes existed is read as
+//! version 0. Files from a newer crate are rejected instead of being misread.
+//!
+//! Fields added to a result type after its first release carry
+//! `#[serde(default)]`, so a version bump is only needed when an existing field
+//! changes meaning or shape.
+//!
+//! The same envelope is the format header of the crate's other on-disk
+//! artifacts: native bundles, run checkpoints and trade journals.
+//! [`migrate_file`] rewrites any of them at the current version, and backs the
+//! `rusty-zipline migrate` command.
+
+use crate::error::{Result, ZiplineError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// A result type with a versioned on-disk schema
+///
+/// Sealed: only this crate's artifact kinds implement it.
+pub trait Versioned: crate::sealed::Sealed + Serialize + DeserializeOwned {
+    /// Name stored in the envelope, checked on read
+    const KIND: &'static str;
+
+    /// Schema version written by this crate
+    const SCHEMA_VERSION: u32;
+
+    /// Upgrade `data` from schema `version` to `version + 1`
+    ///
+    /// The default suits changes that only add defaulted fields.
+    fn migrate(version: u32, data: Value) -> Result<Value> {
+        let _ = version;
+        Ok(data)
+    }
+}
+
+/// Envelope around a serialized result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// What the payload is, e.g. "portfolio"
+    pub kind: String,
+    /// Schema version the payload was written with
+    pub schema_version: u32,
+    /// The serialized value
+    pub data: Value,
+}
+
+impl Envelope {
+    /// Wrap a value at the current schema version
+    pub fn wrap<T: Versioned>(value: &T) -> Result<Self> {
+        Ok(Self {
+            kind: T::KIND.to_string(),
+            schema_version: T::SCHEMA_VERSION,
+            data: serde_json::to_value(value)?,
+        })
+    }
+
+    /// Read an envelope, treating a bare value as a version 0 payload
+    pub fn from_value(value: Value) -> Result<Self> {
+        let is_envelope = value.as_object().is_some_and(|fields| {
+            fields.contains_key("kind")
+                && fields.contains_key("schema_version")
+                && fields.contains_key("data")
+        });
+        if is_envelope {
+            return Ok(serde_json::from_value(value)?);
+        }
+        Ok(Self {
+            kind: String::new(),
+            schema_version: 0,
+            data: value,
+        })
+    }
+
+    /// Migrate the payload to the current schema and deserialize it
+    pub fn unwrap<T: Versioned>(self) -> Result<T> {
+        if !self.kind.is_empty() && self.kind != T::KIND {
+            return Err(ZiplineError::InvalidData(format!(
+                "Expected a {} result, found {}",
+                T::KIND,
+                self.kind
+            )));
+        }
+        if self.schema_version > T::SCHEMA_VERSION {
+            return Err(ZiplineError::UnsupportedSchemaVersion {
+                kind: T::KIND.to_string(),
+                found: self.schema_version,
+                supported: T::SCHEMA_VERSION,
+            });
+        }
+
+        let mut data = self.data;
+        for version in self.schema_version..T::SCHEMA_VERSION {
+            data = T::migrate(version, data)?;
+        }
+        Ok(serde_json::from_value(data)?)
+    }
+}
+
+/// Serialize a result to versioned JSON
+pub fn to_json<T: Versioned>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(&Envelope::wrap(value)?)?)
+}
+
+/// Serialize a result to indented versioned JSON
+pub fn to_json_pretty<T: Versioned>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Envelope::wrap(value)?)?)
+}
+
+/// Deserialize a result written by this or an older crate version
+pub fn from_json<T: Versioned>(json: &str) -> Result<T> {
+    Envelope::from_value(serde_json::from_str(json)?)?.unwrap()
+}
+
+/// Write a result to a versioned JSON file
+pub fn save<T: Versioned>(value: &T, path: &Path) -> Result<()> {
+    fs::write(path, to_json_pretty(value)?)?;
+    Ok(())
+}
+
+/// Read a result from a JSON file written by this or an older crate version
+pub fn load<T: Versioned>(path: &Path) -> Result<T> {
+    from_json(&fs::read_to_string(path)?)
+}
+
+/// Outcome of migrating one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// Kind of artifact in the file
+    pub kind: String,
+    /// Schema version the file was written with (0 for files without a header)
+    pub from_version: u32,
+    /// Schema version after migration
+    pub to_version: u32,
+}
+
+impl Migration {
+    /// Whether the file needed rewriting
+    pub fn is_upgrade(&self) -> bool {
+        self.from_version < self.to_version
+    }
+}
+
+/// Kinds of artifact [`migrate_file`] understands
+pub const KINDS: &[&str] = &[
+    "portfolio",
+    "position",
+    "order",
+    "fill",
+    "performance_metrics",
+    "performance",
+    "pipeline_output",
+    "bundle",
+    "checkpoint",
+    "journal",
+    "holdout",
+    "performance_compact",
+    "backtest_result",
+];
+
+/// Upgrade a file to the current schema version of its kind
+///
+/// Files written before envelopes existed carry no kind, so `kind` must name
+/// it. With `write` false the file is only checked. Files already at the
+/// current version are left untouched.
+pub fn migrate_file(path: &Path, kind: Option<&str>, write: bool) -> Result<Migration> {
+    let mut envelope = Envelope::from_value(serde_json::from_str(&fs::read_to_string(path)?)?)?;
+    if envelope.kind.is_empty() {
+        envelope.kind = kind
+            .ok_or_else(|| {
+                ZiplineError::InvalidData(format!(
+                    "{} has no format header; specify its kind (one of {})",
+                    path.display(),
+                    KINDS.join(", ")
+                ))
+            })?
+            .to_string();
+    }
+
+    let from_version = envelope.schema_version;
+    let upgraded = match envelope.kind.as_str() {
+        "portfolio" => upgrade::<crate::finance::Portfolio>(envelope)?,
+        "position" => upgrade::<crate::finance::Position>(envelope)?,
+        "order" => upgrade::<crate::order::Order>(envelope)?,
+        "fill" => upgrade::<crate::finance::Fill>(envelope)?,
+        "performance_metrics" => upgrade::<crate::finance::PerformanceMetrics>(envelope)?,
+        "performance" => upgrade::<crate::performance::PerformanceTracker>(envelope)?,
+        "pipeline_output" => upgrade::<crate::pipeline::PipelineOutput>(envelope)?,
+        "bundle" => upgrade::<crate::data::bundle::BundleData>(envelope)?,
+        "checkpoint" => upgrade::<crate::engine::Checkpoint>(envelope)?,
+        "journal" => upgrade::<crate::performance::TradeJournal>(envelope)?,
+        "holdout" => upgrade::<crate::engine::Holdout>(envelope)?,
+        "performance_compact" => upgrade::<crate::performance::CompactResults>(envelope)?,
+        "backtest_result" => upgrade::<crate::performance::BacktestResult>(envelope)?,
+        other => {
+            return Err(ZiplineError::InvalidData(format!(
+                "Unknown artifact kind '{}' in {}",
+                other,
+                path.display()
+            )))
+        }
+    };
+
+    let migration = Migration {
+        kind: upgraded.kind.clone(),
+        from_version,
+        to_version: upgraded.schema_version,
+    };
+    if write && migration.is_upgrade() {
+        // Write alongside and rename, so an interrupted migration never
+        // leaves a truncated file behind
+        let staged = path.with_extension("migrating");
+        fs::write(&staged, serde_json::to_string_pretty(&upgraded)?)?;
+        fs::rename(&staged, path)?;
+    }
+    Ok(migration)
+}
+
+/// Read an envelope as `T`, migrating it, and wrap it again at the current version
+fn upgrade<T: Versioned>(envelope: Envelope) -> Result<Envelope> {
+    Envelope::wrap(&envelope.unwrap::<T>()?)
+}
+
+// Every kind is known to `migrate_file`, so the set of versioned types is closed
+impl crate::sealed::Sealed for crate::finance::Portfolio {}
+impl crate::sealed::Sealed for crate::finance::Position {}
+impl crate::sealed::Sealed for crate::order::Order {}
+impl crate::sealed::Sealed for crate::finance::Fill {}
+impl crate::sealed::Sealed for crate::finance::PerformanceMetrics {}
+impl crate::sealed::Sealed for crate::performance::PerformanceTracker {}
+impl crate::sealed::Sealed for crate::pipeline::PipelineOutput {}
+impl crate::sealed::Sealed for crate::data::bundle::BundleData {}
+impl crate::sealed::Sealed for crate::engine::Checkpoint {}
+impl crate::sealed::Sealed for crate::performance::TradeJournal {}
+
+impl Versioned for crate::finance::Portfolio {
+    const KIND: &'static str = "portfolio";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::finance::Position {
+    const KIND: &'static str = "position";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::order::Order {
+    const KIND: &'static str = "order";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::finance::Fill {
+    const KIND: &'static str = "fill";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::finance::PerformanceMetrics {
+    const KIND: &'static str = "performance_metrics";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::performance::PerformanceTracker {
+    const KIND: &'static str = "performance";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::pipeline::PipelineOutput {
+    const KIND: &'static str = "pipeline_output";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::data::bundle::BundleData {
+    const KIND: &'static str = "bundle";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::engine::Checkpoint {
+    const KIND: &'static str = "checkpoint";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Versioned for crate::performance::TradeJournal {
+    const KIND: &'static str = "journal";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::finance::{PerformanceMetrics, Portfolio};
+    use crate::order::{Order, OrderSide};
+    use chrono::{NaiveDate, Utc};
+
+    fn asset() -> Asset {
+        let listed = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        Asset::equity(1, "AAPL".to_string(), "NASDAQ".to_string(), listed)
+    }
+
+    #[test]
+    fn test_round_trip_through_envelope() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        let mut order = Order::market(asset(), OrderSide::Buy, 10.0, Utc::now());
+        order.filled = 10.0;
+        portfolio.execute_order(&order, 100.0, 1.0);
+
+        let json = to_json(&portfolio).unwrap();
+        let envelope: Envelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope.kind, "portfolio");
+        assert_eq!(envelope.schema_version, 1);
+
+        let restored: Portfolio = from_json(&json).unwrap();
+        assert_eq!(restored.cash, portfolio.cash);
+        assert_eq!(restored.positions[&1].quantity, 10.0);
+
+        let restored: Order = from_json(&to_json(&order).unwrap()).unwrap();
+        assert_eq!(restored.id, order.id);
+    }
+
+    #[test]
+    fn test_reads_legacy_bare_json() {
+        // Written before envelopes and before the derived fields were stored
+        let legacy = r#"{"starting_cash": 5000.0, "cash": 5000.0, "positions": {}}"#;
+        let portfolio: Portfolio = from_json(legacy).unwrap();
+        assert_eq!(portfolio.cash, 5000.0);
+        assert!(portfolio.value_history.is_empty());
+
+        let legacy = r#"{"total_return": 0.12, "sharpe_ratio": 1.5}"#;
+        let metrics: PerformanceMetrics = from_json(legacy).unwrap();
+        assert_eq!(metrics.total_return, 0.12);
+        assert_eq!(metrics.trades_count, 0);
+    }
+
+    #[test]
+    fn test_rejects_newer_or_mismatched_files() {
+        let newer = r#"{"kind": "portfolio", "schema_version": 99, "data": {}}"#;
+        assert!(matches!(
+            from_json::<Portfolio>(newer),
+            Err(ZiplineError::UnsupportedSchemaVersion { found: 99, .. })
+        ));
+
+        let json = to_json(&PerformanceMetrics::default()).unwrap();
+        assert!(from_json::<Portfolio>(&json).is_err());
+    }
+
+    #[test]
+    fn test_migrate_file_adds_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.json");
+        fs::write(&path, r#"{"transactions": []}"#).unwrap();
+
+        // Legacy files need their kind named
+        assert!(migrate_file(&path, None, true).is_err());
+
+        let checked = migrate_file(&path, Some("journal"), false).unwrap();
+        assert!(checked.is_upgrade());
+        assert!(!fs::read_to_string(&path).unwrap().contains("schema_version"));
+
+        let migrated = migrate_file(&path, Some("journal"), true).unwrap();
+        assert_eq!((migrated.from_version, migrated.to_version), (0, 1));
+        let envelope: Envelope = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(envelope.kind, "journal");
+
+        // Already current: nothing to do
+        assert!(!migrate_file(&path, None, true).unwrap().is_upgrade());
+    }
+}
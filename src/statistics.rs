@@ -0,0 +1,10 @@
+//! Statistical estimators shared by the optimizer, allocators and risk tools
+
+pub mod cointegration;
+pub mod covariance;
+
+pub use cointegration::{
+    adf_statistic, engle_granger, hedge_ratio, rolling_hedge_ratio, rolling_spread_zscore, CointegrationTest,
+    HedgeRatio,
+};
+pub use covariance::{ewma_covariance, ledoit_wolf, sample_covariance, CovarianceEstimator};
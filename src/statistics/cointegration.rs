@@ -0,0 +1,267 @@
+//! Hedge ratios, spreads and cointegration tests for pairs trading
+//!
+//! A pair trade holds one asset against `beta` units of another, betting
+//! that the spread `y - alpha - beta * x` reverts to zero. The hedge ratio is
+//! the least-squares slope of one price series on the other, and the spread
+//! z-score measures how stretched the pair is relative to the window's
+//! residuals.
+//!
+//! [`engle_granger`] checks that the spread is worth trading at all: it
+//! regresses `y` on `x` and runs an augmented Dickey–Fuller test on the
+//! residuals, compared against MacKinnon's critical values for a two-variable
+//! cointegrating regression with a constant.
+//!
+//! ```ignore
+//! let (y, x) = (data.history_prices(&a, 252)?, data.history_prices(&b, 252)?);
+//! if engle_granger(&y, &x, 1)?.is_cointegrated() {
+//!     let z = *rolling_spread_zscore(&y, &x, 60).last().unwrap();
+//! }
+//! ```
+
+use crate::error::{Result, ZiplineError};
+use crate::pipeline::kernels;
+
+/// Intercept and slope of `y` regressed on `x`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HedgeRatio {
+    pub intercept: f64,
+    /// Units of `x` held against each unit of `y`
+    pub beta: f64,
+}
+
+impl HedgeRatio {
+    /// `y - intercept - beta * x` at each point
+    pub fn spread(&self, y: &[f64], x: &[f64]) -> Vec<f64> {
+        y.iter().zip(x).map(|(y, x)| y - self.intercept - self.beta * x).collect()
+    }
+}
+
+/// Least-squares hedge ratio of `y` on `x` over the whole series
+pub fn hedge_ratio(y: &[f64], x: &[f64]) -> Result<HedgeRatio> {
+    let n = y.len().min(x.len());
+    let (alpha, beta, _) = kernels::rolling_regression(&y[..n], &x[..n], n);
+    match (alpha.last(), beta.last()) {
+        (Some(&intercept), Some(&beta)) if beta.is_finite() => Ok(HedgeRatio { intercept, beta }),
+        _ => Err(ZiplineError::InvalidData(format!(
+            "Hedge ratio needs at least two complete observations with varying x, got {}",
+            n
+        ))),
+    }
+}
+
+/// Hedge ratio over each trailing `window` of the series
+pub fn rolling_hedge_ratio(y: &[f64], x: &[f64], window: usize) -> Vec<f64> {
+    kernels::rolling_regression(y, x, window).1
+}
+
+/// Latest spread over each trailing `window`, in standard deviations of the
+/// window's regression residuals
+///
+/// The hedge ratio is refit on every window, so each z-score uses only data
+/// available at that point.
+pub fn rolling_spread_zscore(y: &[f64], x: &[f64], window: usize) -> Vec<f64> {
+    let (alpha, beta, r_squared) = kernels::rolling_regression(y, x, window);
+    let std_y = kernels::rolling_std(y, window, 1);
+    if window < 3 {
+        return vec![f64::NAN; alpha.len()];
+    }
+    // Residual variance from the fit: SSR = (1 - R²) * total sum of squares
+    let scale = (window - 1) as f64 / (window - 2) as f64;
+    (0..alpha.len())
+        .map(|i| {
+            let residual_std = (std_y[i] * std_y[i] * (1.0 - r_squared[i]) * scale).sqrt();
+            if residual_std > 0.0 {
+                (y[i] - alpha[i] - beta[i] * x[i]) / residual_std
+            } else {
+                f64::NAN
+            }
+        })
+        .collect()
+}
+
+/// Augmented Dickey–Fuller t-statistic of `series`, with a constant and
+/// `lags` lagged differences
+///
+/// More negative values are stronger evidence that the series is stationary.
+pub fn adf_statistic(series: &[f64], lags: usize) -> Result<f64> {
+    dickey_fuller(series, lags, true)
+}
+
+/// Outcome of an Engle–Granger cointegration test
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CointegrationTest {
+    pub hedge_ratio: HedgeRatio,
+    /// Dickey–Fuller t-statistic of the residuals
+    pub statistic: f64,
+    /// Critical values at the 1%, 5% and 10% levels
+    pub critical_values: [f64; 3],
+    pub observations: usize,
+}
+
+impl CointegrationTest {
+    /// Whether the residuals are stationary at the 5% level
+    pub fn is_cointegrated(&self) -> bool {
+        self.statistic < self.critical_values[1]
+    }
+}
+
+/// Engle–Granger two-step test of whether `y` and `x` are cointegrated
+///
+/// Missing (NaN) observations in either series are dropped.
+pub fn engle_granger(y: &[f64], x: &[f64], lags: usize) -> Result<CointegrationTest> {
+    let (y, x): (Vec<f64>, Vec<f64>) = y.iter().zip(x).filter(|(y, x)| !y.is_nan() && !x.is_nan()).unzip();
+    let hedge_ratio = hedge_ratio(&y, &x)?;
+    let residuals = hedge_ratio.spread(&y, &x);
+    let statistic = dickey_fuller(&residuals, lags, false)?;
+
+    // MacKinnon (2010) response surface, two variables with a constant
+    let n = y.len() as f64;
+    let surface = |b: [f64; 3]| b[0] + b[1] / n + b[2] / (n * n);
+    Ok(CointegrationTest {
+        hedge_ratio,
+        statistic,
+        critical_values: [
+            surface([-3.89644, -10.9519, -22.527]),
+            surface([-3.33613, -6.1101, -6.823]),
+            surface([-3.04445, -4.2412, -2.720]),
+        ],
+        observations: y.len(),
+    })
+}
+
+/// t-statistic on `y[t-1]` regressing `Δy[t]` on it and `lags` lagged
+/// differences, optionally with a constant
+fn dickey_fuller(series: &[f64], lags: usize, constant: bool) -> Result<f64> {
+    let diffs: Vec<f64> = series.windows(2).map(|w| w[1] - w[0]).collect();
+    let columns = 1 + lags + usize::from(constant);
+    let rows = diffs.len().saturating_sub(lags);
+    if rows <= columns + 1 || series.iter().any(|v| !v.is_finite()) {
+        return Err(ZiplineError::InvalidData(format!(
+            "Dickey-Fuller test with {} lags needs more than {} finite observations, got {}",
+            lags,
+            columns + lags + 2,
+            series.len()
+        )));
+    }
+
+    let mut design = Vec::with_capacity(rows);
+    let mut response = Vec::with_capacity(rows);
+    for t in lags..diffs.len() {
+        let mut row = vec![series[t]];
+        row.extend((1..=lags).map(|lag| diffs[t - lag]));
+        if constant {
+            row.push(1.0);
+        }
+        design.push(row);
+        response.push(diffs[t]);
+    }
+
+    let (coefficients, inverse) = least_squares(&design, &response)?;
+    let residual_ss: f64 = design
+        .iter()
+        .zip(&response)
+        .map(|(row, y)| {
+            let fit: f64 = row.iter().zip(&coefficients).map(|(x, b)| x * b).sum();
+            (y - fit).powi(2)
+        })
+        .sum();
+    let variance = residual_ss / (rows - columns) as f64;
+    Ok(coefficients[0] / (variance * inverse[0][0]).sqrt())
+}
+
+/// Coefficients of an ordinary least-squares fit and the inverse of `X'X`
+fn least_squares(design: &[Vec<f64>], response: &[f64]) -> Result<(Vec<f64>, Vec<Vec<f64>>)> {
+    let k = design[0].len();
+    let mut gram = vec![vec![0.0; k]; k];
+    let mut moment = vec![0.0; k];
+    for (row, y) in design.iter().zip(response) {
+        for i in 0..k {
+            moment[i] += row[i] * y;
+            for j in 0..k {
+                gram[i][j] += row[i] * row[j];
+            }
+        }
+    }
+
+    // Gauss-Jordan elimination with partial pivoting
+    let mut inverse: Vec<Vec<f64>> = (0..k).map(|i| (0..k).map(|j| f64::from(i == j)).collect()).collect();
+    for col in 0..k {
+        let pivot = (col..k)
+            .max_by(|a, b| gram[*a][col].abs().total_cmp(&gram[*b][col].abs()))
+            .unwrap_or(col);
+        if gram[pivot][col].abs() < 1e-12 {
+            return Err(ZiplineError::InvalidData(
+                "Regressors are collinear; the series may be constant".to_string(),
+            ));
+        }
+        gram.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = gram[col][col];
+        for j in 0..k {
+            gram[col][j] /= scale;
+            inverse[col][j] /= scale;
+        }
+        for row in 0..k {
+            if row != col {
+                let factor = gram[row][col];
+                for j in 0..k {
+                    gram[row][j] -= factor * gram[col][j];
+                    inverse[row][j] -= factor * inverse[col][j];
+                }
+            }
+        }
+    }
+
+    let coefficients = inverse.iter().map(|row| row.iter().zip(&moment).map(|(a, b)| a * b).sum()).collect();
+    Ok((coefficients, inverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_pairs_statistics() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut walk = |n: usize| -> Vec<f64> {
+            let mut level = 100.0;
+            (0..n)
+                .map(|_| {
+                    level += rng.gen::<f64>() - 0.5;
+                    level
+                })
+                .collect()
+        };
+        let x = walk(500);
+        let unrelated = walk(500);
+        // y tracks 2x with mean-reverting noise
+        let mut noise = 0.0;
+        let y: Vec<f64> = x
+            .iter()
+            .map(|x| {
+                noise = 0.5 * noise + rng.gen::<f64>() - 0.5;
+                1.0 + 2.0 * x + noise
+            })
+            .collect();
+
+        let hedge = hedge_ratio(&y, &x).unwrap();
+        assert!((hedge.beta - 2.0).abs() < 0.05);
+        assert!(rolling_hedge_ratio(&y, &x, 100)[499].is_finite());
+
+        let pair = engle_granger(&y, &x, 1).unwrap();
+        assert!(pair.is_cointegrated());
+        assert!(pair.critical_values[0] < pair.critical_values[1]);
+        assert!(!engle_granger(&unrelated, &x, 1).unwrap().is_cointegrated());
+        assert!(adf_statistic(&x, 1).unwrap() > -2.86);
+
+        // A spread stretched at the end of the window stands out
+        let mut stretched = y.clone();
+        stretched[499] += 5.0;
+        let z = rolling_spread_zscore(&stretched, &x, 60);
+        assert!(z[..59].iter().all(|z| z.is_nan()));
+        assert!(z[499] > 3.0 && z[498].abs() < 3.0);
+        assert!(hedge_ratio(&[1.0], &[1.0]).is_err());
+    }
+}
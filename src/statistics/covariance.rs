@@ -0,0 +1,156 @@
+//! Covariance of asset returns from a window of observations
+//!
+//! Estimators take returns laid out like a pipeline window: one row per
+//! observation, oldest first, and one column per asset. Rows with a missing
+//! (NaN) return for any asset are skipped.
+//!
+//! The sample covariance is noisy when there are few observations per asset;
+//! [`ledoit_wolf`] shrinks it towards a scaled identity by the amount that
+//! minimizes expected error, and [`ewma_covariance`] weights recent
+//! observations more heavily.
+//!
+//! ```ignore
+//! let estimate = CovarianceEstimator::LedoitWolf.estimate(returns.view())?;
+//! let covariance = Covariance::estimate(assets, returns.view(), CovarianceEstimator::ewma_halflife(30.0))?;
+//! ```
+
+use crate::error::{Result, ZiplineError};
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+
+/// How a covariance matrix is estimated from returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CovarianceEstimator {
+    /// Unbiased sample covariance
+    Sample,
+    /// Sample covariance shrunk towards a scaled identity (Ledoit–Wolf)
+    LedoitWolf,
+    /// Exponentially weighted covariance, each observation weighted `decay`
+    /// times the one after it
+    Ewma { decay: f64 },
+}
+
+impl CovarianceEstimator {
+    /// Exponential weighting halving every `halflife` observations
+    pub fn ewma_halflife(halflife: f64) -> Self {
+        CovarianceEstimator::Ewma {
+            decay: 0.5_f64.powf(1.0 / halflife),
+        }
+    }
+
+    /// Covariance of the columns of `returns`
+    pub fn estimate(&self, returns: ArrayView2<f64>) -> Result<Array2<f64>> {
+        match self {
+            CovarianceEstimator::Sample => sample_covariance(returns),
+            CovarianceEstimator::LedoitWolf => ledoit_wolf(returns).map(|(covariance, _)| covariance),
+            CovarianceEstimator::Ewma { decay } => ewma_covariance(returns, *decay),
+        }
+    }
+}
+
+/// Rows of `returns` with no missing value, requiring at least two
+fn complete_rows(returns: ArrayView2<f64>) -> Result<Array2<f64>> {
+    let rows: Vec<usize> = (0..returns.nrows())
+        .filter(|row| returns.row(*row).iter().all(|r| r.is_finite()))
+        .collect();
+    if rows.len() < 2 || returns.ncols() == 0 {
+        return Err(ZiplineError::InvalidData(format!(
+            "Covariance needs at least two complete observations of at least one asset, got {} of {}",
+            rows.len(),
+            returns.ncols()
+        )));
+    }
+    Ok(returns.select(Axis(0), &rows))
+}
+
+/// `returns` less each column's mean
+fn demeaned(returns: &Array2<f64>) -> Array2<f64> {
+    let means = returns.mean_axis(Axis(0)).unwrap_or_else(|| Array1::zeros(returns.ncols()));
+    returns - &means
+}
+
+/// Unbiased sample covariance
+pub fn sample_covariance(returns: ArrayView2<f64>) -> Result<Array2<f64>> {
+    let x = demeaned(&complete_rows(returns)?);
+    Ok(x.t().dot(&x) / (x.nrows() - 1) as f64)
+}
+
+/// Ledoit–Wolf shrinkage estimate and the shrinkage applied
+///
+/// The sample covariance (maximum likelihood, dividing by the number of
+/// observations) is blended with the identity scaled to its average variance;
+/// the shrinkage, between 0 and 1, is the weight on the identity.
+pub fn ledoit_wolf(returns: ArrayView2<f64>) -> Result<(Array2<f64>, f64)> {
+    let x = demeaned(&complete_rows(returns)?);
+    let (n, p) = (x.nrows() as f64, x.ncols());
+    let sample = x.t().dot(&x) / n;
+    let scale = sample.diag().sum() / p as f64;
+    let target = Array2::<f64>::eye(p) * scale;
+
+    // Distance of the sample from the target, and the sample's own error
+    let distance = (&sample - &target).mapv(|v| v * v).sum();
+    let error = x
+        .rows()
+        .into_iter()
+        .map(|row| {
+            let column = row.view().insert_axis(Axis(1));
+            let outer = column.dot(&column.t());
+            (&outer - &sample).mapv(|v| v * v).sum()
+        })
+        .sum::<f64>()
+        / (n * n);
+    let shrinkage = if distance > 0.0 { error.min(distance) / distance } else { 0.0 };
+
+    Ok((target * shrinkage + sample * (1.0 - shrinkage), shrinkage))
+}
+
+/// Exponentially weighted covariance, the newest observation weighted most
+///
+/// Each observation is weighted `decay` times the next one, about its
+/// weighted mean.
+pub fn ewma_covariance(returns: ArrayView2<f64>, decay: f64) -> Result<Array2<f64>> {
+    if !(decay > 0.0 && decay <= 1.0) {
+        return Err(ZiplineError::InvalidConfiguration(format!(
+            "EWMA decay must be in (0, 1], got {}",
+            decay
+        )));
+    }
+    let returns = complete_rows(returns)?;
+    let n = returns.nrows();
+    let weights = Array1::from_iter((0..n).map(|t| decay.powi((n - 1 - t) as i32)));
+    let weights = &weights / weights.sum();
+
+    let mean = weights.dot(&returns);
+    let x = &returns - &mean;
+    let weighted = &x * &weights.view().insert_axis(Axis(1));
+    Ok(weighted.t().dot(&x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_estimators() {
+        let returns = array![[0.01, 0.02], [-0.01, 0.0], [0.02, 0.01], [f64::NAN, 0.5], [0.0, -0.03]];
+        let sample = sample_covariance(returns.view()).unwrap();
+        // Means 0.005 and 0; the NaN row is skipped
+        let expected = array![[0.0005, 0.0004], [0.0004, 0.0014]] / 3.0;
+        assert!((&sample - &expected).iter().all(|d| d.abs() < 1e-15));
+
+        let (shrunk, shrinkage) = ledoit_wolf(returns.view()).unwrap();
+        assert!(shrinkage > 0.0 && shrinkage <= 1.0);
+        // Shrinking keeps the average variance and pulls the correlation in
+        let ml = &sample * 0.75;
+        assert!((shrunk.diag().sum() - ml.diag().sum()).abs() < 1e-15);
+        assert!(shrunk[[0, 1]].abs() < ml[[0, 1]].abs());
+
+        // No decay weights evenly, as the maximum likelihood estimate
+        let even = ewma_covariance(returns.view(), 1.0).unwrap();
+        assert!((&even - &ml).iter().all(|d| d.abs() < 1e-15));
+        let recent = CovarianceEstimator::ewma_halflife(1.0).estimate(returns.view()).unwrap();
+        assert!(recent[[1, 1]] > even[[1, 1]]);
+        assert!(ewma_covariance(returns.view(), 0.0).is_err());
+        assert!(sample_covariance(returns.slice(ndarray::s![..1, ..])).is_err());
+    }
+}
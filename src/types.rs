@@ -1,7 +1,8 @@
 //! Core types and constants
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Timestamp type used throughout the library
 pub type Timestamp = DateTime<Utc>;
@@ -15,6 +16,12 @@ pub type Price = f64;
 /// Quantity/volume type
 pub type Quantity = f64;
 
+/// Quantities closer than this to zero are treated as zero
+///
+/// Far below the smallest lot traded (a satoshi is 1e-8), yet well above the
+/// rounding error left by adding up fractional fills.
+pub const QUANTITY_TOLERANCE: Quantity = 1e-9;
+
 /// Money/cash type
 pub type Cash = f64;
 
@@ -27,6 +34,88 @@ pub type OrderId = uuid::Uuid;
 /// Unique identifier for assets
 pub type AssetId = u64;
 
+/// A trading session, named by its date in the exchange's time zone
+///
+/// There is deliberately no conversion from a bare timestamp: a session comes
+/// from [`TradingCalendar::session_of`](crate::calendar::TradingCalendar::session_of),
+/// which knows the exchange's time zone, or from [`SessionId::utc_label_of`]
+/// for bar stores that label sessions by UTC date. Comparing a session with a
+/// UTC midnight is then a type error. Stored as a day number, so comparisons
+/// are integer compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "NaiveDate", into = "NaiveDate")]
+pub struct SessionId(i32);
+
+impl SessionId {
+    /// Session held on an exchange-local date
+    pub fn from_date(date: NaiveDate) -> Self {
+        Self(date.num_days_from_ce())
+    }
+
+    /// Session labelled by the UTC date of `dt`, as bar stores do
+    pub fn utc_label_of(dt: Timestamp) -> Self {
+        Self::from_date(dt.date_naive())
+    }
+
+    /// Exchange-local date of the session
+    pub fn date(self) -> NaiveDate {
+        NaiveDate::from_num_days_from_ce_opt(self.0).expect("session ids are built from valid dates")
+    }
+
+    /// The following calendar day, which need not be a trading session
+    pub fn next_day(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
+
+impl From<NaiveDate> for SessionId {
+    fn from(date: NaiveDate) -> Self {
+        Self::from_date(date)
+    }
+}
+
+impl From<SessionId> for NaiveDate {
+    fn from(session: SessionId) -> Self {
+        session.date()
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date())
+    }
+}
+
+/// The start of a one-minute bar, as whole minutes since the Unix epoch
+///
+/// Minutes are absolute instants, so they compare directly across calendars;
+/// loops over minute bars compare integers rather than `DateTime`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MinuteId(i64);
+
+impl MinuteId {
+    /// The minute containing `dt`
+    pub fn containing(dt: Timestamp) -> Self {
+        Self(dt.timestamp().div_euclid(60))
+    }
+
+    /// Start of the minute
+    pub fn timestamp(self) -> Timestamp {
+        DateTime::from_timestamp(self.0 * 60, 0).expect("minute ids are built from valid timestamps")
+    }
+
+    /// Minutes since the Unix epoch
+    pub fn minutes(self) -> i64 {
+        self.0
+    }
+}
+
+impl fmt::Display for MinuteId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.timestamp())
+    }
+}
+
 /// OHLCV bar data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bar {
@@ -93,4 +182,24 @@ mod tests {
         assert!(bar.is_bullish());
         assert!(!bar.is_bearish());
     }
+
+    #[test]
+    fn test_session_and_minute_ids() {
+        use crate::calendar::{NYSECalendar, TradingCalendar};
+        use chrono::{Duration, TimeZone};
+
+        // 20:30 in New York on 1 March is already 2 March in UTC
+        let dt = Utc.with_ymd_and_hms(2024, 3, 2, 1, 30, 45).unwrap();
+        let session = SessionId::from_date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(NYSECalendar::new().session_of(dt), session);
+        assert!(SessionId::utc_label_of(dt) > session);
+        assert_eq!(session.next_day(), SessionId::utc_label_of(dt));
+        assert_eq!(session.to_string(), "2024-03-01");
+        assert_eq!(serde_json::to_string(&session).unwrap(), "\"2024-03-01\"");
+
+        let minute = MinuteId::containing(dt);
+        assert_eq!(minute.timestamp(), dt - Duration::seconds(45));
+        assert!(MinuteId::containing(dt - Duration::seconds(46)) < minute);
+        assert_eq!(MinuteId::containing(DateTime::from_timestamp(-30, 0).unwrap()).minutes(), -1);
+    }
 }
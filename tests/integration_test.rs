@@ -36,10 +36,9 @@ fn test_buy_and_hold_strategy() {
     data_source.set_date_range(start, end);
 
     // Run backtest
-    let config = EngineConfig {
-        starting_cash: 10_000.0,
-        max_history_len: 100,
-    };
+    let config = EngineConfig::default()
+        .with_starting_cash(10_000.0)
+        .with_max_history_len(100);
 
     let calendar = Arc::new(NYSECalendar::new());
     let broker = SimulatedBroker::default_broker();
@@ -88,10 +87,9 @@ fn test_portfolio_value_tracking() {
 
     data_source.set_date_range(start, end);
 
-    let config = EngineConfig {
-        starting_cash: 10_000.0,
-        max_history_len: 100,
-    };
+    let config = EngineConfig::default()
+        .with_starting_cash(10_000.0)
+        .with_max_history_len(100);
 
     let calendar = Arc::new(NYSECalendar::new());
     let broker = SimulatedBroker::default_broker();
@@ -172,7 +170,7 @@ fn test_data_history() {
     assert_eq!(bar_data.history_len(&asset), 20);
 
     // Get history
-    let history = bar_data.history(&asset, 10).unwrap();
+    let history = bar_data.bar_history(&asset, 10).unwrap();
     assert_eq!(history.len(), 10);
 
     // Check prices